@@ -14,12 +14,14 @@ import { parseFrontmatter } from './lib/parse-frontmatter.js'
 import remarkResolveLinks from './lib/remark-resolve-links.js'
 import { buildNavigation, type DocInfo } from './lib/build-navigation.js'
 import { chunkContent } from './lib/chunk-content.js'
+import { extractAnchors } from './lib/extract-anchors.js'
 import {
   createDatabase,
   insertCollection,
   insertDocumentRaw,
   insertNavigationRaw,
   insertChunksRaw,
+  insertAnchorsRaw,
 } from './lib/create-database.js'
 import type { Collection } from './lib/config.js'
 import type { DocumentMetadata } from './lib/extract-metadata.js'
@@ -417,6 +419,8 @@ async function processCollection(
       lastModified: fileMtime,
     })
 
+    insertAnchorsRaw(db, docId, extractAnchors(file.contentHtml))
+
     const chunks = chunkContent(file.parsed.content)
     if (chunks.length > 0) {
       insertChunksRaw(db, docId, chunks)
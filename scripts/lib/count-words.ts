@@ -0,0 +1,67 @@
+const NAMED_ENTITIES: Record<string, string> = {
+  amp: '&',
+  lt: '<',
+  gt: '>',
+  quot: '"',
+  apos: "'",
+  nbsp: ' ',
+  ndash: '–',
+  mdash: '—',
+  hellip: '…',
+  lsquo: '‘',
+  rsquo: '’',
+  ldquo: '“',
+  rdquo: '”',
+  copy: '©',
+  reg: '®',
+  trade: '™',
+}
+
+const WORD_CHARACTER = /[\p{Alphabetic}\p{N}]/u
+
+/** The character an entity at the start of `text` stands for, and its length. */
+function decodeEntity(text: string): [string, number] | null {
+  const end = text.indexOf(';')
+  const name = text.slice(1, end)
+  if (end === -1 || name.length === 0 || name.length > 10) return null
+  if (!name.startsWith('#')) {
+    const decoded = NAMED_ENTITIES[name]
+    return decoded === undefined ? null : [decoded, end + 1]
+  }
+  const hex = /^#[xX]/.test(name)
+  const digits = name.slice(hex ? 2 : 1)
+  if (!(hex ? /^[0-9a-fA-F]+$/ : /^[0-9]+$/).test(digits)) return null
+  const code = parseInt(digits, hex ? 16 : 10)
+  if (code > 0x10ffff || (code >= 0xd800 && code <= 0xdfff)) return null
+  return [String.fromCodePoint(code), end + 1]
+}
+
+/**
+ * Words in rendered HTML, counted as the app's snippet::word_count does:
+ * runs of visible text holding a letter or digit, with `script` and `style`
+ * contents dropped and entities decoded.
+ */
+export function countWords(contentHtml: string): number {
+  let text = ''
+  let skipped = false
+  let i = 0
+  while (i < contentHtml.length) {
+    if (contentHtml[i] === '<') {
+      const end = contentHtml.indexOf('>', i)
+      if (end !== -1) {
+        const tag = contentHtml.slice(i + 1, end)
+        const name = (/^[A-Za-z0-9]*/.exec(tag.replace(/^\/+/, '')) ?? [''])[0].toLowerCase()
+        if (name === 'script' || name === 'style') skipped = !tag.startsWith('/')
+        // Tags separate words, e.g. adjacent list items
+        text += ' '
+        i = end + 1
+        continue
+      }
+    }
+    const entity = contentHtml[i] === '&' ? decodeEntity(contentHtml.slice(i, i + 12)) : null
+    const [decoded, length] = entity ?? [contentHtml[i], 1]
+    if (!skipped) text += decoded
+    i += length
+  }
+  return text.split(/\s+/).filter((word) => WORD_CHARACTER.test(word)).length
+}
@@ -1,5 +1,6 @@
 import Database from 'better-sqlite3'
 import { existsSync, unlinkSync } from 'node:fs'
+import { countWords } from './count-words.js'
 
 export function createDatabase(dbPath: string): Database.Database {
   if (existsSync(dbPath)) unlinkSync(dbPath)
@@ -31,7 +32,8 @@ export function createDatabase(dbPath: string): Database.Database {
       content_html TEXT NOT NULL,
       content_raw TEXT NOT NULL,
       path TEXT NOT NULL,
-      last_modified TEXT NOT NULL DEFAULT ''
+      last_modified TEXT NOT NULL DEFAULT '',
+      word_count INTEGER NOT NULL DEFAULT 0
     );
 
     CREATE TABLE tags (
@@ -75,9 +77,19 @@ export function createDatabase(dbPath: string): Database.Database {
 
     CREATE VIRTUAL TABLE chunks_fts USING fts5(content_text, heading_context);
 
+    CREATE TABLE document_anchors (
+      document_id INTEGER NOT NULL REFERENCES documents(id),
+      anchor_id TEXT NOT NULL,
+      heading_text TEXT NOT NULL,
+      level INTEGER NOT NULL,
+      position INTEGER NOT NULL,
+      PRIMARY KEY (document_id, anchor_id)
+    );
+
     CREATE INDEX IF NOT EXISTS idx_documents_collection_id ON documents(collection_id);
     CREATE INDEX IF NOT EXISTS idx_navigation_tree_collection_id ON navigation_tree(collection_id);
     CREATE INDEX IF NOT EXISTS idx_chunks_document_id ON chunks(document_id);
+    CREATE INDEX IF NOT EXISTS idx_document_anchors_document ON document_anchors(document_id, position);
     CREATE INDEX IF NOT EXISTS idx_navigation_tree_sort ON navigation_tree(collection_id, parent_slug, sort_order);
   `)
 
@@ -123,8 +135,8 @@ export function insertDocumentRaw(
   doc: DocumentInsertParams,
 ): number {
   const insertDoc = db.prepare(`
-    INSERT INTO documents (collection_id, slug, title, section, sort_order, parent_slug, content_html, content_raw, path, last_modified)
-    VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?)
+    INSERT INTO documents (collection_id, slug, title, section, sort_order, parent_slug, content_html, content_raw, path, last_modified, word_count)
+    VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?)
   `)
 
   const insertFts = db.prepare(`
@@ -147,6 +159,7 @@ export function insertDocumentRaw(
     doc.contentRaw,
     doc.path,
     doc.lastModified,
+    countWords(doc.contentHtml),
   )
 
   const documentId = result.lastInsertRowid as number
@@ -214,6 +227,32 @@ export function insertNavigation(
   insertAll()
 }
 
+interface AnchorInsertParams {
+  anchorId: string
+  headingText: string
+  level: number
+  position: number
+}
+
+/**
+ * Insert heading anchors for a document without wrapping in a transaction.
+ * The caller is responsible for providing an outer transaction.
+ */
+export function insertAnchorsRaw(
+  db: Database.Database,
+  documentId: number,
+  anchors: AnchorInsertParams[],
+): void {
+  const stmt = db.prepare(`
+    INSERT OR IGNORE INTO document_anchors (document_id, anchor_id, heading_text, level, position)
+    VALUES (?, ?, ?, ?, ?)
+  `)
+
+  for (const anchor of anchors) {
+    stmt.run(documentId, anchor.anchorId, anchor.headingText, anchor.level, anchor.position)
+  }
+}
+
 interface ChunkInsertParams {
   chunkIndex: number
   contentText: string
@@ -0,0 +1,33 @@
+export interface DocumentAnchor {
+  anchorId: string
+  headingText: string
+  level: number
+  position: number
+}
+
+const HEADING_PATTERN = /<h([1-6])\s[^>]*?id="([^"]+)"[^>]*>([\s\S]*?)<\/h\1>/g
+
+/**
+ * Extract heading anchors from rendered HTML.
+ * Relies on rehype-slug having assigned an id to every heading.
+ */
+export function extractAnchors(contentHtml: string): DocumentAnchor[] {
+  const anchors: DocumentAnchor[] = []
+  let position = 0
+
+  for (const match of contentHtml.matchAll(HEADING_PATTERN)) {
+    const headingText = match[3]
+      .replace(/<[^>]+>/g, '')
+      .replace(/\s+/g, ' ')
+      .trim()
+
+    anchors.push({
+      anchorId: match[2],
+      headingText,
+      level: parseInt(match[1], 10),
+      position: position++,
+    })
+  }
+
+  return anchors
+}
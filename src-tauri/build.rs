@@ -1,3 +1,8 @@
 fn main() {
+    // Reported by get_about_info; only build scripts can see the target triple
+    println!(
+        "cargo:rustc-env=DALIL_TARGET_TRIPLE={}",
+        std::env::var("TARGET").unwrap_or_default()
+    );
     tauri_build::build()
 }
@@ -0,0 +1,212 @@
+//! Keyword extraction for full-text retrieval. Questions are split and folded
+//! the way the FTS5 `unicode61` tokenizer splits and folds document text, so a
+//! keyword matches however its accents were typed, and stop words are dropped
+//! using the list for the configured language plus the user's own additions.
+
+use crate::models::AppPreferences;
+use std::collections::HashSet;
+use std::sync::Mutex;
+
+const ENGLISH: &[&str] = &[
+    "a", "an", "and", "are", "as", "at", "be", "by", "can", "do", "does", "for", "from", "has",
+    "have", "how", "i", "in", "is", "it", "its", "my", "not", "of", "on", "or", "our", "should",
+    "so", "that", "the", "their", "them", "then", "there", "these", "they", "this", "to", "was",
+    "we", "what", "when", "where", "which", "who", "why", "will", "with", "would", "you", "your",
+];
+
+const GERMAN: &[&str] = &[
+    "aber", "alle", "als", "am", "an", "auch", "auf", "aus", "bei", "bin", "bis", "da", "das",
+    "dass", "dem", "den", "der", "des", "die", "doch", "du", "durch", "ein", "eine", "einem",
+    "einen", "einer", "eines", "er", "es", "für", "haben", "hat", "ich", "ihr", "im", "in", "ist",
+    "ja", "kann", "man", "mit", "muss", "nach", "nicht", "noch", "nur", "oder", "sich", "sie",
+    "sind", "so", "soll", "sollte", "über", "um", "und", "uns", "unter", "vom", "von", "vor",
+    "wann", "war", "warum", "was", "welche", "welcher", "wer", "werden", "wie", "wir", "wird",
+    "wo", "zu", "zum", "zur",
+];
+
+const FRENCH: &[&str] = &[
+    "au", "aux", "avec", "ce", "ces", "cet", "cette", "comment", "dans", "de", "des", "doit", "du",
+    "elle", "en", "est", "et", "être", "faut", "il", "ils", "je", "la", "le", "les", "leur", "lui",
+    "ma", "mais", "me", "mes", "mon", "ne", "nos", "notre", "nous", "on", "ou", "où", "par", "pas",
+    "peut", "pour", "pourquoi", "qu", "quand", "que", "quel", "quelle", "qui", "quoi", "sa", "se",
+    "ses", "son", "sont", "sur", "ta", "te", "tes", "ton", "tu", "un", "une", "vos", "votre",
+    "vous",
+];
+
+const SPANISH: &[&str] = &[
+    "al", "como", "cómo", "con", "cual", "cuando", "de", "debo", "del", "donde", "dónde", "el",
+    "ella", "en", "es", "esta", "está", "este", "esto", "hay", "la", "las", "lo", "los", "más",
+    "me", "mi", "no", "nos", "para", "pero", "por", "porque", "puedo", "que", "qué", "se", "ser",
+    "si", "sin", "son", "su", "sus", "también", "te", "tu", "un", "una", "uno", "ya", "yo",
+];
+
+fn bundled_list(language: &str) -> &'static [&'static str] {
+    match language {
+        "de" => GERMAN,
+        "fr" => FRENCH,
+        "es" => SPANISH,
+        _ => ENGLISH,
+    }
+}
+
+/// Stop words for one language plus custom additions, stored folded.
+#[derive(Debug, Clone)]
+pub struct StopWords {
+    words: HashSet<String>,
+}
+
+impl StopWords {
+    /// `language` is "en", "de", "fr" or "es"; other codes and None use
+    /// English.
+    pub fn new(language: Option<&str>, custom: &[String]) -> Self {
+        let language = language
+            .map(|l| l.trim().to_lowercase())
+            .unwrap_or_default();
+        let words = bundled_list(&language)
+            .iter()
+            .map(|word| fold(word))
+            .chain(custom.iter().flat_map(|word| terms(word)))
+            .collect();
+        Self { words }
+    }
+
+    pub fn from_preferences(preferences: &AppPreferences) -> Self {
+        Self::new(
+            preferences.ai_keyword_language.as_deref(),
+            &preferences.ai_custom_stop_words,
+        )
+    }
+
+    fn contains(&self, term: &str) -> bool {
+        self.words.contains(term)
+    }
+
+    /// Meaningful keywords in `query`, folded and in order.
+    pub fn keywords(&self, query: &str) -> Vec<String> {
+        let cleaned_terms = terms(query)
+            .filter(|w| w.chars().count() >= 2)
+            .collect::<Vec<_>>();
+
+        let keywords = cleaned_terms
+            .iter()
+            .filter(|w| !self.contains(w))
+            .cloned()
+            .collect::<Vec<_>>();
+
+        // For stopword-heavy prompts ("what is this about", etc.), keep a small
+        // fallback token set rather than returning no matches.
+        if keywords.is_empty() {
+            cleaned_terms.into_iter().take(6).collect()
+        } else {
+            keywords
+        }
+    }
+}
+
+impl Default for StopWords {
+    fn default() -> Self {
+        Self::new(None, &[])
+    }
+}
+
+/// Stop words from the saved preferences; None until preferences are applied.
+static ACTIVE_STOP_WORDS: Mutex<Option<StopWords>> = Mutex::new(None);
+
+/// Use the keyword language and custom stop words from `preferences`.
+pub fn apply_preferences(preferences: &AppPreferences) {
+    if let Ok(mut active) = ACTIVE_STOP_WORDS.lock() {
+        *active = Some(StopWords::from_preferences(preferences));
+    }
+}
+
+/// Keywords in `query` using the configured stop words.
+pub fn extract_keywords(query: &str) -> Vec<String> {
+    match ACTIVE_STOP_WORDS.lock() {
+        Ok(active) => match active.as_ref() {
+            Some(stop_words) => stop_words.keywords(query),
+            None => StopWords::default().keywords(query),
+        },
+        Err(_) => StopWords::default().keywords(query),
+    }
+}
+
+/// Split `text` into folded terms at anything that isn't a letter or digit,
+/// as the FTS tokenizer does.
+fn terms(text: &str) -> impl Iterator<Item = String> + '_ {
+    text.split(|c: char| !c.is_alphanumeric())
+        .filter(|w| !w.is_empty())
+        .map(fold)
+}
+
+/// Lowercase `text` and strip diacritics from Latin letters, matching the
+/// `unicode61` tokenizer's default `remove_diacritics` folding.
+pub fn fold(text: &str) -> String {
+    text.chars()
+        .flat_map(char::to_lowercase)
+        .map(strip_diacritic)
+        .collect()
+}
+
+fn strip_diacritic(c: char) -> char {
+    match c {
+        'à' | 'á' | 'â' | 'ã' | 'ä' | 'å' | 'ā' | 'ă' | 'ą' => 'a',
+        'ç' | 'ć' | 'ĉ' | 'ċ' | 'č' => 'c',
+        'ď' => 'd',
+        'è' | 'é' | 'ê' | 'ë' | 'ē' | 'ĕ' | 'ė' | 'ę' | 'ě' => 'e',
+        'ĝ' | 'ğ' | 'ġ' | 'ģ' => 'g',
+        'ì' | 'í' | 'î' | 'ï' | 'ĩ' | 'ī' | 'ĭ' | 'į' => 'i',
+        'ñ' | 'ń' | 'ņ' | 'ň' => 'n',
+        'ò' | 'ó' | 'ô' | 'õ' | 'ö' | 'ō' | 'ŏ' | 'ő' => 'o',
+        'ŕ' | 'ř' => 'r',
+        'ś' | 'ŝ' | 'ş' | 'š' => 's',
+        'ţ' | 'ť' => 't',
+        'ù' | 'ú' | 'û' | 'ü' | 'ũ' | 'ū' | 'ŭ' | 'ů' | 'ű' | 'ų' => 'u',
+        'ý' | 'ÿ' => 'y',
+        'ź' | 'ż' | 'ž' => 'z',
+        other => other,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{fold, StopWords};
+
+    #[test]
+    fn german_questions_keep_their_meaningful_words() {
+        let stop_words = StopWords::new(Some("de"), &[]);
+        assert_eq!(
+            stop_words.keywords("Wie beantrage ich Urlaub für das nächste Jahr?"),
+            vec!["beantrage", "urlaub", "nachste", "jahr"]
+        );
+        // The English list would have kept nearly every word
+        assert!(StopWords::default()
+            .keywords("Wie beantrage ich Urlaub für das nächste Jahr?")
+            .contains(&"das".to_string()));
+    }
+
+    #[test]
+    fn french_questions_split_elisions_and_fold_accents() {
+        let stop_words = StopWords::new(Some("fr"), &[]);
+        assert_eq!(
+            stop_words.keywords("Comment est-ce que je déploie l'application en production ?"),
+            vec!["deploie", "application", "production"]
+        );
+        assert_eq!(
+            stop_words.keywords("Où sont les règles de sécurité ?"),
+            vec!["regles", "securite"]
+        );
+    }
+
+    #[test]
+    fn custom_stop_words_are_folded_and_added() {
+        let stop_words = StopWords::new(
+            Some("de"),
+            &["Handbuch".to_string(), "Übersicht".to_string()],
+        );
+        assert_eq!(
+            stop_words.keywords("Übersicht im Handbuch zur Bereitschaft"),
+            vec!["bereitschaft"]
+        );
+        assert_eq!(fold("ÉQUIPE"), "equipe");
+    }
+}
@@ -0,0 +1,225 @@
+//! Chat and embedding providers. Each provider implements `ChatProvider` in
+//! its own module; `for_provider` maps an `AiProvider` to its implementation so
+//! callers in `ai` and `commands` never match on the provider themselves.
+
+use super::{
+    clear_cancel_request, is_cancelled, mark_generating, AiChatMessage, AiEventSchema,
+    AiResponseChunkEvent, AiResponseDoneEvent, PromptCacheUsage,
+};
+use crate::models::{AiProvider, Settings};
+use futures_util::future::BoxFuture;
+use tauri::{AppHandle, Emitter};
+
+mod anthropic;
+mod gemini;
+pub(crate) mod ollama;
+pub(crate) mod openai;
+mod sse;
+
+use sse::{LineReader, SseLineReader};
+
+pub(crate) trait ChatProvider: Send + Sync {
+    /// Name shown to the user in messages.
+    fn name(&self) -> &'static str;
+
+    /// Whether `settings` has what this provider needs (an API key or URL).
+    fn is_configured(&self, settings: &Settings) -> bool;
+
+    /// Whether an interrupted answer can be continued by sending the partial
+    /// reply back with an instruction to carry on.
+    fn can_resume(&self) -> bool {
+        false
+    }
+
+    /// Stream a response to `messages` as ai-response-chunk events, ending
+    /// with ai-response-done.
+    fn stream_chat<'a>(
+        &'a self,
+        client: &'a reqwest::Client,
+        app: &'a AppHandle,
+        settings: &'a Settings,
+        request_id: &'a str,
+        messages: &'a [AiChatMessage],
+    ) -> BoxFuture<'a, Result<(), StreamError>>;
+
+    /// Send `messages` without streaming and return the whole reply, for
+    /// answers that are parsed rather than shown as they arrive.
+    fn complete<'a>(
+        &'a self,
+        client: &'a reqwest::Client,
+        settings: &'a Settings,
+        messages: &'a [AiChatMessage],
+    ) -> BoxFuture<'a, Result<String, String>>;
+
+    fn embed<'a>(
+        &'a self,
+        client: &'a reqwest::Client,
+        settings: &'a Settings,
+        text: &'a str,
+    ) -> BoxFuture<'a, Result<Vec<f32>, String>>;
+
+    /// Make a cheap request to verify the credentials, returning a message
+    /// for the user on success.
+    fn test_connection<'a>(
+        &'a self,
+        client: &'a reqwest::Client,
+        settings: &'a Settings,
+    ) -> BoxFuture<'a, Result<String, String>>;
+}
+
+pub(crate) fn for_provider(provider: &AiProvider) -> &'static dyn ChatProvider {
+    match provider {
+        AiProvider::Openai => &openai::OpenAi,
+        AiProvider::Anthropic => &anthropic::Anthropic,
+        AiProvider::Gemini => &gemini::Gemini,
+        AiProvider::Ollama => &ollama::Ollama,
+    }
+}
+
+fn has_value(value: &Option<String>) -> bool {
+    value
+        .as_ref()
+        .map(|v| !v.trim().is_empty())
+        .unwrap_or(false)
+}
+
+pub(crate) enum StreamError {
+    /// The request failed before any content was emitted
+    Failed(String),
+    /// The connection dropped after `partial` had already been emitted
+    Interrupted { partial: String, message: String },
+}
+
+impl From<String> for StreamError {
+    fn from(message: String) -> Self {
+        StreamError::Failed(message)
+    }
+}
+
+/// What a provider found in one line of its response stream.
+enum StreamEvent {
+    Content(String),
+    /// Prompt cache usage, reported in the done event
+    CacheUsage(PromptCacheUsage),
+    Done,
+}
+
+fn emit_done(
+    app: &AppHandle,
+    request_id: &str,
+    cancelled: bool,
+    prompt_cache: Option<PromptCacheUsage>,
+) {
+    if let Err(e) = app.emit(
+        "ai-response-done",
+        AiResponseDoneEvent {
+            schema_version: AiEventSchema,
+            request_id: request_id.to_string(),
+            cancelled,
+            prompt_cache,
+        },
+    ) {
+        eprintln!("Warning: failed to emit ai-response-done: {}", e);
+    }
+    clear_cancel_request(request_id);
+}
+
+/// How a provider frames its streamed response.
+enum StreamFormat {
+    /// Server-sent events; each event's data is passed on
+    Sse,
+    /// One JSON object per line
+    JsonLines,
+}
+
+/// Read a streaming response, passing each event's data (or each line) to
+/// `parse` and emitting what it finds. The stream ends at the first `Done`,
+/// when the body ends, or when the request is cancelled.
+async fn drive_stream(
+    app: &AppHandle,
+    request_id: &str,
+    resp: reqwest::Response,
+    format: StreamFormat,
+    mut parse: impl FnMut(&str) -> Vec<StreamEvent>,
+) -> Result<(), StreamError> {
+    use futures_util::StreamExt;
+    let mut stream = resp.bytes_stream();
+    let mut sse = SseLineReader::default();
+    let mut lines = LineReader::default();
+    let mut partial = String::new();
+    let mut prompt_cache = None;
+
+    'outer: loop {
+        let next = stream.next().await;
+        let ended = next.is_none();
+        let payloads = match (next, &format) {
+            (Some(chunk), format) => {
+                let chunk = match chunk {
+                    Ok(chunk) => chunk,
+                    Err(e) if partial.is_empty() => {
+                        return Err(StreamError::Failed(format!("Stream error: {}", e)))
+                    }
+                    Err(e) => {
+                        return Err(StreamError::Interrupted {
+                            partial,
+                            message: format!("Stream error: {}", e),
+                        })
+                    }
+                };
+                match format {
+                    StreamFormat::Sse => sse.push(&chunk),
+                    StreamFormat::JsonLines => lines.push(&chunk),
+                }
+            }
+            (None, StreamFormat::Sse) => sse.finish(),
+            (None, StreamFormat::JsonLines) => lines.finish().into_iter().collect(),
+        };
+
+        for payload in payloads {
+            for event in parse(payload.trim()) {
+                match event {
+                    StreamEvent::Content(content) => {
+                        mark_generating(app, request_id);
+                        partial.push_str(&content);
+                        if app
+                            .emit(
+                                "ai-response-chunk",
+                                AiResponseChunkEvent {
+                                    schema_version: AiEventSchema,
+                                    request_id: request_id.to_string(),
+                                    content,
+                                },
+                            )
+                            .is_err()
+                        {
+                            break 'outer;
+                        }
+                    }
+                    StreamEvent::CacheUsage(usage) => prompt_cache = Some(usage),
+                    StreamEvent::Done => {
+                        emit_done(app, request_id, false, prompt_cache);
+                        return Ok(());
+                    }
+                }
+            }
+        }
+
+        if ended {
+            break;
+        }
+        if is_cancelled(request_id) {
+            emit_done(app, request_id, true, prompt_cache);
+            return Ok(());
+        }
+    }
+
+    emit_done(app, request_id, false, prompt_cache);
+    Ok(())
+}
+
+/// Turn a non-success response into the provider's API error message.
+async fn api_error(name: &str, resp: reqwest::Response) -> String {
+    let status = resp.status();
+    let text = resp.text().await.unwrap_or_default();
+    format!("{} API error ({}): {}", name, status, text)
+}
@@ -0,0 +1,255 @@
+use super::{
+    api_error, drive_stream, for_provider, has_value, ollama, ChatProvider, StreamError,
+    StreamEvent, StreamFormat,
+};
+use crate::ai::{AiChatMessage, PromptCacheUsage};
+use crate::models::{AiProvider, Settings};
+use futures_util::future::BoxFuture;
+use tauri::AppHandle;
+
+const MESSAGES_URL: &str = "https://api.anthropic.com/v1/messages";
+const API_VERSION: &str = "2023-06-01";
+
+pub(crate) struct Anthropic;
+
+/// Prompt caching breakpoint; cached prefixes live for five minutes.
+fn cache_breakpoint() -> serde_json::Value {
+    serde_json::json!({ "type": "ephemeral" })
+}
+
+fn api_key(settings: &Settings) -> Result<&String, String> {
+    settings
+        .anthropic_api_key
+        .as_ref()
+        .ok_or_else(|| "Anthropic API key not configured".to_string())
+}
+
+async fn send_chat(
+    client: &reqwest::Client,
+    settings: &Settings,
+    messages: &[AiChatMessage],
+    stream: bool,
+) -> Result<reqwest::Response, String> {
+    let api_key = api_key(settings)?;
+
+    // Separate system message from user/assistant messages for Anthropic's API format
+    let system_msg = messages
+        .iter()
+        .find(|m| m.role == "system")
+        .map(|m| m.content.clone());
+
+    let caching = settings.anthropic_prompt_caching.unwrap_or(false);
+    let chat_messages: Vec<serde_json::Value> = messages
+        .iter()
+        .filter(|m| m.role != "system")
+        .map(|m| {
+            let content = match m.cache_prefix_len {
+                Some(len) if caching && len > 0 && m.content.is_char_boundary(len) => {
+                    // Cache up to the end of the context; only the question varies
+                    let (prefix, rest) = m.content.split_at(len);
+                    serde_json::json!([
+                        { "type": "text", "text": prefix, "cache_control": cache_breakpoint() },
+                        { "type": "text", "text": rest },
+                    ])
+                }
+                _ => serde_json::Value::String(m.content.clone()),
+            };
+            serde_json::json!({
+                "role": m.role,
+                "content": content,
+            })
+        })
+        .collect();
+
+    let mut body = serde_json::json!({
+        "model": settings.anthropic_model(),
+        "max_tokens": 4096,
+        "messages": chat_messages,
+        "stream": stream,
+    });
+
+    if let Some(sys) = system_msg {
+        body["system"] = if caching {
+            serde_json::json!([
+                { "type": "text", "text": sys, "cache_control": cache_breakpoint() },
+            ])
+        } else {
+            serde_json::Value::String(sys)
+        };
+    }
+
+    let resp = client
+        .post(MESSAGES_URL)
+        .header("x-api-key", api_key)
+        .header("anthropic-version", API_VERSION)
+        .header("content-type", "application/json")
+        .json(&body)
+        .send()
+        .await
+        .map_err(|e| format!("Anthropic request failed: {}", e))?;
+
+    if !resp.status().is_success() {
+        return Err(api_error("Anthropic", resp).await);
+    }
+    Ok(resp)
+}
+
+async fn stream_chat(
+    client: &reqwest::Client,
+    app: &AppHandle,
+    settings: &Settings,
+    request_id: &str,
+    messages: &[AiChatMessage],
+) -> Result<(), StreamError> {
+    let resp = send_chat(client, settings, messages, true).await?;
+    let caching = settings.anthropic_prompt_caching.unwrap_or(false);
+
+    drive_stream(app, request_id, resp, StreamFormat::Sse, |data| {
+        let Ok(parsed) = serde_json::from_str::<serde_json::Value>(data) else {
+            return vec![];
+        };
+        match parsed["type"].as_str().unwrap_or("") {
+            "message_start" if caching => {
+                let usage = &parsed["message"]["usage"];
+                let read = usage["cache_read_input_tokens"].as_u64().unwrap_or(0);
+                vec![StreamEvent::CacheUsage(PromptCacheUsage {
+                    cache_creation_input_tokens: usage["cache_creation_input_tokens"]
+                        .as_u64()
+                        .unwrap_or(0),
+                    cache_read_input_tokens: read,
+                    hit: read > 0,
+                })]
+            }
+            "content_block_delta" => parsed["delta"]["text"]
+                .as_str()
+                .map(|text| StreamEvent::Content(text.to_string()))
+                .into_iter()
+                .collect(),
+            "message_stop" => vec![StreamEvent::Done],
+            _ => vec![],
+        }
+    })
+    .await
+}
+
+async fn complete(
+    client: &reqwest::Client,
+    settings: &Settings,
+    messages: &[AiChatMessage],
+) -> Result<String, String> {
+    let parsed: serde_json::Value = send_chat(client, settings, messages, false)
+        .await?
+        .json()
+        .await
+        .map_err(|e| format!("Failed to parse Anthropic response: {}", e))?;
+    let text = parsed["content"]
+        .as_array()
+        .map(|blocks| {
+            blocks
+                .iter()
+                .filter_map(|block| block["text"].as_str())
+                .collect::<String>()
+        })
+        .unwrap_or_default();
+    if text.is_empty() {
+        return Err("Anthropic response had no text content".to_string());
+    }
+    Ok(text)
+}
+
+/// Anthropic has no embedding API; fall back to Ollama, then OpenAI, then
+/// Gemini, whichever is available.
+async fn embed(
+    client: &reqwest::Client,
+    settings: &Settings,
+    text: &str,
+) -> Result<Vec<f32>, String> {
+    let fallback = if ollama::is_available(client, settings, false).await {
+        AiProvider::Ollama
+    } else if settings.openai_api_key.is_some() {
+        AiProvider::Openai
+    } else if settings.gemini_api_key.is_some() {
+        AiProvider::Gemini
+    } else {
+        return Err("Anthropic does not provide an embedding API. Please configure Ollama, OpenAI, or Gemini for embeddings.".to_string());
+    };
+    for_provider(&fallback).embed(client, settings, text).await
+}
+
+async fn test_connection(client: &reqwest::Client, settings: &Settings) -> Result<String, String> {
+    let api_key = api_key(settings)?;
+
+    // Send a minimal request to verify the key
+    let body = serde_json::json!({
+        "model": settings.anthropic_model(),
+        "max_tokens": 1,
+        "messages": [{"role": "user", "content": "Hi"}],
+    });
+
+    let resp = client
+        .post(MESSAGES_URL)
+        .header("x-api-key", api_key)
+        .header("anthropic-version", API_VERSION)
+        .header("content-type", "application/json")
+        .json(&body)
+        .send()
+        .await
+        .map_err(|e| format!("Connection failed: {}", e))?;
+
+    if resp.status().is_success() {
+        Ok("Anthropic connection successful".to_string())
+    } else {
+        Err(api_error("Anthropic", resp).await)
+    }
+}
+
+impl ChatProvider for Anthropic {
+    fn name(&self) -> &'static str {
+        "Anthropic"
+    }
+
+    fn can_resume(&self) -> bool {
+        true
+    }
+
+    fn is_configured(&self, settings: &Settings) -> bool {
+        has_value(&settings.anthropic_api_key)
+    }
+
+    fn stream_chat<'a>(
+        &'a self,
+        client: &'a reqwest::Client,
+        app: &'a AppHandle,
+        settings: &'a Settings,
+        request_id: &'a str,
+        messages: &'a [AiChatMessage],
+    ) -> BoxFuture<'a, Result<(), StreamError>> {
+        Box::pin(stream_chat(client, app, settings, request_id, messages))
+    }
+
+    fn complete<'a>(
+        &'a self,
+        client: &'a reqwest::Client,
+        settings: &'a Settings,
+        messages: &'a [AiChatMessage],
+    ) -> BoxFuture<'a, Result<String, String>> {
+        Box::pin(complete(client, settings, messages))
+    }
+
+    fn embed<'a>(
+        &'a self,
+        client: &'a reqwest::Client,
+        settings: &'a Settings,
+        text: &'a str,
+    ) -> BoxFuture<'a, Result<Vec<f32>, String>> {
+        Box::pin(embed(client, settings, text))
+    }
+
+    fn test_connection<'a>(
+        &'a self,
+        client: &'a reqwest::Client,
+        settings: &'a Settings,
+    ) -> BoxFuture<'a, Result<String, String>> {
+        Box::pin(test_connection(client, settings))
+    }
+}
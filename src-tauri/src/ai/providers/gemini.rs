@@ -0,0 +1,242 @@
+use super::{
+    api_error, drive_stream, has_value, ChatProvider, StreamError, StreamEvent, StreamFormat,
+};
+use crate::ai::AiChatMessage;
+use crate::models::Settings;
+use futures_util::future::BoxFuture;
+use serde::Deserialize;
+use tauri::AppHandle;
+
+pub(crate) struct Gemini;
+
+fn api_key(settings: &Settings) -> Result<&String, String> {
+    settings
+        .gemini_api_key
+        .as_ref()
+        .ok_or_else(|| "Gemini API key not configured".to_string())
+}
+
+async fn send_chat(
+    client: &reqwest::Client,
+    settings: &Settings,
+    messages: &[AiChatMessage],
+    stream: bool,
+) -> Result<reqwest::Response, String> {
+    let api_key = api_key(settings)?;
+
+    let system_instruction = messages
+        .iter()
+        .find(|m| m.role == "system")
+        .map(|m| m.content.clone())
+        .unwrap_or_default();
+    let user_prompt = messages
+        .iter()
+        .filter(|m| m.role == "user")
+        .map(|m| m.content.clone())
+        .collect::<Vec<_>>()
+        .join("\n\n");
+
+    let body = serde_json::json!({
+        "systemInstruction": {
+            "parts": [{ "text": system_instruction }]
+        },
+        "contents": [{
+            "role": "user",
+            "parts": [{ "text": user_prompt }]
+        }]
+    });
+
+    let url = if stream {
+        format!(
+            "https://generativelanguage.googleapis.com/v1beta/models/{}:streamGenerateContent?alt=sse",
+            settings.gemini_model()
+        )
+    } else {
+        format!(
+            "https://generativelanguage.googleapis.com/v1beta/models/{}:generateContent",
+            settings.gemini_model()
+        )
+    };
+
+    let resp = client
+        .post(url)
+        .header("x-goog-api-key", api_key)
+        .json(&body)
+        .send()
+        .await
+        .map_err(|e| format!("Gemini request failed: {}", e))?;
+
+    if !resp.status().is_success() {
+        return Err(api_error("Gemini", resp).await);
+    }
+    Ok(resp)
+}
+
+async fn stream_chat(
+    client: &reqwest::Client,
+    app: &AppHandle,
+    settings: &Settings,
+    request_id: &str,
+    messages: &[AiChatMessage],
+) -> Result<(), StreamError> {
+    let resp = send_chat(client, settings, messages, true).await?;
+
+    // Some Gemini responses repeat the text so far rather than sending a
+    // delta, so only the new suffix is emitted
+    let mut emitted_text = String::new();
+    drive_stream(app, request_id, resp, StreamFormat::Sse, |data| {
+        if data == "[DONE]" {
+            return vec![StreamEvent::Done];
+        }
+        let Some(parsed) = serde_json::from_str::<serde_json::Value>(data).ok() else {
+            return vec![];
+        };
+        let Some(text) = parsed["candidates"][0]["content"]["parts"][0]["text"].as_str() else {
+            return vec![];
+        };
+        let delta = if let Some(suffix) = text.strip_prefix(&emitted_text) {
+            suffix.to_string()
+        } else {
+            text.to_string()
+        };
+        if delta.is_empty() {
+            return vec![];
+        }
+        emitted_text.push_str(&delta);
+        vec![StreamEvent::Content(delta)]
+    })
+    .await
+}
+
+async fn complete(
+    client: &reqwest::Client,
+    settings: &Settings,
+    messages: &[AiChatMessage],
+) -> Result<String, String> {
+    let parsed: serde_json::Value = send_chat(client, settings, messages, false)
+        .await?
+        .json()
+        .await
+        .map_err(|e| format!("Failed to parse Gemini response: {}", e))?;
+    let text = parsed["candidates"][0]["content"]["parts"]
+        .as_array()
+        .map(|parts| {
+            parts
+                .iter()
+                .filter_map(|part| part["text"].as_str())
+                .collect::<String>()
+        })
+        .unwrap_or_default();
+    if text.is_empty() {
+        return Err("Gemini response had no text content".to_string());
+    }
+    Ok(text)
+}
+
+async fn embed(
+    client: &reqwest::Client,
+    settings: &Settings,
+    text: &str,
+) -> Result<Vec<f32>, String> {
+    let api_key = api_key(settings)?;
+
+    let body = serde_json::json!({
+        "model": "models/text-embedding-004",
+        "content": {
+            "parts": [{ "text": text }]
+        }
+    });
+
+    let resp = client
+        .post("https://generativelanguage.googleapis.com/v1beta/models/text-embedding-004:embedContent")
+        .header("x-goog-api-key", api_key)
+        .json(&body)
+        .send()
+        .await
+        .map_err(|e| format!("Gemini embedding request failed: {}", e))?;
+
+    if !resp.status().is_success() {
+        return Err(api_error("Gemini", resp).await);
+    }
+
+    #[derive(Deserialize)]
+    struct GeminiEmbeddingResponse {
+        embedding: GeminiEmbeddingValues,
+    }
+
+    #[derive(Deserialize)]
+    struct GeminiEmbeddingValues {
+        values: Vec<f32>,
+    }
+
+    let parsed: GeminiEmbeddingResponse = resp
+        .json()
+        .await
+        .map_err(|e| format!("Failed to parse Gemini embedding response: {}", e))?;
+
+    Ok(parsed.embedding.values)
+}
+
+async fn test_connection(client: &reqwest::Client, settings: &Settings) -> Result<String, String> {
+    let api_key = api_key(settings)?;
+
+    let resp = client
+        .get("https://generativelanguage.googleapis.com/v1beta/models")
+        .header("x-goog-api-key", api_key)
+        .send()
+        .await
+        .map_err(|e| format!("Connection failed: {}", e))?;
+
+    if resp.status().is_success() {
+        Ok("Gemini connection successful".to_string())
+    } else {
+        Err(api_error("Gemini", resp).await)
+    }
+}
+
+impl ChatProvider for Gemini {
+    fn name(&self) -> &'static str {
+        "Gemini"
+    }
+
+    fn is_configured(&self, settings: &Settings) -> bool {
+        has_value(&settings.gemini_api_key)
+    }
+
+    fn stream_chat<'a>(
+        &'a self,
+        client: &'a reqwest::Client,
+        app: &'a AppHandle,
+        settings: &'a Settings,
+        request_id: &'a str,
+        messages: &'a [AiChatMessage],
+    ) -> BoxFuture<'a, Result<(), StreamError>> {
+        Box::pin(stream_chat(client, app, settings, request_id, messages))
+    }
+
+    fn complete<'a>(
+        &'a self,
+        client: &'a reqwest::Client,
+        settings: &'a Settings,
+        messages: &'a [AiChatMessage],
+    ) -> BoxFuture<'a, Result<String, String>> {
+        Box::pin(complete(client, settings, messages))
+    }
+
+    fn embed<'a>(
+        &'a self,
+        client: &'a reqwest::Client,
+        settings: &'a Settings,
+        text: &'a str,
+    ) -> BoxFuture<'a, Result<Vec<f32>, String>> {
+        Box::pin(embed(client, settings, text))
+    }
+
+    fn test_connection<'a>(
+        &'a self,
+        client: &'a reqwest::Client,
+        settings: &'a Settings,
+    ) -> BoxFuture<'a, Result<String, String>> {
+        Box::pin(test_connection(client, settings))
+    }
+}
@@ -0,0 +1,393 @@
+use super::{
+    api_error, drive_stream, has_value, ChatProvider, StreamError, StreamEvent, StreamFormat,
+};
+use crate::ai::AiChatMessage;
+use crate::models::Settings;
+use futures_util::future::BoxFuture;
+use serde::Deserialize;
+use std::collections::HashMap;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+use tauri::{AppHandle, Emitter};
+
+/// Cached Ollama availability per base URL, with a 30-second TTL.
+static OLLAMA_AVAILABLE_CACHE: Mutex<Option<HashMap<String, (bool, Instant)>>> = Mutex::new(None);
+const OLLAMA_CACHE_TTL_SECS: u64 = 30;
+/// An unreachable host shouldn't hold up the provider choice for long.
+const OLLAMA_PROBE_TIMEOUT_SECS: u64 = 3;
+const OLLAMA_CHAT_MODEL: &str = "llama3";
+/// How long Ollama keeps the chat model loaded after a request.
+const OLLAMA_KEEP_ALIVE: &str = "30m";
+/// When the model was last loaded or used, treated as warm for a little less
+/// than `OLLAMA_KEEP_ALIVE`.
+static OLLAMA_WARM_CACHE: Mutex<Option<Instant>> = Mutex::new(None);
+const OLLAMA_WARM_TTL_SECS: u64 = 25 * 60;
+/// Loading a large model can take well over the default client timeout.
+const OLLAMA_WARMUP_TIMEOUT_SECS: u64 = 180;
+
+pub(crate) struct Ollama;
+
+const DEFAULT_BASE_URL: &str = "http://localhost:11434";
+
+fn base_url(settings: &Settings) -> &str {
+    settings
+        .ollama_base_url
+        .as_deref()
+        .unwrap_or(DEFAULT_BASE_URL)
+}
+
+/// Cache key for a base URL, so "http://host:11434/" and
+/// "http://host:11434" share a verdict.
+fn cache_key(base_url: &str) -> String {
+    base_url.trim().trim_end_matches('/').to_string()
+}
+
+/// Whether Ollama answers at the configured base URL, probed with
+/// `/api/tags` (which any running server serves) and cached per URL.
+/// `force_refresh` probes even when a fresh verdict is cached.
+pub(crate) async fn is_available(
+    client: &reqwest::Client,
+    settings: &Settings,
+    force_refresh: bool,
+) -> bool {
+    let key = cache_key(base_url(settings));
+    if !force_refresh {
+        if let Ok(cache) = OLLAMA_AVAILABLE_CACHE.lock() {
+            if let Some((available, checked_at)) = cache.as_ref().and_then(|c| c.get(&key)) {
+                if checked_at.elapsed().as_secs() < OLLAMA_CACHE_TTL_SECS {
+                    return *available;
+                }
+            }
+        }
+    }
+
+    let available = client
+        .get(format!("{}/api/tags", key))
+        .timeout(Duration::from_secs(OLLAMA_PROBE_TIMEOUT_SECS))
+        .send()
+        .await
+        .is_ok_and(|resp| resp.status().is_success());
+
+    cache_availability(key, available);
+    available
+}
+
+fn cache_availability(key: String, available: bool) {
+    if let Ok(mut cache) = OLLAMA_AVAILABLE_CACHE.lock() {
+        cache
+            .get_or_insert_with(HashMap::new)
+            .insert(key, (available, Instant::now()));
+    }
+}
+
+/// Forget the cached availability for `base_url` (the default URL when None).
+pub(crate) fn invalidate_availability(base_url: Option<&str>) {
+    let key = cache_key(base_url.unwrap_or(DEFAULT_BASE_URL));
+    if let Ok(mut cache) = OLLAMA_AVAILABLE_CACHE.lock() {
+        if let Some(cache) = cache.as_mut() {
+            cache.remove(&key);
+        }
+    }
+}
+
+fn ollama_is_warm() -> bool {
+    OLLAMA_WARM_CACHE
+        .lock()
+        .ok()
+        .and_then(|cache| *cache)
+        .map(|warmed_at| warmed_at.elapsed().as_secs() < OLLAMA_WARM_TTL_SECS)
+        .unwrap_or(false)
+}
+
+fn mark_ollama_warm() {
+    if let Ok(mut cache) = OLLAMA_WARM_CACHE.lock() {
+        *cache = Some(Instant::now());
+    }
+}
+
+#[derive(serde::Serialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct OllamaWarmupDoneEvent {
+    pub model: String,
+    /// Time Ollama spent loading the model; 0 when it was already loaded.
+    pub load_duration_ms: u64,
+    /// True when the request was skipped because the model was recently used.
+    pub already_warm: bool,
+}
+
+/// Load the Ollama chat model ahead of the first question so it doesn't pay
+/// the model load time. Sends an empty prompt, which loads the model without
+/// generating anything, and emits "ollama-warmup-done". Skipped while the
+/// model is known to be loaded.
+pub async fn warm_ollama_model(
+    client: &reqwest::Client,
+    app: &AppHandle,
+    settings: &Settings,
+) -> Result<(), String> {
+    if ollama_is_warm() {
+        let _ = app.emit(
+            "ollama-warmup-done",
+            OllamaWarmupDoneEvent {
+                model: OLLAMA_CHAT_MODEL.to_string(),
+                load_duration_ms: 0,
+                already_warm: true,
+            },
+        );
+        return Ok(());
+    }
+
+    let body = serde_json::json!({
+        "model": OLLAMA_CHAT_MODEL,
+        "prompt": "",
+        "stream": false,
+        "keep_alive": OLLAMA_KEEP_ALIVE,
+    });
+
+    let resp = client
+        .post(format!("{}/api/generate", base_url(settings)))
+        .timeout(std::time::Duration::from_secs(OLLAMA_WARMUP_TIMEOUT_SECS))
+        .json(&body)
+        .send()
+        .await
+        .map_err(|e| format!("Ollama warmup request failed: {}", e))?;
+
+    if !resp.status().is_success() {
+        return Err(api_error("Ollama", resp).await);
+    }
+
+    #[derive(Deserialize)]
+    struct OllamaGenerateResponse {
+        /// Nanoseconds
+        #[serde(default)]
+        load_duration: u64,
+    }
+
+    let parsed: OllamaGenerateResponse = resp
+        .json()
+        .await
+        .map_err(|e| format!("Failed to parse Ollama warmup response: {}", e))?;
+
+    mark_ollama_warm();
+    let _ = app.emit(
+        "ollama-warmup-done",
+        OllamaWarmupDoneEvent {
+            model: OLLAMA_CHAT_MODEL.to_string(),
+            load_duration_ms: parsed.load_duration / 1_000_000,
+            already_warm: false,
+        },
+    );
+    Ok(())
+}
+
+async fn send_chat(
+    client: &reqwest::Client,
+    settings: &Settings,
+    messages: &[AiChatMessage],
+    stream: bool,
+) -> Result<reqwest::Response, String> {
+    let ollama_messages: Vec<serde_json::Value> = messages
+        .iter()
+        .map(|m| {
+            serde_json::json!({
+                "role": m.role,
+                "content": m.content,
+            })
+        })
+        .collect();
+
+    let body = serde_json::json!({
+        "model": OLLAMA_CHAT_MODEL,
+        "messages": ollama_messages,
+        "stream": stream,
+        "keep_alive": OLLAMA_KEEP_ALIVE,
+    });
+
+    let resp = client
+        .post(format!("{}/api/chat", base_url(settings)))
+        .json(&body)
+        .send()
+        .await
+        .map_err(|e| format!("Ollama request failed: {}. Is Ollama running?", e))?;
+
+    if !resp.status().is_success() {
+        return Err(api_error("Ollama", resp).await);
+    }
+    // The model is loaded once Ollama starts responding
+    mark_ollama_warm();
+    Ok(resp)
+}
+
+async fn stream_chat(
+    client: &reqwest::Client,
+    app: &AppHandle,
+    settings: &Settings,
+    request_id: &str,
+    messages: &[AiChatMessage],
+) -> Result<(), StreamError> {
+    let resp = send_chat(client, settings, messages, true).await?;
+
+    // Ollama streams one JSON object per line rather than SSE
+    drive_stream(app, request_id, resp, StreamFormat::JsonLines, |line| {
+        if line.is_empty() {
+            return vec![];
+        }
+        let Ok(parsed) = serde_json::from_str::<serde_json::Value>(line) else {
+            return vec![];
+        };
+        let mut events = Vec::new();
+        if let Some(content) = parsed["message"]["content"].as_str() {
+            events.push(StreamEvent::Content(content.to_string()));
+        }
+        if parsed["done"].as_bool() == Some(true) {
+            events.push(StreamEvent::Done);
+        }
+        events
+    })
+    .await
+}
+
+async fn complete(
+    client: &reqwest::Client,
+    settings: &Settings,
+    messages: &[AiChatMessage],
+) -> Result<String, String> {
+    let parsed: serde_json::Value = send_chat(client, settings, messages, false)
+        .await?
+        .json()
+        .await
+        .map_err(|e| format!("Failed to parse Ollama response: {}", e))?;
+    parsed["message"]["content"]
+        .as_str()
+        .map(str::to_string)
+        .ok_or_else(|| "Ollama response had no message content".to_string())
+}
+
+async fn embed(
+    client: &reqwest::Client,
+    settings: &Settings,
+    text: &str,
+) -> Result<Vec<f32>, String> {
+    let body = serde_json::json!({
+        "model": "nomic-embed-text",
+        "prompt": text,
+    });
+
+    let resp = client
+        .post(format!("{}/api/embeddings", base_url(settings)))
+        .json(&body)
+        .send()
+        .await
+        .map_err(|e| format!("Ollama embedding request failed: {}", e))?;
+
+    if !resp.status().is_success() {
+        return Err(api_error("Ollama", resp).await);
+    }
+
+    #[derive(Deserialize)]
+    struct OllamaEmbeddingResponse {
+        embedding: Vec<f32>,
+    }
+
+    let parsed: OllamaEmbeddingResponse = resp
+        .json()
+        .await
+        .map_err(|e| format!("Failed to parse Ollama embedding response: {}", e))?;
+
+    Ok(parsed.embedding)
+}
+
+/// Probes the same endpoint as `is_available` and caches the verdict, so a
+/// passing test is picked up by the embedding fallback straight away.
+async fn test_connection(client: &reqwest::Client, settings: &Settings) -> Result<String, String> {
+    let key = cache_key(base_url(settings));
+    let resp = client
+        .get(format!("{}/api/tags", key))
+        .send()
+        .await
+        .map_err(|e| {
+            cache_availability(key.clone(), false);
+            format!("Ollama not reachable: {}. Is Ollama running?", e)
+        })?;
+
+    cache_availability(key, resp.status().is_success());
+    if resp.status().is_success() {
+        Ok("Ollama connection successful".to_string())
+    } else {
+        Err(format!("Ollama returned status {}", resp.status()))
+    }
+}
+
+impl ChatProvider for Ollama {
+    fn name(&self) -> &'static str {
+        "Ollama"
+    }
+
+    fn is_configured(&self, settings: &Settings) -> bool {
+        has_value(&settings.ollama_base_url)
+    }
+
+    fn stream_chat<'a>(
+        &'a self,
+        client: &'a reqwest::Client,
+        app: &'a AppHandle,
+        settings: &'a Settings,
+        request_id: &'a str,
+        messages: &'a [AiChatMessage],
+    ) -> BoxFuture<'a, Result<(), StreamError>> {
+        Box::pin(stream_chat(client, app, settings, request_id, messages))
+    }
+
+    fn complete<'a>(
+        &'a self,
+        client: &'a reqwest::Client,
+        settings: &'a Settings,
+        messages: &'a [AiChatMessage],
+    ) -> BoxFuture<'a, Result<String, String>> {
+        Box::pin(complete(client, settings, messages))
+    }
+
+    fn embed<'a>(
+        &'a self,
+        client: &'a reqwest::Client,
+        settings: &'a Settings,
+        text: &'a str,
+    ) -> BoxFuture<'a, Result<Vec<f32>, String>> {
+        Box::pin(embed(client, settings, text))
+    }
+
+    fn test_connection<'a>(
+        &'a self,
+        client: &'a reqwest::Client,
+        settings: &'a Settings,
+    ) -> BoxFuture<'a, Result<String, String>> {
+        Box::pin(test_connection(client, settings))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{cache_availability, invalidate_availability, is_available};
+    use crate::models::Settings;
+
+    fn settings_for(url: &str) -> Settings {
+        Settings {
+            ollama_base_url: Some(url.to_string()),
+            ..Settings::default()
+        }
+    }
+
+    #[tokio::test]
+    async fn availability_is_cached_per_base_url() {
+        let client = reqwest::Client::new();
+        // Port 9 (discard) refuses connections, so a real probe fails fast
+        let unreachable = "http://127.0.0.1:9";
+        cache_availability(unreachable.to_string(), true);
+        assert!(is_available(&client, &settings_for("http://127.0.0.1:9/"), false).await);
+        assert!(!is_available(&client, &settings_for("http://127.0.0.1:9/ollama"), false).await);
+
+        invalidate_availability(Some(unreachable));
+        assert!(!is_available(&client, &settings_for(unreachable), false).await);
+        cache_availability(unreachable.to_string(), true);
+        assert!(!is_available(&client, &settings_for(unreachable), true).await);
+    }
+}
@@ -0,0 +1,272 @@
+use super::{
+    api_error, drive_stream, has_value, ChatProvider, StreamError, StreamEvent, StreamFormat,
+};
+use crate::ai::AiChatMessage;
+use crate::models::Settings;
+use futures_util::future::BoxFuture;
+use serde::Deserialize;
+use tauri::AppHandle;
+
+const EMBEDDING_MODEL: &str = "text-embedding-3-small";
+const EMBEDDINGS_URL: &str = "https://api.openai.com/v1/embeddings";
+const EMBEDDING_RATE_LIMIT_RETRIES: u32 = 4;
+/// Longest Retry-After honoured; a longer one waits this long instead.
+const EMBEDDING_RETRY_AFTER_MAX_SECS: u64 = 60;
+
+pub(crate) struct OpenAi;
+
+fn api_key(settings: &Settings) -> Result<&String, String> {
+    settings
+        .openai_api_key
+        .as_ref()
+        .ok_or_else(|| "OpenAI API key not configured".to_string())
+}
+
+async fn send_chat(
+    client: &reqwest::Client,
+    settings: &Settings,
+    messages: &[AiChatMessage],
+    stream: bool,
+) -> Result<reqwest::Response, String> {
+    let api_key = api_key(settings)?;
+
+    let body = serde_json::json!({
+        "model": "gpt-4o",
+        "messages": messages,
+        "stream": stream,
+    });
+
+    let resp = client
+        .post("https://api.openai.com/v1/chat/completions")
+        .header("Authorization", format!("Bearer {}", api_key))
+        .json(&body)
+        .send()
+        .await
+        .map_err(|e| format!("OpenAI request failed: {}", e))?;
+
+    if !resp.status().is_success() {
+        return Err(api_error("OpenAI", resp).await);
+    }
+    Ok(resp)
+}
+
+async fn stream_chat(
+    client: &reqwest::Client,
+    app: &AppHandle,
+    settings: &Settings,
+    request_id: &str,
+    messages: &[AiChatMessage],
+) -> Result<(), StreamError> {
+    let resp = send_chat(client, settings, messages, true).await?;
+
+    drive_stream(app, request_id, resp, StreamFormat::Sse, |data| {
+        if data == "[DONE]" {
+            return vec![StreamEvent::Done];
+        }
+        serde_json::from_str::<serde_json::Value>(data)
+            .ok()
+            .and_then(|parsed| {
+                parsed["choices"][0]["delta"]["content"]
+                    .as_str()
+                    .map(|content| StreamEvent::Content(content.to_string()))
+            })
+            .into_iter()
+            .collect()
+    })
+    .await
+}
+
+async fn complete(
+    client: &reqwest::Client,
+    settings: &Settings,
+    messages: &[AiChatMessage],
+) -> Result<String, String> {
+    let parsed: serde_json::Value = send_chat(client, settings, messages, false)
+        .await?
+        .json()
+        .await
+        .map_err(|e| format!("Failed to parse OpenAI response: {}", e))?;
+    parsed["choices"][0]["message"]["content"]
+        .as_str()
+        .map(str::to_string)
+        .ok_or_else(|| "OpenAI response had no message content".to_string())
+}
+
+async fn embed(
+    client: &reqwest::Client,
+    settings: &Settings,
+    text: &str,
+) -> Result<Vec<f32>, String> {
+    let api_key = api_key(settings)?;
+
+    let body = serde_json::json!({
+        "model": EMBEDDING_MODEL,
+        "input": text,
+    });
+
+    let resp = client
+        .post(EMBEDDINGS_URL)
+        .header("Authorization", format!("Bearer {}", api_key))
+        .json(&body)
+        .send()
+        .await
+        .map_err(|e| format!("OpenAI embedding request failed: {}", e))?;
+
+    if !resp.status().is_success() {
+        return Err(api_error("OpenAI", resp).await);
+    }
+
+    #[derive(Deserialize)]
+    struct EmbeddingData {
+        embedding: Vec<f32>,
+    }
+    #[derive(Deserialize)]
+    struct EmbeddingResponse {
+        data: Vec<EmbeddingData>,
+    }
+
+    let parsed: EmbeddingResponse = resp
+        .json()
+        .await
+        .map_err(|e| format!("Failed to parse OpenAI embedding response: {}", e))?;
+
+    parsed
+        .data
+        .into_iter()
+        .next()
+        .map(|d| d.embedding)
+        .ok_or_else(|| "No embedding returned from OpenAI".to_string())
+}
+
+/// Embed several texts in one OpenAI request, as the build pipeline does for
+/// chunk embeddings. Vectors are returned in input order. Requests
+/// rejected with HTTP 429 are retried after the requested delay.
+pub async fn generate_embeddings_batch(
+    client: &reqwest::Client,
+    api_key: &str,
+    texts: &[String],
+) -> Result<Vec<Vec<f32>>, String> {
+    if texts.is_empty() {
+        return Ok(vec![]);
+    }
+
+    let body = serde_json::json!({
+        "model": EMBEDDING_MODEL,
+        "input": texts,
+    });
+
+    // Rate-limited requests are retried after the delay OpenAI asks for
+    let mut attempt = 0;
+    let resp = loop {
+        let resp = client
+            .post(EMBEDDINGS_URL)
+            .header("Authorization", format!("Bearer {}", api_key))
+            .json(&body)
+            .send()
+            .await
+            .map_err(|e| format!("OpenAI embedding request failed: {}", e))?;
+        if resp.status() != reqwest::StatusCode::TOO_MANY_REQUESTS
+            || attempt >= EMBEDDING_RATE_LIMIT_RETRIES
+        {
+            break resp;
+        }
+        attempt += 1;
+        let wait_secs = resp
+            .headers()
+            .get(reqwest::header::RETRY_AFTER)
+            .and_then(|v| v.to_str().ok())
+            .and_then(|v| v.parse::<u64>().ok())
+            .unwrap_or(1 << attempt)
+            .min(EMBEDDING_RETRY_AFTER_MAX_SECS);
+        tokio::time::sleep(std::time::Duration::from_secs(wait_secs)).await;
+    };
+
+    if !resp.status().is_success() {
+        return Err(api_error("OpenAI", resp).await);
+    }
+
+    #[derive(Deserialize)]
+    struct EmbeddingData {
+        index: usize,
+        embedding: Vec<f32>,
+    }
+    #[derive(Deserialize)]
+    struct EmbeddingResponse {
+        data: Vec<EmbeddingData>,
+    }
+
+    let mut parsed: EmbeddingResponse = resp
+        .json()
+        .await
+        .map_err(|e| format!("Failed to parse OpenAI embedding response: {}", e))?;
+    parsed.data.sort_by_key(|d| d.index);
+    Ok(parsed.data.into_iter().map(|d| d.embedding).collect())
+}
+
+async fn test_connection(client: &reqwest::Client, settings: &Settings) -> Result<String, String> {
+    let api_key = api_key(settings)?;
+
+    let resp = client
+        .get("https://api.openai.com/v1/models")
+        .header("Authorization", format!("Bearer {}", api_key))
+        .send()
+        .await
+        .map_err(|e| format!("Connection failed: {}", e))?;
+
+    if resp.status().is_success() {
+        Ok("OpenAI connection successful".to_string())
+    } else {
+        Err(api_error("OpenAI", resp).await)
+    }
+}
+
+impl ChatProvider for OpenAi {
+    fn name(&self) -> &'static str {
+        "OpenAI"
+    }
+
+    fn can_resume(&self) -> bool {
+        true
+    }
+
+    fn is_configured(&self, settings: &Settings) -> bool {
+        has_value(&settings.openai_api_key)
+    }
+
+    fn stream_chat<'a>(
+        &'a self,
+        client: &'a reqwest::Client,
+        app: &'a AppHandle,
+        settings: &'a Settings,
+        request_id: &'a str,
+        messages: &'a [AiChatMessage],
+    ) -> BoxFuture<'a, Result<(), StreamError>> {
+        Box::pin(stream_chat(client, app, settings, request_id, messages))
+    }
+
+    fn complete<'a>(
+        &'a self,
+        client: &'a reqwest::Client,
+        settings: &'a Settings,
+        messages: &'a [AiChatMessage],
+    ) -> BoxFuture<'a, Result<String, String>> {
+        Box::pin(complete(client, settings, messages))
+    }
+
+    fn embed<'a>(
+        &'a self,
+        client: &'a reqwest::Client,
+        settings: &'a Settings,
+        text: &'a str,
+    ) -> BoxFuture<'a, Result<Vec<f32>, String>> {
+        Box::pin(embed(client, settings, text))
+    }
+
+    fn test_connection<'a>(
+        &'a self,
+        client: &'a reqwest::Client,
+        settings: &'a Settings,
+    ) -> BoxFuture<'a, Result<String, String>> {
+        Box::pin(test_connection(client, settings))
+    }
+}
@@ -0,0 +1,180 @@
+//! Incremental parsing of streamed provider responses. Network chunks can end
+//! anywhere, including mid-line or inside a multi-byte character, so bytes are
+//! buffered until a whole line (or, for server-sent events, a whole event) has
+//! arrived.
+
+/// Splits a byte stream into lines ending in `\n`, `\r\n` or `\r`.
+#[derive(Default)]
+pub(crate) struct LineReader {
+    buffer: Vec<u8>,
+    /// The last byte was `\r`, so a following `\n` belongs to the same break.
+    after_cr: bool,
+}
+
+impl LineReader {
+    /// Feed the next chunk and return the lines it completes, without their
+    /// line endings.
+    pub fn push(&mut self, bytes: &[u8]) -> Vec<String> {
+        let mut lines = Vec::new();
+        for &byte in bytes {
+            match byte {
+                b'\n' if self.after_cr => self.after_cr = false,
+                b'\n' | b'\r' => {
+                    lines.push(self.take_line());
+                    self.after_cr = byte == b'\r';
+                }
+                _ => {
+                    self.after_cr = false;
+                    self.buffer.push(byte);
+                }
+            }
+        }
+        lines
+    }
+
+    /// The unterminated last line, once the stream has ended.
+    pub fn finish(&mut self) -> Option<String> {
+        self.after_cr = false;
+        (!self.buffer.is_empty()).then(|| self.take_line())
+    }
+
+    fn take_line(&mut self) -> String {
+        // Lines only break on ASCII bytes, so characters are never split
+        let line = String::from_utf8_lossy(&self.buffer).into_owned();
+        self.buffer.clear();
+        line
+    }
+}
+
+/// Reads a `text/event-stream` body and yields the data of each event. Data
+/// spread over several `data:` lines is joined with newlines; comment lines
+/// (keep-alives) and other fields are skipped.
+#[derive(Default)]
+pub(crate) struct SseLineReader {
+    lines: LineReader,
+    data: Vec<String>,
+}
+
+impl SseLineReader {
+    /// Feed the next chunk and return the data of the events it completes.
+    pub fn push(&mut self, bytes: &[u8]) -> Vec<String> {
+        self.lines
+            .push(bytes)
+            .into_iter()
+            .filter_map(|line| self.line(&line))
+            .collect()
+    }
+
+    /// Data of an event left open when the stream ended.
+    pub fn finish(&mut self) -> Vec<String> {
+        let last = self.lines.finish().and_then(|line| self.line(&line));
+        last.into_iter().chain(self.dispatch()).collect()
+    }
+
+    fn line(&mut self, line: &str) -> Option<String> {
+        if line.is_empty() {
+            return self.dispatch();
+        }
+        if line.starts_with(':') {
+            return None;
+        }
+        let (field, value) = match line.split_once(':') {
+            Some((field, value)) => (field, value.strip_prefix(' ').unwrap_or(value)),
+            None => (line, ""),
+        };
+        if field == "data" {
+            self.data.push(value.to_string());
+        }
+        None
+    }
+
+    fn dispatch(&mut self) -> Option<String> {
+        if self.data.is_empty() {
+            return None;
+        }
+        let data = self.data.join("\n");
+        self.data.clear();
+        Some(data)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{LineReader, SseLineReader};
+
+    const OPENAI_STREAM: &str = "data: {\"choices\":[{\"delta\":{\"content\":\"Hel\"}}]}\n\n\
+        data: {\"choices\":[{\"delta\":{\"content\":\"lo — ok\"}}]}\n\n\
+        data: [DONE]\n\n";
+
+    const ANTHROPIC_STREAM: &str = "event: message_start\n\
+        data: {\"type\":\"message_start\",\"message\":{\"id\":\"msg_1\"}}\n\n\
+        event: ping\n\
+        data: {\"type\": \"ping\"}\n\n\
+        event: content_block_delta\n\
+        data: {\"type\":\"content_block_delta\",\"index\":0,\"delta\":{\"type\":\"text_delta\",\"text\":\"Hi\"}}\n\n\
+        event: message_stop\n\
+        data: {\"type\":\"message_stop\"}\n\n";
+
+    const GEMINI_STREAM: &str = "data: {\"candidates\": [{\"content\": {\"parts\": [{\"text\": \"Deploys run \"}],\"role\": \"model\"}}]}\r\n\r\n\
+        data: {\"candidates\": [{\"content\": {\"parts\": [{\"text\": \"on Fridays ✓\"}],\"role\": \"model\"}}]}\r\n\r\n";
+
+    const OLLAMA_STREAM: &str =
+        "{\"message\":{\"role\":\"assistant\",\"content\":\"Ja\"},\"done\":false}\n\
+        {\"message\":{\"role\":\"assistant\",\"content\":\"wohl\"},\"done\":false}\n\
+        {\"message\":{\"role\":\"assistant\",\"content\":\"\"},\"done\":true}\n";
+
+    fn sse_events(chunks: &[&[u8]]) -> Vec<String> {
+        let mut reader = SseLineReader::default();
+        let mut events: Vec<String> = chunks.iter().flat_map(|c| reader.push(c)).collect();
+        events.extend(reader.finish());
+        events
+    }
+
+    /// Events from `stream` fed in one piece, and split in two at every byte.
+    fn assert_split_invariant(stream: &str, expected_events: usize) {
+        let bytes = stream.as_bytes();
+        let whole = sse_events(&[bytes]);
+        assert_eq!(whole.len(), expected_events);
+        for split in 1..bytes.len() {
+            let (a, b) = bytes.split_at(split);
+            assert_eq!(sse_events(&[a, b]), whole, "split at byte {}", split);
+        }
+        for event in &whole {
+            if event != "[DONE]" {
+                serde_json::from_str::<serde_json::Value>(event).expect("complete JSON event");
+            }
+        }
+    }
+
+    #[test]
+    fn provider_streams_parse_the_same_however_they_are_split() {
+        assert_split_invariant(OPENAI_STREAM, 3);
+        assert_split_invariant(ANTHROPIC_STREAM, 4);
+        assert_split_invariant(GEMINI_STREAM, 2);
+    }
+
+    #[test]
+    fn json_lines_parse_the_same_however_they_are_split() {
+        let bytes = OLLAMA_STREAM.as_bytes();
+        for split in 1..bytes.len() {
+            let (a, b) = bytes.split_at(split);
+            let mut reader = LineReader::default();
+            let mut lines = reader.push(a);
+            lines.extend(reader.push(b));
+            lines.extend(reader.finish());
+            assert_eq!(lines.len(), 3, "split at byte {}", split);
+            assert!(lines[1].contains("wohl"));
+        }
+    }
+
+    #[test]
+    fn sse_handles_crlf_comments_and_multiline_data() {
+        let events = sse_events(&[
+            b": keep-alive\r\n\r\n",
+            b"data: first\r\ndata: second\r\n\r\n",
+            b"event: update\rdata:no-space\r\r",
+            b"data: unterminated",
+        ]);
+        assert_eq!(events, vec!["first\nsecond", "no-space", "unterminated"]);
+    }
+}
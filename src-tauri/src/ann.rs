@@ -0,0 +1,251 @@
+//! Approximate nearest-neighbour index for vector search on large projects.
+//!
+//! Projects with at least `MIN_CHUNKS` embeddings get an IVF index: embeddings
+//! are clustered with spherical k-means, and a query only scores the chunks in
+//! the clusters whose centroids are closest to it. The index holds centroids
+//! and chunk IDs, not vectors, so `vector_search` still reads and scores the
+//! candidate embeddings exactly.
+//!
+//! Indexes are built lazily on a background thread the first time a large
+//! database is searched, cached in memory by database path, and dropped when
+//! the project's connection closes (every rebuild swaps it).
+
+use crate::models::AnnIndexStatus;
+use rusqlite::{Connection, OpenFlags};
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+
+/// Below this many embeddings, exact search is fast enough.
+const MIN_CHUNKS: i64 = 50_000;
+/// Vectors sampled to train the centroids.
+const TRAIN_SAMPLE: usize = 16_384;
+const KMEANS_ITERATIONS: usize = 10;
+const MAX_LISTS: usize = 1024;
+
+struct IvfIndex {
+    centroids: Vec<Vec<f32>>,
+    lists: Vec<Vec<i32>>,
+}
+
+enum IndexState {
+    TooSmall,
+    Building(u64),
+    Ready(Arc<IvfIndex>),
+    Failed,
+}
+
+#[derive(Default)]
+struct Indexes {
+    next_generation: u64,
+    by_path: HashMap<String, IndexState>,
+}
+
+static INDEXES: Mutex<Option<Indexes>> = Mutex::new(None);
+
+fn with_indexes<T>(f: impl FnOnce(&mut Indexes) -> T) -> Option<T> {
+    let mut guard = INDEXES.lock().ok()?;
+    Some(f(guard.get_or_insert_with(Indexes::default)))
+}
+
+fn normalize(mut vector: Vec<f32>) -> Option<Vec<f32>> {
+    let norm = vector.iter().map(|v| v * v).sum::<f32>().sqrt();
+    if norm == 0.0 || !norm.is_finite() {
+        return None;
+    }
+    vector.iter_mut().for_each(|v| *v /= norm);
+    Some(vector)
+}
+
+fn dot(a: &[f32], b: &[f32]) -> f32 {
+    a.iter().zip(b).map(|(x, y)| x * y).sum()
+}
+
+fn nearest(centroids: &[Vec<f32>], vector: &[f32]) -> usize {
+    centroids
+        .iter()
+        .enumerate()
+        .map(|(i, c)| (i, dot(c, vector)))
+        .max_by(|a, b| a.1.partial_cmp(&b.1).unwrap_or(std::cmp::Ordering::Equal))
+        .map(|(i, _)| i)
+        .unwrap_or(0)
+}
+
+/// Stream every embedding in the database as `(chunk_id, unit vector)`.
+/// Vectors with a different dimension from the first are skipped.
+fn for_each_embedding(conn: &Connection, mut f: impl FnMut(i32, Vec<f32>)) -> Result<(), String> {
+    let scale_column = if crate::ai::embeddings_have_scale(conn) {
+        "scale"
+    } else {
+        "NULL"
+    };
+    let mut stmt = conn
+        .prepare(&format!(
+            "SELECT chunk_id, embedding, {} FROM chunk_embeddings ORDER BY chunk_id",
+            scale_column
+        ))
+        .map_err(|e| e.to_string())?;
+    let mut rows = stmt.query([]).map_err(|e| e.to_string())?;
+    let mut dims = None;
+    while let Some(row) = rows.next().map_err(|e| e.to_string())? {
+        let chunk_id: i32 = row.get(0).map_err(|e| e.to_string())?;
+        let blob: Vec<u8> = row.get(1).map_err(|e| e.to_string())?;
+        let scale: Option<f64> = row.get(2).map_err(|e| e.to_string())?;
+        let vector = crate::ai::decode_embedding_blob(&blob, scale);
+        if *dims.get_or_insert(vector.len()) != vector.len() {
+            continue;
+        }
+        if let Some(vector) = normalize(vector) {
+            f(chunk_id, vector);
+        }
+    }
+    Ok(())
+}
+
+fn build_index(db_path: &str, count: i64) -> Result<IvfIndex, String> {
+    let conn = Connection::open_with_flags(db_path, OpenFlags::SQLITE_OPEN_READ_ONLY)
+        .map_err(|e| e.to_string())?;
+
+    // Evenly spaced sample to train on
+    let stride = (count as usize / TRAIN_SAMPLE).max(1);
+    let mut sample = Vec::with_capacity(TRAIN_SAMPLE);
+    let mut seen = 0usize;
+    for_each_embedding(&conn, |_, vector| {
+        if seen.is_multiple_of(stride) && sample.len() < TRAIN_SAMPLE {
+            sample.push(vector);
+        }
+        seen += 1;
+    })?;
+    if sample.is_empty() {
+        return Err("No embeddings to index".to_string());
+    }
+    let list_count = ((count as f64).sqrt() as usize).clamp(1, MAX_LISTS.min(sample.len()));
+
+    let sample_stride = sample.len() / list_count;
+    let mut centroids: Vec<Vec<f32>> = (0..list_count)
+        .map(|i| sample[i * sample_stride].clone())
+        .collect();
+    for _ in 0..KMEANS_ITERATIONS {
+        let mut sums = vec![vec![0.0f32; centroids[0].len()]; list_count];
+        let mut members = vec![0usize; list_count];
+        for vector in &sample {
+            let cluster = nearest(&centroids, vector);
+            members[cluster] += 1;
+            sums[cluster]
+                .iter_mut()
+                .zip(vector)
+                .for_each(|(sum, v)| *sum += v);
+        }
+        for (cluster, sum) in sums.into_iter().enumerate() {
+            // Empty clusters keep their previous centroid
+            if members[cluster] > 0 {
+                if let Some(centroid) = normalize(sum) {
+                    centroids[cluster] = centroid;
+                }
+            }
+        }
+    }
+
+    let mut lists = vec![Vec::new(); list_count];
+    for_each_embedding(&conn, |chunk_id, vector| {
+        lists[nearest(&centroids, &vector)].push(chunk_id);
+    })?;
+    Ok(IvfIndex { centroids, lists })
+}
+
+fn usable_path(db: &Connection) -> Option<String> {
+    db.path()
+        .filter(|path| !path.is_empty())
+        .map(str::to_string)
+}
+
+/// Candidate chunk IDs for `query` from the database's index, or `None` when
+/// the database is too small for one or it isn't built yet, in which case the
+/// caller should search exhaustively. The first call for a large database
+/// starts building the index in the background.
+pub fn candidates(db: &Connection, query: &[f32]) -> Option<Vec<i32>> {
+    let path = usable_path(db)?;
+    let index = with_indexes(|indexes| match indexes.by_path.get(&path) {
+        Some(IndexState::Ready(index)) => Some(Ok(index.clone())),
+        Some(_) => None,
+        None => {
+            indexes.next_generation += 1;
+            Some(Err(indexes.next_generation))
+        }
+    })??;
+
+    let index = match index {
+        Ok(index) => index,
+        Err(generation) => {
+            let count: i64 = db
+                .query_row("SELECT COUNT(*) FROM chunk_embeddings", [], |row| {
+                    row.get(0)
+                })
+                .unwrap_or(0);
+            let state = if count < MIN_CHUNKS {
+                IndexState::TooSmall
+            } else {
+                IndexState::Building(generation)
+            };
+            let building = matches!(state, IndexState::Building(_));
+            with_indexes(|indexes| indexes.by_path.insert(path.clone(), state));
+            if building {
+                std::thread::spawn(move || {
+                    let built = build_index(&path, count);
+                    if let Err(e) = &built {
+                        eprintln!("Warning: failed to build vector index: {}", e);
+                    }
+                    with_indexes(|indexes| {
+                        // Skip the result if the index was invalidated meanwhile
+                        if let Some(IndexState::Building(current)) = indexes.by_path.get(&path) {
+                            if *current == generation {
+                                let state = match built {
+                                    Ok(index) => IndexState::Ready(Arc::new(index)),
+                                    Err(_) => IndexState::Failed,
+                                };
+                                indexes.by_path.insert(path, state);
+                            }
+                        }
+                    });
+                });
+            }
+            return None;
+        }
+    };
+
+    let query = normalize(query.to_vec())?;
+    if query.len() != index.centroids[0].len() {
+        return None;
+    }
+    let probes = (index.centroids.len() / 10).max(8);
+    let mut ranked: Vec<(usize, f32)> = index
+        .centroids
+        .iter()
+        .enumerate()
+        .map(|(i, c)| (i, dot(c, &query)))
+        .collect();
+    ranked.sort_by(|a, b| b.1.partial_cmp(&a.1).unwrap_or(std::cmp::Ordering::Equal));
+    Some(
+        ranked
+            .into_iter()
+            .take(probes)
+            .flat_map(|(i, _)| index.lists[i].iter().copied())
+            .collect(),
+    )
+}
+
+/// Drop any index for the database at `db_path`.
+pub fn invalidate(db_path: &str) {
+    with_indexes(|indexes| indexes.by_path.remove(db_path));
+}
+
+pub fn status(db: &Connection) -> AnnIndexStatus {
+    let Some(path) = usable_path(db) else {
+        return AnnIndexStatus::Inactive;
+    };
+    with_indexes(|indexes| match indexes.by_path.get(&path) {
+        Some(IndexState::Ready(_)) => AnnIndexStatus::Active,
+        Some(IndexState::Building(_)) => AnnIndexStatus::Building,
+        _ => AnnIndexStatus::Inactive,
+    })
+    .unwrap_or(AnnIndexStatus::Inactive)
+}
@@ -0,0 +1,144 @@
+//! Full output of each project build, kept under `logs/builds` in the app data
+//! dir so a failure can be inspected after the short error message is gone.
+//! Files are named `{project_id}-{timestamp}.log` and only the newest few per
+//! project are kept.
+
+use crate::models::BuildLogEntry;
+use std::path::{Path, PathBuf};
+
+/// Logs kept per project; older ones are deleted when a build writes a new one.
+pub const BUILD_LOGS_KEPT: usize = 10;
+
+/// UTC build start, to the millisecond, so names sort chronologically.
+const TIMESTAMP_FORMAT: &str = "%Y%m%d%H%M%S%3f";
+const TIMESTAMP_LEN: usize = 17;
+
+pub fn logs_dir(app_data_dir: &Path) -> PathBuf {
+    app_data_dir.join("logs").join("builds")
+}
+
+/// Write `contents` as a new log for `project_id`, delete all but the newest
+/// `BUILD_LOGS_KEPT`, and return the new log's path.
+pub fn write_build_log(dir: &Path, project_id: &str, contents: &str) -> Result<PathBuf, String> {
+    std::fs::create_dir_all(dir).map_err(|e| e.to_string())?;
+    let timestamp = chrono::Utc::now().format(TIMESTAMP_FORMAT);
+    let path = dir.join(format!("{}-{}.log", project_id, timestamp));
+    std::fs::write(&path, contents).map_err(|e| format!("Failed to write build log: {}", e))?;
+
+    let logs = project_logs(dir, project_id);
+    for old in logs.iter().skip(BUILD_LOGS_KEPT) {
+        let _ = std::fs::remove_file(old);
+    }
+    Ok(path)
+}
+
+/// Logs for `project_id`, newest first.
+pub fn build_logs(dir: &Path, project_id: &str) -> Vec<BuildLogEntry> {
+    project_logs(dir, project_id)
+        .into_iter()
+        .filter_map(|path| {
+            let metadata = std::fs::metadata(&path).ok()?;
+            let modified_at = metadata
+                .modified()
+                .ok()
+                .and_then(|t| t.duration_since(std::time::UNIX_EPOCH).ok())
+                .map(|d| d.as_secs() as i64)
+                .unwrap_or_default();
+            Some(BuildLogEntry {
+                file_name: path.file_name()?.to_string_lossy().to_string(),
+                path: path.to_string_lossy().to_string(),
+                size_bytes: metadata.len(),
+                modified_at,
+            })
+        })
+        .collect()
+}
+
+/// Contents of a log, refusing any path outside `dir`.
+pub fn read_build_log(dir: &Path, path: &str) -> Result<String, String> {
+    let not_a_log = || format!("'{}' is not a build log", path);
+    let dir = dir.canonicalize().map_err(|_| not_a_log())?;
+    let path = Path::new(path).canonicalize().map_err(|_| not_a_log())?;
+    if !path.starts_with(&dir) || path.extension().and_then(|ext| ext.to_str()) != Some("log") {
+        return Err(not_a_log());
+    }
+    std::fs::read_to_string(&path).map_err(|e| e.to_string())
+}
+
+/// Paths of `project_id`'s logs, newest first. The timestamp check keeps
+/// "team" from claiming the logs of "team-docs".
+fn project_logs(dir: &Path, project_id: &str) -> Vec<PathBuf> {
+    let Ok(entries) = std::fs::read_dir(dir) else {
+        return vec![];
+    };
+    let prefix = format!("{}-", project_id);
+    let mut logs: Vec<PathBuf> = entries
+        .filter_map(Result::ok)
+        .map(|entry| entry.path())
+        .filter(|path| {
+            path.file_name()
+                .and_then(|name| name.to_str())
+                .and_then(|name| name.strip_prefix(&prefix)?.strip_suffix(".log"))
+                .is_some_and(|timestamp| {
+                    timestamp.len() == TIMESTAMP_LEN
+                        && timestamp.bytes().all(|b| b.is_ascii_digit())
+                })
+        })
+        .collect();
+    logs.sort();
+    logs.reverse();
+    logs
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{build_logs, read_build_log, BUILD_LOGS_KEPT};
+    use crate::test_support::scratch_dir;
+
+    fn log_name(project_id: &str, n: usize) -> String {
+        format!("{}-20000101000000{:03}.log", project_id, n)
+    }
+
+    #[test]
+    fn rotation_keeps_newest_logs_per_project() {
+        let dir = scratch_dir("build-logs");
+        for n in 0..BUILD_LOGS_KEPT + 3 {
+            std::fs::write(dir.join(log_name("team", n)), "old").unwrap();
+        }
+        std::fs::write(dir.join(log_name("team-docs", 0)), "other").unwrap();
+        std::fs::write(dir.join("team-notes.log"), "unrelated").unwrap();
+
+        let path = super::write_build_log(&dir, "team", "new").unwrap();
+        let logs = build_logs(&dir, "team");
+        assert_eq!(logs.len(), BUILD_LOGS_KEPT);
+        assert_eq!(logs[0].path, path.to_string_lossy());
+        assert_eq!(logs[1].file_name, log_name("team", BUILD_LOGS_KEPT + 2));
+        assert!(!dir.join(log_name("team", 3)).exists());
+        assert_eq!(build_logs(&dir, "team-docs").len(), 1);
+        assert!(dir.join("team-notes.log").exists());
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn reads_only_logs_inside_the_directory() {
+        let root = scratch_dir("build-log-read");
+        let dir = root.join("logs");
+        let path = super::write_build_log(&dir, "team", "stdout\nstderr").unwrap();
+        assert_eq!(
+            read_build_log(&dir, &path.to_string_lossy()).unwrap(),
+            "stdout\nstderr"
+        );
+
+        std::fs::write(root.join("secret.log"), "no").unwrap();
+        std::fs::write(dir.join("notes.txt"), "no").unwrap();
+        for outside in [
+            root.join("secret.log"),
+            dir.join("..").join("secret.log"),
+            dir.join("notes.txt"),
+            dir.join("missing.log"),
+        ] {
+            assert!(read_build_log(&dir, &outside.to_string_lossy()).is_err());
+        }
+        std::fs::remove_dir_all(&root).unwrap();
+    }
+}
@@ -0,0 +1,160 @@
+//! Serialises project builds so only one runs at a time. Builds run in the
+//! order they were requested; each waits for the build slot, and queued builds
+//! can be cancelled before they start.
+//!
+//! Every change to the queue emits `build-queue-updated` with the full queue,
+//! and a newly queued build emits `project-build-queued` with its position.
+
+use crate::models::QueuedBuild;
+use std::sync::Mutex;
+use tauri::AppHandle;
+use tokio::sync::oneshot;
+
+struct Entry {
+    build: QueuedBuild,
+    cancel: Option<oneshot::Sender<()>>,
+}
+
+#[derive(Default)]
+struct QueueState {
+    next_id: u64,
+    entries: Vec<Entry>,
+}
+
+#[derive(Default)]
+pub struct BuildQueue {
+    state: Mutex<QueueState>,
+    /// Held by the running build. tokio's mutex is fair, so waiters acquire it
+    /// in the order they queued.
+    slot: tokio::sync::Mutex<()>,
+}
+
+/// Held for the duration of a build; leaves the queue when dropped.
+pub struct BuildTurn<'a> {
+    app: AppHandle,
+    queue: &'a BuildQueue,
+    id: u64,
+    _slot: tokio::sync::MutexGuard<'a, ()>,
+}
+
+impl BuildQueue {
+    /// Snapshot of the queue with positions, running build first.
+    pub fn snapshot(&self) -> Vec<QueuedBuild> {
+        let Ok(state) = self.state.lock() else {
+            return vec![];
+        };
+        state
+            .entries
+            .iter()
+            .enumerate()
+            .map(|(i, entry)| QueuedBuild {
+                position: i,
+                ..entry.build.clone()
+            })
+            .collect()
+    }
+
+    /// Cancel a build that hasn't started yet.
+    pub fn cancel(&self, app: &AppHandle, id: u64) -> Result<(), String> {
+        {
+            let mut state = self.state.lock().map_err(|e| e.to_string())?;
+            let index = state
+                .entries
+                .iter()
+                .position(|e| e.build.id == id)
+                .ok_or_else(|| format!("Build {} is not queued", id))?;
+            if state.entries[index].build.running {
+                return Err("Build is already running and can't be cancelled".to_string());
+            }
+            let entry = state.entries.remove(index);
+            if let Some(cancel) = entry.cancel {
+                let _ = cancel.send(());
+            }
+        }
+        self.emit_updated(app);
+        Ok(())
+    }
+
+    /// Queue a build and wait for its turn. Returns an error if the build was
+    /// cancelled while waiting.
+    pub async fn wait_for_turn<'a>(
+        &'a self,
+        app: &AppHandle,
+        project_id: &str,
+        kind: &str,
+    ) -> Result<BuildTurn<'a>, String> {
+        let (cancel_tx, cancel_rx) = oneshot::channel();
+        let id = {
+            let mut state = self.state.lock().map_err(|e| e.to_string())?;
+            state.next_id += 1;
+            let id = state.next_id;
+            state.entries.push(Entry {
+                build: QueuedBuild {
+                    id,
+                    project_id: project_id.to_string(),
+                    kind: kind.to_string(),
+                    position: 0,
+                    running: false,
+                    enqueued_at: chrono::Utc::now().timestamp(),
+                },
+                cancel: Some(cancel_tx),
+            });
+            id
+        };
+
+        let position = self
+            .snapshot()
+            .iter()
+            .find(|b| b.id == id)
+            .map(|b| b.position)
+            .unwrap_or_default();
+        crate::events::emit(
+            app,
+            "project-build-queued",
+            serde_json::json!({ "projectId": project_id, "buildId": id, "queuePosition": position }),
+        );
+        self.emit_updated(app);
+
+        let slot = tokio::select! {
+            slot = self.slot.lock() => slot,
+            _ = cancel_rx => return Err("Build cancelled".to_string()),
+        };
+
+        {
+            let mut state = self.state.lock().map_err(|e| e.to_string())?;
+            match state.entries.iter_mut().find(|e| e.build.id == id) {
+                Some(entry) => {
+                    entry.build.running = true;
+                    entry.cancel = None;
+                }
+                // Cancelled just as the slot came free
+                None => return Err("Build cancelled".to_string()),
+            }
+        }
+        self.emit_updated(app);
+
+        Ok(BuildTurn {
+            app: app.clone(),
+            queue: self,
+            id,
+            _slot: slot,
+        })
+    }
+
+    fn emit_updated(&self, app: &AppHandle) {
+        crate::events::emit(
+            app,
+            "build-queue-updated",
+            serde_json::json!(self.snapshot()),
+        );
+    }
+}
+
+impl Drop for BuildTurn<'_> {
+    fn drop(&mut self) {
+        if let Ok(mut state) = self.queue.state.lock() {
+            state.entries.retain(|e| e.build.id != self.id);
+        }
+        self.queue.emit_updated(&self.app);
+    }
+}
@@ -0,0 +1,817 @@
+//! Runs the Node build pipeline (scripts/build-handbook.ts) that turns a
+//! Markdown source folder into a project database. Shared by the GUI commands
+//! and the CLI, so it has no AppHandle dependency and emits no events.
+
+use crate::models::{BuildEnvironmentReport, BuildSummary, ToolCandidate};
+use std::path::{Path, PathBuf};
+use std::process::{Command, Stdio};
+use std::sync::OnceLock;
+use std::time::{Duration, Instant};
+
+/// How long the login shell gets to report where node and npm are.
+const LOGIN_SHELL_TIMEOUT: Duration = Duration::from_secs(3);
+
+/// The only variables the build script and the better-sqlite3 rebuild
+/// inherit. Both run the checkout's node_modules code, so secrets elsewhere in
+/// the user's environment stay out of them; PATH and OPENAI_API_KEY are set
+/// separately.
+const BUILD_ENV_ALLOWLIST: &[&str] = &[
+    "HOME",
+    "USER",
+    "LOGNAME",
+    "LANG",
+    "LC_ALL",
+    "LC_CTYPE",
+    "TMPDIR",
+    "TZ",
+    // Proxies and certificates npm needs behind a corporate network
+    "HTTP_PROXY",
+    "HTTPS_PROXY",
+    "ALL_PROXY",
+    "NO_PROXY",
+    "http_proxy",
+    "https_proxy",
+    "all_proxy",
+    "no_proxy",
+    "NODE_EXTRA_CA_CERTS",
+    "SSL_CERT_FILE",
+    "SSL_CERT_DIR",
+    // Windows needs these to start node and resolve user folders
+    "SYSTEMROOT",
+    "WINDIR",
+    "COMSPEC",
+    "PATHEXT",
+    "USERPROFILE",
+    "APPDATA",
+    "LOCALAPPDATA",
+    "TEMP",
+    "TMP",
+];
+
+pub struct BuildRequest<'a> {
+    pub source_path: &'a str,
+    pub db_path: &'a Path,
+    pub collection_id: &'a str,
+    pub collection_name: &'a str,
+    pub collection_icon: &'a str,
+    pub openai_api_key: Option<&'a str>,
+    /// The project wants chunk embeddings; the API key reaches the script
+    /// only when this is set
+    pub embeddings: bool,
+    /// User-configured node executable, tried before any discovery
+    pub node_binary_path: Option<&'a str>,
+    /// User-configured npm executable or npm-cli.js, tried first when
+    /// rebuilding better-sqlite3
+    pub npm_binary_path: Option<&'a str>,
+}
+
+#[derive(Debug)]
+struct BuildCommandResult {
+    success: bool,
+    stderr: String,
+}
+
+/// Lines of stderr kept in a build error; the build log has the rest.
+const ERROR_TAIL_LINES: usize = 20;
+
+/// Append a command's output to a build transcript.
+fn record_output(transcript: &mut String, label: &str, output: &std::process::Output) {
+    transcript.push_str(&format!("$ {} ({})\n", label, output.status));
+    for (stream, bytes) in [("stdout", &output.stdout), ("stderr", &output.stderr)] {
+        let text = String::from_utf8_lossy(bytes);
+        if !text.trim().is_empty() {
+            transcript.push_str(&format!("--- {} ---\n{}\n", stream, text.trim_end()));
+        }
+    }
+    transcript.push('\n');
+}
+
+/// The operating system family, which decides executable names and where
+/// Node installers put them.
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Platform {
+    MacOs,
+    Windows,
+    /// Linux and the BSDs
+    OtherUnix,
+}
+
+impl Platform {
+    fn current() -> Self {
+        if cfg!(target_os = "macos") {
+            Platform::MacOs
+        } else if cfg!(windows) {
+            Platform::Windows
+        } else {
+            Platform::OtherUnix
+        }
+    }
+
+    fn node_name(self) -> &'static str {
+        match self {
+            Platform::Windows => "node.exe",
+            _ => "node",
+        }
+    }
+
+    /// npm is a .cmd shim on Windows
+    fn npm_name(self) -> &'static str {
+        match self {
+            Platform::Windows => "npm.cmd",
+            _ => "npm",
+        }
+    }
+
+    /// Directories Node installers commonly use, which a GUI app's PATH may
+    /// lack. `env` looks up environment variables.
+    fn install_dirs(self, env: &dyn Fn(&str) -> Option<String>) -> Vec<PathBuf> {
+        match self {
+            Platform::MacOs => ["/opt/homebrew/bin", "/usr/local/bin", "/usr/bin"]
+                .into_iter()
+                .map(PathBuf::from)
+                .collect(),
+            Platform::OtherUnix => ["/usr/local/bin", "/usr/bin"]
+                .into_iter()
+                .map(PathBuf::from)
+                .collect(),
+            Platform::Windows => [
+                ("NVM_SYMLINK", None),
+                ("ProgramFiles", Some("nodejs")),
+                ("ProgramFiles(x86)", Some("nodejs")),
+                ("APPDATA", Some("npm")),
+            ]
+            .into_iter()
+            .filter_map(|(var, subdir)| {
+                let base = PathBuf::from(env(var).filter(|v| !v.is_empty())?);
+                Some(match subdir {
+                    Some(subdir) => base.join(subdir),
+                    None => base,
+                })
+            })
+            .collect(),
+        }
+    }
+
+    /// Where npm's own CLI script sits for a Node installed in `install_dir`.
+    fn npm_cli_in(self, install_dir: &Path) -> PathBuf {
+        let modules = match self {
+            Platform::Windows => install_dir.join("node_modules"),
+            _ => install_dir
+                .parent()
+                .unwrap_or(install_dir)
+                .join("lib/node_modules"),
+        };
+        modules.join("npm/bin/npm-cli.js")
+    }
+}
+
+fn env_var(name: &str) -> Option<String> {
+    std::env::var(name).ok()
+}
+
+/// Where node and npm are on the PATH of the user's login shell, which sees
+/// version managers such as nvm and volta that a GUI app's PATH does not.
+/// The shell is only asked once per process, and never on Windows.
+fn login_shell_tools() -> &'static (Option<String>, Option<String>) {
+    static TOOLS: OnceLock<(Option<String>, Option<String>)> = OnceLock::new();
+    TOOLS.get_or_init(|| {
+        if Platform::current() == Platform::Windows {
+            return (None, None);
+        }
+        let shell = std::env::var("SHELL")
+            .ok()
+            .filter(|s| !s.trim().is_empty())
+            .unwrap_or_else(|| "sh".to_string());
+        let script = r#"printf '%s\n' "$(command -v node)" "$(command -v npm)""#;
+        let Some(stdout) = output_with_timeout(
+            Command::new(shell).args(["-lc", script]),
+            LOGIN_SHELL_TIMEOUT,
+        ) else {
+            return (None, None);
+        };
+        let mut lines = stdout.lines().map(|line| {
+            let line = line.trim();
+            // `command -v` prints a bare name for aliases and functions
+            line.starts_with('/').then(|| line.to_string())
+        });
+        (lines.next().flatten(), lines.next().flatten())
+    })
+}
+
+/// Stdout of a successful run of `command`, or None if it fails or is still
+/// running after `timeout`, in which case it is killed.
+fn output_with_timeout(command: &mut Command, timeout: Duration) -> Option<String> {
+    let mut child = command
+        .stdin(Stdio::null())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::null())
+        .spawn()
+        .ok()?;
+    let deadline = Instant::now() + timeout;
+    loop {
+        match child.try_wait() {
+            Ok(Some(status)) if status.success() => break,
+            Ok(Some(_)) | Err(_) => return None,
+            Ok(None) if Instant::now() >= deadline => {
+                let _ = child.kill();
+                let _ = child.wait();
+                return None;
+            }
+            Ok(None) => std::thread::sleep(Duration::from_millis(20)),
+        }
+    }
+    let mut stdout = String::new();
+    std::io::Read::read_to_string(&mut child.stdout.take()?, &mut stdout).ok()?;
+    Some(stdout)
+}
+
+/// Node executables to try, in order: the configured path, PATH, the login
+/// shell's PATH, then the platform's common install locations.
+fn node_candidates(configured: Option<&str>) -> Vec<String> {
+    let platform = Platform::current();
+    let mut candidates: Vec<String> = configured
+        .map(str::trim)
+        .filter(|path| !path.is_empty())
+        .map(str::to_string)
+        .into_iter()
+        .collect();
+    candidates.push(platform.node_name().to_string());
+    if let Some(node) = &login_shell_tools().0 {
+        candidates.push(node.clone());
+    }
+    for dir in platform.install_dirs(&env_var) {
+        candidates.push(dir.join(platform.node_name()).to_string_lossy().to_string());
+    }
+    let mut seen = std::collections::HashSet::new();
+    candidates.retain(|candidate| seen.insert(candidate.clone()));
+    candidates
+}
+
+/// Trimmed output of `<program> --version`, if it runs successfully.
+fn tool_version(program: &str, args: &[String]) -> Option<String> {
+    Command::new(program)
+        .args(args)
+        .arg("--version")
+        .output()
+        .ok()
+        .filter(|out| out.status.success())
+        .map(|out| String::from_utf8_lossy(&out.stdout).trim().to_string())
+}
+
+fn resolve_node_binary(configured: Option<&str>) -> Option<String> {
+    node_candidates(configured)
+        .into_iter()
+        .find(|candidate| tool_version(candidate, &[]).is_some())
+}
+
+/// Checkout locations that may contain the build scripts, before any
+/// app-specific fallbacks such as the resource directory.
+pub fn project_root_candidates() -> Vec<PathBuf> {
+    let mut candidates = Vec::new();
+
+    if let Ok(cwd) = std::env::current_dir() {
+        // Dev mode: command is often run from repo root.
+        candidates.push(cwd.clone());
+
+        // Dev mode: command can also run from src-tauri/.
+        if cwd.ends_with("src-tauri") {
+            let mut parent = cwd.clone();
+            parent.pop();
+            candidates.push(parent);
+        }
+    }
+
+    // Build-time repo path (useful when packaged app still runs on build host).
+    if let Some(parent) = PathBuf::from(env!("CARGO_MANIFEST_DIR"))
+        .parent()
+        .map(|p| p.to_path_buf())
+    {
+        candidates.push(parent);
+    }
+
+    candidates
+}
+
+pub fn find_project_root(candidates: Vec<PathBuf>) -> Result<PathBuf, String> {
+    for candidate in candidates {
+        if candidate.join("scripts/build-handbook.ts").exists() {
+            return Ok(candidate);
+        }
+    }
+
+    Err("Could not locate project build scripts. Reinstall the app or run from a development checkout."
+        .to_string())
+}
+
+fn normalise_build_error(stderr: &str) -> String {
+    let lines: Vec<&str> = stderr.trim().lines().collect();
+    if lines.is_empty() {
+        "Unknown build failure".to_string()
+    } else if lines.len() > ERROR_TAIL_LINES {
+        format!("…\n{}", lines[lines.len() - ERROR_TAIL_LINES..].join("\n"))
+    } else {
+        lines.join("\n")
+    }
+}
+
+fn is_better_sqlite3_abi_mismatch(stderr: &str) -> bool {
+    let lower = stderr.to_ascii_lowercase();
+    (lower.contains("node_module_version") || lower.contains("err_dlopen_failed"))
+        && lower.contains("better_sqlite3")
+}
+
+fn execute_project_build_command(
+    node_bin: &str,
+    project_root: &Path,
+    tsx_cli_path: &Path,
+    script_path: &Path,
+    request: &BuildRequest<'_>,
+    transcript: &mut String,
+) -> Result<BuildCommandResult, String> {
+    let mut build_command = Command::new(node_bin);
+    build_command
+        .args([
+            tsx_cli_path.to_str().ok_or("Invalid tsx CLI path")?,
+            script_path.to_str().ok_or("Invalid script path")?,
+            "--source",
+            request.source_path,
+            "--output",
+            request.db_path.to_str().ok_or("Invalid DB path")?,
+            "--collection-id",
+            request.collection_id,
+            "--collection-name",
+            request.collection_name,
+            "--collection-icon",
+            request.collection_icon,
+        ])
+        .current_dir(project_root)
+        .env_clear()
+        .envs(build_env(request, build_node_path_env(node_bin), &|name| {
+            std::env::var_os(name)
+        }));
+
+    let output = build_command
+        .output()
+        .map_err(|e| format!("Failed to spawn build process: {}", e))?;
+    record_output(transcript, "build-handbook.ts", &output);
+
+    let stderr = String::from_utf8_lossy(&output.stderr).to_string();
+    Ok(BuildCommandResult {
+        success: output.status.success(),
+        stderr,
+    })
+}
+
+/// The complete environment for npm: the allowlisted variables from
+/// `parent` and `path` as PATH.
+fn tool_env(
+    path: std::ffi::OsString,
+    parent: &dyn Fn(&str) -> Option<std::ffi::OsString>,
+) -> std::collections::BTreeMap<String, std::ffi::OsString> {
+    let mut env: std::collections::BTreeMap<String, std::ffi::OsString> = BUILD_ENV_ALLOWLIST
+        .iter()
+        .filter_map(|name| Some((name.to_string(), parent(name)?)))
+        .collect();
+    env.insert("PATH".to_string(), path);
+    env
+}
+
+/// The complete environment for the build script: `tool_env`, plus the
+/// OpenAI key when the build embeds.
+fn build_env(
+    request: &BuildRequest<'_>,
+    path: std::ffi::OsString,
+    parent: &dyn Fn(&str) -> Option<std::ffi::OsString>,
+) -> std::collections::BTreeMap<String, std::ffi::OsString> {
+    let mut env = tool_env(path, parent);
+    if let Some(api_key) = request
+        .openai_api_key
+        .filter(|key| request.embeddings && !key.trim().is_empty())
+    {
+        env.insert("OPENAI_API_KEY".to_string(), api_key.into());
+    }
+    env
+}
+
+fn resolve_npm_cli_with_node(node_bin: &str) -> Option<String> {
+    let script = "const r = require.resolve('npm/bin/npm-cli.js'); console.log(r);";
+    Command::new(node_bin)
+        .args(["-e", script])
+        .output()
+        .ok()
+        .and_then(|out| {
+            if !out.status.success() {
+                return None;
+            }
+            let path = String::from_utf8_lossy(&out.stdout).trim().to_string();
+            if path.is_empty() {
+                None
+            } else {
+                Some(path)
+            }
+        })
+}
+
+fn build_node_path_env(node_bin: &str) -> std::ffi::OsString {
+    node_path_env(
+        Platform::current(),
+        Path::new(node_bin),
+        &env_var,
+        std::env::var_os("PATH"),
+    )
+}
+
+/// PATH for npm and the scripts it runs: the node executable's directory and
+/// the platform's install locations ahead of the existing PATH.
+fn node_path_env(
+    platform: Platform,
+    node_bin: &Path,
+    env: &dyn Fn(&str) -> Option<String>,
+    existing: Option<std::ffi::OsString>,
+) -> std::ffi::OsString {
+    let mut parts: Vec<PathBuf> = Vec::new();
+    if let Some(parent) = node_bin.parent().filter(|p| !p.as_os_str().is_empty()) {
+        parts.push(parent.to_path_buf());
+    }
+    parts.extend(platform.install_dirs(env));
+    if platform != Platform::Windows {
+        parts.push(PathBuf::from("/bin"));
+    }
+    if let Some(existing) = existing {
+        parts.extend(std::env::split_paths(&existing));
+    }
+    // An entry containing the separator cannot be joined, so leave it out
+    parts.retain(|part| std::env::join_paths([part]).is_ok());
+    std::env::join_paths(parts).unwrap_or_default()
+}
+
+/// Ways to run npm, as a program plus leading arguments, in the order they
+/// should be tried.
+fn npm_invocations(node_bin: &str, configured: Option<&str>) -> Vec<(String, Vec<String>)> {
+    let platform = Platform::current();
+    let mut invocations: Vec<(String, Vec<String>)> = Vec::new();
+
+    // A configured npm-cli.js runs through node; anything else is run directly.
+    if let Some(npm) = configured.map(str::trim).filter(|path| !path.is_empty()) {
+        if npm.ends_with(".js") {
+            invocations.push((node_bin.to_string(), vec![npm.to_string()]));
+        } else {
+            invocations.push((npm.to_string(), vec![]));
+        }
+    }
+
+    // Best option in packaged environments: run npm CLI through resolved Node.
+    if let Some(npm_cli) = resolve_npm_cli_with_node(node_bin) {
+        invocations.push((node_bin.to_string(), vec![npm_cli]));
+    }
+
+    // Explicit npm CLI locations commonly used by Node installs.
+    let install_dirs = platform.install_dirs(&env_var);
+    for dir in &install_dirs {
+        let npm_cli = platform.npm_cli_in(dir);
+        if npm_cli.exists() {
+            invocations.push((
+                node_bin.to_string(),
+                vec![npm_cli.to_string_lossy().to_string()],
+            ));
+        }
+    }
+
+    // Fallback: sibling npm next to the node executable.
+    if let Some(parent) = Path::new(node_bin).parent() {
+        let sibling_npm = parent.join(platform.npm_name());
+        invocations.push((sibling_npm.to_string_lossy().to_string(), vec![]));
+    }
+
+    // Last resort PATH, the login shell's PATH and common locations.
+    invocations.push((platform.npm_name().to_string(), vec![]));
+    if let Some(npm) = &login_shell_tools().1 {
+        invocations.push((npm.clone(), vec![]));
+    }
+    for dir in &install_dirs {
+        invocations.push((
+            dir.join(platform.npm_name()).to_string_lossy().to_string(),
+            vec![],
+        ));
+    }
+
+    let mut seen = std::collections::HashSet::new();
+    invocations.retain(|invocation| seen.insert(invocation.clone()));
+    invocations
+}
+
+fn rebuild_better_sqlite3(
+    node_bin: &str,
+    npm_binary_path: Option<&str>,
+    project_root: &Path,
+    transcript: &mut String,
+) -> Result<(), String> {
+    let env = tool_env(build_node_path_env(node_bin), &|name| {
+        std::env::var_os(name)
+    });
+    let attempts = npm_invocations(node_bin, npm_binary_path)
+        .into_iter()
+        .map(|(cmd, mut args)| {
+            args.extend(["rebuild".to_string(), "better-sqlite3".to_string()]);
+            (cmd, args)
+        });
+
+    let mut errors = Vec::new();
+    for (cmd, args) in attempts {
+        // npm is a .cmd shim on Windows, which std runs through cmd.exe
+        // with its arguments escaped for it
+        let output = Command::new(&cmd)
+            .args(&args)
+            .env_clear()
+            .envs(&env)
+            .current_dir(project_root)
+            .output();
+
+        let label = format!("{} {}", cmd, args.join(" "));
+        match output {
+            Ok(out) if out.status.success() => {
+                record_output(transcript, &label, &out);
+                return Ok(());
+            }
+            Ok(out) => {
+                record_output(transcript, &label, &out);
+                let stderr = String::from_utf8_lossy(&out.stderr).to_string();
+                errors.push(format!(
+                    "attempt '{}': {}",
+                    cmd,
+                    normalise_build_error(&stderr)
+                ));
+            }
+            Err(e) => {
+                transcript.push_str(&format!("$ {} (failed to start: {})\n\n", label, e));
+                errors.push(format!("attempt '{}': {}", cmd, e));
+            }
+        }
+    }
+
+    Err(format!(
+        "Could not rebuild better-sqlite3 automatically. {}",
+        errors.join(" | ")
+    ))
+}
+
+/// Run the build to completion, rebuilding better-sqlite3 and retrying once
+/// if Node reports an ABI mismatch. Blocks the calling thread. The full output
+/// of every command run is appended to `transcript`.
+pub fn run_build(
+    project_root: &Path,
+    request: &BuildRequest<'_>,
+    transcript: &mut String,
+) -> Result<(), String> {
+    let script_path = project_root.join("scripts/build-handbook.ts");
+    let tsx_cli_path = project_root.join("node_modules/tsx/dist/cli.mjs");
+    let node_bin = resolve_node_binary(request.node_binary_path).ok_or(
+        "Node.js executable not found. Install Node.js (v20+) or set its path in preferences to enable project imports.",
+    )?;
+
+    if !tsx_cli_path.exists() {
+        return Err(
+            "Missing local tsx runtime at node_modules/tsx/dist/cli.mjs. Run `npm install` in the project checkout."
+                .to_string(),
+        );
+    }
+
+    let first = execute_project_build_command(
+        &node_bin,
+        project_root,
+        &tsx_cli_path,
+        &script_path,
+        request,
+        transcript,
+    )?;
+
+    if first.success {
+        return Ok(());
+    }
+
+    if is_better_sqlite3_abi_mismatch(&first.stderr) {
+        rebuild_better_sqlite3(&node_bin, request.npm_binary_path, project_root, transcript)?;
+        let retry = execute_project_build_command(
+            &node_bin,
+            project_root,
+            &tsx_cli_path,
+            &script_path,
+            request,
+            transcript,
+        )?;
+
+        if retry.success {
+            return Ok(());
+        }
+
+        return Err(format!(
+            "Build failed after rebuilding better-sqlite3: {}",
+            normalise_build_error(&retry.stderr)
+        ));
+    }
+
+    Err(format!(
+        "Build failed: {}",
+        normalise_build_error(&first.stderr)
+    ))
+}
+
+/// Summarise a database the Node script just built. The script always
+/// rebuilds every document, so nothing is reported as reused.
+pub fn build_summary(db_path: &Path) -> Result<BuildSummary, String> {
+    let conn = crate::db::open_project_db(db_path)?;
+    let stats = crate::queries::project_stats(&conn, 0);
+    Ok(BuildSummary {
+        documents_rebuilt: stats.document_count as i64,
+        documents_reused: 0,
+        chunks_embedded: stats.embedding_count as i64,
+    })
+}
+
+/// What the Node build pipeline would find on this machine: each node and npm
+/// candidate with the version it reported, and whether the project checkout
+/// has its dependencies installed.
+pub fn diagnose_environment(
+    project_root: Result<PathBuf, String>,
+    node_binary_path: Option<&str>,
+    npm_binary_path: Option<&str>,
+) -> BuildEnvironmentReport {
+    let node_candidates: Vec<ToolCandidate> = node_candidates(node_binary_path)
+        .into_iter()
+        .map(|path| ToolCandidate {
+            version: tool_version(&path, &[]),
+            path,
+        })
+        .collect();
+    let node = node_candidates
+        .iter()
+        .find(|c| c.version.is_some())
+        .cloned();
+
+    let npm_candidates: Vec<ToolCandidate> = match &node {
+        Some(node) => npm_invocations(&node.path, npm_binary_path)
+            .into_iter()
+            .map(|(cmd, args)| ToolCandidate {
+                version: tool_version(&cmd, &args),
+                path: args.last().cloned().unwrap_or(cmd),
+            })
+            .collect(),
+        None => vec![],
+    };
+    let npm = npm_candidates.iter().find(|c| c.version.is_some()).cloned();
+
+    let (project_root, project_root_error) = match project_root {
+        Ok(root) => (Some(root), None),
+        Err(e) => (None, Some(e)),
+    };
+    let has = |relative: &str| {
+        project_root
+            .as_ref()
+            .is_some_and(|root| root.join(relative).exists())
+    };
+    BuildEnvironmentReport {
+        login_shell_node: login_shell_tools().0.clone(),
+        login_shell_npm: login_shell_tools().1.clone(),
+        tsx_present: has("node_modules/tsx/dist/cli.mjs"),
+        better_sqlite3_present: has("node_modules/better-sqlite3/package.json"),
+        project_root: project_root.map(|root| root.to_string_lossy().to_string()),
+        project_root_error,
+        node_candidates,
+        node,
+        npm_candidates,
+        npm,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{build_env, node_path_env, normalise_build_error, BuildRequest, Platform};
+    use std::ffi::OsString;
+    use std::path::{Path, PathBuf};
+
+    fn no_env(_: &str) -> Option<String> {
+        None
+    }
+
+    fn split(path: std::ffi::OsString) -> Vec<PathBuf> {
+        std::env::split_paths(&path).collect()
+    }
+
+    #[test]
+    fn macos_path_keeps_homebrew_locations_first() {
+        let existing = std::env::join_paths(["/Users/me/.volta/bin", "/usr/bin"]).unwrap();
+        let path = node_path_env(
+            Platform::MacOs,
+            Path::new("/Users/me/.nvm/versions/node/v20.11.0/bin/node"),
+            &no_env,
+            Some(existing),
+        );
+        let expected: Vec<PathBuf> = [
+            "/Users/me/.nvm/versions/node/v20.11.0/bin",
+            "/opt/homebrew/bin",
+            "/usr/local/bin",
+            "/usr/bin",
+            "/bin",
+            "/Users/me/.volta/bin",
+            "/usr/bin",
+        ]
+        .into_iter()
+        .map(PathBuf::from)
+        .collect();
+        assert_eq!(split(path), expected);
+        assert_eq!(
+            Platform::MacOs.npm_cli_in(Path::new("/opt/homebrew/bin")),
+            PathBuf::from("/opt/homebrew/lib/node_modules/npm/bin/npm-cli.js")
+        );
+    }
+
+    #[test]
+    fn linux_path_skips_bare_node_and_unjoinable_entries() {
+        let path = node_path_env(Platform::OtherUnix, Path::new("node"), &no_env, None);
+        let expected: Vec<PathBuf> = ["/usr/local/bin", "/usr/bin", "/bin"]
+            .into_iter()
+            .map(PathBuf::from)
+            .collect();
+        assert_eq!(split(path), expected);
+
+        let separator = if cfg!(windows) { ";" } else { ":" };
+        let odd_node = format!("/opt/odd{}dir/node", separator);
+        let path = node_path_env(Platform::OtherUnix, Path::new(&odd_node), &no_env, None);
+        assert_eq!(split(path), expected);
+    }
+
+    #[test]
+    fn windows_locations_come_from_the_environment() {
+        let env = |name: &str| match name {
+            "ProgramFiles" => Some(r"C:\Program Files".to_string()),
+            "APPDATA" => Some(r"C:\Users\me\AppData\Roaming".to_string()),
+            "NVM_SYMLINK" => Some(String::new()),
+            _ => None,
+        };
+        let dirs = Platform::Windows.install_dirs(&env);
+        assert_eq!(
+            dirs,
+            vec![
+                PathBuf::from(r"C:\Program Files").join("nodejs"),
+                PathBuf::from(r"C:\Users\me\AppData\Roaming").join("npm"),
+            ]
+        );
+        assert_eq!(
+            Platform::Windows.npm_cli_in(&dirs[0]),
+            dirs[0].join("node_modules").join("npm/bin/npm-cli.js")
+        );
+        assert_eq!(Platform::Windows.node_name(), "node.exe");
+        assert_eq!(Platform::Windows.npm_name(), "npm.cmd");
+    }
+
+    #[test]
+    fn build_env_holds_only_allowlisted_variables() {
+        let parent = |name: &str| match name {
+            "HOME"
+            | "https_proxy"
+            | "AWS_SECRET_ACCESS_KEY"
+            | "GITHUB_TOKEN"
+            | "OPENAI_API_KEY" => Some(OsString::from(format!("parent {}", name))),
+            _ => None,
+        };
+        let mut request = BuildRequest {
+            source_path: "/docs",
+            db_path: Path::new("/tmp/docs.db"),
+            collection_id: "docs",
+            collection_name: "Docs",
+            collection_icon: "book",
+            openai_api_key: Some("sk-project"),
+            embeddings: false,
+            node_binary_path: None,
+            npm_binary_path: None,
+        };
+        let keys = |request: &BuildRequest<'_>| {
+            build_env(request, OsString::from("/usr/bin"), &parent)
+                .into_keys()
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(keys(&request), ["HOME", "PATH", "https_proxy"]);
+
+        request.embeddings = true;
+        let env = build_env(&request, OsString::from("/usr/bin"), &parent);
+        assert_eq!(
+            env.keys().collect::<Vec<_>>(),
+            ["HOME", "OPENAI_API_KEY", "PATH", "https_proxy"]
+        );
+        assert_eq!(env["OPENAI_API_KEY"], "sk-project");
+        assert_eq!(env["PATH"], "/usr/bin");
+
+        request.openai_api_key = Some("  ");
+        assert_eq!(keys(&request), ["HOME", "PATH", "https_proxy"]);
+    }
+
+    #[test]
+    fn build_errors_keep_the_stderr_tail() {
+        assert_eq!(normalise_build_error("  \n"), "Unknown build failure");
+        assert_eq!(normalise_build_error("\nError: boom\n"), "Error: boom");
+        let stderr: String = (1..=25).map(|n| format!("line {}\n", n)).collect();
+        let error = normalise_build_error(&stderr);
+        assert!(error.starts_with("…\nline 6\n"));
+        assert!(error.ends_with("line 25"));
+    }
+}
@@ -0,0 +1,194 @@
+//! Headless command line, invoked as `dalil --cli <command>`, for CI jobs that
+//! rebuild project databases and smoke-test search without opening a window.
+//!
+//! Commands:
+//!   build  --source <dir> --output <db> [--collection-id <id>]
+//!          [--collection-name <name>] [--collection-icon <icon>] [--node] [--force]
+//!   search --db <db> --query <text> [--collection <id>] [--limit <n>]
+//!   stats  --db <db>
+//!
+//! `build` uses the built-in builder unless `--node` selects the Node script.
+//! Either way, chunks are embedded when OPENAI_API_KEY is set. When the output
+//! database already exists, the built-in builder reuses chunks and embeddings
+//! for unchanged files; `--force` rebuilds everything.
+//!
+//! Results are printed to stdout as JSON; errors go to stderr with a non-zero
+//! exit code.
+
+use crate::builder::{self, BuildRequest};
+use crate::db::open_project_db;
+use crate::native_builder::{self, NativeBuildRequest};
+use crate::queries;
+use std::path::{Path, PathBuf};
+
+const USAGE: &str = "Usage:
+  dalil --cli build --source <dir> --output <db> [--collection-id <id>] [--collection-name <name>] [--collection-icon <icon>] [--node] [--force]
+  dalil --cli search --db <db> --query <text> [--collection <id>] [--limit <n>]
+  dalil --cli stats --db <db>";
+
+const DEFAULT_SEARCH_LIMIT: i32 = 20;
+const DEFAULT_COLLECTION_ICON: &str = "document";
+
+/// Exit code for invalid arguments, distinct from command failures.
+const EXIT_USAGE: i32 = 2;
+
+fn flag_value<'a>(args: &'a [String], flag: &str) -> Option<&'a str> {
+    args.iter()
+        .position(|a| a == flag)
+        .and_then(|i| args.get(i + 1))
+        .map(String::as_str)
+}
+
+fn required_flag<'a>(args: &'a [String], flag: &str) -> Result<&'a str, String> {
+    flag_value(args, flag).ok_or_else(|| format!("Missing required argument {}", flag))
+}
+
+fn print_json<T: serde::Serialize>(value: &T) -> Result<(), String> {
+    let json = serde_json::to_string_pretty(value).map_err(|e| e.to_string())?;
+    println!("{}", json);
+    Ok(())
+}
+
+fn db_size_bytes(path: &Path) -> u64 {
+    std::fs::metadata(path).map(|m| m.len()).unwrap_or(0)
+}
+
+fn build(args: &[String]) -> Result<(), String> {
+    let source = required_flag(args, "--source")?;
+    let output = PathBuf::from(required_flag(args, "--output")?);
+
+    let folder_name = Path::new(source)
+        .file_name()
+        .map(|n| n.to_string_lossy().to_string())
+        .unwrap_or_else(|| "project".to_string());
+    let default_id = crate::projects::project_id_from_name(&folder_name);
+    let collection_id = flag_value(args, "--collection-id").unwrap_or(&default_id);
+    let collection_name = flag_value(args, "--collection-name").unwrap_or(&folder_name);
+    let collection_icon = flag_value(args, "--collection-icon").unwrap_or(DEFAULT_COLLECTION_ICON);
+
+    if let Some(parent) = output.parent().filter(|p| !p.as_os_str().is_empty()) {
+        std::fs::create_dir_all(parent).map_err(|e| e.to_string())?;
+    }
+
+    let has_flag = |flag: &str| args.iter().any(|a| a == flag);
+    let summary = if has_flag("--node") {
+        let project_root = builder::find_project_root(builder::project_root_candidates())?;
+        let preferences = crate::headless::load_preferences();
+        // The script does not inherit the environment, so the key is handed on
+        let openai_api_key = std::env::var("OPENAI_API_KEY").ok();
+        let mut transcript = String::new();
+        let result = builder::run_build(
+            &project_root,
+            &BuildRequest {
+                source_path: source,
+                db_path: &output,
+                collection_id,
+                collection_name,
+                collection_icon,
+                openai_api_key: openai_api_key.as_deref(),
+                embeddings: true,
+                node_binary_path: preferences.node_binary_path.as_deref(),
+                npm_binary_path: preferences.npm_binary_path.as_deref(),
+            },
+            &mut transcript,
+        );
+        if result.is_err() {
+            eprint!("{}", transcript);
+        }
+        result?;
+        builder::build_summary(&output)?
+    } else {
+        let mut summary = native_builder::build_project(&NativeBuildRequest {
+            source_path: Path::new(source),
+            db_path: &output,
+            collection_id,
+            collection_name,
+            collection_icon,
+            incremental: !has_flag("--force"),
+        })?;
+        if let Some(api_key) = std::env::var("OPENAI_API_KEY")
+            .ok()
+            .filter(|key| !key.is_empty())
+        {
+            let client = reqwest::Client::new();
+            let embedded = tauri::async_runtime::block_on(native_builder::embed_chunks(
+                &client, &api_key, &output,
+            ));
+            match embedded {
+                Ok(count) => summary.chunks_embedded = count as i64,
+                Err(e) => eprintln!("Warning: chunk embeddings skipped: {}", e),
+            }
+        }
+        summary
+    };
+
+    let conn = open_project_db(&output)?;
+    let mut stats = serde_json::to_value(queries::project_stats(&conn, db_size_bytes(&output)))
+        .map_err(|e| e.to_string())?;
+    stats["build"] = serde_json::to_value(summary).map_err(|e| e.to_string())?;
+    print_json(&stats)
+}
+
+fn search(args: &[String]) -> Result<(), String> {
+    let db = PathBuf::from(required_flag(args, "--db")?);
+    let query = required_flag(args, "--query")?;
+    let limit = match flag_value(args, "--limit") {
+        Some(value) => value
+            .parse::<i32>()
+            .map_err(|_| format!("Invalid --limit '{}'", value))?,
+        None => DEFAULT_SEARCH_LIMIT,
+    };
+
+    let conn = open_project_db(&db)?;
+    let results = queries::search_documents(
+        &conn,
+        query,
+        flag_value(args, "--collection"),
+        &[],
+        limit,
+        crate::snippet::DEFAULT_SNIPPET_TOKENS,
+    )?;
+    print_json(&results)
+}
+
+fn stats(args: &[String]) -> Result<(), String> {
+    let db = PathBuf::from(required_flag(args, "--db")?);
+    let conn = open_project_db(&db)?;
+    print_json(&queries::project_stats(&conn, db_size_bytes(&db)))
+}
+
+/// Run a CLI command. `args` are the arguments after `--cli`. Returns the
+/// process exit code.
+pub fn run(args: &[String]) -> i32 {
+    let Some(command) = args.first() else {
+        eprintln!("{}", USAGE);
+        return EXIT_USAGE;
+    };
+    let rest = &args[1..];
+
+    let result = match command.as_str() {
+        "build" => build(rest),
+        "search" => search(rest),
+        "stats" => stats(rest),
+        "help" | "--help" | "-h" => {
+            println!("{}", USAGE);
+            return 0;
+        }
+        other => {
+            eprintln!("Unknown command '{}'\n\n{}", other, USAGE);
+            return EXIT_USAGE;
+        }
+    };
+
+    match result {
+        Ok(()) => 0,
+        Err(e) if e.starts_with("Missing required argument") || e.starts_with("Invalid --") => {
+            eprintln!("{}\n\n{}", e, USAGE);
+            EXIT_USAGE
+        }
+        Err(e) => {
+            eprintln!("{}", e);
+            1
+        }
+    }
+}
@@ -0,0 +1,1493 @@
+//! Reading activity: views, recent and updated documents, usage counts,
+//! the project home and change feeds, and tag detail.
+
+use super::ai::resolve_provider;
+use super::util::{
+    bookmark_from_row, lock_manager, lock_user_state, project_change_feed_from_row,
+    unix_timestamp_i64,
+};
+use crate::ai;
+use crate::build_queue::BuildQueue;
+use crate::db::HttpClient;
+use crate::models::*;
+use crate::projects::ProjectManager;
+use crate::queries;
+use crate::settings;
+use crate::user_state::{with_busy_retry, UserStateDb};
+use rusqlite::{params, OptionalExtension};
+use std::collections::HashMap;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+use tauri::{AppHandle, Manager, State};
+
+#[tauri::command]
+pub fn mark_document_viewed(
+    app: AppHandle,
+    user_state: State<'_, UserStateDb>,
+    project_id: String,
+    doc_slug: String,
+    viewed_at: Option<i64>,
+) -> Result<(), UserStateError> {
+    let at = viewed_at.unwrap_or_else(unix_timestamp_i64);
+    {
+        with_busy_retry(
+            || lock_user_state(&user_state),
+            |conn| {
+                conn.execute(
+                    "INSERT INTO doc_views (project_id, doc_slug, last_viewed_at)
+                 VALUES (?1, ?2, ?3)
+                 ON CONFLICT(project_id, doc_slug)
+                 DO UPDATE SET last_viewed_at = excluded.last_viewed_at",
+                    params![project_id, doc_slug, at],
+                )
+            },
+        )?;
+    }
+    expire_header_counts(&project_id);
+    count_usage(&app, "documents_viewed", &project_id);
+    Ok(())
+}
+
+/// Count one use of `metric` in the local usage statistics unless they are
+/// turned off. Takes the user_state lock, so callers must not hold it.
+/// Failures are logged rather than failing the command being counted.
+pub(super) fn count_usage(app: &AppHandle, metric: &str, project_id: &str) {
+    if settings::load_preferences(app).is_ok_and(|p| p.usage_stats_disabled) {
+        return;
+    }
+    let day = chrono::Local::now().format("%Y-%m-%d").to_string();
+    let user_state = app.state::<UserStateDb>();
+    let result = user_state
+        .0
+        .lock()
+        .map_err(|e| e.to_string())
+        .and_then(|conn| crate::user_state::record_usage(&conn, &day, metric, project_id));
+    if let Err(e) = result {
+        eprintln!("Warning: {}", e);
+    }
+}
+
+/// Daily counts of questions, searches and document reads over `range`
+/// ("week", "month", "quarter" or "year"), optionally for one project.
+/// Nothing here ever leaves the machine.
+#[tauri::command]
+pub fn get_usage_stats(
+    user_state: State<'_, UserStateDb>,
+    range: String,
+    project_id: Option<String>,
+) -> Result<UsageStats, String> {
+    let days = crate::user_state::usage_range_days(&range, chrono::Local::now().date_naive())?;
+    let conn = user_state.0.lock().map_err(|e| e.to_string())?;
+    let series = crate::user_state::usage_series(&conn, &days, project_id.as_deref())?;
+    Ok(UsageStats {
+        range,
+        days,
+        series,
+    })
+}
+
+/// Bulk-mark a project, collection, or navigation subtree as viewed.
+/// The project connection is read and released before the user_state write
+/// lock is taken, so it never nests with get_recent_documents' lock order.
+#[tauri::command]
+pub fn mark_all_viewed(
+    manager: State<'_, std::sync::Mutex<ProjectManager>>,
+    user_state: State<'_, UserStateDb>,
+    project_id: String,
+    collection_id: Option<String>,
+    parent_slug: Option<String>,
+) -> Result<i64, String> {
+    let doc_slugs: Vec<String> = {
+        let mgr = lock_manager(&manager)?;
+        let project_conn = mgr.connection(&project_id)?;
+        let mut stmt = project_conn
+            .prepare_cached(
+                "WITH RECURSIVE subtree(slug) AS (
+                    SELECT slug FROM documents WHERE ?1 IS NULL OR slug = ?1
+                    UNION
+                    SELECT d.slug FROM documents d
+                    JOIN subtree s ON d.parent_slug = s.slug
+                    WHERE ?1 IS NOT NULL
+                 )
+                 SELECT d.slug FROM documents d
+                 JOIN subtree s ON s.slug = d.slug
+                 WHERE ?2 IS NULL OR d.collection_id = ?2",
+            )
+            .map_err(|e| e.to_string())?;
+        let rows = stmt
+            .query_map(params![parent_slug, collection_id], |row| {
+                row.get::<_, String>(0)
+            })
+            .map_err(|e| e.to_string())?;
+        rows.collect::<Result<Vec<_>, _>>()
+            .map_err(|e| e.to_string())?
+    };
+
+    if doc_slugs.is_empty() {
+        return Ok(0);
+    }
+
+    let now = unix_timestamp_i64();
+    let mut conn = lock_user_state(&user_state)?;
+    let tx = conn.transaction().map_err(|e| e.to_string())?;
+    let mut touched = 0i64;
+    {
+        let mut stmt = tx
+            .prepare_cached(
+                "INSERT INTO doc_views (project_id, doc_slug, last_viewed_at)
+                 VALUES (?1, ?2, ?3)
+                 ON CONFLICT(project_id, doc_slug)
+                 DO UPDATE SET last_viewed_at = excluded.last_viewed_at",
+            )
+            .map_err(|e| e.to_string())?;
+        for doc_slug in &doc_slugs {
+            touched += stmt
+                .execute(params![&project_id, doc_slug, now])
+                .map_err(|e| e.to_string())? as i64;
+        }
+    }
+    tx.commit().map_err(|e| e.to_string())?;
+    expire_header_counts(&project_id);
+    Ok(touched)
+}
+
+fn parse_modified_epoch(
+    project_conn: &rusqlite::Connection,
+    last_modified: Option<&str>,
+) -> Option<i64> {
+    let modified = last_modified?;
+    project_conn
+        .query_row(
+            "SELECT CAST(strftime('%s', ?1) AS INTEGER)",
+            params![modified],
+            |row| row.get::<_, Option<i64>>(0),
+        )
+        .ok()
+        .flatten()
+}
+
+fn is_updated_since_viewed(
+    project_conn: &rusqlite::Connection,
+    last_modified: Option<&str>,
+    last_viewed_at: Option<i64>,
+) -> bool {
+    let modified_epoch = match parse_modified_epoch(project_conn, last_modified) {
+        Some(epoch) => epoch,
+        None => return false,
+    };
+    match last_viewed_at {
+        Some(viewed) => modified_epoch > viewed,
+        None => true,
+    }
+}
+
+fn fetch_recent_views(
+    user_conn: &rusqlite::Connection,
+    project_id: &str,
+    limit: usize,
+) -> Result<Vec<(String, i64)>, String> {
+    let mut stmt = user_conn
+        .prepare_cached(
+            "SELECT doc_slug, last_viewed_at
+             FROM doc_views
+             WHERE project_id = ?1
+             ORDER BY last_viewed_at DESC
+             LIMIT ?2",
+        )
+        .map_err(|e| e.to_string())?;
+    let rows = stmt
+        .query_map(params![project_id, limit as i32], |row| {
+            Ok((row.get::<_, String>(0)?, row.get::<_, i64>(1)?))
+        })
+        .map_err(|e| e.to_string())?;
+    rows.collect::<Result<Vec<_>, _>>()
+        .map_err(|e| e.to_string())
+}
+
+fn fetch_view_map(
+    user_conn: &rusqlite::Connection,
+    project_id: &str,
+) -> Result<std::collections::HashMap<String, i64>, String> {
+    let mut stmt = user_conn
+        .prepare_cached(
+            "SELECT doc_slug, last_viewed_at
+             FROM doc_views
+             WHERE project_id = ?1",
+        )
+        .map_err(|e| e.to_string())?;
+    let rows = stmt
+        .query_map(params![project_id], |row| {
+            Ok((row.get::<_, String>(0)?, row.get::<_, i64>(1)?))
+        })
+        .map_err(|e| e.to_string())?;
+    rows.collect::<Result<std::collections::HashMap<_, _>, _>>()
+        .map_err(|e| e.to_string())
+}
+
+fn resolve_recent_documents(
+    project_conn: &rusqlite::Connection,
+    viewed_docs: Vec<(String, i64)>,
+) -> Result<Vec<DocActivityItem>, String> {
+    let mut out = Vec::with_capacity(viewed_docs.len());
+    for (doc_slug, last_viewed_at) in viewed_docs {
+        let doc = project_conn
+            .query_row(
+                "SELECT collection_id, title, section, last_modified
+                 FROM documents
+                 WHERE slug = ?1",
+                params![&doc_slug],
+                |row| {
+                    Ok((
+                        row.get::<_, String>(0)?,
+                        row.get::<_, String>(1)?,
+                        row.get::<_, String>(2)?,
+                        row.get::<_, Option<String>>(3)?,
+                    ))
+                },
+            )
+            .optional()
+            .map_err(|e| e.to_string())?;
+
+        if let Some((collection_id, title, section, last_modified)) = doc {
+            let updated_since_viewed = is_updated_since_viewed(
+                project_conn,
+                last_modified.as_deref(),
+                Some(last_viewed_at),
+            );
+            out.push(DocActivityItem {
+                doc_slug,
+                collection_id,
+                title,
+                section,
+                last_modified,
+                last_viewed_at: Some(last_viewed_at),
+                updated_since_viewed,
+            });
+        }
+    }
+
+    Ok(out)
+}
+
+/// SQL predicate shared by every "updated since viewed" query. Expects the
+/// project's documents aliased as `d` and the staged view times as `v`; a
+/// document with no parsable last_modified is never considered updated.
+const UPDATED_SINCE_VIEWED_SQL: &str =
+    "CAST(strftime('%s', d.last_modified) AS INTEGER) IS NOT NULL \
+     AND (v.last_viewed_at IS NULL \
+          OR CAST(strftime('%s', d.last_modified) AS INTEGER) > v.last_viewed_at)";
+
+/// Stage the user's view times in a temp table on the project connection so
+/// updated/unread checks run as a single join instead of per-row queries.
+/// TEMP tables are writable even though the project database is read-only.
+fn stage_viewed_docs(
+    project_conn: &rusqlite::Connection,
+    viewed_map: &std::collections::HashMap<String, i64>,
+) -> Result<(), String> {
+    project_conn
+        .execute_batch(
+            "CREATE TEMP TABLE IF NOT EXISTS viewed_docs (
+                doc_slug TEXT PRIMARY KEY,
+                last_viewed_at INTEGER NOT NULL
+             );
+             DELETE FROM temp.viewed_docs;",
+        )
+        .map_err(|e| e.to_string())?;
+
+    let tx = project_conn
+        .unchecked_transaction()
+        .map_err(|e| e.to_string())?;
+    {
+        let mut stmt = tx
+            .prepare_cached(
+                "INSERT OR REPLACE INTO temp.viewed_docs (doc_slug, last_viewed_at) VALUES (?1, ?2)",
+            )
+            .map_err(|e| e.to_string())?;
+        for (doc_slug, last_viewed_at) in viewed_map {
+            stmt.execute(params![doc_slug, last_viewed_at])
+                .map_err(|e| e.to_string())?;
+        }
+    }
+    tx.commit().map_err(|e| e.to_string())
+}
+
+fn collect_updated_documents(
+    project_conn: &rusqlite::Connection,
+    viewed_map: &std::collections::HashMap<String, i64>,
+    limit: usize,
+) -> Result<Vec<DocActivityItem>, String> {
+    stage_viewed_docs(project_conn, viewed_map)?;
+
+    let sql = format!(
+        "SELECT d.slug, d.collection_id, d.title, d.section, d.last_modified, v.last_viewed_at
+         FROM documents d
+         LEFT JOIN temp.viewed_docs v ON v.doc_slug = d.slug
+         WHERE {}
+         ORDER BY d.last_modified DESC
+         LIMIT ?1",
+        UPDATED_SINCE_VIEWED_SQL
+    );
+    let mut stmt = project_conn
+        .prepare_cached(&sql)
+        .map_err(|e| e.to_string())?;
+
+    let rows = stmt
+        .query_map(params![limit as i64], |row| {
+            Ok(DocActivityItem {
+                doc_slug: row.get(0)?,
+                collection_id: row.get(1)?,
+                title: row.get(2)?,
+                section: row.get(3)?,
+                last_modified: row.get(4)?,
+                last_viewed_at: row.get(5)?,
+                updated_since_viewed: true,
+            })
+        })
+        .map_err(|e| e.to_string())?;
+    rows.collect::<Result<Vec<_>, _>>()
+        .map_err(|e| e.to_string())
+}
+
+/// Co-occurring tags returned with a tag's detail.
+const RELATED_TAG_LIMIT: i64 = 20;
+
+const DAY_SECS: i64 = 24 * 60 * 60;
+
+/// Documents carrying `tag` with their view state, the tags they share most
+/// often, and how many were modified in the last 30 and 90 days before `now`.
+fn collect_tag_detail(
+    project_conn: &rusqlite::Connection,
+    tag: &str,
+    viewed_map: &std::collections::HashMap<String, i64>,
+    now: i64,
+) -> Result<TagDetail, String> {
+    stage_viewed_docs(project_conn, viewed_map)?;
+
+    let sql = format!(
+        "SELECT d.slug, d.collection_id, d.title, d.section, d.last_modified, v.last_viewed_at,
+                {}
+         FROM documents d
+         JOIN document_tags dt ON dt.document_id = d.id
+         JOIN tags t ON t.id = dt.tag_id
+         LEFT JOIN temp.viewed_docs v ON v.doc_slug = d.slug
+         WHERE t.tag = ?1
+         ORDER BY d.title COLLATE NOCASE",
+        UPDATED_SINCE_VIEWED_SQL
+    );
+    let mut stmt = project_conn
+        .prepare_cached(&sql)
+        .map_err(|e| e.to_string())?;
+    let documents = stmt
+        .query_map(params![tag], |row| {
+            Ok(DocActivityItem {
+                doc_slug: row.get(0)?,
+                collection_id: row.get(1)?,
+                title: row.get(2)?,
+                section: row.get(3)?,
+                last_modified: row.get(4)?,
+                last_viewed_at: row.get(5)?,
+                updated_since_viewed: row.get(6)?,
+            })
+        })
+        .map_err(|e| e.to_string())?
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(|e| e.to_string())?;
+    if documents.is_empty() {
+        return Err(format!("No documents are tagged '{}'", tag));
+    }
+
+    let mut stmt = project_conn
+        .prepare_cached(
+            "SELECT other.tag, COUNT(*) AS shared
+             FROM tags t
+             JOIN document_tags dt ON dt.tag_id = t.id
+             JOIN document_tags odt ON odt.document_id = dt.document_id AND odt.tag_id != dt.tag_id
+             JOIN tags other ON other.id = odt.tag_id
+             WHERE t.tag = ?1
+             GROUP BY other.tag
+             ORDER BY shared DESC, other.tag COLLATE NOCASE
+             LIMIT ?2",
+        )
+        .map_err(|e| e.to_string())?;
+    let related_tags = stmt
+        .query_map(params![tag, RELATED_TAG_LIMIT], |row| {
+            Ok(Tag {
+                tag: row.get(0)?,
+                count: row.get(1)?,
+            })
+        })
+        .map_err(|e| e.to_string())?
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(|e| e.to_string())?;
+
+    let (updated_last_30_days, updated_last_90_days) = project_conn
+        .query_row(
+            "SELECT COALESCE(SUM(modified_at >= ?2), 0), COALESCE(SUM(modified_at >= ?3), 0)
+             FROM (
+                 SELECT CAST(strftime('%s', d.last_modified) AS INTEGER) AS modified_at
+                 FROM documents d
+                 JOIN document_tags dt ON dt.document_id = d.id
+                 JOIN tags t ON t.id = dt.tag_id
+                 WHERE t.tag = ?1
+             )",
+            params![tag, now - 30 * DAY_SECS, now - 90 * DAY_SECS],
+            |row| Ok((row.get(0)?, row.get(1)?)),
+        )
+        .map_err(|e| e.to_string())?;
+
+    Ok(TagDetail {
+        tag: tag.to_string(),
+        trend: TagTrend {
+            document_count: documents.len() as i64,
+            updated_last_30_days,
+            updated_last_90_days,
+        },
+        documents,
+        related_tags,
+    })
+}
+
+fn fetch_change_feed(
+    user_conn: &rusqlite::Connection,
+    project_id: &str,
+    limit: i32,
+) -> Result<Vec<ProjectChangeFeedItem>, String> {
+    let mut stmt = user_conn
+        .prepare_cached(
+            "SELECT id, project_id, commit_hash, author, committed_at, changed_files_json, changed_doc_slugs_json, recorded_at, message
+             FROM project_change_feed
+             WHERE project_id = ?1
+             ORDER BY recorded_at DESC
+             LIMIT ?2",
+        )
+        .map_err(|e| e.to_string())?;
+    let rows = stmt
+        .query_map(params![project_id, limit], project_change_feed_from_row)
+        .map_err(|e| e.to_string())?;
+    rows.collect::<Result<Vec<_>, _>>()
+        .map_err(|e| e.to_string())
+}
+
+/// Change feed entries recorded in [since, until), newest first.
+fn fetch_change_feed_between(
+    user_conn: &rusqlite::Connection,
+    project_id: &str,
+    since: i64,
+    until: i64,
+) -> Result<Vec<ProjectChangeFeedItem>, String> {
+    let mut stmt = user_conn
+        .prepare_cached(
+            "SELECT id, project_id, commit_hash, author, committed_at, changed_files_json, changed_doc_slugs_json, recorded_at, message
+             FROM project_change_feed
+             WHERE project_id = ?1 AND recorded_at >= ?2 AND recorded_at < ?3
+             ORDER BY recorded_at DESC",
+        )
+        .map_err(|e| e.to_string())?;
+    let rows = stmt
+        .query_map(
+            params![project_id, since, until],
+            project_change_feed_from_row,
+        )
+        .map_err(|e| e.to_string())?;
+    rows.collect::<Result<Vec<_>, _>>()
+        .map_err(|e| e.to_string())
+}
+
+fn fetch_favorite_bookmarks(
+    user_conn: &rusqlite::Connection,
+    project_id: &str,
+    limit: i32,
+) -> Result<Vec<Bookmark>, String> {
+    let mut stmt = user_conn
+        .prepare_cached(
+            "SELECT id, project_id, collection_id, doc_slug, anchor_id, title_snapshot, created_at, updated_at, last_opened_at, order_index, open_count, is_favorite
+             FROM bookmarks
+             WHERE project_id = ?1 AND is_favorite = 1
+             ORDER BY open_count DESC, COALESCE(last_opened_at, updated_at) DESC, created_at DESC
+             LIMIT ?2",
+        )
+        .map_err(|e| e.to_string())?;
+    let rows = stmt
+        .query_map(params![project_id, limit], bookmark_from_row)
+        .map_err(|e| e.to_string())?;
+    rows.collect::<Result<Vec<_>, _>>()
+        .map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+pub fn get_recent_documents(
+    manager: State<'_, std::sync::Mutex<ProjectManager>>,
+    user_state: State<'_, UserStateDb>,
+    project_id: String,
+    limit: Option<i32>,
+) -> Result<Vec<DocActivityItem>, String> {
+    let limit = limit.unwrap_or(10).clamp(1, 100) as usize;
+
+    let viewed_docs = {
+        let user_conn = lock_user_state(&user_state)?;
+        fetch_recent_views(&user_conn, &project_id, limit)?
+    };
+
+    if viewed_docs.is_empty() {
+        return Ok(vec![]);
+    }
+
+    let mgr = lock_manager(&manager)?;
+    let project_conn = mgr.connection(&project_id)?;
+    resolve_recent_documents(project_conn, viewed_docs)
+}
+
+/// Entries kept per project in the recently closed stack.
+const CLOSED_DOCUMENTS_KEPT: i64 = 50;
+
+/// Push a document onto a project's recently closed stack. Closing the
+/// document already on top only refreshes it, and the stack is trimmed to
+/// `CLOSED_DOCUMENTS_KEPT`.
+fn push_closed_document(
+    conn: &rusqlite::Connection,
+    project_id: &str,
+    doc_slug: &str,
+    anchor_id: Option<&str>,
+    now: i64,
+) -> rusqlite::Result<()> {
+    let top: Option<(i64, String)> = conn
+        .query_row(
+            "SELECT id, doc_slug FROM closed_documents
+             WHERE project_id = ?1 ORDER BY id DESC LIMIT 1",
+            params![project_id],
+            |row| Ok((row.get(0)?, row.get(1)?)),
+        )
+        .optional()?;
+    match top {
+        Some((id, slug)) if slug == doc_slug => {
+            conn.execute(
+                "UPDATE closed_documents SET anchor_id = ?2, closed_at = ?3 WHERE id = ?1",
+                params![id, anchor_id, now],
+            )?;
+        }
+        _ => {
+            conn.execute(
+                "INSERT INTO closed_documents (project_id, doc_slug, anchor_id, closed_at)
+                 VALUES (?1, ?2, ?3, ?4)",
+                params![project_id, doc_slug, anchor_id, now],
+            )?;
+            conn.execute(
+                "DELETE FROM closed_documents
+                 WHERE project_id = ?1 AND id NOT IN (
+                     SELECT id FROM closed_documents
+                     WHERE project_id = ?1
+                     ORDER BY id DESC LIMIT ?2
+                 )",
+                params![project_id, CLOSED_DOCUMENTS_KEPT],
+            )?;
+        }
+    }
+    Ok(())
+}
+
+/// The stack newest first as (slug, anchor, closed_at), without documents in
+/// `open_slugs` and with repeats that end up next to each other collapsed.
+fn fetch_recently_closed(
+    user_conn: &rusqlite::Connection,
+    project_id: &str,
+    open_slugs: &[String],
+    limit: usize,
+) -> Result<Vec<(String, Option<String>, i64)>, String> {
+    let mut stmt = user_conn
+        .prepare_cached(
+            "SELECT doc_slug, anchor_id, closed_at FROM closed_documents
+             WHERE project_id = ?1 ORDER BY id DESC",
+        )
+        .map_err(|e| e.to_string())?;
+    let rows = stmt
+        .query_map(params![project_id], |row| {
+            Ok((row.get(0)?, row.get(1)?, row.get(2)?))
+        })
+        .map_err(|e| e.to_string())?;
+    let mut closed: Vec<(String, Option<String>, i64)> = Vec::new();
+    for row in rows {
+        let entry = row.map_err(|e| e.to_string())?;
+        if open_slugs.contains(&entry.0) || closed.last().is_some_and(|last| last.0 == entry.0) {
+            continue;
+        }
+        closed.push(entry);
+        if closed.len() == limit {
+            break;
+        }
+    }
+    Ok(closed)
+}
+
+#[tauri::command]
+pub fn record_document_closed(
+    user_state: State<'_, UserStateDb>,
+    project_id: String,
+    doc_slug: String,
+    anchor_id: Option<String>,
+) -> Result<(), UserStateError> {
+    let now = unix_timestamp_i64();
+    with_busy_retry(
+        || lock_user_state(&user_state),
+        |conn| push_closed_document(conn, &project_id, &doc_slug, anchor_id.as_deref(), now),
+    )
+}
+
+/// Documents recently navigated away from, newest first, for reopening.
+/// `open_slugs` are the documents currently open, which are left out.
+#[tauri::command]
+pub fn get_recently_closed(
+    manager: State<'_, std::sync::Mutex<ProjectManager>>,
+    user_state: State<'_, UserStateDb>,
+    project_id: String,
+    open_slugs: Vec<String>,
+    limit: Option<i32>,
+) -> Result<Vec<ClosedDocument>, String> {
+    let limit = limit.unwrap_or(10).clamp(1, CLOSED_DOCUMENTS_KEPT as i32) as usize;
+    let closed = {
+        let user_conn = lock_user_state(&user_state)?;
+        fetch_recently_closed(&user_conn, &project_id, &open_slugs, limit)?
+    };
+    if closed.is_empty() {
+        return Ok(vec![]);
+    }
+
+    let mgr = lock_manager(&manager)?;
+    let project_conn = mgr.connection(&project_id)?;
+    let mut out = Vec::with_capacity(closed.len());
+    for (doc_slug, anchor_id, closed_at) in closed {
+        let doc = project_conn
+            .query_row(
+                "SELECT collection_id, title, section FROM documents WHERE slug = ?1",
+                params![&doc_slug],
+                |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?)),
+            )
+            .optional()
+            .map_err(|e| e.to_string())?;
+        if let Some((collection_id, title, section)) = doc {
+            out.push(ClosedDocument {
+                doc_slug,
+                anchor_id,
+                collection_id,
+                title,
+                section,
+                closed_at,
+            });
+        }
+    }
+    Ok(out)
+}
+
+#[tauri::command]
+pub fn get_updated_documents(
+    manager: State<'_, std::sync::Mutex<ProjectManager>>,
+    user_state: State<'_, UserStateDb>,
+    project_id: String,
+    limit: Option<i32>,
+) -> Result<Vec<DocActivityItem>, String> {
+    let limit = limit.unwrap_or(20).clamp(1, 200) as usize;
+
+    let viewed_map = {
+        let user_conn = lock_user_state(&user_state)?;
+        fetch_view_map(&user_conn, &project_id)?
+    };
+
+    let mgr = lock_manager(&manager)?;
+    let project_conn = mgr.connection(&project_id)?;
+    collect_updated_documents(project_conn, &viewed_map, limit)
+}
+
+/// Documents carrying a tag, the tags that appear alongside it, and how
+/// recently its documents changed. View state is read from user_state and
+/// released before the project lock, matching get_updated_documents.
+#[tauri::command]
+pub fn get_tag_detail(
+    manager: State<'_, std::sync::Mutex<ProjectManager>>,
+    user_state: State<'_, UserStateDb>,
+    project_id: String,
+    tag: String,
+) -> Result<TagDetail, String> {
+    let viewed_map = {
+        let user_conn = lock_user_state(&user_state)?;
+        fetch_view_map(&user_conn, &project_id)?
+    };
+
+    let mgr = lock_manager(&manager)?;
+    let project_conn = mgr.connection(&project_id)?;
+    collect_tag_detail(project_conn, &tag, &viewed_map, unix_timestamp_i64())
+}
+
+#[tauri::command]
+pub fn get_project_change_feed(
+    user_state: State<'_, UserStateDb>,
+    project_id: String,
+    limit: Option<i32>,
+) -> Result<Vec<ProjectChangeFeedItem>, String> {
+    let limit = limit.unwrap_or(20).clamp(1, 200);
+    let conn = user_state.0.lock().map_err(|e| e.to_string())?;
+    fetch_change_feed(&conn, &project_id, limit)
+}
+
+/// Build a Markdown digest of a project's changes between `since` and
+/// `until` (unix seconds; default now). When `use_ai` isn't false and a
+/// provider is configured, each collection also gets a short AI-written
+/// summary; otherwise, or if a summary fails, the digest is structured only.
+#[tauri::command]
+pub async fn generate_change_digest(
+    app: AppHandle,
+    http_client: State<'_, HttpClient>,
+    project_id: String,
+    since: i64,
+    until: Option<i64>,
+    use_ai: Option<bool>,
+) -> Result<ChangeDigest, String> {
+    let until = until.unwrap_or_else(unix_timestamp_i64);
+    if since >= until {
+        return Err("The digest period must end after it starts".to_string());
+    }
+
+    let feed = {
+        let user_state = app.state::<UserStateDb>();
+        let user_conn = lock_user_state(&user_state)?;
+        fetch_change_feed_between(&user_conn, &project_id, since, until)?
+    };
+
+    let (mut collections, local_only) = {
+        let manager = app.state::<std::sync::Mutex<ProjectManager>>();
+        let mgr = lock_manager(&manager)?;
+        let conn = mgr.connection(&project_id)?;
+        let documents = crate::digest::updated_documents(conn, since, until)?;
+        let doc_collections = crate::digest::document_collections(conn)?;
+        let collections = crate::digest::group_changes(
+            &queries::collections(conn, queries::CollectionSort::SortOrder)?,
+            documents,
+            &feed,
+            &doc_collections,
+        );
+        let local_only = mgr
+            .registry
+            .projects
+            .iter()
+            .any(|p| p.id == project_id && p.local_only);
+        (collections, local_only)
+    };
+
+    let stored = settings::load_settings(&app)?;
+    let provider = if use_ai.unwrap_or(true) {
+        resolve_provider(&stored, None, local_only).ok()
+    } else {
+        None
+    };
+    if let Some(provider) = provider {
+        for collection in &mut collections {
+            match ai::summarise_changes(&http_client.0, &stored, &provider, collection).await {
+                Ok(summary) => collection.summary = Some(summary),
+                Err(e) => eprintln!(
+                    "Warning: failed to summarise changes in {}: {}",
+                    collection.name,
+                    ai::redact_secrets(&e, &stored)
+                ),
+            }
+        }
+    }
+
+    Ok(crate::digest::digest(since, until, collections))
+}
+
+fn home_section<T>(result: Result<Vec<T>, String>) -> HomeSection<T> {
+    match result {
+        Ok(items) => HomeSection { items, error: None },
+        Err(e) => HomeSection {
+            items: vec![],
+            error: Some(e),
+        },
+    }
+}
+
+#[tauri::command]
+pub fn get_collection_badges(
+    manager: State<'_, std::sync::Mutex<ProjectManager>>,
+    user_state: State<'_, UserStateDb>,
+) -> Result<Vec<CollectionBadge>, String> {
+    let project_id = {
+        let mgr = lock_manager(&manager)?;
+        mgr.registry.active_project_id.clone()
+    };
+
+    let (viewed_map, hidden) = {
+        let user_conn = lock_user_state(&user_state)?;
+        (
+            fetch_view_map(&user_conn, &project_id)?,
+            crate::user_state::hidden_collections(&user_conn, &project_id)?,
+        )
+    };
+
+    let mgr = lock_manager(&manager)?;
+    let project_conn = mgr.connection(&project_id)?;
+    collection_badges(project_conn, &viewed_map, &hidden)
+}
+
+/// Updated and never-viewed counts for each collection not in `hidden`.
+fn collection_badges(
+    project_conn: &rusqlite::Connection,
+    viewed_map: &std::collections::HashMap<String, i64>,
+    hidden: &[String],
+) -> Result<Vec<CollectionBadge>, String> {
+    stage_viewed_docs(project_conn, viewed_map)?;
+
+    let sql = format!(
+        "SELECT c.id,
+                COALESCE(SUM(CASE WHEN d.id IS NOT NULL AND {updated} THEN 1 ELSE 0 END), 0),
+                COALESCE(SUM(CASE WHEN d.id IS NOT NULL AND v.doc_slug IS NULL THEN 1 ELSE 0 END), 0)
+         FROM collections c
+         LEFT JOIN documents d ON d.collection_id = c.id
+         LEFT JOIN temp.viewed_docs v ON v.doc_slug = d.slug
+         GROUP BY c.id
+         ORDER BY c.sort_order",
+        updated = UPDATED_SINCE_VIEWED_SQL
+    );
+    let mut stmt = project_conn
+        .prepare_cached(&sql)
+        .map_err(|e| e.to_string())?;
+    let rows = stmt
+        .query_map([], |row| {
+            Ok(CollectionBadge {
+                collection_id: row.get(0)?,
+                updated_count: row.get(1)?,
+                never_viewed_count: row.get(2)?,
+            })
+        })
+        .map_err(|e| e.to_string())?;
+    let badges = rows
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(|e| e.to_string())?;
+    Ok(badges
+        .into_iter()
+        .filter(|badge| !hidden.contains(&badge.collection_id))
+        .collect())
+}
+
+/// How long header counts are served before they count as stale.
+const HEADER_COUNTS_TTL: Duration = Duration::from_secs(30);
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+struct HeaderCounts {
+    document_count: i64,
+    updated_count: i64,
+}
+
+/// A project's header counts and the recount keeping them fresh.
+#[derive(Default)]
+struct CachedHeaderCounts {
+    counts: Option<HeaderCounts>,
+    /// When `counts` were taken; None once something they depend on changed
+    counted_at: Option<Instant>,
+    /// Bumped whenever the counts are expired, so a recount that started
+    /// before then doesn't store what it found
+    generation: u64,
+    recounting: bool,
+}
+
+static HEADER_COUNTS: Mutex<Option<HashMap<String, CachedHeaderCounts>>> = Mutex::new(None);
+
+/// The cached counts for a project and whether they are stale.
+fn cached_header_counts(project_id: &str, now: Instant) -> Option<(HeaderCounts, bool)> {
+    let cache = HEADER_COUNTS.lock().ok()?;
+    let entry = cache.as_ref()?.get(project_id)?;
+    let stale = entry
+        .counted_at
+        .is_none_or(|at| now.duration_since(at) > HEADER_COUNTS_TTL);
+    Some((entry.counts?, stale))
+}
+
+/// Claim the recount of a project's header counts. None when one is already
+/// running; otherwise the generation to hand to `finish_header_recount`.
+fn begin_header_recount(project_id: &str) -> Option<u64> {
+    let mut cache = HEADER_COUNTS.lock().ok()?;
+    let entry = cache
+        .get_or_insert_with(HashMap::new)
+        .entry(project_id.to_string())
+        .or_default();
+    if entry.recounting {
+        return None;
+    }
+    entry.recounting = true;
+    Some(entry.generation)
+}
+
+/// End a recount, storing what it counted unless the counts were expired
+/// while it ran.
+fn finish_header_recount(
+    project_id: &str,
+    generation: u64,
+    counted: Option<(HeaderCounts, Instant)>,
+) {
+    if let Ok(mut cache) = HEADER_COUNTS.lock() {
+        if let Some(entry) = cache.as_mut().and_then(|c| c.get_mut(project_id)) {
+            entry.recounting = false;
+            if let Some((counts, counted_at)) = counted.filter(|_| entry.generation == generation) {
+                entry.counts = Some(counts);
+                entry.counted_at = Some(counted_at);
+            }
+        }
+    }
+}
+
+/// Mark a project's header counts stale after views or a rebuild change them.
+/// The old values are still served until the next recount finishes.
+pub(super) fn expire_header_counts(project_id: &str) {
+    if let Ok(mut cache) = HEADER_COUNTS.lock() {
+        if let Some(entry) = cache.as_mut().and_then(|c| c.get_mut(project_id)) {
+            entry.counted_at = None;
+            entry.generation += 1;
+        }
+    }
+}
+
+fn count_header(
+    manager: &std::sync::Mutex<ProjectManager>,
+    user_state: &UserStateDb,
+    project_id: &str,
+) -> Result<HeaderCounts, String> {
+    let (viewed_map, hidden) = {
+        let user_conn = lock_user_state(user_state)?;
+        (
+            fetch_view_map(&user_conn, project_id)?,
+            crate::user_state::hidden_collections(&user_conn, project_id)?,
+        )
+    };
+    let mgr = lock_manager(manager)?;
+    let project_conn = mgr.connection(project_id)?;
+    let document_count = project_conn
+        .query_row("SELECT COUNT(*) FROM documents", [], |row| row.get(0))
+        .map_err(|e| e.to_string())?;
+    let updated_count = collection_badges(project_conn, &viewed_map, &hidden)?
+        .iter()
+        .map(|badge| badge.updated_count)
+        .sum();
+    Ok(HeaderCounts {
+        document_count,
+        updated_count,
+    })
+}
+
+/// What the app header shows, in one call. Counts come from a cache and are
+/// never taken on this call: stale or missing ones are recounted in the
+/// background, one recount per project at a time, and the counts are None
+/// until the first recount for a project finishes.
+#[tauri::command]
+pub fn get_header_summary(
+    app: AppHandle,
+    manager: State<'_, std::sync::Mutex<ProjectManager>>,
+    build_queue: State<'_, BuildQueue>,
+) -> Result<HeaderSummary, String> {
+    let (project_id, project_name, available) = {
+        let mgr = lock_manager(&manager)?;
+        let project_id = mgr.registry.active_project_id.clone();
+        let name = mgr
+            .registry
+            .projects
+            .iter()
+            .find(|p| p.id == project_id)
+            .map(|p| p.name.clone())
+            .unwrap_or_default();
+        let available = mgr.connections.contains_key(&project_id);
+        (project_id, name, available)
+    };
+    let queue = build_queue.snapshot();
+
+    let counts = if available {
+        let cached = cached_header_counts(&project_id, Instant::now());
+        if cached.is_none_or(|(_, stale)| stale) {
+            if let Some(generation) = begin_header_recount(&project_id) {
+                let project_id = project_id.clone();
+                tauri::async_runtime::spawn_blocking(move || {
+                    let counted_at = Instant::now();
+                    let manager = app.state::<std::sync::Mutex<ProjectManager>>();
+                    let user_state = app.state::<UserStateDb>();
+                    let counted = match count_header(&manager, &user_state, &project_id) {
+                        Ok(counts) => Some((counts, counted_at)),
+                        Err(e) => {
+                            eprintln!("Warning: failed to refresh header counts: {}", e);
+                            None
+                        }
+                    };
+                    finish_header_recount(&project_id, generation, counted);
+                });
+            }
+        }
+        cached
+    } else {
+        None
+    };
+
+    Ok(HeaderSummary {
+        active_project_id: project_id,
+        project_name,
+        document_count: counts.map(|(c, stale)| HeaderCount {
+            value: c.document_count,
+            stale,
+        }),
+        updated_count: counts.map(|(c, stale)| HeaderCount {
+            value: c.updated_count,
+            stale,
+        }),
+        building_project_id: queue
+            .iter()
+            .find(|build| build.running)
+            .map(|build| build.project_id.clone()),
+        queued_build_count: queue.iter().filter(|build| !build.running).count(),
+    })
+}
+
+/// Everything the home screen needs in one call. The user_state lock is taken
+/// first and released before the project connection is locked, so this never
+/// holds both at once.
+#[tauri::command]
+pub fn get_project_home(
+    manager: State<'_, std::sync::Mutex<ProjectManager>>,
+    user_state: State<'_, UserStateDb>,
+    project_id: String,
+) -> Result<ProjectHome, String> {
+    let (recent_views, view_map, change_feed, favorite_bookmarks) =
+        match lock_user_state(&user_state) {
+            Ok(user_conn) => (
+                fetch_recent_views(&user_conn, &project_id, 5),
+                fetch_view_map(&user_conn, &project_id),
+                fetch_change_feed(&user_conn, &project_id, 3),
+                fetch_favorite_bookmarks(&user_conn, &project_id, 5),
+            ),
+            Err(e) => (Err(e.clone()), Err(e.clone()), Err(e.clone()), Err(e)),
+        };
+
+    let (recent, updated) = match lock_manager(&manager) {
+        Ok(mgr) => match mgr.connection(&project_id) {
+            Ok(project_conn) => (
+                recent_views.and_then(|views| resolve_recent_documents(project_conn, views)),
+                view_map.and_then(|map| collect_updated_documents(project_conn, &map, 5)),
+            ),
+            Err(e) => (Err(e.clone()), Err(e)),
+        },
+        Err(e) => (Err(e.clone()), Err(e)),
+    };
+
+    Ok(ProjectHome {
+        recent: home_section(recent),
+        updated: home_section(updated),
+        change_feed: home_section(change_feed),
+        favorite_bookmarks: home_section(favorite_bookmarks),
+    })
+}
+
+fn map_changed_paths_to_doc_slugs(
+    conn: &rusqlite::Connection,
+    source_relative_prefix: &str,
+    changed_files: &[String],
+) -> Result<Vec<String>, String> {
+    let mut slugs = std::collections::BTreeSet::new();
+    let prefix = if source_relative_prefix == "." || source_relative_prefix.is_empty() {
+        String::new()
+    } else {
+        format!("{}/", source_relative_prefix.trim_matches('/'))
+    };
+
+    for changed in changed_files {
+        if !changed.to_ascii_lowercase().ends_with(".md") {
+            continue;
+        }
+        let relative_doc_path = if prefix.is_empty() {
+            changed.clone()
+        } else if changed.starts_with(&prefix) {
+            changed[prefix.len()..].to_string()
+        } else {
+            continue;
+        };
+        let slug: Option<String> = conn
+            .query_row(
+                "SELECT slug FROM documents WHERE path = ?1 LIMIT 1",
+                params![relative_doc_path],
+                |row| row.get(0),
+            )
+            .optional()
+            .map_err(|e| e.to_string())?;
+        if let Some(doc_slug) = slug {
+            slugs.insert(doc_slug);
+        }
+    }
+
+    Ok(slugs.into_iter().collect())
+}
+
+/// The latest commit of a project's source repository.
+struct GitChangeFeedEntry {
+    commit_hash: String,
+    author: String,
+    committed_at: String,
+    /// Subject line of the commit message
+    message: String,
+    changed_files: Vec<String>,
+    changed_doc_slugs: Vec<String>,
+}
+
+fn capture_git_change_feed_entry(
+    project_conn: &rusqlite::Connection,
+    source_path: &str,
+) -> Option<GitChangeFeedEntry> {
+    let show_toplevel = std::process::Command::new("git")
+        .args(["-C", source_path, "rev-parse", "--show-toplevel"])
+        .output()
+        .ok()?;
+    if !show_toplevel.status.success() {
+        return None;
+    }
+    let repo_root = String::from_utf8_lossy(&show_toplevel.stdout)
+        .trim()
+        .to_string();
+    if repo_root.is_empty() {
+        return None;
+    }
+
+    let prefix_out = std::process::Command::new("git")
+        .args(["-C", source_path, "rev-parse", "--show-prefix"])
+        .output()
+        .ok()?;
+    if !prefix_out.status.success() {
+        return None;
+    }
+    let source_prefix = String::from_utf8_lossy(&prefix_out.stdout)
+        .trim()
+        .trim_end_matches('/')
+        .to_string();
+
+    let meta_out = std::process::Command::new("git")
+        .args([
+            "-C",
+            source_path,
+            "log",
+            "-1",
+            "--pretty=format:%H%n%an%n%aI%n%s",
+        ])
+        .output()
+        .ok()?;
+    if !meta_out.status.success() {
+        return None;
+    }
+    let meta_text = String::from_utf8_lossy(&meta_out.stdout);
+    let mut meta_lines = meta_text.lines();
+    let commit_hash = meta_lines.next()?.trim().to_string();
+    let author = meta_lines.next()?.trim().to_string();
+    let committed_at = meta_lines.next()?.trim().to_string();
+    let message = meta_lines.next().unwrap_or("").trim().to_string();
+
+    if commit_hash.is_empty() {
+        return None;
+    }
+
+    let files_out = std::process::Command::new("git")
+        .args([
+            "-C",
+            source_path,
+            "show",
+            "--name-only",
+            "--pretty=format:",
+            &commit_hash,
+        ])
+        .output()
+        .ok()?;
+    if !files_out.status.success() {
+        return None;
+    }
+    let changed_files: Vec<String> = String::from_utf8_lossy(&files_out.stdout)
+        .lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty())
+        .map(|line| line.to_string())
+        .collect();
+
+    let changed_doc_slugs =
+        map_changed_paths_to_doc_slugs(project_conn, &source_prefix, &changed_files).ok()?;
+
+    if repo_root.is_empty() {
+        return None;
+    }
+
+    Some(GitChangeFeedEntry {
+        commit_hash,
+        author,
+        committed_at,
+        message,
+        changed_files,
+        changed_doc_slugs,
+    })
+}
+
+/// Record documents that disappeared in a rebuild, and forget earlier removals
+/// of documents that are back.
+pub(super) fn record_doc_removals(
+    user_state_conn: &rusqlite::Connection,
+    project_id: &str,
+    removed: &[String],
+    current: &std::collections::HashSet<String>,
+) -> Result<(), String> {
+    let now = unix_timestamp_i64();
+    let tx = user_state_conn
+        .unchecked_transaction()
+        .map_err(|e| e.to_string())?;
+    {
+        let mut insert = tx
+            .prepare(
+                "INSERT INTO doc_removals (project_id, doc_slug, removed_at) VALUES (?1, ?2, ?3)
+                 ON CONFLICT(project_id, doc_slug) DO UPDATE SET removed_at = excluded.removed_at",
+            )
+            .map_err(|e| e.to_string())?;
+        for slug in removed {
+            insert
+                .execute(params![project_id, slug, now])
+                .map_err(|e| e.to_string())?;
+        }
+
+        let previously_removed: Vec<String> = {
+            let mut stmt = tx
+                .prepare("SELECT doc_slug FROM doc_removals WHERE project_id = ?1")
+                .map_err(|e| e.to_string())?;
+            let rows = stmt
+                .query_map(params![project_id], |row| row.get(0))
+                .map_err(|e| e.to_string())?;
+            rows.collect::<Result<_, _>>().map_err(|e| e.to_string())?
+        };
+        for slug in previously_removed.iter().filter(|s| current.contains(*s)) {
+            tx.execute(
+                "DELETE FROM doc_removals WHERE project_id = ?1 AND doc_slug = ?2",
+                params![project_id, slug],
+            )
+            .map_err(|e| e.to_string())?;
+        }
+    }
+    tx.commit().map_err(|e| e.to_string())
+}
+
+pub(super) fn record_project_change_feed(
+    user_state_conn: &rusqlite::Connection,
+    project_conn: &rusqlite::Connection,
+    project_id: &str,
+    source_path: &str,
+) -> Result<(), String> {
+    let Some(entry) = capture_git_change_feed_entry(project_conn, source_path) else {
+        return Ok(());
+    };
+
+    let already_exists: Option<i64> = user_state_conn
+        .query_row(
+            "SELECT id FROM project_change_feed WHERE project_id = ?1 AND commit_hash = ?2 LIMIT 1",
+            params![project_id, &entry.commit_hash],
+            |row| row.get(0),
+        )
+        .optional()
+        .map_err(|e| e.to_string())?;
+    if already_exists.is_some() {
+        return Ok(());
+    }
+
+    let changed_files_json =
+        serde_json::to_string(&entry.changed_files).map_err(|e| e.to_string())?;
+    let changed_doc_slugs_json =
+        serde_json::to_string(&entry.changed_doc_slugs).map_err(|e| e.to_string())?;
+    let now = unix_timestamp_i64();
+
+    user_state_conn
+        .execute(
+            "INSERT INTO project_change_feed (
+                project_id, commit_hash, author, committed_at,
+                changed_files_json, changed_doc_slugs_json, recorded_at, message
+             ) VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8)",
+            params![
+                project_id,
+                entry.commit_hash,
+                entry.author,
+                entry.committed_at,
+                changed_files_json,
+                changed_doc_slugs_json,
+                now,
+                entry.message
+            ],
+        )
+        .map_err(|e| e.to_string())?;
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{
+        begin_header_recount, cached_header_counts, collect_tag_detail, expire_header_counts,
+        fetch_recently_closed, finish_header_recount, push_closed_document, HeaderCounts,
+        CLOSED_DOCUMENTS_KEPT, DAY_SECS, HEADER_COUNTS_TTL,
+    };
+    use std::time::{Duration, Instant};
+
+    #[test]
+    fn tag_detail_joins_views_related_tags_and_trend() {
+        let conn = rusqlite::Connection::open_in_memory().unwrap();
+        conn.execute_batch(
+            "CREATE TABLE documents (
+                id INTEGER PRIMARY KEY,
+                collection_id TEXT NOT NULL,
+                slug TEXT NOT NULL,
+                title TEXT NOT NULL,
+                section TEXT NOT NULL DEFAULT '',
+                last_modified TEXT
+            );
+            CREATE TABLE tags (id INTEGER PRIMARY KEY, tag TEXT NOT NULL);
+            CREATE TABLE document_tags (document_id INTEGER NOT NULL, tag_id INTEGER NOT NULL);
+
+            INSERT INTO documents VALUES
+                (1, 'ops', 'ops/deploys', 'Deploys', '', '2026-03-01T00:00:00Z'),
+                (2, 'ops', 'ops/rollback', 'Rollback', '', '2026-01-15T00:00:00Z'),
+                (3, 'guides', 'guides/ci', 'CI', '', NULL),
+                (4, 'guides', 'guides/style', 'Style', '', '2026-03-01T00:00:00Z');
+            INSERT INTO tags VALUES (1, 'deploy'), (2, 'ops'), (3, 'ci'), (4, 'writing');
+            INSERT INTO document_tags VALUES
+                (1, 1), (2, 1), (3, 1), (1, 2), (2, 2), (3, 3), (4, 4);",
+        )
+        .unwrap();
+        // 2026-03-10T00:00:00Z
+        let now = 1_773_100_800;
+        let viewed_map = [
+            ("ops/deploys".to_string(), now - 20 * DAY_SECS),
+            ("ops/rollback".to_string(), now),
+        ]
+        .into_iter()
+        .collect();
+
+        let detail = collect_tag_detail(&conn, "deploy", &viewed_map, now).unwrap();
+        let documents: Vec<(&str, Option<i64>, bool)> = detail
+            .documents
+            .iter()
+            .map(|d| {
+                (
+                    d.doc_slug.as_str(),
+                    d.last_viewed_at,
+                    d.updated_since_viewed,
+                )
+            })
+            .collect();
+        assert_eq!(
+            documents,
+            [
+                ("guides/ci", None, false),
+                ("ops/deploys", Some(now - 20 * DAY_SECS), true),
+                ("ops/rollback", Some(now), false),
+            ]
+        );
+        let related: Vec<(&str, i32)> = detail
+            .related_tags
+            .iter()
+            .map(|t| (t.tag.as_str(), t.count))
+            .collect();
+        assert_eq!(related, [("ops", 2), ("ci", 1)]);
+        assert_eq!(detail.trend.document_count, 3);
+        assert_eq!(detail.trend.updated_last_30_days, 1);
+        assert_eq!(detail.trend.updated_last_90_days, 2);
+
+        assert!(collect_tag_detail(&conn, "missing", &viewed_map, now).is_err());
+    }
+
+    #[test]
+    fn recently_closed_skips_open_documents_and_repeats() {
+        let conn = rusqlite::Connection::open_in_memory().unwrap();
+        crate::user_state::apply_user_state_schema(&conn).unwrap();
+        for (at, slug) in ["a", "b", "b", "c", "b", "d"].into_iter().enumerate() {
+            push_closed_document(&conn, "p", slug, None, at as i64).unwrap();
+        }
+        push_closed_document(&conn, "other", "a", None, 9).unwrap();
+        let count: i64 = conn
+            .query_row(
+                "SELECT COUNT(*) FROM closed_documents WHERE project_id = 'p'",
+                [],
+                |row| row.get(0),
+            )
+            .unwrap();
+        assert_eq!(count, 5);
+
+        let slugs = |open: &[&str], limit| -> Vec<String> {
+            let open: Vec<String> = open.iter().map(|s| s.to_string()).collect();
+            fetch_recently_closed(&conn, "p", &open, limit)
+                .unwrap()
+                .into_iter()
+                .map(|(slug, _, _)| slug)
+                .collect()
+        };
+        assert_eq!(slugs(&[], 10), ["d", "b", "c", "b", "a"]);
+        // With "c" open the two "b" entries meet and show once
+        assert_eq!(slugs(&["c"], 10), ["d", "b", "a"]);
+        assert_eq!(slugs(&["d"], 1), ["b"]);
+
+        for at in 0..CLOSED_DOCUMENTS_KEPT + 10 {
+            push_closed_document(&conn, "p", &format!("doc-{}", at), None, at).unwrap();
+        }
+        assert_eq!(slugs(&[], 100).len() as i64, CLOSED_DOCUMENTS_KEPT);
+        assert_eq!(
+            slugs(&[], 1),
+            [format!("doc-{}", CLOSED_DOCUMENTS_KEPT + 9)]
+        );
+    }
+
+    #[test]
+    fn header_counts_go_stale_after_the_ttl_or_when_expired() {
+        let counts = HeaderCounts {
+            document_count: 12,
+            updated_count: 3,
+        };
+        let counted_at = Instant::now();
+        assert_eq!(cached_header_counts("header-test", counted_at), None);
+
+        let generation = begin_header_recount("header-test").unwrap();
+        assert_eq!(begin_header_recount("header-test"), None);
+        finish_header_recount("header-test", generation, Some((counts, counted_at)));
+        assert_eq!(
+            cached_header_counts("header-test", counted_at),
+            Some((counts, false))
+        );
+        let later = counted_at + HEADER_COUNTS_TTL + Duration::from_secs(1);
+        assert_eq!(
+            cached_header_counts("header-test", later),
+            Some((counts, true))
+        );
+
+        expire_header_counts("header-test");
+        assert_eq!(
+            cached_header_counts("header-test", counted_at),
+            Some((counts, true))
+        );
+        expire_header_counts("never-counted");
+        assert_eq!(cached_header_counts("never-counted", counted_at), None);
+    }
+
+    #[test]
+    fn recounts_started_before_an_expiry_are_discarded() {
+        let counts = |document_count| HeaderCounts {
+            document_count,
+            updated_count: 0,
+        };
+        let counted_at = Instant::now();
+        let generation = begin_header_recount("expiry-test").unwrap();
+        finish_header_recount("expiry-test", generation, Some((counts(1), counted_at)));
+
+        let generation = begin_header_recount("expiry-test").unwrap();
+        expire_header_counts("expiry-test");
+        finish_header_recount("expiry-test", generation, Some((counts(2), counted_at)));
+        assert_eq!(
+            cached_header_counts("expiry-test", counted_at),
+            Some((counts(1), true))
+        );
+
+        // A failed recount frees the slot for the next one
+        let generation = begin_header_recount("expiry-test").unwrap();
+        finish_header_recount("expiry-test", generation, None);
+        let generation = begin_header_recount("expiry-test").unwrap();
+        finish_header_recount("expiry-test", generation, Some((counts(3), counted_at)));
+        assert_eq!(
+            cached_header_counts("expiry-test", counted_at),
+            Some((counts(3), false))
+        );
+    }
+}
@@ -0,0 +1,785 @@
+//! AI commands: provider status and selection, questions, comparisons, tag
+//! suggestions, embeddings and similarity search.
+
+use super::activity::count_usage;
+use super::projects::{
+    reopen_project_connection, rewrite_project_database, writable_project_db_path,
+};
+use super::util::{lock_manager, lock_user_state};
+use crate::ai;
+use crate::build_queue::BuildQueue;
+use crate::db::HttpClient;
+use crate::models::*;
+use crate::projects::ProjectManager;
+use crate::queries;
+use crate::settings;
+use tauri::{AppHandle, Emitter, Manager, State};
+
+/// Embedding count, ANN index state, retrieval exclusions and hybrid search
+/// settings for the active project, and whether Ollama is reachable. Ollama
+/// is only probed when it is the provider requests would use, or when
+/// `force_refresh` asks for a probe even past a recently cached verdict.
+#[tauri::command]
+pub async fn get_ai_status(
+    app: AppHandle,
+    manager: State<'_, std::sync::Mutex<ProjectManager>>,
+    http_client: State<'_, HttpClient>,
+    force_refresh: Option<bool>,
+) -> Result<AiStatus, String> {
+    let stored = settings::load_settings(&app)?;
+    let force_refresh = force_refresh.unwrap_or(false);
+    let ollama_selected = resolve_provider(&stored, None, active_project_local_only(&app)?)
+        .is_ok_and(|provider| provider == AiProvider::Ollama);
+    let ollama_available = if ollama_selected || force_refresh {
+        ai::providers::ollama::is_available(&http_client.0, &stored, force_refresh).await
+    } else {
+        false
+    };
+    let mgr = manager.lock().map_err(|e| e.to_string())?;
+    let conn = mgr.active_connection()?;
+    Ok(AiStatus {
+        embedding_count: queries::project_stats(conn, 0).embedding_count,
+        ann_index: crate::ann::status(conn),
+        excluded_collections: mgr.excluded_collections(&mgr.registry.active_project_id),
+        retrieval: mgr.retrieval_config(&mgr.registry.active_project_id, &stored),
+        ollama_available,
+    })
+}
+
+/// Remove embeddings for short chunks or whole collections from a project
+/// database to reclaim space.
+#[tauri::command]
+pub async fn prune_embeddings(
+    app: AppHandle,
+    manager: State<'_, std::sync::Mutex<ProjectManager>>,
+    build_queue: State<'_, BuildQueue>,
+    project_id: String,
+    min_chunk_chars: usize,
+    excluded_collections: Option<Vec<String>>,
+) -> Result<PruneSummary, String> {
+    let excluded = excluded_collections.unwrap_or_default();
+    let (embeddings_removed, bytes_before, bytes_after) = rewrite_project_database(
+        &app,
+        &manager,
+        &build_queue,
+        &project_id,
+        "prune",
+        move |db| crate::native_builder::prune_embeddings(db, min_chunk_chars, &excluded),
+    )
+    .await?;
+    Ok(PruneSummary {
+        embeddings_removed,
+        bytes_before,
+        bytes_after,
+    })
+}
+
+const DEFAULT_EMBEDDING_CONCURRENCY: usize = 2;
+const MAX_EMBEDDING_CONCURRENCY: usize = 8;
+
+/// Embed chunks in a project that was built without an API key, or whose last
+/// embedding run was cancelled. Emits `embedding-progress` after each round of
+/// batches; `cancel_ai_request(request_id)` stops the run, keeping the
+/// embeddings written so far.
+#[tauri::command]
+pub async fn generate_missing_embeddings(
+    app: AppHandle,
+    manager: State<'_, std::sync::Mutex<ProjectManager>>,
+    http_client: State<'_, HttpClient>,
+    build_queue: State<'_, BuildQueue>,
+    project_id: String,
+    request_id: String,
+    concurrency: Option<usize>,
+) -> Result<EmbeddingRunSummary, String> {
+    let stored = settings::load_settings(&app)?;
+    let api_key = stored
+        .openai_api_key
+        .clone()
+        .filter(|key| !key.is_empty())
+        .ok_or("Add an OpenAI API key in settings to generate embeddings")?;
+    let local_only = {
+        let mgr = manager.lock().map_err(|e| e.to_string())?;
+        mgr.registry
+            .projects
+            .iter()
+            .any(|p| p.id == project_id && p.local_only)
+    };
+    if local_only {
+        return Err(provider_not_allowed(
+            "this project is local-only, so its chunks cannot be sent to OpenAI for embedding.",
+        ));
+    }
+    let db_path = writable_project_db_path(&app, &manager, &project_id)?;
+    let concurrency = concurrency
+        .unwrap_or(DEFAULT_EMBEDDING_CONCURRENCY)
+        .clamp(1, MAX_EMBEDDING_CONCURRENCY);
+
+    let _turn = build_queue
+        .wait_for_turn(&app, &project_id, "embed")
+        .await?;
+
+    let run = crate::native_builder::embed_missing_chunks(
+        &http_client.0,
+        &api_key,
+        &db_path,
+        concurrency,
+        || !ai::is_cancelled(&request_id),
+        |embedded, total| {
+            crate::events::emit(
+                &app,
+                "embedding-progress",
+                serde_json::json!({
+                    "projectId": &project_id,
+                    "requestId": &request_id,
+                    "embedded": embedded,
+                    "total": total,
+                }),
+            )
+        },
+    )
+    .await;
+    ai::clear_cancel_request(&request_id);
+    // Swap even on failure: the database keeps every batch that succeeded
+    reopen_project_connection(&manager, &project_id, &db_path)?;
+    let run = run.map_err(|e| ai::redact_secrets(&e, &stored))?;
+
+    Ok(EmbeddingRunSummary {
+        embedded: run.embedded as i64,
+        remaining: run.remaining as i64,
+        cancelled: run.cancelled,
+    })
+}
+
+/// Store a project's embeddings as int8 to shrink its database.
+#[tauri::command]
+pub async fn convert_embeddings_to_int8(
+    app: AppHandle,
+    manager: State<'_, std::sync::Mutex<ProjectManager>>,
+    build_queue: State<'_, BuildQueue>,
+    project_id: String,
+) -> Result<QuantizeSummary, String> {
+    let (embeddings_converted, bytes_before, bytes_after) = rewrite_project_database(
+        &app,
+        &manager,
+        &build_queue,
+        &project_id,
+        "quantize",
+        crate::native_builder::quantize_embeddings,
+    )
+    .await?;
+    Ok(QuantizeSummary {
+        embeddings_converted,
+        bytes_before,
+        bytes_after,
+    })
+}
+
+#[tauri::command]
+pub fn get_similar_chunks(
+    manager: State<'_, std::sync::Mutex<crate::projects::ProjectManager>>,
+    query_embedding: Vec<f32>,
+    limit: Option<usize>,
+) -> Result<Vec<ScoredChunk>, String> {
+    let mgr = manager.lock().map_err(|e| e.to_string())?;
+    let conn = mgr.active_connection()?;
+    let limit = limit.unwrap_or(10);
+    let excluded = mgr.excluded_collections(&mgr.registry.active_project_id);
+    ai::vector_search(&conn, &query_embedding, limit, &excluded)
+}
+
+/// Hybrid vector and full-text chunk search with a score breakdown per
+/// result. Without `query_embedding` one is generated with the configured
+/// provider; if that isn't possible the search is text-only.
+#[tauri::command]
+pub async fn hybrid_search_chunks(
+    app: AppHandle,
+    manager: State<'_, std::sync::Mutex<crate::projects::ProjectManager>>,
+    http_client: State<'_, HttpClient>,
+    query: String,
+    query_embedding: Option<Vec<f32>>,
+    limit: Option<usize>,
+) -> Result<Vec<ExplainedChunk>, String> {
+    let stored = settings::load_settings(&app)?;
+    let embedding = match query_embedding {
+        Some(embedding) => embedding,
+        None => match resolve_provider(&stored, None, active_project_local_only(&app)?) {
+            Ok(provider) => ai::generate_embedding(&http_client.0, &stored, &provider, &query)
+                .await
+                .unwrap_or_else(|e| {
+                    eprintln!(
+                        "Warning: query embedding failed, using text search: {}",
+                        ai::redact_secrets(&e, &stored)
+                    );
+                    vec![]
+                }),
+            Err(_) => vec![],
+        },
+    };
+
+    let mgr = manager.lock().map_err(|e| e.to_string())?;
+    ai::hybrid_search_explained(
+        mgr.active_connection()?,
+        &embedding,
+        &query,
+        limit.unwrap_or(10),
+        &mgr.excluded_collections(&mgr.registry.active_project_id),
+        &mgr.retrieval_config(&mgr.registry.active_project_id, &stored),
+    )
+}
+
+const DEFAULT_EVALUATION_K: usize = 5;
+
+/// Development aid for tuning retrieval: run each question through hybrid
+/// search with the active project's settings and report how often the
+/// expected document is among the top `k` passages. Questions are embedded
+/// with the configured provider; without one the search is text-only.
+/// Collections hidden from search are left out as they are for questions,
+/// and a case that fails is reported with its error while the rest run.
+#[tauri::command]
+pub async fn evaluate_retrieval(
+    app: AppHandle,
+    manager: State<'_, std::sync::Mutex<crate::projects::ProjectManager>>,
+    user_state: State<'_, crate::user_state::UserStateDb>,
+    http_client: State<'_, HttpClient>,
+    cases: Vec<RetrievalCase>,
+    k: Option<usize>,
+) -> Result<RetrievalEvaluation, String> {
+    let k = k.unwrap_or(DEFAULT_EVALUATION_K).max(1);
+    let stored = settings::load_settings(&app)?;
+    let provider = resolve_provider(&stored, None, active_project_local_only(&app)?).ok();
+    let mut embeddings = Vec::with_capacity(cases.len());
+    for case in &cases {
+        embeddings.push(match &provider {
+            Some(provider) => {
+                ai::generate_embedding(&http_client.0, &stored, provider, &case.question)
+                    .await
+                    .map_err(|e| ai::redact_secrets(&e, &stored))
+            }
+            None => Ok(vec![]),
+        });
+    }
+
+    let hidden_collections = if settings::load_preferences(&app)
+        .unwrap_or_default()
+        .exclude_hidden_collections_from_search
+    {
+        let project_id = lock_manager(&manager)?.registry.active_project_id.clone();
+        let user_conn = lock_user_state(&user_state)?;
+        crate::user_state::hidden_collections(&user_conn, &project_id)?
+    } else {
+        vec![]
+    };
+
+    let mgr = manager.lock().map_err(|e| e.to_string())?;
+    let project_id = &mgr.registry.active_project_id;
+    let conn = mgr.active_connection()?;
+    let config = mgr.retrieval_config(project_id, &stored);
+    let mut excluded = mgr.excluded_collections(project_id);
+    excluded.extend(hidden_collections);
+    let mut results = Vec::with_capacity(cases.len());
+    for (case, embedding) in cases.into_iter().zip(embeddings) {
+        let retrieved = embedding.and_then(|embedding| {
+            let chunks =
+                ai::hybrid_search(conn, &embedding, &case.question, k, &excluded, &config)?;
+            let mut retrieved_slugs = Vec::with_capacity(chunks.len());
+            for chunk in &chunks {
+                retrieved_slugs.push(
+                    conn.query_row(
+                        "SELECT slug FROM documents WHERE id = ?1",
+                        [chunk.document_id],
+                        |row| row.get::<_, String>(0),
+                    )
+                    .map_err(|e| e.to_string())?,
+                );
+            }
+            Ok(retrieved_slugs)
+        });
+        let (retrieved_slugs, error) = match retrieved {
+            Ok(slugs) => (slugs, None),
+            Err(e) => (vec![], Some(e)),
+        };
+        results.push(RetrievalCaseResult {
+            rank: retrieved_slugs
+                .iter()
+                .position(|slug| *slug == case.expected_slug)
+                .map(|i| i + 1),
+            question: case.question,
+            expected_slug: case.expected_slug,
+            retrieved_slugs,
+            error,
+        });
+    }
+    Ok(retrieval_evaluation(k, config, provider.is_some(), results))
+}
+
+/// Hit rate and mean reciprocal rank over the case results, leaving out
+/// cases that failed.
+fn retrieval_evaluation(
+    k: usize,
+    config: RetrievalConfig,
+    used_embeddings: bool,
+    cases: Vec<RetrievalCaseResult>,
+) -> RetrievalEvaluation {
+    let total = cases
+        .iter()
+        .filter(|case| case.error.is_none())
+        .count()
+        .max(1) as f64;
+    let hits = cases.iter().filter(|case| case.rank.is_some()).count();
+    let reciprocal_ranks: f64 = cases
+        .iter()
+        .filter_map(|case| case.rank)
+        .map(|rank| 1.0 / rank as f64)
+        .sum();
+    RetrievalEvaluation {
+        k,
+        config,
+        used_embeddings,
+        hit_rate: hits as f64 / total,
+        mean_reciprocal_rank: reciprocal_ranks / total,
+        cases,
+    }
+}
+
+#[tauri::command]
+pub fn get_similar_documents(
+    manager: State<'_, std::sync::Mutex<crate::projects::ProjectManager>>,
+    doc_slug: String,
+    limit: Option<usize>,
+) -> Result<Vec<SimilarDocument>, String> {
+    let mgr = manager.lock().map_err(|e| e.to_string())?;
+    ai::similar_documents(mgr.active_connection()?, &doc_slug, limit.unwrap_or(5))
+}
+
+#[tauri::command]
+pub async fn test_provider(
+    app: AppHandle,
+    http_client: State<'_, HttpClient>,
+    provider: AiProvider,
+) -> Result<String, String> {
+    let stored = settings::load_settings(&app)?;
+    ai::test_provider_connection(&http_client.0, &stored, &provider)
+        .await
+        .map_err(|e| ai::redact_secrets(&e, &stored))
+}
+
+/// Load the Ollama chat model in the background so the first question doesn't
+/// wait for it. With `only_if_preferred`, nothing happens unless Ollama is the
+/// provider questions would use. Warmup is best-effort: failures are logged,
+/// never returned.
+#[tauri::command]
+pub async fn warm_ollama_model(
+    app: AppHandle,
+    http_client: State<'_, HttpClient>,
+    only_if_preferred: Option<bool>,
+) -> Result<(), String> {
+    warm_ollama(&app, &http_client.0, only_if_preferred.unwrap_or(false)).await;
+    Ok(())
+}
+
+pub async fn warm_ollama(app: &AppHandle, client: &reqwest::Client, only_if_preferred: bool) {
+    let stored = match settings::load_settings(app) {
+        Ok(stored) => stored,
+        Err(e) => {
+            eprintln!("Warning: Ollama warmup skipped: {}", e);
+            return;
+        }
+    };
+    if only_if_preferred {
+        let local_only = active_project_local_only(app).unwrap_or(false);
+        if !matches!(
+            resolve_provider(&stored, None, local_only),
+            Ok(AiProvider::Ollama)
+        ) {
+            return;
+        }
+    }
+    if let Err(e) = ai::warm_ollama_model(client, app, &stored).await {
+        eprintln!(
+            "Warning: Ollama warmup failed: {}",
+            ai::redact_secrets(&e, &stored)
+        );
+    }
+}
+
+fn provider_is_configured(settings: &Settings, provider: &AiProvider) -> bool {
+    ai::providers::for_provider(provider).is_configured(settings)
+}
+
+/// Start of the error for a cloud provider requested in a local-only project,
+/// so the UI can tell it apart from configuration errors.
+const PROVIDER_NOT_ALLOWED: &str = "ProviderNotAllowed";
+
+fn provider_not_allowed(reason: &str) -> String {
+    format!("{}: {}", PROVIDER_NOT_ALLOWED, reason)
+}
+
+/// Whether the active project is restricted to local AI providers.
+fn active_project_local_only(app: &AppHandle) -> Result<bool, String> {
+    let manager = app.state::<std::sync::Mutex<ProjectManager>>();
+    let mgr = manager.lock().map_err(|e| e.to_string())?;
+    Ok(mgr
+        .registry
+        .projects
+        .iter()
+        .any(|p| p.id == mgr.registry.active_project_id && p.local_only))
+}
+
+/// Pick the provider for an AI request. In a local-only project only Ollama
+/// is allowed, even when the caller asks for another provider explicitly.
+pub(super) fn resolve_provider(
+    settings: &Settings,
+    provider: Option<AiProvider>,
+    local_only: bool,
+) -> Result<AiProvider, String> {
+    if local_only {
+        if let Some(explicit) = provider.filter(|p| *p != AiProvider::Ollama) {
+            return Err(provider_not_allowed(&format!(
+                "this project is local-only, so {} cannot be used. Use Ollama instead.",
+                ai::providers::for_provider(&explicit).name()
+            )));
+        }
+        if !provider_is_configured(settings, &AiProvider::Ollama) {
+            return Err(provider_not_allowed(
+                "this project is local-only and needs an Ollama base URL configured in Settings.",
+            ));
+        }
+        return Ok(AiProvider::Ollama);
+    }
+
+    if let Some(explicit) = provider {
+        if provider_is_configured(settings, &explicit) {
+            return Ok(explicit);
+        }
+        return Err(match explicit {
+            AiProvider::Openai => {
+                "OpenAI is selected but no OpenAI API key is configured.".to_string()
+            }
+            AiProvider::Anthropic => {
+                "Anthropic is selected but no Anthropic API key is configured.".to_string()
+            }
+            AiProvider::Gemini => {
+                "Gemini is selected but no Gemini API key is configured.".to_string()
+            }
+            AiProvider::Ollama => {
+                "Ollama is selected but no Ollama base URL is configured.".to_string()
+            }
+        });
+    }
+
+    if let Some(preferred) = settings.preferred_provider.as_ref().and_then(|p| {
+        serde_json::from_value::<AiProvider>(serde_json::Value::String(p.clone())).ok()
+    }) {
+        if provider_is_configured(settings, &preferred) {
+            return Ok(preferred);
+        }
+    }
+
+    for candidate in [
+        AiProvider::Openai,
+        AiProvider::Anthropic,
+        AiProvider::Gemini,
+        AiProvider::Ollama,
+    ] {
+        if provider_is_configured(settings, &candidate) {
+            return Ok(candidate);
+        }
+    }
+
+    Err("No AI provider is configured. Add an OpenAI, Anthropic, or Gemini API key, or configure an Ollama base URL in Settings.".to_string())
+}
+
+#[tauri::command]
+#[allow(clippy::too_many_arguments)]
+pub async fn ask_question(
+    app: AppHandle,
+    http_client: State<'_, HttpClient>,
+    question: String,
+    request_id: String,
+    provider: Option<AiProvider>,
+    queue: Option<bool>,
+    allow_general_knowledge: Option<bool>,
+    answer_language: Option<String>,
+) -> Result<(), String> {
+    let stored = settings::load_settings(&app)?;
+
+    let provider = resolve_provider(&stored, provider, active_project_local_only(&app)?)?;
+    let project_id = {
+        let manager = app.state::<std::sync::Mutex<ProjectManager>>();
+        let mgr = manager.lock().map_err(|e| e.to_string())?;
+        mgr.registry.active_project_id.clone()
+    };
+    count_usage(&app, "questions_asked", &project_id);
+
+    // Held until the answer finishes streaming, or released by cancel_ai_request
+    let Some(_slot) =
+        ai::acquire_request_slot(&app, &stored, &provider, &request_id, queue.unwrap_or(true))
+            .await?
+    else {
+        let _ = app.emit(
+            "ai-response-done",
+            ai::AiResponseDoneEvent {
+                schema_version: ai::AiEventSchema,
+                request_id,
+                cancelled: true,
+                prompt_cache: None,
+            },
+        );
+        return Ok(());
+    };
+
+    // Run the RAG pipeline — errors are emitted as events
+    let result = ai::ask_question_rag(
+        http_client.0.clone(),
+        app.clone(),
+        request_id.clone(),
+        question,
+        provider,
+        allow_general_knowledge.unwrap_or(false),
+        answer_language,
+    )
+    .await;
+    emit_ask_error(&app, &request_id, result, &stored)
+}
+
+/// Redact a failed answer's error and emit it as ai-response-error.
+fn emit_ask_error(
+    app: &AppHandle,
+    request_id: &str,
+    result: Result<(), String>,
+    stored: &Settings,
+) -> Result<(), String> {
+    if let Err(e) = result {
+        let e = ai::redact_secrets(&e, stored);
+        if let Err(emit_err) = tauri::Emitter::emit(
+            app,
+            "ai-response-error",
+            ai::error_event(request_id, &e, stored),
+        ) {
+            eprintln!(
+                "Warning: failed to emit ai-response-error event: {}. Original error: {}",
+                emit_err, e
+            );
+        }
+        return Err(e);
+    }
+
+    Ok(())
+}
+
+/// Answer a question about one document from its full text, streamed like
+/// ask_question but with sources limited to that document.
+#[tauri::command]
+#[allow(clippy::too_many_arguments)]
+pub async fn ask_about_document(
+    app: AppHandle,
+    http_client: State<'_, HttpClient>,
+    doc_slug: String,
+    question: String,
+    request_id: String,
+    provider: Option<AiProvider>,
+    queue: Option<bool>,
+    answer_language: Option<String>,
+) -> Result<(), String> {
+    let stored = settings::load_settings(&app)?;
+
+    let provider = resolve_provider(&stored, provider, active_project_local_only(&app)?)?;
+
+    let Some(_slot) =
+        ai::acquire_request_slot(&app, &stored, &provider, &request_id, queue.unwrap_or(true))
+            .await?
+    else {
+        let _ = app.emit(
+            "ai-response-done",
+            ai::AiResponseDoneEvent {
+                schema_version: ai::AiEventSchema,
+                request_id,
+                cancelled: true,
+                prompt_cache: None,
+            },
+        );
+        return Ok(());
+    };
+
+    let result = ai::ask_about_document(
+        http_client.0.clone(),
+        app.clone(),
+        request_id.clone(),
+        doc_slug,
+        question,
+        provider,
+        answer_language,
+    )
+    .await;
+    emit_ask_error(&app, &request_id, result, &stored)
+}
+
+/// Stream an AI comparison of two documents: differences, contradictions
+/// and gaps. Fails before contacting the provider when the documents are
+/// too long to compare.
+#[tauri::command]
+pub async fn compare_documents(
+    app: AppHandle,
+    http_client: State<'_, HttpClient>,
+    first_slug: String,
+    second_slug: String,
+    request_id: String,
+    provider: Option<AiProvider>,
+    queue: Option<bool>,
+) -> Result<(), String> {
+    let stored = settings::load_settings(&app)?;
+
+    let provider = resolve_provider(&stored, provider, active_project_local_only(&app)?)?;
+
+    let Some(_slot) =
+        ai::acquire_request_slot(&app, &stored, &provider, &request_id, queue.unwrap_or(true))
+            .await?
+    else {
+        let _ = app.emit(
+            "ai-response-done",
+            ai::AiResponseDoneEvent {
+                schema_version: ai::AiEventSchema,
+                request_id,
+                cancelled: true,
+                prompt_cache: None,
+            },
+        );
+        return Ok(());
+    };
+
+    let result = ai::compare_documents(
+        http_client.0.clone(),
+        app.clone(),
+        request_id.clone(),
+        first_slug,
+        second_slug,
+        provider,
+    )
+    .await;
+    emit_ask_error(&app, &request_id, result, &stored)
+}
+
+#[tauri::command]
+pub async fn get_embedding(
+    app: AppHandle,
+    http_client: State<'_, HttpClient>,
+    text: String,
+    provider: Option<AiProvider>,
+) -> Result<Vec<f32>, String> {
+    let stored = settings::load_settings(&app)?;
+    let provider = resolve_provider(&stored, provider, active_project_local_only(&app)?)?;
+
+    ai::generate_embedding(&http_client.0, &stored, &provider, &text)
+        .await
+        .map_err(|e| ai::redact_secrets(&e, &stored))
+}
+
+/// Suggest tags for a document from the project's tag vocabulary. Nothing is
+/// written; the suggestions are for copying into the docs repo.
+#[tauri::command]
+pub async fn suggest_document_tags(
+    app: AppHandle,
+    http_client: State<'_, HttpClient>,
+    doc_slug: String,
+    provider: Option<AiProvider>,
+) -> Result<TagSuggestions, String> {
+    let stored = settings::load_settings(&app)?;
+    let provider = resolve_provider(&stored, provider, active_project_local_only(&app)?)?;
+
+    ai::suggest_document_tags(&http_client.0, &app, &stored, &provider, &doc_slug)
+        .await
+        .map_err(|e| ai::redact_secrets(&e, &stored))
+}
+
+/// The pipeline trace recorded for a question, when the `ai_rag_traces`
+/// preference was on and the trace hasn't been pruned since.
+#[tauri::command]
+pub fn get_rag_trace(app: AppHandle, request_id: String) -> Result<Option<RagTrace>, String> {
+    let app_data_dir = app.path().app_data_dir().map_err(|e| e.to_string())?;
+    crate::rag_traces::read_trace(&crate::rag_traces::traces_dir(&app_data_dir), &request_id)
+}
+
+#[tauri::command]
+pub fn cancel_ai_request(request_id: String) -> Result<(), String> {
+    ai::cancel_request(&request_id)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{resolve_provider, retrieval_evaluation, PROVIDER_NOT_ALLOWED};
+    use crate::models::{AiProvider, RetrievalCaseResult, RetrievalConfig, Settings};
+
+    fn settings_with_all_providers() -> Settings {
+        Settings {
+            openai_api_key: Some("sk-test".to_string()),
+            anthropic_api_key: Some("sk-ant-test".to_string()),
+            gemini_api_key: Some("gemini-test".to_string()),
+            ollama_base_url: Some("http://localhost:11434".to_string()),
+            preferred_provider: Some("openai".to_string()),
+            ..Settings::default()
+        }
+    }
+
+    #[test]
+    fn local_only_rejects_explicit_cloud_provider() {
+        let settings = settings_with_all_providers();
+        for provider in [
+            AiProvider::Openai,
+            AiProvider::Anthropic,
+            AiProvider::Gemini,
+        ] {
+            let err = resolve_provider(&settings, Some(provider), true).unwrap_err();
+            assert!(err.starts_with(PROVIDER_NOT_ALLOWED), "{}", err);
+        }
+        assert_eq!(
+            resolve_provider(&settings, Some(AiProvider::Ollama), true),
+            Ok(AiProvider::Ollama)
+        );
+    }
+
+    #[test]
+    fn local_only_ignores_preferred_cloud_provider() {
+        let settings = settings_with_all_providers();
+        assert_eq!(
+            resolve_provider(&settings, None, true),
+            Ok(AiProvider::Ollama)
+        );
+        assert_eq!(
+            resolve_provider(&settings, None, false),
+            Ok(AiProvider::Openai)
+        );
+
+        let without_ollama = Settings {
+            ollama_base_url: None,
+            ..settings
+        };
+        let err = resolve_provider(&without_ollama, None, true).unwrap_err();
+        assert!(err.starts_with(PROVIDER_NOT_ALLOWED), "{}", err);
+    }
+
+    #[test]
+    fn retrieval_evaluation_counts_hits_and_reciprocal_ranks() {
+        let case = |rank: Option<usize>| RetrievalCaseResult {
+            question: "q".to_string(),
+            expected_slug: "ops/deploys".to_string(),
+            rank,
+            retrieved_slugs: vec![],
+            error: None,
+        };
+        let failed = RetrievalCaseResult {
+            error: Some("Embedding request failed".to_string()),
+            ..case(None)
+        };
+        let evaluation = retrieval_evaluation(
+            5,
+            RetrievalConfig::default(),
+            true,
+            vec![case(Some(1)), case(Some(4)), case(None), failed, case(None)],
+        );
+        assert_eq!(evaluation.hit_rate, 0.5);
+        assert!((evaluation.mean_reciprocal_rank - 1.25 / 4.0).abs() < 1e-9);
+
+        let empty = retrieval_evaluation(5, RetrievalConfig::default(), false, vec![]);
+        assert_eq!((empty.hit_rate, empty.mean_reciprocal_rank), (0.0, 0.0));
+    }
+}
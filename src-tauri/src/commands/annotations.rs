@@ -0,0 +1,1287 @@
+//! Per-document notes and highlights, and repair of annotations whose
+//! documents moved or disappeared.
+
+use super::ai::resolve_provider;
+use super::bookmarks::{move_to_trash, TrashPayload};
+use super::search::document_id_for_slug;
+use super::util::{
+    bookmark_from_row, highlight_from_row, lock_manager, lock_user_state, unix_timestamp_i64,
+};
+use crate::ai;
+use crate::db::HttpClient;
+use crate::external_annotations;
+use crate::flashcards;
+use crate::models::*;
+use crate::note_checklist;
+use crate::projects::ProjectManager;
+use crate::queries;
+use crate::settings;
+use crate::user_state::{restore_doc_note, with_busy_retry, write_doc_note, UserStateDb};
+use rusqlite::{params, OptionalExtension};
+use std::collections::HashMap;
+use tauri::{AppHandle, Manager, State};
+use tauri_plugin_dialog::DialogExt;
+
+fn read_doc_note(
+    conn: &rusqlite::Connection,
+    project_id: &str,
+    doc_slug: &str,
+) -> rusqlite::Result<Option<DocNote>> {
+    conn.query_row(
+        "SELECT project_id, doc_slug, note, updated_at, revision
+         FROM doc_notes
+         WHERE project_id = ?1 AND doc_slug = ?2",
+        params![project_id, doc_slug],
+        doc_note_from_row,
+    )
+    .optional()
+}
+
+fn doc_note_from_row(row: &rusqlite::Row<'_>) -> rusqlite::Result<DocNote> {
+    Ok(DocNote {
+        project_id: row.get(0)?,
+        doc_slug: row.get(1)?,
+        note: row.get(2)?,
+        updated_at: row.get(3)?,
+        revision: row.get(4)?,
+    })
+}
+
+/// The note just written for a document.
+fn saved_doc_note(
+    conn: &rusqlite::Connection,
+    project_id: &str,
+    doc_slug: &str,
+) -> Result<DocNote, String> {
+    read_doc_note(conn, project_id, doc_slug)
+        .map_err(|e| e.to_string())?
+        .ok_or_else(|| format!("No note found for '{}'", doc_slug))
+}
+
+#[tauri::command]
+pub fn get_doc_note(
+    user_state: State<'_, UserStateDb>,
+    project_id: String,
+    doc_slug: String,
+) -> Result<Option<DocNote>, String> {
+    let conn = user_state.0.lock().map_err(|e| e.to_string())?;
+    read_doc_note(&conn, &project_id, &doc_slug).map_err(|e| e.to_string())
+}
+
+/// Save a note and discard its draft in one transaction. With
+/// `expected_revision`, a note that has been saved with different text since
+/// the caller read it is left alone and `UserStateError::Conflict` returned.
+fn save_doc_note_checked(
+    conn: &rusqlite::Connection,
+    project_id: &str,
+    doc_slug: &str,
+    note: &str,
+    expected_revision: Option<i64>,
+    now: i64,
+) -> Result<DocNote, UserStateError> {
+    let tx = conn.unchecked_transaction()?;
+    if let Some(expected) = expected_revision {
+        let current = read_doc_note(&tx, project_id, doc_slug)?;
+        let changed = current
+            .as_ref()
+            .is_none_or(|current| current.revision != expected && current.note != note);
+        if changed {
+            return Err(UserStateError::Conflict {
+                message: "This note was changed somewhere else since you opened it.".to_string(),
+                current,
+            });
+        }
+    }
+    write_doc_note(&tx, project_id, doc_slug, note, now)?;
+    tx.execute(
+        "DELETE FROM doc_note_drafts WHERE project_id = ?1 AND doc_slug = ?2",
+        params![project_id, doc_slug],
+    )?;
+    let saved = saved_doc_note(&tx, project_id, doc_slug)?;
+    tx.commit()?;
+    Ok(saved)
+}
+
+#[tauri::command]
+pub fn save_doc_note(
+    user_state: State<'_, UserStateDb>,
+    project_id: String,
+    doc_slug: String,
+    note: String,
+    expected_revision: Option<i64>,
+) -> Result<DocNote, UserStateError> {
+    let conn = user_state.0.lock().map_err(|e| e.to_string())?;
+    save_doc_note_checked(
+        &conn,
+        &project_id,
+        &doc_slug,
+        &note,
+        expected_revision,
+        unix_timestamp_i64(),
+    )
+}
+
+/// Autosave note text while it is being typed; cheap enough to call on every
+/// pause. Cleared when the note is saved.
+#[tauri::command]
+pub fn save_doc_note_draft(
+    user_state: State<'_, UserStateDb>,
+    project_id: String,
+    doc_slug: String,
+    draft: String,
+) -> Result<(), UserStateError> {
+    let now = unix_timestamp_i64();
+    with_busy_retry(
+        || lock_user_state(&user_state),
+        |conn| {
+            conn.execute(
+                "INSERT INTO doc_note_drafts (project_id, doc_slug, draft, updated_at)
+             VALUES (?1, ?2, ?3, ?4)
+             ON CONFLICT(project_id, doc_slug)
+             DO UPDATE SET draft = excluded.draft, updated_at = excluded.updated_at",
+                params![&project_id, &doc_slug, &draft, now],
+            )
+        },
+    )?;
+    Ok(())
+}
+
+/// The saved note and any unsaved draft left by an earlier session, so the UI
+/// can offer to restore it.
+#[tauri::command]
+pub fn get_doc_note_draft(
+    user_state: State<'_, UserStateDb>,
+    project_id: String,
+    doc_slug: String,
+) -> Result<DocNoteWithDraft, String> {
+    let conn = user_state.0.lock().map_err(|e| e.to_string())?;
+    doc_note_with_draft(&conn, &project_id, &doc_slug).map_err(|e| e.to_string())
+}
+
+fn doc_note_with_draft(
+    conn: &rusqlite::Connection,
+    project_id: &str,
+    doc_slug: &str,
+) -> rusqlite::Result<DocNoteWithDraft> {
+    let note = read_doc_note(conn, project_id, doc_slug)?;
+    let draft = conn
+        .query_row(
+            "SELECT project_id, doc_slug, draft, updated_at
+             FROM doc_note_drafts
+             WHERE project_id = ?1 AND doc_slug = ?2",
+            params![project_id, doc_slug],
+            |row| {
+                Ok(DocNoteDraft {
+                    project_id: row.get(0)?,
+                    doc_slug: row.get(1)?,
+                    draft: row.get(2)?,
+                    updated_at: row.get(3)?,
+                })
+            },
+        )
+        .optional()?
+        .filter(|draft| note.as_ref().is_none_or(|note| note.note != draft.draft));
+    Ok(DocNoteWithDraft { note, draft })
+}
+
+/// Flip the task-list checkbox at `index` (counting from 0) in a note without
+/// resending the whole text.
+#[tauri::command]
+pub fn toggle_note_checkbox(
+    user_state: State<'_, UserStateDb>,
+    project_id: String,
+    doc_slug: String,
+    index: usize,
+) -> Result<DocNote, String> {
+    let now = unix_timestamp_i64();
+    let conn = user_state.0.lock().map_err(|e| e.to_string())?;
+    let tx = conn.unchecked_transaction().map_err(|e| e.to_string())?;
+    let note: String = tx
+        .query_row(
+            "SELECT note FROM doc_notes WHERE project_id = ?1 AND doc_slug = ?2",
+            params![&project_id, &doc_slug],
+            |row| row.get(0),
+        )
+        .optional()
+        .map_err(|e| e.to_string())?
+        .ok_or_else(|| format!("No note found for '{}'", doc_slug))?;
+    let note = note_checklist::toggle_checkbox(&note, index)?;
+    tx.execute(
+        "UPDATE doc_notes SET note = ?3, updated_at = ?4, revision = revision + 1
+         WHERE project_id = ?1 AND doc_slug = ?2",
+        params![&project_id, &doc_slug, &note, now],
+    )
+    .map_err(|e| e.to_string())?;
+    let saved = saved_doc_note(&tx, &project_id, &doc_slug)?;
+    tx.commit().map_err(|e| e.to_string())?;
+    Ok(saved)
+}
+
+pub(super) fn doc_note_revision_from_row(
+    row: &rusqlite::Row<'_>,
+) -> rusqlite::Result<DocNoteRevision> {
+    Ok(DocNoteRevision {
+        id: row.get(0)?,
+        project_id: row.get(1)?,
+        doc_slug: row.get(2)?,
+        note: row.get(3)?,
+        created_at: row.get(4)?,
+    })
+}
+
+/// Earlier texts of a document's note, newest first.
+#[tauri::command]
+pub fn list_doc_note_revisions(
+    user_state: State<'_, UserStateDb>,
+    project_id: String,
+    doc_slug: String,
+) -> Result<Vec<DocNoteRevision>, String> {
+    let conn = user_state.0.lock().map_err(|e| e.to_string())?;
+    let mut stmt = conn
+        .prepare_cached(
+            "SELECT id, project_id, doc_slug, note, created_at
+             FROM doc_note_revisions
+             WHERE project_id = ?1 AND doc_slug = ?2
+             ORDER BY id DESC",
+        )
+        .map_err(|e| e.to_string())?;
+    let rows = stmt
+        .query_map(params![project_id, doc_slug], doc_note_revision_from_row)
+        .map_err(|e| e.to_string())?;
+    rows.collect::<Result<Vec<_>, _>>()
+        .map_err(|e| e.to_string())
+}
+
+/// Put a revision's text back as the note. The note it replaces becomes a
+/// revision itself, so restoring never loses text.
+#[tauri::command]
+pub fn restore_doc_note_revision(
+    user_state: State<'_, UserStateDb>,
+    id: i64,
+) -> Result<DocNote, String> {
+    let conn = user_state.0.lock().map_err(|e| e.to_string())?;
+    restore_revision(&conn, id, unix_timestamp_i64())
+}
+
+fn restore_revision(conn: &rusqlite::Connection, id: i64, now: i64) -> Result<DocNote, String> {
+    let revision = conn
+        .query_row(
+            "SELECT id, project_id, doc_slug, note, created_at
+             FROM doc_note_revisions WHERE id = ?1",
+            params![id],
+            doc_note_revision_from_row,
+        )
+        .optional()
+        .map_err(|e| e.to_string())?
+        .ok_or_else(|| format!("Note revision {} not found", id))?;
+    restore_doc_note(
+        conn,
+        &revision.project_id,
+        &revision.doc_slug,
+        &revision.note,
+        now,
+    )?;
+    saved_doc_note(conn, &revision.project_id, &revision.doc_slug)
+}
+
+#[tauri::command]
+pub fn list_doc_highlights(
+    user_state: State<'_, UserStateDb>,
+    project_id: String,
+    doc_slug: String,
+) -> Result<Vec<DocHighlight>, String> {
+    let conn = user_state.0.lock().map_err(|e| e.to_string())?;
+    let mut stmt = conn
+        .prepare_cached(
+            "SELECT id, project_id, doc_slug, anchor_id, selected_text, context_text, created_at
+             FROM doc_highlights
+             WHERE project_id = ?1 AND doc_slug = ?2
+             ORDER BY created_at DESC",
+        )
+        .map_err(|e| e.to_string())?;
+    let rows = stmt
+        .query_map(params![project_id, doc_slug], highlight_from_row)
+        .map_err(|e| e.to_string())?;
+    rows.collect::<Result<Vec<_>, _>>()
+        .map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+pub fn add_doc_highlight(
+    user_state: State<'_, UserStateDb>,
+    project_id: String,
+    doc_slug: String,
+    anchor_id: Option<String>,
+    selected_text: String,
+    context_text: Option<String>,
+) -> Result<DocHighlight, String> {
+    let text = selected_text.trim();
+    if text.is_empty() {
+        return Err("Highlight text cannot be empty".to_string());
+    }
+
+    let now = unix_timestamp_i64();
+    let conn = user_state.0.lock().map_err(|e| e.to_string())?;
+    conn.execute(
+        "INSERT INTO doc_highlights (project_id, doc_slug, anchor_id, selected_text, context_text, created_at)
+         VALUES (?1, ?2, ?3, ?4, ?5, ?6)",
+        params![project_id, doc_slug, anchor_id, text, context_text, now],
+    )
+    .map_err(|e| e.to_string())?;
+    let id = conn.last_insert_rowid();
+    conn.query_row(
+        "SELECT id, project_id, doc_slug, anchor_id, selected_text, context_text, created_at
+         FROM doc_highlights WHERE id = ?1",
+        params![id],
+        highlight_from_row,
+    )
+    .map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+pub fn delete_doc_highlight(user_state: State<'_, UserStateDb>, id: i64) -> Result<(), String> {
+    let conn = user_state.0.lock().map_err(|e| e.to_string())?;
+    let tx = conn.unchecked_transaction().map_err(|e| e.to_string())?;
+    let highlight = tx
+        .query_row(
+            "SELECT id, project_id, doc_slug, anchor_id, selected_text, context_text, created_at
+             FROM doc_highlights WHERE id = ?1",
+            params![id],
+            highlight_from_row,
+        )
+        .optional()
+        .map_err(|e| e.to_string())?;
+    if let Some(highlight) = highlight {
+        let project_id = highlight.project_id.clone();
+        let folder_ids =
+            annotation_relation_ids(&tx, AnnotationRelation::Folders, "highlight", id)?;
+        let tag_ids = annotation_relation_ids(&tx, AnnotationRelation::Tags, "highlight", id)?;
+        move_to_trash(
+            &tx,
+            &project_id,
+            &TrashPayload::DocHighlight {
+                highlight,
+                folder_ids,
+                tag_ids,
+            },
+        )?;
+        tx.execute("DELETE FROM doc_highlights WHERE id = ?1", params![id])
+            .map_err(|e| e.to_string())?;
+    }
+    tx.commit().map_err(|e| e.to_string())
+}
+
+/// Import highlights and notes from a Readwise or Hypothesis JSON export into
+/// the active project. Items are matched to documents by URL path, else by
+/// title; those matching no document, or more than one, are returned for
+/// review rather than guessed at.
+#[tauri::command]
+pub fn import_external_annotations(
+    manager: State<'_, std::sync::Mutex<ProjectManager>>,
+    user_state: State<'_, UserStateDb>,
+    path: String,
+) -> Result<ExternalAnnotationImport, String> {
+    let json =
+        std::fs::read_to_string(&path).map_err(|e| format!("Failed to read {}: {}", path, e))?;
+    let annotations = external_annotations::parse_export(&json)
+        .map_err(|e| format!("{} is not a Readwise or Hypothesis export: {}", path, e))?;
+    let (project_id, documents) = {
+        let mgr = lock_manager(&manager)?;
+        let conn = mgr.active_connection()?;
+        let mut stmt = conn
+            .prepare("SELECT slug, title FROM documents")
+            .map_err(|e| e.to_string())?;
+        let documents = stmt
+            .query_map([], |row| Ok((row.get(0)?, row.get(1)?)))
+            .map_err(|e| e.to_string())?
+            .collect::<Result<Vec<(String, String)>, _>>()
+            .map_err(|e| e.to_string())?;
+        (mgr.registry.active_project_id.clone(), documents)
+    };
+
+    let conn = lock_user_state(&user_state)?;
+    let tx = conn.unchecked_transaction().map_err(|e| e.to_string())?;
+    let summary = import_annotations(
+        &tx,
+        &project_id,
+        annotations,
+        &documents,
+        unix_timestamp_i64(),
+    )?;
+    tx.commit().map_err(|e| e.to_string())?;
+    Ok(summary)
+}
+
+/// Add matched items as highlights, and their notes to the end of their
+/// document's note. A highlight with the same text, or a note the document's
+/// note already contains, is skipped so an export can be imported again.
+fn import_annotations(
+    conn: &rusqlite::Connection,
+    project_id: &str,
+    annotations: Vec<ExternalAnnotation>,
+    documents: &[(String, String)],
+    now: i64,
+) -> Result<ExternalAnnotationImport, String> {
+    let mut summary = ExternalAnnotationImport {
+        matched: 0,
+        unmatched: 0,
+        highlights_added: 0,
+        notes_added: 0,
+        duplicates_skipped: 0,
+        unmatched_entries: vec![],
+    };
+    let mut notes: Vec<(&str, Vec<String>)> = Vec::new();
+    for annotation in annotations {
+        let Some(doc_slug) = external_annotations::match_document(&annotation, documents) else {
+            summary.unmatched += 1;
+            summary.unmatched_entries.push(annotation);
+            continue;
+        };
+        summary.matched += 1;
+
+        if let Some(text) = &annotation.text {
+            let exists: bool = conn
+                .query_row(
+                    "SELECT EXISTS(SELECT 1 FROM doc_highlights
+                     WHERE project_id = ?1 AND doc_slug = ?2 AND selected_text = ?3)",
+                    params![project_id, doc_slug, text],
+                    |row| row.get(0),
+                )
+                .map_err(|e| e.to_string())?;
+            if exists {
+                summary.duplicates_skipped += 1;
+            } else {
+                conn.execute(
+                    "INSERT INTO doc_highlights (project_id, doc_slug, anchor_id, selected_text, context_text, created_at)
+                     VALUES (?1, ?2, NULL, ?3, ?4, ?5)",
+                    params![project_id, doc_slug, text, annotation.context_text, now],
+                )
+                .map_err(|e| e.to_string())?;
+                summary.highlights_added += 1;
+            }
+        }
+        if let Some(note) = annotation.note {
+            match notes.iter_mut().find(|(slug, _)| *slug == doc_slug) {
+                Some((_, pending)) => pending.push(note),
+                None => notes.push((doc_slug, vec![note])),
+            }
+        }
+    }
+
+    // One write per document, so each gets a single revision
+    for (doc_slug, pending) in notes {
+        let mut note: String = conn
+            .query_row(
+                "SELECT note FROM doc_notes WHERE project_id = ?1 AND doc_slug = ?2",
+                params![project_id, doc_slug],
+                |row| row.get(0),
+            )
+            .optional()
+            .map_err(|e| e.to_string())?
+            .unwrap_or_default();
+        let before = summary.notes_added;
+        for text in pending {
+            if note.contains(&text) {
+                summary.duplicates_skipped += 1;
+                continue;
+            }
+            if !note.trim().is_empty() {
+                note.push_str("\n\n");
+            }
+            note.push_str(&text);
+            summary.notes_added += 1;
+        }
+        if summary.notes_added > before {
+            write_doc_note(conn, project_id, doc_slug, &note, now)?;
+        }
+    }
+    Ok(summary)
+}
+
+/// The shared folders and tags, as they relate to notes and highlights.
+#[derive(Debug, Clone, Copy)]
+pub(super) enum AnnotationRelation {
+    Folders,
+    Tags,
+}
+
+impl AnnotationRelation {
+    /// The relation table, its folder or tag id column, and the table that
+    /// column points at.
+    fn tables(self) -> (&'static str, &'static str, &'static str) {
+        match self {
+            AnnotationRelation::Folders => {
+                ("annotation_folder_items", "folder_id", "bookmark_folders")
+            }
+            AnnotationRelation::Tags => ("annotation_tag_items", "tag_id", "bookmark_tags"),
+        }
+    }
+}
+
+/// The kind and id an annotation's relations are stored under, and its
+/// project. A note's id is its `doc_notes.id`.
+fn annotation_key(
+    conn: &rusqlite::Connection,
+    annotation: &AnnotationRef,
+) -> Result<(&'static str, i64, String), String> {
+    match annotation {
+        AnnotationRef::Note {
+            project_id,
+            doc_slug,
+        } => conn
+            .query_row(
+                "SELECT id FROM doc_notes WHERE project_id = ?1 AND doc_slug = ?2",
+                params![project_id, doc_slug],
+                |row| row.get(0),
+            )
+            .optional()
+            .map_err(|e| e.to_string())?
+            .map(|id| ("note", id, project_id.clone()))
+            .ok_or_else(|| format!("No note found for '{}'", doc_slug)),
+        AnnotationRef::Highlight { id } => conn
+            .query_row(
+                "SELECT project_id FROM doc_highlights WHERE id = ?1",
+                params![id],
+                |row| row.get(0),
+            )
+            .optional()
+            .map_err(|e| e.to_string())?
+            .map(|project_id| ("highlight", *id, project_id))
+            .ok_or_else(|| format!("Highlight {} not found", id)),
+    }
+}
+
+/// Whether the note or highlight `kind`/`item_id` still exists in `project_id`.
+pub(super) fn annotation_exists(
+    conn: &rusqlite::Connection,
+    project_id: &str,
+    kind: &str,
+    item_id: i64,
+) -> Result<bool, String> {
+    let sql = match kind {
+        "note" => "SELECT 1 FROM doc_notes WHERE id = ?1 AND project_id = ?2",
+        "highlight" => "SELECT 1 FROM doc_highlights WHERE id = ?1 AND project_id = ?2",
+        _ => return Ok(false),
+    };
+    conn.query_row(sql, params![item_id, project_id], |_| Ok(()))
+        .optional()
+        .map(|found| found.is_some())
+        .map_err(|e| e.to_string())
+}
+
+/// Folder or tag ids assigned to each note and highlight of a project, keyed
+/// by kind and id, so a listing reads them in one query.
+fn project_annotation_relations(
+    conn: &rusqlite::Connection,
+    relation: AnnotationRelation,
+    project_id: &str,
+) -> Result<HashMap<(String, i64), Vec<i64>>, String> {
+    let (table, column, target) = relation.tables();
+    let mut stmt = conn
+        .prepare_cached(&format!(
+            "SELECT r.kind, r.item_id, r.{column} FROM {table} r
+             JOIN {target} t ON t.id = r.{column}
+             WHERE t.project_id = ?1
+             ORDER BY r.{column}"
+        ))
+        .map_err(|e| e.to_string())?;
+    let mut rows = stmt.query([project_id]).map_err(|e| e.to_string())?;
+    let mut relations: HashMap<(String, i64), Vec<i64>> = HashMap::new();
+    while let Some(row) = rows.next().map_err(|e| e.to_string())? {
+        relations
+            .entry((
+                row.get(0).map_err(|e| e.to_string())?,
+                row.get(1).map_err(|e| e.to_string())?,
+            ))
+            .or_default()
+            .push(row.get(2).map_err(|e| e.to_string())?);
+    }
+    Ok(relations)
+}
+
+/// Folder or tag ids assigned to one note or highlight.
+pub(super) fn annotation_relation_ids(
+    conn: &rusqlite::Connection,
+    relation: AnnotationRelation,
+    kind: &str,
+    item_id: i64,
+) -> Result<Vec<i64>, String> {
+    let (table, column, _) = relation.tables();
+    let mut stmt = conn
+        .prepare_cached(&format!(
+            "SELECT {} FROM {} WHERE kind = ?1 AND item_id = ?2 ORDER BY 1",
+            column, table
+        ))
+        .map_err(|e| e.to_string())?;
+    let rows = stmt
+        .query_map(params![kind, item_id], |row| row.get(0))
+        .map_err(|e| e.to_string())?;
+    rows.collect::<Result<Vec<_>, _>>()
+        .map_err(|e| e.to_string())
+}
+
+/// Assign folders or tags to a note or highlight, skipping ids that don't
+/// belong to its project.
+pub(super) fn add_annotation_relations(
+    conn: &rusqlite::Connection,
+    relation: AnnotationRelation,
+    project_id: &str,
+    kind: &str,
+    item_id: i64,
+    ids: &[i64],
+) -> Result<(), String> {
+    let (table, column, target) = relation.tables();
+    let mut stmt = conn
+        .prepare_cached(&format!(
+            "INSERT OR IGNORE INTO {} ({}, kind, item_id)
+             SELECT id, ?2, ?3 FROM {} WHERE id = ?1 AND project_id = ?4",
+            table, column, target
+        ))
+        .map_err(|e| e.to_string())?;
+    for id in ids {
+        stmt.execute(params![id, kind, item_id, project_id])
+            .map_err(|e| e.to_string())?;
+    }
+    Ok(())
+}
+
+fn set_annotation_relations(
+    user_state: &UserStateDb,
+    relation: AnnotationRelation,
+    annotation: &AnnotationRef,
+    ids: &[i64],
+) -> Result<(), String> {
+    let conn = lock_user_state(user_state)?;
+    let tx = conn.unchecked_transaction().map_err(|e| e.to_string())?;
+    let (kind, item_id, project_id) = annotation_key(&tx, annotation)?;
+    let (table, _, _) = relation.tables();
+    tx.execute(
+        &format!("DELETE FROM {} WHERE kind = ?1 AND item_id = ?2", table),
+        params![kind, item_id],
+    )
+    .map_err(|e| e.to_string())?;
+    add_annotation_relations(&tx, relation, &project_id, kind, item_id, ids)?;
+    tx.commit().map_err(|e| e.to_string())
+}
+
+/// Replace the folders a note or highlight is filed in. Notes and highlights
+/// share the folders bookmarks use.
+#[tauri::command]
+pub fn set_annotation_folders(
+    user_state: State<'_, UserStateDb>,
+    annotation: AnnotationRef,
+    folder_ids: Vec<i64>,
+) -> Result<(), String> {
+    set_annotation_relations(
+        &user_state,
+        AnnotationRelation::Folders,
+        &annotation,
+        &folder_ids,
+    )
+}
+
+/// Replace the tags on a note or highlight. Notes and highlights share the
+/// tags bookmarks use.
+#[tauri::command]
+pub fn set_annotation_tags(
+    user_state: State<'_, UserStateDb>,
+    annotation: AnnotationRef,
+    tag_ids: Vec<i64>,
+) -> Result<(), String> {
+    set_annotation_relations(&user_state, AnnotationRelation::Tags, &annotation, &tag_ids)
+}
+
+/// A project's notes and highlights, most recently changed first, optionally
+/// only those in `folder_id` and/or tagged `tag_id`.
+#[tauri::command]
+pub fn list_all_annotations(
+    user_state: State<'_, UserStateDb>,
+    project_id: String,
+    folder_id: Option<i64>,
+    tag_id: Option<i64>,
+) -> Result<Vec<AnnotationListItem>, String> {
+    let conn = lock_user_state(&user_state)?;
+    let mut stmt = conn
+        .prepare_cached(
+            "SELECT kind, id, doc_slug, anchor_id, text, context_text, updated_at FROM (
+                 SELECT 'note' AS kind, id, doc_slug, NULL AS anchor_id,
+                        note AS text, NULL AS context_text, updated_at
+                 FROM doc_notes
+                 WHERE project_id = ?1 AND note != ''
+                 UNION ALL
+                 SELECT 'highlight', id, doc_slug, anchor_id, selected_text, context_text, created_at
+                 FROM doc_highlights
+                 WHERE project_id = ?1
+             ) a
+             WHERE (?2 IS NULL OR EXISTS (
+                     SELECT 1 FROM annotation_folder_items f
+                     WHERE f.folder_id = ?2 AND f.kind = a.kind AND f.item_id = a.id))
+               AND (?3 IS NULL OR EXISTS (
+                     SELECT 1 FROM annotation_tag_items t
+                     WHERE t.tag_id = ?3 AND t.kind = a.kind AND t.item_id = a.id))
+             ORDER BY updated_at DESC, kind, id DESC",
+        )
+        .map_err(|e| e.to_string())?;
+    let rows = stmt
+        .query_map(params![project_id, folder_id, tag_id], |row| {
+            Ok(AnnotationListItem {
+                kind: row.get(0)?,
+                id: row.get(1)?,
+                doc_slug: row.get(2)?,
+                anchor_id: row.get(3)?,
+                text: row.get(4)?,
+                context_text: row.get(5)?,
+                updated_at: row.get(6)?,
+                folder_ids: vec![],
+                tag_ids: vec![],
+            })
+        })
+        .map_err(|e| e.to_string())?;
+    let mut items = rows
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(|e| e.to_string())?;
+    let mut folders =
+        project_annotation_relations(&conn, AnnotationRelation::Folders, &project_id)?;
+    let mut tags = project_annotation_relations(&conn, AnnotationRelation::Tags, &project_id)?;
+    for item in &mut items {
+        let key = (item.kind.clone(), item.id);
+        item.folder_ids = folders.remove(&key).unwrap_or_default();
+        item.tag_ids = tags.remove(&key).unwrap_or_default();
+    }
+    Ok(items)
+}
+
+/// Turn a project's highlights (or one document's) into flashcards and save
+/// them where the user picks, as Anki TSV (`format` "tsv") or a JSON deck
+/// ("json"). With `ai_assisted`, a configured provider rewrites the cards into
+/// cleaner question and answer pairs in one request; if it can't, the plain
+/// cards are saved. Returns None when the save dialog is cancelled.
+#[tauri::command]
+pub async fn export_highlights_flashcards(
+    app: AppHandle,
+    http_client: State<'_, HttpClient>,
+    project_id: String,
+    doc_slug: Option<String>,
+    format: String,
+    ai_assisted: Option<bool>,
+) -> Result<Option<FlashcardExport>, String> {
+    let format = flashcards::DeckFormat::parse(&format)?;
+    let highlights = {
+        let user_state = app.state::<UserStateDb>();
+        let conn = lock_user_state(&user_state)?;
+        let mut stmt = conn
+            .prepare_cached(
+                "SELECT id, project_id, doc_slug, anchor_id, selected_text, context_text, created_at
+                 FROM doc_highlights
+                 WHERE project_id = ?1 AND (?2 IS NULL OR doc_slug = ?2)
+                 ORDER BY doc_slug, created_at",
+            )
+            .map_err(|e| e.to_string())?;
+        let rows = stmt
+            .query_map(params![project_id, doc_slug], highlight_from_row)
+            .map_err(|e| e.to_string())?;
+        rows.collect::<Result<Vec<_>, _>>()
+            .map_err(|e| e.to_string())?
+    };
+    if highlights.is_empty() {
+        return Err("There are no highlights to export".to_string());
+    }
+
+    let (mut cards, deck_name, local_only) = {
+        let manager = app.state::<std::sync::Mutex<ProjectManager>>();
+        let mgr = lock_manager(&manager)?;
+        let conn = mgr.connection(&project_id)?;
+        let mut documents: HashMap<&str, (String, Vec<DocumentAnchor>)> = HashMap::new();
+        let mut cards = Vec::with_capacity(highlights.len());
+        for highlight in &highlights {
+            if !documents.contains_key(highlight.doc_slug.as_str()) {
+                let title: Option<String> = conn
+                    .query_row(
+                        "SELECT title FROM documents WHERE slug = ?1",
+                        params![highlight.doc_slug],
+                        |row| row.get(0),
+                    )
+                    .optional()
+                    .map_err(|e| e.to_string())?;
+                let anchors = match document_id_for_slug(conn, &highlight.doc_slug) {
+                    Ok(Some(id)) => queries::document_anchors(conn, id).unwrap_or_default(),
+                    _ => vec![],
+                };
+                documents.insert(
+                    &highlight.doc_slug,
+                    (title.unwrap_or_else(|| highlight.doc_slug.clone()), anchors),
+                );
+            }
+            let (title, anchors) = &documents[highlight.doc_slug.as_str()];
+            let heading = highlight.anchor_id.as_deref().and_then(|anchor_id| {
+                anchors
+                    .iter()
+                    .find(|anchor| anchor.anchor_id == anchor_id)
+                    .map(|anchor| anchor.heading_text.as_str())
+            });
+            cards.push(flashcards::card(highlight, heading, title));
+        }
+        let project = mgr.registry.projects.iter().find(|p| p.id == project_id);
+        let deck_name = match (&doc_slug, project) {
+            (Some(slug), _) => documents
+                .get(slug.as_str())
+                .map_or_else(|| slug.clone(), |(title, _)| title.clone()),
+            (None, Some(project)) => project.name.clone(),
+            (None, None) => project_id.clone(),
+        };
+        (cards, deck_name, project.is_some_and(|p| p.local_only))
+    };
+
+    let mut rewritten = 0;
+    if ai_assisted.unwrap_or(false) {
+        let stored = settings::load_settings(&app)?;
+        let provider = resolve_provider(&stored, None, local_only)?;
+        match ai::rewrite_flashcards(&http_client.0, &stored, &provider, &mut cards).await {
+            Ok(count) => rewritten = count,
+            Err(e) => eprintln!(
+                "Warning: failed to rewrite flashcards: {}",
+                ai::redact_secrets(&e, &stored)
+            ),
+        }
+    }
+
+    let (tx, rx) = tokio::sync::oneshot::channel();
+    app.dialog()
+        .file()
+        .set_title("Export flashcards")
+        .set_file_name(format!("{}.{}", deck_name, format.extension()))
+        .add_filter("Flashcards", &[format.extension()])
+        .save_file(move |path| {
+            let _ = tx.send(path);
+        });
+    let Some(path) = rx.await.map_err(|e| e.to_string())? else {
+        return Ok(None);
+    };
+    let path = path.into_path().map_err(|e| e.to_string())?;
+    let contents = match format {
+        flashcards::DeckFormat::Tsv => flashcards::tsv_deck(&cards),
+        flashcards::DeckFormat::Json => flashcards::json_deck(&deck_name, &cards)?,
+    };
+    std::fs::write(&path, contents)
+        .map_err(|e| format!("Failed to write {}: {}", path.display(), e))?;
+
+    Ok(Some(FlashcardExport {
+        path: path.to_string_lossy().to_string(),
+        card_count: cards.len(),
+        ai_assisted: rewritten > 0,
+    }))
+}
+
+#[tauri::command]
+pub fn get_orphaned_user_state(
+    user_state: State<'_, UserStateDb>,
+    project_id: String,
+) -> Result<OrphanedUserState, String> {
+    let conn = user_state.0.lock().map_err(|e| e.to_string())?;
+    let removals = {
+        let mut stmt = conn
+            .prepare_cached(
+                "SELECT doc_slug, removed_at FROM doc_removals
+                 WHERE project_id = ?1
+                 ORDER BY removed_at DESC, doc_slug",
+            )
+            .map_err(|e| e.to_string())?;
+        let rows = stmt
+            .query_map(params![&project_id], |row| {
+                Ok(DocRemoval {
+                    doc_slug: row.get(0)?,
+                    removed_at: row.get(1)?,
+                })
+            })
+            .map_err(|e| e.to_string())?;
+        rows.collect::<Result<Vec<_>, _>>()
+            .map_err(|e| e.to_string())?
+    };
+
+    let bookmarks = {
+        let mut stmt = conn
+            .prepare_cached(
+                "SELECT id, project_id, collection_id, doc_slug, anchor_id, title_snapshot, created_at, updated_at, last_opened_at, order_index, open_count, is_favorite
+                 FROM bookmarks
+                 WHERE project_id = ?1
+                   AND doc_slug IN (SELECT doc_slug FROM doc_removals WHERE project_id = ?1)
+                 ORDER BY updated_at DESC",
+            )
+            .map_err(|e| e.to_string())?;
+        let rows = stmt
+            .query_map(params![&project_id], bookmark_from_row)
+            .map_err(|e| e.to_string())?;
+        rows.collect::<Result<Vec<_>, _>>()
+            .map_err(|e| e.to_string())?
+    };
+
+    let notes = {
+        let mut stmt = conn
+            .prepare_cached(
+                "SELECT project_id, doc_slug, note, updated_at, revision
+                 FROM doc_notes
+                 WHERE project_id = ?1
+                   AND doc_slug IN (SELECT doc_slug FROM doc_removals WHERE project_id = ?1)
+                 ORDER BY updated_at DESC",
+            )
+            .map_err(|e| e.to_string())?;
+        let rows = stmt
+            .query_map(params![&project_id], doc_note_from_row)
+            .map_err(|e| e.to_string())?;
+        rows.collect::<Result<Vec<_>, _>>()
+            .map_err(|e| e.to_string())?
+    };
+
+    let highlights = {
+        let mut stmt = conn
+            .prepare_cached(
+                "SELECT id, project_id, doc_slug, anchor_id, selected_text, context_text, created_at
+                 FROM doc_highlights
+                 WHERE project_id = ?1
+                   AND doc_slug IN (SELECT doc_slug FROM doc_removals WHERE project_id = ?1)
+                 ORDER BY created_at DESC",
+            )
+            .map_err(|e| e.to_string())?;
+        let rows = stmt
+            .query_map(params![&project_id], highlight_from_row)
+            .map_err(|e| e.to_string())?;
+        rows.collect::<Result<Vec<_>, _>>()
+            .map_err(|e| e.to_string())?
+    };
+
+    Ok(OrphanedUserState {
+        removals,
+        bookmarks,
+        notes,
+        highlights,
+    })
+}
+
+/// Move a note to another document. If the target already has a note, the
+/// moved text is appended to it.
+#[tauri::command]
+pub fn repair_doc_note(
+    user_state: State<'_, UserStateDb>,
+    project_id: String,
+    doc_slug: String,
+    new_doc_slug: String,
+) -> Result<DocNote, String> {
+    let now = unix_timestamp_i64();
+    let conn = user_state.0.lock().map_err(|e| e.to_string())?;
+    let tx = conn.unchecked_transaction().map_err(|e| e.to_string())?;
+    let (note_id, note): (i64, String) = tx
+        .query_row(
+            "SELECT id, note FROM doc_notes WHERE project_id = ?1 AND doc_slug = ?2",
+            params![&project_id, &doc_slug],
+            |row| Ok((row.get(0)?, row.get(1)?)),
+        )
+        .optional()
+        .map_err(|e| e.to_string())?
+        .ok_or_else(|| format!("No note found for '{}'", doc_slug))?;
+    let existing: Option<String> = tx
+        .query_row(
+            "SELECT note FROM doc_notes WHERE project_id = ?1 AND doc_slug = ?2",
+            params![&project_id, &new_doc_slug],
+            |row| row.get(0),
+        )
+        .optional()
+        .map_err(|e| e.to_string())?;
+    let merged = match existing.filter(|n| !n.trim().is_empty()) {
+        Some(existing) => format!("{}\n\n{}", existing, note),
+        None => note,
+    };
+
+    tx.execute(
+        "INSERT INTO doc_notes (project_id, doc_slug, note, updated_at)
+         VALUES (?1, ?2, ?3, ?4)
+         ON CONFLICT(project_id, doc_slug)
+         DO UPDATE SET note = excluded.note, updated_at = excluded.updated_at,
+             revision = doc_notes.revision + 1",
+        params![&project_id, &new_doc_slug, &merged, now],
+    )
+    .map_err(|e| e.to_string())?;
+    let moved_id: i64 = tx
+        .query_row(
+            "SELECT id FROM doc_notes WHERE project_id = ?1 AND doc_slug = ?2",
+            params![&project_id, &new_doc_slug],
+            |row| row.get(0),
+        )
+        .map_err(|e| e.to_string())?;
+    if moved_id != note_id {
+        // Carried over before the delete, whose trigger drops the old note's relations
+        for relation in [AnnotationRelation::Folders, AnnotationRelation::Tags] {
+            let ids = annotation_relation_ids(&tx, relation, "note", note_id)?;
+            add_annotation_relations(&tx, relation, &project_id, "note", moved_id, &ids)?;
+        }
+        tx.execute(
+            "DELETE FROM doc_notes WHERE project_id = ?1 AND doc_slug = ?2",
+            params![&project_id, &doc_slug],
+        )
+        .map_err(|e| e.to_string())?;
+    }
+    tx.execute(
+        "UPDATE doc_note_revisions SET doc_slug = ?3 WHERE project_id = ?1 AND doc_slug = ?2",
+        params![&project_id, &doc_slug, &new_doc_slug],
+    )
+    .map_err(|e| e.to_string())?;
+    // A draft already open for the new document wins over the moved one
+    tx.execute(
+        "UPDATE OR IGNORE doc_note_drafts SET doc_slug = ?3 WHERE project_id = ?1 AND doc_slug = ?2",
+        params![&project_id, &doc_slug, &new_doc_slug],
+    )
+    .map_err(|e| e.to_string())?;
+    tx.execute(
+        "DELETE FROM doc_note_drafts WHERE project_id = ?1 AND doc_slug = ?2",
+        params![&project_id, &doc_slug],
+    )
+    .map_err(|e| e.to_string())?;
+    let moved = saved_doc_note(&tx, &project_id, &new_doc_slug)?;
+    tx.commit().map_err(|e| e.to_string())?;
+    Ok(moved)
+}
+
+#[tauri::command]
+pub fn repair_doc_highlight(
+    user_state: State<'_, UserStateDb>,
+    id: i64,
+    doc_slug: String,
+    anchor_id: Option<String>,
+) -> Result<DocHighlight, String> {
+    let conn = user_state.0.lock().map_err(|e| e.to_string())?;
+    let updated = conn
+        .execute(
+            "UPDATE doc_highlights SET doc_slug = ?1, anchor_id = ?2 WHERE id = ?3",
+            params![doc_slug, anchor_id, id],
+        )
+        .map_err(|e| e.to_string())?;
+    if updated == 0 {
+        return Err(format!("Highlight {} not found", id));
+    }
+    conn.query_row(
+        "SELECT id, project_id, doc_slug, anchor_id, selected_text, context_text, created_at
+         FROM doc_highlights WHERE id = ?1",
+        params![id],
+        highlight_from_row,
+    )
+    .map_err(|e| e.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::AnnotationRelation::{Folders, Tags};
+    use super::{
+        annotation_exists, annotation_relation_ids, doc_note_with_draft, import_annotations,
+        project_annotation_relations, restore_revision, save_doc_note_checked,
+        set_annotation_relations,
+    };
+    use crate::models::{AnnotationRef, ExternalAnnotation, UserStateError};
+    use crate::user_state::{apply_user_state_schema, write_doc_note, UserStateDb};
+
+    #[test]
+    fn restoring_a_revision_keeps_the_note_it_replaces() {
+        let conn = rusqlite::Connection::open_in_memory().unwrap();
+        apply_user_state_schema(&conn).unwrap();
+        write_doc_note(&conn, "p", "intro", "first", 1).unwrap();
+        write_doc_note(&conn, "p", "intro", "second", 2).unwrap();
+        // Within the revision interval, so this edit replaces "second" in place
+        write_doc_note(&conn, "p", "intro", "third", 3).unwrap();
+        let first_id: i64 = conn
+            .query_row(
+                "SELECT id FROM doc_note_revisions WHERE note = 'first'",
+                [],
+                |row| row.get(0),
+            )
+            .unwrap();
+
+        let restored = restore_revision(&conn, first_id, 4).unwrap();
+        assert_eq!(restored.note, "first");
+        let mut stmt = conn
+            .prepare("SELECT note FROM doc_note_revisions ORDER BY id DESC")
+            .unwrap();
+        let revisions: Vec<String> = stmt
+            .query_map([], |row| row.get(0))
+            .unwrap()
+            .collect::<Result<_, _>>()
+            .unwrap();
+        assert_eq!(revisions, ["third", "first"]);
+        assert!(restore_revision(&conn, 999, 5).is_err());
+    }
+
+    #[test]
+    fn annotation_relations_stay_within_the_project() {
+        let conn = rusqlite::Connection::open_in_memory().unwrap();
+        conn.execute_batch(
+            "CREATE TABLE bookmark_folders (id INTEGER PRIMARY KEY, project_id TEXT NOT NULL);
+             CREATE TABLE bookmark_tags (id INTEGER PRIMARY KEY, project_id TEXT NOT NULL);
+             CREATE TABLE annotation_folder_items (
+                 folder_id INTEGER NOT NULL, kind TEXT NOT NULL, item_id INTEGER NOT NULL,
+                 PRIMARY KEY(folder_id, kind, item_id));
+             CREATE TABLE annotation_tag_items (
+                 tag_id INTEGER NOT NULL, kind TEXT NOT NULL, item_id INTEGER NOT NULL,
+                 PRIMARY KEY(tag_id, kind, item_id));
+             CREATE TABLE doc_notes (
+                 id INTEGER PRIMARY KEY, project_id TEXT NOT NULL, doc_slug TEXT NOT NULL,
+                 note TEXT NOT NULL, UNIQUE(project_id, doc_slug));
+             CREATE TABLE doc_highlights (id INTEGER PRIMARY KEY, project_id TEXT NOT NULL);
+
+             INSERT INTO bookmark_folders VALUES (1, 'p'), (2, 'p'), (3, 'other');
+             INSERT INTO bookmark_tags VALUES (1, 'p');
+             INSERT INTO doc_notes (project_id, doc_slug, note) VALUES ('p', 'intro', 'Remember this');
+             INSERT INTO doc_highlights VALUES (9, 'p');",
+        )
+        .unwrap();
+        let user_state = UserStateDb(std::sync::Mutex::new(conn));
+        let note = AnnotationRef::Note {
+            project_id: "p".to_string(),
+            doc_slug: "intro".to_string(),
+        };
+
+        set_annotation_relations(&user_state, Folders, &note, &[1, 2, 3, 99]).unwrap();
+        set_annotation_relations(&user_state, Folders, &note, &[2, 3]).unwrap();
+        set_annotation_relations(&user_state, Tags, &AnnotationRef::Highlight { id: 9 }, &[1])
+            .unwrap();
+        assert!(set_annotation_relations(
+            &user_state,
+            Tags,
+            &AnnotationRef::Highlight { id: 10 },
+            &[1]
+        )
+        .is_err());
+
+        let conn = user_state.0.lock().unwrap();
+        let note_id: i64 = conn
+            .query_row("SELECT id FROM doc_notes", [], |row| row.get(0))
+            .unwrap();
+        assert_eq!(
+            annotation_relation_ids(&conn, Folders, "note", note_id).unwrap(),
+            [2]
+        );
+        assert_eq!(
+            annotation_relation_ids(&conn, Tags, "highlight", 9).unwrap(),
+            [1]
+        );
+        let folders = project_annotation_relations(&conn, Folders, "p").unwrap();
+        assert_eq!(folders.len(), 1);
+        assert_eq!(folders[&("note".to_string(), note_id)], [2]);
+        assert!(project_annotation_relations(&conn, Tags, "other")
+            .unwrap()
+            .is_empty());
+        assert!(annotation_exists(&conn, "p", "note", note_id).unwrap());
+        assert!(!annotation_exists(&conn, "other", "highlight", 9).unwrap());
+    }
+
+    #[test]
+    fn saving_clears_the_draft_unless_the_note_changed_elsewhere() {
+        let conn = rusqlite::Connection::open_in_memory().unwrap();
+        apply_user_state_schema(&conn).unwrap();
+        let draft = |text: &str| {
+            conn.execute(
+                "INSERT OR REPLACE INTO doc_note_drafts VALUES ('p', 'intro', ?1, 5)",
+                [text],
+            )
+            .unwrap();
+        };
+
+        draft("typing…");
+        let pending = doc_note_with_draft(&conn, "p", "intro").unwrap();
+        assert!(pending.note.is_none());
+        assert_eq!(pending.draft.unwrap().draft, "typing…");
+
+        let saved = save_doc_note_checked(&conn, "p", "intro", "first", None, 10).unwrap();
+        assert!(doc_note_with_draft(&conn, "p", "intro")
+            .unwrap()
+            .draft
+            .is_none());
+
+        // A draft matching the saved text is nothing to restore
+        draft("first");
+        assert!(doc_note_with_draft(&conn, "p", "intro")
+            .unwrap()
+            .draft
+            .is_none());
+
+        // Another window saves, then this one saves from the version it read,
+        // all within the same second
+        let theirs =
+            save_doc_note_checked(&conn, "p", "intro", "theirs", Some(saved.revision), 10).unwrap();
+        assert_eq!(theirs.revision, saved.revision + 1);
+        draft("mine, unsaved");
+        let conflict = save_doc_note_checked(&conn, "p", "intro", "mine", Some(saved.revision), 10);
+        match conflict {
+            Err(UserStateError::Conflict { current, .. }) => {
+                assert_eq!(current.unwrap().note, "theirs")
+            }
+            other => panic!("expected a conflict, got {:?}", other),
+        }
+        let kept = doc_note_with_draft(&conn, "p", "intro").unwrap();
+        assert_eq!(kept.note.unwrap().note, "theirs");
+        assert_eq!(kept.draft.unwrap().draft, "mine, unsaved");
+
+        // Saving the text that is already stored is not a conflict
+        assert!(
+            save_doc_note_checked(&conn, "p", "intro", "theirs", Some(saved.revision), 40).is_ok()
+        );
+    }
+
+    #[test]
+    fn external_annotations_import_once_and_unmatched_ones_are_returned() {
+        let conn = rusqlite::Connection::open_in_memory().unwrap();
+        apply_user_state_schema(&conn).unwrap();
+        conn.execute(
+            "INSERT INTO doc_notes (project_id, doc_slug, note, updated_at)
+                 VALUES ('p', 'ops/deploys', 'My own note', 1)",
+            [],
+        )
+        .unwrap();
+        let documents = vec![
+            ("ops/deploys".to_string(), "Deploys".to_string()),
+            ("ops/restarts".to_string(), "Restarts".to_string()),
+        ];
+        let item = |url: &str, text: Option<&str>, note: Option<&str>| ExternalAnnotation {
+            source: "readwise".to_string(),
+            title: None,
+            url: Some(url.to_string()),
+            text: text.map(str::to_string),
+            note: note.map(str::to_string),
+            context_text: None,
+        };
+        let export = || {
+            vec![
+                item(
+                    "https://x.test/ops/deploys",
+                    Some("Roll back first"),
+                    Some("Not in March"),
+                ),
+                item("https://x.test/ops/deploys", None, Some("Ask the on-call")),
+                item("https://x.test/ops/restarts", Some("Page first"), None),
+                item("https://x.test/elsewhere", Some("Unrelated"), None),
+            ]
+        };
+
+        let first = import_annotations(&conn, "p", export(), &documents, 5).unwrap();
+        assert_eq!((first.matched, first.unmatched), (3, 1));
+        assert_eq!((first.highlights_added, first.notes_added), (2, 2));
+        assert_eq!(
+            first.unmatched_entries[0].text.as_deref(),
+            Some("Unrelated")
+        );
+        let note: String = conn
+            .query_row(
+                "SELECT note FROM doc_notes WHERE doc_slug = 'ops/deploys'",
+                [],
+                |row| row.get(0),
+            )
+            .unwrap();
+        assert_eq!(note, "My own note\n\nNot in March\n\nAsk the on-call");
+
+        let again = import_annotations(&conn, "p", export(), &documents, 6).unwrap();
+        assert_eq!((again.highlights_added, again.notes_added), (0, 0));
+        assert_eq!(again.duplicates_skipped, 4);
+        let revisions: i64 = conn
+            .query_row("SELECT COUNT(*) FROM doc_note_revisions", [], |row| {
+                row.get(0)
+            })
+            .unwrap();
+        assert_eq!(revisions, 1);
+    }
+}
@@ -0,0 +1,1610 @@
+//! Bookmarks with their folders, tags and relations, bulk edits, and the
+//! trash that deleted bookmarks go through.
+
+use super::annotations::{add_annotation_relations, annotation_exists, AnnotationRelation};
+use super::search::document_id_for_slug;
+use super::util::{
+    bookmark_from_row, folder_from_row, lock_manager, lock_user_state, tag_from_row,
+    unix_timestamp_i64,
+};
+use crate::models::*;
+use crate::projects::ProjectManager;
+use crate::queries;
+use crate::snippet::text_similarity;
+use crate::user_state::{with_busy_retry, UserStateDb};
+use rusqlite::{params, OptionalExtension};
+use tauri::State;
+
+#[tauri::command]
+pub fn list_bookmark_folders(
+    user_state: State<'_, UserStateDb>,
+    project_id: String,
+) -> Result<Vec<BookmarkFolder>, String> {
+    let conn = user_state.0.lock().map_err(|e| e.to_string())?;
+    let mut stmt = conn
+        .prepare_cached(
+            "SELECT id, project_id, name, created_at, updated_at
+             FROM bookmark_folders
+             WHERE project_id = ?1
+             ORDER BY name COLLATE NOCASE ASC",
+        )
+        .map_err(|e| e.to_string())?;
+    let rows = stmt
+        .query_map(params![project_id], folder_from_row)
+        .map_err(|e| e.to_string())?;
+    rows.collect::<Result<Vec<_>, _>>()
+        .map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+pub fn create_bookmark_folder(
+    user_state: State<'_, UserStateDb>,
+    project_id: String,
+    name: String,
+) -> Result<BookmarkFolder, String> {
+    let trimmed = name.trim();
+    if trimmed.is_empty() {
+        return Err("Folder name cannot be empty".to_string());
+    }
+
+    let now = unix_timestamp_i64();
+    let conn = user_state.0.lock().map_err(|e| e.to_string())?;
+    conn.execute(
+        "INSERT INTO bookmark_folders (project_id, name, created_at, updated_at)
+         VALUES (?1, ?2, ?3, ?4)",
+        params![project_id, trimmed, now, now],
+    )
+    .map_err(|e| e.to_string())?;
+    let id = conn.last_insert_rowid();
+    conn.query_row(
+        "SELECT id, project_id, name, created_at, updated_at
+         FROM bookmark_folders WHERE id = ?1",
+        params![id],
+        folder_from_row,
+    )
+    .map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+pub fn delete_bookmark_folder(
+    user_state: State<'_, UserStateDb>,
+    folder_id: i64,
+) -> Result<(), String> {
+    let conn = user_state.0.lock().map_err(|e| e.to_string())?;
+    let tx = conn.unchecked_transaction().map_err(|e| e.to_string())?;
+    let folder = tx
+        .query_row(
+            "SELECT id, project_id, name, created_at, updated_at
+             FROM bookmark_folders WHERE id = ?1",
+            params![folder_id],
+            folder_from_row,
+        )
+        .optional()
+        .map_err(|e| e.to_string())?;
+    if let Some(folder) = folder {
+        let bookmark_ids = query_ids(
+            &tx,
+            "SELECT bookmark_id FROM bookmark_folder_items WHERE folder_id = ?1",
+            folder_id,
+        )?;
+        let annotations = trashed_annotations(
+            &tx,
+            "SELECT kind, item_id FROM annotation_folder_items WHERE folder_id = ?1",
+            folder_id,
+        )?;
+        let project_id = folder.project_id.clone();
+        move_to_trash(
+            &tx,
+            &project_id,
+            &TrashPayload::BookmarkFolder {
+                folder,
+                bookmark_ids,
+                annotations,
+            },
+        )?;
+        tx.execute(
+            "DELETE FROM bookmark_folders WHERE id = ?1",
+            params![folder_id],
+        )
+        .map_err(|e| e.to_string())?;
+    }
+    tx.commit().map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+pub fn list_bookmark_tags(
+    user_state: State<'_, UserStateDb>,
+    project_id: String,
+) -> Result<Vec<BookmarkTagEntity>, String> {
+    let conn = user_state.0.lock().map_err(|e| e.to_string())?;
+    let mut stmt = conn
+        .prepare_cached(
+            "SELECT id, project_id, name, created_at, updated_at
+             FROM bookmark_tags
+             WHERE project_id = ?1
+             ORDER BY name COLLATE NOCASE ASC",
+        )
+        .map_err(|e| e.to_string())?;
+    let rows = stmt
+        .query_map(params![project_id], tag_from_row)
+        .map_err(|e| e.to_string())?;
+    rows.collect::<Result<Vec<_>, _>>()
+        .map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+pub fn create_bookmark_tag(
+    user_state: State<'_, UserStateDb>,
+    project_id: String,
+    name: String,
+) -> Result<BookmarkTagEntity, String> {
+    let trimmed = name.trim();
+    if trimmed.is_empty() {
+        return Err("Tag name cannot be empty".to_string());
+    }
+
+    let now = unix_timestamp_i64();
+    let conn = user_state.0.lock().map_err(|e| e.to_string())?;
+
+    let existing: Option<BookmarkTagEntity> = conn
+        .query_row(
+            "SELECT id, project_id, name, created_at, updated_at
+             FROM bookmark_tags
+             WHERE project_id = ?1 AND name = ?2
+             LIMIT 1",
+            params![&project_id, trimmed],
+            tag_from_row,
+        )
+        .optional()
+        .map_err(|e| e.to_string())?;
+
+    if let Some(tag) = existing {
+        return Ok(tag);
+    }
+
+    conn.execute(
+        "INSERT INTO bookmark_tags (project_id, name, created_at, updated_at)
+         VALUES (?1, ?2, ?3, ?4)",
+        params![project_id, trimmed, now, now],
+    )
+    .map_err(|e| e.to_string())?;
+    let id = conn.last_insert_rowid();
+    conn.query_row(
+        "SELECT id, project_id, name, created_at, updated_at
+         FROM bookmark_tags WHERE id = ?1",
+        params![id],
+        tag_from_row,
+    )
+    .map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+pub fn delete_bookmark_tag(user_state: State<'_, UserStateDb>, tag_id: i64) -> Result<(), String> {
+    let conn = user_state.0.lock().map_err(|e| e.to_string())?;
+    let tx = conn.unchecked_transaction().map_err(|e| e.to_string())?;
+    let tag = tx
+        .query_row(
+            "SELECT id, project_id, name, created_at, updated_at
+             FROM bookmark_tags WHERE id = ?1",
+            params![tag_id],
+            tag_from_row,
+        )
+        .optional()
+        .map_err(|e| e.to_string())?;
+    if let Some(tag) = tag {
+        let bookmark_ids = query_ids(
+            &tx,
+            "SELECT bookmark_id FROM bookmark_tag_items WHERE tag_id = ?1",
+            tag_id,
+        )?;
+        let annotations = trashed_annotations(
+            &tx,
+            "SELECT kind, item_id FROM annotation_tag_items WHERE tag_id = ?1",
+            tag_id,
+        )?;
+        let project_id = tag.project_id.clone();
+        move_to_trash(
+            &tx,
+            &project_id,
+            &TrashPayload::BookmarkTag {
+                tag,
+                bookmark_ids,
+                annotations,
+            },
+        )?;
+        tx.execute("DELETE FROM bookmark_tags WHERE id = ?1", params![tag_id])
+            .map_err(|e| e.to_string())?;
+    }
+    tx.commit().map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+pub fn list_bookmark_relations(
+    user_state: State<'_, UserStateDb>,
+    project_id: String,
+) -> Result<Vec<BookmarkRelations>, String> {
+    let conn = user_state.0.lock().map_err(|e| e.to_string())?;
+
+    let mut bookmark_stmt = conn
+        .prepare_cached("SELECT id FROM bookmarks WHERE project_id = ?1")
+        .map_err(|e| e.to_string())?;
+    let bookmark_ids = bookmark_stmt
+        .query_map(params![&project_id], |row| row.get::<_, i64>(0))
+        .map_err(|e| e.to_string())?
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(|e| e.to_string())?;
+
+    let mut folder_stmt = conn
+        .prepare_cached(
+            "SELECT bfi.bookmark_id, bfi.folder_id
+             FROM bookmark_folder_items bfi
+             JOIN bookmarks b ON b.id = bfi.bookmark_id
+             WHERE b.project_id = ?1",
+        )
+        .map_err(|e| e.to_string())?;
+    let folder_pairs = folder_stmt
+        .query_map(params![&project_id], |row| {
+            Ok((row.get::<_, i64>(0)?, row.get::<_, i64>(1)?))
+        })
+        .map_err(|e| e.to_string())?
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(|e| e.to_string())?;
+
+    let mut tag_stmt = conn
+        .prepare_cached(
+            "SELECT bti.bookmark_id, bti.tag_id
+             FROM bookmark_tag_items bti
+             JOIN bookmarks b ON b.id = bti.bookmark_id
+             WHERE b.project_id = ?1",
+        )
+        .map_err(|e| e.to_string())?;
+    let tag_pairs = tag_stmt
+        .query_map(params![&project_id], |row| {
+            Ok((row.get::<_, i64>(0)?, row.get::<_, i64>(1)?))
+        })
+        .map_err(|e| e.to_string())?
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(|e| e.to_string())?;
+
+    let mut by_bookmark: std::collections::HashMap<i64, BookmarkRelations> = bookmark_ids
+        .into_iter()
+        .map(|id| {
+            (
+                id,
+                BookmarkRelations {
+                    bookmark_id: id,
+                    folder_ids: vec![],
+                    tag_ids: vec![],
+                },
+            )
+        })
+        .collect();
+
+    for (bookmark_id, folder_id) in folder_pairs {
+        if let Some(entry) = by_bookmark.get_mut(&bookmark_id) {
+            entry.folder_ids.push(folder_id);
+        }
+    }
+
+    for (bookmark_id, tag_id) in tag_pairs {
+        if let Some(entry) = by_bookmark.get_mut(&bookmark_id) {
+            entry.tag_ids.push(tag_id);
+        }
+    }
+
+    Ok(by_bookmark.into_values().collect())
+}
+
+#[tauri::command]
+pub fn bulk_delete_bookmarks(
+    user_state: State<'_, UserStateDb>,
+    project_id: String,
+    bookmark_ids: Vec<i64>,
+) -> Result<i64, String> {
+    if bookmark_ids.is_empty() {
+        return Ok(0);
+    }
+    let conn = user_state.0.lock().map_err(|e| e.to_string())?;
+    let tx = conn.unchecked_transaction().map_err(|e| e.to_string())?;
+    let mut trashed = Vec::new();
+    for bookmark_id in &bookmark_ids {
+        if let Some(captured) = capture_bookmark(&tx, &project_id, *bookmark_id)? {
+            trashed.push(captured);
+        }
+    }
+    if trashed.is_empty() {
+        return Ok(0);
+    }
+    move_to_trash(
+        &tx,
+        &project_id,
+        &TrashPayload::Bookmarks { bookmarks: trashed },
+    )?;
+
+    let mut deleted = 0;
+    for bookmark_id in bookmark_ids {
+        let affected = tx
+            .execute(
+                "DELETE FROM bookmarks WHERE id = ?1 AND project_id = ?2",
+                params![bookmark_id, &project_id],
+            )
+            .map_err(|e| e.to_string())?;
+        deleted += affected as i64;
+    }
+    tx.commit().map_err(|e| e.to_string())?;
+    Ok(deleted)
+}
+
+#[tauri::command]
+pub fn bulk_set_bookmark_folder(
+    user_state: State<'_, UserStateDb>,
+    project_id: String,
+    bookmark_ids: Vec<i64>,
+    folder_id: Option<i64>,
+) -> Result<(), String> {
+    let conn = user_state.0.lock().map_err(|e| e.to_string())?;
+
+    if let Some(fid) = folder_id {
+        let exists: Option<i64> = conn
+            .query_row(
+                "SELECT id FROM bookmark_folders WHERE id = ?1 AND project_id = ?2 LIMIT 1",
+                params![fid, &project_id],
+                |row| row.get(0),
+            )
+            .optional()
+            .map_err(|e| e.to_string())?;
+        if exists.is_none() {
+            return Err("Folder does not exist for this project".to_string());
+        }
+    }
+
+    for bookmark_id in bookmark_ids {
+        conn.execute(
+            "DELETE FROM bookmark_folder_items WHERE bookmark_id = ?1",
+            params![bookmark_id],
+        )
+        .map_err(|e| e.to_string())?;
+
+        if let Some(fid) = folder_id {
+            let belongs_to_project: Option<i64> = conn
+                .query_row(
+                    "SELECT id FROM bookmarks WHERE id = ?1 AND project_id = ?2 LIMIT 1",
+                    params![bookmark_id, &project_id],
+                    |row| row.get(0),
+                )
+                .optional()
+                .map_err(|e| e.to_string())?;
+            if belongs_to_project.is_some() {
+                conn.execute(
+                    "INSERT OR IGNORE INTO bookmark_folder_items (folder_id, bookmark_id)
+                     VALUES (?1, ?2)",
+                    params![fid, bookmark_id],
+                )
+                .map_err(|e| e.to_string())?;
+            }
+        }
+    }
+
+    Ok(())
+}
+
+#[tauri::command]
+pub fn bulk_set_bookmark_tags(
+    user_state: State<'_, UserStateDb>,
+    project_id: String,
+    bookmark_ids: Vec<i64>,
+    tag_ids: Vec<i64>,
+) -> Result<(), String> {
+    let conn = user_state.0.lock().map_err(|e| e.to_string())?;
+
+    for tag_id in &tag_ids {
+        let exists: Option<i64> = conn
+            .query_row(
+                "SELECT id FROM bookmark_tags WHERE id = ?1 AND project_id = ?2 LIMIT 1",
+                params![tag_id, &project_id],
+                |row| row.get(0),
+            )
+            .optional()
+            .map_err(|e| e.to_string())?;
+        if exists.is_none() {
+            return Err(format!("Tag {} does not exist for this project", tag_id));
+        }
+    }
+
+    for bookmark_id in bookmark_ids {
+        conn.execute(
+            "DELETE FROM bookmark_tag_items WHERE bookmark_id = ?1",
+            params![bookmark_id],
+        )
+        .map_err(|e| e.to_string())?;
+
+        let belongs_to_project: Option<i64> = conn
+            .query_row(
+                "SELECT id FROM bookmarks WHERE id = ?1 AND project_id = ?2 LIMIT 1",
+                params![bookmark_id, &project_id],
+                |row| row.get(0),
+            )
+            .optional()
+            .map_err(|e| e.to_string())?;
+        if belongs_to_project.is_none() {
+            continue;
+        }
+
+        for tag_id in &tag_ids {
+            conn.execute(
+                "INSERT OR IGNORE INTO bookmark_tag_items (tag_id, bookmark_id)
+                 VALUES (?1, ?2)",
+                params![tag_id, bookmark_id],
+            )
+            .map_err(|e| e.to_string())?;
+        }
+    }
+
+    Ok(())
+}
+
+#[tauri::command]
+pub fn bulk_set_bookmark_favorite(
+    user_state: State<'_, UserStateDb>,
+    project_id: String,
+    bookmark_ids: Vec<i64>,
+    is_favorite: bool,
+) -> Result<i64, UserStateError> {
+    set_bookmarks_favorite(
+        &user_state,
+        &project_id,
+        &bookmark_ids,
+        is_favorite,
+        unix_timestamp_i64(),
+    )
+}
+
+/// Favorite or un-favorite bookmarks of `project_id` in one transaction,
+/// recording an event for each. Every id must be one of the project's
+/// bookmarks. Bookmarks already in that state are left alone; returns how
+/// many changed.
+fn set_bookmarks_favorite(
+    user_state: &UserStateDb,
+    project_id: &str,
+    bookmark_ids: &[i64],
+    is_favorite: bool,
+    now: i64,
+) -> Result<i64, UserStateError> {
+    if bookmark_ids.is_empty() {
+        return Ok(0);
+    }
+    let sql = format!(
+        "SELECT id FROM bookmarks WHERE project_id = ? AND id IN ({})",
+        vec!["?"; bookmark_ids.len()].join(", ")
+    );
+    let mut params: Vec<rusqlite::types::Value> = vec![project_id.to_string().into()];
+    params.extend(bookmark_ids.iter().map(|id| (*id).into()));
+    with_busy_retry(
+        || lock_user_state(user_state),
+        |conn| {
+            let owned = conn
+                .prepare(&sql)?
+                .query_map(rusqlite::params_from_iter(&params), |row| {
+                    row.get::<_, i64>(0)
+                })?
+                .collect::<rusqlite::Result<std::collections::HashSet<i64>>>()?;
+            if let Some(missing) = bookmark_ids.iter().find(|id| !owned.contains(id)) {
+                return Ok(Err(format!(
+                    "Bookmark {} does not exist for this project",
+                    missing
+                )));
+            }
+
+            let mut changed = 0;
+            for bookmark_id in bookmark_ids {
+                let updated = conn.execute(
+                    "UPDATE bookmarks
+                 SET is_favorite = ?1, updated_at = ?2
+                 WHERE id = ?3 AND is_favorite != ?1",
+                    params![is_favorite as i64, now, bookmark_id],
+                )?;
+                if updated == 0 {
+                    continue;
+                }
+                conn.execute(
+                    "INSERT INTO bookmark_events (bookmark_id, event_type, created_at)
+                 VALUES (?1, ?2, ?3)",
+                    params![
+                        bookmark_id,
+                        if is_favorite {
+                            "favorited"
+                        } else {
+                            "unfavorited"
+                        },
+                        now
+                    ],
+                )?;
+                changed += 1;
+            }
+            Ok(Ok(changed))
+        },
+    )?
+    .map_err(UserStateError::from)
+}
+
+const TRASH_LABEL_CHARS: usize = 80;
+
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
+pub(super) struct TrashedBookmark {
+    pub(super) bookmark: Bookmark,
+    pub(super) folder_ids: Vec<i64>,
+    pub(super) tag_ids: Vec<i64>,
+    /// (event_type, created_at) of its history in `bookmark_events`
+    #[serde(default)]
+    pub(super) events: Vec<(String, i64)>,
+}
+
+/// Rows captured by a delete command, enough to rebuild them on restore.
+#[derive(Debug, serde::Serialize, serde::Deserialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub(super) enum TrashPayload {
+    Bookmarks {
+        bookmarks: Vec<TrashedBookmark>,
+    },
+    BookmarkFolder {
+        folder: BookmarkFolder,
+        bookmark_ids: Vec<i64>,
+        #[serde(default)]
+        annotations: Vec<TrashedAnnotation>,
+    },
+    BookmarkTag {
+        tag: BookmarkTagEntity,
+        bookmark_ids: Vec<i64>,
+        #[serde(default)]
+        annotations: Vec<TrashedAnnotation>,
+    },
+    DocHighlight {
+        highlight: DocHighlight,
+        #[serde(default)]
+        folder_ids: Vec<i64>,
+        #[serde(default)]
+        tag_ids: Vec<i64>,
+    },
+}
+
+/// A note or highlight that a trashed folder or tag was assigned to.
+#[derive(Debug, serde::Serialize, serde::Deserialize)]
+pub(super) struct TrashedAnnotation {
+    kind: String,
+    item_id: i64,
+}
+
+/// Display metadata stored alongside a payload so listing the trash never
+/// has to deserialize every row.
+#[derive(Debug, Default, serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+struct TrashRestoreHints {
+    label: String,
+    item_count: i64,
+    original_ids: Vec<i64>,
+}
+
+impl TrashPayload {
+    fn kind(&self) -> &'static str {
+        match self {
+            TrashPayload::Bookmarks { .. } => "bookmarks",
+            TrashPayload::BookmarkFolder { .. } => "bookmark_folder",
+            TrashPayload::BookmarkTag { .. } => "bookmark_tag",
+            TrashPayload::DocHighlight { .. } => "doc_highlight",
+        }
+    }
+
+    fn restore_hints(&self) -> TrashRestoreHints {
+        match self {
+            TrashPayload::Bookmarks { bookmarks } => TrashRestoreHints {
+                label: match bookmarks.as_slice() {
+                    [single] => single.bookmark.title_snapshot.clone(),
+                    _ => format!("{} bookmarks", bookmarks.len()),
+                },
+                item_count: bookmarks.len() as i64,
+                original_ids: bookmarks.iter().map(|b| b.bookmark.id).collect(),
+            },
+            TrashPayload::BookmarkFolder {
+                folder,
+                bookmark_ids,
+                annotations,
+            } => TrashRestoreHints {
+                label: folder.name.clone(),
+                item_count: (bookmark_ids.len() + annotations.len()) as i64,
+                original_ids: vec![folder.id],
+            },
+            TrashPayload::BookmarkTag {
+                tag,
+                bookmark_ids,
+                annotations,
+            } => TrashRestoreHints {
+                label: tag.name.clone(),
+                item_count: (bookmark_ids.len() + annotations.len()) as i64,
+                original_ids: vec![tag.id],
+            },
+            TrashPayload::DocHighlight { highlight, .. } => TrashRestoreHints {
+                label: highlight
+                    .selected_text
+                    .chars()
+                    .take(TRASH_LABEL_CHARS)
+                    .collect(),
+                item_count: 1,
+                original_ids: vec![highlight.id],
+            },
+        }
+    }
+}
+
+fn query_ids(conn: &rusqlite::Connection, sql: &str, id: i64) -> Result<Vec<i64>, String> {
+    let mut stmt = conn.prepare_cached(sql).map_err(|e| e.to_string())?;
+    let rows = stmt
+        .query_map(params![id], |row| row.get::<_, i64>(0))
+        .map_err(|e| e.to_string())?;
+    rows.collect::<Result<Vec<_>, _>>()
+        .map_err(|e| e.to_string())
+}
+
+fn trashed_annotations(
+    conn: &rusqlite::Connection,
+    sql: &str,
+    id: i64,
+) -> Result<Vec<TrashedAnnotation>, String> {
+    let mut stmt = conn.prepare_cached(sql).map_err(|e| e.to_string())?;
+    let rows = stmt
+        .query_map(params![id], |row| {
+            Ok(TrashedAnnotation {
+                kind: row.get(0)?,
+                item_id: row.get(1)?,
+            })
+        })
+        .map_err(|e| e.to_string())?;
+    rows.collect::<Result<Vec<_>, _>>()
+        .map_err(|e| e.to_string())
+}
+
+/// Reassign a restored folder or tag to the notes and highlights it had that
+/// still exist.
+fn restore_annotation_relations(
+    conn: &rusqlite::Connection,
+    relation: AnnotationRelation,
+    project_id: &str,
+    id: i64,
+    annotations: &[TrashedAnnotation],
+) -> Result<(), String> {
+    for annotation in annotations {
+        let item_id = remapped_id(conn, project_id, &annotation.kind, annotation.item_id)?;
+        if annotation_exists(conn, project_id, &annotation.kind, item_id)? {
+            add_annotation_relations(conn, relation, project_id, &annotation.kind, item_id, &[id])?;
+        }
+    }
+    Ok(())
+}
+
+/// Returns the original id when no live row holds it, so restores keep
+/// external references (deep links, relations) intact where possible.
+fn reusable_id(conn: &rusqlite::Connection, table: &str, id: i64) -> Result<Option<i64>, String> {
+    let taken: Option<i64> = conn
+        .query_row(
+            &format!("SELECT id FROM {} WHERE id = ?1", table),
+            params![id],
+            |row| row.get(0),
+        )
+        .optional()
+        .map_err(|e| e.to_string())?;
+    Ok(if taken.is_some() { None } else { Some(id) })
+}
+
+/// Note that a restored row of `kind` ("bookmark", "folder", "tag" or
+/// "highlight") lives at `current_id` rather than the `original_id` other
+/// trashed payloads refer to it by.
+fn record_remap(
+    conn: &rusqlite::Connection,
+    project_id: &str,
+    kind: &str,
+    original_id: i64,
+    current_id: i64,
+) -> Result<(), String> {
+    if original_id == current_id {
+        return Ok(());
+    }
+    conn.execute(
+        "INSERT OR REPLACE INTO trash_id_remaps (project_id, kind, original_id, current_id)
+         VALUES (?1, ?2, ?3, ?4)",
+        params![project_id, kind, original_id, current_id],
+    )
+    .map_err(|e| e.to_string())?;
+    Ok(())
+}
+
+/// The id a row recorded in a trashed payload lives at now, following
+/// restores that moved it.
+fn remapped_id(
+    conn: &rusqlite::Connection,
+    project_id: &str,
+    kind: &str,
+    id: i64,
+) -> Result<i64, String> {
+    let mut current = id;
+    // Bounded so a cycle left by id reuse can't loop forever
+    for _ in 0..8 {
+        let next: Option<i64> = conn
+            .query_row(
+                "SELECT current_id FROM trash_id_remaps
+                 WHERE project_id = ?1 AND kind = ?2 AND original_id = ?3",
+                params![project_id, kind, current],
+                |row| row.get(0),
+            )
+            .optional()
+            .map_err(|e| e.to_string())?;
+        match next {
+            Some(next) if next != id => current = next,
+            _ => break,
+        }
+    }
+    Ok(current)
+}
+
+pub(super) fn capture_bookmark(
+    conn: &rusqlite::Connection,
+    project_id: &str,
+    bookmark_id: i64,
+) -> Result<Option<TrashedBookmark>, String> {
+    let bookmark = conn
+        .query_row(
+            "SELECT id, project_id, collection_id, doc_slug, anchor_id, title_snapshot, created_at, updated_at, last_opened_at, order_index, open_count, is_favorite
+             FROM bookmarks WHERE id = ?1 AND project_id = ?2",
+            params![bookmark_id, project_id],
+            bookmark_from_row,
+        )
+        .optional()
+        .map_err(|e| e.to_string())?;
+    let Some(bookmark) = bookmark else {
+        return Ok(None);
+    };
+    let folder_ids = query_ids(
+        conn,
+        "SELECT folder_id FROM bookmark_folder_items WHERE bookmark_id = ?1",
+        bookmark_id,
+    )?;
+    let tag_ids = query_ids(
+        conn,
+        "SELECT tag_id FROM bookmark_tag_items WHERE bookmark_id = ?1",
+        bookmark_id,
+    )?;
+    let mut stmt = conn
+        .prepare_cached(
+            "SELECT event_type, created_at FROM bookmark_events WHERE bookmark_id = ?1 ORDER BY id",
+        )
+        .map_err(|e| e.to_string())?;
+    let events = stmt
+        .query_map(params![bookmark_id], |row| Ok((row.get(0)?, row.get(1)?)))
+        .map_err(|e| e.to_string())?
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(|e| e.to_string())?;
+    Ok(Some(TrashedBookmark {
+        bookmark,
+        folder_ids,
+        tag_ids,
+        events,
+    }))
+}
+
+pub(super) fn move_to_trash(
+    conn: &rusqlite::Connection,
+    project_id: &str,
+    payload: &TrashPayload,
+) -> Result<i64, String> {
+    let payload_json = serde_json::to_string(payload).map_err(|e| e.to_string())?;
+    let hints_json = serde_json::to_string(&payload.restore_hints()).map_err(|e| e.to_string())?;
+    conn.execute(
+        "INSERT INTO deleted_items (project_id, kind, payload_json, restore_hints_json, deleted_at)
+         VALUES (?1, ?2, ?3, ?4, ?5)",
+        params![
+            project_id,
+            payload.kind(),
+            payload_json,
+            hints_json,
+            unix_timestamp_i64()
+        ],
+    )
+    .map_err(|e| e.to_string())?;
+    Ok(conn.last_insert_rowid())
+}
+
+fn deleted_item_from_row(row: &rusqlite::Row<'_>) -> rusqlite::Result<DeletedItem> {
+    let hints_json: String = row.get(3)?;
+    let hints = serde_json::from_str::<TrashRestoreHints>(&hints_json).unwrap_or_default();
+    Ok(DeletedItem {
+        id: row.get(0)?,
+        project_id: row.get(1)?,
+        kind: row.get(2)?,
+        label: hints.label,
+        item_count: hints.item_count,
+        deleted_at: row.get(4)?,
+    })
+}
+
+/// Reinserts a trashed bookmark, reusing its id when free. A bookmark that was
+/// re-created for the same doc/anchor in the meantime absorbs the relations
+/// instead of being duplicated. Returns the id the bookmark now lives at.
+pub(super) fn restore_bookmark(
+    conn: &rusqlite::Connection,
+    trashed: &TrashedBookmark,
+) -> Result<i64, String> {
+    let b = &trashed.bookmark;
+    let existing: Option<i64> = conn
+        .query_row(
+            "SELECT id FROM bookmarks
+             WHERE project_id = ?1 AND doc_slug = ?2 AND anchor_id IS ?3
+             LIMIT 1",
+            params![&b.project_id, &b.doc_slug, &b.anchor_id],
+            |row| row.get(0),
+        )
+        .optional()
+        .map_err(|e| e.to_string())?;
+
+    let bookmark_id = match existing {
+        Some(id) => id,
+        None => {
+            conn.execute(
+                "INSERT INTO bookmarks (
+                    id, project_id, collection_id, doc_slug, anchor_id, title_snapshot,
+                    created_at, updated_at, last_opened_at, order_index, open_count, is_favorite
+                 ) VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12)",
+                params![
+                    reusable_id(conn, "bookmarks", b.id)?,
+                    &b.project_id,
+                    &b.collection_id,
+                    &b.doc_slug,
+                    &b.anchor_id,
+                    &b.title_snapshot,
+                    b.created_at,
+                    b.updated_at,
+                    b.last_opened_at,
+                    b.order_index,
+                    b.open_count,
+                    if b.is_favorite { 1 } else { 0 }
+                ],
+            )
+            .map_err(|e| e.to_string())?;
+            conn.last_insert_rowid()
+        }
+    };
+
+    for folder_id in &trashed.folder_ids {
+        conn.execute(
+            "INSERT OR IGNORE INTO bookmark_folder_items (folder_id, bookmark_id)
+             SELECT id, ?2 FROM bookmark_folders WHERE id = ?1 AND project_id = ?3",
+            params![folder_id, bookmark_id, &b.project_id],
+        )
+        .map_err(|e| e.to_string())?;
+    }
+    for tag_id in &trashed.tag_ids {
+        conn.execute(
+            "INSERT OR IGNORE INTO bookmark_tag_items (tag_id, bookmark_id)
+             SELECT id, ?2 FROM bookmark_tags WHERE id = ?1 AND project_id = ?3",
+            params![tag_id, bookmark_id, &b.project_id],
+        )
+        .map_err(|e| e.to_string())?;
+    }
+    for (event_type, created_at) in &trashed.events {
+        conn.execute(
+            "INSERT INTO bookmark_events (bookmark_id, event_type, created_at) VALUES (?1, ?2, ?3)",
+            params![bookmark_id, event_type, created_at],
+        )
+        .map_err(|e| e.to_string())?;
+    }
+    Ok(bookmark_id)
+}
+
+fn restore_payload(conn: &rusqlite::Connection, payload: &TrashPayload) -> Result<(), String> {
+    match payload {
+        TrashPayload::Bookmarks { bookmarks } => {
+            for trashed in bookmarks {
+                // Folders and tags trashed and restored since come back
+                // under new ids
+                let project_id = &trashed.bookmark.project_id;
+                let remap_all = |kind: &str, ids: &[i64]| {
+                    ids.iter()
+                        .map(|id| remapped_id(conn, project_id, kind, *id))
+                        .collect::<Result<Vec<_>, String>>()
+                };
+                let trashed = TrashedBookmark {
+                    folder_ids: remap_all("folder", &trashed.folder_ids)?,
+                    tag_ids: remap_all("tag", &trashed.tag_ids)?,
+                    ..trashed.clone()
+                };
+                let bookmark_id = restore_bookmark(conn, &trashed)?;
+                record_remap(
+                    conn,
+                    project_id,
+                    "bookmark",
+                    trashed.bookmark.id,
+                    bookmark_id,
+                )?;
+            }
+        }
+        TrashPayload::BookmarkFolder {
+            folder,
+            bookmark_ids,
+            annotations,
+        } => {
+            conn.execute(
+                "INSERT INTO bookmark_folders (id, project_id, name, created_at, updated_at)
+                 VALUES (?1, ?2, ?3, ?4, ?5)",
+                params![
+                    reusable_id(conn, "bookmark_folders", folder.id)?,
+                    &folder.project_id,
+                    &folder.name,
+                    folder.created_at,
+                    folder.updated_at
+                ],
+            )
+            .map_err(|e| e.to_string())?;
+            let folder_id = conn.last_insert_rowid();
+            record_remap(conn, &folder.project_id, "folder", folder.id, folder_id)?;
+            for bookmark_id in bookmark_ids {
+                conn.execute(
+                    "INSERT OR IGNORE INTO bookmark_folder_items (folder_id, bookmark_id)
+                     SELECT ?1, id FROM bookmarks WHERE id = ?2 AND project_id = ?3",
+                    params![
+                        folder_id,
+                        remapped_id(conn, &folder.project_id, "bookmark", *bookmark_id)?,
+                        &folder.project_id
+                    ],
+                )
+                .map_err(|e| e.to_string())?;
+            }
+            restore_annotation_relations(
+                conn,
+                AnnotationRelation::Folders,
+                &folder.project_id,
+                folder_id,
+                annotations,
+            )?;
+        }
+        TrashPayload::BookmarkTag {
+            tag,
+            bookmark_ids,
+            annotations,
+        } => {
+            // Tag names are unique per project, so merge into a same-named tag
+            // created since the delete rather than inserting a duplicate.
+            let existing: Option<i64> = conn
+                .query_row(
+                    "SELECT id FROM bookmark_tags WHERE project_id = ?1 AND name = ?2 LIMIT 1",
+                    params![&tag.project_id, &tag.name],
+                    |row| row.get(0),
+                )
+                .optional()
+                .map_err(|e| e.to_string())?;
+            let tag_id = match existing {
+                Some(id) => id,
+                None => {
+                    conn.execute(
+                        "INSERT INTO bookmark_tags (id, project_id, name, created_at, updated_at)
+                         VALUES (?1, ?2, ?3, ?4, ?5)",
+                        params![
+                            reusable_id(conn, "bookmark_tags", tag.id)?,
+                            &tag.project_id,
+                            &tag.name,
+                            tag.created_at,
+                            tag.updated_at
+                        ],
+                    )
+                    .map_err(|e| e.to_string())?;
+                    conn.last_insert_rowid()
+                }
+            };
+            record_remap(conn, &tag.project_id, "tag", tag.id, tag_id)?;
+            for bookmark_id in bookmark_ids {
+                conn.execute(
+                    "INSERT OR IGNORE INTO bookmark_tag_items (tag_id, bookmark_id)
+                     SELECT ?1, id FROM bookmarks WHERE id = ?2 AND project_id = ?3",
+                    params![
+                        tag_id,
+                        remapped_id(conn, &tag.project_id, "bookmark", *bookmark_id)?,
+                        &tag.project_id
+                    ],
+                )
+                .map_err(|e| e.to_string())?;
+            }
+            restore_annotation_relations(
+                conn,
+                AnnotationRelation::Tags,
+                &tag.project_id,
+                tag_id,
+                annotations,
+            )?;
+        }
+        TrashPayload::DocHighlight {
+            highlight,
+            folder_ids,
+            tag_ids,
+        } => {
+            conn.execute(
+                "INSERT INTO doc_highlights (id, project_id, doc_slug, anchor_id, selected_text, context_text, created_at)
+                 VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7)",
+                params![
+                    reusable_id(conn, "doc_highlights", highlight.id)?,
+                    &highlight.project_id,
+                    &highlight.doc_slug,
+                    &highlight.anchor_id,
+                    &highlight.selected_text,
+                    &highlight.context_text,
+                    highlight.created_at
+                ],
+            )
+            .map_err(|e| e.to_string())?;
+            let id = conn.last_insert_rowid();
+            record_remap(conn, &highlight.project_id, "highlight", highlight.id, id)?;
+            for (relation, kind, ids) in [
+                (AnnotationRelation::Folders, "folder", folder_ids),
+                (AnnotationRelation::Tags, "tag", tag_ids),
+            ] {
+                let ids = ids
+                    .iter()
+                    .map(|relation_id| remapped_id(conn, &highlight.project_id, kind, *relation_id))
+                    .collect::<Result<Vec<_>, _>>()?;
+                add_annotation_relations(
+                    conn,
+                    relation,
+                    &highlight.project_id,
+                    "highlight",
+                    id,
+                    &ids,
+                )?;
+            }
+        }
+    }
+    Ok(())
+}
+
+#[tauri::command]
+pub fn list_deleted_items(
+    user_state: State<'_, UserStateDb>,
+    project_id: String,
+) -> Result<Vec<DeletedItem>, String> {
+    let conn = user_state.0.lock().map_err(|e| e.to_string())?;
+    let mut stmt = conn
+        .prepare_cached(
+            "SELECT id, project_id, kind, restore_hints_json, deleted_at
+             FROM deleted_items
+             WHERE project_id = ?1
+             ORDER BY deleted_at DESC, id DESC",
+        )
+        .map_err(|e| e.to_string())?;
+    let rows = stmt
+        .query_map(params![project_id], deleted_item_from_row)
+        .map_err(|e| e.to_string())?;
+    rows.collect::<Result<Vec<_>, _>>()
+        .map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+pub fn restore_deleted_item(
+    user_state: State<'_, UserStateDb>,
+    id: i64,
+) -> Result<DeletedItem, String> {
+    let conn = user_state.0.lock().map_err(|e| e.to_string())?;
+    let tx = conn.unchecked_transaction().map_err(|e| e.to_string())?;
+    let (item, payload_json) = tx
+        .query_row(
+            "SELECT id, project_id, kind, restore_hints_json, deleted_at, payload_json
+             FROM deleted_items WHERE id = ?1",
+            params![id],
+            |row| Ok((deleted_item_from_row(row)?, row.get::<_, String>(5)?)),
+        )
+        .optional()
+        .map_err(|e| e.to_string())?
+        .ok_or_else(|| format!("Deleted item {} not found", id))?;
+    let payload: TrashPayload = serde_json::from_str(&payload_json)
+        .map_err(|e| format!("Deleted item {} is unreadable: {}", id, e))?;
+
+    restore_payload(&tx, &payload)?;
+    tx.execute("DELETE FROM deleted_items WHERE id = ?1", params![id])
+        .map_err(|e| e.to_string())?;
+    tx.commit().map_err(|e| e.to_string())?;
+    Ok(item)
+}
+
+/// Permanently removes trashed items. Without `older_than_days` the standard
+/// 30-day retention applies; pass 0 to empty the trash.
+#[tauri::command]
+pub fn purge_deleted_items(
+    user_state: State<'_, UserStateDb>,
+    project_id: Option<String>,
+    older_than_days: Option<i64>,
+) -> Result<i64, String> {
+    let conn = user_state.0.lock().map_err(|e| e.to_string())?;
+    let retention_secs = older_than_days
+        .map(|days| days.max(0) * 86_400)
+        .unwrap_or(crate::user_state::TRASH_RETENTION_SECS);
+    conn.execute(
+        "DELETE FROM deleted_items
+         WHERE deleted_at <= ?1 AND (?2 IS NULL OR project_id = ?2)",
+        params![unix_timestamp_i64() - retention_secs, project_id],
+    )
+    .map(|n| n as i64)
+    .map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+pub fn list_bookmarks(
+    user_state: State<'_, UserStateDb>,
+    project_id: String,
+    query: Option<String>,
+    limit: Option<i32>,
+) -> Result<Vec<Bookmark>, String> {
+    let limit = limit.unwrap_or(200).clamp(1, 5000);
+    let conn = user_state.0.lock().map_err(|e| e.to_string())?;
+    let has_query = query
+        .as_ref()
+        .map(|q| !q.trim().is_empty())
+        .unwrap_or(false);
+
+    let sql = if has_query {
+        "SELECT id, project_id, collection_id, doc_slug, anchor_id, title_snapshot, created_at, updated_at, last_opened_at, order_index, open_count, is_favorite \
+         FROM bookmarks \
+         WHERE project_id = ?1 AND title_snapshot LIKE ?2 \
+         ORDER BY is_favorite DESC, open_count DESC, COALESCE(last_opened_at, updated_at) DESC, created_at DESC \
+         LIMIT ?3"
+    } else {
+        "SELECT id, project_id, collection_id, doc_slug, anchor_id, title_snapshot, created_at, updated_at, last_opened_at, order_index, open_count, is_favorite \
+         FROM bookmarks \
+         WHERE project_id = ?1 \
+         ORDER BY is_favorite DESC, open_count DESC, COALESCE(last_opened_at, updated_at) DESC, created_at DESC \
+         LIMIT ?2"
+    };
+
+    let mut stmt = conn.prepare_cached(sql).map_err(|e| e.to_string())?;
+
+    let rows = if has_query {
+        let search = format!("%{}%", query.unwrap_or_default().trim());
+        stmt.query_map(params![project_id, search, limit], bookmark_from_row)
+            .map_err(|e| e.to_string())?
+    } else {
+        stmt.query_map(params![project_id, limit], bookmark_from_row)
+            .map_err(|e| e.to_string())?
+    };
+
+    rows.collect::<Result<Vec<_>, _>>()
+        .map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+pub fn upsert_bookmark(
+    user_state: State<'_, UserStateDb>,
+    project_id: String,
+    collection_id: String,
+    doc_slug: String,
+    anchor_id: Option<String>,
+    title_snapshot: String,
+) -> Result<Bookmark, UserStateError> {
+    let now = unix_timestamp_i64();
+    with_busy_retry(
+        || lock_user_state(&user_state),
+        |conn| {
+            let existing_id: Option<i64> = conn
+                .query_row(
+                    "SELECT id FROM bookmarks \
+                 WHERE project_id = ?1 AND doc_slug = ?2 \
+                 AND ((anchor_id IS NULL AND ?3 IS NULL) OR anchor_id = ?3) \
+                 LIMIT 1",
+                    params![&project_id, &doc_slug, &anchor_id],
+                    |row| row.get(0),
+                )
+                .optional()?;
+
+            let bookmark_id = if let Some(id) = existing_id {
+                conn.execute(
+                    "UPDATE bookmarks \
+                 SET collection_id = ?1, title_snapshot = ?2, updated_at = ?3 \
+                 WHERE id = ?4",
+                    params![&collection_id, &title_snapshot, now, id],
+                )?;
+                conn.execute(
+                "INSERT INTO bookmark_events (bookmark_id, event_type, created_at) VALUES (?1, 'updated', ?2)",
+                params![id, now],
+            )?;
+                id
+            } else {
+                let next_order_index: i64 = conn.query_row(
+                    "SELECT COALESCE(MAX(order_index), 0) + 1 FROM bookmarks WHERE project_id = ?1",
+                    params![&project_id],
+                    |row| row.get(0),
+                )?;
+
+                conn.execute(
+                    "INSERT INTO bookmarks (
+                    project_id, collection_id, doc_slug, anchor_id, title_snapshot,
+                    created_at, updated_at, last_opened_at, order_index, open_count, is_favorite
+                 ) VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, NULL, ?8, 0, 0)",
+                    params![
+                        &project_id,
+                        &collection_id,
+                        &doc_slug,
+                        &anchor_id,
+                        &title_snapshot,
+                        now,
+                        now,
+                        next_order_index
+                    ],
+                )?;
+                let id = conn.last_insert_rowid();
+                conn.execute(
+                "INSERT INTO bookmark_events (bookmark_id, event_type, created_at) VALUES (?1, 'created', ?2)",
+                params![id, now],
+            )?;
+                id
+            };
+
+            conn.query_row(
+            "SELECT id, project_id, collection_id, doc_slug, anchor_id, title_snapshot, created_at, updated_at, last_opened_at, order_index, open_count, is_favorite \
+             FROM bookmarks WHERE id = ?1",
+            params![bookmark_id],
+            bookmark_from_row,
+        )
+        },
+    )
+}
+
+#[tauri::command]
+pub fn remove_bookmark(
+    user_state: State<'_, UserStateDb>,
+    project_id: String,
+    doc_slug: String,
+    anchor_id: Option<String>,
+) -> Result<bool, UserStateError> {
+    let removed = with_busy_retry(
+        || lock_user_state(&user_state),
+        |conn| {
+            conn.execute(
+                "DELETE FROM bookmarks \
+             WHERE project_id = ?1 AND doc_slug = ?2 \
+             AND ((anchor_id IS NULL AND ?3 IS NULL) OR anchor_id = ?3)",
+                params![project_id, doc_slug, anchor_id],
+            )
+        },
+    )?;
+    Ok(removed > 0)
+}
+
+#[tauri::command]
+pub fn repair_bookmark_target(
+    user_state: State<'_, UserStateDb>,
+    bookmark_id: i64,
+    collection_id: String,
+    doc_slug: String,
+    anchor_id: Option<String>,
+    title_snapshot: String,
+) -> Result<Bookmark, UserStateError> {
+    let now = unix_timestamp_i64();
+    with_busy_retry(
+        || lock_user_state(&user_state),
+        |conn| {
+            conn.execute(
+            "UPDATE bookmarks
+             SET collection_id = ?1, doc_slug = ?2, anchor_id = ?3, title_snapshot = ?4, updated_at = ?5
+             WHERE id = ?6",
+            params![
+                collection_id,
+                doc_slug,
+                anchor_id,
+                title_snapshot,
+                now,
+                bookmark_id
+            ],
+        )?;
+            conn.execute(
+            "INSERT INTO bookmark_events (bookmark_id, event_type, created_at) VALUES (?1, 'repaired', ?2)",
+            params![bookmark_id, now],
+        )?;
+
+            conn.query_row(
+            "SELECT id, project_id, collection_id, doc_slug, anchor_id, title_snapshot, created_at, updated_at, last_opened_at, order_index, open_count, is_favorite
+             FROM bookmarks WHERE id = ?1",
+            params![bookmark_id],
+            bookmark_from_row,
+        )
+        },
+    )
+}
+
+#[tauri::command]
+pub fn touch_bookmark_opened(
+    user_state: State<'_, UserStateDb>,
+    bookmark_id: i64,
+) -> Result<(), UserStateError> {
+    let now = unix_timestamp_i64();
+    with_busy_retry(
+        || lock_user_state(&user_state),
+        |conn| {
+            conn.execute(
+                "UPDATE bookmarks
+             SET last_opened_at = ?1, updated_at = ?1, open_count = open_count + 1
+             WHERE id = ?2",
+                params![now, bookmark_id],
+            )?;
+            conn.execute(
+            "INSERT INTO bookmark_events (bookmark_id, event_type, created_at) VALUES (?1, 'opened', ?2)",
+            params![bookmark_id, now],
+        )?;
+            Ok(())
+        },
+    )
+}
+
+#[tauri::command]
+pub fn set_bookmark_favorite(
+    user_state: State<'_, UserStateDb>,
+    bookmark_id: i64,
+    is_favorite: bool,
+) -> Result<Bookmark, UserStateError> {
+    let now = unix_timestamp_i64();
+    with_busy_retry(
+        || lock_user_state(&user_state),
+        |conn| {
+            conn.execute(
+                "UPDATE bookmarks
+             SET is_favorite = ?1, updated_at = ?2
+             WHERE id = ?3",
+                params![if is_favorite { 1 } else { 0 }, now, bookmark_id],
+            )?;
+            conn.execute(
+                "INSERT INTO bookmark_events (bookmark_id, event_type, created_at)
+             VALUES (?1, ?2, ?3)",
+                params![
+                    bookmark_id,
+                    if is_favorite {
+                        "favorited"
+                    } else {
+                        "unfavorited"
+                    },
+                    now
+                ],
+            )?;
+
+            conn.query_row(
+            "SELECT id, project_id, collection_id, doc_slug, anchor_id, title_snapshot, created_at, updated_at, last_opened_at, order_index, open_count, is_favorite
+             FROM bookmarks WHERE id = ?1",
+            params![bookmark_id],
+            bookmark_from_row,
+        )
+        },
+    )
+}
+
+#[tauri::command]
+pub fn validate_bookmarks(
+    manager: State<'_, std::sync::Mutex<ProjectManager>>,
+    user_state: State<'_, UserStateDb>,
+    project_id: String,
+) -> Result<Vec<BookmarkValidation>, String> {
+    let bookmarks: Vec<(i64, String, Option<String>)> = {
+        let user_conn = lock_user_state(&user_state)?;
+        let mut stmt = user_conn
+            .prepare_cached(
+                "SELECT id, doc_slug, anchor_id FROM bookmarks WHERE project_id = ?1 ORDER BY id",
+            )
+            .map_err(|e| e.to_string())?;
+        let rows = stmt
+            .query_map(params![&project_id], |row| {
+                Ok((row.get(0)?, row.get(1)?, row.get(2)?))
+            })
+            .map_err(|e| e.to_string())?;
+        rows.collect::<Result<Vec<_>, _>>()
+            .map_err(|e| e.to_string())?
+    };
+
+    let mgr = lock_manager(&manager)?;
+    let project_conn = mgr.connection(&project_id)?;
+    let mut anchors_by_doc: std::collections::HashMap<i32, Vec<DocumentAnchor>> =
+        std::collections::HashMap::new();
+
+    let mut out = Vec::with_capacity(bookmarks.len());
+    for (bookmark_id, doc_slug, anchor_id) in bookmarks {
+        let mut validation = BookmarkValidation {
+            bookmark_id,
+            doc_slug,
+            anchor_id,
+            status: "ok".to_string(),
+            suggested_anchor_id: None,
+            suggested_heading_text: None,
+        };
+
+        let Some(document_id) = document_id_for_slug(project_conn, &validation.doc_slug)? else {
+            validation.status = "missing_document".to_string();
+            out.push(validation);
+            continue;
+        };
+
+        if let Some(ref anchor) = validation.anchor_id {
+            let anchors = match anchors_by_doc.entry(document_id) {
+                std::collections::hash_map::Entry::Occupied(entry) => entry.into_mut(),
+                std::collections::hash_map::Entry::Vacant(entry) => {
+                    entry.insert(queries::document_anchors(project_conn, document_id)?)
+                }
+            };
+
+            if !anchors.iter().any(|a| &a.anchor_id == anchor) {
+                validation.status = "missing_anchor".to_string();
+                let wanted = anchor.replace('-', " ");
+                let best = anchors
+                    .iter()
+                    .map(|a| {
+                        let score = text_similarity(&wanted, &a.heading_text)
+                            .max(text_similarity(&wanted, &a.anchor_id.replace('-', " ")));
+                        (score, a)
+                    })
+                    .filter(|(score, _)| *score > 0.3)
+                    .max_by(|a, b| a.0.partial_cmp(&b.0).unwrap_or(std::cmp::Ordering::Equal));
+                if let Some((_, suggestion)) = best {
+                    validation.suggested_anchor_id = Some(suggestion.anchor_id.clone());
+                    validation.suggested_heading_text = Some(suggestion.heading_text.clone());
+                }
+            }
+        }
+
+        out.push(validation);
+    }
+
+    Ok(out)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{
+        capture_bookmark, folder_from_row, move_to_trash, query_ids, restore_payload,
+        set_bookmarks_favorite, TrashPayload,
+    };
+    use crate::user_state::{apply_user_state_schema, UserStateDb};
+
+    #[test]
+    fn bulk_favorites_change_only_the_projects_bookmarks_not_yet_in_that_state() {
+        let conn = rusqlite::Connection::open_in_memory().unwrap();
+        apply_user_state_schema(&conn).unwrap();
+        for (id, project_id, is_favorite) in [(1, "p", 0), (2, "p", 1), (3, "p", 0), (4, "q", 0)] {
+            conn.execute(
+                "INSERT INTO bookmarks (id, project_id, collection_id, doc_slug, title_snapshot,
+                     created_at, updated_at, is_favorite)
+                 VALUES (?1, ?2, 'ops', ?3, 'Doc', 0, 0, ?4)",
+                rusqlite::params![id, project_id, format!("ops/doc-{}", id), is_favorite],
+            )
+            .unwrap();
+        }
+        let db = UserStateDb(std::sync::Mutex::new(conn));
+        let favorites = || -> Vec<i64> {
+            let conn = db.0.lock().unwrap();
+            let mut stmt = conn
+                .prepare("SELECT id FROM bookmarks WHERE is_favorite = 1 ORDER BY id")
+                .unwrap();
+            let ids = stmt
+                .query_map([], |row| row.get(0))
+                .unwrap()
+                .collect::<Result<_, _>>()
+                .unwrap();
+            ids
+        };
+        let events = || -> i64 {
+            let conn = db.0.lock().unwrap();
+            conn.query_row("SELECT COUNT(*) FROM bookmark_events", [], |row| row.get(0))
+                .unwrap()
+        };
+
+        assert_eq!(
+            set_bookmarks_favorite(&db, "p", &[1, 2, 3, 1], true, 5).unwrap(),
+            2
+        );
+        assert_eq!(favorites(), [1, 2, 3]);
+        assert_eq!(events(), 2);
+
+        // A bookmark of another project fails the whole batch
+        assert!(set_bookmarks_favorite(&db, "p", &[1, 4], false, 6).is_err());
+        assert_eq!(favorites(), [1, 2, 3]);
+
+        assert_eq!(
+            set_bookmarks_favorite(&db, "p", &[2, 3], false, 7).unwrap(),
+            2
+        );
+        assert_eq!(favorites(), [1]);
+        assert_eq!(events(), 4);
+        assert_eq!(set_bookmarks_favorite(&db, "p", &[], true, 8).unwrap(), 0);
+    }
+
+    #[test]
+    fn restoring_after_a_merge_relinks_folders_to_the_merged_bookmark() {
+        let conn = rusqlite::Connection::open_in_memory().unwrap();
+        apply_user_state_schema(&conn).unwrap();
+        conn.execute_batch(
+            "INSERT INTO bookmark_folders (id, project_id, name, created_at, updated_at)
+                 VALUES (1, 'p', 'Deploys', 0, 0);
+             INSERT INTO bookmarks (id, project_id, collection_id, doc_slug, title_snapshot,
+                 created_at, updated_at)
+                 VALUES (1, 'p', 'ops', 'ops/deploy', 'Deploy', 0, 0);
+             INSERT INTO bookmark_folder_items (folder_id, bookmark_id) VALUES (1, 1);
+             INSERT INTO bookmark_events (bookmark_id, event_type, created_at)
+                 VALUES (1, 'created', 10), (1, 'opened', 20);",
+        )
+        .unwrap();
+        let trash = |payload: TrashPayload| -> i64 { move_to_trash(&conn, "p", &payload).unwrap() };
+        let restore = |deleted_id: i64| {
+            let json: String = conn
+                .query_row(
+                    "SELECT payload_json FROM deleted_items WHERE id = ?1",
+                    [deleted_id],
+                    |row| row.get(0),
+                )
+                .unwrap();
+            restore_payload(&conn, &serde_json::from_str(&json).unwrap()).unwrap();
+        };
+
+        // The folder goes first, then its bookmark
+        let folder = conn
+            .query_row(
+                "SELECT id, project_id, name, created_at, updated_at
+                 FROM bookmark_folders WHERE id = 1",
+                [],
+                folder_from_row,
+            )
+            .unwrap();
+        let trashed_folder = trash(TrashPayload::BookmarkFolder {
+            folder,
+            bookmark_ids: vec![1],
+            annotations: vec![],
+        });
+        conn.execute("DELETE FROM bookmark_folders WHERE id = 1", [])
+            .unwrap();
+        let bookmark = capture_bookmark(&conn, "p", 1).unwrap().unwrap();
+        let trashed_bookmark = trash(TrashPayload::Bookmarks {
+            bookmarks: vec![bookmark],
+        });
+        conn.execute("DELETE FROM bookmarks WHERE id = 1", [])
+            .unwrap();
+
+        // The doc is bookmarked again, under a new id
+        conn.execute(
+            "INSERT INTO bookmarks (project_id, collection_id, doc_slug, title_snapshot,
+                 created_at, updated_at)
+             VALUES ('p', 'ops', 'ops/deploy', 'Deploy', 30, 30)",
+            [],
+        )
+        .unwrap();
+        let rebookmarked = conn.last_insert_rowid();
+        assert_ne!(rebookmarked, 1);
+
+        restore(trashed_bookmark);
+        restore(trashed_folder);
+
+        let folder_id: i64 = conn
+            .query_row(
+                "SELECT id FROM bookmark_folders WHERE name = 'Deploys'",
+                [],
+                |row| row.get(0),
+            )
+            .unwrap();
+        assert_eq!(
+            query_ids(
+                &conn,
+                "SELECT bookmark_id FROM bookmark_folder_items WHERE folder_id = ?1",
+                folder_id,
+            )
+            .unwrap(),
+            [rebookmarked]
+        );
+        let mut stmt = conn
+            .prepare("SELECT event_type, created_at FROM bookmark_events WHERE bookmark_id = ?1 ORDER BY id")
+            .unwrap();
+        let events = stmt
+            .query_map([rebookmarked], |row| {
+                Ok((row.get::<_, String>(0)?, row.get::<_, i64>(1)?))
+            })
+            .unwrap()
+            .collect::<Result<Vec<_>, _>>()
+            .unwrap();
+        assert_eq!(
+            events,
+            [("created".to_string(), 10), ("opened".to_string(), 20)]
+        );
+    }
+}
@@ -0,0 +1,309 @@
+//! The quick-capture inbox: snippets jotted down before they belong to a
+//! document, later filed as a note or highlight on one.
+
+use super::search::document_id_for_slug;
+use super::util::{lock_manager, lock_user_state, unix_timestamp_i64};
+use crate::ai;
+use crate::models::*;
+use crate::projects::ProjectManager;
+use crate::queries;
+use crate::user_state::{write_doc_note, UserStateDb};
+use rusqlite::{params, OptionalExtension};
+use tauri::State;
+
+/// Passages searched for a snippet before they are grouped by document.
+const INBOX_SUGGESTION_PASSAGES: usize = 20;
+const DEFAULT_INBOX_SUGGESTIONS: usize = 5;
+
+pub(super) fn inbox_item_from_row(row: &rusqlite::Row<'_>) -> rusqlite::Result<InboxItem> {
+    Ok(InboxItem {
+        id: row.get(0)?,
+        project_id: row.get(1)?,
+        content: row.get(2)?,
+        created_at: row.get(3)?,
+        resolved_at: row.get(4)?,
+        resolved_doc_slug: row.get(5)?,
+    })
+}
+
+fn inbox_item(conn: &rusqlite::Connection, id: i64) -> Result<InboxItem, String> {
+    conn.query_row(
+        "SELECT id, project_id, content, created_at, resolved_at, resolved_doc_slug
+         FROM inbox_items WHERE id = ?1",
+        params![id],
+        inbox_item_from_row,
+    )
+    .optional()
+    .map_err(|e| e.to_string())?
+    .ok_or_else(|| format!("Inbox item {} not found", id))
+}
+
+#[tauri::command]
+pub fn add_inbox_item(
+    user_state: State<'_, UserStateDb>,
+    project_id: Option<String>,
+    content: String,
+) -> Result<InboxItem, String> {
+    let content = content.trim();
+    if content.is_empty() {
+        return Err("Inbox items cannot be empty".to_string());
+    }
+    let conn = lock_user_state(&user_state)?;
+    conn.execute(
+        "INSERT INTO inbox_items (project_id, content, created_at) VALUES (?1, ?2, ?3)",
+        params![project_id, content, unix_timestamp_i64()],
+    )
+    .map_err(|e| e.to_string())?;
+    inbox_item(&conn, conn.last_insert_rowid())
+}
+
+/// Inbox items, newest first. With `project_id`, only that project's items and
+/// those not tied to a project are listed. Resolved items are left out unless
+/// `include_resolved` is set.
+#[tauri::command]
+pub fn list_inbox_items(
+    user_state: State<'_, UserStateDb>,
+    project_id: Option<String>,
+    include_resolved: Option<bool>,
+) -> Result<Vec<InboxItem>, String> {
+    let conn = lock_user_state(&user_state)?;
+    let mut stmt = conn
+        .prepare_cached(
+            "SELECT id, project_id, content, created_at, resolved_at, resolved_doc_slug
+             FROM inbox_items
+             WHERE (?1 IS NULL OR project_id IS NULL OR project_id = ?1)
+               AND (?2 OR resolved_at IS NULL)
+             ORDER BY created_at DESC, id DESC",
+        )
+        .map_err(|e| e.to_string())?;
+    let rows = stmt
+        .query_map(
+            params![project_id, include_resolved.unwrap_or(false)],
+            inbox_item_from_row,
+        )
+        .map_err(|e| e.to_string())?;
+    rows.collect::<Result<Vec<_>, _>>()
+        .map_err(|e| e.to_string())
+}
+
+/// File an inbox item under a document, as a note (`attach_as` "note", added
+/// to the end of any existing note) or a highlight ("highlight"), and mark it
+/// resolved. `project_id` is needed for items not tied to a project.
+#[tauri::command]
+pub fn resolve_inbox_item(
+    manager: State<'_, std::sync::Mutex<ProjectManager>>,
+    user_state: State<'_, UserStateDb>,
+    id: i64,
+    project_id: Option<String>,
+    doc_slug: String,
+    attach_as: String,
+) -> Result<InboxItem, String> {
+    let now = unix_timestamp_i64();
+    let item_project_id = {
+        let conn = lock_user_state(&user_state)?;
+        inbox_item(&conn, id)?.project_id
+    };
+    let project_id = project_id
+        .or(item_project_id)
+        .ok_or("Choose a project for this inbox item")?;
+    {
+        let mgr = lock_manager(&manager)?;
+        if document_id_for_slug(mgr.connection(&project_id)?, &doc_slug)?.is_none() {
+            return Err(format!("Document '{}' not found", doc_slug));
+        }
+    }
+
+    let conn = lock_user_state(&user_state)?;
+    let tx = conn.unchecked_transaction().map_err(|e| e.to_string())?;
+    let item = inbox_item(&tx, id)?;
+    if item.resolved_at.is_some() {
+        return Err(format!("Inbox item {} is already resolved", id));
+    }
+
+    match attach_as.as_str() {
+        "note" => {
+            let existing: Option<String> = tx
+                .query_row(
+                    "SELECT note FROM doc_notes WHERE project_id = ?1 AND doc_slug = ?2",
+                    params![&project_id, &doc_slug],
+                    |row| row.get(0),
+                )
+                .optional()
+                .map_err(|e| e.to_string())?;
+            let note = match existing.filter(|n| !n.trim().is_empty()) {
+                Some(existing) => format!("{}\n\n{}", existing, item.content),
+                None => item.content,
+            };
+            write_doc_note(&tx, &project_id, &doc_slug, &note, now)?;
+        }
+        "highlight" => {
+            tx.execute(
+                "INSERT INTO doc_highlights (project_id, doc_slug, anchor_id, selected_text, context_text, created_at)
+                 VALUES (?1, ?2, NULL, ?3, NULL, ?4)",
+                params![&project_id, &doc_slug, item.content, now],
+            )
+            .map_err(|e| e.to_string())?;
+        }
+        other => return Err(format!("Unknown inbox destination '{}'", other)),
+    }
+    tx.execute(
+        "UPDATE inbox_items
+         SET project_id = ?2, resolved_at = ?3, resolved_doc_slug = ?4
+         WHERE id = ?1",
+        params![id, &project_id, now, &doc_slug],
+    )
+    .map_err(|e| e.to_string())?;
+    let item = inbox_item(&tx, id)?;
+    tx.commit().map_err(|e| e.to_string())?;
+    Ok(item)
+}
+
+#[tauri::command]
+pub fn delete_inbox_item(user_state: State<'_, UserStateDb>, id: i64) -> Result<(), String> {
+    let conn = lock_user_state(&user_state)?;
+    conn.execute("DELETE FROM inbox_items WHERE id = ?1", params![id])
+        .map_err(|e| e.to_string())?;
+    Ok(())
+}
+
+/// Documents an inbox item is likely to belong to, found by searching its
+/// text against passages and whole documents. Items not tied to a project are
+/// matched against the active project.
+#[tauri::command]
+pub fn suggest_inbox_destinations(
+    manager: State<'_, std::sync::Mutex<ProjectManager>>,
+    user_state: State<'_, UserStateDb>,
+    id: i64,
+    limit: Option<usize>,
+) -> Result<Vec<InboxDestination>, String> {
+    let item = {
+        let conn = lock_user_state(&user_state)?;
+        inbox_item(&conn, id)?
+    };
+    let project_id = match item.project_id {
+        Some(project_id) => project_id,
+        None => lock_manager(&manager)?.registry.active_project_id.clone(),
+    };
+    // Collections hidden from navigation aren't offered as destinations
+    let hidden = {
+        let conn = lock_user_state(&user_state)?;
+        crate::user_state::hidden_collections(&conn, &project_id)?
+    };
+    let mgr = lock_manager(&manager)?;
+    let conn = mgr.connection(&project_id)?;
+
+    let mut passages = Vec::new();
+    for chunk in ai::fts_chunk_search(conn, &item.content, INBOX_SUGGESTION_PASSAGES, &hidden)? {
+        let destination = conn
+            .query_row(
+                "SELECT slug, title, collection_id, section FROM documents WHERE id = ?1",
+                params![chunk.document_id],
+                |row| {
+                    Ok(InboxDestination {
+                        slug: row.get(0)?,
+                        title: row.get(1)?,
+                        collection_id: row.get(2)?,
+                        section: row.get(3)?,
+                        heading: Some(chunk.heading_context.clone())
+                            .filter(|heading| !heading.is_empty()),
+                        score: 0.0,
+                    })
+                },
+            )
+            .optional()
+            .map_err(|e| e.to_string())?;
+        passages.extend(destination);
+    }
+    let documents = queries::search_documents(
+        conn,
+        &item.content,
+        None,
+        &hidden,
+        INBOX_SUGGESTION_PASSAGES as i32,
+        crate::snippet::DEFAULT_SNIPPET_TOKENS,
+    )?
+    .into_iter()
+    .map(|result| InboxDestination {
+        slug: result.slug,
+        title: result.title,
+        collection_id: result.collection_id,
+        section: result.section,
+        heading: None,
+        score: 0.0,
+    })
+    .collect();
+
+    Ok(rank_destinations(
+        passages,
+        documents,
+        limit.unwrap_or(DEFAULT_INBOX_SUGGESTIONS),
+    ))
+}
+
+/// Merge passage and document matches, each listed best first, into one
+/// ranking. Every match adds 1 / (rank + 1) to its document's score, so a
+/// document found by both searches or by several passages comes first.
+fn rank_destinations(
+    passages: Vec<InboxDestination>,
+    documents: Vec<InboxDestination>,
+    limit: usize,
+) -> Vec<InboxDestination> {
+    let mut ranked: Vec<InboxDestination> = Vec::new();
+    for (rank, mut destination) in passages
+        .into_iter()
+        .enumerate()
+        .chain(documents.into_iter().enumerate())
+    {
+        let score = 1.0 / (rank as f64 + 1.0);
+        match ranked.iter_mut().find(|d| d.slug == destination.slug) {
+            Some(existing) => {
+                existing.score += score;
+                if existing.heading.is_none() {
+                    existing.heading = destination.heading;
+                }
+            }
+            None => {
+                destination.score = score;
+                ranked.push(destination);
+            }
+        }
+    }
+    ranked.sort_by(|a, b| b.score.total_cmp(&a.score));
+    ranked.truncate(limit);
+    ranked
+}
+
+#[cfg(test)]
+mod tests {
+    use super::rank_destinations;
+    use crate::models::InboxDestination;
+
+    fn destination(slug: &str, heading: Option<&str>) -> InboxDestination {
+        InboxDestination {
+            slug: slug.to_string(),
+            title: slug.to_string(),
+            collection_id: "ops".to_string(),
+            section: String::new(),
+            heading: heading.map(str::to_string),
+            score: 0.0,
+        }
+    }
+
+    #[test]
+    fn documents_matched_twice_rank_first() {
+        let passages = vec![
+            destination("ops/deploys", Some("Rollouts")),
+            destination("ops/restarts", Some("Restarting")),
+            destination("ops/restarts", Some("Paging")),
+        ];
+        let documents = vec![
+            destination("ops/restarts", None),
+            destination("ops/oncall", None),
+        ];
+        let ranked = rank_destinations(passages, documents, 2);
+        let slugs: Vec<&str> = ranked.iter().map(|d| d.slug.as_str()).collect();
+        assert_eq!(slugs, ["ops/restarts", "ops/deploys"]);
+        assert_eq!(ranked[0].heading.as_deref(), Some("Restarting"));
+        assert!((ranked[0].score - (0.5 + 1.0 / 3.0 + 1.0)).abs() < 1e-9);
+    }
+}
@@ -0,0 +1,24 @@
+//! Tauri commands, one module per area of the app. `util` holds the helpers
+//! they share; everything else is re-exported so handlers are registered as
+//! `commands::name`.
+
+mod activity;
+mod ai;
+mod annotations;
+mod bookmarks;
+mod inbox;
+mod projects;
+mod search;
+mod settings;
+mod snapshot;
+mod util;
+
+pub use activity::*;
+pub use ai::*;
+pub use annotations::*;
+pub use bookmarks::*;
+pub use inbox::*;
+pub use projects::*;
+pub use search::*;
+pub use settings::*;
+pub use snapshot::*;
@@ -0,0 +1,2162 @@
+//! Projects: registration, building and rebuilding, switching, collections,
+//! per-project options, stats and exports.
+
+use super::activity::{expire_header_counts, record_doc_removals, record_project_change_feed};
+use super::search::document_id_for_slug;
+#[cfg(target_os = "macos")]
+use super::settings::refresh_spotlight_export;
+use super::util::{
+    bookmark_from_row, lock_manager, lock_user_state, millis, project_word_counts, unix_timestamp,
+    unix_timestamp_i64, with_states,
+};
+use crate::ai;
+use crate::build_queue::BuildQueue;
+use crate::db::{handbook_db_path, HttpClient};
+use crate::models::*;
+use crate::projects::ProjectManager;
+use crate::queries;
+use crate::settings;
+use crate::user_state::UserStateDb;
+use rusqlite::{params, OptionalExtension};
+use tauri::{AppHandle, Manager, State};
+use tauri_plugin_shell::ShellExt;
+
+#[tauri::command]
+pub fn get_project_stats(
+    app: AppHandle,
+    manager: State<'_, std::sync::Mutex<ProjectManager>>,
+    user_state: State<'_, UserStateDb>,
+    project_id: String,
+) -> Result<ProjectStats, String> {
+    let hidden_collection_count = {
+        let user_conn = lock_user_state(&user_state)?;
+        crate::user_state::hidden_collections(&user_conn, &project_id)?.len() as i32
+    };
+    let mgr = lock_manager(&manager)?;
+
+    let conn = mgr
+        .connections
+        .get(&project_id)
+        .ok_or_else(|| format!("No database connection for project '{}'", project_id))?;
+
+    // Determine DB file path for size calculation
+    let project = mgr.registry.projects.iter().find(|p| p.id == project_id);
+    let db_size_bytes = if let Some(p) = project {
+        if p.built_in {
+            let path = handbook_db_path(&app);
+            std::fs::metadata(&path).map(|m| m.len()).unwrap_or(0)
+        } else if let Some(ref relative_path) = p.db_path {
+            let app_data_dir = app.path().app_data_dir().unwrap_or_default();
+            let path = app_data_dir.join(relative_path);
+            std::fs::metadata(&path).map(|m| m.len()).unwrap_or(0)
+        } else {
+            0
+        }
+    } else {
+        0
+    };
+
+    Ok(ProjectStats {
+        hidden_collection_count,
+        ..queries::project_stats(conn, db_size_bytes)
+    })
+}
+
+#[tauri::command]
+pub fn get_storage_breakdown(
+    manager: State<'_, std::sync::Mutex<ProjectManager>>,
+    project_id: String,
+) -> Result<StorageBreakdown, String> {
+    let mgr = manager.lock().map_err(|e| e.to_string())?;
+    queries::storage_breakdown(mgr.connection(&project_id)?)
+}
+
+/// Write a documents, tags, bookmarks or annotations report for a project to
+/// `destination` as CSV or JSON, returning the number of rows written.
+/// `excel_bom` starts CSV files with a UTF-8 byte order mark for Excel. The
+/// report is written beside `destination` and renamed over it once complete,
+/// so a failed export never leaves half a file.
+#[tauri::command]
+pub async fn export_report(
+    app: AppHandle,
+    project_id: String,
+    kind: String,
+    format: String,
+    destination: String,
+    excel_bom: Option<bool>,
+) -> Result<usize, String> {
+    tauri::async_runtime::spawn_blocking(move || {
+        let staging = format!("{}.tmp", destination);
+        let written = write_report(
+            &app,
+            &project_id,
+            &kind,
+            &format,
+            &staging,
+            excel_bom.unwrap_or(false),
+        )
+        .and_then(|rows| {
+            std::fs::rename(&staging, &destination)
+                .map_err(|e| format!("Failed to write {}: {}", destination, e))?;
+            Ok(rows)
+        });
+        if written.is_err() {
+            let _ = std::fs::remove_file(&staging);
+        }
+        written
+    })
+    .await
+    .map_err(|e| e.to_string())?
+}
+
+fn write_report(
+    app: &AppHandle,
+    project_id: &str,
+    kind: &str,
+    format: &str,
+    destination: &str,
+    excel_bom: bool,
+) -> Result<usize, String> {
+    use crate::report::{ReportFormat, ReportKind, ReportWriter};
+    use serde_json::json;
+
+    let kind = ReportKind::parse(kind)?;
+    let format = ReportFormat::parse(format)?;
+    let manager = app.state::<std::sync::Mutex<ProjectManager>>();
+    let user_state = app.state::<UserStateDb>();
+    let file = std::fs::File::create(destination)
+        .map_err(|e| format!("Failed to create {}: {}", destination, e))?;
+    let mut writer = ReportWriter::new(
+        std::io::BufWriter::new(file),
+        format,
+        kind.columns(),
+        excel_bom,
+    )?;
+
+    match kind {
+        ReportKind::Documents => {
+            let word_counts = project_word_counts(&manager, project_id)?;
+            let mgr = lock_manager(&manager)?;
+            queries::each_listed_document(
+                mgr.connection(project_id)?,
+                &word_counts,
+                None,
+                |doc| {
+                    writer.row(&[
+                        json!(doc.slug),
+                        json!(doc.title),
+                        json!(doc.collection_id),
+                        json!(doc.section),
+                        json!(doc.last_modified),
+                        json!(doc.word_count),
+                    ])
+                },
+            )?;
+        }
+        ReportKind::Tags => {
+            let tags = {
+                let mgr = lock_manager(&manager)?;
+                queries::tags(mgr.connection(project_id)?, None, queries::TagSort::Count)?
+            };
+            for tag in tags {
+                writer.row(&[json!(tag.tag), json!(tag.count)])?;
+            }
+        }
+        ReportKind::Bookmarks => {
+            let conn = lock_user_state(&user_state)?;
+            let mut stmt = conn
+                .prepare(
+                    "SELECT id, project_id, collection_id, doc_slug, anchor_id, title_snapshot, created_at, updated_at, last_opened_at, order_index, open_count, is_favorite \
+                     FROM bookmarks \
+                     WHERE project_id = ?1 \
+                     ORDER BY collection_id, doc_slug, id",
+                )
+                .map_err(|e| e.to_string())?;
+            let rows = stmt
+                .query_map(params![project_id], bookmark_from_row)
+                .map_err(|e| e.to_string())?;
+            for row in rows {
+                let bookmark = row.map_err(|e| e.to_string())?;
+                writer.row(&[
+                    json!(bookmark.id),
+                    json!(bookmark.collection_id),
+                    json!(bookmark.doc_slug),
+                    json!(bookmark.anchor_id),
+                    json!(bookmark.title_snapshot),
+                    json!(bookmark.created_at),
+                    json!(bookmark.updated_at),
+                    json!(bookmark.last_opened_at),
+                    json!(bookmark.open_count),
+                    json!(bookmark.is_favorite),
+                ])?;
+            }
+        }
+        ReportKind::Annotations => {
+            let conn = lock_user_state(&user_state)?;
+            let mut stmt = conn
+                .prepare(
+                    "SELECT 'note', doc_slug, NULL, note, NULL, updated_at
+                     FROM doc_notes
+                     WHERE project_id = ?1 AND note != ''
+                     UNION ALL
+                     SELECT 'highlight', doc_slug, anchor_id, selected_text, context_text, created_at
+                     FROM doc_highlights
+                     WHERE project_id = ?1
+                     ORDER BY 2, 6",
+                )
+                .map_err(|e| e.to_string())?;
+            let mut rows = stmt.query(params![project_id]).map_err(|e| e.to_string())?;
+            while let Some(row) = rows.next().map_err(|e| e.to_string())? {
+                let values = (0..6)
+                    .map(|i| {
+                        row.get::<_, rusqlite::types::Value>(i)
+                            .map(|value| match value {
+                                rusqlite::types::Value::Integer(n) => json!(n),
+                                rusqlite::types::Value::Text(text) => json!(text),
+                                _ => serde_json::Value::Null,
+                            })
+                    })
+                    .collect::<Result<Vec<_>, _>>()
+                    .map_err(|e| e.to_string())?;
+                writer.row(&values)?;
+            }
+        }
+    }
+    writer.finish()
+}
+
+/// Write a read-only HTML copy of `collection_ids` (every collection when
+/// omitted) into `output_dir` for static hosting. Emits
+/// `static-site-export-progress` as pages are written.
+#[tauri::command]
+pub async fn export_static_site(
+    app: AppHandle,
+    project_id: String,
+    collection_ids: Option<Vec<String>>,
+    output_dir: String,
+) -> Result<StaticSiteExport, String> {
+    tauri::async_runtime::spawn_blocking(move || {
+        write_static_site(&app, project_id, collection_ids, output_dir)
+    })
+    .await
+    .map_err(|e| e.to_string())?
+}
+
+fn write_static_site(
+    app: &AppHandle,
+    project_id: String,
+    collection_ids: Option<Vec<String>>,
+    output_dir: String,
+) -> Result<StaticSiteExport, String> {
+    use crate::static_site::{SiteCollection, StaticPage, StaticSite};
+
+    let manager = app.state::<std::sync::Mutex<ProjectManager>>();
+    let site = {
+        let mgr = lock_manager(&manager)?;
+        let conn = mgr.connection(&project_id)?;
+        let title = mgr
+            .registry
+            .projects
+            .iter()
+            .find(|p| p.id == project_id)
+            .map_or_else(|| project_id.clone(), |p| p.name.clone());
+        let selected: Vec<Collection> =
+            queries::collections(conn, queries::CollectionSort::SortOrder)?
+                .into_iter()
+                .filter(|c| {
+                    collection_ids
+                        .as_ref()
+                        .is_none_or(|ids| ids.contains(&c.id))
+                })
+                .collect();
+        if selected.is_empty() {
+            return Err("No collections to export".to_string());
+        }
+
+        let mut stmt = conn
+            .prepare(
+                "SELECT slug, title, section, content_html FROM documents \
+                 WHERE collection_id = ?1 ORDER BY slug",
+            )
+            .map_err(|e| e.to_string())?;
+        let mut collections = Vec::new();
+        let mut pages = Vec::new();
+        for collection in selected {
+            let rows = stmt
+                .query_map([&collection.id], |row| {
+                    Ok(StaticPage {
+                        slug: row.get(0)?,
+                        title: row.get(1)?,
+                        section: row.get(2)?,
+                        content_html: row.get(3)?,
+                    })
+                })
+                .map_err(|e| e.to_string())?;
+            for row in rows {
+                pages.push(row.map_err(|e| e.to_string())?);
+            }
+            collections.push(SiteCollection {
+                nodes: queries::navigation(conn, &collection.id)?,
+                name: collection.name,
+            });
+        }
+        StaticSite {
+            title,
+            collections,
+            pages,
+        }
+    };
+
+    let file_count = crate::static_site::write_site(
+        std::path::Path::new(&output_dir),
+        &site,
+        |written, total| {
+            crate::events::emit(
+                app,
+                "static-site-export-progress",
+                serde_json::json!({
+                    "projectId": &project_id,
+                    "written": written,
+                    "total": total,
+                }),
+            )
+        },
+    )?;
+    Ok(StaticSiteExport {
+        output_dir,
+        file_count,
+    })
+}
+
+/// Mismatches between the active project's navigation, documents, tags,
+/// chunks and embeddings, with example slugs for each.
+#[tauri::command]
+pub fn get_project_integrity_report(
+    manager: State<'_, std::sync::Mutex<ProjectManager>>,
+) -> Result<IntegrityReport, String> {
+    let mgr = manager.lock().map_err(|e| e.to_string())?;
+    crate::integrity::integrity_report(mgr.active_connection()?)
+}
+
+/// Database file of a project the app built itself, which maintenance
+/// commands may replace.
+pub(super) fn writable_project_db_path(
+    app: &AppHandle,
+    manager: &State<'_, std::sync::Mutex<ProjectManager>>,
+    project_id: &str,
+) -> Result<std::path::PathBuf, String> {
+    let db_relative_path = {
+        let mgr = manager.lock().map_err(|e| e.to_string())?;
+        let project = mgr
+            .registry
+            .projects
+            .iter()
+            .find(|p| p.id == project_id)
+            .ok_or_else(|| format!("Project '{}' not found", project_id))?;
+        if project.built_in {
+            return Err("Cannot modify the built-in project".to_string());
+        }
+        project
+            .db_path
+            .clone()
+            .ok_or("No database path for project")?
+    };
+    let app_data_dir = app.path().app_data_dir().map_err(|e| e.to_string())?;
+    Ok(app_data_dir.join(&db_relative_path))
+}
+
+pub(super) fn reopen_project_connection(
+    manager: &State<'_, std::sync::Mutex<ProjectManager>>,
+    project_id: &str,
+    db_path: &std::path::Path,
+) -> Result<(), String> {
+    let mut mgr = manager.lock().map_err(|e| e.to_string())?;
+    mgr.swap_connection(project_id, db_path)
+}
+
+/// Run `rewrite` against a project's database file in the build queue, so it
+/// can't race a rebuild, then swap the connection the same way
+/// `rebuild_project` does. Returns the result with the file size before and after.
+pub(super) async fn rewrite_project_database<T: Send + 'static>(
+    app: &AppHandle,
+    manager: &State<'_, std::sync::Mutex<ProjectManager>>,
+    build_queue: &State<'_, BuildQueue>,
+    project_id: &str,
+    kind: &str,
+    rewrite: impl FnOnce(&std::path::Path) -> Result<T, String> + Send + 'static,
+) -> Result<(T, u64, u64), String> {
+    let db_path = writable_project_db_path(app, manager, project_id)?;
+    let _turn = build_queue.wait_for_turn(app, project_id, kind).await?;
+
+    let bytes_before = std::fs::metadata(&db_path).map(|m| m.len()).unwrap_or(0);
+    let rewrite_path = db_path.clone();
+    let value = tauri::async_runtime::spawn_blocking(move || rewrite(&rewrite_path))
+        .await
+        .map_err(|e| e.to_string())??;
+
+    reopen_project_connection(manager, project_id, &db_path)?;
+    let bytes_after = std::fs::metadata(&db_path).map(|m| m.len()).unwrap_or(0);
+    Ok((value, bytes_before, bytes_after))
+}
+
+#[tauri::command]
+pub async fn open_in_editor(
+    app: AppHandle,
+    editor_command: String,
+    path: String,
+) -> Result<(), String> {
+    app.shell()
+        .command(&editor_command)
+        .args([&path])
+        .spawn()
+        .map_err(|e| format!("Failed to open editor '{}': {}", editor_command, e))?;
+    Ok(())
+}
+
+fn resolve_project_root(app: &AppHandle) -> Result<std::path::PathBuf, String> {
+    let mut candidates = crate::builder::project_root_candidates();
+
+    // Optional runtime resource fallback.
+    if let Ok(resource_dir) = app.path().resource_dir() {
+        candidates.push(resource_dir.clone());
+        if let Some(parent) = resource_dir.parent() {
+            candidates.push(parent.to_path_buf());
+        }
+    }
+
+    crate::builder::find_project_root(candidates)
+}
+
+/// Which node and npm the Node build pipeline would use, what else it tried,
+/// and whether the checkout's dependencies are installed.
+#[tauri::command]
+pub async fn diagnose_build_environment(app: AppHandle) -> Result<BuildEnvironmentReport, String> {
+    let preferences = settings::load_preferences(&app).unwrap_or_default();
+    let project_root = resolve_project_root(&app);
+    tauri::async_runtime::spawn_blocking(move || {
+        crate::builder::diagnose_environment(
+            project_root,
+            preferences.node_binary_path.as_deref(),
+            preferences.npm_binary_path.as_deref(),
+        )
+    })
+    .await
+    .map_err(|e| e.to_string())
+}
+
+/// Saved build logs for a project, newest first.
+#[tauri::command]
+pub fn get_build_logs(app: AppHandle, project_id: String) -> Result<Vec<BuildLogEntry>, String> {
+    let app_data_dir = app.path().app_data_dir().map_err(|e| e.to_string())?;
+    Ok(crate::build_logs::build_logs(
+        &crate::build_logs::logs_dir(&app_data_dir),
+        &project_id,
+    ))
+}
+
+/// Contents of a build log. Only files in the build logs directory can be read.
+#[tauri::command]
+pub fn read_build_log(app: AppHandle, path: String) -> Result<String, String> {
+    let app_data_dir = app.path().app_data_dir().map_err(|e| e.to_string())?;
+    crate::build_logs::read_build_log(&crate::build_logs::logs_dir(&app_data_dir), &path)
+}
+
+/// Inputs for building one project database, owned so they can move onto a
+/// blocking thread.
+struct ProjectBuild {
+    source_path: String,
+    db_path: std::path::PathBuf,
+    collection_id: String,
+    collection_name: String,
+    collection_icon: String,
+    /// Reuse unchanged documents from the existing database (built-in builder only)
+    incremental: bool,
+    /// Skip embedding, which would send the project's content to OpenAI
+    local_only: bool,
+}
+
+/// A failed build, and where its full output was saved if the log could be
+/// written.
+struct BuildFailure {
+    error: String,
+    log_path: Option<std::path::PathBuf>,
+}
+
+impl BuildFailure {
+    /// The error with a pointer to the full log, as returned to the frontend.
+    fn message(&self) -> String {
+        match &self.log_path {
+            Some(path) => format!("{}\n\nFull build log: {}", self.error, path.display()),
+            None => self.error.clone(),
+        }
+    }
+}
+
+/// Build a project and save everything it printed as a build log, with
+/// secrets redacted.
+async fn run_project_build(
+    app: &AppHandle,
+    stored_settings: &Settings,
+    build: ProjectBuild,
+) -> Result<BuildSummary, BuildFailure> {
+    // Builds use the project id as their collection id
+    let project_id = build.collection_id.clone();
+    let mut transcript = format!(
+        "Project: {}\nSource: {}\nStarted: {}\n\n",
+        project_id,
+        build.source_path,
+        chrono::Utc::now().to_rfc3339()
+    );
+    let result = run_build_pipeline(app, stored_settings, build, &mut transcript).await;
+    match &result {
+        Ok(summary) => transcript.push_str(&format!(
+            "Build succeeded: {} documents rebuilt, {} reused, {} chunks embedded\n",
+            summary.documents_rebuilt, summary.documents_reused, summary.chunks_embedded
+        )),
+        Err(e) => transcript.push_str(&format!("Build failed: {}\n", e)),
+    }
+
+    let written = app
+        .path()
+        .app_data_dir()
+        .map_err(|e| e.to_string())
+        .and_then(|dir| {
+            crate::build_logs::write_build_log(
+                &crate::build_logs::logs_dir(&dir),
+                &project_id,
+                &ai::redact_secrets(&transcript, stored_settings),
+            )
+        });
+    let log_path = match written {
+        Ok(path) => Some(path),
+        Err(e) => {
+            eprintln!("Warning: failed to write build log: {}", e);
+            None
+        }
+    };
+    result.map_err(|error| BuildFailure { error, log_path })
+}
+
+async fn run_build_pipeline(
+    app: &AppHandle,
+    stored_settings: &Settings,
+    build: ProjectBuild,
+    transcript: &mut String,
+) -> Result<BuildSummary, String> {
+    let preferences = settings::load_preferences(app).unwrap_or_default();
+    if !preferences.node_build_pipeline {
+        transcript.push_str("Pipeline: built-in builder\n\n");
+        return run_native_build(app, stored_settings, build, transcript).await;
+    }
+    transcript.push_str("Pipeline: Node script\n\n");
+
+    let project_root = resolve_project_root(app)?;
+    let openai_api_key = stored_settings.openai_api_key.clone();
+
+    let (result, output) = tauri::async_runtime::spawn_blocking(move || {
+        let mut output = String::new();
+        let result = crate::builder::run_build(
+            &project_root,
+            &crate::builder::BuildRequest {
+                source_path: &build.source_path,
+                db_path: &build.db_path,
+                collection_id: &build.collection_id,
+                collection_name: &build.collection_name,
+                collection_icon: &build.collection_icon,
+                openai_api_key: openai_api_key.as_deref(),
+                embeddings: !build.local_only,
+                node_binary_path: preferences.node_binary_path.as_deref(),
+                npm_binary_path: preferences.npm_binary_path.as_deref(),
+            },
+            &mut output,
+        )
+        .and_then(|_| crate::builder::build_summary(&build.db_path));
+        (result, output)
+    })
+    .await
+    .map_err(|e| e.to_string())?;
+    transcript.push_str(&output);
+    result
+}
+
+/// Build with the built-in Rust builder, then embed chunks when an OpenAI key
+/// is configured. Embedding failures leave a searchable database without
+/// vectors, as the Node pipeline does.
+async fn run_native_build(
+    app: &AppHandle,
+    stored_settings: &Settings,
+    build: ProjectBuild,
+    transcript: &mut String,
+) -> Result<BuildSummary, String> {
+    let db_path = build.db_path.clone();
+    let local_only = build.local_only;
+    let mut summary = tauri::async_runtime::spawn_blocking(move || {
+        crate::native_builder::build_project(&crate::native_builder::NativeBuildRequest {
+            source_path: std::path::Path::new(&build.source_path),
+            db_path: &build.db_path,
+            collection_id: &build.collection_id,
+            collection_name: &build.collection_name,
+            collection_icon: &build.collection_icon,
+            incremental: build.incremental,
+        })
+    })
+    .await
+    .map_err(|e| e.to_string())??;
+
+    if let Some(api_key) = stored_settings
+        .openai_api_key
+        .as_deref()
+        .filter(|_| !local_only)
+    {
+        let client = app.state::<HttpClient>().0.clone();
+        match crate::native_builder::embed_chunks(&client, api_key, &db_path).await {
+            Ok(embedded) => summary.chunks_embedded = embedded as i64,
+            Err(e) => {
+                let warning = format!(
+                    "Warning: chunk embeddings skipped: {}",
+                    ai::redact_secrets(&e, stored_settings)
+                );
+                eprintln!("{}", warning);
+                transcript.push_str(&warning);
+                transcript.push('\n');
+            }
+        }
+    }
+    Ok(summary)
+}
+
+// Note: Mutex poisoning is mitigated by panic = "abort" in release profile.
+// rusqlite::Connection is not Sync, so Mutex is required over RwLock.
+/// Collections of the active project, with the user's overrides applied and
+/// pinned collections first. Hidden collections are left out unless
+/// `include_hidden` is set.
+#[tauri::command]
+pub fn get_collections(
+    manager: State<'_, std::sync::Mutex<crate::projects::ProjectManager>>,
+    user_state: State<'_, UserStateDb>,
+    include_hidden: Option<bool>,
+    sort: Option<String>,
+) -> Result<Vec<Collection>, String> {
+    let sort = sort.as_deref().map_or(
+        Ok(queries::CollectionSort::SortOrder),
+        queries::CollectionSort::parse,
+    )?;
+    let (project_id, mut collections) = {
+        let mgr = lock_manager(&manager)?;
+        (
+            mgr.registry.active_project_id.clone(),
+            queries::collections(mgr.active_connection()?, sort)?,
+        )
+    };
+    let conn = lock_user_state(&user_state)?;
+    let overrides = collection_overrides(&conn, &project_id)?;
+    for collection in &mut collections {
+        let Some(user_override) = overrides.iter().find(|o| o.collection_id == collection.id)
+        else {
+            continue;
+        };
+        if let Some(description) = &user_override.custom_description {
+            collection.description = Some(description.clone());
+        }
+        if let Some(icon) = &user_override.custom_icon {
+            collection.icon = icon.clone();
+        }
+        collection.pinned = user_override.pinned;
+        collection.hidden = user_override.hidden;
+    }
+    if !include_hidden.unwrap_or(false) {
+        collections.retain(|collection| !collection.hidden);
+    }
+    collections.sort_by_key(|collection| !collection.pinned);
+    Ok(collections)
+}
+
+pub(super) fn collection_overrides(
+    conn: &rusqlite::Connection,
+    project_id: &str,
+) -> Result<Vec<CollectionOverride>, String> {
+    let mut stmt = conn
+        .prepare_cached(
+            "SELECT project_id, collection_id, custom_description, custom_icon, pinned, hidden,
+                    updated_at
+             FROM collection_overrides
+             WHERE project_id = ?1",
+        )
+        .map_err(|e| e.to_string())?;
+    let rows = stmt
+        .query_map(params![project_id], |row| {
+            Ok(CollectionOverride {
+                project_id: row.get(0)?,
+                collection_id: row.get(1)?,
+                custom_description: row.get(2)?,
+                custom_icon: row.get(3)?,
+                pinned: row.get::<_, i64>(4)? != 0,
+                hidden: row.get::<_, i64>(5)? != 0,
+                updated_at: row.get(6)?,
+            })
+        })
+        .map_err(|e| e.to_string())?;
+    rows.collect::<Result<Vec<_>, _>>()
+        .map_err(|e| e.to_string())
+}
+
+/// Store a user's description, icon and pin for a collection. Blank text
+/// falls back to the built value.
+#[tauri::command]
+pub fn set_collection_override(
+    user_state: State<'_, UserStateDb>,
+    project_id: String,
+    collection_id: String,
+    custom_description: Option<String>,
+    custom_icon: Option<String>,
+    pinned: bool,
+) -> Result<CollectionOverride, String> {
+    let custom_description = custom_description.filter(|d| !d.trim().is_empty());
+    let custom_icon = custom_icon.filter(|i| !i.trim().is_empty());
+    let conn = user_state.0.lock().map_err(|e| e.to_string())?;
+    conn.execute(
+        "INSERT INTO collection_overrides
+            (project_id, collection_id, custom_description, custom_icon, pinned, updated_at)
+         VALUES (?1, ?2, ?3, ?4, ?5, ?6)
+         ON CONFLICT(project_id, collection_id)
+         DO UPDATE SET custom_description = excluded.custom_description,
+                       custom_icon = excluded.custom_icon,
+                       pinned = excluded.pinned,
+                       updated_at = excluded.updated_at",
+        params![
+            &project_id,
+            &collection_id,
+            &custom_description,
+            &custom_icon,
+            pinned as i64,
+            unix_timestamp_i64()
+        ],
+    )
+    .map_err(|e| e.to_string())?;
+    saved_collection_override(&conn, project_id, collection_id)
+}
+
+/// Hide a collection from navigation, badges and, with the matching
+/// preference, search, or bring it back.
+#[tauri::command]
+pub fn set_collection_hidden(
+    user_state: State<'_, UserStateDb>,
+    project_id: String,
+    collection_id: String,
+    hidden: bool,
+) -> Result<CollectionOverride, String> {
+    let conn = user_state.0.lock().map_err(|e| e.to_string())?;
+    conn.execute(
+        "INSERT INTO collection_overrides (project_id, collection_id, hidden, updated_at)
+         VALUES (?1, ?2, ?3, ?4)
+         ON CONFLICT(project_id, collection_id)
+         DO UPDATE SET hidden = excluded.hidden, updated_at = excluded.updated_at",
+        params![
+            &project_id,
+            &collection_id,
+            hidden as i64,
+            unix_timestamp_i64()
+        ],
+    )
+    .map_err(|e| e.to_string())?;
+    saved_collection_override(&conn, project_id, collection_id)
+}
+
+/// The override just written, removing it when nothing is left set.
+fn saved_collection_override(
+    conn: &rusqlite::Connection,
+    project_id: String,
+    collection_id: String,
+) -> Result<CollectionOverride, String> {
+    let saved = collection_overrides(conn, &project_id)?
+        .into_iter()
+        .find(|o| o.collection_id == collection_id)
+        .ok_or_else(|| format!("Collection override for '{}' not found", collection_id))?;
+    if saved.custom_description.is_none()
+        && saved.custom_icon.is_none()
+        && !saved.pinned
+        && !saved.hidden
+    {
+        conn.execute(
+            "DELETE FROM collection_overrides WHERE project_id = ?1 AND collection_id = ?2",
+            params![&project_id, &collection_id],
+        )
+        .map_err(|e| e.to_string())?;
+    }
+    Ok(saved)
+}
+
+#[tauri::command]
+pub fn set_project_local_only(
+    app: AppHandle,
+    manager: State<'_, std::sync::Mutex<ProjectManager>>,
+    project_id: String,
+    local_only: bool,
+) -> Result<(), String> {
+    let mut mgr = manager.lock().map_err(|e| e.to_string())?;
+    let project = mgr
+        .registry
+        .projects
+        .iter_mut()
+        .find(|p| p.id == project_id)
+        .ok_or_else(|| format!("Project '{}' not found", project_id))?;
+    project.local_only = local_only;
+    crate::projects::save_registry(&app, &mgr.registry)?;
+    Ok(())
+}
+
+/// Replace the collections left out of AI retrieval for a project.
+#[tauri::command]
+pub fn set_project_excluded_collections(
+    app: AppHandle,
+    manager: State<'_, std::sync::Mutex<ProjectManager>>,
+    project_id: String,
+    collection_ids: Vec<String>,
+) -> Result<(), String> {
+    let mut mgr = manager.lock().map_err(|e| e.to_string())?;
+    let project = mgr
+        .registry
+        .projects
+        .iter_mut()
+        .find(|p| p.id == project_id)
+        .ok_or_else(|| format!("Project '{}' not found", project_id))?;
+    let mut collection_ids: Vec<String> = collection_ids
+        .into_iter()
+        .map(|id| id.trim().to_string())
+        .filter(|id| !id.is_empty())
+        .collect();
+    collection_ids.sort();
+    collection_ids.dedup();
+    project.excluded_collections = collection_ids;
+    crate::projects::save_registry(&app, &mgr.registry)?;
+    Ok(())
+}
+
+/// Override the hybrid search settings for one project; None goes back to
+/// those in `Settings`.
+#[tauri::command]
+pub fn set_project_retrieval_config(
+    app: AppHandle,
+    manager: State<'_, std::sync::Mutex<ProjectManager>>,
+    project_id: String,
+    config: Option<RetrievalConfig>,
+) -> Result<(), String> {
+    if let Some(config) = &config {
+        crate::settings::validate_retrieval(config)?;
+    }
+    let mut mgr = manager.lock().map_err(|e| e.to_string())?;
+    let project = mgr
+        .registry
+        .projects
+        .iter_mut()
+        .find(|p| p.id == project_id)
+        .ok_or_else(|| format!("Project '{}' not found", project_id))?;
+    project.retrieval = config;
+    crate::projects::save_registry(&app, &mgr.registry)?;
+    Ok(())
+}
+
+#[tauri::command]
+pub fn set_project_glossary_collection(
+    app: AppHandle,
+    manager: State<'_, std::sync::Mutex<ProjectManager>>,
+    project_id: String,
+    glossary_collection: Option<String>,
+) -> Result<(), String> {
+    let mut mgr = manager.lock().map_err(|e| e.to_string())?;
+    let project = mgr
+        .registry
+        .projects
+        .iter_mut()
+        .find(|p| p.id == project_id)
+        .ok_or_else(|| format!("Project '{}' not found", project_id))?;
+    project.glossary_collection = glossary_collection
+        .map(|c| c.trim().to_string())
+        .filter(|c| !c.is_empty());
+    crate::projects::save_registry(&app, &mgr.registry)?;
+    Ok(())
+}
+
+#[tauri::command]
+pub fn list_projects(
+    manager: State<'_, std::sync::Mutex<ProjectManager>>,
+) -> Result<Vec<crate::projects::Project>, String> {
+    let mgr = manager.lock().map_err(|e| e.to_string())?;
+    Ok(mgr.projects_with_status())
+}
+
+#[tauri::command]
+pub fn get_active_project_id(
+    manager: State<'_, std::sync::Mutex<ProjectManager>>,
+) -> Result<String, String> {
+    let mgr = manager.lock().map_err(|e| e.to_string())?;
+    Ok(mgr.registry.active_project_id.clone())
+}
+
+/// Resolve the saved session for a project against its current database.
+/// A document removed by a rebuild falls back to the first root document of
+/// its collection (or of the project when the collection is gone too).
+fn resolve_last_session(
+    manager: &std::sync::Mutex<ProjectManager>,
+    user_state: &UserStateDb,
+    project_id: &str,
+) -> Result<Option<LastSession>, String> {
+    let saved = {
+        let conn = lock_user_state(user_state)?;
+        conn.query_row(
+            "SELECT collection_id, doc_slug, anchor_id, updated_at
+             FROM last_sessions WHERE project_id = ?1",
+            params![project_id],
+            |row| {
+                Ok(LastSession {
+                    project_id: project_id.to_string(),
+                    collection_id: row.get(0)?,
+                    doc_slug: row.get(1)?,
+                    anchor_id: row.get(2)?,
+                    updated_at: row.get(3)?,
+                    is_fallback: false,
+                })
+            },
+        )
+        .optional()
+        .map_err(|e| e.to_string())?
+    };
+    let Some(mut session) = saved else {
+        return Ok(None);
+    };
+
+    let mgr = lock_manager(manager)?;
+    let conn = mgr.connection(project_id)?;
+    if document_id_for_slug(conn, &session.doc_slug)?.is_some() {
+        return Ok(Some(session));
+    }
+
+    let root: Option<(String, String)> = conn
+        .query_row(
+            "SELECT collection_id, slug FROM documents
+             WHERE parent_slug = ''
+             ORDER BY (collection_id = ?1) DESC, (section = '') DESC, sort_order ASC, id ASC
+             LIMIT 1",
+            params![&session.collection_id],
+            |row| Ok((row.get(0)?, row.get(1)?)),
+        )
+        .optional()
+        .map_err(|e| e.to_string())?;
+    Ok(root.map(|(collection_id, doc_slug)| {
+        session.collection_id = collection_id;
+        session.doc_slug = doc_slug;
+        session.anchor_id = None;
+        session.is_fallback = true;
+        session
+    }))
+}
+
+#[tauri::command]
+pub fn get_last_session(
+    manager: State<'_, std::sync::Mutex<ProjectManager>>,
+    user_state: State<'_, UserStateDb>,
+    project_id: String,
+) -> Result<Option<LastSession>, String> {
+    resolve_last_session(&manager, &user_state, &project_id)
+}
+
+#[tauri::command]
+pub fn save_last_session(
+    user_state: State<'_, UserStateDb>,
+    project_id: String,
+    collection_id: String,
+    doc_slug: String,
+    anchor_id: Option<String>,
+) -> Result<(), String> {
+    let conn = user_state.0.lock().map_err(|e| e.to_string())?;
+    conn.execute(
+        "INSERT INTO last_sessions (project_id, collection_id, doc_slug, anchor_id, updated_at)
+         VALUES (?1, ?2, ?3, ?4, ?5)
+         ON CONFLICT(project_id) DO UPDATE SET
+            collection_id = excluded.collection_id,
+            doc_slug = excluded.doc_slug,
+            anchor_id = excluded.anchor_id,
+            updated_at = excluded.updated_at",
+        params![
+            project_id,
+            collection_id,
+            doc_slug,
+            anchor_id,
+            unix_timestamp_i64()
+        ],
+    )
+    .map_err(|e| e.to_string())?;
+    Ok(())
+}
+
+/// Activation rows kept for recency ordering; older rows are trimmed on insert.
+const PROJECT_ACTIVATION_RETENTION: i64 = 500;
+
+#[tauri::command]
+pub fn set_active_project(
+    app: AppHandle,
+    manager: State<'_, std::sync::Mutex<ProjectManager>>,
+    user_state: State<'_, UserStateDb>,
+    project_id: String,
+) -> Result<ProjectSwitchTiming, String> {
+    let started = std::time::Instant::now();
+    let (previous_project_id, lock_wait, validation, save) = {
+        let mut mgr = lock_manager(&manager)?;
+        let lock_wait = started.elapsed();
+        let previous = mgr.registry.active_project_id.clone();
+        let step = std::time::Instant::now();
+        mgr.set_active_project(&project_id)?;
+        let validation = step.elapsed();
+        let step = std::time::Instant::now();
+        crate::projects::save_registry(&app, &mgr.registry)?;
+        (previous, lock_wait, validation, step.elapsed())
+    };
+
+    {
+        let conn = lock_user_state(&user_state)?;
+        conn.execute(
+            "INSERT INTO project_activations (project_id, activated_at) VALUES (?1, ?2)",
+            params![&project_id, unix_timestamp_i64()],
+        )
+        .map_err(|e| e.to_string())?;
+        conn.execute(
+            "DELETE FROM project_activations
+             WHERE id NOT IN (
+                SELECT id FROM project_activations ORDER BY id DESC LIMIT ?1
+             )",
+            params![PROJECT_ACTIVATION_RETENTION],
+        )
+        .map_err(|e| e.to_string())?;
+    }
+
+    let timing = ProjectSwitchTiming {
+        lock_wait_ms: millis(lock_wait),
+        validation_ms: millis(validation),
+        save_registry_ms: millis(save),
+        total_ms: millis(started.elapsed()),
+    };
+    crate::events::emit(
+        &app,
+        "active-project-changed",
+        serde_json::json!({
+            "projectId": &project_id,
+            "previousProjectId": previous_project_id,
+            "timing": &timing,
+        }),
+    );
+
+    let handle = app.clone();
+    tauri::async_runtime::spawn_blocking(move || warm_project(&handle, &project_id));
+    Ok(timing)
+}
+
+/// Run the queries the frontend makes first after a switch — the collection
+/// list and the first collection's navigation — so the database pages they
+/// read are already in the OS cache. Uses a connection of its own, so
+/// foreground commands never wait on the manager lock for it. Skipped if the
+/// user has switched again in the meantime.
+fn warm_project(app: &AppHandle, project_id: &str) {
+    let db_path = {
+        let manager = app.state::<std::sync::Mutex<ProjectManager>>();
+        let Ok(mgr) = manager.lock() else {
+            return;
+        };
+        if mgr.registry.active_project_id != project_id {
+            return;
+        }
+        match mgr.connection(project_id).map(|conn| conn.path()) {
+            Ok(Some(path)) if !path.is_empty() => std::path::PathBuf::from(path),
+            _ => return,
+        }
+    };
+    let Ok(conn) = crate::db::open_project_db(&db_path) else {
+        return;
+    };
+    if let Ok(collections) = queries::collections(&conn, queries::CollectionSort::SortOrder) {
+        if let Some(first) = collections.first() {
+            let _ = queries::navigation(&conn, &first.id);
+        }
+    }
+}
+
+/// Projects ordered by when they were last made active. Projects that have
+/// never been activated, or are no longer registered, are omitted.
+#[tauri::command]
+pub fn get_recent_projects(
+    manager: State<'_, std::sync::Mutex<ProjectManager>>,
+    user_state: State<'_, UserStateDb>,
+    limit: Option<i32>,
+) -> Result<Vec<RecentProject>, String> {
+    let limit = limit.unwrap_or(10).clamp(1, 100);
+    let activations = {
+        let conn = lock_user_state(&user_state)?;
+        let mut stmt = conn
+            .prepare_cached(
+                "SELECT project_id, MAX(activated_at), COUNT(*)
+                 FROM project_activations
+                 GROUP BY project_id
+                 ORDER BY MAX(activated_at) DESC, MAX(id) DESC",
+            )
+            .map_err(|e| e.to_string())?;
+        let rows = stmt
+            .query_map([], |row| {
+                Ok((
+                    row.get::<_, String>(0)?,
+                    row.get::<_, i64>(1)?,
+                    row.get::<_, i64>(2)?,
+                ))
+            })
+            .map_err(|e| e.to_string())?;
+        rows.collect::<Result<Vec<_>, _>>()
+            .map_err(|e| e.to_string())?
+    };
+
+    let mgr = lock_manager(&manager)?;
+    Ok(activations
+        .into_iter()
+        .filter_map(|(project_id, last_used_at, activation_count)| {
+            let project = mgr.registry.projects.iter().find(|p| p.id == project_id)?;
+            Some(RecentProject {
+                project: project.clone(),
+                last_used_at,
+                activation_count,
+            })
+        })
+        .take(limit as usize)
+        .collect())
+}
+
+#[tauri::command]
+pub async fn add_project(
+    app: AppHandle,
+    manager: State<'_, std::sync::Mutex<ProjectManager>>,
+    user_state: State<'_, UserStateDb>,
+    build_queue: State<'_, BuildQueue>,
+    name: String,
+    icon: String,
+    source_path: String,
+) -> Result<crate::projects::Project, String> {
+    let stored_settings = settings::load_settings(&app).unwrap_or_default();
+
+    // Generate a slug ID from the name
+    let id = crate::projects::project_id_from_name(&name);
+
+    // Determine output DB path in app data directory
+    let app_data_dir = app.path().app_data_dir().map_err(|e| e.to_string())?;
+    let projects_dir = app_data_dir.join("projects");
+    std::fs::create_dir_all(&projects_dir).map_err(|e| e.to_string())?;
+    let db_path = projects_dir.join(format!("{}.db", id));
+
+    let _turn = build_queue.wait_for_turn(&app, &id, "add").await?;
+
+    // Emit build started event
+    crate::events::emit(
+        &app,
+        "project-build-started",
+        serde_json::json!({ "projectId": &id }),
+    );
+    let build_started = std::time::Instant::now();
+
+    if let Err(failure) = run_project_build(
+        &app,
+        &stored_settings,
+        ProjectBuild {
+            source_path: source_path.clone(),
+            db_path: db_path.clone(),
+            collection_id: id.clone(),
+            collection_name: name.clone(),
+            collection_icon: icon.clone(),
+            incremental: false,
+            local_only: false,
+        },
+    )
+    .await
+    {
+        let build_err = failure.message();
+        crate::events::emit(
+            &app,
+            "project-build-error",
+            serde_json::json!({
+                "projectId": &id,
+                "error": build_err.clone(),
+                "logPath": &failure.log_path,
+            }),
+        );
+        crate::notifications::notify_build_finished(
+            &app,
+            &id,
+            &name,
+            build_started.elapsed(),
+            Some(&failure.error),
+        );
+        return Err(build_err);
+    }
+
+    expire_header_counts(&id);
+    crate::events::emit(
+        &app,
+        "project-build-complete",
+        serde_json::json!({ "projectId": &id }),
+    );
+    crate::notifications::notify_build_finished(&app, &id, &name, build_started.elapsed(), None);
+
+    // Create the project entry
+    let project = crate::projects::Project {
+        id: id.clone(),
+        name: name.clone(),
+        icon,
+        built_in: false,
+        source_path: Some(source_path.clone()),
+        db_path: Some(format!("projects/{}.db", id)),
+        last_built: Some(unix_timestamp()),
+        built_from_db: false,
+        collections: vec![],
+        glossary_collection: None,
+        local_only: false,
+        excluded_collections: vec![],
+        retrieval: None,
+        status: None,
+    };
+
+    // Register in ProjectManager
+    with_states(&manager, &user_state, |mgr, user_state_conn| {
+        mgr.open_connection(&id, &db_path)?;
+        if let Some(project_conn) = mgr.connections.get(&id) {
+            let _ = record_project_change_feed(user_state_conn, project_conn, &id, &source_path);
+        }
+        mgr.add_project(project.clone());
+        crate::projects::save_registry(&app, &mgr.registry)
+    })?;
+
+    crate::events::emit(
+        &app,
+        "project-added",
+        serde_json::json!({ "projectId": &project.id }),
+    );
+    Ok(project)
+}
+
+/// Register an externally built project database. The file is validated and
+/// copied into the app data dir; importing again under the same name replaces
+/// the previous copy.
+#[tauri::command]
+pub fn add_project_from_db(
+    app: AppHandle,
+    manager: State<'_, std::sync::Mutex<ProjectManager>>,
+    name: String,
+    icon: String,
+    db_path: String,
+) -> Result<crate::projects::Project, String> {
+    let id = crate::projects::project_id_from_name(&name);
+    if id.is_empty() {
+        return Err("Project name must contain letters or digits".to_string());
+    }
+
+    let replacing = {
+        let mgr = manager.lock().map_err(|e| e.to_string())?;
+        match mgr.registry.projects.iter().find(|p| p.id == id) {
+            Some(existing) if existing.built_from_db => true,
+            Some(_) => {
+                return Err(format!(
+                    "A project named '{}' already exists. Choose a different name.",
+                    name
+                ))
+            }
+            None => false,
+        }
+    };
+
+    let source = crate::db::open_project_db(std::path::Path::new(&db_path))?;
+    let missing = crate::db::missing_project_tables(&source)?;
+    if !missing.is_empty() {
+        return Err(format!(
+            "Not a Dalil project database: missing table(s) {}",
+            missing.join(", ")
+        ));
+    }
+
+    let app_data_dir = app.path().app_data_dir().map_err(|e| e.to_string())?;
+    let projects_dir = app_data_dir.join("projects");
+    std::fs::create_dir_all(&projects_dir).map_err(|e| e.to_string())?;
+    let relative_path = format!("projects/{}.db", id);
+    let dest = app_data_dir.join(&relative_path);
+    // Copy into a temporary file first so a failed import leaves any existing copy intact.
+    // VACUUM INTO also folds in pending WAL content, which a plain file copy would miss.
+    let staging = projects_dir.join(format!("{}.db.importing", id));
+    let _ = std::fs::remove_file(&staging);
+    source
+        .execute(
+            "VACUUM INTO ?1",
+            [staging.to_str().ok_or("Invalid destination path")?],
+        )
+        .map_err(|e| format!("Failed to copy database: {}", e))?;
+    drop(source);
+
+    let project = {
+        let mut mgr = manager.lock().map_err(|e| e.to_string())?;
+        mgr.close_connection(&id);
+        if let Err(e) = std::fs::rename(&staging, &dest) {
+            let _ = std::fs::remove_file(&staging);
+            return Err(e.to_string());
+        }
+        mgr.open_connection(&id, &dest)?;
+
+        let project = crate::projects::Project {
+            id: id.clone(),
+            name: name.clone(),
+            icon,
+            built_in: false,
+            source_path: None,
+            db_path: Some(relative_path),
+            last_built: Some(unix_timestamp()),
+            built_from_db: true,
+            collections: vec![],
+            glossary_collection: None,
+            local_only: false,
+            excluded_collections: vec![],
+            retrieval: None,
+            status: None,
+        };
+        if replacing {
+            if let Some(existing) = mgr.registry.projects.iter_mut().find(|p| p.id == id) {
+                existing.name = project.name.clone();
+                existing.icon = project.icon.clone();
+                existing.last_built = project.last_built.clone();
+            }
+        } else {
+            mgr.add_project(project.clone());
+        }
+        crate::projects::save_registry(&app, &mgr.registry)?;
+        project
+    };
+
+    crate::events::emit(
+        &app,
+        "project-added",
+        serde_json::json!({ "projectId": &project.id }),
+    );
+    Ok(project)
+}
+
+/// Databases in the projects folder that no registered project points at,
+/// typically left behind when projects.json was lost or corrupted.
+#[tauri::command]
+pub fn list_orphaned_projects(
+    app: AppHandle,
+    manager: State<'_, std::sync::Mutex<ProjectManager>>,
+) -> Result<Vec<crate::projects::OrphanedProject>, String> {
+    let app_data_dir = app.path().app_data_dir().map_err(|e| e.to_string())?;
+    let mgr = manager.lock().map_err(|e| e.to_string())?;
+    Ok(crate::projects::orphaned_project_dbs(
+        &app_data_dir,
+        &mgr.registry,
+    ))
+}
+
+/// An orphaned database to register again, under a name chosen by the user.
+#[derive(Debug, serde::Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct OrphanRecovery {
+    pub id: String,
+    pub name: String,
+    pub icon: String,
+}
+
+/// Register orphaned databases as projects again. Each keeps the id it was
+/// created under, so bookmarks and other saved state reattach to it. They come
+/// back as imported databases because their source folders are not recorded.
+#[tauri::command]
+pub fn recover_orphaned_projects(
+    app: AppHandle,
+    manager: State<'_, std::sync::Mutex<ProjectManager>>,
+    projects: Vec<OrphanRecovery>,
+) -> Result<Vec<crate::projects::Project>, String> {
+    let app_data_dir = app.path().app_data_dir().map_err(|e| e.to_string())?;
+    let mut recovered = Vec::new();
+    {
+        let mut mgr = manager.lock().map_err(|e| e.to_string())?;
+        let orphans = crate::projects::orphaned_project_dbs(&app_data_dir, &mgr.registry);
+        for request in projects {
+            let name = request.name.trim();
+            if name.is_empty() {
+                return Err(format!("A name is required for '{}'", request.id));
+            }
+            let orphan = orphans
+                .iter()
+                .find(|o| o.id == request.id)
+                .ok_or_else(|| format!("No orphaned database '{}'", request.id))?;
+            if mgr.registry.projects.iter().any(|p| p.id == orphan.id) {
+                return Err(format!("A project with id '{}' already exists", orphan.id));
+            }
+
+            let path = app_data_dir.join(&orphan.db_path);
+            let conn = crate::db::open_project_db(&path)?;
+            let missing = crate::db::missing_project_tables(&conn)?;
+            if !missing.is_empty() {
+                return Err(format!(
+                    "'{}' is not a Dalil project database: missing table(s) {}",
+                    orphan.db_path,
+                    missing.join(", ")
+                ));
+            }
+            drop(conn);
+            mgr.open_connection(&orphan.id, &path)?;
+
+            let project = crate::projects::Project {
+                id: orphan.id.clone(),
+                name: name.to_string(),
+                icon: request.icon,
+                built_in: false,
+                source_path: None,
+                db_path: Some(orphan.db_path.clone()),
+                last_built: Some(unix_timestamp()),
+                built_from_db: true,
+                collections: vec![],
+                glossary_collection: None,
+                local_only: false,
+                excluded_collections: vec![],
+                retrieval: None,
+                status: None,
+            };
+            mgr.add_project(project.clone());
+            crate::projects::save_registry(&app, &mgr.registry)?;
+            recovered.push(project);
+        }
+    }
+
+    for project in &recovered {
+        crate::events::emit(
+            &app,
+            "project-added",
+            serde_json::json!({ "projectId": &project.id }),
+        );
+    }
+    Ok(recovered)
+}
+
+/// Replace the project registry with projects.json.bak, the version before
+/// the last save. The registry being replaced becomes the new backup, so
+/// restoring twice undoes the restore.
+#[tauri::command]
+pub fn restore_registry_backup(
+    app: AppHandle,
+    manager: State<'_, std::sync::Mutex<ProjectManager>>,
+) -> Result<Vec<crate::projects::Project>, String> {
+    let app_data_dir = app.path().app_data_dir().map_err(|e| e.to_string())?;
+    let restored = crate::projects::load_registry_backup(&app_data_dir)?;
+
+    let (added, removed, previous_active, active, projects) = {
+        let mut mgr = manager.lock().map_err(|e| e.to_string())?;
+        let previous: Vec<String> = mgr.registry.projects.iter().map(|p| p.id.clone()).collect();
+        let previous_active = mgr.registry.active_project_id.clone();
+
+        for id in &previous {
+            if !restored.projects.iter().any(|p| &p.id == id) {
+                mgr.close_connection(id);
+                mgr.unavailable.remove(id);
+            }
+        }
+        for project in restored.projects.iter().filter(|p| !p.built_in) {
+            let Some(db_path) = project.db_path.as_ref().map(|db| app_data_dir.join(db)) else {
+                continue;
+            };
+            // Reopen even when connected, in case the restored entry names another file
+            mgr.close_connection(&project.id);
+            if db_path.exists() {
+                if let Err(e) = mgr.open_connection(&project.id, &db_path) {
+                    mgr.unavailable.insert(project.id.clone(), e);
+                }
+            } else {
+                mgr.unavailable.insert(
+                    project.id.clone(),
+                    format!("database not found at {:?}", db_path),
+                );
+            }
+        }
+
+        mgr.registry = restored;
+        if !mgr
+            .connections
+            .contains_key(&mgr.registry.active_project_id)
+        {
+            if let Some(fallback) = mgr
+                .registry
+                .projects
+                .iter()
+                .find(|p| mgr.connections.contains_key(&p.id))
+                .map(|p| p.id.clone())
+            {
+                mgr.registry.active_project_id = fallback;
+            }
+        }
+        crate::projects::save_registry(&app, &mgr.registry)?;
+
+        let added: Vec<String> = mgr
+            .registry
+            .projects
+            .iter()
+            .filter(|p| !previous.contains(&p.id))
+            .map(|p| p.id.clone())
+            .collect();
+        let removed: Vec<String> = previous
+            .into_iter()
+            .filter(|id| !mgr.registry.projects.iter().any(|p| &p.id == id))
+            .collect();
+        (
+            added,
+            removed,
+            previous_active,
+            mgr.registry.active_project_id.clone(),
+            mgr.projects_with_status(),
+        )
+    };
+
+    for id in &removed {
+        crate::events::emit(
+            &app,
+            "project-removed",
+            serde_json::json!({ "projectId": id }),
+        );
+    }
+    for id in &added {
+        crate::events::emit(
+            &app,
+            "project-added",
+            serde_json::json!({ "projectId": id }),
+        );
+    }
+    if active != previous_active {
+        crate::events::emit(
+            &app,
+            "active-project-changed",
+            serde_json::json!({
+                "projectId": active,
+                "previousProjectId": previous_active,
+            }),
+        );
+    }
+    Ok(projects)
+}
+
+#[tauri::command]
+pub async fn rebuild_project(
+    app: AppHandle,
+    manager: State<'_, std::sync::Mutex<ProjectManager>>,
+    user_state: State<'_, UserStateDb>,
+    build_queue: State<'_, BuildQueue>,
+    project_id: String,
+    force: Option<bool>,
+) -> Result<BuildSummary, String> {
+    let stored_settings = settings::load_settings(&app).unwrap_or_default();
+
+    // Get project details
+    let (source_path, db_relative_path, name, icon, local_only) = {
+        let mgr = lock_manager(&manager)?;
+        let project = mgr
+            .registry
+            .projects
+            .iter()
+            .find(|p| p.id == project_id)
+            .ok_or_else(|| format!("Project '{}' not found", project_id))?;
+
+        if project.built_in {
+            return Err("Cannot rebuild built-in project".to_string());
+        }
+        if project.built_from_db {
+            return Err(format!(
+                "'{}' was imported from a prebuilt database and is built externally. Import the updated .db file to refresh it.",
+                project.name
+            ));
+        }
+
+        (
+            project
+                .source_path
+                .clone()
+                .ok_or("No source path for project")?,
+            project
+                .db_path
+                .clone()
+                .ok_or("No database path for project")?,
+            project.name.clone(),
+            project.icon.clone(),
+            project.local_only,
+        )
+    };
+
+    let app_data_dir = app.path().app_data_dir().map_err(|e| e.to_string())?;
+    let db_path = app_data_dir.join(&db_relative_path);
+
+    // Keep the old connection alive during the build so queries still work.
+    // We only swap it out after the new database is ready.
+
+    let _turn = build_queue
+        .wait_for_turn(&app, &project_id, "rebuild")
+        .await?;
+
+    crate::events::emit(
+        &app,
+        "project-build-started",
+        serde_json::json!({ "projectId": &project_id }),
+    );
+    let build_started = std::time::Instant::now();
+
+    let build = ProjectBuild {
+        source_path: source_path.clone(),
+        db_path: db_path.clone(),
+        collection_id: project_id.clone(),
+        collection_name: name.clone(),
+        collection_icon: icon.clone(),
+        incremental: !force.unwrap_or(false),
+        local_only,
+    };
+    let summary = match run_project_build(&app, &stored_settings, build).await {
+        Ok(summary) => summary,
+        Err(failure) => {
+            let build_err = failure.message();
+            crate::events::emit(
+                &app,
+                "project-build-error",
+                serde_json::json!({
+                    "projectId": &project_id,
+                    "error": build_err.clone(),
+                    "logPath": &failure.log_path,
+                }),
+            );
+            crate::notifications::notify_build_finished(
+                &app,
+                &project_id,
+                &name,
+                build_started.elapsed(),
+                Some(&failure.error),
+            );
+            return Err(build_err);
+        }
+    };
+
+    // Build succeeded — swap in the new connection in a single lock; if the new
+    // database cannot be opened the project keeps the old one
+    let slug_diff = {
+        let mut mgr = lock_manager(&manager)?;
+        let old_slugs = mgr
+            .connections
+            .get(&project_id)
+            .and_then(|conn| queries::document_slugs(conn).ok());
+        let renames = match mgr.connections.get(&project_id) {
+            Some(old_conn) => crate::slug_aliases::record_slug_aliases(old_conn, &db_path)
+                .unwrap_or_else(|e| {
+                    eprintln!("Warning: failed to record slug aliases: {}", e);
+                    vec![]
+                }),
+            None => vec![],
+        };
+        mgr.swap_connection(&project_id, &db_path)?;
+        if let Some(conn) = mgr.connections.get(&project_id) {
+            match crate::integrity::integrity_report(conn) {
+                Ok(report) => {
+                    for issue in report.issues {
+                        eprintln!(
+                            "Warning: build of '{}' has {} {} (e.g. {})",
+                            project_id,
+                            issue.count,
+                            issue.category,
+                            issue.examples.join(", ")
+                        );
+                    }
+                }
+                Err(e) => eprintln!("Warning: failed to check build integrity: {}", e),
+            }
+        }
+        let new_slugs = mgr
+            .connections
+            .get(&project_id)
+            .and_then(|conn| queries::document_slugs(conn).ok());
+
+        // Update last_built timestamp
+        if let Some(project) = mgr
+            .registry
+            .projects
+            .iter_mut()
+            .find(|p| p.id == project_id)
+        {
+            project.last_built = Some(unix_timestamp());
+        }
+        crate::projects::save_registry(&app, &mgr.registry)?;
+
+        old_slugs.zip(new_slugs).map(|(old_slugs, new_slugs)| {
+            // Renamed documents moved rather than disappeared
+            let mut removed: Vec<String> = old_slugs
+                .difference(&new_slugs)
+                .filter(|slug| !renames.iter().any(|r| &r.old_slug == *slug))
+                .cloned()
+                .collect();
+            removed.sort();
+            (removed, new_slugs, renames)
+        })
+    };
+    let _ = with_states(&manager, &user_state, |mgr, user_state_conn| {
+        match mgr.connections.get(&project_id) {
+            Some(project_conn) => {
+                record_project_change_feed(user_state_conn, project_conn, &project_id, &source_path)
+            }
+            None => Ok(()),
+        }
+    });
+
+    if let Some((removed, current, renames)) = slug_diff {
+        if !renames.is_empty() {
+            let migrated = {
+                let conn = lock_user_state(&user_state)?;
+                crate::slug_aliases::migrate_user_state_slugs(
+                    &conn,
+                    &project_id,
+                    &renames,
+                    unix_timestamp_i64(),
+                )
+            };
+            match migrated {
+                Ok(rows_changed) => crate::events::emit(
+                    &app,
+                    "project-slugs-migrated",
+                    serde_json::json!({
+                        "projectId": &project_id,
+                        "renames": &renames,
+                        "rowsChanged": rows_changed,
+                    }),
+                ),
+                Err(e) => eprintln!("Warning: failed to migrate user state slugs: {}", e),
+            }
+        }
+
+        let recorded = {
+            let conn = lock_user_state(&user_state)?;
+            record_doc_removals(&conn, &project_id, &removed, &current)
+        };
+        if let Err(e) = recorded {
+            eprintln!("Warning: failed to record removed documents: {}", e);
+        } else if !removed.is_empty() {
+            crate::events::emit(
+                &app,
+                "project-docs-removed",
+                serde_json::json!({ "projectId": &project_id, "count": removed.len() }),
+            );
+        }
+    }
+
+    expire_header_counts(&project_id);
+    crate::events::emit(
+        &app,
+        "project-build-complete",
+        serde_json::json!({ "projectId": &project_id, "summary": &summary }),
+    );
+    crate::notifications::notify_build_finished(
+        &app,
+        &project_id,
+        &name,
+        build_started.elapsed(),
+        None,
+    );
+
+    #[cfg(target_os = "macos")]
+    if settings::load_preferences(&app)
+        .unwrap_or_default()
+        .spotlight_export_enabled
+    {
+        if let Err(e) = refresh_spotlight_export(&app, &manager, &project_id) {
+            eprintln!("Warning: failed to refresh Spotlight export: {}", e);
+        }
+    }
+
+    Ok(summary)
+}
+
+/// Re-render one Markdown file from a project's source folder and serve the
+/// result from `get_document` until the next rebuild. Meant for editing docs
+/// with the app open; search, navigation and chunks are not updated.
+#[tauri::command]
+pub async fn reload_document(
+    app: AppHandle,
+    manager: State<'_, std::sync::Mutex<ProjectManager>>,
+    project_id: String,
+    file_path: String,
+) -> Result<Document, String> {
+    let source_path = {
+        let mgr = manager.lock().map_err(|e| e.to_string())?;
+        let project = mgr
+            .registry
+            .projects
+            .iter()
+            .find(|p| p.id == project_id)
+            .ok_or_else(|| format!("Project '{}' not found", project_id))?;
+        if project.built_in || project.built_from_db {
+            return Err(format!(
+                "'{}' has no source folder to reload from",
+                project.name
+            ));
+        }
+        project
+            .source_path
+            .clone()
+            .ok_or("No source path for project")?
+    };
+
+    let source = std::path::PathBuf::from(&source_path);
+    let file = std::path::PathBuf::from(&file_path);
+    let file = if file.is_absolute() {
+        file
+    } else {
+        source.join(file)
+    };
+    let canonical_source = source.canonicalize().map_err(|e| e.to_string())?;
+    let canonical_file = file
+        .canonicalize()
+        .map_err(|e| format!("Failed to read {}: {}", file_path, e))?;
+    if !canonical_file.starts_with(&canonical_source) {
+        return Err(format!(
+            "{} is outside the project source folder",
+            file_path
+        ));
+    }
+
+    let collection_id = project_id.clone();
+    let rendered = tauri::async_runtime::spawn_blocking(move || {
+        crate::native_builder::render_document(&canonical_source, &collection_id, &canonical_file)
+    })
+    .await
+    .map_err(|e| e.to_string())??;
+
+    let doc = {
+        let mut mgr = manager.lock().map_err(|e| e.to_string())?;
+        let mut doc =
+            queries::document(mgr.connection(&project_id)?, &rendered.slug).map_err(|_| {
+                format!(
+                    "'{}' is not in the built project yet. Rebuild to add new documents.",
+                    rendered.slug
+                )
+            })?;
+        doc.title = rendered.title;
+        doc.content_html = rendered.content_html;
+        doc.last_modified = Some(rendered.last_modified);
+        mgr.document_overlays
+            .entry(project_id.clone())
+            .or_default()
+            .insert(doc.slug.clone(), doc.clone());
+        doc
+    };
+
+    crate::events::emit(
+        &app,
+        "document-reloaded",
+        serde_json::json!({ "projectId": &project_id, "slug": &doc.slug }),
+    );
+    Ok(doc)
+}
+
+#[tauri::command]
+pub fn get_build_queue(build_queue: State<'_, BuildQueue>) -> Vec<QueuedBuild> {
+    build_queue.snapshot()
+}
+
+#[tauri::command]
+pub fn cancel_queued_build(
+    app: AppHandle,
+    build_queue: State<'_, BuildQueue>,
+    build_id: u64,
+) -> Result<(), String> {
+    build_queue.cancel(&app, build_id)
+}
+
+/// Records a project leaves behind that only make sense while it is
+/// registered; always removed with it.
+const PROJECT_RECORD_TABLES: [&str; 4] = [
+    "project_change_feed",
+    "project_activations",
+    "closed_documents",
+    "recent_searches",
+];
+
+/// The user's own data for a project, removed with it unless they choose to
+/// keep it for re-adding the project later.
+const PROJECT_USER_DATA_TABLES: [&str; 13] = [
+    "bookmarks",
+    "bookmark_folders",
+    "bookmark_tags",
+    "doc_notes",
+    "doc_note_revisions",
+    "doc_note_drafts",
+    "doc_highlights",
+    "doc_views",
+    "inbox_items",
+    "deleted_items",
+    "trash_id_remaps",
+    "last_sessions",
+    "collection_overrides",
+];
+
+fn remove_project_user_state(
+    conn: &rusqlite::Connection,
+    project_id: &str,
+    purge_user_state: bool,
+) -> Result<(), String> {
+    let tx = conn.unchecked_transaction().map_err(|e| e.to_string())?;
+    let tables = if purge_user_state {
+        &PROJECT_USER_DATA_TABLES[..]
+    } else {
+        &[]
+    };
+    for table in PROJECT_RECORD_TABLES.iter().chain(tables) {
+        tx.execute(
+            &format!("DELETE FROM {} WHERE project_id = ?1", table),
+            params![project_id],
+        )
+        .map_err(|e| e.to_string())?;
+    }
+    tx.commit().map_err(|e| e.to_string())
+}
+
+fn project_user_state_counts(
+    conn: &rusqlite::Connection,
+    project_id: &str,
+) -> Result<ProjectRemovalPreview, String> {
+    let count = |table: &str| -> Result<i64, String> {
+        conn.query_row(
+            &format!("SELECT COUNT(*) FROM {} WHERE project_id = ?1", table),
+            params![project_id],
+            |row| row.get(0),
+        )
+        .map_err(|e| e.to_string())
+    };
+    Ok(ProjectRemovalPreview {
+        project_id: project_id.to_string(),
+        bookmarks: count("bookmarks")?,
+        notes: count("doc_notes")?,
+        highlights: count("doc_highlights")?,
+        doc_views: count("doc_views")?,
+        inbox_items: count("inbox_items")?,
+        feed_entries: count("project_change_feed")?,
+        db_size_bytes: None,
+    })
+}
+
+/// What `remove_project` would delete: the user state it purges by default
+/// and the size of the project's database file.
+#[tauri::command]
+pub fn get_project_removal_preview(
+    app: AppHandle,
+    manager: State<'_, std::sync::Mutex<ProjectManager>>,
+    user_state: State<'_, UserStateDb>,
+    project_id: String,
+) -> Result<ProjectRemovalPreview, String> {
+    let db_relative_path = {
+        let mgr = lock_manager(&manager)?;
+        let project = mgr
+            .registry
+            .projects
+            .iter()
+            .find(|p| p.id == project_id)
+            .ok_or_else(|| format!("Project '{}' not found", project_id))?;
+        if project.built_in {
+            return Err("Cannot remove built-in project".to_string());
+        }
+        project.db_path.clone()
+    };
+    let mut preview = {
+        let conn = lock_user_state(&user_state)?;
+        project_user_state_counts(&conn, &project_id)?
+    };
+    if let Some(relative_path) = db_relative_path {
+        let app_data_dir = app.path().app_data_dir().map_err(|e| e.to_string())?;
+        preview.db_size_bytes = std::fs::metadata(app_data_dir.join(relative_path))
+            .ok()
+            .map(|metadata| metadata.len());
+    }
+    Ok(preview)
+}
+
+/// Unregister a project and delete its database file. Its user state is
+/// purged too unless `purge_user_state` is false, in which case only
+/// `PROJECT_RECORD_TABLES` are cleared.
+#[tauri::command]
+pub async fn remove_project(
+    app: AppHandle,
+    manager: State<'_, std::sync::Mutex<ProjectManager>>,
+    user_state: State<'_, UserStateDb>,
+    project_id: String,
+    purge_user_state: Option<bool>,
+) -> Result<(), String> {
+    let db_relative_path = {
+        let mgr = lock_manager(&manager)?;
+        let project = mgr
+            .registry
+            .projects
+            .iter()
+            .find(|p| p.id == project_id)
+            .ok_or_else(|| format!("Project '{}' not found", project_id))?;
+
+        if project.built_in {
+            return Err("Cannot remove built-in project".to_string());
+        }
+
+        project.db_path.clone()
+    };
+
+    // Remove from manager (closes connection, removes from registry)
+    {
+        let mut mgr = lock_manager(&manager)?;
+        mgr.remove_project(&project_id)?;
+        crate::projects::save_registry(&app, &mgr.registry)?;
+    }
+
+    // Delete the database file
+    if let Some(relative_path) = db_relative_path {
+        let app_data_dir = app.path().app_data_dir().map_err(|e| e.to_string())?;
+        let db_path = app_data_dir.join(&relative_path);
+        if db_path.exists() {
+            std::fs::remove_file(&db_path).map_err(|e| e.to_string())?;
+        }
+    }
+
+    #[cfg(target_os = "macos")]
+    if let Err(e) =
+        crate::spotlight::remove_export(&crate::spotlight::spotlight_root(&app)?, Some(&project_id))
+    {
+        eprintln!("Warning: failed to remove Spotlight export: {}", e);
+    }
+
+    {
+        let conn = lock_user_state(&user_state)?;
+        remove_project_user_state(&conn, &project_id, purge_user_state.unwrap_or(true))?;
+    }
+
+    crate::events::emit(
+        &app,
+        "project-removed",
+        serde_json::json!({ "projectId": &project_id }),
+    );
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{project_user_state_counts, remove_project_user_state};
+    use crate::user_state::apply_user_state_schema;
+    use rusqlite::Connection;
+
+    fn populated() -> Connection {
+        let conn = Connection::open_in_memory().unwrap();
+        apply_user_state_schema(&conn).unwrap();
+        for project in ["p", "other"] {
+            conn.execute_batch(&format!(
+                "INSERT INTO bookmarks (project_id, collection_id, doc_slug, title_snapshot, created_at, updated_at)
+                     VALUES ('{p}', 'ops', 'ops/restarts', 'Restarts', 1, 1);
+                 INSERT INTO bookmark_folders (project_id, name, created_at, updated_at) VALUES ('{p}', 'F', 1, 1);
+                 INSERT INTO bookmark_tags (project_id, name, created_at, updated_at) VALUES ('{p}', 'T', 1, 1);
+                 INSERT INTO doc_views VALUES ('{p}', 'ops/restarts', 1);
+                 INSERT INTO doc_notes (project_id, doc_slug, note, updated_at) VALUES ('{p}', 'ops/restarts', 'note', 1);
+                 INSERT INTO doc_note_revisions (project_id, doc_slug, note, created_at)
+                     VALUES ('{p}', 'ops/restarts', 'old', 1);
+                 INSERT INTO doc_note_drafts VALUES ('{p}', 'ops/restarts', 'unsaved', 1);
+                 INSERT INTO doc_highlights (project_id, doc_slug, selected_text, created_at)
+                     VALUES ('{p}', 'ops/restarts', 'text', 1);
+                 INSERT INTO doc_highlights (project_id, doc_slug, selected_text, created_at)
+                     VALUES ('{p}', 'ops/deploys', 'more', 1);
+                 INSERT INTO project_change_feed (project_id, commit_hash, author, committed_at,
+                                                  changed_files_json, changed_doc_slugs_json, recorded_at)
+                     VALUES ('{p}', 'abc', 'ana', '2026-01-01', '[]', '[]', 1);
+                 INSERT INTO deleted_items (project_id, kind, payload_json, deleted_at)
+                     VALUES ('{p}', 'bookmarks', '{{}}', 1);
+                 INSERT INTO last_sessions VALUES ('{p}', 'ops', 'ops/restarts', NULL, 1);
+                 INSERT INTO doc_removals VALUES ('{p}', 'ops/old', 1);
+                 INSERT INTO slug_migrations (project_id, old_slug, new_slug, table_name, rows_changed, migrated_at)
+                     VALUES ('{p}', 'ops/old', 'ops/new', 'bookmarks', 1, 1);
+                 INSERT INTO collection_overrides (project_id, collection_id, pinned, updated_at)
+                     VALUES ('{p}', 'ops', 1, 1);
+                 INSERT INTO project_activations (project_id, activated_at) VALUES ('{p}', 1);
+                 INSERT INTO closed_documents (project_id, doc_slug, closed_at) VALUES ('{p}', 'ops/restarts', 1);
+                 INSERT INTO recent_searches VALUES ('{p}', 'deploys', 1);
+                 INSERT INTO local_stats VALUES ('2026-01-01', 'searches', '{p}', 3);
+                 INSERT INTO inbox_items (project_id, content, created_at) VALUES ('{p}', 'later', 1);",
+                p = project
+            ))
+            .unwrap();
+        }
+        conn
+    }
+
+    /// Tables with a `project_id` column that still hold rows for `project_id`.
+    fn tables_with_rows(conn: &Connection, project_id: &str) -> Vec<String> {
+        let mut stmt = conn
+            .prepare(
+                "SELECT t.name FROM sqlite_master t
+                 WHERE t.type = 'table' AND EXISTS (
+                     SELECT 1 FROM pragma_table_info(t.name) c WHERE c.name = 'project_id'
+                 )
+                 ORDER BY t.name",
+            )
+            .unwrap();
+        let tables: Vec<String> = stmt
+            .query_map([], |row| row.get(0))
+            .unwrap()
+            .collect::<Result<_, _>>()
+            .unwrap();
+        tables
+            .into_iter()
+            .filter(|table| {
+                conn.query_row(
+                    &format!("SELECT COUNT(*) FROM {} WHERE project_id = ?1", table),
+                    [project_id],
+                    |row| row.get::<_, i64>(0),
+                )
+                .unwrap()
+                    > 0
+            })
+            .collect()
+    }
+
+    #[test]
+    fn purging_removes_the_projects_user_state() {
+        let conn = populated();
+        let preview = project_user_state_counts(&conn, "p").unwrap();
+        assert_eq!(
+            (
+                preview.bookmarks,
+                preview.notes,
+                preview.highlights,
+                preview.doc_views,
+                preview.inbox_items,
+                preview.feed_entries
+            ),
+            (1, 1, 2, 1, 1, 1)
+        );
+        let before = tables_with_rows(&conn, "other");
+
+        remove_project_user_state(&conn, "p", true).unwrap();
+        // Slug history and local usage stats are kept, as before
+        assert_eq!(
+            tables_with_rows(&conn, "p"),
+            ["doc_removals", "local_stats", "slug_migrations"]
+        );
+        assert_eq!(tables_with_rows(&conn, "other"), before);
+    }
+
+    #[test]
+    fn keeping_user_state_only_clears_project_records() {
+        let conn = populated();
+        let before = tables_with_rows(&conn, "p");
+
+        remove_project_user_state(&conn, "p", false).unwrap();
+        let after = tables_with_rows(&conn, "p");
+        let removed: Vec<&String> = before.iter().filter(|t| !after.contains(t)).collect();
+        assert_eq!(
+            removed,
+            [
+                "closed_documents",
+                "project_activations",
+                "project_change_feed",
+                "recent_searches"
+            ]
+        );
+        assert!(after.contains(&"doc_notes".to_string()));
+        assert_eq!(project_user_state_counts(&conn, "p").unwrap().highlights, 2);
+    }
+}
@@ -0,0 +1,729 @@
+//! Reading documents: listings, navigation, full-text search, tags, the
+//! glossary, document anchors and outlines, and share links.
+
+use super::activity::count_usage;
+use super::util::{lock_manager, lock_user_state, project_word_counts, unix_timestamp_i64};
+use crate::ai;
+use crate::models::*;
+use crate::projects::ProjectManager;
+use crate::queries;
+use crate::settings;
+use crate::share_links;
+use crate::user_state::UserStateDb;
+use rusqlite::{params, OptionalExtension};
+use tauri::{AppHandle, Manager, State};
+
+/// How many times the requested number of results to fetch when similar
+/// titles are collapsed, so a page of collapsed results is still full.
+const DEDUPE_OVERFETCH: i32 = 3;
+
+/// Searches remembered per project for query suggestions.
+const RECENT_SEARCHES_KEPT: i64 = 50;
+/// Most query suggestions returned, and most of those taken from recent
+/// searches.
+const QUERY_SUGGESTION_LIMIT: usize = 10;
+const RECENT_SEARCH_SUGGESTIONS: usize = 3;
+
+/// Default and largest page sizes for `list_all_documents`.
+const DOCUMENT_LISTING_PAGE: i64 = 200;
+const DOCUMENT_LISTING_MAX_PAGE: i64 = 1000;
+
+/// Metadata for every document in a project, a page at a time, optionally
+/// limited to one collection. Off the main thread, since word counts are
+/// counted from the text on first use for databases from older builds.
+#[tauri::command]
+pub async fn list_all_documents(
+    app: AppHandle,
+    project_id: String,
+    collection_id: Option<String>,
+    limit: Option<i64>,
+    offset: Option<i64>,
+) -> Result<DocumentListing, String> {
+    tauri::async_runtime::spawn_blocking(move || {
+        let manager = app.state::<std::sync::Mutex<ProjectManager>>();
+        let word_counts = project_word_counts(&manager, &project_id)?;
+        let mgr = lock_manager(&manager)?;
+        queries::document_listing(
+            mgr.connection(&project_id)?,
+            &word_counts,
+            collection_id.as_deref(),
+            limit
+                .unwrap_or(DOCUMENT_LISTING_PAGE)
+                .clamp(1, DOCUMENT_LISTING_MAX_PAGE),
+            offset.unwrap_or(0).max(0),
+        )
+    })
+    .await
+    .map_err(|e| e.to_string())?
+}
+
+/// Full-text tokenizer settings for a project, and the MATCH expression an
+/// optional `query` turns into, for tracking down surprising search misses.
+#[tauri::command]
+pub fn get_search_diagnostics(
+    manager: State<'_, std::sync::Mutex<ProjectManager>>,
+    project_id: String,
+    query: Option<String>,
+) -> Result<SearchDiagnostics, String> {
+    let mgr = manager.lock().map_err(|e| e.to_string())?;
+    Ok(queries::search_diagnostics(
+        mgr.connection(&project_id)?,
+        query.as_deref(),
+    ))
+}
+
+/// A collection's navigation tree, with word counts and reading times unless
+/// the preference turns them off. Off the main thread like
+/// `list_all_documents`.
+#[tauri::command]
+pub async fn get_navigation(
+    app: AppHandle,
+    collection_id: String,
+) -> Result<Vec<NavigationNode>, String> {
+    tauri::async_runtime::spawn_blocking(move || {
+        let reading_time_disabled = settings::load_preferences(&app)
+            .unwrap_or_default()
+            .reading_time_disabled;
+        let manager = app.state::<std::sync::Mutex<ProjectManager>>();
+        let (project_id, mut nodes) = {
+            let mgr = lock_manager(&manager)?;
+            let project_id = mgr.registry.active_project_id.clone();
+            let nodes = queries::navigation(mgr.connection(&project_id)?, &collection_id)?;
+            (project_id, nodes)
+        };
+        if !reading_time_disabled {
+            let counts = project_word_counts(&manager, &project_id)?;
+            for node in &mut nodes {
+                // Navigation slugs are within the collection, counts by full slug
+                let slug = format!("{}/{}", node.collection_id, node.slug);
+                if let Some(&words) = counts.get(&slug) {
+                    node.word_count = Some(words);
+                    node.reading_minutes = Some(queries::reading_minutes(words));
+                }
+            }
+        }
+        Ok(nodes)
+    })
+    .await
+    .map_err(|e| e.to_string())?
+}
+
+/// A document by slug. With `highlight_terms`, matches in its text are
+/// wrapped in `<mark data-hl>` and counted. A miss says whether a rebuild
+/// renamed or removed the document.
+#[tauri::command]
+pub fn get_document(
+    app: AppHandle,
+    manager: State<'_, std::sync::Mutex<crate::projects::ProjectManager>>,
+    user_state: State<'_, UserStateDb>,
+    slug: String,
+    highlight_terms: Option<Vec<String>>,
+) -> Result<DocumentView, DocumentError> {
+    let (project_id, found) = {
+        let mgr = lock_manager(&manager)?;
+        let project_id = mgr.registry.active_project_id.clone();
+        let found = match mgr
+            .document_overlays
+            .get(&project_id)
+            .and_then(|overlay| overlay.get(&slug))
+        {
+            Some(doc) => Ok(doc.clone()),
+            None => {
+                let conn = mgr.active_connection()?;
+                queries::find_document(conn, &slug)?.ok_or_else(|| queries::slug_alias(conn, &slug))
+            }
+        };
+        (project_id, found)
+    };
+    let mut document = match found {
+        Ok(document) => document,
+        Err(renamed_to) => {
+            let conn = lock_user_state(&user_state)?;
+            return Err(crate::slug_aliases::missing_document(
+                &conn,
+                &project_id,
+                &slug,
+                renamed_to,
+            ));
+        }
+    };
+    count_usage(&app, "documents_opened", &project_id);
+    let highlight_count = highlight_terms.map(|terms| {
+        let (html, count) = crate::highlight::highlight_html(&document.content_html, &terms);
+        document.content_html = html;
+        count
+    });
+    Ok(DocumentView {
+        document,
+        highlight_count,
+    })
+}
+
+/// Full-text search in the active project. With `dedupe_similar_titles`,
+/// results whose titles differ only by case, punctuation, a version suffix or
+/// a trailing segment are folded into the best-ranked one.
+#[tauri::command]
+#[allow(clippy::too_many_arguments)]
+pub fn search_documents(
+    app: AppHandle,
+    manager: State<'_, std::sync::Mutex<crate::projects::ProjectManager>>,
+    user_state: State<'_, UserStateDb>,
+    query: String,
+    collection_id: Option<String>,
+    limit: Option<i32>,
+    snippet_tokens: Option<i32>,
+    dedupe_similar_titles: Option<bool>,
+) -> Result<Vec<SearchResult>, String> {
+    let project_id = {
+        let mgr = lock_manager(&manager)?;
+        mgr.registry.active_project_id.clone()
+    };
+    let preferences = settings::load_preferences(&app)?;
+    let snippet_tokens = snippet_tokens
+        .or(preferences.search_snippet_tokens)
+        .unwrap_or(crate::snippet::DEFAULT_SNIPPET_TOKENS);
+    let hidden = if preferences.exclude_hidden_collections_from_search {
+        let user_conn = lock_user_state(&user_state)?;
+        crate::user_state::hidden_collections(&user_conn, &project_id)?
+    } else {
+        vec![]
+    };
+    count_usage(&app, "searches", &project_id);
+    let limit = limit.unwrap_or(20);
+    let dedupe = dedupe_similar_titles.unwrap_or(false);
+    let mgr = lock_manager(&manager)?;
+    let results = queries::search_documents(
+        mgr.connection(&project_id)?,
+        &query,
+        collection_id.as_deref(),
+        &hidden,
+        if dedupe {
+            limit.saturating_mul(DEDUPE_OVERFETCH)
+        } else {
+            limit
+        },
+        snippet_tokens,
+    )?;
+    if !dedupe {
+        return Ok(results);
+    }
+    let mut results = queries::collapse_similar_titles(results);
+    results.truncate(limit.max(0) as usize);
+    Ok(results)
+}
+
+#[tauri::command]
+pub fn get_tags(
+    manager: State<'_, std::sync::Mutex<crate::projects::ProjectManager>>,
+    collection_id: Option<String>,
+    sort: Option<String>,
+) -> Result<Vec<Tag>, String> {
+    let sort = sort
+        .as_deref()
+        .map_or(Ok(queries::TagSort::Count), queries::TagSort::parse)?;
+    let mgr = manager.lock().map_err(|e| e.to_string())?;
+    queries::tags(mgr.active_connection()?, collection_id.as_deref(), sort)
+}
+
+/// Remember a search the user acted on, such as by opening one of its
+/// results, so it can be suggested again.
+#[tauri::command]
+pub fn record_recent_search(
+    manager: State<'_, std::sync::Mutex<ProjectManager>>,
+    user_state: State<'_, UserStateDb>,
+    query: String,
+) -> Result<(), String> {
+    let query = query.trim();
+    if query.is_empty() {
+        return Ok(());
+    }
+    let project_id = {
+        let mgr = lock_manager(&manager)?;
+        mgr.registry.active_project_id.clone()
+    };
+    let conn = lock_user_state(&user_state)?;
+    conn.execute(
+        "INSERT INTO recent_searches (project_id, query, searched_at) VALUES (?1, ?2, ?3)
+         ON CONFLICT(project_id, query) DO UPDATE SET searched_at = excluded.searched_at",
+        params![&project_id, query, unix_timestamp_i64()],
+    )
+    .map_err(|e| e.to_string())?;
+    conn.execute(
+        "DELETE FROM recent_searches WHERE project_id = ?1 AND query NOT IN (
+             SELECT query FROM recent_searches WHERE project_id = ?1
+             ORDER BY searched_at DESC LIMIT ?2
+         )",
+        params![&project_id, RECENT_SEARCHES_KEPT],
+    )
+    .map_err(|e| e.to_string())?;
+    Ok(())
+}
+
+/// Completions for a partly typed search in the active project: recent
+/// searches starting with `prefix`, then document titles and headings with a
+/// word starting with it. Titles and headings come from an index built on
+/// the first call after the project's database opens.
+#[tauri::command]
+pub fn suggest_queries(
+    manager: State<'_, std::sync::Mutex<ProjectManager>>,
+    user_state: State<'_, UserStateDb>,
+    prefix: String,
+    limit: Option<usize>,
+) -> Result<Vec<QuerySuggestion>, String> {
+    let limit = limit
+        .unwrap_or(QUERY_SUGGESTION_LIMIT)
+        .clamp(1, QUERY_SUGGESTION_LIMIT);
+    let prefix_lower = prefix.trim().to_lowercase();
+    if prefix_lower.is_empty() {
+        return Ok(vec![]);
+    }
+    let project_id = {
+        let mgr = lock_manager(&manager)?;
+        mgr.registry.active_project_id.clone()
+    };
+    let mut suggestions: Vec<QuerySuggestion> = {
+        let conn = lock_user_state(&user_state)?;
+        let mut stmt = conn
+            .prepare_cached(
+                "SELECT query FROM recent_searches WHERE project_id = ?1
+                 ORDER BY searched_at DESC",
+            )
+            .map_err(|e| e.to_string())?;
+        let rows = stmt
+            .query_map(params![&project_id], |row| row.get::<_, String>(0))
+            .map_err(|e| e.to_string())?;
+        let recent_limit = RECENT_SEARCH_SUGGESTIONS.min(limit);
+        let mut recent = Vec::new();
+        for query in rows {
+            if recent.len() >= recent_limit {
+                break;
+            }
+            let query = query.map_err(|e| e.to_string())?;
+            let lower = query.to_lowercase();
+            if lower.starts_with(&prefix_lower) && lower != prefix_lower {
+                recent.push(QuerySuggestion {
+                    kind: "recent".to_string(),
+                    text: query,
+                    slug: None,
+                    anchor_id: None,
+                });
+            }
+        }
+        recent
+    };
+    let mut mgr = lock_manager(&manager)?;
+    let index = mgr.suggestion_index(&project_id)?;
+    suggestions.extend(index.lookup(&prefix_lower, limit.saturating_sub(suggestions.len())));
+    Ok(suggestions)
+}
+
+/// Tagged, untagged and dangling-tag document counts for each collection in
+/// the active project.
+#[tauri::command]
+pub fn get_tag_coverage(
+    manager: State<'_, std::sync::Mutex<crate::projects::ProjectManager>>,
+) -> Result<Vec<TagCoverage>, String> {
+    let mgr = manager.lock().map_err(|e| e.to_string())?;
+    queries::tag_coverage(mgr.active_connection()?)
+}
+
+#[tauri::command]
+pub fn get_documents_by_tag(
+    manager: State<'_, std::sync::Mutex<crate::projects::ProjectManager>>,
+    tag: String,
+) -> Result<Vec<SearchResult>, String> {
+    let mgr = manager.lock().map_err(|e| e.to_string())?;
+    queries::documents_by_tag(mgr.active_connection()?, &tag)
+}
+
+const DEFAULT_GLOSSARY_COLLECTION: &str = "glossary";
+const GLOSSARY_EXCERPT_WORDS: usize = 60;
+
+/// Resolve the collections in the active project that hold glossary entries,
+/// matching the configured fragment (or "glossary") against collection id or name.
+fn glossary_collection_ids(mgr: &ProjectManager) -> Result<Vec<String>, String> {
+    let conn = mgr.active_connection()?;
+    let matcher = mgr
+        .registry
+        .projects
+        .iter()
+        .find(|p| p.id == mgr.registry.active_project_id)
+        .and_then(|p| p.glossary_collection.as_deref())
+        .map(str::trim)
+        .filter(|m| !m.is_empty())
+        .unwrap_or(DEFAULT_GLOSSARY_COLLECTION)
+        .to_lowercase();
+    let pattern = like_contains_pattern(&matcher);
+
+    let mut stmt = conn
+        .prepare_cached(
+            "SELECT id FROM collections \
+             WHERE lower(id) LIKE ?1 ESCAPE '\\' OR lower(name) LIKE ?1 ESCAPE '\\' \
+             ORDER BY sort_order",
+        )
+        .map_err(|e| e.to_string())?;
+    let rows = stmt
+        .query_map(params![pattern], |row| row.get::<_, String>(0))
+        .map_err(|e| e.to_string())?;
+    rows.collect::<Result<Vec<_>, _>>()
+        .map_err(|e| e.to_string())
+}
+
+/// A LIKE pattern matching text that contains `text`, with its wildcards
+/// escaped for `ESCAPE '\'`.
+fn like_contains_pattern(text: &str) -> String {
+    format!(
+        "%{}%",
+        text.replace('\\', "\\\\")
+            .replace('%', "\\%")
+            .replace('_', "\\_")
+    )
+}
+
+/// Take the first paragraph of body text from raw markdown, skipping headings.
+pub(super) fn definition_excerpt(content_raw: &str, max_words: usize) -> String {
+    let paragraph = content_raw
+        .split("\n\n")
+        .map(str::trim)
+        .find(|block| !block.is_empty() && !block.starts_with('#'))
+        .unwrap_or_default();
+
+    let words: Vec<&str> = paragraph.split_whitespace().collect();
+    if words.len() > max_words {
+        format!("{}...", words[..max_words].join(" "))
+    } else {
+        words.join(" ")
+    }
+}
+
+#[tauri::command]
+pub fn get_term_definition(
+    manager: State<'_, std::sync::Mutex<ProjectManager>>,
+    term: String,
+) -> Result<Option<TermDefinition>, String> {
+    let term = term.trim();
+    if term.is_empty() {
+        return Ok(None);
+    }
+
+    let mgr = manager.lock().map_err(|e| e.to_string())?;
+    let conn = mgr.active_connection()?;
+    let collection_ids = glossary_collection_ids(&mgr)?;
+
+    // Exact title match first — glossary entries are usually one document per term.
+    for collection_id in &collection_ids {
+        let exact = conn
+            .query_row(
+                "SELECT slug, title, content_raw FROM documents \
+                 WHERE collection_id = ?1 AND title = ?2 COLLATE NOCASE \
+                 LIMIT 1",
+                params![collection_id, term],
+                |row| {
+                    Ok((
+                        row.get::<_, String>(0)?,
+                        row.get::<_, String>(1)?,
+                        row.get::<_, String>(2)?,
+                    ))
+                },
+            )
+            .optional()
+            .map_err(|e| e.to_string())?;
+
+        if let Some((doc_slug, doc_title, content_raw)) = exact {
+            return Ok(Some(TermDefinition {
+                term: term.to_string(),
+                definition: definition_excerpt(&content_raw, GLOSSARY_EXCERPT_WORDS),
+                doc_slug,
+                doc_title,
+                collection_id: collection_id.clone(),
+                match_kind: "title".to_string(),
+            }));
+        }
+    }
+
+    // Fall back to a phrase match on glossary definitions.
+    let phrase: String = term.chars().filter(|c| *c != '"').collect();
+    if phrase.trim().is_empty() {
+        return Ok(None);
+    }
+    let fts_query = format!("content : \"{}\"", phrase);
+
+    for collection_id in &collection_ids {
+        let matched = conn
+            .query_row(
+                "SELECT d.slug, d.title, \
+                 snippet(documents_fts, 1, '', '', '...', 40) as snippet \
+                 FROM documents_fts \
+                 JOIN documents d ON d.id = documents_fts.rowid \
+                 WHERE documents_fts MATCH ?1 AND d.collection_id = ?2 \
+                 ORDER BY rank \
+                 LIMIT 1",
+                params![&fts_query, collection_id],
+                |row| {
+                    Ok((
+                        row.get::<_, String>(0)?,
+                        row.get::<_, String>(1)?,
+                        row.get::<_, String>(2)?,
+                    ))
+                },
+            )
+            .optional()
+            .map_err(|e| e.to_string())?;
+
+        if let Some((doc_slug, doc_title, snippet)) = matched {
+            return Ok(Some(TermDefinition {
+                term: term.to_string(),
+                definition: snippet,
+                doc_slug,
+                doc_title,
+                collection_id: collection_id.clone(),
+                match_kind: "definition".to_string(),
+            }));
+        }
+    }
+
+    Ok(None)
+}
+
+#[tauri::command]
+pub fn list_glossary_terms(
+    manager: State<'_, std::sync::Mutex<ProjectManager>>,
+) -> Result<Vec<GlossaryTerm>, String> {
+    let mgr = manager.lock().map_err(|e| e.to_string())?;
+    let conn = mgr.active_connection()?;
+    let collection_ids = glossary_collection_ids(&mgr)?;
+
+    let mut stmt = conn
+        .prepare_cached(
+            "SELECT title, slug FROM documents \
+             WHERE collection_id = ?1 \
+             ORDER BY title COLLATE NOCASE",
+        )
+        .map_err(|e| e.to_string())?;
+
+    let mut terms = Vec::new();
+    for collection_id in &collection_ids {
+        let rows = stmt
+            .query_map(params![collection_id], |row| {
+                Ok(GlossaryTerm {
+                    term: row.get(0)?,
+                    slug: row.get(1)?,
+                })
+            })
+            .map_err(|e| e.to_string())?;
+        for row in rows {
+            terms.push(row.map_err(|e| e.to_string())?);
+        }
+    }
+
+    Ok(terms)
+}
+
+/// Rank how closely a document title matches a search fragment: 0.95 for
+/// an identical title, just under an exact slug match's 1.0.
+fn title_similarity(fragment: &str, title: &str) -> f64 {
+    let fragment = fragment.to_lowercase();
+    let title_lower = title.to_lowercase();
+    if title_lower == fragment {
+        return 0.95;
+    }
+    // Shorter titles containing the fragment are closer matches.
+    let coverage = fragment.chars().count() as f64 / title_lower.chars().count().max(1) as f64;
+    if title_lower.starts_with(&fragment) {
+        0.6 + 0.3 * coverage
+    } else {
+        0.3 + 0.3 * coverage
+    }
+}
+
+/// Find a document by slug or title across every open project. A project
+/// whose database can't be searched is skipped with a warning.
+#[tauri::command]
+pub fn locate_document(
+    manager: State<'_, std::sync::Mutex<ProjectManager>>,
+    query: String,
+    limit: Option<i32>,
+) -> Result<Vec<DocumentLocation>, String> {
+    let query = query.trim();
+    if query.is_empty() {
+        return Ok(vec![]);
+    }
+    let limit = limit.unwrap_or(10).clamp(1, 50) as usize;
+    let mgr = manager.lock().map_err(|e| e.to_string())?;
+
+    let mut out: Vec<DocumentLocation> = Vec::new();
+    for project in &mgr.registry.projects {
+        let Some(conn) = mgr.connections.get(&project.id) else {
+            continue;
+        };
+        match locate_in_project(conn, project, query, limit) {
+            Ok(found) => out.extend(found),
+            Err(e) => eprintln!(
+                "Warning: skipped project '{}' when locating documents: {}",
+                project.id, e
+            ),
+        }
+    }
+
+    out.sort_by(|a, b| {
+        b.score
+            .partial_cmp(&a.score)
+            .unwrap_or(std::cmp::Ordering::Equal)
+    });
+    out.truncate(limit);
+    Ok(out)
+}
+
+fn locate_in_project(
+    conn: &rusqlite::Connection,
+    project: &crate::projects::Project,
+    query: &str,
+    limit: usize,
+) -> Result<Vec<DocumentLocation>, String> {
+    let mut out = Vec::new();
+    // Slug is UNIQUE in documents, so this is an indexed equality lookup.
+    let exact = conn
+        .query_row(
+            "SELECT slug, title, collection_id FROM documents WHERE slug = ?1",
+            params![query],
+            |row| {
+                Ok((
+                    row.get::<_, String>(0)?,
+                    row.get::<_, String>(1)?,
+                    row.get::<_, String>(2)?,
+                ))
+            },
+        )
+        .optional()
+        .map_err(|e| e.to_string())?;
+    if let Some((slug, title, collection_id)) = exact {
+        out.push(DocumentLocation {
+            project_id: project.id.clone(),
+            project_name: project.name.clone(),
+            slug,
+            title,
+            collection_id,
+            match_kind: "slug".to_string(),
+            score: 1.0,
+        });
+    }
+
+    let mut stmt = conn
+        .prepare_cached(
+            "SELECT slug, title, collection_id FROM documents \
+             WHERE title LIKE ?1 ESCAPE '\\' AND slug != ?2 \
+             ORDER BY length(title) \
+             LIMIT ?3",
+        )
+        .map_err(|e| e.to_string())?;
+    let rows = stmt
+        .query_map(
+            params![like_contains_pattern(query), query, limit as i64],
+            |row| {
+                Ok((
+                    row.get::<_, String>(0)?,
+                    row.get::<_, String>(1)?,
+                    row.get::<_, String>(2)?,
+                ))
+            },
+        )
+        .map_err(|e| e.to_string())?;
+    for row in rows {
+        let (slug, title, collection_id) = row.map_err(|e| e.to_string())?;
+        let score = title_similarity(query, &title);
+        out.push(DocumentLocation {
+            project_id: project.id.clone(),
+            project_name: project.name.clone(),
+            slug,
+            title,
+            collection_id,
+            match_kind: "title".to_string(),
+            score,
+        });
+    }
+    Ok(out)
+}
+
+pub(super) fn document_id_for_slug(
+    conn: &rusqlite::Connection,
+    slug: &str,
+) -> Result<Option<i32>, String> {
+    conn.query_row(
+        "SELECT id FROM documents WHERE slug = ?1",
+        params![slug],
+        |row| row.get(0),
+    )
+    .optional()
+    .map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+pub fn get_document_anchors(
+    manager: State<'_, std::sync::Mutex<ProjectManager>>,
+    slug: String,
+) -> Result<Vec<DocumentAnchor>, String> {
+    let mgr = manager.lock().map_err(|e| e.to_string())?;
+    let conn = mgr.active_connection()?;
+    let Some(document_id) = document_id_for_slug(conn, &slug)? else {
+        return Err(format!("Document '{}' not found", slug));
+    };
+    if !ai::table_exists(conn, "document_anchors") {
+        return Ok(vec![]);
+    }
+    queries::document_anchors(conn, document_id)
+}
+
+#[tauri::command]
+pub fn get_document_outline(
+    manager: State<'_, std::sync::Mutex<ProjectManager>>,
+    slug: String,
+) -> Result<Vec<DocumentAnchor>, String> {
+    let mgr = manager.lock().map_err(|e| e.to_string())?;
+    let conn = mgr.active_connection()?;
+    let Some(document_id) = document_id_for_slug(conn, &slug)? else {
+        return Err(format!("Document '{}' not found", slug));
+    };
+    queries::document_anchors(conn, document_id)
+}
+
+/// A dalil:// link to a document of `project_id`, opening at `anchor_id`
+/// and highlighting `selected_text` when they are given.
+#[tauri::command]
+pub fn create_share_link(
+    manager: State<'_, std::sync::Mutex<ProjectManager>>,
+    project_id: String,
+    doc_slug: String,
+    anchor_id: Option<String>,
+    selected_text: Option<String>,
+) -> Result<ShareLink, String> {
+    let mgr = lock_manager(&manager)?;
+    let conn = mgr.connection(&project_id)?;
+    let collection_id: String = conn
+        .query_row(
+            "SELECT collection_id FROM documents WHERE slug = ?1",
+            params![doc_slug],
+            |row| row.get(0),
+        )
+        .optional()
+        .map_err(|e| e.to_string())?
+        .ok_or_else(|| format!("Document '{}' not found", doc_slug))?;
+    let relative_slug = doc_slug
+        .strip_prefix(&format!("{}/", collection_id))
+        .unwrap_or(&doc_slug);
+    let anchor_id = anchor_id.filter(|anchor| !anchor.trim().is_empty());
+    let selection = selected_text
+        .as_deref()
+        .and_then(share_links::shared_selection);
+    let url = share_links::share_link(
+        &project_id,
+        &collection_id,
+        relative_slug,
+        anchor_id.as_deref(),
+        selection.as_ref(),
+    );
+    Ok(ShareLink {
+        selection: share_links::parse_selection(&url),
+        selection_shortened: selection.is_some_and(|selection| selection.end.is_some()),
+        url,
+    })
+}
@@ -0,0 +1,386 @@
+//! Preferences and settings, and app-level features: quick search, Spotlight
+//! export, the HTTP API token, About and release notes, and crash reports.
+
+#[cfg(target_os = "macos")]
+use super::search::definition_excerpt;
+use super::util::{lock_manager, lock_user_state, unix_timestamp_i64};
+use crate::ai;
+use crate::db::{handbook_db_path, HttpClient};
+use crate::models::*;
+use crate::projects::ProjectManager;
+use crate::settings;
+use crate::user_state::UserStateDb;
+use tauri::{AppHandle, Emitter, Manager, State};
+use tauri_plugin_updater::UpdaterExt;
+
+/// Version of the `ai-response-*` event payloads, so a webview bundle can
+/// tell whether it understands the events this build emits.
+#[tauri::command]
+pub fn get_event_schema_version() -> u32 {
+    ai::AI_EVENT_SCHEMA_VERSION
+}
+
+#[tauri::command]
+pub fn get_preferences(app: AppHandle) -> Result<AppPreferences, String> {
+    settings::load_preferences(&app)
+}
+
+#[tauri::command]
+pub fn save_preferences(app: AppHandle, mut preferences: AppPreferences) -> Result<(), String> {
+    let previous = settings::load_preferences(&app).unwrap_or_default();
+    if preferences
+        .http_api_token
+        .as_deref()
+        .unwrap_or("")
+        .is_empty()
+    {
+        preferences.http_api_token = previous.http_api_token.clone();
+    }
+    if preferences.http_api_enabled && preferences.http_api_token.is_none() {
+        preferences.http_api_token = Some(crate::http_api::generate_token()?);
+    }
+    if let Some(url) = preferences
+        .event_webhook_url
+        .as_deref()
+        .filter(|u| !u.trim().is_empty())
+    {
+        if !url.starts_with("http://") && !url.starts_with("https://") {
+            return Err("Event webhook URL must start with http:// or https://".to_string());
+        }
+    }
+    crate::notifications::validate_quiet_hours(&preferences)?;
+
+    if let Err(e) = crate::quick_search::apply_preferences(&app, &preferences) {
+        // Keep the previous shortcut working rather than leaving none registered
+        let _ = crate::quick_search::apply_preferences(&app, &previous);
+        return Err(e);
+    }
+    if let Err(e) = crate::http_api::apply_preferences(&app, &preferences) {
+        let _ = crate::quick_search::apply_preferences(&app, &previous);
+        let _ = crate::http_api::apply_preferences(&app, &previous);
+        return Err(e);
+    }
+    #[cfg(target_os = "macos")]
+    if previous.spotlight_export_enabled && !preferences.spotlight_export_enabled {
+        crate::spotlight::remove_export(&crate::spotlight::spotlight_root(&app)?, None)?;
+    }
+    ai::keywords::apply_preferences(&preferences);
+    settings::save_preferences_to_store(&app, &preferences)
+}
+
+#[cfg(target_os = "macos")]
+const SPOTLIGHT_EXCERPT_WORDS: usize = 60;
+
+/// Re-export a project's Spotlight stubs. Documents are read under the
+/// manager lock, which is released before any files are written.
+#[cfg(target_os = "macos")]
+pub(super) fn refresh_spotlight_export(
+    app: &AppHandle,
+    manager: &std::sync::Mutex<ProjectManager>,
+    project_id: &str,
+) -> Result<SpotlightExportSummary, String> {
+    let stubs = {
+        let mgr = manager.lock().map_err(|e| e.to_string())?;
+        let conn = mgr.connection(project_id)?;
+        let mut stmt = conn
+            .prepare("SELECT collection_id, slug, title, content_raw FROM documents")
+            .map_err(|e| e.to_string())?;
+        let rows = stmt
+            .query_map([], |row| {
+                let content_raw: String = row.get(3)?;
+                Ok(crate::spotlight::SpotlightStub {
+                    collection_id: row.get(0)?,
+                    slug: row.get(1)?,
+                    title: row.get(2)?,
+                    excerpt: definition_excerpt(&content_raw, SPOTLIGHT_EXCERPT_WORDS),
+                })
+            })
+            .map_err(|e| e.to_string())?;
+        rows.collect::<Result<Vec<_>, _>>()
+            .map_err(|e| e.to_string())?
+    };
+    crate::spotlight::export_project(&crate::spotlight::spotlight_root(app)?, project_id, &stubs)
+}
+
+/// Write Spotlight stubs for one project, or every open project when
+/// `project_id` is omitted. Requires the opt-in preference.
+#[tauri::command]
+pub fn export_spotlight_index(
+    app: AppHandle,
+    manager: State<'_, std::sync::Mutex<ProjectManager>>,
+    project_id: Option<String>,
+) -> Result<Vec<SpotlightExportSummary>, String> {
+    #[cfg(target_os = "macos")]
+    {
+        if !settings::load_preferences(&app)
+            .unwrap_or_default()
+            .spotlight_export_enabled
+        {
+            return Err("Spotlight export is disabled in preferences".to_string());
+        }
+        let project_ids = match project_id {
+            Some(id) => vec![id],
+            None => {
+                let mgr = manager.lock().map_err(|e| e.to_string())?;
+                mgr.registry
+                    .projects
+                    .iter()
+                    .filter(|p| mgr.connections.contains_key(&p.id))
+                    .map(|p| p.id.clone())
+                    .collect()
+            }
+        };
+        project_ids
+            .iter()
+            .map(|id| refresh_spotlight_export(&app, &manager, id))
+            .collect()
+    }
+    #[cfg(not(target_os = "macos"))]
+    {
+        let _ = (app, manager, project_id);
+        Err("Spotlight export is only available on macOS".to_string())
+    }
+}
+
+/// Replace the HTTP API token, restarting the server if it is running.
+#[tauri::command]
+pub fn regenerate_http_api_token(app: AppHandle) -> Result<AppPreferences, String> {
+    let mut preferences = settings::load_preferences(&app).unwrap_or_default();
+    preferences.http_api_token = Some(crate::http_api::generate_token()?);
+    crate::http_api::apply_preferences(&app, &preferences)?;
+    settings::save_preferences_to_store(&app, &preferences)?;
+    Ok(preferences)
+}
+
+#[tauri::command]
+pub fn show_quick_search(app: AppHandle) -> Result<(), String> {
+    crate::quick_search::show_quick_search(&app)
+}
+
+#[tauri::command]
+pub fn hide_quick_search(app: AppHandle) -> Result<(), String> {
+    crate::quick_search::hide_quick_search(&app)
+}
+
+/// Called by the quick-search window when a result is picked: hides the popup,
+/// brings the main window forward, and asks it to open the document.
+#[tauri::command]
+pub fn open_quick_search_result(
+    app: AppHandle,
+    collection_id: String,
+    slug: String,
+    anchor_id: Option<String>,
+) -> Result<(), String> {
+    crate::quick_search::hide_quick_search(&app)?;
+    crate::quick_search::focus_main_window(&app);
+    app.emit_to(
+        "main",
+        "navigate-to-document",
+        serde_json::json!({
+            "collectionId": collection_id,
+            "slug": slug,
+            "anchorId": anchor_id,
+        }),
+    )
+    .map_err(|e| e.to_string())
+}
+
+/// How long get_about_info waits for the update server.
+const ABOUT_UPDATE_CHECK_TIMEOUT: std::time::Duration = std::time::Duration::from_secs(5);
+
+/// App, handbook and schema versions, the build target, and whether an update
+/// is waiting.
+#[tauri::command]
+pub async fn get_about_info(
+    app: AppHandle,
+    manager: State<'_, std::sync::Mutex<ProjectManager>>,
+    user_state: State<'_, UserStateDb>,
+) -> Result<AboutInfo, String> {
+    let handbook_built_at = std::fs::metadata(handbook_db_path(&app))
+        .and_then(|m| m.modified())
+        .ok()
+        .and_then(|t| t.duration_since(std::time::UNIX_EPOCH).ok())
+        .map(|d| d.as_secs() as i64);
+    let handbook_document_count = {
+        let mgr = lock_manager(&manager)?;
+        mgr.connection("engineering-handbook")
+            .ok()
+            .and_then(|conn| {
+                conn.query_row("SELECT COUNT(*) FROM documents", [], |row| row.get(0))
+                    .ok()
+            })
+    };
+    let user_state_schema_revision = {
+        let conn = lock_user_state(&user_state)?;
+        conn.pragma_query_value(None, "user_version", |row| row.get(0))
+            .map_err(|e| e.to_string())?
+    };
+
+    let checked = match app
+        .updater_builder()
+        .timeout(ABOUT_UPDATE_CHECK_TIMEOUT)
+        .build()
+    {
+        Ok(updater) => updater.check().await.map_err(|e| e.to_string()),
+        Err(e) => Err(e.to_string()),
+    };
+    let (pending_update_version, update_check_error) = match checked {
+        Ok(update) => (update.map(|u| u.version), None),
+        Err(e) => (None, Some(e)),
+    };
+
+    Ok(AboutInfo {
+        crate_version: env!("CARGO_PKG_VERSION").to_string(),
+        app_version: app.package_info().version.to_string(),
+        handbook_built_at,
+        handbook_document_count,
+        user_state_schema_revision,
+        target_triple: env!("DALIL_TARGET_TRIPLE").to_string(),
+        pending_update_version,
+        update_check_error,
+    })
+}
+
+/// Release notes for the running version and newer ones, from the configured
+/// feed or the updater's latest.json. When the feed cannot be reached the
+/// cached copy is returned marked stale.
+#[tauri::command]
+pub async fn get_release_notes(
+    app: AppHandle,
+    http_client: State<'_, HttpClient>,
+) -> Result<ReleaseNotes, String> {
+    use crate::release_notes::{fetch, notes_since, parse_feed, Fetched, ReleaseNotesCache};
+
+    let preferences = settings::load_preferences(&app).unwrap_or_default();
+    let url = preferences
+        .release_notes_url
+        .filter(|url| !url.trim().is_empty())
+        .or_else(|| {
+            app.config()
+                .plugins
+                .0
+                .get("updater")?
+                .get("endpoints")?
+                .get(0)?
+                .as_str()
+                .map(str::to_string)
+        })
+        .ok_or("No release notes feed is configured")?;
+    let cached = settings::load_release_notes_cache(&app).filter(|cache| cache.url == url);
+
+    let fetched = fetch(
+        &http_client.0,
+        &url,
+        cached.as_ref().and_then(|cache| cache.etag.as_deref()),
+    )
+    .await
+    .and_then(|fetched| match fetched {
+        Fetched::NotModified => cached
+            .clone()
+            .ok_or("Feed unchanged but nothing is cached".to_string()),
+        Fetched::Feed { etag, body } => Ok(ReleaseNotesCache {
+            url: url.clone(),
+            etag,
+            fetched_at: 0,
+            entries: parse_feed(&body)?,
+        }),
+    });
+
+    let current_version = app.package_info().version.to_string();
+    match fetched {
+        Ok(mut cache) => {
+            cache.fetched_at = unix_timestamp_i64();
+            if let Err(e) = settings::save_release_notes_cache(&app, &cache) {
+                eprintln!("Warning: failed to cache release notes: {}", e);
+            }
+            Ok(ReleaseNotes {
+                entries: notes_since(&cache.entries, &current_version),
+                stale: false,
+                fetched_at: Some(cache.fetched_at),
+                error: None,
+            })
+        }
+        Err(e) => Ok(ReleaseNotes {
+            entries: cached
+                .as_ref()
+                .map(|cache| notes_since(&cache.entries, &current_version))
+                .unwrap_or_default(),
+            stale: true,
+            fetched_at: cached.map(|cache| cache.fetched_at),
+            error: Some(e),
+        }),
+    }
+}
+
+/// The most recent panic report, flagged if the app crashed since the last
+/// call.
+#[tauri::command]
+pub fn get_last_crash_report(app: AppHandle) -> Result<Option<CrashReport>, String> {
+    let app_data_dir = app.path().app_data_dir().map_err(|e| e.to_string())?;
+    crate::crash_report::last_crash_report(&crate::crash_report::logs_dir(&app_data_dir))
+}
+
+#[tauri::command]
+pub fn get_settings(app: AppHandle) -> Result<Settings, String> {
+    let stored = settings::load_settings(&app)?;
+    Ok(settings::mask_settings(&stored))
+}
+
+#[tauri::command]
+pub fn save_settings(app: AppHandle, new_settings: Settings) -> Result<(), String> {
+    if let Some(retrieval) = &new_settings.retrieval {
+        settings::validate_retrieval(retrieval)?;
+    }
+    // When saving, if a key looks masked (contains "..."), keep the existing key
+    let existing = settings::load_settings(&app).unwrap_or_default();
+
+    let merged = Settings {
+        openai_api_key: merge_key(&new_settings.openai_api_key, &existing.openai_api_key),
+        anthropic_api_key: merge_key(&new_settings.anthropic_api_key, &existing.anthropic_api_key),
+        gemini_api_key: merge_key(&new_settings.gemini_api_key, &existing.gemini_api_key),
+        ollama_base_url: new_settings.ollama_base_url,
+        preferred_provider: new_settings.preferred_provider,
+        anthropic_model: new_settings.anthropic_model,
+        gemini_model: new_settings.gemini_model,
+        max_concurrent_requests: new_settings.max_concurrent_requests,
+        anthropic_prompt_caching: new_settings.anthropic_prompt_caching,
+        embedding_requests_per_minute: new_settings.embedding_requests_per_minute,
+        answer_language: new_settings.answer_language,
+        retrieval: new_settings.retrieval,
+    };
+
+    // A changed Ollama URL is probed afresh rather than trusting a cached verdict
+    if merged.ollama_base_url != existing.ollama_base_url {
+        ai::providers::ollama::invalidate_availability(existing.ollama_base_url.as_deref());
+        ai::providers::ollama::invalidate_availability(merged.ollama_base_url.as_deref());
+    }
+
+    // New keys or URLs can change which model embeds questions
+    ai::clear_query_embedding_cache();
+    settings::save_settings_to_store(&app, &merged)
+}
+
+/// If the incoming key matches the masked format (prefix...suffix), keep the existing key.
+fn merge_key(incoming: &Option<String>, existing: &Option<String>) -> Option<String> {
+    match incoming {
+        Some(k) if is_masked_key(k) => existing.clone(),
+        Some(k) if k.is_empty() => None,
+        other => other.clone(),
+    }
+}
+
+/// Check whether a string matches the output format of `mask_key`:
+/// either all asterisks (short keys) or chars...chars (longer keys).
+fn is_masked_key(value: &str) -> bool {
+    // All asterisks — masked short key
+    if !value.is_empty() && value.chars().all(|c| c == '*') {
+        return true;
+    }
+    // Pattern: <prefix>...<suffix> where prefix and suffix are non-empty
+    if let Some(dot_pos) = value.find("...") {
+        let prefix = &value[..dot_pos];
+        let suffix = &value[dot_pos + 3..];
+        return !prefix.is_empty() && !suffix.is_empty();
+    }
+    false
+}
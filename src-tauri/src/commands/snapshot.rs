@@ -0,0 +1,794 @@
+//! Snapshots of everything a project keeps in user state (bookmarks with
+//! their folders and tags, notes and their revisions, highlights, reading
+//! history, inbox items and collection overrides) as one versioned JSON file,
+//! for moving personal data to another machine.
+
+use super::annotations::{
+    add_annotation_relations, annotation_relation_ids, doc_note_revision_from_row,
+    AnnotationRelation,
+};
+use super::bookmarks::{capture_bookmark, restore_bookmark, TrashedBookmark};
+use super::inbox::inbox_item_from_row;
+use super::projects::collection_overrides;
+use super::util::{
+    folder_from_row, highlight_from_row, lock_user_state, tag_from_row, unix_timestamp_i64,
+};
+use crate::models::*;
+use crate::user_state::{write_doc_note, UserStateDb};
+use rusqlite::{params, Connection, OptionalExtension};
+use std::collections::HashMap;
+use tauri::State;
+
+const SNAPSHOT_FORMAT: &str = "dal-l-user-state";
+/// Bumped when the snapshot layout changes; older snapshots keep importing.
+const SNAPSHOT_VERSION: u32 = 1;
+
+/// Tables cleared before a snapshot is imported with the "replace" mode. Folder
+/// and tag relations go with their rows.
+const SNAPSHOT_TABLES: [&str; 9] = [
+    "bookmarks",
+    "bookmark_folders",
+    "bookmark_tags",
+    "doc_notes",
+    "doc_note_revisions",
+    "doc_highlights",
+    "doc_views",
+    "inbox_items",
+    "collection_overrides",
+];
+
+/// A bookmark, note or highlight with the folders and tags it is filed under.
+#[derive(Debug, serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct Filed<T> {
+    #[serde(flatten)]
+    item: T,
+    #[serde(default)]
+    folder_ids: Vec<i64>,
+    #[serde(default)]
+    tag_ids: Vec<i64>,
+}
+
+/// A bookmark with its folders, tags and the history in `bookmark_events`.
+#[derive(Debug, serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct FiledBookmark {
+    #[serde(flatten)]
+    item: Bookmark,
+    #[serde(default)]
+    folder_ids: Vec<i64>,
+    #[serde(default)]
+    tag_ids: Vec<i64>,
+    #[serde(default)]
+    events: Vec<BookmarkEvent>,
+}
+
+#[derive(Debug, Clone, PartialEq, serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct BookmarkEvent {
+    event_type: String,
+    created_at: i64,
+}
+
+#[derive(Debug, serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct DocView {
+    doc_slug: String,
+    last_viewed_at: i64,
+}
+
+#[derive(Debug, serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct UserStateSnapshot {
+    format: String,
+    version: u32,
+    project_id: String,
+    exported_at: i64,
+    #[serde(default)]
+    folders: Vec<BookmarkFolder>,
+    #[serde(default)]
+    tags: Vec<BookmarkTagEntity>,
+    #[serde(default)]
+    bookmarks: Vec<FiledBookmark>,
+    #[serde(default)]
+    notes: Vec<Filed<DocNote>>,
+    #[serde(default)]
+    note_revisions: Vec<DocNoteRevision>,
+    #[serde(default)]
+    highlights: Vec<Filed<DocHighlight>>,
+    #[serde(default)]
+    doc_views: Vec<DocView>,
+    #[serde(default)]
+    inbox_items: Vec<InboxItem>,
+    #[serde(default)]
+    collection_overrides: Vec<CollectionOverride>,
+}
+
+impl UserStateSnapshot {
+    fn summary(&self, path: String) -> UserStateSnapshotSummary {
+        UserStateSnapshotSummary {
+            path,
+            project_id: self.project_id.clone(),
+            exported_at: self.exported_at,
+            bookmarks: self.bookmarks.len(),
+            notes: self.notes.len(),
+            highlights: self.highlights.len(),
+            doc_views: self.doc_views.len(),
+            inbox_items: self.inbox_items.len(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum ImportMode {
+    /// Keep what is already there; the newer of two notes or overrides wins
+    Merge,
+    /// Clear the project's user state first
+    Replace,
+}
+
+impl ImportMode {
+    fn parse(mode: &str) -> Result<Self, String> {
+        match mode {
+            "merge" => Ok(ImportMode::Merge),
+            "replace" => Ok(ImportMode::Replace),
+            other => Err(format!("Unknown import mode '{}'", other)),
+        }
+    }
+}
+
+fn query_all<T>(
+    conn: &Connection,
+    sql: &str,
+    project_id: &str,
+    from_row: impl FnMut(&rusqlite::Row<'_>) -> rusqlite::Result<T>,
+) -> Result<Vec<T>, String> {
+    let mut stmt = conn.prepare(sql).map_err(|e| e.to_string())?;
+    let rows = stmt
+        .query_map(params![project_id], from_row)
+        .map_err(|e| e.to_string())?;
+    rows.collect::<Result<Vec<_>, _>>()
+        .map_err(|e| e.to_string())
+}
+
+fn filed_annotation<T>(
+    conn: &Connection,
+    kind: &str,
+    item_id: i64,
+    item: T,
+) -> Result<Filed<T>, String> {
+    Ok(Filed {
+        item,
+        folder_ids: annotation_relation_ids(conn, AnnotationRelation::Folders, kind, item_id)?,
+        tag_ids: annotation_relation_ids(conn, AnnotationRelation::Tags, kind, item_id)?,
+    })
+}
+
+fn export_snapshot(
+    conn: &Connection,
+    project_id: &str,
+    now: i64,
+) -> Result<UserStateSnapshot, String> {
+    let folders = query_all(
+        conn,
+        "SELECT id, project_id, name, created_at, updated_at
+         FROM bookmark_folders WHERE project_id = ?1 ORDER BY id",
+        project_id,
+        folder_from_row,
+    )?;
+    let tags = query_all(
+        conn,
+        "SELECT id, project_id, name, created_at, updated_at
+         FROM bookmark_tags WHERE project_id = ?1 ORDER BY id",
+        project_id,
+        tag_from_row,
+    )?;
+
+    let mut bookmarks = Vec::new();
+    let bookmark_ids = query_all(
+        conn,
+        "SELECT id FROM bookmarks WHERE project_id = ?1 ORDER BY id",
+        project_id,
+        |row| row.get::<_, i64>(0),
+    )?;
+    for id in bookmark_ids {
+        if let Some(trashed) = capture_bookmark(conn, project_id, id)? {
+            bookmarks.push(FiledBookmark {
+                item: trashed.bookmark,
+                folder_ids: trashed.folder_ids,
+                tag_ids: trashed.tag_ids,
+                events: trashed
+                    .events
+                    .into_iter()
+                    .map(|(event_type, created_at)| BookmarkEvent {
+                        event_type,
+                        created_at,
+                    })
+                    .collect(),
+            });
+        }
+    }
+
+    let mut notes = Vec::new();
+    let rows = query_all(
+        conn,
+        "SELECT id, project_id, doc_slug, note, updated_at, revision
+         FROM doc_notes WHERE project_id = ?1 ORDER BY doc_slug",
+        project_id,
+        |row| {
+            Ok((
+                row.get::<_, i64>(0)?,
+                DocNote {
+                    project_id: row.get(1)?,
+                    doc_slug: row.get(2)?,
+                    note: row.get(3)?,
+                    updated_at: row.get(4)?,
+                    revision: row.get(5)?,
+                },
+            ))
+        },
+    )?;
+    for (id, note) in rows {
+        notes.push(filed_annotation(conn, "note", id, note)?);
+    }
+
+    let mut highlights = Vec::new();
+    let rows = query_all(
+        conn,
+        "SELECT id, project_id, doc_slug, anchor_id, selected_text, context_text, created_at
+         FROM doc_highlights WHERE project_id = ?1 ORDER BY id",
+        project_id,
+        highlight_from_row,
+    )?;
+    for highlight in rows {
+        highlights.push(filed_annotation(
+            conn,
+            "highlight",
+            highlight.id,
+            highlight,
+        )?);
+    }
+
+    Ok(UserStateSnapshot {
+        format: SNAPSHOT_FORMAT.to_string(),
+        version: SNAPSHOT_VERSION,
+        project_id: project_id.to_string(),
+        exported_at: now,
+        folders,
+        tags,
+        bookmarks,
+        notes,
+        note_revisions: query_all(
+            conn,
+            "SELECT id, project_id, doc_slug, note, created_at
+             FROM doc_note_revisions WHERE project_id = ?1 ORDER BY id",
+            project_id,
+            doc_note_revision_from_row,
+        )?,
+        highlights,
+        doc_views: query_all(
+            conn,
+            "SELECT doc_slug, last_viewed_at FROM doc_views
+             WHERE project_id = ?1 ORDER BY doc_slug",
+            project_id,
+            |row| {
+                Ok(DocView {
+                    doc_slug: row.get(0)?,
+                    last_viewed_at: row.get(1)?,
+                })
+            },
+        )?,
+        inbox_items: query_all(
+            conn,
+            "SELECT id, project_id, content, created_at, resolved_at, resolved_doc_slug
+             FROM inbox_items WHERE project_id = ?1 ORDER BY id",
+            project_id,
+            inbox_item_from_row,
+        )?,
+        collection_overrides: collection_overrides(conn, project_id)?,
+    })
+}
+
+/// The id of the project's folder or tag called `name`, created if missing.
+fn named_id(
+    conn: &Connection,
+    table: &str,
+    project_id: &str,
+    name: &str,
+    created_at: i64,
+    updated_at: i64,
+) -> Result<i64, String> {
+    let existing: Option<i64> = conn
+        .query_row(
+            &format!(
+                "SELECT id FROM {} WHERE project_id = ?1 AND name = ?2 ORDER BY id LIMIT 1",
+                table
+            ),
+            params![project_id, name],
+            |row| row.get(0),
+        )
+        .optional()
+        .map_err(|e| e.to_string())?;
+    if let Some(id) = existing {
+        return Ok(id);
+    }
+    conn.execute(
+        &format!(
+            "INSERT INTO {} (project_id, name, created_at, updated_at) VALUES (?1, ?2, ?3, ?4)",
+            table
+        ),
+        params![project_id, name, created_at, updated_at],
+    )
+    .map_err(|e| e.to_string())?;
+    Ok(conn.last_insert_rowid())
+}
+
+/// Snapshot folder or tag ids translated to the ids they were imported as.
+fn remap(ids: &[i64], imported: &HashMap<i64, i64>) -> Vec<i64> {
+    ids.iter()
+        .filter_map(|id| imported.get(id).copied())
+        .collect()
+}
+
+fn add_filed_relations<T>(
+    conn: &Connection,
+    project_id: &str,
+    kind: &str,
+    item_id: i64,
+    filed: &Filed<T>,
+    folders: &HashMap<i64, i64>,
+    tags: &HashMap<i64, i64>,
+) -> Result<(), String> {
+    add_annotation_relations(
+        conn,
+        AnnotationRelation::Folders,
+        project_id,
+        kind,
+        item_id,
+        &remap(&filed.folder_ids, folders),
+    )?;
+    add_annotation_relations(
+        conn,
+        AnnotationRelation::Tags,
+        project_id,
+        kind,
+        item_id,
+        &remap(&filed.tag_ids, tags),
+    )
+}
+
+/// Write a snapshot into `project_id`, which need not be the project it was
+/// taken from. Rows already present (the same bookmark target, highlight text,
+/// inbox snippet or revision) are matched rather than duplicated. Run inside a
+/// transaction.
+/// Record a bookmark's exported events, skipping those it already has, so
+/// merging the same snapshot twice doesn't double its history.
+fn add_missing_events(
+    conn: &Connection,
+    bookmark_id: i64,
+    events: &[BookmarkEvent],
+) -> Result<(), String> {
+    let mut recorded = {
+        let mut stmt = conn
+            .prepare_cached(
+                "SELECT event_type, created_at FROM bookmark_events WHERE bookmark_id = ?1",
+            )
+            .map_err(|e| e.to_string())?;
+        let rows = stmt
+            .query_map(params![bookmark_id], |row| {
+                Ok(BookmarkEvent {
+                    event_type: row.get(0)?,
+                    created_at: row.get(1)?,
+                })
+            })
+            .map_err(|e| e.to_string())?;
+        rows.collect::<Result<Vec<_>, _>>()
+            .map_err(|e| e.to_string())?
+    };
+    for event in events {
+        // Each recorded event matches one exported event at most
+        if let Some(i) = recorded.iter().position(|r| r == event) {
+            recorded.swap_remove(i);
+            continue;
+        }
+        conn.execute(
+            "INSERT INTO bookmark_events (bookmark_id, event_type, created_at) VALUES (?1, ?2, ?3)",
+            params![bookmark_id, &event.event_type, event.created_at],
+        )
+        .map_err(|e| e.to_string())?;
+    }
+    Ok(())
+}
+
+fn import_snapshot(
+    conn: &Connection,
+    snapshot: &UserStateSnapshot,
+    project_id: &str,
+    mode: ImportMode,
+) -> Result<(), String> {
+    if mode == ImportMode::Replace {
+        for table in SNAPSHOT_TABLES {
+            conn.execute(
+                &format!("DELETE FROM {} WHERE project_id = ?1", table),
+                params![project_id],
+            )
+            .map_err(|e| e.to_string())?;
+        }
+    }
+
+    let mut folders = HashMap::new();
+    for folder in &snapshot.folders {
+        let id = named_id(
+            conn,
+            "bookmark_folders",
+            project_id,
+            &folder.name,
+            folder.created_at,
+            folder.updated_at,
+        )?;
+        folders.insert(folder.id, id);
+    }
+    let mut tags = HashMap::new();
+    for tag in &snapshot.tags {
+        let id = named_id(
+            conn,
+            "bookmark_tags",
+            project_id,
+            &tag.name,
+            tag.created_at,
+            tag.updated_at,
+        )?;
+        tags.insert(tag.id, id);
+    }
+
+    // Bookmarks come back the way trashed ones do, absorbing into a bookmark
+    // for the same target
+    for filed in &snapshot.bookmarks {
+        let mut bookmark = filed.item.clone();
+        bookmark.project_id = project_id.to_string();
+        let bookmark_id = restore_bookmark(
+            conn,
+            &TrashedBookmark {
+                bookmark,
+                folder_ids: remap(&filed.folder_ids, &folders),
+                tag_ids: remap(&filed.tag_ids, &tags),
+                events: Vec::new(),
+            },
+        )?;
+        add_missing_events(conn, bookmark_id, &filed.events)?;
+    }
+
+    for filed in &snapshot.notes {
+        let note = &filed.item;
+        let current: Option<(String, i64)> = conn
+            .query_row(
+                "SELECT note, updated_at FROM doc_notes WHERE project_id = ?1 AND doc_slug = ?2",
+                params![project_id, &note.doc_slug],
+                |row| Ok((row.get(0)?, row.get(1)?)),
+            )
+            .optional()
+            .map_err(|e| e.to_string())?;
+        let newer = current
+            .is_none_or(|(text, updated_at)| text != note.note && note.updated_at > updated_at);
+        if newer {
+            // Goes through the note writer so a replaced local note is kept as a revision
+            write_doc_note(
+                conn,
+                project_id,
+                &note.doc_slug,
+                &note.note,
+                note.updated_at,
+            )?;
+        }
+        let note_id: i64 = conn
+            .query_row(
+                "SELECT id FROM doc_notes WHERE project_id = ?1 AND doc_slug = ?2",
+                params![project_id, &note.doc_slug],
+                |row| row.get(0),
+            )
+            .map_err(|e| e.to_string())?;
+        add_filed_relations(conn, project_id, "note", note_id, filed, &folders, &tags)?;
+    }
+    for revision in &snapshot.note_revisions {
+        conn.execute(
+            "INSERT INTO doc_note_revisions (project_id, doc_slug, note, created_at)
+             SELECT ?1, ?2, ?3, ?4
+             WHERE NOT EXISTS (
+                 SELECT 1 FROM doc_note_revisions
+                 WHERE project_id = ?1 AND doc_slug = ?2 AND note = ?3 AND created_at = ?4
+             )",
+            params![
+                project_id,
+                &revision.doc_slug,
+                &revision.note,
+                revision.created_at
+            ],
+        )
+        .map_err(|e| e.to_string())?;
+    }
+
+    for filed in &snapshot.highlights {
+        let h = &filed.item;
+        let existing: Option<i64> = conn
+            .query_row(
+                "SELECT id FROM doc_highlights
+                 WHERE project_id = ?1 AND doc_slug = ?2 AND anchor_id IS ?3 AND selected_text = ?4
+                 LIMIT 1",
+                params![project_id, &h.doc_slug, &h.anchor_id, &h.selected_text],
+                |row| row.get(0),
+            )
+            .optional()
+            .map_err(|e| e.to_string())?;
+        let id = match existing {
+            Some(id) => id,
+            None => {
+                conn.execute(
+                    "INSERT INTO doc_highlights (project_id, doc_slug, anchor_id, selected_text, context_text, created_at)
+                     VALUES (?1, ?2, ?3, ?4, ?5, ?6)",
+                    params![
+                        project_id,
+                        &h.doc_slug,
+                        &h.anchor_id,
+                        &h.selected_text,
+                        &h.context_text,
+                        h.created_at
+                    ],
+                )
+                .map_err(|e| e.to_string())?;
+                conn.last_insert_rowid()
+            }
+        };
+        add_filed_relations(conn, project_id, "highlight", id, filed, &folders, &tags)?;
+    }
+
+    for view in &snapshot.doc_views {
+        conn.execute(
+            "INSERT INTO doc_views (project_id, doc_slug, last_viewed_at)
+             VALUES (?1, ?2, ?3)
+             ON CONFLICT(project_id, doc_slug)
+             DO UPDATE SET last_viewed_at = MAX(last_viewed_at, excluded.last_viewed_at)",
+            params![project_id, &view.doc_slug, view.last_viewed_at],
+        )
+        .map_err(|e| e.to_string())?;
+    }
+
+    for item in &snapshot.inbox_items {
+        conn.execute(
+            "INSERT INTO inbox_items (project_id, content, created_at, resolved_at, resolved_doc_slug)
+             SELECT ?1, ?2, ?3, ?4, ?5
+             WHERE NOT EXISTS (
+                 SELECT 1 FROM inbox_items
+                 WHERE project_id = ?1 AND content = ?2 AND created_at = ?3
+             )",
+            params![
+                project_id,
+                &item.content,
+                item.created_at,
+                item.resolved_at,
+                &item.resolved_doc_slug
+            ],
+        )
+        .map_err(|e| e.to_string())?;
+    }
+
+    for o in &snapshot.collection_overrides {
+        conn.execute(
+            "INSERT INTO collection_overrides
+                (project_id, collection_id, custom_description, custom_icon, pinned, hidden, updated_at)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7)
+             ON CONFLICT(project_id, collection_id) DO UPDATE SET
+                custom_description = excluded.custom_description,
+                custom_icon = excluded.custom_icon,
+                pinned = excluded.pinned,
+                hidden = excluded.hidden,
+                updated_at = excluded.updated_at
+             WHERE excluded.updated_at > collection_overrides.updated_at",
+            params![
+                project_id,
+                &o.collection_id,
+                &o.custom_description,
+                &o.custom_icon,
+                o.pinned,
+                o.hidden,
+                o.updated_at
+            ],
+        )
+        .map_err(|e| e.to_string())?;
+    }
+    Ok(())
+}
+
+/// Write all of a project's user state to `dest` as a JSON snapshot.
+#[tauri::command]
+pub fn export_user_state(
+    user_state: State<'_, UserStateDb>,
+    project_id: String,
+    dest: String,
+) -> Result<UserStateSnapshotSummary, String> {
+    let snapshot = {
+        let conn = lock_user_state(&user_state)?;
+        export_snapshot(&conn, &project_id, unix_timestamp_i64())?
+    };
+    let json = serde_json::to_string_pretty(&snapshot).map_err(|e| e.to_string())?;
+    std::fs::write(&dest, json).map_err(|e| format!("Failed to write {}: {}", dest, e))?;
+    Ok(snapshot.summary(dest))
+}
+
+/// Load a snapshot written by `export_user_state` into `project_id`. `mode` is
+/// "merge" to add to the project's user state or "replace" to swap it out.
+#[tauri::command]
+pub fn import_user_state(
+    user_state: State<'_, UserStateDb>,
+    path: String,
+    project_id: String,
+    mode: String,
+) -> Result<UserStateSnapshotSummary, String> {
+    let mode = ImportMode::parse(&mode)?;
+    let json =
+        std::fs::read_to_string(&path).map_err(|e| format!("Failed to read {}: {}", path, e))?;
+    let snapshot: UserStateSnapshot = serde_json::from_str(&json)
+        .map_err(|e| format!("{} is not a dal-l user state snapshot: {}", path, e))?;
+    if snapshot.format != SNAPSHOT_FORMAT {
+        return Err(format!("{} is not a dal-l user state snapshot", path));
+    }
+    if snapshot.version > SNAPSHOT_VERSION {
+        return Err(format!(
+            "{} was written by a newer version of dal-l (snapshot version {})",
+            path, snapshot.version
+        ));
+    }
+
+    let conn = lock_user_state(&user_state)?;
+    let tx = conn.unchecked_transaction().map_err(|e| e.to_string())?;
+    import_snapshot(&tx, &snapshot, &project_id, mode)?;
+    tx.commit().map_err(|e| e.to_string())?;
+    Ok(snapshot.summary(path))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{export_snapshot, import_snapshot, ImportMode, UserStateSnapshot};
+    use crate::user_state::apply_user_state_schema;
+    use rusqlite::Connection;
+
+    fn populated() -> Connection {
+        let conn = Connection::open_in_memory().unwrap();
+        apply_user_state_schema(&conn).unwrap();
+        conn.execute_batch(
+            "INSERT INTO bookmark_folders VALUES (4, 'p', 'Runbooks', 1, 1);
+             INSERT INTO bookmark_tags VALUES (6, 'p', 'urgent', 1, 1);
+             INSERT INTO bookmarks (id, project_id, collection_id, doc_slug, anchor_id, title_snapshot,
+                                    created_at, updated_at, last_opened_at, order_index, open_count, is_favorite)
+                 VALUES (2, 'p', 'ops', 'ops/restarts', 'paging', 'Paging', 1, 2, 3, 0, 5, 1);
+             INSERT INTO bookmark_folder_items VALUES (4, 2);
+             INSERT INTO bookmark_tag_items VALUES (6, 2);
+             INSERT INTO bookmark_events (bookmark_id, event_type, created_at)
+                 VALUES (2, 'opened', 3), (2, 'opened', 3), (2, 'favorited', 4);
+             INSERT INTO doc_notes (project_id, doc_slug, note, updated_at)
+                 VALUES ('p', 'ops/restarts', '- [ ] page the on-call', 10);
+             INSERT INTO doc_note_revisions (project_id, doc_slug, note, created_at)
+                 VALUES ('p', 'ops/restarts', 'first draft', 5);
+             INSERT INTO doc_highlights VALUES (8, 'p', 'ops/deploys', NULL, 'roll back', 'Always roll back.', 7);
+             INSERT INTO annotation_folder_items VALUES (4, 'note', (SELECT id FROM doc_notes));
+             INSERT INTO annotation_tag_items VALUES (6, 'highlight', 8);
+             INSERT INTO doc_views VALUES ('p', 'ops/deploys', 20);
+             INSERT INTO inbox_items (project_id, content, created_at) VALUES ('p', 'check the pager', 9);
+             INSERT INTO inbox_items (project_id, content, created_at) VALUES (NULL, 'unfiled', 9);
+             INSERT INTO collection_overrides VALUES ('p', 'ops', 'Operations', NULL, 1, 0, 4);
+             INSERT INTO project_change_feed (project_id, commit_hash, author, committed_at,
+                                              changed_files_json, changed_doc_slugs_json, recorded_at)
+                 VALUES ('p', 'abc', 'ana', '2026-01-01', '[]', '[]', 1);
+             INSERT INTO deleted_items (project_id, kind, payload_json, deleted_at)
+                 VALUES ('p', 'bookmarks', '{}', 1);
+             INSERT INTO last_sessions VALUES ('p', 'ops', 'ops/restarts', NULL, 1);
+             INSERT INTO doc_removals VALUES ('p', 'ops/old', 1);
+             INSERT INTO slug_migrations (project_id, old_slug, new_slug, table_name, rows_changed, migrated_at)
+                 VALUES ('p', 'ops/old', 'ops/new', 'bookmarks', 1, 1);
+             INSERT INTO project_activations (project_id, activated_at) VALUES ('p', 1);
+             INSERT INTO local_stats VALUES ('2026-01-01', 'searches', 'p', 3);
+             INSERT INTO bookmarks (project_id, collection_id, doc_slug, title_snapshot, created_at, updated_at)
+                 VALUES ('other', 'ops', 'ops/elsewhere', 'Elsewhere', 1, 1);",
+        )
+        .unwrap();
+        conn
+    }
+
+    fn round_trip(snapshot: &UserStateSnapshot) -> UserStateSnapshot {
+        serde_json::from_str(&serde_json::to_string(snapshot).unwrap()).unwrap()
+    }
+
+    /// Every exported row, minus ids and the project, so snapshots taken from
+    /// different databases compare equal when they hold the same data.
+    fn contents(snapshot: &UserStateSnapshot) -> String {
+        let mut value = serde_json::to_value(snapshot).unwrap();
+        fn strip(value: &mut serde_json::Value) {
+            match value {
+                serde_json::Value::Object(map) => {
+                    for key in ["id", "projectId", "exportedAt", "folderIds", "tagIds"] {
+                        map.remove(key);
+                    }
+                    map.values_mut().for_each(strip);
+                }
+                serde_json::Value::Array(items) => items.iter_mut().for_each(strip),
+                _ => {}
+            }
+        }
+        strip(&mut value);
+        value.to_string()
+    }
+
+    #[test]
+    fn snapshots_round_trip_into_an_empty_project() {
+        let source = populated();
+        let snapshot = round_trip(&export_snapshot(&source, "p", 100).unwrap());
+        assert_eq!(snapshot.bookmarks.len(), 1);
+        assert_eq!(snapshot.bookmarks[0].folder_ids, [4]);
+        assert_eq!(snapshot.bookmarks[0].events.len(), 3);
+        assert_eq!(snapshot.notes[0].folder_ids, [4]);
+        assert_eq!(snapshot.highlights[0].tag_ids, [6]);
+        assert_eq!(snapshot.inbox_items.len(), 1);
+
+        let target = Connection::open_in_memory().unwrap();
+        apply_user_state_schema(&target).unwrap();
+        target
+            .execute_batch("INSERT INTO bookmark_folders VALUES (4, 'other', 'Taken', 1, 1);")
+            .unwrap();
+        import_snapshot(&target, &snapshot, "q", ImportMode::Merge).unwrap();
+        let imported = export_snapshot(&target, "q", 100).unwrap();
+        assert_eq!(contents(&imported), contents(&snapshot));
+
+        // Relations point at the folder and tag as re-created in the target
+        let folder_id = imported
+            .folders
+            .iter()
+            .find(|f| f.name == "Runbooks")
+            .unwrap()
+            .id;
+        assert_ne!(folder_id, 4);
+        assert_eq!(imported.bookmarks[0].folder_ids, [folder_id]);
+        assert_eq!(imported.notes[0].folder_ids, [folder_id]);
+        assert_eq!(imported.highlights[0].tag_ids, [imported.tags[0].id]);
+
+        // Importing again matches every row instead of duplicating it
+        import_snapshot(&target, &snapshot, "q", ImportMode::Merge).unwrap();
+        assert_eq!(
+            contents(&export_snapshot(&target, "q", 100).unwrap()),
+            contents(&imported)
+        );
+    }
+
+    #[test]
+    fn merge_keeps_newer_local_state_and_replace_discards_it() {
+        let conn = populated();
+        let snapshot = export_snapshot(&conn, "p", 100).unwrap();
+        conn.execute_batch(
+            "UPDATE doc_notes SET note = 'edited later', updated_at = 50;
+             UPDATE collection_overrides SET custom_description = 'Ops', updated_at = 50;
+             INSERT INTO doc_highlights (project_id, doc_slug, selected_text, created_at)
+                 VALUES ('p', 'ops/deploys', 'local only', 60);",
+        )
+        .unwrap();
+
+        import_snapshot(&conn, &snapshot, "p", ImportMode::Merge).unwrap();
+        let merged = export_snapshot(&conn, "p", 100).unwrap();
+        assert_eq!(merged.notes[0].item.note, "edited later");
+        assert_eq!(
+            merged.collection_overrides[0].custom_description.as_deref(),
+            Some("Ops")
+        );
+        assert_eq!(merged.highlights.len(), 2);
+        assert_eq!(merged.bookmarks.len(), 1);
+
+        import_snapshot(&conn, &snapshot, "p", ImportMode::Replace).unwrap();
+        let replaced = export_snapshot(&conn, "p", 100).unwrap();
+        assert_eq!(contents(&replaced), contents(&snapshot));
+        let other: i64 = conn
+            .query_row(
+                "SELECT COUNT(*) FROM bookmarks WHERE project_id = 'other'",
+                [],
+                |row| row.get(0),
+            )
+            .unwrap();
+        assert_eq!(other, 1);
+    }
+}
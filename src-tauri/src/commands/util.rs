@@ -0,0 +1,269 @@
+//! Helpers shared by the command modules: timestamps, row mapping and the
+//! state-locking helpers.
+
+use crate::models::*;
+use crate::projects::ProjectManager;
+use crate::user_state::UserStateDb;
+
+/// The two state locks, in the order they must be taken when a command holds
+/// both: the project manager first, then user state. Holding user state while
+/// waiting on the manager could deadlock against a command doing the reverse.
+#[derive(Debug, Clone, Copy, PartialEq, PartialOrd)]
+enum LockRank {
+    ProjectManager,
+    UserState,
+}
+
+#[cfg(debug_assertions)]
+thread_local! {
+    static HELD_LOCKS: std::cell::RefCell<Vec<LockRank>> =
+        const { std::cell::RefCell::new(Vec::new()) };
+}
+
+/// A lock taken through `lock_manager` or `lock_user_state`. Dev builds track
+/// the locks each thread holds and panic when one is taken out of order.
+pub(super) struct RankedGuard<G> {
+    guard: G,
+    #[cfg(debug_assertions)]
+    rank: LockRank,
+}
+
+impl<G> RankedGuard<G> {
+    fn new(rank: LockRank, lock: impl FnOnce() -> Result<G, String>) -> Result<Self, String> {
+        #[cfg(debug_assertions)]
+        HELD_LOCKS.with(|held| {
+            if let Some(last) = held.borrow().last() {
+                assert!(
+                    *last < rank,
+                    "lock order violated: {:?} taken while holding {:?}",
+                    rank,
+                    last
+                );
+            }
+        });
+        let guard = lock()?;
+        #[cfg(debug_assertions)]
+        HELD_LOCKS.with(|held| held.borrow_mut().push(rank));
+        #[cfg(not(debug_assertions))]
+        let _ = rank;
+        Ok(RankedGuard {
+            guard,
+            #[cfg(debug_assertions)]
+            rank,
+        })
+    }
+}
+
+#[cfg(debug_assertions)]
+impl<G> Drop for RankedGuard<G> {
+    fn drop(&mut self) {
+        HELD_LOCKS.with(|held| {
+            let mut held = held.borrow_mut();
+            if let Some(i) = held.iter().rposition(|rank| *rank == self.rank) {
+                held.remove(i);
+            }
+        });
+    }
+}
+
+impl<G: std::ops::Deref> std::ops::Deref for RankedGuard<G> {
+    type Target = G::Target;
+
+    fn deref(&self) -> &G::Target {
+        &self.guard
+    }
+}
+
+impl<G: std::ops::DerefMut> std::ops::DerefMut for RankedGuard<G> {
+    fn deref_mut(&mut self) -> &mut G::Target {
+        &mut self.guard
+    }
+}
+
+pub(super) fn lock_manager(
+    manager: &std::sync::Mutex<ProjectManager>,
+) -> Result<RankedGuard<std::sync::MutexGuard<'_, ProjectManager>>, String> {
+    RankedGuard::new(LockRank::ProjectManager, || {
+        manager.lock().map_err(|e| e.to_string())
+    })
+}
+
+/// Words per document of a project, keyed by full slug. Cached by the
+/// manager; on a miss they are read on a connection of the command's own,
+/// since databases from older builds are counted from every document's text
+/// and the manager stays locked for no longer than the lookups.
+pub(super) fn project_word_counts(
+    manager: &std::sync::Mutex<ProjectManager>,
+    project_id: &str,
+) -> Result<std::sync::Arc<std::collections::HashMap<String, i64>>, String> {
+    let db_path = {
+        let mgr = lock_manager(manager)?;
+        if let Some(counts) = mgr.word_counts.get(project_id) {
+            return Ok(std::sync::Arc::clone(counts));
+        }
+        let conn = mgr.connection(project_id)?;
+        match conn.path() {
+            Some(path) if !path.is_empty() => std::path::PathBuf::from(path),
+            // In-memory databases can't be opened twice
+            _ => return crate::queries::document_word_counts(conn).map(std::sync::Arc::new),
+        }
+    };
+    let conn = crate::db::open_project_db(&db_path)?;
+    let counts = std::sync::Arc::new(crate::queries::document_word_counts(&conn)?);
+    lock_manager(manager)?.cache_word_counts(project_id, &db_path, std::sync::Arc::clone(&counts));
+    Ok(counts)
+}
+
+pub(super) fn lock_user_state(
+    user_state: &UserStateDb,
+) -> Result<RankedGuard<std::sync::MutexGuard<'_, rusqlite::Connection>>, String> {
+    RankedGuard::new(LockRank::UserState, || {
+        user_state.0.lock().map_err(|e| e.to_string())
+    })
+}
+
+/// Run `f` with both the project manager and the user state database locked,
+/// taken in `LockRank` order. Commands that only read one and then the other
+/// should take them one at a time instead, so neither is held for long.
+pub(super) fn with_states<T>(
+    manager: &std::sync::Mutex<ProjectManager>,
+    user_state: &UserStateDb,
+    f: impl FnOnce(&mut ProjectManager, &rusqlite::Connection) -> Result<T, String>,
+) -> Result<T, String> {
+    let mut mgr = lock_manager(manager)?;
+    let conn = lock_user_state(user_state)?;
+    f(&mut mgr, &conn)
+}
+
+pub(super) fn unix_timestamp() -> String {
+    std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .map(|d| d.as_secs().to_string())
+        .unwrap_or_default()
+}
+
+pub(super) fn unix_timestamp_i64() -> i64 {
+    std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .map(|d| d.as_secs() as i64)
+        .unwrap_or_default()
+}
+
+pub(super) fn bookmark_from_row(row: &rusqlite::Row<'_>) -> rusqlite::Result<Bookmark> {
+    let is_favorite_int: i64 = row.get(11)?;
+    Ok(Bookmark {
+        id: row.get(0)?,
+        project_id: row.get(1)?,
+        collection_id: row.get(2)?,
+        doc_slug: row.get(3)?,
+        anchor_id: row.get(4)?,
+        title_snapshot: row.get(5)?,
+        created_at: row.get(6)?,
+        updated_at: row.get(7)?,
+        last_opened_at: row.get(8)?,
+        order_index: row.get(9)?,
+        open_count: row.get(10)?,
+        is_favorite: is_favorite_int != 0,
+    })
+}
+
+pub(super) fn project_change_feed_from_row(
+    row: &rusqlite::Row<'_>,
+) -> rusqlite::Result<ProjectChangeFeedItem> {
+    let changed_files_json: String = row.get(5)?;
+    let changed_doc_slugs_json: String = row.get(6)?;
+    let changed_files =
+        serde_json::from_str::<Vec<String>>(&changed_files_json).unwrap_or_default();
+    let changed_doc_slugs =
+        serde_json::from_str::<Vec<String>>(&changed_doc_slugs_json).unwrap_or_default();
+    Ok(ProjectChangeFeedItem {
+        id: row.get(0)?,
+        project_id: row.get(1)?,
+        commit_hash: row.get(2)?,
+        author: row.get(3)?,
+        committed_at: row.get(4)?,
+        changed_files,
+        changed_doc_slugs,
+        recorded_at: row.get(7)?,
+        message: row.get(8)?,
+    })
+}
+
+pub(super) fn folder_from_row(row: &rusqlite::Row<'_>) -> rusqlite::Result<BookmarkFolder> {
+    Ok(BookmarkFolder {
+        id: row.get(0)?,
+        project_id: row.get(1)?,
+        name: row.get(2)?,
+        created_at: row.get(3)?,
+        updated_at: row.get(4)?,
+    })
+}
+
+pub(super) fn tag_from_row(row: &rusqlite::Row<'_>) -> rusqlite::Result<BookmarkTagEntity> {
+    Ok(BookmarkTagEntity {
+        id: row.get(0)?,
+        project_id: row.get(1)?,
+        name: row.get(2)?,
+        created_at: row.get(3)?,
+        updated_at: row.get(4)?,
+    })
+}
+
+pub(super) fn highlight_from_row(row: &rusqlite::Row<'_>) -> rusqlite::Result<DocHighlight> {
+    Ok(DocHighlight {
+        id: row.get(0)?,
+        project_id: row.get(1)?,
+        doc_slug: row.get(2)?,
+        anchor_id: row.get(3)?,
+        selected_text: row.get(4)?,
+        context_text: row.get(5)?,
+        created_at: row.get(6)?,
+    })
+}
+
+pub(super) fn millis(duration: std::time::Duration) -> f64 {
+    duration.as_secs_f64() * 1000.0
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{lock_manager, lock_user_state, with_states};
+    use crate::projects::{ProjectManager, ProjectRegistry};
+    use crate::user_state::UserStateDb;
+    use std::sync::Mutex;
+
+    fn states() -> (Mutex<ProjectManager>, UserStateDb) {
+        (
+            Mutex::new(ProjectManager::new(ProjectRegistry::default())),
+            UserStateDb(Mutex::new(rusqlite::Connection::open_in_memory().unwrap())),
+        )
+    }
+
+    #[test]
+    fn with_states_locks_both_and_releases_them() {
+        let (manager, user_state) = states();
+        let (projects, one) = with_states(&manager, &user_state, |mgr, conn| {
+            let one: i64 = conn
+                .query_row("SELECT 1", [], |row| row.get(0))
+                .map_err(|e| e.to_string())?;
+            Ok((mgr.registry.projects.len(), one))
+        })
+        .unwrap();
+        assert_eq!((projects, one), (1, 1));
+
+        // One at a time is fine in either order
+        drop(lock_user_state(&user_state).unwrap());
+        drop(lock_manager(&manager).unwrap());
+        let _mgr = lock_manager(&manager).unwrap();
+        let _conn = lock_user_state(&user_state).unwrap();
+    }
+
+    #[cfg(debug_assertions)]
+    #[test]
+    #[should_panic(expected = "lock order violated")]
+    fn taking_the_manager_while_holding_user_state_panics() {
+        let (manager, user_state) = states();
+        let _conn = lock_user_state(&user_state).unwrap();
+        let _ = lock_manager(&manager);
+    }
+}
@@ -0,0 +1,172 @@
+//! Panic reports. Release builds abort on panic, so a panic on any thread ends
+//! the app without a word; the hook installed here first writes the message
+//! and a backtrace to `logs/panic-{unix_millis}.log` in the app data dir and
+//! leaves a flag so the next launch can offer to show it.
+
+use crate::models::CrashReport;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// Reports kept; older ones are deleted when the hook is installed.
+pub const CRASH_REPORTS_KEPT: usize = 5;
+
+/// Present while a crash report has not been looked at.
+const CRASH_FLAG: &str = "crashed-last-session";
+
+pub fn logs_dir(app_data_dir: &Path) -> PathBuf {
+    app_data_dir.join("logs")
+}
+
+/// Write a report for every panic before the default hook runs (and, in
+/// release builds, before the process aborts). Only builds that abort on
+/// panic leave the crash flag: elsewhere a panic unwinds, and may be caught
+/// or end just one thread, so its report is marked as not fatal.
+pub fn install_panic_hook(dir: PathBuf) {
+    let _ = std::fs::create_dir_all(&dir);
+    prune_reports(&dir);
+    // Worked out now so the hook has as little to allocate as possible
+    let flag = cfg!(panic = "abort").then(|| dir.join(CRASH_FLAG));
+    let default_hook = std::panic::take_hook();
+    std::panic::set_hook(Box::new(move |info| {
+        let backtrace = std::backtrace::Backtrace::force_capture();
+        write_crash_report(&dir, flag.as_deref(), info, &backtrace);
+        default_hook(info);
+    }));
+}
+
+fn prune_reports(dir: &Path) {
+    for old in report_paths(dir).iter().skip(CRASH_REPORTS_KEPT) {
+        let _ = std::fs::remove_file(old);
+    }
+}
+
+/// Best effort: a failure here has nowhere to be reported. `flag` is set
+/// for panics that end the process.
+fn write_crash_report(
+    dir: &Path,
+    flag: Option<&Path>,
+    panic: &dyn std::fmt::Display,
+    backtrace: &dyn std::fmt::Display,
+) {
+    let millis = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_millis())
+        .unwrap_or_default();
+    let Ok(mut file) = std::fs::File::create(dir.join(format!("panic-{}.log", millis))) else {
+        return;
+    };
+    let thread = std::thread::current();
+    let _ = write!(
+        file,
+        "dalil {} panicked on thread '{}'{}\n{}\n\nBacktrace:\n{}\n",
+        env!("CARGO_PKG_VERSION"),
+        thread.name().unwrap_or("<unnamed>"),
+        if flag.is_some() { "" } else { " (not fatal)" },
+        panic,
+        backtrace,
+    );
+    let _ = file.sync_all();
+    if let Some(flag) = flag {
+        let _ = std::fs::File::create(flag);
+    }
+}
+
+/// The newest crash report, if any. Clears the crashed-last-session flag so
+/// the prompt is only offered once.
+pub fn last_crash_report(dir: &Path) -> Result<Option<CrashReport>, String> {
+    let crashed_last_session = dir.join(CRASH_FLAG).exists();
+    let _ = std::fs::remove_file(dir.join(CRASH_FLAG));
+    let Some(path) = report_paths(dir).into_iter().next() else {
+        return Ok(None);
+    };
+    let contents = std::fs::read_to_string(&path).map_err(|e| e.to_string())?;
+    Ok(Some(CrashReport {
+        created_at: report_millis(&path).map_or(0, |millis| (millis / 1000) as i64),
+        path: path.to_string_lossy().to_string(),
+        contents,
+        crashed_last_session,
+    }))
+}
+
+/// Milliseconds in a report's file name, for names of the form
+/// `panic-{unix_millis}.log`.
+fn report_millis(path: &Path) -> Option<u128> {
+    let millis = path
+        .file_name()?
+        .to_str()?
+        .strip_prefix("panic-")?
+        .strip_suffix(".log")?;
+    if millis.is_empty() || !millis.bytes().all(|b| b.is_ascii_digit()) {
+        return None;
+    }
+    millis.parse().ok()
+}
+
+/// Report paths, newest first.
+fn report_paths(dir: &Path) -> Vec<PathBuf> {
+    let Ok(entries) = std::fs::read_dir(dir) else {
+        return vec![];
+    };
+    let mut reports: Vec<(u128, PathBuf)> = entries
+        .filter_map(Result::ok)
+        .map(|entry| entry.path())
+        .filter_map(|path| Some((report_millis(&path)?, path)))
+        .collect();
+    reports.sort_by_key(|(millis, _)| std::cmp::Reverse(*millis));
+    reports.into_iter().map(|(_, path)| path).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{last_crash_report, prune_reports, write_crash_report, CRASH_FLAG};
+    use crate::test_support::scratch_dir;
+
+    #[test]
+    fn newest_report_is_returned_and_flag_cleared() {
+        let dir = scratch_dir("crash-report");
+        assert!(last_crash_report(&dir).unwrap().is_none());
+
+        std::fs::write(dir.join("panic-999.log"), "older").unwrap();
+        std::fs::write(dir.join("panic-notes.log"), "unrelated").unwrap();
+        write_crash_report(
+            &dir,
+            Some(&dir.join(CRASH_FLAG)),
+            &"index out of bounds",
+            &"0: main",
+        );
+
+        let report = last_crash_report(&dir).unwrap().unwrap();
+        assert!(report.crashed_last_session);
+        assert!(report.contents.contains("panicked on thread"));
+        assert!(report.contents.contains("index out of bounds"));
+        assert!(report.contents.contains("Backtrace:\n0: main"));
+        assert!(report.created_at > 0);
+
+        let again = last_crash_report(&dir).unwrap().unwrap();
+        assert!(!again.crashed_last_session);
+        assert_eq!(again.path, report.path);
+
+        // A panic that unwinds leaves a report but no flag
+        std::thread::sleep(std::time::Duration::from_millis(2));
+        write_crash_report(&dir, None, &"caught", &"0: worker");
+        let caught = last_crash_report(&dir).unwrap().unwrap();
+        assert!(!caught.crashed_last_session);
+        assert!(caught.contents.contains("(not fatal)\ncaught"));
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn only_the_newest_reports_are_kept() {
+        let dir = scratch_dir("crash-report-prune");
+        for n in 0..super::CRASH_REPORTS_KEPT + 2 {
+            std::fs::write(dir.join(format!("panic-{}.log", 1000 + n)), "old").unwrap();
+        }
+        prune_reports(&dir);
+
+        assert!(!dir.join("panic-1000.log").exists());
+        assert!(!dir.join("panic-1001.log").exists());
+        assert!(dir.join("panic-1002.log").exists());
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
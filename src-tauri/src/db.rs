@@ -22,15 +22,51 @@ pub fn handbook_db_path(app: &AppHandle) -> std::path::PathBuf {
     }
 }
 
-pub fn init_db(app: &AppHandle) -> Connection {
-    let db_path = handbook_db_path(app);
+pub fn init_db(app: &AppHandle) -> Result<Connection, String> {
+    open_project_db(&handbook_db_path(app))
+}
+
+/// Open a built project database read-only and verify it is readable.
+pub fn open_project_db(db_path: &std::path::Path) -> Result<Connection, String> {
+    if !db_path.exists() {
+        return Err(format!("database not found at {:?}", db_path));
+    }
 
     // SAFETY: SQLITE_OPEN_NO_MUTEX disables SQLite's internal thread safety.
     // All access MUST go through the Rust Mutex wrapper.
     // rusqlite::Connection is not Sync so Mutex is required over RwLock.
-    Connection::open_with_flags(
-        &db_path,
+    let conn = Connection::open_with_flags(
+        db_path,
         rusqlite::OpenFlags::SQLITE_OPEN_READ_ONLY | rusqlite::OpenFlags::SQLITE_OPEN_NO_MUTEX,
     )
-    .unwrap_or_else(|e| panic!("Failed to open database at {:?}: {}", db_path, e))
+    .map_err(|e| format!("failed to open database at {:?}: {}", db_path, e))?;
+
+    // Opening is lazy, so touch the schema to catch truncated or corrupt files now.
+    conn.query_row("SELECT COUNT(*) FROM documents", [], |row| {
+        row.get::<_, i64>(0)
+    })
+    .map_err(|e| format!("database at {:?} is unreadable: {}", db_path, e))?;
+    Ok(conn)
+}
+
+/// Tables a project database must contain to be usable.
+pub const REQUIRED_PROJECT_TABLES: &[&str] =
+    &["documents", "collections", "navigation_tree", "chunks"];
+
+/// Required tables that are absent from `conn`.
+pub fn missing_project_tables(conn: &Connection) -> Result<Vec<&'static str>, String> {
+    let mut missing = Vec::new();
+    for table in REQUIRED_PROJECT_TABLES {
+        let exists: bool = conn
+            .query_row(
+                "SELECT EXISTS(SELECT 1 FROM sqlite_master WHERE type = 'table' AND name = ?1)",
+                [table],
+                |row| row.get(0),
+            )
+            .map_err(|e| e.to_string())?;
+        if !exists {
+            missing.push(*table);
+        }
+    }
+    Ok(missing)
 }
@@ -0,0 +1,257 @@
+//! Digest of what changed in a project over a date range: the documents
+//! updated and the commits recorded in the change feed, grouped by
+//! collection and rendered as Markdown. AI summaries are optional; the
+//! structured digest works without a provider.
+
+use crate::models::{ChangeDigest, DigestCollection, DigestCommit, DigestDocument};
+use crate::models::{Collection, ProjectChangeFeedItem};
+use rusqlite::{params, Connection};
+use std::collections::HashMap;
+
+/// Group for commits that touched no known document.
+const OTHER_CHANGES_ID: &str = "";
+
+/// Documents whose last_modified falls in [since, until), newest first, with
+/// their collection ids.
+pub fn updated_documents(
+    conn: &Connection,
+    since: i64,
+    until: i64,
+) -> Result<Vec<(String, DigestDocument)>, String> {
+    let mut stmt = conn
+        .prepare_cached(
+            "SELECT collection_id, slug, title, last_modified FROM documents
+             WHERE CAST(strftime('%s', last_modified) AS INTEGER) >= ?1
+               AND CAST(strftime('%s', last_modified) AS INTEGER) < ?2
+             ORDER BY last_modified DESC",
+        )
+        .map_err(|e| e.to_string())?;
+    let rows = stmt
+        .query_map(params![since, until], |row| {
+            Ok((
+                row.get(0)?,
+                DigestDocument {
+                    slug: row.get(1)?,
+                    title: row.get(2)?,
+                    last_modified: row.get(3)?,
+                },
+            ))
+        })
+        .map_err(|e| e.to_string())?;
+    rows.collect::<Result<Vec<_>, _>>()
+        .map_err(|e| e.to_string())
+}
+
+/// Collection id of every document, for placing commits.
+pub fn document_collections(conn: &Connection) -> Result<HashMap<String, String>, String> {
+    let mut stmt = conn
+        .prepare_cached("SELECT slug, collection_id FROM documents")
+        .map_err(|e| e.to_string())?;
+    let rows = stmt
+        .query_map([], |row| Ok((row.get(0)?, row.get(1)?)))
+        .map_err(|e| e.to_string())?;
+    rows.collect::<Result<HashMap<_, _>, _>>()
+        .map_err(|e| e.to_string())
+}
+
+/// Group updated documents and commits by collection, in the project's
+/// collection order. A commit appears under every collection it touched, or
+/// under "Other changes" when it touched no known document. Collections with
+/// nothing to report are left out.
+pub fn group_changes(
+    collections: &[Collection],
+    documents: Vec<(String, DigestDocument)>,
+    feed: &[ProjectChangeFeedItem],
+    doc_collections: &HashMap<String, String>,
+) -> Vec<DigestCollection> {
+    let mut groups: Vec<DigestCollection> = collections
+        .iter()
+        .map(|collection| DigestCollection {
+            collection_id: collection.id.clone(),
+            name: collection.name.clone(),
+            documents: vec![],
+            commits: vec![],
+            summary: None,
+        })
+        .collect();
+    groups.push(DigestCollection {
+        collection_id: OTHER_CHANGES_ID.to_string(),
+        name: "Other changes".to_string(),
+        documents: vec![],
+        commits: vec![],
+        summary: None,
+    });
+    let index: HashMap<String, usize> = groups
+        .iter()
+        .enumerate()
+        .map(|(i, group)| (group.collection_id.clone(), i))
+        .collect();
+    let other = groups.len() - 1;
+
+    for (collection_id, document) in documents {
+        let i = index.get(&collection_id).copied().unwrap_or(other);
+        groups[i].documents.push(document);
+    }
+
+    for item in feed {
+        let mut targets: Vec<usize> = item
+            .changed_doc_slugs
+            .iter()
+            .filter_map(|slug| doc_collections.get(slug))
+            .filter_map(|collection_id| index.get(collection_id).copied())
+            .collect();
+        targets.sort_unstable();
+        targets.dedup();
+        if targets.is_empty() {
+            targets.push(other);
+        }
+        for i in targets {
+            groups[i].commits.push(DigestCommit {
+                commit_hash: item.commit_hash.clone(),
+                message: item.message.clone(),
+                author: item.author.clone(),
+                committed_at: item.committed_at.clone(),
+            });
+        }
+    }
+
+    groups.retain(|group| !group.documents.is_empty() || !group.commits.is_empty());
+    groups
+}
+
+fn format_date(unix_secs: i64) -> String {
+    chrono::DateTime::from_timestamp(unix_secs, 0)
+        .map(|date| date.format("%Y-%m-%d").to_string())
+        .unwrap_or_default()
+}
+
+/// Render the digest as Markdown, one section per collection.
+pub fn render_markdown(since: i64, until: i64, collections: &[DigestCollection]) -> String {
+    let mut out = format!(
+        "# Handbook changes, {} to {}\n",
+        format_date(since),
+        format_date(until)
+    );
+    if collections.is_empty() {
+        out.push_str("\nNo documents changed in this period.\n");
+        return out;
+    }
+
+    for collection in collections {
+        out.push_str(&format!("\n## {}\n", collection.name));
+        if let Some(summary) = &collection.summary {
+            out.push_str(&format!("\n{}\n", summary.trim()));
+        }
+        if !collection.documents.is_empty() {
+            out.push_str("\n**Updated documents**\n\n");
+            for document in &collection.documents {
+                out.push_str(&format!("- {} (`{}`)\n", document.title, document.slug));
+            }
+        }
+        if !collection.commits.is_empty() {
+            out.push_str("\n**Commits**\n\n");
+            for commit in &collection.commits {
+                let hash: String = commit.commit_hash.chars().take(7).collect();
+                let message = if commit.message.is_empty() {
+                    "(no message recorded)"
+                } else {
+                    commit.message.as_str()
+                };
+                out.push_str(&format!("- `{}` {} — {}\n", hash, message, commit.author));
+            }
+        }
+    }
+    out
+}
+
+/// Assemble the digest once any AI summaries have been filled in.
+pub fn digest(since: i64, until: i64, collections: Vec<DigestCollection>) -> ChangeDigest {
+    ChangeDigest {
+        since,
+        until,
+        ai_summaries: collections.iter().any(|c| c.summary.is_some()),
+        markdown: render_markdown(since, until, &collections),
+        collections,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{digest, group_changes};
+    use crate::models::{Collection, DigestDocument, ProjectChangeFeedItem};
+    use std::collections::HashMap;
+
+    fn collection(id: &str, name: &str) -> Collection {
+        Collection {
+            id: id.to_string(),
+            name: name.to_string(),
+            icon: String::new(),
+            description: None,
+            sort_order: 0,
+            pinned: false,
+            hidden: false,
+        }
+    }
+
+    fn commit(hash: &str, message: &str, slugs: &[&str]) -> ProjectChangeFeedItem {
+        ProjectChangeFeedItem {
+            id: 0,
+            project_id: "p".to_string(),
+            commit_hash: hash.to_string(),
+            author: "Sam".to_string(),
+            committed_at: "2026-10-07T10:00:00Z".to_string(),
+            changed_files: vec![],
+            changed_doc_slugs: slugs.iter().map(|s| s.to_string()).collect(),
+            recorded_at: 0,
+            message: message.to_string(),
+        }
+    }
+
+    #[test]
+    fn changes_are_grouped_by_collection_without_a_provider() {
+        let collections = [
+            collection("eng", "Engineering"),
+            collection("ops", "Operations"),
+        ];
+        let documents = vec![(
+            "ops".to_string(),
+            DigestDocument {
+                slug: "ops/on-call".to_string(),
+                title: "On-call".to_string(),
+                last_modified: "2026-10-07T10:00:00Z".to_string(),
+            },
+        )];
+        let feed = [
+            commit(
+                "aaaaaaa111",
+                "Rework rotation",
+                &["ops/on-call", "eng/deploy"],
+            ),
+            commit("bbbbbbb222", "Fix CI config", &[]),
+        ];
+        let doc_collections = HashMap::from([
+            ("ops/on-call".to_string(), "ops".to_string()),
+            ("eng/deploy".to_string(), "eng".to_string()),
+        ]);
+
+        let groups = group_changes(&collections, documents, &feed, &doc_collections);
+        let names = groups.iter().map(|g| g.name.as_str()).collect::<Vec<_>>();
+        assert_eq!(names, vec!["Engineering", "Operations", "Other changes"]);
+        assert_eq!(groups[0].commits.len(), 1);
+        assert!(groups[0].documents.is_empty());
+        assert_eq!(groups[1].documents[0].title, "On-call");
+        assert_eq!(groups[2].commits[0].message, "Fix CI config");
+
+        // 2026-10-05 to 2026-10-12
+        let digest = digest(1_791_158_400, 1_791_763_200, groups);
+        assert!(!digest.ai_summaries);
+        assert!(digest
+            .markdown
+            .starts_with("# Handbook changes, 2026-10-05 to 2026-10-12"));
+        assert!(digest.markdown.contains("## Operations"));
+        assert!(digest.markdown.contains("- On-call (`ops/on-call`)"));
+        assert!(digest
+            .markdown
+            .contains("- `aaaaaaa` Rework rotation — Sam"));
+    }
+}
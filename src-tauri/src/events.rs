@@ -0,0 +1,115 @@
+//! App-level events for automation. When enabled in preferences, each event is
+//! also appended to `events.jsonl` in the app data dir and, optionally, POSTed
+//! to a webhook.
+//!
+//! Only events on the allowlist below are forwarded, and their payloads carry
+//! project ids and build results only, so nothing from settings (API keys,
+//! provider config) can leave through this channel.
+
+use crate::db::HttpClient;
+use std::io::Write;
+use std::path::Path;
+use std::time::Duration;
+use tauri::{AppHandle, Emitter, Manager};
+
+pub const EVENT_LOG_FILE: &str = "events.jsonl";
+const ROTATED_EVENT_LOG_FILE: &str = "events.1.jsonl";
+/// Roll the log over once it reaches this size, keeping one previous file.
+const EVENT_LOG_MAX_BYTES: u64 = 5 * 1024 * 1024;
+const WEBHOOK_TIMEOUT: Duration = Duration::from_secs(10);
+const WEBHOOK_RETRY_DELAY: Duration = Duration::from_secs(5);
+
+const FORWARDED_EVENTS: &[&str] = &[
+    "project-build-started",
+    "project-build-complete",
+    "project-build-error",
+    "project-docs-removed",
+    "project-added",
+    "project-removed",
+    "active-project-changed",
+];
+
+/// Emit an event to the frontend and forward it to the event log and webhook.
+pub fn emit(app: &AppHandle, event: &str, payload: serde_json::Value) {
+    let _ = app.emit(event, &payload);
+    if FORWARDED_EVENTS.contains(&event) {
+        forward(app, event, payload);
+    }
+}
+
+fn forward(app: &AppHandle, event: &str, payload: serde_json::Value) {
+    let preferences = crate::settings::load_preferences(app).unwrap_or_default();
+    let webhook_url = preferences
+        .event_webhook_url
+        .filter(|url| !url.trim().is_empty());
+    if !preferences.event_log_enabled && webhook_url.is_none() {
+        return;
+    }
+
+    let timestamp = std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or_default();
+    let line = serde_json::json!({ "timestamp": timestamp, "event": event, "payload": payload });
+
+    if preferences.event_log_enabled {
+        if let Ok(app_data_dir) = app.path().app_data_dir() {
+            if let Err(e) = append_event_line(&app_data_dir, &line) {
+                eprintln!("Warning: failed to write event log: {}", e);
+            }
+        }
+    }
+
+    if let Some(url) = webhook_url {
+        let client = app.state::<HttpClient>().0.clone();
+        tauri::async_runtime::spawn(async move {
+            post_webhook(&client, &url, &line).await;
+        });
+    }
+}
+
+fn append_event_line(app_data_dir: &Path, line: &serde_json::Value) -> Result<(), String> {
+    let path = app_data_dir.join(EVENT_LOG_FILE);
+    let too_large = std::fs::metadata(&path)
+        .map(|m| m.len() >= EVENT_LOG_MAX_BYTES)
+        .unwrap_or(false);
+    if too_large {
+        std::fs::rename(&path, app_data_dir.join(ROTATED_EVENT_LOG_FILE))
+            .map_err(|e| e.to_string())?;
+    }
+
+    let mut file = std::fs::OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(&path)
+        .map_err(|e| e.to_string())?;
+    writeln!(file, "{}", line).map_err(|e| e.to_string())
+}
+
+/// POST the event, retrying once after a short delay on failure.
+async fn post_webhook(client: &reqwest::Client, url: &str, line: &serde_json::Value) {
+    for attempt in 0..2 {
+        if attempt > 0 {
+            tokio::time::sleep(WEBHOOK_RETRY_DELAY).await;
+        }
+        let result = client
+            .post(url)
+            .timeout(WEBHOOK_TIMEOUT)
+            .json(line)
+            .send()
+            .await;
+        match result {
+            Ok(response) if response.status().is_success() => return,
+            Ok(response) => eprintln!(
+                "Warning: event webhook returned {} (attempt {})",
+                response.status(),
+                attempt + 1
+            ),
+            Err(e) => eprintln!(
+                "Warning: event webhook failed (attempt {}): {}",
+                attempt + 1,
+                e
+            ),
+        }
+    }
+}
@@ -0,0 +1,351 @@
+//! Highlights and notes exported from Readwise or Hypothesis, matched to a
+//! project's documents so they can be imported as highlights and notes.
+//! Matching is deliberately strict: an item that could belong to more than
+//! one document is left for the user rather than guessed.
+
+use crate::models::ExternalAnnotation;
+use crate::snippet::text_similarity;
+use serde_json::Value;
+
+/// Least title similarity accepted when no title matches exactly.
+const TITLE_MATCH_SIMILARITY: f64 = 0.92;
+/// How far a fuzzy title match must lead the next closest document.
+const TITLE_MATCH_MARGIN: f64 = 0.1;
+
+/// Separators web pages put between a page title and the site's name.
+const SITE_NAME_SEPARATORS: [&str; 4] = [" | ", " - ", " – ", " — "];
+
+/// Extensions dropped from the last URL segment before comparing with slugs.
+const PAGE_EXTENSIONS: [&str; 5] = [".html", ".htm", ".md", ".mdx", ".php"];
+
+/// Items of a Readwise export (books with their highlights, as from the
+/// export API) or a Hypothesis export (annotations with quote selectors).
+/// Items with neither highlighted text nor a note are left out.
+pub fn parse_export(json: &str) -> Result<Vec<ExternalAnnotation>, String> {
+    let root: Value = serde_json::from_str(json).map_err(|e| e.to_string())?;
+    let items = match &root {
+        Value::Array(items) => items,
+        Value::Object(map) => ["results", "annotations", "rows", "highlights"]
+            .iter()
+            .find_map(|key| map.get(*key).and_then(Value::as_array))
+            .ok_or("expected a list of annotations")?,
+        _ => return Err("expected a list of annotations".to_string()),
+    };
+
+    let mut annotations = Vec::new();
+    for item in items {
+        if let Some(highlights) = item.get("highlights").and_then(Value::as_array) {
+            let title = text_field(item, &["readable_title", "title"]);
+            let url = text_field(item, &["source_url", "url"]);
+            for highlight in highlights {
+                annotations.push(ExternalAnnotation {
+                    source: "readwise".to_string(),
+                    title: title.clone(),
+                    url: url.clone().or_else(|| text_field(highlight, &["url"])),
+                    text: text_field(highlight, &["text"]),
+                    note: text_field(highlight, &["note"]),
+                    context_text: None,
+                });
+            }
+        } else if item.get("target").is_some() || item.get("uri").is_some() {
+            let quote = item
+                .get("target")
+                .and_then(Value::as_array)
+                .into_iter()
+                .flatten()
+                .filter_map(|target| target.get("selector")?.as_array())
+                .flatten()
+                .find(|selector| {
+                    selector.get("type").and_then(Value::as_str) == Some("TextQuoteSelector")
+                });
+            let text = quote.and_then(|quote| text_field(quote, &["exact"]));
+            let context_text = quote.zip(text.as_deref()).map(|(quote, exact)| {
+                let side = |key| quote.get(key).and_then(Value::as_str).unwrap_or("");
+                format!("{}{}{}", side("prefix"), exact, side("suffix"))
+                    .trim()
+                    .to_string()
+            });
+            annotations.push(ExternalAnnotation {
+                source: "hypothesis".to_string(),
+                title: item
+                    .pointer("/document/title/0")
+                    .or_else(|| item.pointer("/document/title"))
+                    .and_then(Value::as_str)
+                    .map(str::trim)
+                    .filter(|title| !title.is_empty())
+                    .map(str::to_string),
+                url: text_field(item, &["uri"]),
+                text,
+                note: text_field(item, &["text"]),
+                context_text,
+            });
+        } else {
+            // A flat highlight, as in Readwise's highlight list
+            annotations.push(ExternalAnnotation {
+                source: "readwise".to_string(),
+                title: text_field(item, &["readable_title", "title", "book_title"]),
+                url: text_field(item, &["source_url", "url"]),
+                text: text_field(item, &["text"]),
+                note: text_field(item, &["note"]),
+                context_text: None,
+            });
+        }
+    }
+    annotations.retain(|a| a.text.is_some() || a.note.is_some());
+    Ok(annotations)
+}
+
+/// The first of `keys` holding non-blank text.
+fn text_field(value: &Value, keys: &[&str]) -> Option<String> {
+    keys.iter()
+        .filter_map(|key| value.get(*key)?.as_str())
+        .map(str::trim)
+        .find(|text| !text.is_empty())
+        .map(str::to_string)
+}
+
+/// The document an annotation belongs to, from `documents` as (slug, title).
+/// A URL whose path ends with exactly one document's slug wins, and one that
+/// doesn't belongs to some other page, whatever its title. Without a URL the
+/// title must match one document exactly, or be very close to one document
+/// and clearly closer to it than to any other.
+pub fn match_document<'a>(
+    annotation: &ExternalAnnotation,
+    documents: &'a [(String, String)],
+) -> Option<&'a str> {
+    match (annotation.url.as_deref(), annotation.title.as_deref()) {
+        (Some(url), _) => match_url(url, documents),
+        (None, Some(title)) => match_title(title, documents),
+        (None, None) => None,
+    }
+}
+
+/// Lowercased path segments of a URL, without a page extension.
+fn url_segments(url: &str) -> Vec<String> {
+    let without_scheme = url.split_once("://").map_or(url, |(_, rest)| rest);
+    let path = without_scheme
+        .split(['?', '#'])
+        .next()
+        .unwrap_or("")
+        .split_once('/')
+        .map_or("", |(_, path)| path);
+    let mut segments: Vec<String> = path
+        .split('/')
+        .filter(|segment| !segment.is_empty())
+        .map(str::to_lowercase)
+        .collect();
+    if let Some(last) = segments.last_mut() {
+        if let Some(stem) = PAGE_EXTENSIONS
+            .iter()
+            .find_map(|extension| last.strip_suffix(extension))
+        {
+            *last = stem.to_string();
+        }
+    }
+    segments
+}
+
+/// The path of an index page also stands for its directory, but only a path
+/// that is nothing but the index page matches a bare "index" slug.
+fn match_url<'a>(url: &str, documents: &'a [(String, String)]) -> Option<&'a str> {
+    let path = url_segments(url);
+    let directory = match path.split_last() {
+        Some((last, directory)) if last == "index" => Some(directory),
+        _ => None,
+    };
+    let mut best: Option<(usize, &str)> = None;
+    let mut tied = false;
+    for (slug, _) in documents {
+        let slug_segments: Vec<String> = slug
+            .split('/')
+            .filter(|segment| !segment.is_empty())
+            .map(str::to_lowercase)
+            .collect();
+        let matches = match slug_segments.as_slice() {
+            [] => false,
+            [only] if only == "index" => path.len() == 1 && directory.is_some(),
+            _ => {
+                path.ends_with(&slug_segments)
+                    || directory.is_some_and(|directory| directory.ends_with(&slug_segments))
+            }
+        };
+        if !matches {
+            continue;
+        }
+        match best {
+            Some((length, _)) if length > slug_segments.len() => {}
+            Some((length, _)) if length == slug_segments.len() => tied = true,
+            _ => {
+                best = Some((slug_segments.len(), slug));
+                tied = false;
+            }
+        }
+    }
+    best.filter(|_| !tied).map(|(_, slug)| slug)
+}
+
+/// Lowercased words of a title, punctuation dropped.
+fn title_words(title: &str) -> String {
+    title
+        .to_lowercase()
+        .split(|c: char| !c.is_alphanumeric())
+        .filter(|word| !word.is_empty())
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+/// The title as given, and without a trailing site name.
+fn title_variants(title: &str) -> Vec<String> {
+    let mut variants = vec![title_words(title)];
+    if let Some(end) = SITE_NAME_SEPARATORS
+        .iter()
+        .filter_map(|separator| title.rfind(separator))
+        .max()
+    {
+        variants.push(title_words(&title[..end]));
+    }
+    variants.retain(|variant| !variant.is_empty());
+    variants
+}
+
+fn match_title<'a>(title: &str, documents: &'a [(String, String)]) -> Option<&'a str> {
+    let variants = title_variants(title);
+    if variants.is_empty() {
+        return None;
+    }
+    let exact: Vec<&str> = documents
+        .iter()
+        .filter(|(_, doc_title)| variants.contains(&title_words(doc_title)))
+        .map(|(slug, _)| slug.as_str())
+        .collect();
+    match exact.as_slice() {
+        [slug] => return Some(slug),
+        [] => {}
+        _ => return None,
+    }
+
+    let mut scored: Vec<(f64, &str)> = documents
+        .iter()
+        .map(|(slug, doc_title)| {
+            let doc_title = title_words(doc_title);
+            let score = variants
+                .iter()
+                .map(|variant| text_similarity(variant, &doc_title))
+                .fold(0.0, f64::max);
+            (score, slug.as_str())
+        })
+        .collect();
+    scored.sort_by(|a, b| b.0.total_cmp(&a.0));
+    let (best, slug) = *scored.first()?;
+    let runner_up = scored.get(1).map_or(0.0, |(score, _)| *score);
+    (best >= TITLE_MATCH_SIMILARITY && best - runner_up >= TITLE_MATCH_MARGIN).then_some(slug)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{match_document, parse_export};
+    use crate::models::ExternalAnnotation;
+
+    fn documents() -> Vec<(String, String)> {
+        [
+            ("ops/deploys", "Deploys"),
+            ("guides/deploys", "Deploying a service"),
+            ("ops/restarts", "Restarting services"),
+            ("api/v1", "API reference v1"),
+            ("api/v2", "API reference v2"),
+            ("index", "Overview"),
+        ]
+        .iter()
+        .map(|(slug, title)| (slug.to_string(), title.to_string()))
+        .collect()
+    }
+
+    fn annotation(url: Option<&str>, title: Option<&str>) -> ExternalAnnotation {
+        ExternalAnnotation {
+            source: "readwise".to_string(),
+            title: title.map(str::to_string),
+            url: url.map(str::to_string),
+            text: Some("quote".to_string()),
+            note: None,
+            context_text: None,
+        }
+    }
+
+    #[test]
+    fn readwise_and_hypothesis_exports_are_read() {
+        let readwise = r#"{"count": 1, "results": [{
+            "title": "deploys", "readable_title": "Deploys",
+            "source_url": "https://handbook.example.com/ops/deploys",
+            "highlights": [
+                {"text": "Always roll back first.", "note": "We skipped this in March"},
+                {"text": "  ", "note": ""}
+            ]}]}"#;
+        let items = parse_export(readwise).unwrap();
+        assert_eq!(items.len(), 1);
+        assert_eq!(items[0].title.as_deref(), Some("Deploys"));
+        assert_eq!(items[0].note.as_deref(), Some("We skipped this in March"));
+
+        let hypothesis = r#"[{
+            "uri": "https://handbook.example.com/ops/restarts.html",
+            "text": "",
+            "document": {"title": ["Restarting services"]},
+            "target": [{"selector": [
+                {"type": "RangeSelector"},
+                {"type": "TextQuoteSelector", "exact": "page the on-call",
+                 "prefix": "Before restarting, ", "suffix": " and wait."}
+            ]}]
+        }]"#;
+        let items = parse_export(hypothesis).unwrap();
+        assert_eq!(items[0].source, "hypothesis");
+        assert_eq!(items[0].text.as_deref(), Some("page the on-call"));
+        assert_eq!(items[0].note, None);
+        assert_eq!(
+            items[0].context_text.as_deref(),
+            Some("Before restarting, page the on-call and wait.")
+        );
+        assert!(parse_export("42").is_err());
+    }
+
+    #[test]
+    fn matches_need_a_single_clear_document() {
+        let docs = documents();
+        let matched = |url, title| match_document(&annotation(url, title), &docs);
+
+        assert_eq!(
+            matched(
+                Some("https://x.test/handbook/ops/deploys/index.html?ref=1"),
+                None
+            ),
+            Some("ops/deploys")
+        );
+        // Two slugs end in "deploys", and a path needs all of a slug's segments
+        assert_eq!(matched(Some("https://x.test/deploys"), None), None);
+        assert_eq!(
+            matched(None, Some("Deploys | Handbook")),
+            Some("ops/deploys")
+        );
+        assert_eq!(
+            matched(None, Some("Restarting service")),
+            Some("ops/restarts")
+        );
+        // Titles differing only in version are too close to tell apart
+        assert_eq!(matched(None, Some("API reference")), None);
+        assert_eq!(matched(None, Some("Restarts")), None);
+        assert_eq!(matched(None, None), None);
+
+        assert_eq!(
+            matched(Some("https://x.test/index.html"), None),
+            Some("index")
+        );
+        assert_eq!(matched(Some("https://x.test/blog/index.html"), None), None);
+        // A page that matches no slug doesn't fall back to its title
+        assert_eq!(
+            matched(
+                Some("https://blog.example.org/posts/launch"),
+                Some("Overview")
+            ),
+            None
+        );
+        assert_eq!(matched(None, Some("Overview")), Some("index"));
+    }
+}
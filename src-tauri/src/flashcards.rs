@@ -0,0 +1,219 @@
+//! Flashcards from highlights: the highlighted text is the answer and the
+//! heading it sits under (or the sentence leading up to it) is the prompt.
+//! Decks are written as Anki-importable TSV or as JSON.
+
+use crate::models::{DocHighlight, Flashcard};
+use crate::static_site::escape_html;
+
+/// Most cards sent to the provider in the one AI-assisted request; any after
+/// these keep their plain front and back.
+pub const AI_CARD_LIMIT: usize = 100;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum DeckFormat {
+    Tsv,
+    Json,
+}
+
+impl DeckFormat {
+    pub fn parse(format: &str) -> Result<Self, String> {
+        match format {
+            "tsv" => Ok(DeckFormat::Tsv),
+            "json" => Ok(DeckFormat::Json),
+            other => Err(format!("Unknown flashcard format '{}'", other)),
+        }
+    }
+
+    pub fn extension(self) -> &'static str {
+        match self {
+            DeckFormat::Tsv => "tsv",
+            DeckFormat::Json => "json",
+        }
+    }
+}
+
+/// A card for one highlight. The front is the highlight's heading, else the
+/// sentence before it in its context, else the document title.
+pub fn card(highlight: &DocHighlight, heading: Option<&str>, doc_title: &str) -> Flashcard {
+    let front = heading
+        .map(str::trim)
+        .filter(|heading| !heading.is_empty())
+        .map(str::to_string)
+        .or_else(|| {
+            highlight
+                .context_text
+                .as_deref()
+                .and_then(|context| sentence_before(context, &highlight.selected_text))
+        })
+        .unwrap_or_else(|| doc_title.to_string());
+    Flashcard {
+        front,
+        back: highlight.selected_text.trim().to_string(),
+        doc_slug: highlight.doc_slug.clone(),
+        highlight_id: highlight.id,
+    }
+}
+
+/// The sentence in `context` that leads up to `selected`, or the part of it
+/// before the selection when the selection starts mid-sentence.
+fn sentence_before(context: &str, selected: &str) -> Option<String> {
+    let selected = selected.trim();
+    if selected.is_empty() {
+        return None;
+    }
+    let before = context[..context.find(selected)?].trim_end();
+    // A terminator ending `before` closes the sentence we want, not the one before it
+    let search = before.strip_suffix(['.', '!', '?']).unwrap_or(before);
+    let start = search
+        .char_indices()
+        .rfind(|&(i, c)| {
+            matches!(c, '.' | '!' | '?') && search[i + 1..].starts_with(char::is_whitespace)
+        })
+        .map_or(0, |(i, _)| i + 1);
+    let sentence = before[start..].trim();
+    (!sentence.is_empty()).then(|| sentence.to_string())
+}
+
+/// Anki's text import: header lines naming the separator, then one tab
+/// separated front and back per line. Fields are HTML so line breaks survive.
+pub fn tsv_deck(cards: &[Flashcard]) -> String {
+    let field = |text: &str| {
+        escape_html(text)
+            .replace("\r\n", "\n")
+            .replace('\n', "<br>")
+            .replace('\t', " ")
+    };
+    let mut deck = String::from("#separator:tab\n#html:true\n");
+    for card in cards {
+        deck.push_str(&field(&card.front));
+        deck.push('\t');
+        deck.push_str(&field(&card.back));
+        deck.push('\n');
+    }
+    deck
+}
+
+pub fn json_deck(name: &str, cards: &[Flashcard]) -> Result<String, String> {
+    serde_json::to_string_pretty(&serde_json::json!({ "name": name, "cards": cards }))
+        .map_err(|e| e.to_string())
+}
+
+/// The cards as a numbered list for the AI-assisted request.
+pub fn prompt_list(cards: &[Flashcard]) -> String {
+    cards
+        .iter()
+        .take(AI_CARD_LIMIT)
+        .enumerate()
+        .map(|(i, card)| {
+            format!(
+                "{}. Context: {}\n   Highlight: {}",
+                i + 1,
+                card.front,
+                card.back
+            )
+        })
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+/// Replace cards with the question and answer pairs in an AI reply, matched
+/// by position. Pairs that are missing or empty leave their card as it was.
+/// Returns how many cards were rewritten.
+pub fn apply_reply(cards: &mut [Flashcard], reply: &str) -> usize {
+    let (Some(start), Some(end)) = (reply.find('['), reply.rfind(']')) else {
+        return 0;
+    };
+    let Some(Ok(serde_json::Value::Array(pairs))) =
+        (start < end).then(|| serde_json::from_str(&reply[start..=end]))
+    else {
+        return 0;
+    };
+    let text = |pair: &serde_json::Value, key: &str| {
+        pair.get(key)
+            .and_then(serde_json::Value::as_str)
+            .map(str::trim)
+            .filter(|text| !text.is_empty())
+            .map(str::to_string)
+    };
+    let mut rewritten = 0;
+    for (card, pair) in cards.iter_mut().take(AI_CARD_LIMIT).zip(&pairs) {
+        if let (Some(question), Some(answer)) = (text(pair, "question"), text(pair, "answer")) {
+            card.front = question;
+            card.back = answer;
+            rewritten += 1;
+        }
+    }
+    rewritten
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{apply_reply, card, sentence_before, tsv_deck};
+    use crate::models::DocHighlight;
+
+    fn highlight(selected: &str, context: Option<&str>) -> DocHighlight {
+        DocHighlight {
+            id: 7,
+            project_id: "p".to_string(),
+            doc_slug: "ops/restarts".to_string(),
+            anchor_id: None,
+            selected_text: selected.to_string(),
+            context_text: context.map(str::to_string),
+            created_at: 0,
+        }
+    }
+
+    #[test]
+    fn fronts_prefer_heading_then_preceding_sentence_then_title() {
+        let context = "Deploys run nightly. Before deploying, check the dashboard. \
+                       Always page the on-call.";
+        let h = highlight("Always page the on-call.", Some(context));
+        assert_eq!(card(&h, Some(" Paging "), "Restarts").front, "Paging");
+        assert_eq!(
+            card(&h, None, "Restarts").front,
+            "Before deploying, check the dashboard."
+        );
+        assert_eq!(
+            card(&highlight("x", None), Some(""), "Restarts").front,
+            "Restarts"
+        );
+
+        assert_eq!(
+            sentence_before(
+                "Done. To restart, run systemctl restart app now.",
+                "systemctl restart app"
+            )
+            .as_deref(),
+            Some("To restart, run")
+        );
+        assert_eq!(sentence_before("Start here. Then", "Start here."), None);
+        assert_eq!(sentence_before("unrelated", "missing"), None);
+    }
+
+    #[test]
+    fn tsv_fields_are_single_line_html() {
+        let c = card(&highlight("a <b>\tc\nd", None), Some("Q & A"), "T");
+        assert_eq!(
+            tsv_deck(&[c]),
+            "#separator:tab\n#html:true\nQ &amp; A\ta &lt;b&gt; c<br>d\n"
+        );
+    }
+
+    #[test]
+    fn ai_pairs_replace_cards_by_position() {
+        let mut cards = vec![
+            card(&highlight("one", None), Some("H1"), "T"),
+            card(&highlight("two", None), Some("H2"), "T"),
+            card(&highlight("three", None), Some("H3"), "T"),
+        ];
+        let reply = r#"Here you go:
+            [{"question": "What is one?", "answer": "One."},
+             {"question": "", "answer": "ignored"},
+             {"question": "What is three?", "answer": "Three."}]"#;
+        assert_eq!(apply_reply(&mut cards, reply), 2);
+        assert_eq!(cards[0].front, "What is one?");
+        assert_eq!(cards[1].front, "H2");
+        assert_eq!(cards[2].back, "Three.");
+        assert_eq!(apply_reply(&mut cards, "no json"), 0);
+    }
+}
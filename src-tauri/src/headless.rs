@@ -0,0 +1,118 @@
+//! Access to the app's persisted state without a running Tauri app, for modes
+//! launched from the command line (e.g. the MCP stdio server).
+
+use crate::models::{AppPreferences, Settings};
+use crate::projects::{ProjectManager, ProjectRegistry};
+use std::path::PathBuf;
+
+/// Must match `identifier` in tauri.conf.json, which Tauri uses to derive app_data_dir.
+const APP_IDENTIFIER: &str = "com.withoutfanfare.dalil";
+const HANDBOOK_ID: &str = "engineering-handbook";
+
+/// Same location Tauri resolves for `app.path().app_data_dir()`.
+pub fn app_data_dir() -> Result<PathBuf, String> {
+    dirs::data_dir()
+        .map(|dir| dir.join(APP_IDENTIFIER))
+        .ok_or_else(|| "Could not resolve the user data directory".to_string())
+}
+
+/// Read one key from a tauri-plugin-store JSON file in the app data dir.
+fn read_store_value(file: &str, key: &str) -> Option<serde_json::Value> {
+    let path = app_data_dir().ok()?.join(file);
+    let contents = std::fs::read_to_string(path).ok()?;
+    let mut store: serde_json::Map<String, serde_json::Value> =
+        serde_json::from_str(&contents).ok()?;
+    store.remove(key)
+}
+
+pub fn load_preferences() -> AppPreferences {
+    read_store_value("settings.json", "app_preferences")
+        .and_then(|value| serde_json::from_value(value).ok())
+        .unwrap_or_default()
+}
+
+pub fn load_settings() -> Settings {
+    read_store_value("settings.json", "ai_settings")
+        .and_then(|value| serde_json::from_value(value).ok())
+        .unwrap_or_default()
+}
+
+/// Candidate locations for the bundled handbook database, mirroring
+/// `db::handbook_db_path` without needing an AppHandle.
+fn handbook_candidates() -> Vec<PathBuf> {
+    let mut candidates = Vec::new();
+    if let Ok(exe) = std::env::current_exe() {
+        if let Some(exe_dir) = exe.parent() {
+            // macOS bundles keep resources in Contents/Resources next to Contents/MacOS
+            candidates.push(exe_dir.join("../Resources/dalil.db"));
+            candidates.push(exe_dir.join("dalil.db"));
+        }
+    }
+    if cfg!(debug_assertions) {
+        if let Ok(mut dir) = std::env::current_dir() {
+            if dir.ends_with("src-tauri") {
+                dir.pop();
+            }
+            candidates.push(dir.join("dalil.db"));
+        }
+    }
+    candidates
+}
+
+/// Open every registered project read-only, recording the reason for any that
+/// cannot be opened, the same way app setup does.
+pub fn load_project_manager(handbook_db: Option<PathBuf>) -> Result<ProjectManager, String> {
+    let registry: ProjectRegistry = read_store_value("projects.json", "projects")
+        .and_then(|value| serde_json::from_value(value).ok())
+        .unwrap_or_default();
+    let app_data_dir = app_data_dir()?;
+    let mut manager = ProjectManager::new(registry);
+
+    let handbook_path =
+        handbook_db.or_else(|| handbook_candidates().into_iter().find(|p| p.exists()));
+    match handbook_path {
+        Some(path) => {
+            if let Err(e) = manager.open_connection(HANDBOOK_ID, &path) {
+                manager.unavailable.insert(HANDBOOK_ID.to_string(), e);
+            }
+        }
+        None => {
+            manager
+                .unavailable
+                .insert(HANDBOOK_ID.to_string(), "database not found".to_string());
+        }
+    }
+
+    let user_projects: Vec<_> = manager
+        .registry
+        .projects
+        .iter()
+        .filter(|p| !p.built_in)
+        .filter_map(|p| {
+            p.db_path
+                .as_ref()
+                .map(|db| (p.id.clone(), app_data_dir.join(db)))
+        })
+        .collect();
+    for (id, db_path) in user_projects {
+        if let Err(e) = manager.open_connection(&id, &db_path) {
+            manager.unavailable.insert(id, e);
+        }
+    }
+
+    if !manager
+        .connections
+        .contains_key(&manager.registry.active_project_id)
+    {
+        if let Some(fallback) = manager
+            .registry
+            .projects
+            .iter()
+            .find(|p| manager.connections.contains_key(&p.id))
+            .map(|p| p.id.clone())
+        {
+            manager.registry.active_project_id = fallback;
+        }
+    }
+    Ok(manager)
+}
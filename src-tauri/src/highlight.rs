@@ -0,0 +1,233 @@
+//! Search-term highlighting over rendered document HTML. Matches are wrapped
+//! in `<mark data-hl>` inside text nodes only, so tags, attribute values and
+//! code samples come through untouched.
+
+const MARK_OPEN: &str = "<mark data-hl>";
+const MARK_CLOSE: &str = "</mark>";
+
+/// Elements whose text is never highlighted.
+const SKIPPED_ELEMENTS: [&str; 5] = ["code", "pre", "script", "style", "textarea"];
+
+/// A character of a text node and the source it came from, which is longer
+/// than one character for an entity such as `&amp;`.
+struct Unit<'a> {
+    text: char,
+    source: &'a str,
+}
+
+/// `html` with every case-insensitive occurrence of `terms` marked, and the
+/// number of marks inserted. Overlapping terms prefer the longest match.
+pub fn highlight_html(html: &str, terms: &[String]) -> (String, usize) {
+    let mut folded: Vec<Vec<char>> = terms
+        .iter()
+        .map(|term| term.trim())
+        .filter(|term| !term.is_empty())
+        .map(|term| term.chars().flat_map(char::to_lowercase).collect())
+        .collect();
+    folded.sort_by(|a, b| b.len().cmp(&a.len()).then_with(|| a.cmp(b)));
+    folded.dedup();
+    if folded.is_empty() {
+        return (html.to_string(), 0);
+    }
+
+    let mut out = String::with_capacity(html.len());
+    let mut count = 0;
+    let mut skipped_depth = 0usize;
+    let mut rest = html;
+    while !rest.is_empty() {
+        if let Some(len) = tag_len(rest) {
+            let tag = &rest[..len];
+            if let Some((name, closing)) = tag_name(tag) {
+                if SKIPPED_ELEMENTS
+                    .iter()
+                    .any(|e| e.eq_ignore_ascii_case(name))
+                {
+                    if closing {
+                        skipped_depth = skipped_depth.saturating_sub(1);
+                    } else if !tag.ends_with("/>") {
+                        skipped_depth += 1;
+                    }
+                }
+            }
+            out.push_str(tag);
+            rest = &rest[len..];
+            continue;
+        }
+        // A text node runs to the next '<', past a stray one at its start
+        let first_len = rest.chars().next().map_or(1, char::len_utf8);
+        let text_len = rest[first_len..]
+            .find('<')
+            .map_or(rest.len(), |i| i + first_len);
+        let text = &rest[..text_len];
+        if skipped_depth > 0 {
+            out.push_str(text);
+        } else {
+            count += highlight_text(text, &folded, &mut out);
+        }
+        rest = &rest[text_len..];
+    }
+    (out, count)
+}
+
+/// Length of the tag or comment at the start of `html`. Quoted attribute
+/// values may contain `>`.
+fn tag_len(html: &str) -> Option<usize> {
+    if html.starts_with("<!--") {
+        return Some(html.find("-->").map_or(html.len(), |i| i + 3));
+    }
+    let next = html.strip_prefix('<')?.chars().next()?;
+    if !(next.is_ascii_alphabetic() || next == '/' || next == '!' || next == '?') {
+        return None;
+    }
+    let mut quote = None;
+    for (i, c) in html.char_indices() {
+        match (quote, c) {
+            (Some(q), c) if c == q => quote = None,
+            (Some(_), _) => {}
+            (None, '"' | '\'') => quote = Some(c),
+            (None, '>') => return Some(i + 1),
+            _ => {}
+        }
+    }
+    Some(html.len())
+}
+
+/// Element name of a tag, and whether it closes the element.
+fn tag_name(tag: &str) -> Option<(&str, bool)> {
+    let inner = tag.strip_prefix('<')?;
+    let (inner, closing) = match inner.strip_prefix('/') {
+        Some(name) => (name, true),
+        None => (inner, false),
+    };
+    let end = inner
+        .find(|c: char| !c.is_ascii_alphanumeric())
+        .unwrap_or(inner.len());
+    (end > 0).then(|| (&inner[..end], closing))
+}
+
+fn units(text: &str) -> Vec<Unit<'_>> {
+    let mut units = Vec::new();
+    let mut rest = text;
+    while let Some(c) = rest.chars().next() {
+        let (decoded, len) = if c == '&' {
+            crate::snippet::decode_entity(rest).unwrap_or((c, 1))
+        } else {
+            (c, c.len_utf8())
+        };
+        units.push(Unit {
+            text: decoded,
+            source: &rest[..len],
+        });
+        rest = &rest[len..];
+    }
+    units
+}
+
+/// Append `text` to `out` with matches marked, returning how many.
+fn highlight_text(text: &str, terms: &[Vec<char>], out: &mut String) -> usize {
+    let units = units(text);
+    let mut count = 0;
+    let mut i = 0;
+    while i < units.len() {
+        match terms.iter().find_map(|term| match_len(&units[i..], term)) {
+            Some(len) => {
+                out.push_str(MARK_OPEN);
+                for unit in &units[i..i + len] {
+                    out.push_str(unit.source);
+                }
+                out.push_str(MARK_CLOSE);
+                count += 1;
+                i += len;
+            }
+            None => {
+                out.push_str(units[i].source);
+                i += 1;
+            }
+        }
+    }
+    count
+}
+
+/// Number of units `term` spans at the start of `units`, compared with
+/// lowercase folding.
+fn match_len(units: &[Unit<'_>], term: &[char]) -> Option<usize> {
+    let mut expected = term.iter();
+    let mut used = 0;
+    let mut remaining = term.len();
+    for unit in units {
+        if remaining == 0 {
+            break;
+        }
+        for c in unit.text.to_lowercase() {
+            if remaining == 0 || expected.next() != Some(&c) {
+                return None;
+            }
+            remaining -= 1;
+        }
+        used += 1;
+    }
+    (remaining == 0).then_some(used)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::highlight_html;
+
+    fn terms(terms: &[&str]) -> Vec<String> {
+        terms.iter().map(|t| t.to_string()).collect()
+    }
+
+    #[test]
+    fn marks_text_but_not_code_or_attributes() {
+        let html = "<h2 id=\"deploy\" title=\"Deploy > staging\">Deploy</h2>\
+                    <p>To deploy, run <code>deploy --prod</code>.</p>\
+                    <pre><code class=\"deploy\">make deploy</code></pre>\
+                    <p><a href=\"/deploy\">DEPLOYING</a> &amp; more</p>";
+        let (out, count) = highlight_html(html, &terms(&["deploy"]));
+        assert_eq!(count, 3);
+        assert_eq!(
+            out,
+            "<h2 id=\"deploy\" title=\"Deploy > staging\"><mark data-hl>Deploy</mark></h2>\
+             <p>To <mark data-hl>deploy</mark>, run <code>deploy --prod</code>.</p>\
+             <pre><code class=\"deploy\">make deploy</code></pre>\
+             <p><a href=\"/deploy\"><mark data-hl>DEPLOY</mark>ING</a> &amp; more</p>"
+        );
+    }
+
+    #[test]
+    fn entities_match_as_whole_characters() {
+        let html = "<p>R&amp;D and Caf&eacute; &amp; amp</p>";
+        let (out, count) = highlight_html(html, &terms(&["r&d", "amp"]));
+        assert_eq!(count, 2);
+        assert_eq!(
+            out,
+            "<p><mark data-hl>R&amp;D</mark> and Caf&eacute; &amp; <mark data-hl>amp</mark></p>"
+        );
+    }
+
+    #[test]
+    fn text_starting_with_a_multibyte_character_is_marked() {
+        let html = "<p>é deploy</p><li>配置 deploy</li><p>🚀deploy</p><p>< 配</p>";
+        let (out, count) = highlight_html(html, &terms(&["deploy", "配"]));
+        assert_eq!(count, 5);
+        assert_eq!(
+            out,
+            "<p>é <mark data-hl>deploy</mark></p>\
+             <li><mark data-hl>配</mark>置 <mark data-hl>deploy</mark></li>\
+             <p>🚀<mark data-hl>deploy</mark></p><p>< <mark data-hl>配</mark></p>"
+        );
+    }
+
+    #[test]
+    fn longest_term_wins_and_blank_terms_leave_html_alone() {
+        let (out, count) = highlight_html("<p>Runbooks</p>", &terms(&["run", "runbook"]));
+        assert_eq!(count, 1);
+        assert_eq!(out, "<p><mark data-hl>Runbook</mark>s</p>");
+
+        let html = "<p>Unchanged</p>";
+        assert_eq!(
+            highlight_html(html, &terms(&[" ", ""])),
+            (html.to_string(), 0)
+        );
+    }
+}
@@ -0,0 +1,306 @@
+//! Optional read-only HTTP API for other local tools (editor plugins, scripts).
+//!
+//! Bound to 127.0.0.1 only and guarded by a bearer token. Routes map onto the
+//! shared functions in `queries`, so responses match the Tauri commands. No
+//! route touches settings, preferences, user state, or AI keys, and
+//! local-only projects are not served.
+
+use crate::models::AppPreferences;
+use crate::projects::ProjectManager;
+use crate::queries;
+use rusqlite::Connection;
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use std::thread::JoinHandle;
+use tauri::{AppHandle, Manager};
+use tiny_http::{Header, Method, Request, Response, Server};
+
+pub const DEFAULT_HTTP_API_PORT: u16 = 7841;
+const DEFAULT_SEARCH_LIMIT: i32 = 20;
+const MAX_SEARCH_LIMIT: i32 = 100;
+
+struct RunningServer {
+    server: Arc<Server>,
+    /// Set before the server is unblocked, so the serving thread can tell
+    /// being stopped from a failed connection
+    stopping: Arc<AtomicBool>,
+    port: u16,
+    token: String,
+    thread: Option<JoinHandle<()>>,
+}
+
+/// Handle to the running server, if any. Managed as Tauri state.
+#[derive(Default)]
+pub struct HttpApiState(Mutex<Option<RunningServer>>);
+
+/// Random 32-byte token, hex encoded.
+pub fn generate_token() -> Result<String, String> {
+    let mut bytes = [0u8; 32];
+    getrandom::fill(&mut bytes).map_err(|e| e.to_string())?;
+    Ok(bytes.iter().map(|b| format!("{:02x}", b)).collect())
+}
+
+/// Start, restart, or stop the server so it matches the preferences.
+pub fn apply_preferences(app: &AppHandle, preferences: &AppPreferences) -> Result<(), String> {
+    let state = app.state::<HttpApiState>();
+    let mut running = state.0.lock().map_err(|e| e.to_string())?;
+
+    let port = preferences.http_api_port.unwrap_or(DEFAULT_HTTP_API_PORT);
+    let token = preferences
+        .http_api_token
+        .as_deref()
+        .filter(|t| !t.is_empty());
+    let wanted = match (preferences.http_api_enabled, token) {
+        (true, Some(token)) => Some((port, token)),
+        (true, None) => return Err("HTTP API requires a token".to_string()),
+        (false, _) => None,
+    };
+
+    if let Some(current) = running.as_ref() {
+        if wanted == Some((current.port, current.token.as_str())) {
+            return Ok(());
+        }
+    }
+    if let Some(current) = running.take() {
+        stop(current);
+    }
+
+    if let Some((port, token)) = wanted {
+        let server = Server::http(("127.0.0.1", port))
+            .map_err(|e| format!("Failed to start HTTP API on port {}: {}", port, e))?;
+        let server = Arc::new(server);
+        let stopping = Arc::new(AtomicBool::new(false));
+        let thread = {
+            let app = app.clone();
+            let server = Arc::clone(&server);
+            let stopping = Arc::clone(&stopping);
+            let token = token.to_string();
+            std::thread::spawn(move || serve(app, server, stopping, token))
+        };
+        *running = Some(RunningServer {
+            server,
+            stopping,
+            port,
+            token: token.to_string(),
+            thread: Some(thread),
+        });
+    }
+    Ok(())
+}
+
+fn stop(mut running: RunningServer) {
+    running.stopping.store(true, Ordering::SeqCst);
+    running.server.unblock();
+    if let Some(thread) = running.thread.take() {
+        let _ = thread.join();
+    }
+    // Dropping the last Arc closes the listening socket
+}
+
+fn serve(app: AppHandle, server: Arc<Server>, stopping: Arc<AtomicBool>, token: String) {
+    loop {
+        let request = match server.recv() {
+            Ok(request) => request,
+            Err(_) if stopping.load(Ordering::SeqCst) => break,
+            // One client's broken connection is no reason to stop serving
+            Err(e) => {
+                eprintln!("Warning: HTTP API failed to receive a request: {}", e);
+                continue;
+            }
+        };
+        let (status, body) = handle(&app, &request, &token);
+        let response = Response::from_string(body.to_string())
+            .with_status_code(status)
+            .with_header(
+                Header::from_bytes(&b"Content-Type"[..], &b"application/json"[..])
+                    .expect("static header is valid"),
+            );
+        let _ = request.respond(response);
+    }
+}
+
+fn tokens_match(provided: &str, expected: &str) -> bool {
+    provided.len() == expected.len()
+        && provided
+            .bytes()
+            .zip(expected.bytes())
+            .fold(0u8, |acc, (a, b)| acc | (a ^ b))
+            == 0
+}
+
+fn percent_decode(value: &str) -> String {
+    let bytes = value.as_bytes();
+    let mut out = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        match bytes[i] {
+            b'+' => out.push(b' '),
+            b'%' if i + 2 < bytes.len() => {
+                let hex = std::str::from_utf8(&bytes[i + 1..i + 3]).unwrap_or("");
+                match u8::from_str_radix(hex, 16) {
+                    Ok(b) => {
+                        out.push(b);
+                        i += 2;
+                    }
+                    Err(_) => out.push(b'%'),
+                }
+            }
+            b => out.push(b),
+        }
+        i += 1;
+    }
+    String::from_utf8_lossy(&out).to_string()
+}
+
+fn parse_url(url: &str) -> (String, HashMap<String, String>) {
+    let (path, query) = url.split_once('?').unwrap_or((url, ""));
+    let params = query
+        .split('&')
+        .filter(|pair| !pair.is_empty())
+        .map(|pair| {
+            let (key, value) = pair.split_once('=').unwrap_or((pair, ""));
+            (percent_decode(key), percent_decode(value))
+        })
+        .collect();
+    (percent_decode(path), params)
+}
+
+fn error_body(message: impl Into<String>) -> serde_json::Value {
+    serde_json::json!({ "error": message.into() })
+}
+
+/// Why a route has no answer.
+enum RouteError {
+    NotFound,
+    Failed(String),
+}
+
+impl From<String> for RouteError {
+    fn from(message: String) -> Self {
+        RouteError::Failed(message)
+    }
+}
+
+fn to_json<T: serde::Serialize, E: Into<RouteError>>(
+    result: Result<T, E>,
+) -> (u16, serde_json::Value) {
+    match result.map_err(Into::into) {
+        Ok(value) => match serde_json::to_value(value) {
+            Ok(json) => (200, json),
+            Err(e) => (500, error_body(e.to_string())),
+        },
+        Err(RouteError::NotFound) => (404, error_body("Not found")),
+        Err(RouteError::Failed(e)) => (400, error_body(e)),
+    }
+}
+
+/// The requested project's connection, else the active project's. Local-only
+/// projects are not served.
+fn project_connection<'a>(
+    mgr: &'a ProjectManager,
+    project_id: Option<&String>,
+) -> Result<&'a Connection, (u16, serde_json::Value)> {
+    let project_id = project_id.unwrap_or(&mgr.registry.active_project_id);
+    if mgr.is_local_only(project_id) {
+        return Err((403, error_body("This project is local-only")));
+    }
+    mgr.connection(project_id).map_err(|e| (404, error_body(e)))
+}
+
+fn handle(app: &AppHandle, request: &Request, token: &str) -> (u16, serde_json::Value) {
+    if *request.method() != Method::Get {
+        return (405, error_body("Only GET is supported"));
+    }
+    let authorised = request
+        .headers()
+        .iter()
+        .find(|h| h.field.equiv("Authorization"))
+        .and_then(|h| h.value.as_str().strip_prefix("Bearer "))
+        .map(|provided| tokens_match(provided.trim(), token))
+        .unwrap_or(false);
+    if !authorised {
+        return (401, error_body("Missing or invalid bearer token"));
+    }
+
+    let (path, params) = parse_url(request.url());
+    let manager = app.state::<Mutex<ProjectManager>>();
+    let mgr = match manager.lock() {
+        Ok(mgr) => mgr,
+        Err(e) => return (500, error_body(e.to_string())),
+    };
+    let conn = match project_connection(&mgr, params.get("project")) {
+        Ok(conn) => conn,
+        Err(response) => return response,
+    };
+    let collection = params.get("collection").map(String::as_str);
+
+    match path.trim_end_matches('/') {
+        "/search" => {
+            let Some(query) = params.get("q") else {
+                return (400, error_body("Missing query parameter 'q'"));
+            };
+            let limit = params
+                .get("limit")
+                .and_then(|l| l.parse::<i32>().ok())
+                .unwrap_or(DEFAULT_SEARCH_LIMIT)
+                .clamp(1, MAX_SEARCH_LIMIT);
+            to_json(queries::search_documents(
+                conn,
+                query,
+                collection,
+                &[],
+                limit,
+                crate::snippet::DEFAULT_SNIPPET_TOKENS,
+            ))
+        }
+        "/collections" => to_json(queries::collections(
+            conn,
+            queries::CollectionSort::SortOrder,
+        )),
+        "/tags" => to_json(queries::tags(conn, collection, queries::TagSort::Count)),
+        other => match other.strip_prefix("/documents/") {
+            Some(slug) if !slug.is_empty() => to_json(
+                queries::find_document(conn, slug)
+                    .map_err(RouteError::from)
+                    .and_then(|document| document.ok_or(RouteError::NotFound)),
+            ),
+            _ => (404, error_body("Unknown route")),
+        },
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::project_connection;
+    use crate::projects::{ProjectManager, ProjectRegistry};
+
+    #[test]
+    fn local_only_projects_are_not_served() {
+        let mut registry = ProjectRegistry::default();
+        let mut confidential = registry.projects[0].clone();
+        confidential.id = "confidential".to_string();
+        confidential.local_only = true;
+        registry.projects.push(confidential);
+        let mut mgr = ProjectManager::new(registry);
+        for id in ["engineering-handbook", "confidential"] {
+            mgr.connections.insert(
+                id.to_string(),
+                rusqlite::Connection::open_in_memory().unwrap(),
+            );
+        }
+
+        assert!(project_connection(&mgr, None).is_ok());
+        let refused = project_connection(&mgr, Some(&"confidential".to_string()));
+        assert_eq!(refused.err().map(|(status, _)| status), Some(403));
+        mgr.registry.active_project_id = "confidential".to_string();
+        assert_eq!(
+            project_connection(&mgr, None)
+                .err()
+                .map(|(status, _)| status),
+            Some(403)
+        );
+        let missing = project_connection(&mgr, Some(&"unknown".to_string()));
+        assert_eq!(missing.err().map(|(status, _)| status), Some(404));
+    }
+}
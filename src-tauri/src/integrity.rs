@@ -0,0 +1,297 @@
+//! Cross-checks between the tables of a built project database: navigation
+//! entries without documents, documents without navigation or chunks, tag
+//! links to missing rows, and chunks and embeddings that lost their partner.
+//! Used to report builder bugs and as a post-build sanity check. Rows the
+//! builder leaves unpaired on purpose are not issues: folder entries in the
+//! navigation, collection index pages outside it, and documents with no text
+//! to chunk.
+
+use crate::ai::table_exists;
+use crate::models::{IntegrityIssue, IntegrityReport};
+use crate::native_builder::has_chunkable_text;
+use rusqlite::Connection;
+
+/// Examples kept per issue category.
+pub const MAX_EXAMPLES: usize = 50;
+
+/// A category of mismatch: the tables it needs, and a query for the
+/// identifying text of each offending row.
+struct Check {
+    category: &'static str,
+    tables: &'static [&'static str],
+    sql: &'static str,
+    /// Given the query's second column, whether the row is expected after
+    /// all and not an issue
+    expected: Option<fn(&str) -> bool>,
+}
+
+fn has_no_chunkable_text(content_raw: &str) -> bool {
+    !has_chunkable_text(content_raw)
+}
+
+const CHECKS: &[Check] = &[
+    Check {
+        category: "navigation_without_document",
+        tables: &["navigation_tree", "documents"],
+        // Navigation slugs are relative to their collection; folders have
+        // no document of their own
+        sql: "SELECT n.collection_id || '/' || n.slug FROM navigation_tree n
+              WHERE n.has_children = 0
+                AND NOT EXISTS (
+                    SELECT 1 FROM documents d WHERE d.slug = n.collection_id || '/' || n.slug
+                )
+              ORDER BY 1",
+        expected: None,
+    },
+    Check {
+        category: "document_without_navigation",
+        tables: &["navigation_tree", "documents"],
+        // Root documents outside any section are collection index pages,
+        // which the navigation leaves out
+        sql: "SELECT d.slug FROM documents d
+              WHERE NOT (d.section = '' AND d.parent_slug = '')
+                AND NOT EXISTS (
+                    SELECT 1 FROM navigation_tree n
+                    WHERE n.collection_id || '/' || n.slug = d.slug
+                )
+              ORDER BY d.slug",
+        expected: None,
+    },
+    Check {
+        category: "document_without_chunks",
+        tables: &["chunks", "documents"],
+        sql: "SELECT d.slug, d.content_raw FROM documents d
+              WHERE NOT EXISTS (SELECT 1 FROM chunks c WHERE c.document_id = d.id)
+              ORDER BY d.slug",
+        expected: Some(has_no_chunkable_text),
+    },
+    Check {
+        category: "chunk_without_document",
+        tables: &["chunks", "documents"],
+        sql: "SELECT 'chunk ' || c.id FROM chunks c
+              WHERE NOT EXISTS (SELECT 1 FROM documents d WHERE d.id = c.document_id)
+              ORDER BY c.id",
+        expected: None,
+    },
+    Check {
+        category: "document_tag_without_tag",
+        tables: &["document_tags", "tags", "documents"],
+        sql: "SELECT COALESCE(d.slug, 'document ' || dt.document_id) FROM document_tags dt
+              LEFT JOIN documents d ON d.id = dt.document_id
+              WHERE NOT EXISTS (SELECT 1 FROM tags t WHERE t.id = dt.tag_id)
+              ORDER BY 1",
+        expected: None,
+    },
+    Check {
+        category: "document_tag_without_document",
+        tables: &["document_tags", "tags", "documents"],
+        sql: "SELECT COALESCE(t.tag, 'tag ' || dt.tag_id) FROM document_tags dt
+              LEFT JOIN tags t ON t.id = dt.tag_id
+              WHERE NOT EXISTS (SELECT 1 FROM documents d WHERE d.id = dt.document_id)
+              ORDER BY 1",
+        expected: None,
+    },
+    // Embeddings are optional, so a project with none is not missing any
+    Check {
+        category: "chunk_without_embedding",
+        tables: &["chunks", "chunk_embeddings", "documents"],
+        sql: "SELECT COALESCE(d.slug, 'document ' || c.document_id) || ' #' || c.chunk_index
+              FROM chunks c
+              LEFT JOIN documents d ON d.id = c.document_id
+              WHERE EXISTS (SELECT 1 FROM chunk_embeddings)
+                AND NOT EXISTS (SELECT 1 FROM chunk_embeddings e WHERE e.chunk_id = c.id)
+              ORDER BY 1",
+        expected: None,
+    },
+    Check {
+        category: "embedding_without_chunk",
+        tables: &["chunks", "chunk_embeddings"],
+        sql: "SELECT 'chunk ' || e.chunk_id FROM chunk_embeddings e
+              WHERE NOT EXISTS (SELECT 1 FROM chunks c WHERE c.id = e.chunk_id)
+              ORDER BY e.chunk_id",
+        expected: None,
+    },
+];
+
+/// Every category with at least one offending row. Categories whose tables
+/// the database lacks are skipped.
+pub fn integrity_report(conn: &Connection) -> Result<IntegrityReport, String> {
+    let mut issues = Vec::new();
+    for check in CHECKS {
+        if !check.tables.iter().all(|table| table_exists(conn, table)) {
+            continue;
+        }
+        let mut stmt = conn.prepare(check.sql).map_err(|e| e.to_string())?;
+        let mut rows = stmt.query([]).map_err(|e| e.to_string())?;
+        let mut count = 0;
+        let mut examples = Vec::new();
+        while let Some(row) = rows.next().map_err(|e| e.to_string())? {
+            if let Some(expected) = check.expected {
+                let detail: String = row.get(1).map_err(|e| e.to_string())?;
+                if expected(&detail) {
+                    continue;
+                }
+            }
+            if examples.len() < MAX_EXAMPLES {
+                examples.push(row.get(0).map_err(|e| e.to_string())?);
+            }
+            count += 1;
+        }
+        if count > 0 {
+            issues.push(IntegrityIssue {
+                category: check.category.to_string(),
+                count,
+                examples,
+            });
+        }
+    }
+    Ok(IntegrityReport { issues })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{integrity_report, MAX_EXAMPLES};
+    use rusqlite::Connection;
+
+    fn project_db() -> Connection {
+        let db = Connection::open_in_memory().expect("open in-memory sqlite");
+        db.execute_batch(
+            "CREATE TABLE documents (
+                id INTEGER PRIMARY KEY,
+                slug TEXT NOT NULL,
+                section TEXT NOT NULL DEFAULT 'Guides',
+                parent_slug TEXT NOT NULL DEFAULT '',
+                content_raw TEXT NOT NULL DEFAULT 'Some text.'
+            );
+            CREATE TABLE navigation_tree (
+                id INTEGER PRIMARY KEY,
+                collection_id TEXT NOT NULL DEFAULT 'guides',
+                slug TEXT NOT NULL,
+                has_children INTEGER NOT NULL DEFAULT 0
+            );
+            CREATE TABLE tags (id INTEGER PRIMARY KEY, tag TEXT NOT NULL);
+            CREATE TABLE document_tags (document_id INTEGER NOT NULL, tag_id INTEGER NOT NULL);
+            CREATE TABLE chunks (
+                id INTEGER PRIMARY KEY,
+                document_id INTEGER NOT NULL,
+                chunk_index INTEGER NOT NULL
+            );
+            CREATE TABLE chunk_embeddings (chunk_id INTEGER PRIMARY KEY, embedding BLOB);
+
+            INSERT INTO documents (id, slug) VALUES (1, 'guides/setup'), (2, 'guides/deploys');
+            INSERT INTO navigation_tree (id, slug) VALUES (1, 'setup'), (2, 'deploys');
+            INSERT INTO tags VALUES (1, 'ops');
+            INSERT INTO document_tags VALUES (1, 1), (2, 1);
+            INSERT INTO chunks VALUES (1, 1, 0), (2, 2, 0);
+            INSERT INTO chunk_embeddings VALUES (1, x'00'), (2, x'00');",
+        )
+        .expect("create project tables");
+        db
+    }
+
+    #[test]
+    fn consistent_database_has_no_issues() {
+        let db = project_db();
+        assert!(integrity_report(&db).unwrap().issues.is_empty());
+        db.execute("DELETE FROM chunk_embeddings", []).unwrap();
+        assert!(integrity_report(&db).unwrap().issues.is_empty());
+
+        // A folder, the collection's index page and a heading-only page
+        db.execute_batch(
+            "INSERT INTO navigation_tree (slug, has_children) VALUES ('runbooks', 1);
+            INSERT INTO documents (id, slug, section) VALUES (5, 'guides/index', '');
+            INSERT INTO chunks VALUES (5, 5, 0);
+            INSERT INTO documents (id, slug, content_raw) VALUES (6, 'guides/todo', '# To do');
+            INSERT INTO navigation_tree (slug) VALUES ('todo');",
+        )
+        .unwrap();
+        assert!(integrity_report(&db).unwrap().issues.is_empty());
+    }
+
+    #[test]
+    fn freshly_built_database_has_no_issues() {
+        let dir = std::env::temp_dir().join(format!("dalil-integrity-{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        let source = dir.join("source");
+        std::fs::create_dir_all(source.join("deploys/advanced/canary")).unwrap();
+        std::fs::write(source.join("README.md"), "# Guides\n\nStart here.\n").unwrap();
+        std::fs::write(source.join("01-setup.md"), "# Setup\n\nInstall it.\n").unwrap();
+        std::fs::write(source.join("todo.md"), "# To do\n").unwrap();
+        std::fs::write(source.join("deploys/rollback.md"), "# Rollback\n\nUndo.\n").unwrap();
+        std::fs::write(
+            source.join("deploys/advanced/canary/steps.md"),
+            "# Steps\n\nGo slowly.\n",
+        )
+        .unwrap();
+        let db_path = dir.join("guides.db");
+        crate::native_builder::build_project(&crate::native_builder::NativeBuildRequest {
+            source_path: &source,
+            db_path: &db_path,
+            collection_id: "guides",
+            collection_name: "Guides",
+            collection_icon: "",
+            incremental: false,
+        })
+        .unwrap();
+
+        let db = Connection::open(&db_path).unwrap();
+        let issues = integrity_report(&db).unwrap().issues;
+        assert!(issues.is_empty(), "{:?}", issues);
+        drop(db);
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn mismatches_are_counted_with_examples() {
+        let db = project_db();
+        db.execute_batch(
+            "INSERT INTO navigation_tree (id, slug) VALUES (3, 'gone');
+            INSERT INTO documents (id, slug) VALUES (3, 'guides/unlisted');
+            INSERT INTO document_tags VALUES (9, 1), (1, 7);
+            INSERT INTO chunks VALUES (3, 9, 0), (4, 1, 1);
+            INSERT INTO chunk_embeddings VALUES (8, x'00'), (3, x'00');",
+        )
+        .unwrap();
+        let issues: Vec<(String, usize, Vec<String>)> = integrity_report(&db)
+            .unwrap()
+            .issues
+            .into_iter()
+            .map(|issue| (issue.category, issue.count, issue.examples))
+            .collect();
+        let expected = |category: &str, count, examples: &[&str]| {
+            (
+                category.to_string(),
+                count,
+                examples.iter().map(|e| e.to_string()).collect::<Vec<_>>(),
+            )
+        };
+        assert_eq!(
+            issues,
+            vec![
+                expected("navigation_without_document", 1, &["guides/gone"]),
+                expected("document_without_navigation", 1, &["guides/unlisted"]),
+                expected("document_without_chunks", 1, &["guides/unlisted"]),
+                expected("chunk_without_document", 1, &["chunk 3"]),
+                expected("document_tag_without_tag", 1, &["guides/setup"]),
+                expected("document_tag_without_document", 1, &["ops"]),
+                expected("chunk_without_embedding", 1, &["guides/setup #1"]),
+                expected("embedding_without_chunk", 1, &["chunk 8"]),
+            ]
+        );
+    }
+
+    #[test]
+    fn examples_are_capped() {
+        let db = project_db();
+        for i in 0..MAX_EXAMPLES + 5 {
+            db.execute(
+                "INSERT INTO navigation_tree (collection_id, slug) VALUES ('missing', ?1)",
+                [format!("page-{:03}", i)],
+            )
+            .unwrap();
+        }
+        let report = integrity_report(&db).unwrap();
+        assert_eq!(report.issues[0].count, MAX_EXAMPLES + 5);
+        assert_eq!(report.issues[0].examples.len(), MAX_EXAMPLES);
+    }
+}
@@ -1,9 +1,41 @@
 mod ai;
+mod ann;
+mod build_logs;
+mod build_queue;
+mod builder;
+mod cli;
 mod commands;
+mod crash_report;
 mod db;
+mod digest;
+mod events;
+mod external_annotations;
+mod flashcards;
+mod headless;
+mod highlight;
+mod http_api;
+mod integrity;
+mod mcp;
 mod models;
+mod native_builder;
+mod note_checklist;
+mod notifications;
 mod projects;
+mod queries;
+mod quick_search;
+mod rag_traces;
+mod release_notes;
+mod report;
 mod settings;
+mod share_links;
+mod slug_aliases;
+mod snippet;
+#[cfg(target_os = "macos")]
+mod spotlight;
+mod static_site;
+mod suggestions;
+#[cfg(test)]
+mod test_support;
 mod user_state;
 
 use db::{init_db, HttpClient};
@@ -28,49 +60,148 @@ fn set_dock_icon() {
     }
 }
 
+/// Entry point for `dalil --cli <command>`. Exits with the command's status.
+pub fn run_cli() {
+    let args: Vec<String> = std::env::args()
+        .skip_while(|a| a != "--cli")
+        .skip(1)
+        .collect();
+    std::process::exit(cli::run(&args));
+}
+
+/// Entry point for `dalil --mcp`: serve MCP over stdio without opening a
+/// window. `--handbook-db <path>` overrides the bundled handbook location.
+pub fn run_mcp_stdio() {
+    let preferences = headless::load_preferences();
+    if !preferences.mcp_server_enabled {
+        eprintln!("MCP server is disabled. Enable it in Dalil's preferences first.");
+        std::process::exit(1);
+    }
+    ai::keywords::apply_preferences(&preferences);
+    let args: Vec<String> = std::env::args().collect();
+    let handbook_db = args
+        .iter()
+        .position(|a| a == "--handbook-db")
+        .and_then(|i| args.get(i + 1))
+        .map(std::path::PathBuf::from);
+
+    let result = headless::load_project_manager(handbook_db).and_then(|manager| {
+        for (id, reason) in &manager.unavailable {
+            eprintln!("Project '{}' is unavailable: {}", id, reason);
+        }
+        let server = mcp::McpServer::new(
+            manager,
+            headless::load_settings(),
+            headless::app_data_dir().ok(),
+        );
+        mcp::run_stdio(server)
+    });
+    if let Err(e) = result {
+        eprintln!("MCP server failed: {}", e);
+        std::process::exit(1);
+    }
+}
+
 pub fn run() {
     tauri::Builder::default()
         .plugin(tauri_plugin_shell::init())
         .plugin(tauri_plugin_store::Builder::default().build())
         .plugin(tauri_plugin_process::init())
         .plugin(tauri_plugin_updater::Builder::new().build())
-        .plugin(tauri_plugin_window_state::Builder::new().build())
+        .plugin(
+            tauri_plugin_window_state::Builder::new()
+                .with_denylist(&[quick_search::QUICK_SEARCH_LABEL])
+                .build(),
+        )
         .plugin(tauri_plugin_deep_link::init())
         .plugin(tauri_plugin_dialog::init())
+        .plugin(tauri_plugin_global_shortcut::Builder::new().build())
         .setup(|app| {
+            if let Ok(app_data_dir) = app.path().app_data_dir() {
+                crash_report::install_panic_hook(crash_report::logs_dir(&app_data_dir));
+            }
+
             #[cfg(target_os = "macos")]
             set_dock_icon();
 
             // ProjectManager: manages multiple project DB connections
-            let registry = load_registry(app.handle()).unwrap_or_default();
+            let registry = load_registry(app.handle()).unwrap_or_else(|e| {
+                eprintln!("Warning: failed to load project registry: {}", e);
+                Default::default()
+            });
             let mut manager = ProjectManager::new(registry);
 
-            // Open the built-in handbook connection
-            let handbook_conn = init_db(app.handle());
-            manager.connections.insert("engineering-handbook".to_string(), handbook_conn);
+            // Open the built-in handbook connection. A broken bundle should not
+            // take user projects down with it, so record the reason and carry on.
+            match init_db(app.handle()) {
+                Ok(handbook_conn) => {
+                    manager
+                        .connections
+                        .insert("engineering-handbook".to_string(), handbook_conn);
+                }
+                Err(e) => {
+                    eprintln!("Warning: built-in handbook unavailable: {}", e);
+                    manager
+                        .unavailable
+                        .insert("engineering-handbook".to_string(), e);
+                }
+            }
 
             // Restore connections for user-added projects
             let app_data_dir = app.path().app_data_dir()?;
-            let user_projects: Vec<_> = manager.registry.projects.iter()
+            let user_projects: Vec<_> = manager
+                .registry
+                .projects
+                .iter()
                 .filter(|p| !p.built_in)
-                .filter_map(|p| p.db_path.as_ref().map(|db| (p.id.clone(), app_data_dir.join(db))))
+                .filter_map(|p| {
+                    p.db_path
+                        .as_ref()
+                        .map(|db| (p.id.clone(), app_data_dir.join(db)))
+                })
                 .collect();
             for (id, db_path) in user_projects {
                 if db_path.exists() {
                     if let Err(e) = manager.open_connection(&id, &db_path) {
-                        eprintln!("Warning: failed to open database for project '{}': {}", id, e);
+                        eprintln!(
+                            "Warning: failed to open database for project '{}': {}",
+                            id, e
+                        );
+                        manager.unavailable.insert(id, e);
                     }
+                } else {
+                    manager
+                        .unavailable
+                        .insert(id, format!("database not found at {:?}", db_path));
                 }
             }
 
-            // If the active project has no connection, fall back to the handbook
-            if !manager.connections.contains_key(&manager.registry.active_project_id) {
-                eprintln!(
-                    "Warning: active project '{}' has no database — falling back to engineering-handbook",
-                    manager.registry.active_project_id
-                );
-                manager.registry.active_project_id = "engineering-handbook".to_string();
-                let _ = projects::save_registry(app.handle(), &manager.registry);
+            // If the active project has no connection, fall back to the handbook,
+            // or to the first project that did open when the handbook is missing too
+            if !manager
+                .connections
+                .contains_key(&manager.registry.active_project_id)
+            {
+                let fallback = if manager.connections.contains_key("engineering-handbook") {
+                    Some("engineering-handbook".to_string())
+                } else {
+                    manager
+                        .registry
+                        .projects
+                        .iter()
+                        .find(|p| manager.connections.contains_key(&p.id))
+                        .map(|p| p.id.clone())
+                };
+                if let Some(fallback) = fallback {
+                    eprintln!(
+                        "Warning: active project '{}' has no database — falling back to {}",
+                        manager.registry.active_project_id, fallback
+                    );
+                    manager.registry.active_project_id = fallback;
+                    let _ = projects::save_registry(app.handle(), &manager.registry);
+                } else {
+                    eprintln!("Warning: no project databases could be opened");
+                }
             }
 
             app.manage(std::sync::Mutex::new(manager));
@@ -83,31 +214,113 @@ pub fn run() {
                 .expect("Failed to build HTTP client");
             app.manage(HttpClient(http_client));
 
+            app.manage(build_queue::BuildQueue::default());
+
+            // Tray icon and global quick-search shortcut (both opt-in via preferences)
+            let preferences = settings::load_preferences(app.handle()).unwrap_or_default();
+            ai::keywords::apply_preferences(&preferences);
+            if let Err(e) = quick_search::setup_tray(app.handle(), &preferences) {
+                eprintln!("Warning: failed to create tray icon: {}", e);
+            }
+            if let Err(e) = quick_search::apply_preferences(app.handle(), &preferences) {
+                eprintln!("Warning: {}", e);
+            }
+
+            // Read-only local HTTP API (opt-in via preferences)
+            app.manage(http_api::HttpApiState::default());
+            if let Err(e) = http_api::apply_preferences(app.handle(), &preferences) {
+                eprintln!("Warning: {}", e);
+            }
+
+            // Load the Ollama model early when it will answer questions
+            let handle = app.handle().clone();
+            tauri::async_runtime::spawn(async move {
+                let client = handle.state::<HttpClient>().0.clone();
+                commands::warm_ollama(&handle, &client, true).await;
+            });
+
             Ok(())
         })
         .invoke_handler(tauri::generate_handler![
             commands::get_collections,
+            commands::set_collection_override,
+            commands::set_collection_hidden,
             commands::get_navigation,
             commands::get_document,
             commands::search_documents,
+            commands::suggest_queries,
+            commands::record_recent_search,
             commands::get_tags,
+            commands::get_tag_coverage,
             commands::get_documents_by_tag,
+            commands::get_term_definition,
+            commands::list_glossary_terms,
+            commands::set_project_glossary_collection,
+            commands::set_project_local_only,
+            commands::set_project_excluded_collections,
+            commands::set_project_retrieval_config,
+            commands::locate_document,
+            commands::get_document_anchors,
+            commands::get_document_outline,
+            commands::create_share_link,
+            commands::validate_bookmarks,
             commands::get_similar_chunks,
+            commands::get_similar_documents,
+            commands::hybrid_search_chunks,
+            commands::evaluate_retrieval,
             commands::get_settings,
             commands::save_settings,
             commands::test_provider,
+            commands::warm_ollama_model,
             commands::ask_question,
+            commands::ask_about_document,
+            commands::compare_documents,
+            commands::suggest_document_tags,
             commands::get_embedding,
             commands::list_projects,
             commands::get_active_project_id,
             commands::set_active_project,
+            commands::get_recent_projects,
+            commands::get_last_session,
+            commands::save_last_session,
             commands::add_project,
+            commands::add_project_from_db,
+            commands::list_orphaned_projects,
+            commands::recover_orphaned_projects,
+            commands::restore_registry_backup,
             commands::rebuild_project,
+            commands::diagnose_build_environment,
+            commands::get_build_logs,
+            commands::get_about_info,
+            commands::get_release_notes,
+            commands::read_build_log,
+            commands::get_last_crash_report,
+            commands::reload_document,
+            commands::get_build_queue,
+            commands::cancel_queued_build,
+            commands::get_project_removal_preview,
             commands::remove_project,
             commands::get_project_stats,
+            commands::get_usage_stats,
+            commands::get_ai_status,
+            commands::get_storage_breakdown,
+            commands::list_all_documents,
+            commands::export_report,
+            commands::get_project_integrity_report,
+            commands::get_event_schema_version,
+            commands::get_search_diagnostics,
+            commands::prune_embeddings,
+            commands::convert_embeddings_to_int8,
+            commands::generate_missing_embeddings,
             commands::open_in_editor,
             commands::get_preferences,
             commands::save_preferences,
+            commands::show_quick_search,
+            commands::hide_quick_search,
+            commands::open_quick_search_result,
+            commands::export_spotlight_index,
+            commands::export_static_site,
+            commands::regenerate_http_api_token,
             commands::list_bookmarks,
             commands::upsert_bookmark,
             commands::remove_bookmark,
@@ -124,16 +337,49 @@ pub fn run() {
             commands::bulk_delete_bookmarks,
             commands::bulk_set_bookmark_folder,
             commands::bulk_set_bookmark_tags,
+            commands::bulk_set_bookmark_favorite,
             commands::mark_document_viewed,
+            commands::mark_all_viewed,
             commands::get_recent_documents,
+            commands::record_document_closed,
+            commands::get_recently_closed,
             commands::get_updated_documents,
+            commands::get_tag_detail,
             commands::get_project_change_feed,
+            commands::generate_change_digest,
+            commands::get_project_home,
+            commands::get_collection_badges,
+            commands::get_header_summary,
             commands::get_doc_note,
             commands::save_doc_note,
+            commands::save_doc_note_draft,
+            commands::get_doc_note_draft,
+            commands::list_doc_note_revisions,
+            commands::restore_doc_note_revision,
+            commands::toggle_note_checkbox,
             commands::list_doc_highlights,
             commands::add_doc_highlight,
+            commands::export_highlights_flashcards,
+            commands::add_inbox_item,
+            commands::list_inbox_items,
+            commands::resolve_inbox_item,
+            commands::delete_inbox_item,
+            commands::suggest_inbox_destinations,
+            commands::export_user_state,
+            commands::import_user_state,
             commands::delete_doc_highlight,
+            commands::set_annotation_folders,
+            commands::set_annotation_tags,
+            commands::list_all_annotations,
+            commands::get_orphaned_user_state,
+            commands::repair_doc_note,
+            commands::repair_doc_highlight,
+            commands::import_external_annotations,
+            commands::list_deleted_items,
+            commands::restore_deleted_item,
+            commands::purge_deleted_items,
             commands::cancel_ai_request,
+            commands::get_rag_trace,
         ])
         .run(tauri::generate_context!())
         .expect("error while running tauri application");
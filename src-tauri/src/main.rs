@@ -2,5 +2,13 @@
 #![cfg_attr(not(debug_assertions), windows_subsystem = "windows")]
 
 fn main() {
+    if std::env::args().any(|a| a == "--cli") {
+        dalil_lib::run_cli();
+        return;
+    }
+    if std::env::args().any(|a| a == "--mcp") {
+        dalil_lib::run_mcp_stdio();
+        return;
+    }
     dalil_lib::run()
 }
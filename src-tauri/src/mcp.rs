@@ -0,0 +1,309 @@
+//! Model Context Protocol server over stdio, so MCP clients (e.g. Claude
+//! Desktop) can search handbook content. Launched with `--mcp`; requires the
+//! `mcpServerEnabled` preference.
+//!
+//! Only read-only tools are exposed, backed by the same query functions as the
+//! app. Local-only projects are refused. Every tool call is recorded in the
+//! AI debug log.
+
+use crate::ai;
+use crate::models::Settings;
+use crate::projects::ProjectManager;
+use crate::queries;
+use serde_json::{json, Value};
+use std::io::{BufRead, Write};
+use std::path::PathBuf;
+use std::sync::Mutex;
+
+/// MCP protocol versions this server speaks, newest first.
+const PROTOCOL_VERSIONS: &[&str] = &["2024-11-05"];
+const DEFAULT_TOOL_LIMIT: i64 = 10;
+const MAX_TOOL_LIMIT: i64 = 50;
+
+pub struct McpServer {
+    manager: Mutex<ProjectManager>,
+    /// Read for the retrieval config; providers are never called
+    settings: Settings,
+    log_dir: Option<PathBuf>,
+}
+
+fn project_property() -> Value {
+    json!({
+        "type": "string",
+        "description": "Project id; defaults to the active project"
+    })
+}
+
+fn tool_definitions() -> Value {
+    json!([
+        {
+            "name": "search_documents",
+            "description": "Full-text search over handbook documents. Returns slugs, titles, and highlighted snippets.",
+            "inputSchema": {
+                "type": "object",
+                "properties": {
+                    "query": { "type": "string" },
+                    "project": project_property(),
+                    "collection": { "type": "string", "description": "Restrict to one collection id" },
+                    "limit": { "type": "integer", "minimum": 1, "maximum": MAX_TOOL_LIMIT }
+                },
+                "required": ["query"]
+            }
+        },
+        {
+            "name": "get_document",
+            "description": "Fetch a document by slug, as returned by search_documents.",
+            "inputSchema": {
+                "type": "object",
+                "properties": {
+                    "slug": { "type": "string" },
+                    "project": project_property()
+                },
+                "required": ["slug"]
+            }
+        },
+        {
+            "name": "get_similar_chunks",
+            "description": "Retrieve the document chunks most relevant to a question. Pass an embedding from the project's embedding model to add vector ranking; otherwise keyword ranking is used.",
+            "inputSchema": {
+                "type": "object",
+                "properties": {
+                    "query": { "type": "string" },
+                    "embedding": { "type": "array", "items": { "type": "number" } },
+                    "project": project_property(),
+                    "limit": { "type": "integer", "minimum": 1, "maximum": MAX_TOOL_LIMIT }
+                },
+                "required": ["query"]
+            }
+        }
+    ])
+}
+
+fn rpc_result(id: &Value, result: Value) -> Value {
+    json!({ "jsonrpc": "2.0", "id": id, "result": result })
+}
+
+fn rpc_error(id: &Value, code: i64, message: &str) -> Value {
+    json!({ "jsonrpc": "2.0", "id": id, "error": { "code": code, "message": message } })
+}
+
+fn required_str<'a>(args: &'a Value, key: &str) -> Result<&'a str, String> {
+    args.get(key)
+        .and_then(Value::as_str)
+        .ok_or_else(|| format!("Missing string argument '{}'", key))
+}
+
+fn limit_arg(args: &Value) -> i64 {
+    args.get("limit")
+        .and_then(Value::as_i64)
+        .unwrap_or(DEFAULT_TOOL_LIMIT)
+        .clamp(1, MAX_TOOL_LIMIT)
+}
+
+impl McpServer {
+    pub fn new(manager: ProjectManager, settings: Settings, log_dir: Option<PathBuf>) -> Self {
+        Self {
+            manager: Mutex::new(manager),
+            settings,
+            log_dir,
+        }
+    }
+
+    fn call_tool(&self, name: &str, args: &Value) -> Result<Value, String> {
+        let mgr = self.manager.lock().map_err(|e| e.to_string())?;
+        let project_id = args
+            .get("project")
+            .and_then(Value::as_str)
+            .unwrap_or(&mgr.registry.active_project_id);
+        let conn = mgr.external_connection(project_id)?;
+
+        match name {
+            "search_documents" => {
+                let results = queries::search_documents(
+                    conn,
+                    required_str(args, "query")?,
+                    args.get("collection").and_then(Value::as_str),
+                    &[],
+                    limit_arg(args) as i32,
+                    crate::snippet::DEFAULT_SNIPPET_TOKENS,
+                )?;
+                serde_json::to_value(results).map_err(|e| e.to_string())
+            }
+            "get_document" => {
+                let document = queries::document(conn, required_str(args, "slug")?)?;
+                serde_json::to_value(document).map_err(|e| e.to_string())
+            }
+            "get_similar_chunks" => {
+                let query = required_str(args, "query")?;
+                let limit = limit_arg(args) as usize;
+                let embedding: Vec<f32> = args
+                    .get("embedding")
+                    .and_then(Value::as_array)
+                    .map(|values| {
+                        values
+                            .iter()
+                            .filter_map(Value::as_f64)
+                            .map(|v| v as f32)
+                            .collect()
+                    })
+                    .unwrap_or_default();
+                let excluded = mgr.excluded_collections(project_id);
+                let chunks = if embedding.is_empty() {
+                    ai::fts_chunk_search(conn, query, limit, &excluded)?
+                } else {
+                    let retrieval = mgr.retrieval_config(project_id, &self.settings);
+                    ai::hybrid_search(conn, &embedding, query, limit, &excluded, &retrieval)?
+                };
+                serde_json::to_value(chunks).map_err(|e| e.to_string())
+            }
+            other => Err(format!("Unknown tool '{}'", other)),
+        }
+    }
+
+    /// Handle one JSON-RPC message. Notifications yield no response.
+    pub fn handle_message(&self, message: &Value) -> Option<Value> {
+        // Notifications (e.g. notifications/initialized) have no id and need no reply
+        let id = message.get("id").cloned()?;
+        let method = message.get("method").and_then(Value::as_str).unwrap_or("");
+
+        let response = match method {
+            "initialize" => {
+                // The client's version if supported, else the newest this
+                // server has, which the client may then reject
+                let requested = message
+                    .pointer("/params/protocolVersion")
+                    .and_then(Value::as_str);
+                let version = PROTOCOL_VERSIONS
+                    .iter()
+                    .find(|version| Some(**version) == requested)
+                    .unwrap_or(&PROTOCOL_VERSIONS[0]);
+                rpc_result(
+                    &id,
+                    json!({
+                        "protocolVersion": version,
+                        "capabilities": { "tools": {} },
+                        "serverInfo": { "name": "dalil", "version": env!("CARGO_PKG_VERSION") }
+                    }),
+                )
+            }
+            "ping" => rpc_result(&id, json!({})),
+            "tools/list" => rpc_result(&id, json!({ "tools": tool_definitions() })),
+            "tools/call" => {
+                let name = message
+                    .pointer("/params/name")
+                    .and_then(Value::as_str)
+                    .unwrap_or("");
+                let args = message
+                    .pointer("/params/arguments")
+                    .cloned()
+                    .unwrap_or_else(|| json!({}));
+                let outcome = self.call_tool(name, &args);
+                if let Some(dir) = &self.log_dir {
+                    ai::append_debug_log(
+                        dir,
+                        "mcp_tool_call",
+                        json!({
+                            "tool": name,
+                            "arguments": &args,
+                            "error": outcome.as_ref().err(),
+                        }),
+                    );
+                }
+                let (text, is_error) = match outcome {
+                    Ok(value) => (value.to_string(), false),
+                    Err(e) => (e, true),
+                };
+                rpc_result(
+                    &id,
+                    json!({
+                        "content": [{ "type": "text", "text": text }],
+                        "isError": is_error
+                    }),
+                )
+            }
+            _ => rpc_error(&id, -32601, "Method not found"),
+        };
+        Some(response)
+    }
+}
+
+/// Serve newline-delimited JSON-RPC on stdin/stdout until stdin closes. The
+/// loop runs on a background thread; stdout carries protocol messages only.
+pub fn run_stdio(server: McpServer) -> Result<(), String> {
+    let worker = std::thread::spawn(move || {
+        let stdin = std::io::stdin();
+        let mut stdout = std::io::stdout();
+        for line in stdin.lock().lines() {
+            let Ok(line) = line else { break };
+            if line.trim().is_empty() {
+                continue;
+            }
+            let response = match serde_json::from_str::<Value>(&line) {
+                Ok(message) => server.handle_message(&message),
+                Err(e) => Some(rpc_error(&Value::Null, -32700, &e.to_string())),
+            };
+            if let Some(response) = response {
+                if writeln!(stdout, "{}", response)
+                    .and_then(|_| stdout.flush())
+                    .is_err()
+                {
+                    break;
+                }
+            }
+        }
+    });
+    worker
+        .join()
+        .map_err(|_| "MCP server thread panicked".to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::McpServer;
+    use crate::models::Settings;
+    use crate::projects::{ProjectManager, ProjectRegistry};
+    use serde_json::json;
+
+    #[test]
+    fn local_only_projects_are_refused() {
+        let mut registry = ProjectRegistry::default();
+        let mut confidential = registry.projects[0].clone();
+        confidential.id = "confidential".to_string();
+        confidential.local_only = true;
+        registry.projects.push(confidential);
+        let mut mgr = ProjectManager::new(registry);
+        for id in ["engineering-handbook", "confidential"] {
+            mgr.connections.insert(
+                id.to_string(),
+                rusqlite::Connection::open_in_memory().unwrap(),
+            );
+        }
+        let server = McpServer::new(mgr, Settings::default(), None);
+        let call = |name: &str, arguments: serde_json::Value| {
+            let response = server
+                .handle_message(&json!({
+                    "jsonrpc": "2.0",
+                    "id": 1,
+                    "method": "tools/call",
+                    "params": { "name": name, "arguments": arguments }
+                }))
+                .unwrap();
+            let text = response["result"]["content"][0]["text"]
+                .as_str()
+                .unwrap()
+                .to_string();
+            (response["result"]["isError"] == json!(true), text)
+        };
+
+        for name in ["search_documents", "get_document", "get_similar_chunks"] {
+            let (is_error, text) = call(
+                name,
+                json!({ "project": "confidential", "query": "intro", "slug": "intro" }),
+            );
+            assert!(is_error, "{}", name);
+            assert!(text.contains("local-only"), "{}: {}", name, text);
+        }
+        let (_, text) = call("get_document", json!({ "slug": "intro" }));
+        assert!(!text.contains("local-only"), "{}", text);
+    }
+}
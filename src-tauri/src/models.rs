@@ -7,6 +7,10 @@ pub struct Collection {
     pub icon: String,
     pub description: Option<String>,
     pub sort_order: i32,
+    /// Set from the user's collection override; never true from the build
+    pub pinned: bool,
+    /// Hidden from navigation by the user
+    pub hidden: bool,
 }
 
 #[derive(Debug, Serialize)]
@@ -19,9 +23,12 @@ pub struct NavigationNode {
     pub sort_order: i32,
     pub level: i32,
     pub has_children: bool,
+    /// Filled in by `get_navigation` unless reading times are turned off
+    pub word_count: Option<i64>,
+    pub reading_minutes: Option<i64>,
 }
 
-#[derive(Debug, Serialize)]
+#[derive(Debug, Clone, Serialize)]
 pub struct Document {
     pub id: i32,
     pub collection_id: String,
@@ -35,6 +42,16 @@ pub struct Document {
     pub last_modified: Option<String>,
 }
 
+/// A document as returned to the UI, with search terms optionally marked.
+#[derive(Debug, Serialize)]
+pub struct DocumentView {
+    #[serde(flatten)]
+    pub document: Document,
+    /// Marks inserted into `content_html`; absent unless terms were requested
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub highlight_count: Option<usize>,
+}
+
 #[derive(Debug, Serialize)]
 pub struct SearchResult {
     pub slug: String,
@@ -42,6 +59,34 @@ pub struct SearchResult {
     pub section: String,
     pub collection_id: String,
     pub snippet: String,
+    /// Plain text around the first match, for the expanded result card;
+    /// empty outside full-text search
+    pub context: String,
+    /// Lower-ranked results with the same normalized title folded into this
+    /// one; zero unless search was asked to dedupe similar titles
+    pub collapsed_count: usize,
+    pub collapsed_slugs: Vec<String>,
+}
+
+/// A document's metadata for sitemap-style listings and exports.
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DocumentListItem {
+    pub slug: String,
+    pub title: String,
+    pub collection_id: String,
+    pub section: String,
+    pub last_modified: Option<String>,
+    pub word_count: i64,
+    pub reading_minutes: i64,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DocumentListing {
+    pub documents: Vec<DocumentListItem>,
+    /// Documents matching the filter across all pages
+    pub total: i64,
 }
 
 #[derive(Debug, Serialize)]
@@ -50,6 +95,84 @@ pub struct Tag {
     pub count: i32,
 }
 
+/// How much of a collection is tagged.
+#[derive(Debug, Serialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct TagCoverage {
+    pub collection_id: String,
+    pub collection_name: String,
+    pub document_count: i64,
+    /// Documents with no tag rows at all
+    pub untagged_count: i64,
+    /// Documents with a tag row pointing at a tag that no longer exists
+    pub dangling_tag_count: i64,
+}
+
+#[derive(Debug, Serialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct TermDefinition {
+    pub term: String,
+    pub definition: String,
+    pub doc_slug: String,
+    pub doc_title: String,
+    pub collection_id: String,
+    /// "title" for an exact glossary title match, "definition" for a full-text match
+    pub match_kind: String,
+}
+
+#[derive(Debug, Serialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct GlossaryTerm {
+    pub term: String,
+    pub slug: String,
+}
+
+#[derive(Debug, Serialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct DocumentLocation {
+    pub project_id: String,
+    pub project_name: String,
+    pub slug: String,
+    pub title: String,
+    pub collection_id: String,
+    /// "slug" for an exact slug match, "title" for a title match
+    pub match_kind: String,
+    pub score: f64,
+}
+
+/// A completion offered while typing in the search box.
+#[derive(Debug, Serialize, Clone, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct QuerySuggestion {
+    /// "title", "heading" or "recent" for one of the user's recent searches
+    pub kind: String,
+    pub text: String,
+    /// The document a title or heading belongs to; None for recent searches
+    pub slug: Option<String>,
+    pub anchor_id: Option<String>,
+}
+
+#[derive(Debug, Serialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct DocumentAnchor {
+    pub anchor_id: String,
+    pub heading_text: String,
+    pub level: i32,
+    pub position: i32,
+}
+
+#[derive(Debug, Serialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct BookmarkValidation {
+    pub bookmark_id: i64,
+    pub doc_slug: String,
+    pub anchor_id: Option<String>,
+    /// "ok", "missing_document", or "missing_anchor"
+    pub status: String,
+    pub suggested_anchor_id: Option<String>,
+    pub suggested_heading_text: Option<String>,
+}
+
 #[derive(Debug, Serialize, Clone)]
 pub struct ScoredChunk {
     pub id: i32,
@@ -60,6 +183,41 @@ pub struct ScoredChunk {
     pub score: f64,
 }
 
+/// A hybrid search result with the parts of its score.
+#[derive(Debug, Serialize, Clone)]
+pub struct ExplainedChunk {
+    #[serde(flatten)]
+    pub chunk: ScoredChunk,
+    /// Cosine similarity, when the chunk matched the query embedding
+    pub vector_score: Option<f64>,
+    pub fts_matched: bool,
+    /// Added for the full-text match
+    pub boost: f64,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SimilarDocument {
+    pub slug: String,
+    pub title: String,
+    pub collection_id: String,
+    /// Cosine similarity of the best-matching chunk
+    pub score: f64,
+}
+
+/// AI tag suggestions for a document, split by whether the tag is already
+/// used in the project.
+#[derive(Debug, Serialize, Clone, Default, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct TagSuggestions {
+    /// Tags from the project's vocabulary
+    pub existing: Vec<String>,
+    /// New tags the model proposed
+    pub proposed: Vec<String>,
+    /// Tags the document already has, which are never suggested
+    pub current: Vec<String>,
+}
+
 #[derive(Debug, Serialize)]
 #[serde(rename_all = "camelCase")]
 pub struct ProjectStats {
@@ -69,12 +227,538 @@ pub struct ProjectStats {
     pub chunk_count: i32,
     pub embedding_count: i32,
     pub db_size_bytes: u64,
+    /// Collections the user has hidden; still included in the counts above
+    pub hidden_collection_count: i32,
+}
+
+/// What removing a project would delete from user state, and its database size.
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ProjectRemovalPreview {
+    pub project_id: String,
+    pub bookmarks: i64,
+    pub notes: i64,
+    pub highlights: i64,
+    pub doc_views: i64,
+    pub inbox_items: i64,
+    pub feed_entries: i64,
+    /// None when the project has no database file on disk
+    pub db_size_bytes: Option<u64>,
+}
+
+/// Where the time went in a project switch, in milliseconds.
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ProjectSwitchTiming {
+    /// Waiting for the project manager lock, e.g. behind a running query
+    pub lock_wait_ms: f64,
+    /// Checking the project exists and has an open connection
+    pub validation_ms: f64,
+    pub save_registry_ms: f64,
+    /// The whole command, including recording the activation
+    pub total_ms: f64,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TableStorage {
+    pub name: String,
+    pub bytes: i64,
+    pub pages: i64,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct StorageBreakdown {
+    pub total_bytes: i64,
+    pub tables: Vec<TableStorage>,
+}
+
+/// How an FTS5 table splits text into tokens, read from its declaration.
+#[derive(Debug, Serialize, Clone, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct FtsTokenizer {
+    pub table: String,
+    /// The `tokenize` option as declared; empty when the default is used
+    pub declaration: String,
+    /// "unicode61", "ascii" or "trigram"; a porter stemmer wraps one of the first two
+    pub tokenizer: String,
+    pub porter: bool,
+    /// Extra characters treated as part of a token
+    pub token_chars: String,
+    /// Extra characters treated as separators
+    pub separators: String,
+}
+
+impl FtsTokenizer {
+    /// The tokenizer FTS5 uses when a table declares none.
+    pub fn default_for(table: &str) -> Self {
+        Self {
+            table: table.to_string(),
+            declaration: String::new(),
+            tokenizer: "unicode61".to_string(),
+            porter: false,
+            token_chars: String::new(),
+            separators: String::new(),
+        }
+    }
+
+    /// Whether `c` is part of a token rather than a separator.
+    pub fn is_token_char(&self, c: char) -> bool {
+        if self.separators.contains(c) {
+            return false;
+        }
+        if self.token_chars.contains(c) {
+            return true;
+        }
+        match self.tokenizer.as_str() {
+            "ascii" => !c.is_ascii() || c.is_ascii_alphanumeric(),
+            // Trigram tokens are any three characters, punctuation included
+            "trigram" => true,
+            _ => c.is_alphanumeric(),
+        }
+    }
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SearchDiagnostics {
+    /// None when the project has no such table
+    pub documents_fts: Option<FtsTokenizer>,
+    pub chunks_fts: Option<FtsTokenizer>,
+    /// The MATCH expression search would send for the given query
+    pub sanitised_query: Option<String>,
+}
+
+/// Rows of one kind that disagree with another table, e.g. navigation
+/// entries whose document is missing.
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct IntegrityIssue {
+    /// e.g. "navigation_without_document" or "chunk_without_embedding"
+    pub category: String,
+    pub count: usize,
+    /// Slugs, or row ids where there is no slug, up to 50
+    pub examples: Vec<String>,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct IntegrityReport {
+    /// Only categories with at least one problem
+    pub issues: Vec<IntegrityIssue>,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PruneSummary {
+    pub embeddings_removed: i64,
+    pub bytes_before: u64,
+    pub bytes_after: u64,
+}
+
+#[derive(Debug, Serialize, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum AnnIndexStatus {
+    /// Exact search only: the project is small or the index failed to build
+    Inactive,
+    Building,
+    Active,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AiStatus {
+    pub embedding_count: i32,
+    pub ann_index: AnnIndexStatus,
+    /// Collections AI retrieval currently skips
+    pub excluded_collections: Vec<String>,
+    /// Hybrid search settings in effect for the project
+    pub retrieval: RetrievalConfig,
+    /// Ollama answered at the configured base URL; false when it was not
+    /// probed because another provider is in use
+    pub ollama_available: bool,
+}
+
+/// How hybrid search gathers and scores passages. Set in `Settings`, and
+/// overridden per project.
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+#[serde(rename_all = "camelCase", default)]
+pub struct RetrievalConfig {
+    /// Nearest chunks taken from the vector search before merging
+    pub vector_candidates: usize,
+    /// Chunks taken from the full-text search before merging
+    pub fts_candidates: usize,
+    /// Score added to chunks that also match the full-text query, and the
+    /// floor for text-only matches
+    pub fts_boost: f64,
+    /// Vector matches scoring below this are dropped before merging
+    pub min_vector_score: f64,
+}
+
+impl Default for RetrievalConfig {
+    fn default() -> Self {
+        Self {
+            vector_candidates: 20,
+            fts_candidates: 20,
+            fts_boost: 0.35,
+            min_vector_score: 0.0,
+        }
+    }
+}
+
+/// One chunk as seen at a stage of the RAG pipeline.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct RagTraceChunk {
+    pub chunk_id: i32,
+    pub document_id: i32,
+    pub heading_context: String,
+    pub score: f64,
+    /// Merged results only: the vector leg's score, if the chunk came from it
+    #[serde(default)]
+    pub vector_score: Option<f64>,
+    #[serde(default)]
+    pub fts_matched: bool,
+    #[serde(default)]
+    pub boost: f64,
+    /// A short excerpt, or the whole chunk in a verbose trace
+    pub text: String,
+}
+
+/// What happened while answering one question, for working out why an
+/// answer went wrong.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct RagTrace {
+    pub request_id: String,
+    /// Unix seconds
+    pub created_at: i64,
+    pub project_id: String,
+    pub question: String,
+    /// Provider that embedded the question and answered it
+    pub provider: String,
+    /// The question's embedding came from the cache
+    pub embedding_cached: bool,
+    /// Why embedding failed, leaving retrieval to full-text search
+    pub embedding_error: Option<String>,
+    pub retrieval: RetrievalConfig,
+    pub excluded_collections: Vec<String>,
+    pub vector_candidates: Vec<RagTraceChunk>,
+    pub fts_candidates: Vec<RagTraceChunk>,
+    /// Both legs merged, best first; sent as context only when `grounded`
+    pub merged: Vec<RagTraceChunk>,
+    /// Notes and highlights of the user's included as context
+    pub personal_context_count: usize,
+    pub best_score: Option<f64>,
+    pub min_score: f64,
+    pub grounded: bool,
+    /// Zero when nothing was sent to the provider
+    pub prompt_messages: usize,
+    pub prompt_chars: usize,
+    pub prompt_tokens_estimate: usize,
+    /// Chunk texts are kept whole rather than cut to excerpts
+    pub verbose: bool,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RetrievalCase {
+    pub question: String,
+    pub expected_slug: String,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RetrievalCaseResult {
+    pub question: String,
+    pub expected_slug: String,
+    /// 1-based position of the first passage from the expected document,
+    /// None when it isn't in the top k
+    pub rank: Option<usize>,
+    /// Documents of the top k passages, best first
+    pub retrieved_slugs: Vec<String>,
+    /// Why the case couldn't be run, as when its question failed to embed;
+    /// such cases count toward neither metric
+    pub error: Option<String>,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RetrievalEvaluation {
+    pub k: usize,
+    pub config: RetrievalConfig,
+    /// False when no embedding provider was available and only text search ran
+    pub used_embeddings: bool,
+    /// Share of cases whose expected document was in the top k
+    pub hit_rate: f64,
+    /// Mean of 1 / rank, counting misses as 0
+    pub mean_reciprocal_rank: f64,
+    pub cases: Vec<RetrievalCaseResult>,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct EmbeddingRunSummary {
+    pub embedded: i64,
+    /// Chunks still without an embedding, e.g. after cancelling
+    pub remaining: i64,
+    pub cancelled: bool,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct QuantizeSummary {
+    pub embeddings_converted: i64,
+    pub bytes_before: u64,
+    pub bytes_after: u64,
+}
+
+/// Outcome of a project build. Reused documents kept their chunks and
+/// embeddings from the previous build; rebuilt ones were chunked afresh.
+#[derive(Debug, Serialize, Clone, Default)]
+#[serde(rename_all = "camelCase")]
+pub struct BuildSummary {
+    pub documents_rebuilt: i64,
+    pub documents_reused: i64,
+    pub chunks_embedded: i64,
 }
 
 #[derive(Debug, Serialize, Deserialize, Clone, Default)]
 #[serde(rename_all = "camelCase")]
 pub struct AppPreferences {
     pub editor_command: Option<String>,
+    /// Show the tray icon and register the global quick-search shortcut
+    #[serde(default)]
+    pub quick_search_enabled: bool,
+    /// Accelerator such as "CmdOrCtrl+Shift+Space"; None uses the default
+    #[serde(default)]
+    pub quick_search_shortcut: Option<String>,
+    /// Show system notifications when long builds finish
+    #[serde(default)]
+    pub notifications_enabled: bool,
+    /// Local hour (0-23) at which notifications are silenced
+    #[serde(default)]
+    pub quiet_hours_start: Option<u8>,
+    /// Local hour (0-23) at which notifications resume
+    #[serde(default)]
+    pub quiet_hours_end: Option<u8>,
+    /// Keep per-document Spotlight stubs in sync with each build (macOS only)
+    #[serde(default)]
+    pub spotlight_export_enabled: bool,
+    /// Serve the read-only local HTTP API on 127.0.0.1
+    #[serde(default)]
+    pub http_api_enabled: bool,
+    /// Port for the HTTP API; None uses the default
+    #[serde(default)]
+    pub http_api_port: Option<u16>,
+    /// Bearer token clients must send; generated when the API is first enabled
+    #[serde(default)]
+    pub http_api_token: Option<String>,
+    /// Allow `dalil --mcp` to serve handbook search to MCP clients over stdio
+    #[serde(default)]
+    pub mcp_server_enabled: bool,
+    /// Append app-level events to events.jsonl in the app data dir
+    #[serde(default)]
+    pub event_log_enabled: bool,
+    /// POST each app-level event to this URL when set
+    #[serde(default)]
+    pub event_webhook_url: Option<String>,
+    /// Build projects with the Node script (Shiki highlighting) instead of the built-in builder
+    #[serde(default)]
+    pub node_build_pipeline: bool,
+    /// Keep notes and highlights out of questions sent to AI providers
+    #[serde(default)]
+    pub ai_exclude_personal_context: bool,
+    /// Best retrieval score a question needs before it is sent to a provider
+    #[serde(default)]
+    pub ai_min_context_score: Option<f64>,
+    /// Stop-word list for question keywords, e.g. "de"; None uses English
+    #[serde(default)]
+    pub ai_keyword_language: Option<String>,
+    /// Extra words left out of question keywords
+    #[serde(default)]
+    pub ai_custom_stop_words: Vec<String>,
+    /// Record how each question was answered under logs/rag in the app data dir
+    #[serde(default)]
+    pub ai_rag_traces: bool,
+    /// Keep whole chunk texts in RAG traces rather than short excerpts
+    #[serde(default)]
+    pub ai_rag_trace_verbose: bool,
+    /// Leave collections hidden from navigation out of search and AI retrieval
+    #[serde(default)]
+    pub exclude_hidden_collections_from_search: bool,
+    /// Node executable for the Node build pipeline; None searches for one
+    #[serde(default)]
+    pub node_binary_path: Option<String>,
+    /// npm executable or npm-cli.js used to rebuild native modules; None searches for one
+    #[serde(default)]
+    pub npm_binary_path: Option<String>,
+    /// Feed for in-app release notes; None reads the updater's latest.json
+    #[serde(default)]
+    pub release_notes_url: Option<String>,
+    /// Stop counting questions, searches and reads in the local usage statistics
+    #[serde(default)]
+    pub usage_stats_disabled: bool,
+    /// Tokens in search result snippets (8-64); None uses 30
+    #[serde(default)]
+    pub search_snippet_tokens: Option<i32>,
+    /// Skip word counts and reading times in navigation, which otherwise
+    /// means one pass over every document of a project built without counts
+    #[serde(default)]
+    pub reading_time_disabled: bool,
+}
+
+/// Daily counts of one usage metric.
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct UsageSeries {
+    pub metric: String,
+    /// One count per entry of `UsageStats::days`
+    pub counts: Vec<i64>,
+    pub total: i64,
+}
+
+/// Local usage statistics over a range of days.
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct UsageStats {
+    pub range: String,
+    /// YYYY-MM-DD in local time, oldest first
+    pub days: Vec<String>,
+    pub series: Vec<UsageSeries>,
+}
+
+/// One release's notes from the release feed.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ReleaseNote {
+    /// Without a leading "v"
+    pub version: String,
+    /// Publication date as given by the feed, usually RFC 3339
+    pub date: Option<String>,
+    /// Markdown
+    pub body: String,
+}
+
+/// Release notes for this version and newer ones.
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ReleaseNotes {
+    /// Newest first
+    pub entries: Vec<ReleaseNote>,
+    /// The feed could not be reached, so these are from the last fetch
+    pub stale: bool,
+    /// Unix seconds of the last successful fetch
+    pub fetched_at: Option<i64>,
+    pub error: Option<String>,
+}
+
+/// Versions for the About dialog and support requests.
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AboutInfo {
+    /// Version of the Rust crate
+    pub crate_version: String,
+    /// Version from the Tauri config, which the updater compares against
+    pub app_version: String,
+    /// Unix seconds when the bundled handbook database was written
+    pub handbook_built_at: Option<i64>,
+    /// None when the handbook database could not be opened
+    pub handbook_document_count: Option<i64>,
+    /// `user_version` of user_state.db
+    pub user_state_schema_revision: i64,
+    /// e.g. "aarch64-apple-darwin"
+    pub target_triple: String,
+    /// Version offered by the update server, if newer than this one
+    pub pending_update_version: Option<String>,
+    /// Why the update check failed, e.g. when offline
+    pub update_check_error: Option<String>,
+}
+
+/// A panic report written by the crash hook.
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CrashReport {
+    pub path: String,
+    /// Unix seconds when the panic happened
+    pub created_at: i64,
+    pub contents: String,
+    /// True the first time the report is fetched after the crash
+    pub crashed_last_session: bool,
+}
+
+/// A saved build log.
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct BuildLogEntry {
+    pub path: String,
+    pub file_name: String,
+    pub size_bytes: u64,
+    /// Unix seconds when the build finished writing it
+    pub modified_at: i64,
+}
+
+/// A node or npm executable the build pipeline tried.
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ToolCandidate {
+    pub path: String,
+    /// Output of `--version`; None when it could not be run
+    pub version: Option<String>,
+}
+
+/// Where the Node build pipeline looks for its tools and what it finds.
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct BuildEnvironmentReport {
+    /// Candidates in the order they are tried
+    pub node_candidates: Vec<ToolCandidate>,
+    /// The candidate builds would use
+    pub node: Option<ToolCandidate>,
+    /// Only searched once node is found, since some candidates run through it
+    pub npm_candidates: Vec<ToolCandidate>,
+    pub npm: Option<ToolCandidate>,
+    /// `command -v` results from the user's login shell
+    pub login_shell_node: Option<String>,
+    pub login_shell_npm: Option<String>,
+    /// The checkout holding scripts/build-handbook.ts
+    pub project_root: Option<String>,
+    pub project_root_error: Option<String>,
+    pub tsx_present: bool,
+    pub better_sqlite3_present: bool,
+}
+
+/// A build waiting in, or running at the head of, the build queue.
+#[derive(Debug, Serialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct QueuedBuild {
+    pub id: u64,
+    pub project_id: String,
+    /// "add" or "rebuild"
+    pub kind: String,
+    /// Number of builds ahead of this one; 0 for the running build
+    pub position: usize,
+    pub running: bool,
+    pub enqueued_at: i64,
+}
+
+#[derive(Debug, Serialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct SpotlightExportSummary {
+    pub project_id: String,
+    pub directory: String,
+    pub written: i64,
+    pub unchanged: i64,
+    pub removed: i64,
+}
+
+#[derive(Debug, Serialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct StaticSiteExport {
+    pub output_dir: String,
+    /// Pages plus the index page, search index and search script
+    pub file_count: usize,
 }
 
 #[derive(Debug, Serialize, Deserialize, Clone)]
@@ -122,6 +806,39 @@ pub struct BookmarkRelations {
     pub tag_ids: Vec<i64>,
 }
 
+#[derive(Debug, Serialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct LastSession {
+    pub project_id: String,
+    pub collection_id: String,
+    pub doc_slug: String,
+    pub anchor_id: Option<String>,
+    pub updated_at: i64,
+    /// True when the saved document no longer exists and `doc_slug` points at
+    /// the root of its collection instead
+    pub is_fallback: bool,
+}
+
+#[derive(Debug, Serialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct RecentProject {
+    pub project: crate::projects::Project,
+    pub last_used_at: i64,
+    pub activation_count: i64,
+}
+
+#[derive(Debug, Serialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct DeletedItem {
+    pub id: i64,
+    pub project_id: String,
+    /// "bookmarks", "bookmark_folder", "bookmark_tag", or "doc_highlight"
+    pub kind: String,
+    pub label: String,
+    pub item_count: i64,
+    pub deleted_at: i64,
+}
+
 #[derive(Debug, Serialize, Clone)]
 #[serde(rename_all = "camelCase")]
 pub struct DocActivityItem {
@@ -134,13 +851,132 @@ pub struct DocActivityItem {
     pub updated_since_viewed: bool,
 }
 
-#[derive(Debug, Serialize, Deserialize, Clone)]
+/// A cached count shown in the app header.
+#[derive(Debug, Serialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct HeaderCount {
+    pub value: i64,
+    /// The cached value is past its refresh threshold or out of date; a
+    /// recount is running in the background
+    pub stale: bool,
+}
+
+/// Everything the app header shows, from `get_header_summary`.
+#[derive(Debug, Serialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct HeaderSummary {
+    pub active_project_id: String,
+    pub project_name: String,
+    /// None when the active project's database isn't open, or until its
+    /// first count finishes in the background
+    pub document_count: Option<HeaderCount>,
+    /// Documents updated since they were last viewed, in visible collections
+    pub updated_count: Option<HeaderCount>,
+    pub building_project_id: Option<String>,
+    /// Builds waiting behind the running one
+    pub queued_build_count: usize,
+}
+
+/// A document the user navigated away from, for reopening.
+#[derive(Debug, Serialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct ClosedDocument {
+    pub doc_slug: String,
+    pub anchor_id: Option<String>,
+    pub collection_id: String,
+    pub title: String,
+    pub section: String,
+    pub closed_at: i64,
+}
+
+/// How recently the documents carrying a tag changed, by `last_modified`.
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TagTrend {
+    pub document_count: i64,
+    pub updated_last_30_days: i64,
+    pub updated_last_90_days: i64,
+}
+
+/// A tag's documents with their view state, the tags most often found
+/// alongside it, and its trend.
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TagDetail {
+    pub tag: String,
+    /// Ordered by title
+    pub documents: Vec<DocActivityItem>,
+    /// `count` is the number of documents sharing both tags
+    pub related_tags: Vec<Tag>,
+    pub trend: TagTrend,
+}
+
+#[derive(Debug, Serialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct CollectionBadge {
+    pub collection_id: String,
+    /// Documents changed since last viewed, including never-viewed documents
+    pub updated_count: i64,
+    pub never_viewed_count: i64,
+}
+
+/// One section of the home screen; a failed section is returned empty with its error.
+#[derive(Debug, Serialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct HomeSection<T> {
+    pub items: Vec<T>,
+    pub error: Option<String>,
+}
+
+#[derive(Debug, Serialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct ProjectHome {
+    pub recent: HomeSection<DocActivityItem>,
+    pub updated: HomeSection<DocActivityItem>,
+    pub change_feed: HomeSection<ProjectChangeFeedItem>,
+    pub favorite_bookmarks: HomeSection<Bookmark>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
 #[serde(rename_all = "camelCase")]
 pub struct DocNote {
     pub project_id: String,
     pub doc_slug: String,
     pub note: String,
     pub updated_at: i64,
+    /// Bumped by every save; what a save checks to detect a conflict
+    #[serde(default)]
+    pub revision: i64,
+}
+
+/// Note text autosaved while typing, not yet saved as the note.
+#[derive(Debug, Serialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct DocNoteDraft {
+    pub project_id: String,
+    pub doc_slug: String,
+    pub draft: String,
+    pub updated_at: i64,
+}
+
+/// A document's saved note alongside any unsaved draft of it.
+#[derive(Debug, Serialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct DocNoteWithDraft {
+    pub note: Option<DocNote>,
+    /// None when there is no draft or it matches the saved note
+    pub draft: Option<DocNoteDraft>,
+}
+
+/// An earlier text of a doc note, kept when the note was overwritten.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct DocNoteRevision {
+    pub id: i64,
+    pub project_id: String,
+    pub doc_slug: String,
+    pub note: String,
+    pub created_at: i64,
 }
 
 #[derive(Debug, Serialize, Deserialize, Clone)]
@@ -155,6 +991,249 @@ pub struct DocHighlight {
     pub created_at: i64,
 }
 
+/// A highlight or note from a Readwise or Hypothesis export.
+#[derive(Debug, Serialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct ExternalAnnotation {
+    /// "readwise" or "hypothesis"
+    pub source: String,
+    pub title: Option<String>,
+    pub url: Option<String>,
+    /// The highlighted text
+    pub text: Option<String>,
+    pub note: Option<String>,
+    /// The highlight with the text around it, when the export has it
+    pub context_text: Option<String>,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ExternalAnnotationImport {
+    pub matched: usize,
+    pub unmatched: usize,
+    pub highlights_added: usize,
+    /// Notes appended to their document's note
+    pub notes_added: usize,
+    /// Highlights and notes the document already had
+    pub duplicates_skipped: usize,
+    /// Items matching no document, or more than one, for review
+    pub unmatched_entries: Vec<ExternalAnnotation>,
+}
+
+/// Text a share link highlights: the whole selection, or its first and
+/// last words when it was too long to carry whole.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct SharedSelection {
+    pub start: String,
+    pub end: Option<String>,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ShareLink {
+    pub url: String,
+    /// The selection as the link carries it
+    pub selection: Option<SharedSelection>,
+    /// Whether the selection was cut to its first and last words
+    pub selection_shortened: bool,
+}
+
+/// A snippet captured before it was filed under a document. `project_id` is
+/// None for snippets not tied to a project yet.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct InboxItem {
+    pub id: i64,
+    pub project_id: Option<String>,
+    pub content: String,
+    pub created_at: i64,
+    pub resolved_at: Option<i64>,
+    pub resolved_doc_slug: Option<String>,
+}
+
+/// A document an inbox snippet might belong to.
+#[derive(Debug, Serialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct InboxDestination {
+    pub slug: String,
+    pub title: String,
+    pub collection_id: String,
+    pub section: String,
+    /// Heading of the best matching passage, when a passage matched
+    pub heading: Option<String>,
+    pub score: f64,
+}
+
+/// A note or highlight that folders and tags can be assigned to. Notes are
+/// named by document; highlights by id.
+#[derive(Debug, Deserialize, Clone)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum AnnotationRef {
+    #[serde(rename_all = "camelCase")]
+    Note {
+        project_id: String,
+        doc_slug: String,
+    },
+    Highlight {
+        id: i64,
+    },
+}
+
+/// A note or highlight with the folders and tags assigned to it.
+#[derive(Debug, Serialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct AnnotationListItem {
+    /// "note" or "highlight"
+    pub kind: String,
+    pub id: i64,
+    pub doc_slug: String,
+    pub anchor_id: Option<String>,
+    pub text: String,
+    pub context_text: Option<String>,
+    pub updated_at: i64,
+    pub folder_ids: Vec<i64>,
+    pub tag_ids: Vec<i64>,
+}
+
+/// A study card made from a highlight.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct Flashcard {
+    pub front: String,
+    pub back: String,
+    pub doc_slug: String,
+    pub highlight_id: i64,
+}
+
+#[derive(Debug, Serialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct FlashcardExport {
+    pub path: String,
+    pub card_count: usize,
+    /// Whether the provider rewrote any of the cards.
+    pub ai_assisted: bool,
+}
+
+/// What a user state snapshot file holds, returned by its export and import.
+#[derive(Debug, Serialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct UserStateSnapshotSummary {
+    pub path: String,
+    /// The project the snapshot was taken from
+    pub project_id: String,
+    pub exported_at: i64,
+    pub bookmarks: usize,
+    pub notes: usize,
+    pub highlights: usize,
+    pub doc_views: usize,
+    pub inbox_items: usize,
+}
+
+/// Why `get_document` returned no document.
+#[derive(Debug, Serialize, PartialEq)]
+#[serde(
+    tag = "kind",
+    rename_all = "snake_case",
+    rename_all_fields = "camelCase"
+)]
+pub enum DocumentError {
+    /// Nothing matches `slug`. A rebuild may have renamed the document to
+    /// `renamed_to`, or removed it at `removed_at` (Unix seconds); both are set
+    /// when it was renamed and the new slug later removed.
+    NotFound {
+        slug: String,
+        renamed_to: Option<String>,
+        removed_at: Option<i64>,
+    },
+    /// The lookup itself failed
+    Failed { message: String },
+}
+
+impl From<String> for DocumentError {
+    fn from(message: String) -> Self {
+        DocumentError::Failed { message }
+    }
+}
+
+/// Why a write to user_state.db failed.
+#[derive(Debug, Serialize, PartialEq)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum UserStateError {
+    /// Another connection kept the database locked through every retry;
+    /// trying again shortly usually works
+    Busy {
+        message: String,
+    },
+    Failed {
+        message: String,
+    },
+    /// The note changed since the caller read it, e.g. in another window;
+    /// `current` is what is stored now
+    Conflict {
+        message: String,
+        current: Option<DocNote>,
+    },
+}
+
+impl From<String> for UserStateError {
+    fn from(message: String) -> Self {
+        UserStateError::Failed { message }
+    }
+}
+
+impl From<rusqlite::Error> for UserStateError {
+    fn from(e: rusqlite::Error) -> Self {
+        if crate::user_state::is_busy(&e) {
+            UserStateError::Busy {
+                message: "Your saved data is temporarily busy. Try again in a moment.".to_string(),
+            }
+        } else {
+            UserStateError::Failed {
+                message: e.to_string(),
+            }
+        }
+    }
+}
+
+/// A user's annotations for a built collection, kept across rebuilds.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct CollectionOverride {
+    pub project_id: String,
+    pub collection_id: String,
+    pub custom_description: Option<String>,
+    pub custom_icon: Option<String>,
+    pub pinned: bool,
+    pub hidden: bool,
+    pub updated_at: i64,
+}
+
+/// A document whose slug changed between builds.
+#[derive(Debug, Serialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct SlugRename {
+    pub old_slug: String,
+    pub new_slug: String,
+    pub collection_id: String,
+}
+
+#[derive(Debug, Serialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct DocRemoval {
+    pub doc_slug: String,
+    pub removed_at: i64,
+}
+
+/// User state still pointing at documents a rebuild removed.
+#[derive(Debug, Serialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct OrphanedUserState {
+    pub removals: Vec<DocRemoval>,
+    pub bookmarks: Vec<Bookmark>,
+    pub notes: Vec<DocNote>,
+    pub highlights: Vec<DocHighlight>,
+}
+
 #[derive(Debug, Serialize, Clone)]
 #[serde(rename_all = "camelCase")]
 pub struct ProjectChangeFeedItem {
@@ -166,6 +1245,51 @@ pub struct ProjectChangeFeedItem {
     pub changed_files: Vec<String>,
     pub changed_doc_slugs: Vec<String>,
     pub recorded_at: i64,
+    /// Commit subject; empty for entries recorded before it was captured
+    pub message: String,
+}
+
+/// What changed in a project over a date range.
+#[derive(Debug, Serialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct ChangeDigest {
+    /// Unix seconds, inclusive
+    pub since: i64,
+    /// Unix seconds, exclusive
+    pub until: i64,
+    pub collections: Vec<DigestCollection>,
+    /// Whether any collection has an AI-written summary
+    pub ai_summaries: bool,
+    pub markdown: String,
+}
+
+#[derive(Debug, Serialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct DigestCollection {
+    /// Empty for commits that touched no known document
+    pub collection_id: String,
+    pub name: String,
+    pub documents: Vec<DigestDocument>,
+    pub commits: Vec<DigestCommit>,
+    /// Short prose digest, when a provider wrote one
+    pub summary: Option<String>,
+}
+
+#[derive(Debug, Serialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct DigestDocument {
+    pub slug: String,
+    pub title: String,
+    pub last_modified: String,
+}
+
+#[derive(Debug, Serialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct DigestCommit {
+    pub commit_hash: String,
+    pub message: String,
+    pub author: String,
+    pub committed_at: String,
 }
 
 #[derive(Debug, Serialize, Deserialize, Clone)]
@@ -177,6 +1301,24 @@ pub struct Settings {
     pub preferred_provider: Option<String>,
     pub anthropic_model: Option<String>,
     pub gemini_model: Option<String>,
+    /// Requests allowed in flight per provider (default 2)
+    #[serde(default)]
+    pub max_concurrent_requests: Option<u32>,
+    /// Mark the repeated system prompt and context for Anthropic prompt
+    /// caching. Prompts under the model's minimum cacheable size (about 1024
+    /// tokens) aren't cached, and cache writes are billed at a premium.
+    #[serde(default)]
+    pub anthropic_prompt_caching: Option<bool>,
+    /// Query embedding requests allowed per minute (default 120)
+    #[serde(default)]
+    pub embedding_requests_per_minute: Option<u32>,
+    /// Language AI answers are written in, e.g. "German"; the model's
+    /// choice (usually the question's language) when unset
+    #[serde(default)]
+    pub answer_language: Option<String>,
+    /// Hybrid search settings; projects can override them
+    #[serde(default)]
+    pub retrieval: Option<RetrievalConfig>,
 }
 
 impl Default for Settings {
@@ -189,6 +1331,11 @@ impl Default for Settings {
             preferred_provider: None,
             anthropic_model: None,
             gemini_model: None,
+            max_concurrent_requests: None,
+            anthropic_prompt_caching: None,
+            embedding_requests_per_minute: None,
+            answer_language: None,
+            retrieval: None,
         }
     }
 }
@@ -203,6 +1350,13 @@ impl Settings {
     pub fn gemini_model(&self) -> &str {
         self.gemini_model.as_deref().unwrap_or("gemini-2.5-flash")
     }
+
+    pub fn answer_language(&self) -> Option<&str> {
+        self.answer_language
+            .as_deref()
+            .map(str::trim)
+            .filter(|language| !language.is_empty())
+    }
 }
 
 #[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
@@ -0,0 +1,1750 @@
+//! Built-in project builder: turns a folder of Markdown into a project
+//! database without Node. Mirrors scripts/build-handbook.ts (slugs, sections,
+//! navigation, link resolution, chunking) so both pipelines produce databases
+//! the app reads the same way.
+//!
+//! Not yet covered: Shiki syntax highlighting (code blocks are emitted as plain
+//! `<pre><code class="language-*">`) and bare-URL autolinks. Projects that need
+//! those can opt into the Node pipeline.
+
+use crate::models::BuildSummary;
+use pulldown_cmark::{html, CowStr, Event, Options, Parser, Tag, TagEnd};
+use rusqlite::{params, Connection, OpenFlags};
+use std::collections::{HashMap, HashSet};
+use std::path::{Path, PathBuf};
+
+/// Same batch size as the Node pipeline.
+const EMBEDDING_BATCH_SIZE: usize = 32;
+const CHUNK_TARGET_TOKENS: usize = 500;
+const CHUNK_OVERLAP_TOKENS: usize = 50;
+const DEFAULT_SORT_ORDER: i64 = 999;
+
+pub struct NativeBuildRequest<'a> {
+    pub source_path: &'a Path,
+    pub db_path: &'a Path,
+    pub collection_id: &'a str,
+    pub collection_name: &'a str,
+    pub collection_icon: &'a str,
+    /// Reuse chunks and embeddings for files unchanged since the build that
+    /// produced the existing database at `db_path`
+    pub incremental: bool,
+}
+
+const SCHEMA: &str = "
+    CREATE TABLE collections (
+      id TEXT PRIMARY KEY,
+      name TEXT NOT NULL,
+      icon TEXT NOT NULL,
+      description TEXT,
+      sort_order INTEGER NOT NULL DEFAULT 0
+    );
+
+    CREATE TABLE documents (
+      id INTEGER PRIMARY KEY AUTOINCREMENT,
+      collection_id TEXT NOT NULL REFERENCES collections(id),
+      slug TEXT NOT NULL UNIQUE,
+      title TEXT NOT NULL,
+      section TEXT NOT NULL DEFAULT '',
+      sort_order INTEGER NOT NULL DEFAULT 999,
+      parent_slug TEXT NOT NULL DEFAULT '',
+      content_html TEXT NOT NULL,
+      content_raw TEXT NOT NULL,
+      path TEXT NOT NULL,
+      last_modified TEXT NOT NULL DEFAULT '',
+      word_count INTEGER NOT NULL DEFAULT 0
+    );
+
+    CREATE TABLE tags (
+      id INTEGER PRIMARY KEY AUTOINCREMENT,
+      tag TEXT NOT NULL UNIQUE
+    );
+
+    CREATE TABLE document_tags (
+      document_id INTEGER NOT NULL REFERENCES documents(id),
+      tag_id INTEGER NOT NULL REFERENCES tags(id),
+      PRIMARY KEY (document_id, tag_id)
+    );
+
+    CREATE TABLE navigation_tree (
+      id INTEGER PRIMARY KEY AUTOINCREMENT,
+      collection_id TEXT NOT NULL REFERENCES collections(id),
+      slug TEXT NOT NULL,
+      parent_slug TEXT NOT NULL DEFAULT '',
+      title TEXT NOT NULL,
+      sort_order INTEGER NOT NULL DEFAULT 999,
+      level INTEGER NOT NULL DEFAULT 0,
+      has_children INTEGER NOT NULL DEFAULT 0
+    );
+
+    CREATE VIRTUAL TABLE documents_fts USING fts5(
+      title, content, section, collection, tags
+    );
+
+    CREATE TABLE chunks (
+      id INTEGER PRIMARY KEY AUTOINCREMENT,
+      document_id INTEGER NOT NULL REFERENCES documents(id),
+      chunk_index INTEGER NOT NULL,
+      content_text TEXT NOT NULL,
+      heading_context TEXT NOT NULL DEFAULT ''
+    );
+
+    CREATE TABLE chunk_embeddings (
+      chunk_id INTEGER PRIMARY KEY REFERENCES chunks(id),
+      embedding BLOB,
+      scale REAL
+    );
+
+    CREATE VIRTUAL TABLE chunks_fts USING fts5(content_text, heading_context);
+
+    CREATE TABLE document_anchors (
+      document_id INTEGER NOT NULL REFERENCES documents(id),
+      anchor_id TEXT NOT NULL,
+      heading_text TEXT NOT NULL,
+      level INTEGER NOT NULL,
+      position INTEGER NOT NULL,
+      PRIMARY KEY (document_id, anchor_id)
+    );
+
+    CREATE TABLE build_manifest (
+      path TEXT PRIMARY KEY,
+      content_hash TEXT NOT NULL,
+      document_id INTEGER NOT NULL REFERENCES documents(id)
+    );
+
+    CREATE INDEX IF NOT EXISTS idx_documents_collection_id ON documents(collection_id);
+    CREATE INDEX IF NOT EXISTS idx_navigation_tree_collection_id ON navigation_tree(collection_id);
+    CREATE INDEX IF NOT EXISTS idx_chunks_document_id ON chunks(document_id);
+    CREATE INDEX IF NOT EXISTS idx_document_anchors_document ON document_anchors(document_id, position);
+    CREATE INDEX IF NOT EXISTS idx_navigation_tree_sort ON navigation_tree(collection_id, parent_slug, sort_order);
+";
+
+// -- Paths and metadata --
+
+fn find_markdown_files(dir: &Path) -> Result<Vec<PathBuf>, String> {
+    let mut files = Vec::new();
+    let mut entries: Vec<PathBuf> = std::fs::read_dir(dir)
+        .map_err(|e| format!("Failed to read {:?}: {}", dir, e))?
+        .filter_map(|entry| entry.ok().map(|e| e.path()))
+        .collect();
+    entries.sort();
+    for path in entries {
+        if path.is_dir() {
+            files.extend(find_markdown_files(&path)?);
+        } else if path.extension().is_some_and(|ext| ext == "md") {
+            files.push(path);
+        }
+    }
+    Ok(files)
+}
+
+fn strip_numeric_prefix(segment: &str) -> &str {
+    let digits = segment.chars().take_while(|c| c.is_ascii_digit()).count();
+    if digits > 0 && segment[digits..].starts_with('-') {
+        &segment[digits + 1..]
+    } else {
+        segment
+    }
+}
+
+fn extract_sort_order(segment: &str) -> i64 {
+    let digits: String = segment.chars().take_while(|c| c.is_ascii_digit()).collect();
+    if !digits.is_empty() && segment[digits.len()..].starts_with('-') {
+        digits.parse().unwrap_or(DEFAULT_SORT_ORDER)
+    } else {
+        DEFAULT_SORT_ORDER
+    }
+}
+
+fn to_slug(segment: &str, strip_prefix: bool) -> String {
+    let value = if strip_prefix {
+        strip_numeric_prefix(segment)
+    } else {
+        segment
+    };
+    let mut slug = String::new();
+    let mut pending_hyphen = false;
+    for c in value.to_lowercase().chars() {
+        if c.is_ascii_lowercase() || c.is_ascii_digit() {
+            if pending_hyphen && !slug.is_empty() {
+                slug.push('-');
+            }
+            pending_hyphen = false;
+            slug.push(c);
+        } else {
+            pending_hyphen = true;
+        }
+    }
+    slug
+}
+
+/// Upper-case the first letter of each word, as `/\b\w/` does in the Node script.
+fn title_case(value: &str) -> String {
+    let mut out = String::with_capacity(value.len());
+    let mut previous_is_word = false;
+    for c in value.chars() {
+        let is_word = c.is_ascii_alphanumeric() || c == '_';
+        if is_word && !previous_is_word {
+            out.push(c.to_ascii_uppercase());
+        } else {
+            out.push(c);
+        }
+        previous_is_word = is_word;
+    }
+    out
+}
+
+fn clean_section_name(dir_name: &str) -> String {
+    title_case(&strip_numeric_prefix(dir_name).replace('-', " "))
+}
+
+#[derive(Debug, Clone)]
+struct DocumentMetadata {
+    slug: String,
+    title: String,
+    section: String,
+    sort_order: i64,
+    parent_slug: String,
+    /// Source-relative path with `/` separators
+    relative_path: String,
+}
+
+fn extract_metadata(relative_path: &str) -> DocumentMetadata {
+    let mut segments: Vec<&str> = relative_path.split('/').collect();
+    let file_base = segments.pop().unwrap_or_default();
+    let file_name = file_base.strip_suffix(".md").unwrap_or(file_base);
+    let is_index = file_name.eq_ignore_ascii_case("readme") || file_name.ends_with("-index");
+    let dir_slug_parts: Vec<String> = segments.iter().map(|s| to_slug(s, true)).collect();
+
+    let slug = if is_index {
+        // Root-level README.md becomes the collection index
+        if dir_slug_parts.is_empty() {
+            "index".to_string()
+        } else {
+            dir_slug_parts.join("/")
+        }
+    } else {
+        let mut parts = dir_slug_parts.clone();
+        parts.push(to_slug(file_name, true));
+        parts.join("/")
+    };
+
+    let sort_order = match segments.last() {
+        Some(dir) if is_index => extract_sort_order(dir),
+        _ => extract_sort_order(file_base),
+    };
+
+    let section = segments
+        .first()
+        .map(|s| clean_section_name(s))
+        .unwrap_or_default();
+
+    let parent_slug = if is_index {
+        if dir_slug_parts.len() > 1 {
+            dir_slug_parts[..dir_slug_parts.len() - 1].join("/")
+        } else {
+            String::new()
+        }
+    } else {
+        dir_slug_parts.join("/")
+    };
+
+    let title_source = match segments.last() {
+        Some(dir) if is_index => strip_numeric_prefix(dir),
+        _ => strip_numeric_prefix(file_name),
+    };
+
+    DocumentMetadata {
+        slug,
+        title: title_case(&title_source.replace('-', " ")),
+        section,
+        sort_order,
+        parent_slug,
+        relative_path: relative_path.to_string(),
+    }
+}
+
+// -- Frontmatter --
+
+#[derive(Debug, PartialEq)]
+struct ParsedDocument {
+    content: String,
+    title: String,
+    tags: Vec<String>,
+}
+
+fn unquote(value: &str) -> String {
+    let value = value.trim();
+    if value.len() >= 2 {
+        if let Some(inner) = value.strip_prefix('"').and_then(|v| v.strip_suffix('"')) {
+            return inner.replace("\\\"", "\"");
+        }
+        if let Some(inner) = value.strip_prefix('\'').and_then(|v| v.strip_suffix('\'')) {
+            return inner.replace("''", "'");
+        }
+    }
+    value.to_string()
+}
+
+/// Split a leading `---` YAML block from the body. Only `title` and `tags`
+/// are read; tags may be an inline `[a, b]` list or a block `- a` list.
+fn split_frontmatter(file_content: &str) -> (HashMap<String, String>, Vec<String>, &str) {
+    let mut fields = HashMap::new();
+    let mut tags = Vec::new();
+
+    let Some(rest) = file_content
+        .strip_prefix("---\n")
+        .or_else(|| file_content.strip_prefix("---\r\n"))
+    else {
+        return (fields, tags, file_content);
+    };
+
+    let mut offset = 0;
+    let mut body_start = None;
+    let mut current_key: Option<String> = None;
+    for line in rest.split_inclusive('\n') {
+        let trimmed = line.trim_end();
+        offset += line.len();
+        if trimmed == "---" {
+            body_start = Some(offset);
+            break;
+        }
+        if let Some(item) = trimmed.trim_start().strip_prefix("- ") {
+            if current_key.as_deref() == Some("tags") {
+                tags.push(unquote(item));
+            }
+            continue;
+        }
+        if line.starts_with(' ') || line.starts_with('\t') {
+            continue;
+        }
+        if let Some((key, value)) = trimmed.split_once(':') {
+            let key = key.trim().to_string();
+            let value = value.trim();
+            if key == "tags" {
+                if let Some(list) = value.strip_prefix('[').and_then(|v| v.strip_suffix(']')) {
+                    tags.extend(list.split(',').map(unquote).filter(|tag| !tag.is_empty()));
+                }
+            } else if !value.is_empty() {
+                fields.insert(key.clone(), unquote(value));
+            }
+            current_key = Some(key);
+        }
+    }
+
+    match body_start {
+        Some(start) => (fields, tags, &rest[start..]),
+        // Unterminated block: treat the whole file as content
+        None => (HashMap::new(), Vec::new(), file_content),
+    }
+}
+
+fn extract_h1(content: &str) -> Option<String> {
+    content.lines().find_map(|line| {
+        let rest = line.strip_prefix('#')?;
+        if !rest.starts_with(|c: char| c.is_whitespace()) {
+            return None;
+        }
+        let title = rest.trim();
+        (!title.is_empty()).then(|| title.to_string())
+    })
+}
+
+fn parse_frontmatter(file_content: &str, fallback_title: &str) -> ParsedDocument {
+    let (fields, tags, content) = split_frontmatter(file_content);
+    // gray-matter drops the newline that follows the closing delimiter
+    let content = content.strip_prefix('\n').unwrap_or(content).to_string();
+    let title = fields
+        .get("title")
+        .cloned()
+        .or_else(|| extract_h1(&content))
+        .unwrap_or_else(|| fallback_title.to_string());
+    ParsedDocument {
+        content,
+        title,
+        tags,
+    }
+}
+
+// -- Markdown rendering --
+
+struct LinkContext<'a> {
+    collection_id: &'a str,
+    current_file: &'a str,
+    /// Relative path -> document slug (without the collection prefix)
+    slug_map: &'a HashMap<String, String>,
+    lower_slug_map: &'a HashMap<String, String>,
+    ordered_paths: &'a [String],
+}
+
+fn parent_dir(path: &str) -> &str {
+    path.rsplit_once('/').map(|(dir, _)| dir).unwrap_or("")
+}
+
+fn base_name(path: &str) -> &str {
+    path.rsplit_once('/').map(|(_, base)| base).unwrap_or(path)
+}
+
+fn normalise_path(dir: &str, relative: &str) -> String {
+    let mut parts: Vec<&str> = Vec::new();
+    for part in dir.split('/').chain(relative.split('/')) {
+        match part {
+            "" | "." => {}
+            ".." => {
+                if parts.last().is_some_and(|p| *p != "..") {
+                    parts.pop();
+                } else {
+                    parts.push("..");
+                }
+            }
+            other => parts.push(other),
+        }
+    }
+    parts.join("/")
+}
+
+impl LinkContext<'_> {
+    /// README links point at sections, so they resolve to the first concrete
+    /// page in the same directory, or the first page in the collection.
+    fn readme_fallback(&self, resolved: &str) -> Option<String> {
+        let dir_lower = parent_dir(resolved).to_lowercase();
+        let mut candidates: Vec<&String> = self
+            .ordered_paths
+            .iter()
+            .filter(|p| !base_name(p).eq_ignore_ascii_case("readme.md"))
+            .collect();
+        candidates.sort();
+        candidates
+            .iter()
+            .find(|p| parent_dir(p).to_lowercase() == dir_lower)
+            .or_else(|| candidates.first())
+            .and_then(|p| self.slug_map.get(*p).cloned())
+    }
+
+    /// Rewrite a relative Markdown link to an app route. None means the link
+    /// target is missing and the link should become plain text.
+    fn resolve(&self, url: &str) -> Option<Option<String>> {
+        let is_external = ["http:", "https:", "mailto:", "tel:"]
+            .iter()
+            .any(|scheme| url.starts_with(scheme));
+        if is_external || url.starts_with('#') {
+            return Some(None);
+        }
+
+        let split_at = url.find(['#', '?']).unwrap_or(url.len());
+        let (pathname, suffix) = url.split_at(split_at);
+        let resolved = normalise_path(parent_dir(self.current_file), pathname);
+
+        let slug = if base_name(&resolved).eq_ignore_ascii_case("readme.md") {
+            self.readme_fallback(&resolved)
+        } else {
+            self.slug_map
+                .get(&resolved)
+                .or_else(|| self.lower_slug_map.get(&resolved.to_lowercase()))
+                .cloned()
+        };
+
+        match slug {
+            Some(slug) => Some(Some(format!(
+                "/docs/{}/{}{}",
+                self.collection_id, slug, suffix
+            ))),
+            None => {
+                eprintln!(
+                    "Warning: broken link in {}: \"{}\" (resolved to \"{}\")",
+                    self.current_file, url, resolved
+                );
+                None
+            }
+        }
+    }
+}
+
+#[derive(Debug, PartialEq)]
+struct Anchor {
+    anchor_id: String,
+    heading_text: String,
+    level: i64,
+}
+
+/// Heading ids as rehype-slug (github-slugger) generates them, deduplicated
+/// within a document by suffixing -1, -2, ...
+struct Slugger {
+    seen: HashSet<String>,
+}
+
+impl Slugger {
+    fn slug(&mut self, text: &str) -> String {
+        let base: String = text
+            .to_lowercase()
+            .chars()
+            .filter_map(|c| match c {
+                ' ' => Some('-'),
+                c if c.is_alphanumeric() || c == '-' || c == '_' => Some(c),
+                _ => None,
+            })
+            .collect();
+        let mut slug = base.clone();
+        let mut n = 0;
+        while self.seen.contains(&slug) {
+            n += 1;
+            slug = format!("{}-{}", base, n);
+        }
+        self.seen.insert(slug.clone());
+        slug
+    }
+}
+
+fn render_markdown(markdown: &str, links: &LinkContext<'_>) -> (String, Vec<Anchor>) {
+    let options = Options::ENABLE_TABLES
+        | Options::ENABLE_FOOTNOTES
+        | Options::ENABLE_STRIKETHROUGH
+        | Options::ENABLE_TASKLISTS;
+    let mut events: Vec<Event> = Vec::new();
+    let mut anchors = Vec::new();
+    let mut slugger = Slugger {
+        seen: HashSet::new(),
+    };
+    let mut heading: Option<(usize, String)> = None;
+    let mut dropped_link = false;
+
+    for event in Parser::new_ext(markdown, options) {
+        match event {
+            Event::Start(Tag::Link {
+                link_type,
+                dest_url,
+                title,
+                id,
+            }) => match links.resolve(&dest_url) {
+                Some(rewritten) => {
+                    let dest_url = rewritten.map(CowStr::from).unwrap_or(dest_url);
+                    events.push(Event::Start(Tag::Link {
+                        link_type,
+                        dest_url,
+                        title,
+                        id,
+                    }));
+                }
+                None => dropped_link = true,
+            },
+            Event::End(TagEnd::Link) if dropped_link => dropped_link = false,
+            Event::Start(Tag::Heading { .. }) => {
+                heading = Some((events.len(), String::new()));
+                events.push(event);
+            }
+            Event::Text(ref text) | Event::Code(ref text) => {
+                if let Some((_, heading_text)) = heading.as_mut() {
+                    heading_text.push_str(text);
+                }
+                events.push(event);
+            }
+            Event::End(TagEnd::Heading(level)) => {
+                if let Some((start, text)) = heading.take() {
+                    let anchor_id = slugger.slug(&text);
+                    if !anchor_id.is_empty() {
+                        if let Event::Start(Tag::Heading { id, .. }) = &mut events[start] {
+                            *id = Some(CowStr::from(anchor_id.clone()));
+                        }
+                        anchors.push(Anchor {
+                            anchor_id,
+                            heading_text: text.split_whitespace().collect::<Vec<_>>().join(" "),
+                            level: level as i64,
+                        });
+                    }
+                }
+                events.push(event);
+            }
+            other => events.push(other),
+        }
+    }
+
+    let mut content_html = String::new();
+    html::push_html(&mut content_html, events.into_iter());
+    (content_html, anchors)
+}
+
+// -- Chunking (port of scripts/lib/chunk-content.ts) --
+
+#[derive(Debug, Clone, PartialEq)]
+struct ContentChunk {
+    content_text: String,
+    heading_context: String,
+}
+
+fn estimate_tokens(text: &str) -> usize {
+    let words = text.split_whitespace().count();
+    (words as f64 / 0.75).ceil() as usize
+}
+
+fn is_sentence_end(c: char) -> bool {
+    matches!(c, '.' | '!' | '?')
+}
+
+/// Sentence split matching `/[^.!?]+(?:[.!?]+["'\s]|[.!?]+$)/g`, with any
+/// unmatched remainder appended as a final piece.
+fn split_sentences(text: &str) -> Vec<String> {
+    let chars: Vec<char> = text.chars().collect();
+    let mut sentences: Vec<String> = Vec::new();
+    let mut pos = 0;
+    while pos < chars.len() {
+        let Some(start) = (pos..chars.len()).find(|&i| !is_sentence_end(chars[i])) else {
+            break;
+        };
+        let mut end = start;
+        while end < chars.len() && !is_sentence_end(chars[end]) {
+            end += 1;
+        }
+        if end == chars.len() {
+            break;
+        }
+        let mut term_end = end;
+        while term_end < chars.len() && is_sentence_end(chars[term_end]) {
+            term_end += 1;
+        }
+        if term_end == chars.len() {
+            sentences.push(chars[start..term_end].iter().collect());
+            pos = term_end;
+        } else if matches!(chars[term_end], '"' | '\'') || chars[term_end].is_whitespace() {
+            sentences.push(chars[start..=term_end].iter().collect());
+            pos = term_end + 1;
+        } else {
+            pos = term_end;
+        }
+    }
+
+    if sentences.is_empty() {
+        return vec![text.to_string()];
+    }
+    let joined_len: usize = sentences.iter().map(|s| s.chars().count()).sum();
+    if joined_len < chars.len() {
+        sentences.push(chars[joined_len..].iter().collect());
+    }
+    sentences.retain(|s| !s.is_empty());
+    sentences
+}
+
+fn heading_line_text(line: &str) -> Option<&str> {
+    let hashes = line.chars().take_while(|c| *c == '#').count();
+    if !(1..=6).contains(&hashes) {
+        return None;
+    }
+    let rest = &line[hashes..];
+    rest.starts_with(|c: char| c.is_whitespace())
+        .then(|| rest.trim())
+}
+
+struct Section {
+    heading: String,
+    content: String,
+}
+
+fn split_by_headings(markdown: &str) -> Vec<Section> {
+    let mut sections = Vec::new();
+    let mut current_heading = String::new();
+    let mut current_lines: Vec<&str> = Vec::new();
+
+    for line in markdown.split('\n') {
+        if let Some(heading) = heading_line_text(line) {
+            if !current_lines.is_empty() || sections.is_empty() {
+                sections.push(Section {
+                    heading: current_heading.clone(),
+                    content: current_lines.join("\n"),
+                });
+            }
+            current_heading = heading.to_string();
+            current_lines.clear();
+        } else {
+            current_lines.push(line);
+        }
+    }
+    sections.push(Section {
+        heading: current_heading,
+        content: current_lines.join("\n"),
+    });
+
+    sections.retain(|s| !s.content.trim().is_empty());
+    sections
+}
+
+/// Split on blank lines (`/\n\s*\n/`).
+fn split_paragraphs(content: &str) -> Vec<String> {
+    let bytes = content.as_bytes();
+    let mut paragraphs = Vec::new();
+    let mut start = 0;
+    let mut i = 0;
+    while i < bytes.len() {
+        if bytes[i] == b'\n' {
+            let mut j = i + 1;
+            let mut last_newline = None;
+            while j < bytes.len() && bytes[j].is_ascii_whitespace() {
+                if bytes[j] == b'\n' {
+                    last_newline = Some(j);
+                }
+                j += 1;
+            }
+            if let Some(split_end) = last_newline {
+                paragraphs.push(content[start..i].to_string());
+                start = split_end + 1;
+                i = split_end + 1;
+                continue;
+            }
+        }
+        i += 1;
+    }
+    paragraphs.push(content[start..].to_string());
+    paragraphs.retain(|p| !p.trim().is_empty());
+    paragraphs
+}
+
+fn extract_tail(text: &str, overlap_tokens: usize) -> String {
+    if overlap_tokens == 0 {
+        return String::new();
+    }
+    let words: Vec<&str> = text.split_whitespace().collect();
+    let word_count = (overlap_tokens as f64 * 0.75).ceil() as usize;
+    if words.len() <= word_count {
+        return text.to_string();
+    }
+    format!("{} ", words[words.len() - word_count..].join(" "))
+}
+
+fn push_chunk(chunks: &mut Vec<ContentChunk>, text: &str, heading: &str) {
+    chunks.push(ContentChunk {
+        content_text: text.trim().to_string(),
+        heading_context: heading.to_string(),
+    });
+}
+
+fn build_chunks(
+    blocks: &[String],
+    heading: &str,
+    chunks: &mut Vec<ContentChunk>,
+    previous_tail: String,
+) -> String {
+    let mut buffer = previous_tail;
+    let mut tail = String::new();
+
+    for block in blocks {
+        if estimate_tokens(block) > CHUNK_TARGET_TOKENS {
+            if !buffer.trim().is_empty() {
+                push_chunk(chunks, &buffer, heading);
+                tail = extract_tail(&buffer, CHUNK_OVERLAP_TOKENS);
+                buffer.clear();
+            }
+
+            let mut sentence_buffer = tail.clone();
+            for sentence in split_sentences(block) {
+                if estimate_tokens(&format!("{}{}", sentence_buffer, sentence))
+                    > CHUNK_TARGET_TOKENS
+                    && !sentence_buffer.trim().is_empty()
+                {
+                    push_chunk(chunks, &sentence_buffer, heading);
+                    tail = extract_tail(&sentence_buffer, CHUNK_OVERLAP_TOKENS);
+                    sentence_buffer = format!("{}{}", tail, sentence);
+                } else {
+                    sentence_buffer.push_str(&sentence);
+                }
+            }
+            buffer = sentence_buffer;
+            continue;
+        }
+
+        if estimate_tokens(&format!("{}\n\n{}", buffer, block)) > CHUNK_TARGET_TOKENS
+            && !buffer.trim().is_empty()
+        {
+            push_chunk(chunks, &buffer, heading);
+            tail = extract_tail(&buffer, CHUNK_OVERLAP_TOKENS);
+            buffer = format!("{}{}", tail, block);
+        } else if buffer.is_empty() {
+            buffer = block.clone();
+        } else {
+            buffer = format!("{}\n\n{}", buffer, block);
+        }
+    }
+
+    buffer
+}
+
+/// Whether `markdown` gives at least one chunk; empty and heading-only
+/// documents don't.
+pub(crate) fn has_chunkable_text(markdown: &str) -> bool {
+    !chunk_content(markdown).is_empty()
+}
+
+fn chunk_content(markdown: &str) -> Vec<ContentChunk> {
+    if markdown.trim().is_empty() {
+        return vec![];
+    }
+    let sections = split_by_headings(markdown);
+    let Some(last_section) = sections.last() else {
+        return vec![];
+    };
+    let last_heading = last_section.heading.clone();
+
+    let mut chunks = Vec::new();
+    let mut carry_over = String::new();
+    // The heading that contributed the most content to the carry-over buffer
+    let mut carry_over_heading = String::new();
+    let mut carry_over_heading_size = 0;
+
+    for section in &sections {
+        let heading = &section.heading;
+        let section_tokens = estimate_tokens(&section.content);
+
+        if section_tokens <= CHUNK_TARGET_TOKENS {
+            let combined = if carry_over.is_empty() {
+                section.content.clone()
+            } else {
+                format!("{}\n\n{}", carry_over, section.content)
+            };
+            if estimate_tokens(&combined) <= CHUNK_TARGET_TOKENS {
+                if section_tokens >= carry_over_heading_size {
+                    carry_over_heading = heading.clone();
+                    carry_over_heading_size = section_tokens;
+                }
+                carry_over = combined;
+                continue;
+            }
+            if !carry_over.trim().is_empty() {
+                let chunk_heading = if carry_over_heading.is_empty() {
+                    heading
+                } else {
+                    &carry_over_heading
+                };
+                push_chunk(&mut chunks, &carry_over, chunk_heading);
+                carry_over = format!(
+                    "{}{}",
+                    extract_tail(&carry_over, CHUNK_OVERLAP_TOKENS),
+                    section.content
+                );
+            } else {
+                carry_over = section.content.clone();
+            }
+            carry_over_heading = heading.clone();
+            carry_over_heading_size = section_tokens;
+            continue;
+        }
+
+        // Section exceeds the target, so split it by paragraphs
+        let paragraphs = split_paragraphs(&section.content);
+        let previous_tail = if carry_over.trim().is_empty() {
+            String::new()
+        } else {
+            let chunk_heading = if carry_over_heading.is_empty() {
+                heading
+            } else {
+                &carry_over_heading
+            };
+            push_chunk(&mut chunks, &carry_over, chunk_heading);
+            extract_tail(&carry_over, CHUNK_OVERLAP_TOKENS)
+        };
+
+        carry_over = build_chunks(&paragraphs, heading, &mut chunks, previous_tail);
+        carry_over_heading = heading.clone();
+        carry_over_heading_size = estimate_tokens(&carry_over);
+    }
+
+    if !carry_over.trim().is_empty() {
+        let chunk_heading = if carry_over_heading.is_empty() {
+            &last_heading
+        } else {
+            &carry_over_heading
+        };
+        push_chunk(&mut chunks, &carry_over, chunk_heading);
+    }
+
+    chunks
+}
+
+// -- Navigation (port of scripts/lib/build-navigation.ts) --
+
+struct DocInfo {
+    slug: String,
+    title: String,
+    section: String,
+    sort_order: i64,
+    parent_slug: String,
+    level: i64,
+}
+
+#[derive(Debug, Clone)]
+struct NavigationNode {
+    slug: String,
+    parent_slug: String,
+    title: String,
+    /// None until a document or child provides a sort order
+    sort_order: Option<i64>,
+    level: i64,
+    has_children: bool,
+}
+
+fn build_navigation(documents: &[DocInfo]) -> Vec<NavigationNode> {
+    // Insertion-ordered map so ties sort the same way as the Node script
+    let mut nodes: Vec<NavigationNode> = Vec::new();
+
+    // Pass 1: section nodes for top-level directories
+    for doc in documents.iter().filter(|d| !d.section.is_empty()) {
+        let section_slug = to_slug(&doc.section, false);
+        if !nodes.iter().any(|n| n.slug == section_slug) {
+            nodes.push(NavigationNode {
+                slug: section_slug,
+                parent_slug: String::new(),
+                title: doc.section.clone(),
+                sort_order: None,
+                level: 0,
+                has_children: false,
+            });
+        }
+    }
+
+    // Pass 2: intermediate parents for deeply nested documents
+    for doc in documents.iter().filter(|d| d.level >= 2) {
+        let parts: Vec<&str> = doc.parent_slug.split('/').collect();
+        for i in 1..parts.len() {
+            let intermediate = parts[..=i].join("/");
+            if !nodes.iter().any(|n| n.slug == intermediate) {
+                nodes.push(NavigationNode {
+                    slug: intermediate,
+                    parent_slug: parts[..i].join("/"),
+                    title: title_case(&parts[i].replace('-', " ")),
+                    sort_order: Some(DEFAULT_SORT_ORDER),
+                    level: i as i64,
+                    has_children: false,
+                });
+            }
+        }
+    }
+
+    // Pass 3: document nodes, merged into matching section/intermediate nodes.
+    // Root-level documents with no section are collection index pages.
+    for doc in documents {
+        if doc.section.is_empty() && doc.parent_slug.is_empty() && doc.level == 0 {
+            continue;
+        }
+        match nodes.iter_mut().find(|n| n.slug == doc.slug) {
+            Some(existing) => {
+                existing.title = doc.title.clone();
+                existing.sort_order = Some(
+                    existing
+                        .sort_order
+                        .map_or(doc.sort_order, |s| s.min(doc.sort_order)),
+                );
+                existing.parent_slug = doc.parent_slug.clone();
+                existing.level = doc.level;
+            }
+            None => nodes.push(NavigationNode {
+                slug: doc.slug.clone(),
+                parent_slug: doc.parent_slug.clone(),
+                title: doc.title.clone(),
+                sort_order: Some(doc.sort_order),
+                level: doc.level,
+                has_children: false,
+            }),
+        }
+    }
+
+    let index = nodes
+        .iter()
+        .enumerate()
+        .map(|(i, n)| (n.slug.clone(), i))
+        .collect::<HashMap<_, _>>();
+
+    // Pass 4: sections without an index document take their first child's order
+    for i in 0..nodes.len() {
+        if nodes[i].parent_slug.is_empty() {
+            continue;
+        }
+        if let Some(&parent) = index.get(&nodes[i].parent_slug) {
+            if nodes[parent].sort_order.is_none() {
+                nodes[parent].sort_order = nodes[i].sort_order;
+            }
+        }
+    }
+
+    // Pass 5: mark parents that have children
+    for i in 0..nodes.len() {
+        if nodes[i].parent_slug.is_empty() {
+            continue;
+        }
+        if let Some(&parent) = index.get(&nodes[i].parent_slug) {
+            nodes[parent].has_children = true;
+        }
+    }
+
+    let sort_key = |n: &NavigationNode| n.sort_order.unwrap_or(DEFAULT_SORT_ORDER);
+    nodes.sort_by(|a, b| {
+        a.parent_slug
+            .cmp(&b.parent_slug)
+            .then_with(|| sort_key(a).cmp(&sort_key(b)))
+    });
+    nodes
+}
+
+// -- Incremental rebuilds --
+
+/// 64-bit FNV-1a of the file contents. Only used to detect edits between
+/// builds, so collision resistance beyond that isn't needed.
+fn content_hash(content: &str) -> String {
+    let mut hash: u64 = 0xcbf2_9ce4_8422_2325;
+    for byte in content.as_bytes() {
+        hash ^= u64::from(*byte);
+        hash = hash.wrapping_mul(0x0100_0000_01b3);
+    }
+    format!("{:016x}", hash)
+}
+
+struct ReusedChunk {
+    chunk: ContentChunk,
+    /// Embedding blob and, for int8 embeddings, its scale
+    embedding: Option<(Vec<u8>, Option<f64>)>,
+}
+
+/// The previous build of this project, read to carry over chunks and
+/// embeddings for files whose content hash hasn't changed. Databases built by
+/// the Node pipeline have no manifest, so everything is rebuilt.
+struct PreviousBuild {
+    conn: Connection,
+    manifest: HashMap<String, (String, i64)>,
+    has_scale: bool,
+}
+
+impl PreviousBuild {
+    fn open(db_path: &Path) -> Option<Self> {
+        let conn = Connection::open_with_flags(db_path, OpenFlags::SQLITE_OPEN_READ_ONLY).ok()?;
+        let manifest = {
+            let mut stmt = conn
+                .prepare("SELECT path, content_hash, document_id FROM build_manifest")
+                .ok()?;
+            let rows = stmt
+                .query_map([], |row| Ok((row.get(0)?, (row.get(1)?, row.get(2)?))))
+                .ok()?;
+            rows.collect::<Result<HashMap<String, (String, i64)>, _>>()
+                .ok()?
+        };
+        let has_scale = crate::ai::embeddings_have_scale(&conn);
+        Some(Self {
+            conn,
+            manifest,
+            has_scale,
+        })
+    }
+
+    fn reusable_chunks(&self, path: &str, hash: &str) -> Option<Vec<ReusedChunk>> {
+        let (previous_hash, document_id) = self.manifest.get(path)?;
+        if previous_hash != hash {
+            return None;
+        }
+        let scale_column = if self.has_scale { "ce.scale" } else { "NULL" };
+        let mut stmt = self
+            .conn
+            .prepare(&format!(
+                "SELECT c.content_text, c.heading_context, ce.embedding, {} FROM chunks c \
+                 LEFT JOIN chunk_embeddings ce ON ce.chunk_id = c.id \
+                 WHERE c.document_id = ?1 ORDER BY c.chunk_index ASC",
+                scale_column
+            ))
+            .ok()?;
+        let rows = stmt
+            .query_map([document_id], |row| {
+                let embedding: Option<Vec<u8>> = row.get(2)?;
+                let scale: Option<f64> = row.get(3)?;
+                Ok(ReusedChunk {
+                    chunk: ContentChunk {
+                        content_text: row.get(0)?,
+                        heading_context: row.get(1)?,
+                    },
+                    embedding: embedding.map(|blob| (blob, scale)),
+                })
+            })
+            .ok()?;
+        rows.collect::<Result<Vec<_>, _>>().ok()
+    }
+}
+
+// -- Database --
+
+fn modified_iso(path: &Path) -> String {
+    std::fs::metadata(path)
+        .and_then(|m| m.modified())
+        .map(|t| {
+            chrono::DateTime::<chrono::Utc>::from(t)
+                .to_rfc3339_opts(chrono::SecondsFormat::Millis, true)
+        })
+        .unwrap_or_default()
+}
+
+fn relative_path(source: &Path, file: &Path) -> String {
+    file.strip_prefix(source)
+        .unwrap_or(file)
+        .components()
+        .map(|c| c.as_os_str().to_string_lossy().to_string())
+        .collect::<Vec<_>>()
+        .join("/")
+}
+
+/// Every Markdown file under a source folder with its metadata, plus the
+/// lookups link resolution needs.
+struct SourceIndex {
+    files: Vec<(PathBuf, DocumentMetadata)>,
+    slug_map: HashMap<String, String>,
+    lower_slug_map: HashMap<String, String>,
+    ordered_paths: Vec<String>,
+}
+
+impl SourceIndex {
+    fn scan(source: &Path) -> Result<Self, String> {
+        let files: Vec<(PathBuf, DocumentMetadata)> = find_markdown_files(source)?
+            .into_iter()
+            .map(|file| {
+                let meta = extract_metadata(&relative_path(source, &file));
+                (file, meta)
+            })
+            .collect();
+        let slug_map: HashMap<String, String> = files
+            .iter()
+            .map(|(_, m)| (m.relative_path.clone(), m.slug.clone()))
+            .collect();
+        let ordered_paths: Vec<String> =
+            files.iter().map(|(_, m)| m.relative_path.clone()).collect();
+        let mut lower_slug_map = HashMap::new();
+        for path in &ordered_paths {
+            lower_slug_map
+                .entry(path.to_lowercase())
+                .or_insert_with(|| slug_map[path].clone());
+        }
+        Ok(Self {
+            files,
+            slug_map,
+            lower_slug_map,
+            ordered_paths,
+        })
+    }
+
+    fn links<'a>(&'a self, collection_id: &'a str, current_file: &'a str) -> LinkContext<'a> {
+        LinkContext {
+            collection_id,
+            current_file,
+            slug_map: &self.slug_map,
+            lower_slug_map: &self.lower_slug_map,
+            ordered_paths: &self.ordered_paths,
+        }
+    }
+}
+
+fn insert_collection(
+    conn: &Connection,
+    request: &NativeBuildRequest<'_>,
+    previous: Option<&PreviousBuild>,
+) -> Result<BuildSummary, String> {
+    let index = SourceIndex::scan(request.source_path)?;
+    let metadata = &index.files;
+    let collection_id = request.collection_id;
+
+    // When README.md and *-index.md share a slug, prefer the index file
+    let mut slug_to_file: HashMap<String, usize> = HashMap::new();
+    for (i, (_, meta)) in metadata.iter().enumerate() {
+        match slug_to_file.get(&meta.slug) {
+            Some(&existing) => {
+                let existing_is_readme = base_name(&metadata[existing].1.relative_path)
+                    .eq_ignore_ascii_case("readme.md");
+                let current_is_readme =
+                    base_name(&meta.relative_path).eq_ignore_ascii_case("readme.md");
+                if existing_is_readme && !current_is_readme {
+                    slug_to_file.insert(meta.slug.clone(), i);
+                } else {
+                    eprintln!("Skipping {} (duplicate slug)", meta.relative_path);
+                }
+            }
+            None => {
+                slug_to_file.insert(meta.slug.clone(), i);
+            }
+        }
+    }
+
+    conn.execute(
+        "INSERT INTO collections (id, name, icon, description, sort_order) VALUES (?1, ?2, ?3, NULL, 0)",
+        params![collection_id, request.collection_name, request.collection_icon],
+    )
+    .map_err(|e| e.to_string())?;
+
+    let mut insert_doc = conn
+        .prepare(
+            "INSERT INTO documents (collection_id, slug, title, section, sort_order, parent_slug, content_html, content_raw, path, last_modified, word_count) \
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11)",
+        )
+        .map_err(|e| e.to_string())?;
+    let mut insert_fts = conn
+        .prepare(
+            "INSERT INTO documents_fts (rowid, title, content, section, collection, tags) VALUES (?1, ?2, ?3, ?4, ?5, ?6)",
+        )
+        .map_err(|e| e.to_string())?;
+    let mut insert_tag = conn
+        .prepare("INSERT OR IGNORE INTO tags (tag) VALUES (?1)")
+        .map_err(|e| e.to_string())?;
+    let mut insert_doc_tag = conn
+        .prepare(
+            "INSERT OR IGNORE INTO document_tags (document_id, tag_id) SELECT ?1, id FROM tags WHERE tag = ?2",
+        )
+        .map_err(|e| e.to_string())?;
+    let mut insert_anchor = conn
+        .prepare(
+            "INSERT OR IGNORE INTO document_anchors (document_id, anchor_id, heading_text, level, position) VALUES (?1, ?2, ?3, ?4, ?5)",
+        )
+        .map_err(|e| e.to_string())?;
+    let mut insert_chunk = conn
+        .prepare(
+            "INSERT INTO chunks (document_id, chunk_index, content_text, heading_context) VALUES (?1, ?2, ?3, ?4)",
+        )
+        .map_err(|e| e.to_string())?;
+    let mut insert_chunk_fts = conn
+        .prepare(
+            "INSERT INTO chunks_fts (rowid, content_text, heading_context) VALUES (?1, ?2, ?3)",
+        )
+        .map_err(|e| e.to_string())?;
+
+    let mut insert_embedding = conn
+        .prepare("INSERT INTO chunk_embeddings (chunk_id, embedding, scale) VALUES (?1, ?2, ?3)")
+        .map_err(|e| e.to_string())?;
+    let mut insert_manifest = conn
+        .prepare("INSERT INTO build_manifest (path, content_hash, document_id) VALUES (?1, ?2, ?3)")
+        .map_err(|e| e.to_string())?;
+
+    let mut summary = BuildSummary::default();
+    let mut doc_infos = Vec::new();
+    for (i, (file, meta)) in metadata.iter().enumerate() {
+        if slug_to_file.get(&meta.slug) != Some(&i) {
+            continue;
+        }
+        let file_content = std::fs::read_to_string(file)
+            .map_err(|e| format!("Failed to read {}: {}", meta.relative_path, e))?;
+        let parsed = parse_frontmatter(&file_content, &meta.title);
+        let (content_html, anchors) = render_markdown(
+            &parsed.content,
+            &index.links(collection_id, &meta.relative_path),
+        );
+        let full_slug = format!("{}/{}", collection_id, meta.slug);
+        let parent_slug = if meta.parent_slug.is_empty() {
+            String::new()
+        } else {
+            format!("{}/{}", collection_id, meta.parent_slug)
+        };
+
+        insert_doc
+            .execute(params![
+                collection_id,
+                full_slug,
+                parsed.title,
+                meta.section,
+                meta.sort_order,
+                parent_slug,
+                content_html,
+                parsed.content,
+                meta.relative_path,
+                modified_iso(file),
+                crate::snippet::word_count(&content_html),
+            ])
+            .map_err(|e| e.to_string())?;
+        let document_id = conn.last_insert_rowid();
+
+        insert_fts
+            .execute(params![
+                document_id,
+                parsed.title,
+                parsed.content,
+                meta.section,
+                collection_id,
+                parsed.tags.join(" "),
+            ])
+            .map_err(|e| e.to_string())?;
+        for tag in &parsed.tags {
+            insert_tag.execute([tag]).map_err(|e| e.to_string())?;
+            insert_doc_tag
+                .execute(params![document_id, tag])
+                .map_err(|e| e.to_string())?;
+        }
+        for (position, anchor) in anchors.iter().enumerate() {
+            insert_anchor
+                .execute(params![
+                    document_id,
+                    anchor.anchor_id,
+                    anchor.heading_text,
+                    anchor.level,
+                    position as i64,
+                ])
+                .map_err(|e| e.to_string())?;
+        }
+
+        // HTML is always re-rendered because link targets depend on other
+        // files; chunks and embeddings depend only on this file's content.
+        let hash = content_hash(&file_content);
+        let chunks = match previous.and_then(|p| p.reusable_chunks(&meta.relative_path, &hash)) {
+            Some(reused) => {
+                summary.documents_reused += 1;
+                reused
+            }
+            None => {
+                summary.documents_rebuilt += 1;
+                chunk_content(&parsed.content)
+                    .into_iter()
+                    .map(|chunk| ReusedChunk {
+                        chunk,
+                        embedding: None,
+                    })
+                    .collect()
+            }
+        };
+        for (chunk_index, ReusedChunk { chunk, embedding }) in chunks.iter().enumerate() {
+            insert_chunk
+                .execute(params![
+                    document_id,
+                    chunk_index as i64,
+                    chunk.content_text,
+                    chunk.heading_context,
+                ])
+                .map_err(|e| e.to_string())?;
+            let chunk_id = conn.last_insert_rowid();
+            insert_chunk_fts
+                .execute(params![chunk_id, chunk.content_text, chunk.heading_context])
+                .map_err(|e| e.to_string())?;
+            if let Some((embedding, scale)) = embedding {
+                insert_embedding
+                    .execute(params![chunk_id, embedding, scale])
+                    .map_err(|e| e.to_string())?;
+            }
+        }
+        insert_manifest
+            .execute(params![meta.relative_path, hash, document_id])
+            .map_err(|e| e.to_string())?;
+
+        doc_infos.push(DocInfo {
+            slug: meta.slug.clone(),
+            title: parsed.title,
+            section: meta.section.clone(),
+            sort_order: meta.sort_order,
+            parent_slug: meta.parent_slug.clone(),
+            level: meta.slug.split('/').count() as i64 - 1,
+        });
+    }
+
+    let mut insert_nav = conn
+        .prepare(
+            "INSERT INTO navigation_tree (collection_id, slug, parent_slug, title, sort_order, level, has_children) \
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7)",
+        )
+        .map_err(|e| e.to_string())?;
+    for node in build_navigation(&doc_infos) {
+        insert_nav
+            .execute(params![
+                collection_id,
+                node.slug,
+                node.parent_slug,
+                node.title,
+                node.sort_order.unwrap_or(DEFAULT_SORT_ORDER),
+                node.level,
+                node.has_children as i64,
+            ])
+            .map_err(|e| e.to_string())?;
+    }
+    Ok(summary)
+}
+
+pub struct RenderedDocument {
+    /// Full slug, including the collection prefix
+    pub slug: String,
+    pub title: String,
+    pub content_html: String,
+    pub last_modified: String,
+}
+
+/// Render a single source file exactly as a build would, without touching the
+/// database. Links are resolved against the files currently in `source_path`.
+pub fn render_document(
+    source_path: &Path,
+    collection_id: &str,
+    file_path: &Path,
+) -> Result<RenderedDocument, String> {
+    let index = SourceIndex::scan(source_path)?;
+    let relative = relative_path(source_path, file_path);
+    let (file, meta) = index
+        .files
+        .iter()
+        .find(|(_, m)| m.relative_path == relative)
+        .ok_or_else(|| format!("{} is not a Markdown file in the project source", relative))?;
+    let file_content =
+        std::fs::read_to_string(file).map_err(|e| format!("Failed to read {}: {}", relative, e))?;
+    let parsed = parse_frontmatter(&file_content, &meta.title);
+    let (content_html, _) = render_markdown(
+        &parsed.content,
+        &index.links(collection_id, &meta.relative_path),
+    );
+    Ok(RenderedDocument {
+        slug: format!("{}/{}", collection_id, meta.slug),
+        title: parsed.title,
+        content_html,
+        last_modified: modified_iso(file),
+    })
+}
+
+fn staging_path(db_path: &Path) -> PathBuf {
+    let mut name = db_path.as_os_str().to_owned();
+    name.push(".building");
+    PathBuf::from(name)
+}
+
+/// Build a single-collection project database at `request.db_path`. The
+/// database is written next to the target and renamed into place on success,
+/// so an existing database stays intact if the build fails.
+pub fn build_project(request: &NativeBuildRequest<'_>) -> Result<BuildSummary, String> {
+    if !request.source_path.is_dir() {
+        return Err(format!(
+            "Source folder not found: {}",
+            request.source_path.display()
+        ));
+    }
+
+    let staging = staging_path(request.db_path);
+    let _ = std::fs::remove_file(&staging);
+    let previous = if request.incremental && request.db_path.exists() {
+        PreviousBuild::open(request.db_path)
+    } else {
+        None
+    };
+    let result = (|| {
+        let mut conn = Connection::open(&staging).map_err(|e| e.to_string())?;
+        conn.execute_batch(SCHEMA).map_err(|e| e.to_string())?;
+        let tx = conn.transaction().map_err(|e| e.to_string())?;
+        let summary = insert_collection(&tx, request, previous.as_ref())?;
+        tx.commit().map_err(|e| e.to_string())?;
+        Ok::<_, String>(summary)
+    })();
+    drop(previous);
+    let summary = match result {
+        Ok(summary) => summary,
+        Err(e) => {
+            let _ = std::fs::remove_file(&staging);
+            return Err(format!("Build failed: {}", e));
+        }
+    };
+
+    replace_database(&staging, request.db_path)?;
+    Ok(summary)
+}
+
+/// Move a finished staging database over `db_path`.
+fn replace_database(staging: &Path, db_path: &Path) -> Result<(), String> {
+    // Leftovers from a WAL-mode database built by the Node pipeline
+    for suffix in ["-wal", "-shm"] {
+        let mut sidecar = db_path.as_os_str().to_owned();
+        sidecar.push(suffix);
+        let _ = std::fs::remove_file(PathBuf::from(sidecar));
+    }
+    std::fs::rename(staging, db_path).map_err(|e| e.to_string())
+}
+
+fn read_unembedded_chunks(db_path: &Path) -> Result<Vec<(i64, String)>, String> {
+    let conn = Connection::open(db_path).map_err(|e| e.to_string())?;
+    let mut stmt = conn
+        .prepare(
+            "SELECT c.id, c.content_text FROM chunks c \
+             LEFT JOIN chunk_embeddings ce ON ce.chunk_id = c.id \
+             WHERE ce.chunk_id IS NULL ORDER BY c.id ASC",
+        )
+        .map_err(|e| e.to_string())?;
+    let rows = stmt
+        .query_map([], |row| Ok((row.get(0)?, row.get(1)?)))
+        .map_err(|e| e.to_string())?;
+    rows.collect::<Result<Vec<_>, _>>()
+        .map_err(|e| e.to_string())
+}
+
+fn write_embeddings(db_path: &Path, embeddings: &[(i64, Vec<f32>)]) -> Result<(), String> {
+    let mut conn = Connection::open(db_path).map_err(|e| e.to_string())?;
+    let tx = conn.transaction().map_err(|e| e.to_string())?;
+    {
+        let mut stmt = tx
+            .prepare("INSERT INTO chunk_embeddings (chunk_id, embedding) VALUES (?1, ?2)")
+            .map_err(|e| e.to_string())?;
+        for (chunk_id, embedding) in embeddings {
+            let blob: Vec<u8> = embedding.iter().flat_map(|v| v.to_le_bytes()).collect();
+            stmt.execute(params![chunk_id, blob])
+                .map_err(|e| e.to_string())?;
+        }
+    }
+    tx.commit().map_err(|e| e.to_string())
+}
+
+/// Embed chunks that don't have an embedding yet (new or changed files after
+/// an incremental build) with OpenAI, as the Node pipeline does when
+/// OPENAI_API_KEY is set. Nothing is written unless every batch succeeds.
+pub async fn embed_chunks(
+    client: &reqwest::Client,
+    openai_api_key: &str,
+    db_path: &Path,
+) -> Result<usize, String> {
+    let chunks = read_unembedded_chunks(db_path)?;
+    let mut embeddings = Vec::with_capacity(chunks.len());
+    for batch in chunks.chunks(EMBEDDING_BATCH_SIZE) {
+        let inputs: Vec<String> = batch.iter().map(|(_, text)| text.clone()).collect();
+        let vectors = crate::ai::generate_embeddings_batch(client, openai_api_key, &inputs).await?;
+        if vectors.len() != batch.len() {
+            return Err(format!(
+                "Embedding batch size mismatch: expected {}, got {}",
+                batch.len(),
+                vectors.len()
+            ));
+        }
+        embeddings.extend(batch.iter().map(|(id, _)| *id).zip(vectors));
+    }
+    write_embeddings(db_path, &embeddings)?;
+    Ok(embeddings.len())
+}
+
+/// Copy the database at `db_path` to a sibling file with `suffix` appended,
+/// including anything still in its WAL.
+fn writable_copy(db_path: &Path, suffix: &str) -> Result<PathBuf, String> {
+    let mut staging = db_path.as_os_str().to_owned();
+    staging.push(suffix);
+    let staging = PathBuf::from(staging);
+    let _ = std::fs::remove_file(&staging);
+    let source = Connection::open_with_flags(db_path, OpenFlags::SQLITE_OPEN_READ_ONLY)
+        .map_err(|e| e.to_string())?;
+    source
+        .execute("VACUUM INTO ?1", [staging.to_string_lossy()])
+        .map_err(|e| e.to_string())?;
+    Ok(staging)
+}
+
+/// Apply `rewrite` to a copy of the database at `db_path`, VACUUM it, and
+/// rename it over the original on success. The original is untouched on error.
+fn rewrite_database<T>(
+    db_path: &Path,
+    rewrite: impl FnOnce(&Connection) -> Result<T, String>,
+) -> Result<T, String> {
+    let staging = writable_copy(db_path, ".rewriting")?;
+    let result = (|| {
+        let conn = Connection::open(&staging).map_err(|e| e.to_string())?;
+        let value = rewrite(&conn)?;
+        conn.execute_batch("VACUUM").map_err(|e| e.to_string())?;
+        Ok::<_, String>(value)
+    })();
+
+    match result {
+        Ok(value) => {
+            replace_database(&staging, db_path)?;
+            Ok(value)
+        }
+        Err(e) => {
+            let _ = std::fs::remove_file(&staging);
+            Err(e)
+        }
+    }
+}
+
+pub struct EmbeddingRun {
+    pub embedded: usize,
+    pub remaining: usize,
+    pub cancelled: bool,
+}
+
+/// Run blocking database work on tokio's blocking pool rather than on a
+/// runtime worker thread.
+async fn run_blocking<T: Send + 'static>(
+    work: impl FnOnce() -> Result<T, String> + Send + 'static,
+) -> Result<T, String> {
+    tokio::task::spawn_blocking(work)
+        .await
+        .map_err(|e| e.to_string())?
+}
+
+/// Embed every chunk without an embedding, sending up to `concurrency` batches
+/// at a time. Work happens on a copy of the database that is committed after
+/// each round of batches and renamed over `db_path` when the run ends, so a
+/// cancelled or failed run, including one whose write failed, keeps what it
+/// embedded and the next run only sees the chunks still missing.
+/// `on_progress` receives (embedded, total) and returning `false` from
+/// `keep_going` stops after the current round. Database work runs on the
+/// blocking pool.
+pub async fn embed_missing_chunks(
+    client: &reqwest::Client,
+    openai_api_key: &str,
+    db_path: &Path,
+    concurrency: usize,
+    mut keep_going: impl FnMut() -> bool,
+    mut on_progress: impl FnMut(usize, usize),
+) -> Result<EmbeddingRun, String> {
+    let source = db_path.to_path_buf();
+    let (staging, chunks) = run_blocking(move || {
+        let staging = writable_copy(&source, ".embedding")?;
+        let chunks = read_unembedded_chunks(&staging)?;
+        Ok((staging, chunks))
+    })
+    .await?;
+    let total = chunks.len();
+    let mut embedded = 0;
+    let mut cancelled = false;
+    let mut failure = None;
+
+    for round in chunks.chunks(EMBEDDING_BATCH_SIZE * concurrency.max(1)) {
+        if !keep_going() {
+            cancelled = true;
+            break;
+        }
+        let requests = round.chunks(EMBEDDING_BATCH_SIZE).map(|batch| async move {
+            let inputs: Vec<String> = batch.iter().map(|(_, text)| text.clone()).collect();
+            let vectors =
+                crate::ai::generate_embeddings_batch(client, openai_api_key, &inputs).await?;
+            if vectors.len() != batch.len() {
+                return Err(format!(
+                    "Embedding batch size mismatch: expected {}, got {}",
+                    batch.len(),
+                    vectors.len()
+                ));
+            }
+            Ok(batch
+                .iter()
+                .map(|(id, _)| *id)
+                .zip(vectors)
+                .collect::<Vec<_>>())
+        });
+        let mut round_embeddings = Vec::with_capacity(round.len());
+        for result in futures_util::future::join_all(requests).await {
+            match result {
+                Ok(embeddings) => round_embeddings.extend(embeddings),
+                Err(e) => failure = Some(e),
+            }
+        }
+        // Successful batches are kept even when another in the round failed.
+        // A failed write rolls back only this round; earlier ones stay.
+        let round_staging = staging.clone();
+        let written = round_embeddings.len();
+        match run_blocking(move || write_embeddings(&round_staging, &round_embeddings)).await {
+            Ok(()) => {
+                embedded += written;
+                on_progress(embedded, total);
+            }
+            Err(e) => failure = Some(e),
+        }
+        if failure.is_some() {
+            break;
+        }
+    }
+
+    let target = db_path.to_path_buf();
+    run_blocking(move || replace_database(&staging, &target)).await?;
+    match failure {
+        Some(e) => Err(e),
+        None => Ok(EmbeddingRun {
+            embedded,
+            remaining: total - embedded,
+            cancelled,
+        }),
+    }
+}
+
+/// Delete embeddings for chunks shorter than `min_chunk_chars` or in one of
+/// `excluded_collections`, reclaiming the space. Returns the number of
+/// embeddings removed. Embedding on the next build fills the gaps again.
+pub fn prune_embeddings(
+    db_path: &Path,
+    min_chunk_chars: usize,
+    excluded_collections: &[String],
+) -> Result<i64, String> {
+    rewrite_database(db_path, |conn| {
+        let mut removed = conn
+            .execute(
+                "DELETE FROM chunk_embeddings WHERE chunk_id IN \
+                 (SELECT id FROM chunks WHERE length(content_text) < ?1)",
+                [min_chunk_chars as i64],
+            )
+            .map_err(|e| e.to_string())?;
+        for collection_id in excluded_collections {
+            removed += conn
+                .execute(
+                    "DELETE FROM chunk_embeddings WHERE chunk_id IN \
+                     (SELECT c.id FROM chunks c JOIN documents d ON d.id = c.document_id \
+                      WHERE d.collection_id = ?1)",
+                    [collection_id],
+                )
+                .map_err(|e| e.to_string())?;
+        }
+        Ok(removed as i64)
+    })
+    .map_err(|e| format!("Pruning embeddings failed: {}", e))
+}
+
+/// Store the database's embeddings as int8 with a per-vector scale, about a
+/// quarter of the float32 size. Returns the number of embeddings converted.
+/// Embeddings added by later builds are float32 until converted again.
+pub fn quantize_embeddings(db_path: &Path) -> Result<i64, String> {
+    rewrite_database(db_path, |conn| {
+        crate::ai::quantize_embeddings(conn).map(|converted| converted as i64)
+    })
+    .map_err(|e| format!("Converting embeddings failed: {}", e))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{chunk_content, extract_metadata, parse_frontmatter, split_sentences, Slugger};
+    use std::collections::HashSet;
+
+    #[test]
+    fn metadata_matches_node_slugs_and_sections() {
+        let doc = extract_metadata("02-operations/10-on-call.md");
+        assert_eq!(doc.slug, "operations/on-call");
+        assert_eq!(doc.section, "Operations");
+        assert_eq!(doc.sort_order, 10);
+        assert_eq!(doc.parent_slug, "operations");
+        assert_eq!(doc.title, "On Call");
+
+        let index = extract_metadata("02-operations/README.md");
+        assert_eq!(index.slug, "operations");
+        assert_eq!(index.sort_order, 2);
+        assert_eq!(index.parent_slug, "");
+
+        assert_eq!(extract_metadata("README.md").slug, "index");
+    }
+
+    #[test]
+    fn frontmatter_title_and_tags_are_parsed() {
+        let inline = parse_frontmatter(
+            "---\ntitle: \"Runbook\"\ntags: [ops, pager]\n---\n\nBody\n",
+            "x",
+        );
+        assert_eq!(inline.title, "Runbook");
+        assert_eq!(inline.tags, vec!["ops", "pager"]);
+        assert_eq!(inline.content, "Body\n");
+
+        let block = parse_frontmatter("---\ntags:\n  - alpha\n  - beta\n---\n# Heading\n", "x");
+        assert_eq!(block.title, "Heading");
+        assert_eq!(block.tags, vec!["alpha", "beta"]);
+
+        let none = parse_frontmatter("No headings here", "Fallback");
+        assert_eq!(none.title, "Fallback");
+        assert!(none.tags.is_empty());
+    }
+
+    #[test]
+    fn heading_ids_are_deduplicated() {
+        let mut slugger = Slugger {
+            seen: HashSet::new(),
+        };
+        assert_eq!(slugger.slug("Getting Started!"), "getting-started");
+        assert_eq!(slugger.slug("Getting Started!"), "getting-started-1");
+        assert_eq!(slugger.slug("API & CLI"), "api--cli");
+    }
+
+    #[test]
+    fn long_sections_are_split_into_overlapping_chunks() {
+        assert_eq!(
+            split_sentences("One. Two! Three"),
+            vec!["One. ", "Two! ", "Three"]
+        );
+
+        let paragraph = "word ".repeat(300);
+        let markdown = format!("# Intro\n\n{}\n\n{}", paragraph, paragraph);
+        let chunks = chunk_content(&markdown);
+        assert_eq!(chunks.len(), 2);
+        assert!(chunks.iter().all(|c| c.heading_context == "Intro"));
+        // The second chunk starts with the tail of the first
+        assert_eq!(chunks[1].content_text.split_whitespace().count(), 300 + 38);
+    }
+}
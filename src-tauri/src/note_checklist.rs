@@ -0,0 +1,118 @@
+//! Task-list checkboxes in markdown doc notes. Markers are found with the same
+//! parser the native builder uses, so only `[ ]` and `[x]` at the start of a
+//! list item count; brackets in code blocks, code spans or running text are
+//! left alone.
+
+use pulldown_cmark::{Event, Options, Parser};
+use std::ops::Range;
+
+/// Byte ranges of the checkbox markers in `note`, in document order. Each range
+/// covers the brackets, e.g. `[x]`.
+pub fn checkbox_markers(note: &str) -> Vec<Range<usize>> {
+    Parser::new_ext(note, Options::ENABLE_TASKLISTS)
+        .into_offset_iter()
+        .filter(|(event, _)| matches!(event, Event::TaskListMarker(_)))
+        .map(|(_, range)| range)
+        .collect()
+}
+
+/// `note` with the checkbox at `index` (counting from 0) flipped between
+/// `[ ]` and `[x]`. Nothing else in the note changes.
+pub fn toggle_checkbox(note: &str, index: usize) -> Result<String, String> {
+    let markers = checkbox_markers(note);
+    let range = markers.get(index).ok_or_else(|| {
+        format!(
+            "Checkbox {} not found; the note has {}",
+            index,
+            markers.len()
+        )
+    })?;
+    let checked = note[range.clone()].contains(['x', 'X']);
+    let mut toggled = String::with_capacity(note.len());
+    toggled.push_str(&note[..range.start]);
+    toggled.push_str(if checked { "[ ]" } else { "[x]" });
+    toggled.push_str(&note[range.end..]);
+    Ok(toggled)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{checkbox_markers, toggle_checkbox};
+
+    #[test]
+    fn only_list_item_markers_are_checkboxes() {
+        let note = "\
+- [ ] write intro
+- [x] review
+* [X] star bullet
+1. [ ] numbered
+
+Not a list: [ ] stays put, nor `[ ]` in code.
+
+```
+- [ ] inside a fence
+```
+
+    - [ ] indented code
+- plain item with [ ] later
+";
+        let markers: Vec<&str> = checkbox_markers(note)
+            .into_iter()
+            .map(|range| &note[range])
+            .collect();
+        assert_eq!(markers, ["[ ]", "[x]", "[X]", "[ ]"]);
+
+        let toggled = toggle_checkbox(note, 1).unwrap();
+        assert!(toggled.starts_with("- [ ] write intro\n- [ ] review\n"));
+        assert_eq!(toggled.len(), note.len());
+        let toggled = toggle_checkbox(note, 2).unwrap();
+        assert!(toggled.contains("* [ ] star bullet"));
+        assert!(toggle_checkbox(note, 4).is_err());
+    }
+
+    /// Notes built from random mixes of task items, plain items, prose and
+    /// fenced code. Toggling any checkbox changes that marker only, keeps the
+    /// markers where they were, and toggling twice restores the note (with `[X]`
+    /// normalised to `[x]`).
+    #[test]
+    fn toggling_generated_notes_changes_one_marker() {
+        const LINES: [&str; 9] = [
+            "- [ ] open task",
+            "- [x] done task",
+            "  * [X] nested done",
+            "1. [ ] numbered task",
+            "- plain item [ ] with brackets",
+            "prose with [x] inline",
+            "```\n- [ ] fenced\n```",
+            "`[ ]` in a span",
+            "",
+        ];
+        let mut seed: u64 = 0x2545_f491_4f6c_dd1d;
+        let mut next = move || {
+            seed ^= seed << 13;
+            seed ^= seed >> 7;
+            seed ^= seed << 17;
+            seed
+        };
+        for _ in 0..500 {
+            let len = (next() % 12) as usize;
+            let note = (0..len)
+                .map(|_| LINES[(next() % LINES.len() as u64) as usize])
+                .collect::<Vec<_>>()
+                .join("\n");
+            let markers = checkbox_markers(&note);
+            for (index, range) in markers.iter().enumerate() {
+                let toggled = toggle_checkbox(&note, index).unwrap();
+                assert_eq!(checkbox_markers(&toggled), markers, "{:?}", note);
+                assert_eq!(toggled[..range.start], note[..range.start]);
+                assert_eq!(toggled[range.end..], note[range.end..]);
+                assert_ne!(toggled[range.clone()], note[range.clone()].to_lowercase());
+
+                let restored = toggle_checkbox(&toggled, index).unwrap();
+                assert_eq!(restored[range.clone()], note[range.clone()].to_lowercase());
+                assert_eq!(restored[range.end..], note[range.end..]);
+            }
+            assert!(toggle_checkbox(&note, markers.len()).is_err());
+        }
+    }
+}
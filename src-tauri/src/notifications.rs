@@ -0,0 +1,131 @@
+use crate::models::AppPreferences;
+use chrono::Timelike;
+use std::time::Duration;
+use tauri::{AppHandle, Emitter};
+
+/// Builds quicker than this finish while the user is still watching, so they
+/// never produce a notification.
+const MIN_NOTIFY_BUILD_SECS: u64 = 5;
+
+/// Whether `hour` (0-23, local time) falls inside the configured quiet hours.
+/// A start later than the end wraps past midnight, e.g. 22 → 7.
+fn in_quiet_hours(preferences: &AppPreferences, hour: u32) -> bool {
+    match (preferences.quiet_hours_start, preferences.quiet_hours_end) {
+        (Some(start), Some(end)) if start != end => {
+            let (start, end) = (u32::from(start), u32::from(end));
+            if start < end {
+                hour >= start && hour < end
+            } else {
+                hour >= start || hour < end
+            }
+        }
+        _ => false,
+    }
+}
+
+fn format_duration(duration: Duration) -> String {
+    let secs = duration.as_secs();
+    if secs < 60 {
+        format!("{}s", secs)
+    } else {
+        format!("{}m {}s", secs / 60, secs % 60)
+    }
+}
+
+/// Notify that a project build finished, subject to the notification
+/// preference, quiet hours, and the minimum build duration.
+pub fn notify_build_finished(
+    app: &AppHandle,
+    project_id: &str,
+    project_name: &str,
+    duration: Duration,
+    error: Option<&str>,
+) {
+    if duration.as_secs() < MIN_NOTIFY_BUILD_SECS {
+        return;
+    }
+    let preferences = crate::settings::load_preferences(app).unwrap_or_default();
+    if !preferences.notifications_enabled
+        || in_quiet_hours(&preferences, chrono::Local::now().hour())
+    {
+        return;
+    }
+
+    let (title, body) = match error {
+        None => (
+            format!("{} is ready", project_name),
+            format!("Rebuilt in {}", format_duration(duration)),
+        ),
+        Some(error) => (
+            format!("{} failed to build", project_name),
+            format!(
+                "After {}: {}",
+                format_duration(duration),
+                error.lines().next().unwrap_or(error)
+            ),
+        ),
+    };
+
+    let mut notification = notify_rust::Notification::new();
+    notification.summary(&title).body(&body).auto_icon();
+    // Freedesktop servers only report a click on the notification when it
+    // offers the "default" action
+    #[cfg(all(unix, not(target_os = "macos")))]
+    notification.action("default", "Open");
+    // Set up as tauri-plugin-notification does, so notifications keep the app's
+    // name and icon
+    #[cfg(windows)]
+    if !tauri::is_dev() {
+        notification.app_id(&app.config().identifier);
+    }
+    #[cfg(target_os = "macos")]
+    {
+        let _ = notify_rust::set_application(if tauri::is_dev() {
+            "com.apple.Terminal"
+        } else {
+            &app.config().identifier
+        });
+    }
+
+    // Waiting for the response blocks until the notification is clicked or
+    // closed, so it gets a thread of its own
+    let app = app.clone();
+    let project_id = project_id.to_string();
+    std::thread::spawn(move || {
+        let shown = notification.show().and_then(|handle| {
+            handle.wait_for_response(|response: &notify_rust::NotificationResponse| {
+                if is_click(response) {
+                    notification_opened(&app, &project_id);
+                }
+            })
+        });
+        if let Err(e) = shown {
+            eprintln!("Warning: failed to show notification: {}", e);
+        }
+    });
+}
+
+/// Whether the user activated the notification itself, rather than closing
+/// it or letting it expire.
+fn is_click(response: &notify_rust::NotificationResponse) -> bool {
+    matches!(response, notify_rust::NotificationResponse::Default)
+}
+
+/// Bring the app forward and tell the frontend which project a clicked
+/// notification was about.
+fn notification_opened(app: &AppHandle, project_id: &str) {
+    crate::quick_search::focus_main_window(app);
+    let _ = app.emit(
+        "notification-opened",
+        serde_json::json!({ "projectId": project_id }),
+    );
+}
+
+/// Quiet hours as saved: each, when set, a local hour 0-23.
+pub fn validate_quiet_hours(preferences: &AppPreferences) -> Result<(), String> {
+    let hours = [preferences.quiet_hours_start, preferences.quiet_hours_end];
+    if hours.into_iter().flatten().any(|hour| hour >= 24) {
+        return Err("Quiet hours must be between 0 and 23".to_string());
+    }
+    Ok(())
+}
@@ -1,8 +1,8 @@
 use rusqlite::Connection;
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
-use tauri::AppHandle;
-use tauri_plugin_store::StoreExt;
+use std::sync::Arc;
+use tauri::{AppHandle, Manager};
 
 /// A single collection within a project (maps to the existing Collection concept)
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -28,8 +28,28 @@ pub struct Project {
     pub db_path: Option<String>,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub last_built: Option<String>,
+    /// Imported from a prebuilt database rather than built from a source folder
+    #[serde(default)]
+    pub built_from_db: bool,
     #[serde(default)]
     pub collections: Vec<ProjectCollection>,
+    /// Collection id or name fragment used to find glossary entries (defaults to "glossary")
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub glossary_collection: Option<String>,
+    /// Confidential: AI features may only use local providers (Ollama), and
+    /// builds don't send chunks out for embedding
+    #[serde(default)]
+    pub local_only: bool,
+    /// Collections left out of AI retrieval, e.g. an archive of outdated docs.
+    /// They are still searchable.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub excluded_collections: Vec<String>,
+    /// Hybrid search settings for this project, replacing those in `Settings`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub retrieval: Option<crate::models::RetrievalConfig>,
+    /// Runtime availability, e.g. "unavailable: <reason>" (None when the database is open)
+    #[serde(skip_deserializing, skip_serializing_if = "Option::is_none")]
+    pub status: Option<String>,
 }
 
 /// Persisted project registry (saved to projects.json via Tauri store)
@@ -51,19 +71,46 @@ impl Default for ProjectRegistry {
                 source_path: None,
                 db_path: None,
                 last_built: None,
+                built_from_db: false,
                 collections: vec![],
+                glossary_collection: None,
+                local_only: false,
+                excluded_collections: vec![],
+                retrieval: None,
+                status: None,
             }],
             active_project_id: "engineering-handbook".to_string(),
         }
     }
 }
 
+/// Derive a project id from its display name, e.g. "Team Docs" -> "team-docs".
+pub fn project_id_from_name(name: &str) -> String {
+    name.to_lowercase()
+        .chars()
+        .map(|c| if c.is_alphanumeric() { c } else { '-' })
+        .collect::<String>()
+        .trim_matches('-')
+        .to_string()
+}
+
 /// Runtime state managing multiple project database connections
 pub struct ProjectManager {
     /// Open database connections keyed by project ID
     pub connections: HashMap<String, Connection>,
     /// Project registry (persisted to projects.json)
     pub registry: ProjectRegistry,
+    /// Reasons a registered project's database could not be opened, keyed by project ID
+    pub unavailable: HashMap<String, String>,
+    /// Documents re-rendered by `reload_document` since the database was opened,
+    /// keyed by project ID then slug. Dropped whenever the connection closes.
+    pub document_overlays: HashMap<String, HashMap<String, crate::models::Document>>,
+    /// Words per document keyed by project ID then slug, worked out on first
+    /// use. Dropped whenever the connection is opened or closed.
+    pub word_counts: HashMap<String, Arc<HashMap<String, i64>>>,
+    /// Title and heading prefix indexes keyed by project ID, built on first
+    /// use. Dropped whenever the connection is opened or closed.
+    pub suggestion_indexes: HashMap<String, crate::suggestions::SuggestionIndex>,
 }
 
 impl ProjectManager {
@@ -71,9 +118,29 @@ impl ProjectManager {
         Self {
             connections: HashMap::new(),
             registry,
+            unavailable: HashMap::new(),
+            document_overlays: HashMap::new(),
+            word_counts: HashMap::new(),
+            suggestion_indexes: HashMap::new(),
         }
     }
 
+    /// Registry projects annotated with their runtime availability
+    pub fn projects_with_status(&self) -> Vec<Project> {
+        self.registry
+            .projects
+            .iter()
+            .map(|p| {
+                let mut project = p.clone();
+                project.status = self
+                    .unavailable
+                    .get(&p.id)
+                    .map(|reason| format!("unavailable: {}", reason));
+                project
+            })
+            .collect()
+    }
+
     /// Get a reference to the active project's database connection
     pub fn active_connection(&self) -> Result<&Connection, String> {
         self.connections
@@ -86,6 +153,53 @@ impl ProjectManager {
             })
     }
 
+    /// Whether a project is marked confidential, so its documents stay out of
+    /// cloud AI providers and clients outside the app.
+    pub fn is_local_only(&self, project_id: &str) -> bool {
+        self.registry
+            .projects
+            .iter()
+            .any(|p| p.id == project_id && p.local_only)
+    }
+
+    /// A project's connection for serving clients outside the app, such as
+    /// the MCP server. Local-only projects are refused.
+    pub fn external_connection(&self, project_id: &str) -> Result<&Connection, String> {
+        if self.is_local_only(project_id) {
+            return Err(format!(
+                "Project '{}' is local-only and is not shared with external clients",
+                project_id
+            ));
+        }
+        self.connection(project_id)
+    }
+
+    /// Collections of a project that AI retrieval skips.
+    pub fn excluded_collections(&self, project_id: &str) -> Vec<String> {
+        self.registry
+            .projects
+            .iter()
+            .find(|p| p.id == project_id)
+            .map(|p| p.excluded_collections.clone())
+            .unwrap_or_default()
+    }
+
+    /// Hybrid search settings for a project: its own override, else those in
+    /// `settings`, else the defaults.
+    pub fn retrieval_config(
+        &self,
+        project_id: &str,
+        settings: &crate::models::Settings,
+    ) -> crate::models::RetrievalConfig {
+        self.registry
+            .projects
+            .iter()
+            .find(|p| p.id == project_id)
+            .and_then(|p| p.retrieval.clone())
+            .or_else(|| settings.retrieval.clone())
+            .unwrap_or_default()
+    }
+
     /// Get a reference to a specific project's connection.
     pub fn connection(&self, project_id: &str) -> Result<&Connection, String> {
         self.connections
@@ -99,24 +213,80 @@ impl ProjectManager {
         project_id: &str,
         db_path: &std::path::Path,
     ) -> Result<(), String> {
-        let conn = Connection::open_with_flags(
-            db_path,
-            rusqlite::OpenFlags::SQLITE_OPEN_READ_ONLY | rusqlite::OpenFlags::SQLITE_OPEN_NO_MUTEX,
-        )
-        .map_err(|e| {
-            format!(
-                "Failed to open database for project '{}': {}",
-                project_id, e
-            )
-        })?;
+        let conn = open_project_database(project_id, db_path)?;
+        if let Some(path) = conn.path() {
+            crate::ai::invalidate_chunk_anchors(path);
+        }
+        self.connections.insert(project_id.to_string(), conn);
+        self.unavailable.remove(project_id);
+        self.word_counts.remove(project_id);
+        self.suggestion_indexes.remove(project_id);
+        Ok(())
+    }
 
+    /// Replace a project's connection with one to `db_path`, as after a
+    /// rebuild. The new database is opened and queried before the old
+    /// connection is closed, so on failure the project keeps the old one.
+    pub fn swap_connection(
+        &mut self,
+        project_id: &str,
+        db_path: &std::path::Path,
+    ) -> Result<(), String> {
+        let conn = open_project_database(project_id, db_path)?;
+        // Opening is lazy; preparing reads the schema, which catches files
+        // that are not SQLite or have no documents table
+        conn.prepare("SELECT 1 FROM documents LIMIT 1")
+            .map_err(|e| format!("Database for project '{}' is unusable: {}", project_id, e))?;
+        self.close_connection(project_id);
         self.connections.insert(project_id.to_string(), conn);
+        self.unavailable.remove(project_id);
         Ok(())
     }
 
     /// Close a project's database connection
     pub fn close_connection(&mut self, project_id: &str) {
-        self.connections.remove(project_id);
+        if let Some(conn) = self.connections.remove(project_id) {
+            if let Some(path) = conn.path() {
+                crate::ann::invalidate(path);
+                crate::ai::invalidate_chunk_anchors(path);
+            }
+        }
+        self.document_overlays.remove(project_id);
+        self.word_counts.remove(project_id);
+        self.suggestion_indexes.remove(project_id);
+    }
+
+    /// Keep a project's word counts, read from the database at `counted_from`,
+    /// until its connection closes. Counts from a database the project has
+    /// since moved off are dropped.
+    pub fn cache_word_counts(
+        &mut self,
+        project_id: &str,
+        counted_from: &std::path::Path,
+        counts: Arc<HashMap<String, i64>>,
+    ) {
+        let current = self
+            .connections
+            .get(project_id)
+            .and_then(|conn| conn.path())
+            .map(std::path::Path::new);
+        if current == Some(counted_from) {
+            self.word_counts.insert(project_id.to_string(), counts);
+        }
+    }
+
+    /// The title and heading index of a project for query suggestions,
+    /// built on the first call after the connection opens.
+    pub fn suggestion_index(
+        &mut self,
+        project_id: &str,
+    ) -> Result<&crate::suggestions::SuggestionIndex, String> {
+        if !self.suggestion_indexes.contains_key(project_id) {
+            let index = crate::suggestions::SuggestionIndex::build(self.connection(project_id)?)?;
+            self.suggestion_indexes
+                .insert(project_id.to_string(), index);
+        }
+        Ok(&self.suggestion_indexes[project_id])
     }
 
     /// Set the active project
@@ -169,24 +339,432 @@ impl ProjectManager {
 const PROJECTS_STORE_FILE: &str = "projects.json";
 const PROJECTS_KEY: &str = "projects";
 
-/// Load the project registry from the Tauri store.
+/// Load the project registry from projects.json.
 /// Returns the default registry (with just the Engineering Handbook) if none exists.
+/// A corrupt projects.json is copied aside, and whatever projects can still be
+/// read from it are saved back as the registry.
 pub fn load_registry(app: &AppHandle) -> Result<ProjectRegistry, String> {
-    let store = app.store(PROJECTS_STORE_FILE).map_err(|e| e.to_string())?;
+    let app_data_dir = app.path().app_data_dir().map_err(|e| e.to_string())?;
+    let path = app_data_dir.join(PROJECTS_STORE_FILE);
+    let contents = match std::fs::read_to_string(&path) {
+        Ok(contents) => contents,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(ProjectRegistry::default()),
+        Err(e) => return Err(format!("Failed to read {}: {}", PROJECTS_STORE_FILE, e)),
+    };
 
-    match store.get(PROJECTS_KEY) {
-        Some(value) => {
-            serde_json::from_value::<ProjectRegistry>(value.clone()).map_err(|e| e.to_string())
+    match serde_json::from_str::<serde_json::Value>(&contents) {
+        Ok(store) => Ok(match store.get(PROJECTS_KEY) {
+            Some(value) => serde_json::from_value::<ProjectRegistry>(value.clone())
+                .unwrap_or_else(|_| registry_from_value(value)),
+            None => ProjectRegistry::default(),
+        }),
+        Err(_) => {
+            let backup = app_data_dir.join(format!(
+                "{}.corrupt-{}",
+                PROJECTS_STORE_FILE,
+                chrono::Utc::now().timestamp()
+            ));
+            std::fs::copy(&path, &backup)
+                .map_err(|e| format!("Failed to back up corrupt {}: {}", PROJECTS_STORE_FILE, e))?;
+            let registry = lenient_registry(&contents);
+            eprintln!(
+                "Warning: {} was corrupt and was backed up to {:?}; recovered {} project(s)",
+                PROJECTS_STORE_FILE,
+                backup,
+                registry.projects.len()
+            );
+            // Keep the last good registry as the backup rather than the corrupt file
+            std::fs::remove_file(&path).map_err(|e| e.to_string())?;
+            save_registry(app, &registry)?;
+            Ok(registry)
         }
-        None => Ok(ProjectRegistry::default()),
     }
 }
 
-/// Save the project registry to the Tauri store.
+/// Registry from a store value whose shape is off, keeping each project entry
+/// that still deserializes and skipping the rest.
+fn registry_from_value(value: &serde_json::Value) -> ProjectRegistry {
+    let projects = value
+        .get("projects")
+        .and_then(serde_json::Value::as_array)
+        .map(|entries| {
+            entries
+                .iter()
+                .filter_map(|entry| serde_json::from_value::<Project>(entry.clone()).ok())
+                .collect()
+        })
+        .unwrap_or_default();
+    let active_project_id = value
+        .get("activeProjectId")
+        .and_then(serde_json::Value::as_str)
+        .unwrap_or_default();
+    repaired_registry(projects, active_project_id)
+}
+
+/// Projects salvaged from the text of a corrupt projects.json. A file cut
+/// short keeps every project entry that was complete before the cut.
+fn lenient_registry(contents: &str) -> ProjectRegistry {
+    let Some(list_start) = contents
+        .find("\"projects\"")
+        .and_then(|outer| {
+            contents[outer + 1..]
+                .find("\"projects\"")
+                .map(|i| outer + 1 + i)
+        })
+        .and_then(|inner| contents[inner..].find('[').map(|i| inner + i + 1))
+    else {
+        return ProjectRegistry::default();
+    };
+
+    let mut projects = Vec::new();
+    let mut rest = &contents[list_start..];
+    loop {
+        rest = rest.trim_start_matches(|c: char| c.is_whitespace() || c == ',');
+        if rest.is_empty() || rest.starts_with(']') {
+            break;
+        }
+        let mut entries = serde_json::Deserializer::from_str(rest).into_iter::<serde_json::Value>();
+        let Some(Ok(entry)) = entries.next() else {
+            break;
+        };
+        rest = &rest[entries.byte_offset()..];
+        if let Ok(project) = serde_json::from_value::<Project>(entry) {
+            projects.push(project);
+        }
+    }
+
+    let active_project_id = contents
+        .find("\"activeProjectId\"")
+        .and_then(|key| {
+            let value = contents[key..].split_once(':')?.1.trim_start();
+            serde_json::Deserializer::from_str(value)
+                .into_iter::<String>()
+                .next()?
+                .ok()
+        })
+        .unwrap_or_default();
+    repaired_registry(projects, &active_project_id)
+}
+
+/// Registry holding `projects` plus the built-in handbook if they lost it, with
+/// the active project falling back to the first one when it is gone.
+fn repaired_registry(mut projects: Vec<Project>, active_project_id: &str) -> ProjectRegistry {
+    let default = ProjectRegistry::default();
+    if !projects.iter().any(|p| p.built_in) {
+        projects.splice(0..0, default.projects);
+    }
+    let active_project_id = if projects.iter().any(|p| p.id == active_project_id) {
+        active_project_id.to_string()
+    } else {
+        projects[0].id.clone()
+    };
+    ProjectRegistry {
+        projects,
+        active_project_id,
+    }
+}
+
+/// A database in the app's projects folder that no registered project uses,
+/// e.g. after projects.json was lost.
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct OrphanedProject {
+    /// The project id the database was created under, from its file name
+    pub id: String,
+    /// Relative to app_data_dir, like `Project::db_path`
+    pub db_path: String,
+    pub size_bytes: u64,
+}
+
+/// Databases under `app_data_dir/projects` that `registry` does not reference.
+pub fn orphaned_project_dbs(
+    app_data_dir: &std::path::Path,
+    registry: &ProjectRegistry,
+) -> Vec<OrphanedProject> {
+    let Ok(entries) = std::fs::read_dir(app_data_dir.join("projects")) else {
+        return vec![];
+    };
+    let mut orphans: Vec<OrphanedProject> = entries
+        .filter_map(Result::ok)
+        .filter_map(|entry| {
+            let path = entry.path();
+            if path.extension()? != "db" {
+                return None;
+            }
+            let id = path.file_stem()?.to_str()?.to_string();
+            let db_path = format!("projects/{}.db", id);
+            if registry
+                .projects
+                .iter()
+                .any(|p| p.db_path.as_deref() == Some(db_path.as_str()))
+            {
+                return None;
+            }
+            Some(OrphanedProject {
+                id,
+                db_path,
+                size_bytes: entry.metadata().map(|m| m.len()).unwrap_or(0),
+            })
+        })
+        .collect();
+    orphans.sort_by(|a, b| a.id.cmp(&b.id));
+    orphans
+}
+
+/// Persist the project registry. The new registry is written to a temporary
+/// file and renamed over projects.json, so a crash mid-save leaves either the
+/// old or the new registry in place, and the previous version is kept as
+/// projects.json.bak.
 pub fn save_registry(app: &AppHandle, registry: &ProjectRegistry) -> Result<(), String> {
-    let store = app.store(PROJECTS_STORE_FILE).map_err(|e| e.to_string())?;
-    let value = serde_json::to_value(registry).map_err(|e| e.to_string())?;
-    store.set(PROJECTS_KEY, value);
-    store.save().map_err(|e| e.to_string())?;
-    Ok(())
+    let app_data_dir = app.path().app_data_dir().map_err(|e| e.to_string())?;
+    std::fs::create_dir_all(&app_data_dir).map_err(|e| e.to_string())?;
+    let staged = stage_registry(&app_data_dir, registry)?;
+    commit_registry(&app_data_dir, &staged)
+}
+
+/// Write `registry` to a temporary file beside projects.json, returning its path.
+fn stage_registry(
+    app_data_dir: &std::path::Path,
+    registry: &ProjectRegistry,
+) -> Result<std::path::PathBuf, String> {
+    let mut store = serde_json::Map::new();
+    store.insert(
+        PROJECTS_KEY.to_string(),
+        serde_json::to_value(registry).map_err(|e| e.to_string())?,
+    );
+    let contents = serde_json::to_vec_pretty(&store).map_err(|e| e.to_string())?;
+
+    let staged = app_data_dir.join(format!("{}.tmp", PROJECTS_STORE_FILE));
+    let mut file = std::fs::File::create(&staged)
+        .map_err(|e| format!("Failed to write {}: {}", PROJECTS_STORE_FILE, e))?;
+    std::io::Write::write_all(&mut file, &contents)
+        .and_then(|_| file.sync_all())
+        .map_err(|e| format!("Failed to write {}: {}", PROJECTS_STORE_FILE, e))?;
+    Ok(staged)
+}
+
+/// Keep the current projects.json as the backup, then move the staged file
+/// into its place.
+fn commit_registry(app_data_dir: &std::path::Path, staged: &std::path::Path) -> Result<(), String> {
+    let path = app_data_dir.join(PROJECTS_STORE_FILE);
+    if path.exists() {
+        std::fs::copy(&path, registry_backup_path(app_data_dir))
+            .map_err(|e| format!("Failed to back up {}: {}", PROJECTS_STORE_FILE, e))?;
+    }
+    std::fs::rename(staged, &path)
+        .map_err(|e| format!("Failed to replace {}: {}", PROJECTS_STORE_FILE, e))
+}
+
+/// Where the registry before the last save is kept.
+pub fn registry_backup_path(app_data_dir: &std::path::Path) -> std::path::PathBuf {
+    app_data_dir.join(format!("{}.bak", PROJECTS_STORE_FILE))
+}
+
+/// The registry saved in projects.json.bak, with unreadable entries skipped.
+pub fn load_registry_backup(app_data_dir: &std::path::Path) -> Result<ProjectRegistry, String> {
+    let contents = std::fs::read_to_string(registry_backup_path(app_data_dir))
+        .map_err(|e| format!("No registry backup to restore: {}", e))?;
+    let store: serde_json::Value = serde_json::from_str(&contents)
+        .map_err(|e| format!("Registry backup is unreadable: {}", e))?;
+    let value = store
+        .get(PROJECTS_KEY)
+        .ok_or("Registry backup has no projects")?;
+    Ok(serde_json::from_value::<ProjectRegistry>(value.clone())
+        .unwrap_or_else(|_| registry_from_value(value)))
+}
+
+fn open_project_database(
+    project_id: &str,
+    db_path: &std::path::Path,
+) -> Result<Connection, String> {
+    Connection::open_with_flags(
+        db_path,
+        rusqlite::OpenFlags::SQLITE_OPEN_READ_ONLY | rusqlite::OpenFlags::SQLITE_OPEN_NO_MUTEX,
+    )
+    .map_err(|e| {
+        format!(
+            "Failed to open database for project '{}': {}",
+            project_id, e
+        )
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{
+        commit_registry, lenient_registry, load_registry_backup, registry_backup_path,
+        stage_registry, ProjectManager, ProjectRegistry, PROJECTS_STORE_FILE,
+    };
+    use crate::test_support::scratch_dir;
+    use std::path::Path;
+
+    const HANDBOOK: &str = r#"{"id":"engineering-handbook","name":"Engineering Handbook","icon":"book","builtIn":true}"#;
+
+    fn team_docs(id: &str) -> String {
+        format!(
+            r#"{{"id":"{id}","name":"{id}","icon":"folder","builtIn":false,"dbPath":"projects/{id}.db"}}"#
+        )
+    }
+
+    fn ids(contents: &str) -> (Vec<String>, String) {
+        let registry = lenient_registry(contents);
+        (
+            registry.projects.into_iter().map(|p| p.id).collect(),
+            registry.active_project_id,
+        )
+    }
+
+    #[test]
+    fn truncated_file_keeps_complete_entries() {
+        let full = format!(
+            r#"{{"projects":{{"projects":[{},{},{}],"activeProjectId":"beta"}}}}"#,
+            HANDBOOK,
+            team_docs("alpha"),
+            team_docs("beta")
+        );
+        let cut = &full[..full.find("beta").unwrap() + 2];
+        assert_eq!(
+            ids(cut),
+            (
+                vec!["engineering-handbook".to_string(), "alpha".to_string()],
+                "engineering-handbook".to_string()
+            )
+        );
+        assert_eq!(
+            ids(&full),
+            (
+                vec![
+                    "engineering-handbook".to_string(),
+                    "alpha".to_string(),
+                    "beta".to_string()
+                ],
+                "beta".to_string()
+            )
+        );
+    }
+
+    #[test]
+    fn invalid_entries_are_skipped_and_handbook_restored() {
+        let contents = format!(
+            r#"{{"projects":{{"projects":[{{"id":42}},{},{{"name":"no id"}}],"activeProjectId":"alpha"}}"#,
+            team_docs("alpha")
+        );
+        assert_eq!(
+            ids(&contents),
+            (
+                vec!["engineering-handbook".to_string(), "alpha".to_string()],
+                "alpha".to_string()
+            )
+        );
+        assert_eq!(
+            ids("garbage"),
+            (
+                vec!["engineering-handbook".to_string()],
+                "engineering-handbook".to_string()
+            )
+        );
+    }
+
+    fn saved_ids(path: &Path) -> Vec<String> {
+        let contents = std::fs::read_to_string(path).unwrap();
+        let store: serde_json::Value = serde_json::from_str(&contents).unwrap();
+        let registry: ProjectRegistry = serde_json::from_value(store["projects"].clone()).unwrap();
+        registry.projects.into_iter().map(|p| p.id).collect()
+    }
+
+    fn registry_with(ids: &[&str]) -> ProjectRegistry {
+        let entries: Vec<String> = ids.iter().map(|id| team_docs(id)).collect();
+        lenient_registry(&format!(
+            r#"{{"projects":{{"projects":[{},{}],"activeProjectId":""}}}}"#,
+            HANDBOOK,
+            entries.join(",")
+        ))
+    }
+
+    #[test]
+    fn save_keeps_previous_registry_as_backup() {
+        let dir = scratch_dir("registry-save");
+        let path = dir.join(PROJECTS_STORE_FILE);
+        for ids in [&["alpha"][..], &["alpha", "beta"][..]] {
+            let staged = stage_registry(&dir, &registry_with(ids)).unwrap();
+            commit_registry(&dir, &staged).unwrap();
+        }
+        assert_eq!(saved_ids(&path), ["engineering-handbook", "alpha", "beta"]);
+        assert_eq!(
+            saved_ids(&registry_backup_path(&dir)),
+            ["engineering-handbook", "alpha"]
+        );
+        let restored = load_registry_backup(&dir).unwrap();
+        assert_eq!(restored.projects.len(), 2);
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn failure_before_rename_leaves_registry_intact() {
+        let dir = scratch_dir("registry-crash");
+        let path = dir.join(PROJECTS_STORE_FILE);
+        let staged = stage_registry(&dir, &registry_with(&["alpha"])).unwrap();
+        commit_registry(&dir, &staged).unwrap();
+
+        // A crash after writing the new registry but before renaming it
+        let staged = stage_registry(&dir, &registry_with(&["alpha", "beta"])).unwrap();
+        assert_eq!(saved_ids(&path), ["engineering-handbook", "alpha"]);
+        assert!(!registry_backup_path(&dir).exists());
+
+        // A crash part way through writing leaves a torn temporary file only
+        std::fs::write(&staged, b"{\"projects\":{\"proj").unwrap();
+        assert_eq!(saved_ids(&path), ["engineering-handbook", "alpha"]);
+
+        // The next save replaces the leftover temporary file
+        let staged = stage_registry(&dir, &registry_with(&["gamma"])).unwrap();
+        commit_registry(&dir, &staged).unwrap();
+        assert_eq!(saved_ids(&path), ["engineering-handbook", "gamma"]);
+        assert_eq!(
+            saved_ids(&registry_backup_path(&dir)),
+            ["engineering-handbook", "alpha"]
+        );
+        assert!(!staged.exists());
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn failed_swap_keeps_the_old_connection() {
+        let dir = scratch_dir("connection-swap");
+        let project_db = |name: &str, documents: usize| {
+            let path = dir.join(name);
+            let db = rusqlite::Connection::open(&path).unwrap();
+            db.execute_batch("CREATE TABLE documents (slug TEXT NOT NULL)")
+                .unwrap();
+            for n in 0..documents {
+                db.execute("INSERT INTO documents VALUES (?1)", [format!("doc-{}", n)])
+                    .unwrap();
+            }
+            path
+        };
+        let document_count = |mgr: &ProjectManager| -> i64 {
+            mgr.connection("alpha")
+                .unwrap()
+                .query_row("SELECT COUNT(*) FROM documents", [], |row| row.get(0))
+                .unwrap()
+        };
+
+        let mut mgr = ProjectManager::new(registry_with(&["alpha"]));
+        mgr.open_connection("alpha", &project_db("alpha.db", 1))
+            .unwrap();
+
+        std::fs::write(dir.join("torn.db"), b"not a database").unwrap();
+        rusqlite::Connection::open(dir.join("empty.db"))
+            .unwrap()
+            .execute_batch("CREATE TABLE other (id INTEGER)")
+            .unwrap();
+        for unusable in ["missing.db", "torn.db", "empty.db"] {
+            assert!(mgr.swap_connection("alpha", &dir.join(unusable)).is_err());
+            assert_eq!(document_count(&mgr), 1, "{}", unusable);
+        }
+
+        mgr.swap_connection("alpha", &project_db("rebuilt.db", 3))
+            .unwrap();
+        assert_eq!(document_count(&mgr), 3);
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
 }
@@ -0,0 +1,924 @@
+//! Read-only queries against a project database, shared by the Tauri commands,
+//! the local HTTP API, and the CLI so all return identical results.
+
+use crate::ai;
+use crate::models::*;
+use rusqlite::{Connection, OptionalExtension};
+use std::collections::HashMap;
+
+/// Orders for collection listings.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum CollectionSort {
+    /// The builder's order
+    SortOrder,
+    Name,
+    /// Most recently modified document first; collections without dates last
+    Recent,
+}
+
+impl CollectionSort {
+    pub fn parse(sort: &str) -> Result<Self, String> {
+        match sort {
+            "sort_order" => Ok(CollectionSort::SortOrder),
+            "name" => Ok(CollectionSort::Name),
+            "recent" => Ok(CollectionSort::Recent),
+            other => Err(format!("Unknown collection sort '{}'", other)),
+        }
+    }
+}
+
+pub fn collections(conn: &Connection, sort: CollectionSort) -> Result<Vec<Collection>, String> {
+    let sql = match sort {
+        CollectionSort::SortOrder => {
+            "SELECT id, name, icon, description, sort_order FROM collections ORDER BY sort_order"
+        }
+        CollectionSort::Name => {
+            "SELECT id, name, icon, description, sort_order FROM collections \
+             ORDER BY name COLLATE NOCASE, sort_order"
+        }
+        // Builders store '' or NULL when a file's modification time is unknown
+        CollectionSort::Recent => {
+            "SELECT c.id, c.name, c.icon, c.description, c.sort_order, \
+             MAX(NULLIF(d.last_modified, '')) AS last_updated \
+             FROM collections c \
+             LEFT JOIN documents d ON d.collection_id = c.id \
+             GROUP BY c.id \
+             ORDER BY last_updated IS NULL, last_updated DESC, c.sort_order"
+        }
+    };
+    let mut stmt = conn.prepare_cached(sql).map_err(|e| e.to_string())?;
+    let results = stmt
+        .query_map([], |row| {
+            Ok(Collection {
+                id: row.get(0)?,
+                name: row.get(1)?,
+                icon: row.get(2)?,
+                description: row.get(3)?,
+                sort_order: row.get(4)?,
+                pinned: false,
+                hidden: false,
+            })
+        })
+        .map_err(|e| e.to_string())?;
+    results
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(|e| e.to_string())
+}
+
+/// Every document slug in the project, used to spot documents a rebuild removed.
+pub fn document_slugs(conn: &Connection) -> Result<std::collections::HashSet<String>, String> {
+    let mut stmt = conn
+        .prepare("SELECT slug FROM documents")
+        .map_err(|e| e.to_string())?;
+    let rows = stmt
+        .query_map([], |row| row.get(0))
+        .map_err(|e| e.to_string())?;
+    rows.collect::<Result<_, _>>().map_err(|e| e.to_string())
+}
+
+fn count_rows(conn: &Connection, table: &str) -> i32 {
+    conn.query_row(&format!("SELECT COUNT(*) FROM {}", table), [], |row| {
+        row.get(0)
+    })
+    .unwrap_or(0)
+}
+
+/// Row counts for a project database. Missing tables count as zero.
+pub fn project_stats(conn: &Connection, db_size_bytes: u64) -> ProjectStats {
+    ProjectStats {
+        document_count: count_rows(conn, "documents"),
+        collection_count: count_rows(conn, "collections"),
+        tag_count: count_rows(conn, "tags"),
+        chunk_count: count_rows(conn, "chunks"),
+        embedding_count: count_rows(conn, "chunk_embeddings"),
+        db_size_bytes,
+        hidden_collection_count: 0,
+    }
+}
+
+/// Bytes used by each table and index, largest first, from the `dbstat`
+/// virtual table. FTS shadow tables are listed individually.
+pub fn storage_breakdown(conn: &Connection) -> Result<StorageBreakdown, String> {
+    let mut stmt = conn
+        .prepare(
+            "SELECT name, SUM(pgsize), COUNT(*) FROM dbstat \
+             GROUP BY name ORDER BY SUM(pgsize) DESC, name",
+        )
+        .map_err(|e| e.to_string())?;
+    let tables = stmt
+        .query_map([], |row| {
+            Ok(TableStorage {
+                name: row.get(0)?,
+                bytes: row.get(1)?,
+                pages: row.get(2)?,
+            })
+        })
+        .map_err(|e| e.to_string())?
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(|e| e.to_string())?;
+    Ok(StorageBreakdown {
+        total_bytes: tables.iter().map(|t| t.bytes).sum(),
+        tables,
+    })
+}
+
+/// Reading speed behind the reading-time estimates.
+pub const READING_WORDS_PER_MINUTE: i64 = 200;
+
+/// Minutes to read `word_count` words, rounded up.
+pub fn reading_minutes(word_count: i64) -> i64 {
+    (word_count.max(0) + READING_WORDS_PER_MINUTE - 1) / READING_WORDS_PER_MINUTE
+}
+
+fn has_word_count_column(conn: &Connection) -> bool {
+    conn.query_row(
+        "SELECT EXISTS(SELECT 1 FROM pragma_table_info('documents') WHERE name = 'word_count')",
+        [],
+        |row| row.get::<_, i64>(0),
+    )
+    .is_ok_and(|exists| exists == 1)
+}
+
+/// Words in every document, keyed by slug: the build's `word_count` when it
+/// stored one, otherwise counted from `content_html` the same way. Databases
+/// from older builds read every document, so call this off the main thread.
+pub fn document_word_counts(conn: &Connection) -> Result<HashMap<String, i64>, String> {
+    let mut counts = HashMap::new();
+    if has_word_count_column(conn) {
+        let mut stmt = conn
+            .prepare("SELECT slug, word_count FROM documents")
+            .map_err(|e| e.to_string())?;
+        let rows = stmt
+            .query_map([], |row| Ok((row.get(0)?, row.get(1)?)))
+            .map_err(|e| e.to_string())?;
+        for row in rows {
+            let (slug, words): (String, i64) = row.map_err(|e| e.to_string())?;
+            counts.insert(slug, words);
+        }
+    } else {
+        let mut stmt = conn
+            .prepare("SELECT slug, content_html FROM documents")
+            .map_err(|e| e.to_string())?;
+        let mut rows = stmt.query([]).map_err(|e| e.to_string())?;
+        while let Some(row) = rows.next().map_err(|e| e.to_string())? {
+            let html: String = row.get(1).map_err(|e| e.to_string())?;
+            counts.insert(
+                row.get(0).map_err(|e| e.to_string())?,
+                crate::snippet::word_count(&html),
+            );
+        }
+    }
+    Ok(counts)
+}
+
+const DOCUMENT_LISTING_SELECT: &str =
+    "SELECT d.slug, d.title, d.collection_id, d.section, NULLIF(d.last_modified, '') \
+     FROM documents d \
+     WHERE ?1 IS NULL OR d.collection_id = ?1";
+
+/// A listed document, its words looked up in `word_counts` from
+/// `document_word_counts`.
+fn document_list_item_from_row(
+    row: &rusqlite::Row<'_>,
+    word_counts: &HashMap<String, i64>,
+) -> rusqlite::Result<DocumentListItem> {
+    let slug: String = row.get(0)?;
+    let word_count = word_counts.get(&slug).copied().unwrap_or(0);
+    Ok(DocumentListItem {
+        slug,
+        title: row.get(1)?,
+        collection_id: row.get(2)?,
+        section: row.get(3)?,
+        last_modified: row.get(4)?,
+        word_count,
+        reading_minutes: reading_minutes(word_count),
+    })
+}
+
+/// One page of every document's metadata, ordered by collection and title,
+/// with the number of documents across all pages.
+pub fn document_listing(
+    conn: &Connection,
+    word_counts: &HashMap<String, i64>,
+    collection_id: Option<&str>,
+    limit: i64,
+    offset: i64,
+) -> Result<DocumentListing, String> {
+    let total = conn
+        .query_row(
+            "SELECT COUNT(*) FROM documents WHERE ?1 IS NULL OR collection_id = ?1",
+            [collection_id],
+            |row| row.get(0),
+        )
+        .map_err(|e| e.to_string())?;
+    let sql = format!(
+        "{} ORDER BY d.collection_id, d.title, d.slug LIMIT ?2 OFFSET ?3",
+        DOCUMENT_LISTING_SELECT
+    );
+    let mut stmt = conn.prepare_cached(&sql).map_err(|e| e.to_string())?;
+    let documents = stmt
+        .query_map(rusqlite::params![collection_id, limit, offset], |row| {
+            document_list_item_from_row(row, word_counts)
+        })
+        .map_err(|e| e.to_string())?
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(|e| e.to_string())?;
+    Ok(DocumentListing { documents, total })
+}
+
+/// Call `f` with every document's metadata in listing order, reading rows as
+/// they are needed. Returns the number of documents.
+pub fn each_listed_document(
+    conn: &Connection,
+    word_counts: &HashMap<String, i64>,
+    collection_id: Option<&str>,
+    mut f: impl FnMut(DocumentListItem) -> Result<(), String>,
+) -> Result<usize, String> {
+    let sql = format!(
+        "{} ORDER BY d.collection_id, d.title, d.slug",
+        DOCUMENT_LISTING_SELECT
+    );
+    let mut stmt = conn.prepare(&sql).map_err(|e| e.to_string())?;
+    let rows = stmt
+        .query_map([collection_id], |row| {
+            document_list_item_from_row(row, word_counts)
+        })
+        .map_err(|e| e.to_string())?;
+    let mut count = 0;
+    for row in rows {
+        f(row.map_err(|e| e.to_string())?)?;
+        count += 1;
+    }
+    Ok(count)
+}
+
+pub fn navigation(conn: &Connection, collection_id: &str) -> Result<Vec<NavigationNode>, String> {
+    let mut stmt = conn
+        .prepare_cached(
+            "SELECT id, collection_id, slug, parent_slug, title, sort_order, level, has_children \
+             FROM navigation_tree \
+             WHERE collection_id = ? \
+             ORDER BY level, sort_order",
+        )
+        .map_err(|e| e.to_string())?;
+    let results = stmt
+        .query_map([collection_id], |row| {
+            let has_children_int: i32 = row.get(7)?;
+            Ok(NavigationNode {
+                id: row.get(0)?,
+                collection_id: row.get(1)?,
+                slug: row.get(2)?,
+                parent_slug: row.get(3)?,
+                title: row.get(4)?,
+                sort_order: row.get(5)?,
+                level: row.get(6)?,
+                has_children: has_children_int != 0,
+                word_count: None,
+                reading_minutes: None,
+            })
+        })
+        .map_err(|e| e.to_string())?;
+    results
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(|e| e.to_string())
+}
+
+/// Look up a document by slug, following `slug_aliases` when the document was
+/// renamed. The returned document carries its current slug.
+pub fn document(conn: &Connection, slug: &str) -> Result<Document, String> {
+    find_document(conn, slug)?.ok_or_else(|| rusqlite::Error::QueryReturnedNoRows.to_string())
+}
+
+/// A document by slug, or by the slug it was renamed to; None when neither exists.
+pub fn find_document(conn: &Connection, slug: &str) -> Result<Option<Document>, String> {
+    match document_by_slug(conn, slug) {
+        Err(rusqlite::Error::QueryReturnedNoRows) => match slug_alias(conn, slug) {
+            Some(canonical) => document_by_slug(conn, &canonical),
+            None => Err(rusqlite::Error::QueryReturnedNoRows),
+        },
+        result => result,
+    }
+    .optional()
+    .map_err(|e| e.to_string())
+}
+
+/// Current slug for a renamed document. Databases built before aliases
+/// existed have no table, which reads as no alias.
+pub fn slug_alias(conn: &Connection, slug: &str) -> Option<String> {
+    conn.query_row(
+        "SELECT new_slug FROM slug_aliases WHERE old_slug = ?1",
+        [slug],
+        |row| row.get(0),
+    )
+    .ok()
+}
+
+fn document_by_slug(conn: &Connection, slug: &str) -> rusqlite::Result<Document> {
+    conn.query_row(
+        "SELECT id, collection_id, slug, title, section, sort_order, parent_slug, \
+         content_html, path, last_modified \
+         FROM documents WHERE slug = ?",
+        [slug],
+        |row| {
+            Ok(Document {
+                id: row.get(0)?,
+                collection_id: row.get(1)?,
+                slug: row.get(2)?,
+                title: row.get(3)?,
+                section: row.get(4)?,
+                sort_order: row.get(5)?,
+                parent_slug: row.get(6)?,
+                content_html: row.get(7)?,
+                path: row.get(8)?,
+                last_modified: row.get(9)?,
+            })
+        },
+    )
+}
+
+fn search_result_from_row(row: &rusqlite::Row<'_>) -> rusqlite::Result<SearchResult> {
+    Ok(SearchResult {
+        slug: row.get(0)?,
+        title: row.get(1)?,
+        section: row.get(2)?,
+        collection_id: row.get(3)?,
+        snippet: crate::snippet::clean_snippet(&row.get::<_, String>(4)?),
+        context: String::new(),
+        collapsed_count: 0,
+        collapsed_slugs: Vec::new(),
+    })
+}
+
+/// Characters of plain text around the first match in a search result's
+/// `context`.
+const SEARCH_CONTEXT_CHARS: usize = 300;
+
+/// Documents matching `query`, best first. Documents in
+/// `excluded_collections` are left out. `snippet_tokens` is clamped to
+/// `MIN_SNIPPET_TOKENS..=MAX_SNIPPET_TOKENS`.
+pub fn search_documents(
+    conn: &Connection,
+    query: &str,
+    collection_id: Option<&str>,
+    excluded_collections: &[String],
+    limit: i32,
+    snippet_tokens: i32,
+) -> Result<Vec<SearchResult>, String> {
+    let tokenizer = ai::fts_tokenizer(conn, "documents_fts")
+        .unwrap_or_else(|| FtsTokenizer::default_for("documents_fts"));
+    let sanitised_query = ai::sanitise_fts5_query(query, &tokenizer);
+    if sanitised_query.is_empty() {
+        return Ok(vec![]);
+    }
+
+    let mut sql = String::from(
+        "SELECT d.slug, d.title, d.section, d.collection_id, \
+         snippet(documents_fts, 1, '<mark>', '</mark>', '...', ?) as snippet, \
+         d.content_html \
+         FROM documents_fts \
+         JOIN documents d ON d.id = documents_fts.rowid \
+         WHERE documents_fts MATCH ?",
+    );
+    let snippet_tokens = snippet_tokens.clamp(
+        crate::snippet::MIN_SNIPPET_TOKENS,
+        crate::snippet::MAX_SNIPPET_TOKENS,
+    );
+    let mut params: Vec<rusqlite::types::Value> =
+        vec![snippet_tokens.into(), sanitised_query.into()];
+    if let Some(cid) = collection_id {
+        sql.push_str(" AND d.collection_id = ?");
+        params.push(cid.to_string().into());
+    }
+    if !excluded_collections.is_empty() {
+        sql.push_str(&format!(
+            " AND d.collection_id NOT IN ({})",
+            vec!["?"; excluded_collections.len()].join(", ")
+        ));
+        params.extend(excluded_collections.iter().cloned().map(Into::into));
+    }
+    sql.push_str(" ORDER BY rank LIMIT ?");
+    params.push(limit.into());
+
+    let terms: Vec<String> = query
+        .split_whitespace()
+        .map(|term| {
+            term.trim_matches(|c: char| !c.is_alphanumeric())
+                .to_string()
+        })
+        .filter(|term| !term.is_empty())
+        .collect();
+    let mut stmt = conn.prepare_cached(&sql).map_err(|e| e.to_string())?;
+    let rows = stmt
+        .query_map(rusqlite::params_from_iter(params), |row| {
+            let mut result = search_result_from_row(row)?;
+            result.context = crate::snippet::excerpt_around(
+                &crate::snippet::html_text(&row.get::<_, String>(5)?),
+                &terms,
+                SEARCH_CONTEXT_CHARS,
+            );
+            Ok(result)
+        })
+        .map_err(|e| e.to_string())?;
+    rows.collect::<Result<Vec<_>, _>>()
+        .map_err(|e| e.to_string())
+}
+
+/// Separators that set off a title's trailing segment, as in
+/// "Endpoint reference: users" or "Deploys - staging".
+const TITLE_SEGMENT_SEPARATORS: [&str; 6] = [": ", " - ", " – ", " — ", " | ", " / "];
+
+/// A title reduced for comparison: lowercased, punctuation turned into
+/// spaces, and trailing version tokens such as "v2" or "1.4.0" dropped. A
+/// bare number is part of the title, as in "Python 3" or "Chapter 12".
+pub fn normalize_title(title: &str) -> String {
+    let lower = title.to_lowercase();
+    let mut tokens: Vec<&str> = lower.split_whitespace().collect();
+    let is_version = |token: &str| {
+        let token = token.trim_matches(|c: char| !c.is_alphanumeric());
+        let (number, prefixed) = match token.strip_prefix('v') {
+            Some(number) => (number, true),
+            None => (token, false),
+        };
+        (prefixed || number.contains('.'))
+            && number.starts_with(|c: char| c.is_ascii_digit())
+            && number.chars().all(|c| c.is_ascii_digit() || c == '.')
+    };
+    while tokens.len() > 1 && tokens.last().is_some_and(|token| is_version(token)) {
+        tokens.pop();
+    }
+    tokens
+        .iter()
+        .flat_map(|token| token.split(|c: char| !c.is_alphanumeric()))
+        .filter(|word| !word.is_empty())
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+/// The normalized title without its trailing segment, so titles differing
+/// only after the last separator compare equal. Titles without a separator
+/// are just normalized.
+fn title_stem(title: &str) -> String {
+    let stem = TITLE_SEGMENT_SEPARATORS
+        .iter()
+        .filter_map(|separator| title.rfind(separator))
+        .max()
+        .map_or(title, |end| &title[..end]);
+    match normalize_title(stem) {
+        stem if stem.is_empty() => normalize_title(title),
+        stem => stem,
+    }
+}
+
+/// Fold each result into the best-ranked earlier one with the same title
+/// stem, recording the folded slugs on the one kept. Order is otherwise kept.
+pub fn collapse_similar_titles(results: Vec<SearchResult>) -> Vec<SearchResult> {
+    let mut kept: Vec<(String, SearchResult)> = Vec::new();
+    for result in results {
+        let stem = title_stem(&result.title);
+        match kept.iter_mut().find(|(kept_stem, _)| *kept_stem == stem) {
+            Some((_, best)) => {
+                best.collapsed_count += 1;
+                best.collapsed_slugs.push(result.slug);
+            }
+            None => kept.push((stem, result)),
+        }
+    }
+    kept.into_iter().map(|(_, result)| result).collect()
+}
+
+/// Tokenizers of the project's full-text tables, and how `query` would be
+/// sent to document search.
+pub fn search_diagnostics(conn: &Connection, query: Option<&str>) -> SearchDiagnostics {
+    let documents_fts = ai::fts_tokenizer(conn, "documents_fts");
+    let sanitised_query = query.map(|query| {
+        let tokenizer = documents_fts
+            .clone()
+            .unwrap_or_else(|| FtsTokenizer::default_for("documents_fts"));
+        ai::sanitise_fts5_query(query, &tokenizer)
+    });
+    SearchDiagnostics {
+        documents_fts,
+        chunks_fts: ai::fts_tokenizer(conn, "chunks_fts"),
+        sanitised_query,
+    }
+}
+
+/// Orders for tag listings.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum TagSort {
+    /// Most used first
+    Count,
+    Alpha,
+}
+
+impl TagSort {
+    pub fn parse(sort: &str) -> Result<Self, String> {
+        match sort {
+            "count" => Ok(TagSort::Count),
+            "alpha" => Ok(TagSort::Alpha),
+            other => Err(format!("Unknown tag sort '{}'", other)),
+        }
+    }
+}
+
+pub fn tags(
+    conn: &Connection,
+    collection_id: Option<&str>,
+    sort: TagSort,
+) -> Result<Vec<Tag>, String> {
+    let order_by = match sort {
+        TagSort::Count => "count DESC",
+        TagSort::Alpha => "t.tag COLLATE NOCASE",
+    };
+    let sql = format!(
+        "SELECT t.tag, COUNT(dt.document_id) as count \
+         FROM tags t \
+         JOIN document_tags dt ON dt.tag_id = t.id \
+         JOIN documents d ON d.id = dt.document_id \
+         WHERE ?1 IS NULL OR d.collection_id = ?1 \
+         GROUP BY t.tag \
+         ORDER BY {}",
+        order_by
+    );
+    let mut stmt = conn.prepare_cached(&sql).map_err(|e| e.to_string())?;
+    let rows = stmt
+        .query_map([collection_id], |row| {
+            Ok(Tag {
+                tag: row.get(0)?,
+                count: row.get(1)?,
+            })
+        })
+        .map_err(|e| e.to_string())?;
+    rows.collect::<Result<Vec<_>, _>>()
+        .map_err(|e| e.to_string())
+}
+
+/// Per collection, how many documents there are, how many have no tags at all,
+/// and how many have at least one tag row pointing at a deleted tag. The last
+/// are the documents behind the integrity report's `document_tag_without_tag`
+/// findings.
+pub fn tag_coverage(conn: &Connection) -> Result<Vec<TagCoverage>, String> {
+    let mut stmt = conn
+        .prepare_cached(
+            "SELECT c.id, c.name, COUNT(d.id), \
+             COALESCE(SUM(d.id IS NOT NULL AND dt.document_id IS NULL), 0), \
+             COALESCE(SUM(dt.tag_count < dt.row_count), 0) \
+             FROM collections c \
+             LEFT JOIN documents d ON d.collection_id = c.id \
+             LEFT JOIN ( \
+                 SELECT dt.document_id, COUNT(t.id) AS tag_count, COUNT(*) AS row_count \
+                 FROM document_tags dt \
+                 LEFT JOIN tags t ON t.id = dt.tag_id \
+                 GROUP BY dt.document_id \
+             ) dt ON dt.document_id = d.id \
+             GROUP BY c.id \
+             ORDER BY c.sort_order",
+        )
+        .map_err(|e| e.to_string())?;
+    let rows = stmt
+        .query_map([], |row| {
+            Ok(TagCoverage {
+                collection_id: row.get(0)?,
+                collection_name: row.get(1)?,
+                document_count: row.get(2)?,
+                untagged_count: row.get(3)?,
+                dangling_tag_count: row.get(4)?,
+            })
+        })
+        .map_err(|e| e.to_string())?;
+    rows.collect::<Result<Vec<_>, _>>()
+        .map_err(|e| e.to_string())
+}
+
+pub fn documents_by_tag(conn: &Connection, tag: &str) -> Result<Vec<SearchResult>, String> {
+    let mut stmt = conn
+        .prepare_cached(
+            "SELECT d.slug, d.title, d.section, d.collection_id, '' as snippet \
+             FROM documents d \
+             JOIN document_tags dt ON d.id = dt.document_id \
+             JOIN tags t ON t.id = dt.tag_id \
+             WHERE t.tag = ? \
+             ORDER BY d.title",
+        )
+        .map_err(|e| e.to_string())?;
+    let results = stmt
+        .query_map([tag], search_result_from_row)
+        .map_err(|e| e.to_string())?;
+    results
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(|e| e.to_string())
+}
+
+pub fn document_tags(conn: &Connection, document_id: i32) -> Result<Vec<String>, String> {
+    let mut stmt = conn
+        .prepare_cached(
+            "SELECT t.tag FROM tags t \
+             JOIN document_tags dt ON dt.tag_id = t.id \
+             WHERE dt.document_id = ? \
+             ORDER BY t.tag",
+        )
+        .map_err(|e| e.to_string())?;
+    let tags = stmt
+        .query_map([document_id], |row| row.get(0))
+        .map_err(|e| e.to_string())?;
+    tags.collect::<Result<Vec<_>, _>>()
+        .map_err(|e| e.to_string())
+}
+
+/// Fallback heading extraction for databases built before document_anchors existed.
+fn parse_html_headings(html: &str) -> Vec<DocumentAnchor> {
+    let mut anchors = Vec::new();
+    let mut rest = html;
+
+    while let Some(start) = rest.find("<h") {
+        rest = &rest[start + 2..];
+        let Some(level) = rest.chars().next().and_then(|c| c.to_digit(10)) else {
+            continue;
+        };
+        if !(1..=6).contains(&level) {
+            continue;
+        }
+        let Some(tag_end) = rest.find('>') else {
+            break;
+        };
+        let attrs = &rest[1..tag_end];
+        let close_tag = format!("</h{}>", level);
+        let Some(close) = rest.find(&close_tag) else {
+            break;
+        };
+        if close < tag_end {
+            continue;
+        }
+
+        let anchor_id = attrs.find("id=\"").and_then(|pos| {
+            let value = &attrs[pos + 4..];
+            value.find('"').map(|end| value[..end].to_string())
+        });
+
+        if let Some(anchor_id) = anchor_id {
+            let inner = &rest[tag_end + 1..close];
+            let mut heading_text = String::new();
+            let mut in_tag = false;
+            for c in inner.chars() {
+                match c {
+                    '<' => in_tag = true,
+                    '>' => in_tag = false,
+                    _ if !in_tag => heading_text.push(c),
+                    _ => {}
+                }
+            }
+            anchors.push(DocumentAnchor {
+                anchor_id,
+                heading_text: heading_text
+                    .split_whitespace()
+                    .collect::<Vec<_>>()
+                    .join(" "),
+                level: level as i32,
+                position: anchors.len() as i32,
+            });
+        }
+
+        rest = &rest[close + close_tag.len()..];
+    }
+
+    anchors
+}
+
+/// Load heading anchors for a document, preferring the builder-emitted
+/// document_anchors table and falling back to parsing the rendered HTML.
+pub fn document_anchors(
+    conn: &Connection,
+    document_id: i32,
+) -> Result<Vec<DocumentAnchor>, String> {
+    if ai::table_exists(conn, "document_anchors") {
+        let mut stmt = conn
+            .prepare_cached(
+                "SELECT anchor_id, heading_text, level, position \
+                 FROM document_anchors \
+                 WHERE document_id = ?1 \
+                 ORDER BY position",
+            )
+            .map_err(|e| e.to_string())?;
+        let rows = stmt
+            .query_map([document_id], |row| {
+                Ok(DocumentAnchor {
+                    anchor_id: row.get(0)?,
+                    heading_text: row.get(1)?,
+                    level: row.get(2)?,
+                    position: row.get(3)?,
+                })
+            })
+            .map_err(|e| e.to_string())?;
+        return rows
+            .collect::<Result<Vec<_>, _>>()
+            .map_err(|e| e.to_string());
+    }
+
+    let content_html: String = conn
+        .query_row(
+            "SELECT content_html FROM documents WHERE id = ?1",
+            [document_id],
+            |row| row.get(0),
+        )
+        .map_err(|e| e.to_string())?;
+    Ok(parse_html_headings(&content_html))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{
+        collapse_similar_titles, collections, document_word_counts, normalize_title,
+        reading_minutes, tag_coverage, tags, CollectionSort, TagSort,
+    };
+    use crate::models::SearchResult;
+    use rusqlite::Connection;
+
+    fn project_db() -> Connection {
+        let db = Connection::open_in_memory().expect("open in-memory sqlite");
+        db.execute_batch(
+            "CREATE TABLE collections (
+                id TEXT PRIMARY KEY,
+                name TEXT NOT NULL,
+                icon TEXT NOT NULL DEFAULT '',
+                description TEXT,
+                sort_order INTEGER NOT NULL DEFAULT 0
+            );
+            CREATE TABLE documents (
+                id INTEGER PRIMARY KEY,
+                collection_id TEXT NOT NULL,
+                slug TEXT NOT NULL,
+                last_modified TEXT
+            );
+            CREATE TABLE tags (id INTEGER PRIMARY KEY, tag TEXT NOT NULL);
+            CREATE TABLE document_tags (document_id INTEGER NOT NULL, tag_id INTEGER NOT NULL);
+
+            INSERT INTO collections VALUES
+                ('guides', 'Guides', '', NULL, 1),
+                ('adr', 'ADRs', '', NULL, 2),
+                ('empty', 'Empty', '', NULL, 3),
+                ('undated', 'undated notes', '', NULL, 4),
+                ('ops', 'Ops', '', NULL, 5);
+            INSERT INTO documents VALUES
+                (1, 'guides', 'guides/setup', '2026-01-10T09:00:00Z'),
+                (2, 'guides', 'guides/deploys', NULL),
+                (3, 'adr', 'adr/001', '2026-03-02T12:00:00Z'),
+                (4, 'undated', 'undated/a', NULL),
+                (5, 'undated', 'undated/b', ''),
+                (6, 'ops', 'ops/on-call', '2026-02-14T08:30:00Z');
+            INSERT INTO tags VALUES (1, 'ops'), (2, 'Deploys'), (3, 'api');
+            INSERT INTO document_tags VALUES (1, 1), (2, 1), (3, 1), (1, 2), (2, 2), (3, 3);",
+        )
+        .expect("create project tables");
+        db
+    }
+
+    fn ids(db: &Connection, sort: CollectionSort) -> Vec<String> {
+        collections(db, sort)
+            .unwrap()
+            .into_iter()
+            .map(|c| c.id)
+            .collect()
+    }
+
+    #[test]
+    fn recent_collections_put_undated_ones_last() {
+        let db = project_db();
+        assert_eq!(
+            ids(&db, CollectionSort::Recent),
+            ["adr", "ops", "guides", "empty", "undated"]
+        );
+        assert_eq!(
+            ids(&db, CollectionSort::SortOrder),
+            ["guides", "adr", "empty", "undated", "ops"]
+        );
+        assert_eq!(
+            ids(&db, CollectionSort::Name),
+            ["adr", "empty", "guides", "ops", "undated"]
+        );
+        assert!(CollectionSort::parse("newest").is_err());
+    }
+
+    #[test]
+    fn tags_sort_by_count_or_name() {
+        let db = project_db();
+        let names = |collection_id, sort| {
+            tags(&db, collection_id, sort)
+                .unwrap()
+                .into_iter()
+                .map(|t| t.tag)
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(names(None, TagSort::Count), ["ops", "Deploys", "api"]);
+        assert_eq!(names(None, TagSort::Alpha), ["api", "Deploys", "ops"]);
+        assert_eq!(names(Some("adr"), TagSort::Alpha), ["api", "ops"]);
+    }
+
+    #[test]
+    fn tag_coverage_separates_untagged_from_dangling() {
+        let db = project_db();
+        db.execute_batch("INSERT INTO document_tags VALUES (4, 99), (6, 1), (6, 98);")
+            .unwrap();
+        let coverage: Vec<(String, i64, i64, i64)> = tag_coverage(&db)
+            .unwrap()
+            .into_iter()
+            .map(|c| {
+                (
+                    c.collection_id,
+                    c.document_count,
+                    c.untagged_count,
+                    c.dangling_tag_count,
+                )
+            })
+            .collect();
+        assert_eq!(
+            coverage,
+            [
+                ("guides".to_string(), 2, 0, 0),
+                ("adr".to_string(), 1, 0, 0),
+                ("empty".to_string(), 0, 0, 0),
+                ("undated".to_string(), 2, 1, 1),
+                ("ops".to_string(), 1, 0, 1),
+            ]
+        );
+    }
+
+    #[test]
+    fn titles_normalize_without_case_punctuation_or_versions() {
+        assert_eq!(
+            normalize_title("Endpoint Reference (v2)"),
+            "endpoint reference"
+        );
+        assert_eq!(
+            normalize_title("Deploys: Blue/Green 1.4.0"),
+            "deploys blue green"
+        );
+        assert_eq!(normalize_title("  Don't   panic! "), "don t panic");
+        assert_eq!(normalize_title("Python 3"), "python 3");
+        assert_eq!(normalize_title("Python 3.12"), "python");
+        assert_eq!(normalize_title("v2"), "v2");
+        assert_eq!(normalize_title("Release 2024 notes"), "release 2024 notes");
+    }
+
+    #[test]
+    fn similar_titles_collapse_into_the_best_ranked() {
+        let result = |slug: &str, title: &str| SearchResult {
+            slug: slug.to_string(),
+            title: title.to_string(),
+            section: String::new(),
+            collection_id: "api".to_string(),
+            snippet: String::new(),
+            context: String::new(),
+            collapsed_count: 0,
+            collapsed_slugs: Vec::new(),
+        };
+        let collapsed = collapse_similar_titles(vec![
+            result("api/users", "Endpoint reference: users"),
+            result("guides/setup", "Setup"),
+            result("api/orders", "Endpoint Reference: orders"),
+            result("api/index", "Endpoint reference v2"),
+            result("guides/setup-v1", "Setup (v1)"),
+            result("guides/setup-ci", "Setup CI"),
+        ]);
+        let summary: Vec<(&str, usize, Vec<&str>)> = collapsed
+            .iter()
+            .map(|r| {
+                let slugs = r.collapsed_slugs.iter().map(String::as_str).collect();
+                (r.slug.as_str(), r.collapsed_count, slugs)
+            })
+            .collect();
+        assert_eq!(
+            summary,
+            [
+                ("api/users", 2, vec!["api/orders", "api/index"]),
+                ("guides/setup", 1, vec!["guides/setup-v1"]),
+                ("guides/setup-ci", 0, vec![]),
+            ]
+        );
+    }
+
+    #[test]
+    fn word_counts_come_from_the_build_or_the_rendered_text() {
+        let db = Connection::open_in_memory().unwrap();
+        db.execute_batch(
+            "CREATE TABLE documents (slug TEXT NOT NULL, content_html TEXT NOT NULL);
+            INSERT INTO documents VALUES
+                ('guides/setup', '<h1>Setup</h1><p>Run <code>make   install</code>.</p>'),
+                ('guides/empty', '<script>var skipped = 1;</script>');",
+        )
+        .unwrap();
+        let counts = document_word_counts(&db).unwrap();
+        assert_eq!(counts["guides/setup"], 4);
+        assert_eq!(counts["guides/empty"], 0);
+
+        db.execute_batch(
+            "ALTER TABLE documents ADD COLUMN word_count INTEGER NOT NULL DEFAULT 0;
+            UPDATE documents SET word_count = 450 WHERE slug = 'guides/setup';",
+        )
+        .unwrap();
+        assert_eq!(document_word_counts(&db).unwrap()["guides/setup"], 450);
+
+        assert_eq!(reading_minutes(0), 0);
+        assert_eq!(reading_minutes(1), 1);
+        assert_eq!(reading_minutes(450), 3);
+    }
+}
@@ -0,0 +1,171 @@
+use crate::models::AppPreferences;
+use tauri::menu::{Menu, MenuItem, PredefinedMenuItem};
+use tauri::tray::TrayIconBuilder;
+use tauri::{AppHandle, Emitter, Manager, PhysicalPosition, WebviewUrl, WebviewWindowBuilder};
+use tauri_plugin_global_shortcut::{GlobalShortcutExt, ShortcutState};
+
+/// Window label for the quick-search popup (excluded from window-state restore).
+pub const QUICK_SEARCH_LABEL: &str = "quick-search";
+pub const DEFAULT_QUICK_SEARCH_SHORTCUT: &str = "CmdOrCtrl+Shift+Space";
+
+const TRAY_ID: &str = "dalil-tray";
+const QUICK_SEARCH_WIDTH: f64 = 640.0;
+const QUICK_SEARCH_HEIGHT: f64 = 420.0;
+
+fn shortcut_for(preferences: &AppPreferences) -> &str {
+    preferences
+        .quick_search_shortcut
+        .as_deref()
+        .map(str::trim)
+        .filter(|s| !s.is_empty())
+        .unwrap_or(DEFAULT_QUICK_SEARCH_SHORTCUT)
+}
+
+/// Build the tray icon and its menu. The tray is always created and then
+/// shown or hidden to follow the quick-search preference.
+pub fn setup_tray(app: &AppHandle, preferences: &AppPreferences) -> Result<(), String> {
+    let quick_search = MenuItem::with_id(
+        app,
+        "quick-search",
+        "Quick Search",
+        true,
+        Some(shortcut_for(preferences)),
+    )
+    .map_err(|e| e.to_string())?;
+    let show = MenuItem::with_id(app, "show", "Show Dalil", true, None::<&str>)
+        .map_err(|e| e.to_string())?;
+    let separator = PredefinedMenuItem::separator(app).map_err(|e| e.to_string())?;
+    let quit =
+        MenuItem::with_id(app, "quit", "Quit", true, None::<&str>).map_err(|e| e.to_string())?;
+    let menu = Menu::with_items(app, &[&quick_search, &show, &separator, &quit])
+        .map_err(|e| e.to_string())?;
+
+    let mut builder = TrayIconBuilder::with_id(TRAY_ID)
+        .tooltip("Dalil")
+        .menu(&menu)
+        .show_menu_on_left_click(true)
+        .on_menu_event(|app, event| match event.id.as_ref() {
+            "quick-search" => {
+                if let Err(e) = show_quick_search(app) {
+                    eprintln!("Warning: failed to open quick search: {}", e);
+                }
+            }
+            "show" => focus_main_window(app),
+            "quit" => app.exit(0),
+            _ => {}
+        });
+    if let Some(icon) = app.default_window_icon() {
+        builder = builder.icon(icon.clone());
+    }
+    let tray = builder.build(app).map_err(|e| e.to_string())?;
+    tray.set_visible(preferences.quick_search_enabled)
+        .map_err(|e| e.to_string())
+}
+
+/// Register (or clear) the global shortcut to match the preferences. Returns an
+/// error when the OS refuses the shortcut, typically because another app owns it.
+pub fn apply_preferences(app: &AppHandle, preferences: &AppPreferences) -> Result<(), String> {
+    let shortcuts = app.global_shortcut();
+    shortcuts.unregister_all().map_err(|e| e.to_string())?;
+
+    if let Some(tray) = app.tray_by_id(TRAY_ID) {
+        tray.set_visible(preferences.quick_search_enabled)
+            .map_err(|e| e.to_string())?;
+    }
+    if !preferences.quick_search_enabled {
+        return Ok(());
+    }
+
+    let shortcut = shortcut_for(preferences);
+    shortcuts
+        .on_shortcut(shortcut, |app, _shortcut, event| {
+            if event.state == ShortcutState::Pressed {
+                if let Err(e) = toggle_quick_search(app) {
+                    eprintln!("Warning: failed to toggle quick search: {}", e);
+                }
+            }
+        })
+        .map_err(|e| {
+            format!(
+                "Shortcut '{}' could not be registered (it may be in use by another app): {}",
+                shortcut, e
+            )
+        })
+}
+
+fn toggle_quick_search(app: &AppHandle) -> Result<(), String> {
+    match app.get_webview_window(QUICK_SEARCH_LABEL) {
+        Some(window) if window.is_visible().unwrap_or(false) => {
+            window.hide().map_err(|e| e.to_string())
+        }
+        _ => show_quick_search(app),
+    }
+}
+
+/// Show the quick-search window, creating it on first use. It is positioned
+/// near the top of whichever monitor the main window is on and hides itself
+/// when it loses focus.
+pub fn show_quick_search(app: &AppHandle) -> Result<(), String> {
+    let window = match app.get_webview_window(QUICK_SEARCH_LABEL) {
+        Some(window) => window,
+        None => {
+            let window = WebviewWindowBuilder::new(
+                app,
+                QUICK_SEARCH_LABEL,
+                WebviewUrl::App("quick-search.html".into()),
+            )
+            .title("Quick Search")
+            .inner_size(QUICK_SEARCH_WIDTH, QUICK_SEARCH_HEIGHT)
+            .resizable(false)
+            .decorations(false)
+            .always_on_top(true)
+            .skip_taskbar(true)
+            .visible(false)
+            .build()
+            .map_err(|e| e.to_string())?;
+            let handle = window.clone();
+            window.on_window_event(move |event| {
+                if let tauri::WindowEvent::Focused(false) = event {
+                    let _ = handle.hide();
+                }
+            });
+            window
+        }
+    };
+
+    let monitor = app
+        .get_webview_window("main")
+        .and_then(|main| main.current_monitor().ok().flatten())
+        .or_else(|| app.primary_monitor().ok().flatten());
+    if let Some(monitor) = monitor {
+        let scale = monitor.scale_factor();
+        let width = (QUICK_SEARCH_WIDTH * scale) as i32;
+        let origin = monitor.position();
+        let size = monitor.size();
+        let x = origin.x + (size.width as i32 - width) / 2;
+        let y = origin.y + size.height as i32 / 5;
+        window
+            .set_position(PhysicalPosition::new(x, y))
+            .map_err(|e| e.to_string())?;
+    }
+
+    window.show().map_err(|e| e.to_string())?;
+    window.set_focus().map_err(|e| e.to_string())?;
+    let _ = window.emit("quick-search-shown", ());
+    Ok(())
+}
+
+pub fn hide_quick_search(app: &AppHandle) -> Result<(), String> {
+    if let Some(window) = app.get_webview_window(QUICK_SEARCH_LABEL) {
+        window.hide().map_err(|e| e.to_string())?;
+    }
+    Ok(())
+}
+
+pub fn focus_main_window(app: &AppHandle) {
+    if let Some(main) = app.get_webview_window("main") {
+        let _ = main.unminimize();
+        let _ = main.show();
+        let _ = main.set_focus();
+    }
+}
@@ -0,0 +1,164 @@
+//! Traces of the RAG pipeline, one JSON file per question under `logs/rag` in
+//! the app data dir, so a wrong answer can be traced back to what retrieval
+//! found. Files are named `{timestamp}-{request_id}.json` and only the newest
+//! `RAG_TRACES_KEPT` are kept. Written only with the `ai_rag_traces`
+//! preference.
+
+use crate::models::RagTrace;
+use std::path::{Path, PathBuf};
+
+pub const RAG_TRACES_KEPT: usize = 100;
+
+/// Characters of each chunk kept in a trace unless it is verbose.
+const TRACE_EXCERPT_CHARS: usize = 160;
+
+/// UTC write time, to the millisecond, so names sort chronologically.
+const TIMESTAMP_FORMAT: &str = "%Y%m%d%H%M%S%3f";
+const TIMESTAMP_LEN: usize = 17;
+
+pub fn traces_dir(app_data_dir: &Path) -> PathBuf {
+    app_data_dir.join("logs").join("rag")
+}
+
+/// Chunk text as stored in a trace: whole when `verbose`, else its start.
+pub fn trace_text(text: &str, verbose: bool) -> String {
+    if verbose || text.chars().count() <= TRACE_EXCERPT_CHARS {
+        return text.to_string();
+    }
+    let mut excerpt: String = text.chars().take(TRACE_EXCERPT_CHARS).collect();
+    excerpt.push('…');
+    excerpt
+}
+
+/// Request ids become file names, so only plain ones are traced.
+fn valid_request_id(request_id: &str) -> bool {
+    !request_id.is_empty()
+        && request_id.len() <= 128
+        && request_id
+            .bytes()
+            .all(|b| b.is_ascii_alphanumeric() || b == b'-' || b == b'_')
+}
+
+/// Write `trace` and delete all but the newest `RAG_TRACES_KEPT`.
+pub fn write_trace(dir: &Path, trace: &RagTrace) -> Result<PathBuf, String> {
+    if !valid_request_id(&trace.request_id) {
+        return Err(format!("'{}' is not a valid request id", trace.request_id));
+    }
+    std::fs::create_dir_all(dir).map_err(|e| e.to_string())?;
+    let timestamp = chrono::Utc::now().format(TIMESTAMP_FORMAT);
+    let path = dir.join(format!("{}-{}.json", timestamp, trace.request_id));
+    let json = serde_json::to_string_pretty(trace).map_err(|e| e.to_string())?;
+    std::fs::write(&path, json).map_err(|e| format!("Failed to write RAG trace: {}", e))?;
+
+    for old in trace_files(dir).iter().skip(RAG_TRACES_KEPT) {
+        let _ = std::fs::remove_file(old);
+    }
+    Ok(path)
+}
+
+/// The newest trace for `request_id`, if one was kept.
+pub fn read_trace(dir: &Path, request_id: &str) -> Result<Option<RagTrace>, String> {
+    if !valid_request_id(request_id) {
+        return Err(format!("'{}' is not a valid request id", request_id));
+    }
+    let Some(path) = trace_files(dir)
+        .into_iter()
+        .find(|path| trace_request_id(path) == Some(request_id))
+    else {
+        return Ok(None);
+    };
+    let json = std::fs::read_to_string(&path).map_err(|e| e.to_string())?;
+    serde_json::from_str(&json)
+        .map(Some)
+        .map_err(|e| format!("Failed to read RAG trace: {}", e))
+}
+
+/// The request id in a trace file's name.
+fn trace_request_id(path: &Path) -> Option<&str> {
+    let name = path.file_name()?.to_str()?.strip_suffix(".json")?;
+    let (timestamp, request_id) = name.split_at_checked(TIMESTAMP_LEN)?;
+    if !timestamp.bytes().all(|b| b.is_ascii_digit()) {
+        return None;
+    }
+    request_id.strip_prefix('-')
+}
+
+/// Paths of all traces, newest first.
+fn trace_files(dir: &Path) -> Vec<PathBuf> {
+    let Ok(entries) = std::fs::read_dir(dir) else {
+        return vec![];
+    };
+    let mut traces: Vec<PathBuf> = entries
+        .filter_map(Result::ok)
+        .map(|entry| entry.path())
+        .filter(|path| trace_request_id(path).is_some())
+        .collect();
+    traces.sort();
+    traces.reverse();
+    traces
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{read_trace, trace_text, write_trace, RAG_TRACES_KEPT};
+    use crate::models::{RagTrace, RetrievalConfig};
+    use crate::test_support::scratch_dir;
+
+    fn trace(request_id: &str) -> RagTrace {
+        RagTrace {
+            request_id: request_id.to_string(),
+            created_at: 0,
+            project_id: "p".to_string(),
+            question: "How do deploys work?".to_string(),
+            provider: "ollama".to_string(),
+            embedding_cached: false,
+            embedding_error: None,
+            retrieval: RetrievalConfig::default(),
+            excluded_collections: vec![],
+            vector_candidates: vec![],
+            fts_candidates: vec![],
+            merged: vec![],
+            personal_context_count: 0,
+            best_score: None,
+            min_score: 0.3,
+            grounded: false,
+            prompt_messages: 0,
+            prompt_chars: 0,
+            prompt_tokens_estimate: 0,
+            verbose: false,
+        }
+    }
+
+    #[test]
+    fn traces_are_found_by_request_id_and_pruned_to_the_newest() {
+        let dir = scratch_dir("rag-traces");
+        for n in 0..RAG_TRACES_KEPT + 2 {
+            let name = format!("20000101000000{:03}-old-{}.json", n, n);
+            std::fs::write(dir.join(name), "{}").unwrap();
+        }
+        std::fs::write(dir.join("notes.json"), "{}").unwrap();
+
+        write_trace(&dir, &trace("req-1")).unwrap();
+        let traces = std::fs::read_dir(&dir).unwrap().count();
+        assert_eq!(traces, RAG_TRACES_KEPT + 1);
+        assert!(!dir.join("20000101000000000-old-0.json").exists());
+        assert!(dir.join("notes.json").exists());
+
+        let found = read_trace(&dir, "req-1").unwrap().unwrap();
+        assert_eq!(found.question, "How do deploys work?");
+        assert!(read_trace(&dir, "req-2").unwrap().is_none());
+        assert!(read_trace(&dir, "../req-1").is_err());
+        assert!(write_trace(&dir, &trace("a/b")).is_err());
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn chunk_text_is_cut_to_an_excerpt_unless_verbose() {
+        let long = "word ".repeat(100);
+        let excerpt = trace_text(&long, false);
+        assert_eq!(excerpt.chars().count(), 161);
+        assert!(excerpt.ends_with('…'));
+        assert_eq!(trace_text(&long, true), long);
+        assert_eq!(trace_text("short", false), "short");
+    }
+}
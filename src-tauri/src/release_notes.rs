@@ -0,0 +1,160 @@
+//! Release notes for the About dialog, read from the updater's latest.json or
+//! a configured feed of releases. The last response is cached with its ETag so
+//! the notes stay readable offline.
+
+use crate::models::ReleaseNote;
+use serde::{Deserialize, Serialize};
+use std::time::Duration;
+
+const FETCH_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// The last feed response, kept in the settings store.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ReleaseNotesCache {
+    pub url: String,
+    pub etag: Option<String>,
+    /// Unix seconds of the last successful request, including 304s
+    pub fetched_at: i64,
+    pub entries: Vec<ReleaseNote>,
+}
+
+pub enum Fetched {
+    NotModified,
+    Feed { etag: Option<String>, body: String },
+}
+
+/// Request the feed, sending `etag` so an unchanged feed costs a 304.
+pub async fn fetch(
+    client: &reqwest::Client,
+    url: &str,
+    etag: Option<&str>,
+) -> Result<Fetched, String> {
+    let mut request = client.get(url).timeout(FETCH_TIMEOUT);
+    if let Some(etag) = etag {
+        request = request.header(reqwest::header::IF_NONE_MATCH, etag);
+    }
+    let response = request.send().await.map_err(|e| e.to_string())?;
+    if response.status() == reqwest::StatusCode::NOT_MODIFIED {
+        return Ok(Fetched::NotModified);
+    }
+    if !response.status().is_success() {
+        return Err(format!(
+            "Release notes request failed ({})",
+            response.status()
+        ));
+    }
+    let etag = response
+        .headers()
+        .get(reqwest::header::ETAG)
+        .and_then(|v| v.to_str().ok())
+        .map(str::to_string);
+    let body = response.text().await.map_err(|e| e.to_string())?;
+    Ok(Fetched::Feed { etag, body })
+}
+
+/// Entries in a feed: either one updater manifest (`version`, `notes`,
+/// `pub_date`) or an array of releases in that shape or GitHub's (`tag_name`,
+/// `body`, `published_at`).
+pub fn parse_feed(body: &str) -> Result<Vec<ReleaseNote>, String> {
+    let value: serde_json::Value =
+        serde_json::from_str(body).map_err(|e| format!("Unreadable release feed: {}", e))?;
+    let releases = match value {
+        serde_json::Value::Array(releases) => releases,
+        manifest => vec![manifest],
+    };
+    Ok(releases.iter().filter_map(release_note).collect())
+}
+
+fn release_note(release: &serde_json::Value) -> Option<ReleaseNote> {
+    let text = |keys: &[&str]| {
+        keys.iter()
+            .find_map(|key| release.get(*key).and_then(serde_json::Value::as_str))
+            .map(str::to_string)
+    };
+    let version = text(&["version", "tag_name"])?;
+    Some(ReleaseNote {
+        version: version.trim_start_matches('v').to_string(),
+        date: text(&["pub_date", "published_at"]),
+        body: text(&["notes", "body"]).unwrap_or_default(),
+    })
+}
+
+/// Entries for `current_version` and newer, newest first.
+pub fn notes_since(entries: &[ReleaseNote], current_version: &str) -> Vec<ReleaseNote> {
+    let current = version_key(current_version);
+    let mut notes: Vec<ReleaseNote> = entries
+        .iter()
+        .filter(|entry| version_key(&entry.version) >= current)
+        .cloned()
+        .collect();
+    notes.sort_by_key(|note| std::cmp::Reverse(version_key(&note.version)));
+    notes
+}
+
+/// Numeric parts of a version such as "1.4.2" or "v1.5.0-beta.1"; anything
+/// after a `-` or `+` is ignored.
+fn version_key(version: &str) -> Vec<u64> {
+    version
+        .trim_start_matches('v')
+        .split(['-', '+'])
+        .next()
+        .unwrap_or_default()
+        .split('.')
+        .map(|part| part.parse().unwrap_or(0))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{notes_since, parse_feed};
+
+    #[test]
+    fn reads_updater_manifests_and_release_lists() {
+        let manifest = r#"{
+            "version": "v1.4.0",
+            "notes": "- Faster search",
+            "pub_date": "2026-09-01T10:00:00Z",
+            "platforms": {}
+        }"#;
+        let entries = parse_feed(manifest).unwrap();
+        assert_eq!(entries.len(), 1);
+        assert_eq!(entries[0].version, "1.4.0");
+        assert_eq!(entries[0].date.as_deref(), Some("2026-09-01T10:00:00Z"));
+        assert_eq!(entries[0].body, "- Faster search");
+
+        let releases = r#"[
+            {"tag_name": "v1.3.0", "body": "Older", "published_at": "2026-08-01T00:00:00Z"},
+            {"name": "untagged draft"},
+            {"version": "1.2.0"}
+        ]"#;
+        let entries = parse_feed(releases).unwrap();
+        assert_eq!(
+            entries
+                .iter()
+                .map(|e| e.version.as_str())
+                .collect::<Vec<_>>(),
+            ["1.3.0", "1.2.0"]
+        );
+        assert_eq!(entries[1].body, "");
+        assert!(parse_feed("<html>").is_err());
+    }
+
+    #[test]
+    fn keeps_current_and_newer_versions_newest_first() {
+        let entries = parse_feed(
+            r#"[
+                {"version": "1.9.0"},
+                {"version": "1.10.0"},
+                {"version": "1.8.2"},
+                {"version": "1.10.1-beta.1"}
+            ]"#,
+        )
+        .unwrap();
+        let versions: Vec<String> = notes_since(&entries, "1.9.0")
+            .into_iter()
+            .map(|e| e.version)
+            .collect();
+        assert_eq!(versions, ["1.10.1-beta.1", "1.10.0", "1.9.0"]);
+    }
+}
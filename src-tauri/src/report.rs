@@ -0,0 +1,265 @@
+//! Flat reports of a project's documents, tags, bookmarks and annotations for
+//! spreadsheets. Rows are written as they are read, so exports of thousands of
+//! documents never sit in memory as one string.
+
+use serde_json::Value;
+use std::io::Write;
+
+/// Marks a CSV file as UTF-8 for Excel, which otherwise assumes the system
+/// code page.
+const UTF8_BOM: &[u8] = b"\xEF\xBB\xBF";
+
+/// First characters that make spreadsheets read a cell as a formula.
+const FORMULA_PREFIXES: [char; 4] = ['=', '+', '-', '@'];
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ReportKind {
+    Documents,
+    Tags,
+    Bookmarks,
+    Annotations,
+}
+
+impl ReportKind {
+    pub fn parse(kind: &str) -> Result<Self, String> {
+        match kind {
+            "documents" => Ok(ReportKind::Documents),
+            "tags" => Ok(ReportKind::Tags),
+            "bookmarks" => Ok(ReportKind::Bookmarks),
+            "annotations" => Ok(ReportKind::Annotations),
+            other => Err(format!("Unknown report kind '{}'", other)),
+        }
+    }
+
+    pub fn columns(self) -> &'static [&'static str] {
+        match self {
+            ReportKind::Documents => &[
+                "slug",
+                "title",
+                "collection_id",
+                "section",
+                "last_modified",
+                "word_count",
+            ],
+            ReportKind::Tags => &["tag", "count"],
+            ReportKind::Bookmarks => &[
+                "id",
+                "collection_id",
+                "doc_slug",
+                "anchor_id",
+                "title",
+                "created_at",
+                "updated_at",
+                "last_opened_at",
+                "open_count",
+                "is_favorite",
+            ],
+            ReportKind::Annotations => &[
+                "kind",
+                "doc_slug",
+                "anchor_id",
+                "text",
+                "context",
+                "updated_at",
+            ],
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ReportFormat {
+    Csv,
+    Json,
+}
+
+impl ReportFormat {
+    pub fn parse(format: &str) -> Result<Self, String> {
+        match format {
+            "csv" => Ok(ReportFormat::Csv),
+            "json" => Ok(ReportFormat::Json),
+            other => Err(format!("Unknown report format '{}'", other)),
+        }
+    }
+}
+
+/// Writes report rows one at a time: CSV with a header line, or a JSON array
+/// of objects keyed by column.
+pub struct ReportWriter<W: Write> {
+    out: W,
+    format: ReportFormat,
+    columns: &'static [&'static str],
+    rows: usize,
+}
+
+impl<W: Write> ReportWriter<W> {
+    /// Start a report. `excel_bom` prefixes CSV output with a UTF-8 BOM.
+    pub fn new(
+        mut out: W,
+        format: ReportFormat,
+        columns: &'static [&'static str],
+        excel_bom: bool,
+    ) -> Result<Self, String> {
+        match format {
+            ReportFormat::Csv => {
+                if excel_bom {
+                    out.write_all(UTF8_BOM).map_err(|e| e.to_string())?;
+                }
+                let header: Vec<Value> = columns.iter().map(|c| Value::from(*c)).collect();
+                write_csv_line(&mut out, &header)?;
+            }
+            ReportFormat::Json => out.write_all(b"[").map_err(|e| e.to_string())?,
+        }
+        Ok(ReportWriter {
+            out,
+            format,
+            columns,
+            rows: 0,
+        })
+    }
+
+    /// Write one row, with a value for each column.
+    pub fn row(&mut self, values: &[Value]) -> Result<(), String> {
+        if values.len() != self.columns.len() {
+            return Err(format!(
+                "Report row has {} values for {} columns",
+                values.len(),
+                self.columns.len()
+            ));
+        }
+        match self.format {
+            ReportFormat::Csv => write_csv_line(&mut self.out, values)?,
+            ReportFormat::Json => {
+                // Written by hand to keep the keys in column order
+                let separator: &[u8] = if self.rows == 0 { b"\n{" } else { b",\n{" };
+                self.out.write_all(separator).map_err(|e| e.to_string())?;
+                for (i, (column, value)) in self.columns.iter().zip(values).enumerate() {
+                    if i > 0 {
+                        self.out.write_all(b",").map_err(|e| e.to_string())?;
+                    }
+                    serde_json::to_writer(&mut self.out, column).map_err(|e| e.to_string())?;
+                    self.out.write_all(b":").map_err(|e| e.to_string())?;
+                    serde_json::to_writer(&mut self.out, value).map_err(|e| e.to_string())?;
+                }
+                self.out.write_all(b"}").map_err(|e| e.to_string())?;
+            }
+        }
+        self.rows += 1;
+        Ok(())
+    }
+
+    /// Close the report and return the number of rows written.
+    pub fn finish(mut self) -> Result<usize, String> {
+        if self.format == ReportFormat::Json {
+            let end: &[u8] = if self.rows == 0 { b"]\n" } else { b"\n]\n" };
+            self.out.write_all(end).map_err(|e| e.to_string())?;
+        }
+        self.out.flush().map_err(|e| e.to_string())?;
+        Ok(self.rows)
+    }
+}
+
+fn write_csv_line(out: &mut impl Write, values: &[Value]) -> Result<(), String> {
+    let line = values.iter().map(csv_field).collect::<Vec<_>>().join(",");
+    out.write_all(line.as_bytes())
+        .and_then(|_| out.write_all(b"\r\n"))
+        .map_err(|e| e.to_string())
+}
+
+/// A value as a CSV field, quoted when it holds a delimiter, quote, line
+/// break or edge whitespace. Null is an empty field. Text that a spreadsheet
+/// would run as a formula gets a leading `'` so it opens as text.
+fn csv_field(value: &Value) -> String {
+    let text = match value {
+        Value::Null => return String::new(),
+        Value::String(s) if s.starts_with(FORMULA_PREFIXES) => format!("'{}", s),
+        Value::String(s) => s.clone(),
+        other => other.to_string(),
+    };
+    let needs_quotes = text.contains([',', '"', '\n', '\r'])
+        || text.starts_with(char::is_whitespace)
+        || text.ends_with(char::is_whitespace);
+    if needs_quotes {
+        format!("\"{}\"", text.replace('"', "\"\""))
+    } else {
+        text
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{ReportFormat, ReportKind, ReportWriter};
+    use serde_json::{json, Value};
+
+    fn write(format: ReportFormat, excel_bom: bool, rows: &[Vec<Value>]) -> Vec<u8> {
+        let mut out = Vec::new();
+        let mut writer =
+            ReportWriter::new(&mut out, format, ReportKind::Tags.columns(), excel_bom).unwrap();
+        for row in rows {
+            writer.row(row).unwrap();
+        }
+        assert_eq!(writer.finish().unwrap(), rows.len());
+        out
+    }
+
+    #[test]
+    fn csv_quotes_fields_that_need_it() {
+        let rows = vec![
+            vec![json!("plain"), json!(3)],
+            vec![json!("a, \"quoted\"\nline"), json!(null)],
+            vec![json!(" café "), json!(true)],
+        ];
+        let out = write(ReportFormat::Csv, true, &rows);
+        assert_eq!(
+            String::from_utf8(out).unwrap(),
+            "\u{feff}tag,count\r\nplain,3\r\n\"a, \"\"quoted\"\"\nline\",\r\n\" café \",true\r\n"
+        );
+        let out = write(ReportFormat::Csv, false, &[]);
+        assert_eq!(out, b"tag,count\r\n");
+    }
+
+    #[test]
+    fn csv_cells_never_start_a_formula() {
+        let rows = vec![
+            vec![json!("=HYPERLINK(\"http://x.test\")"), json!(-3)],
+            vec![json!("+1"), json!("@SUM(A1)")],
+            vec![json!("-, list"), json!("a=b")],
+        ];
+        let out = write(ReportFormat::Csv, false, &rows);
+        assert_eq!(
+            String::from_utf8(out).unwrap(),
+            "tag,count\r\n\"'=HYPERLINK(\"\"http://x.test\"\")\",-3\r\n'+1,'@SUM(A1)\r\n\"'-, list\",a=b\r\n"
+        );
+    }
+
+    #[test]
+    fn json_is_an_array_of_objects() {
+        let rows = vec![
+            vec![json!("deploys"), json!(2)],
+            vec![json!("on-call"), json!(1)],
+        ];
+        let out = write(ReportFormat::Json, true, &rows);
+        assert!(String::from_utf8(out.clone())
+            .unwrap()
+            .starts_with("[\n{\"tag\":\"deploys\",\"count\":2}"));
+        let parsed: Value = serde_json::from_slice(&out).unwrap();
+        assert_eq!(
+            parsed,
+            json!([{"tag": "deploys", "count": 2}, {"tag": "on-call", "count": 1}])
+        );
+        let empty: Value = serde_json::from_slice(&write(ReportFormat::Json, false, &[])).unwrap();
+        assert_eq!(empty, json!([]));
+    }
+
+    #[test]
+    fn rows_must_match_columns() {
+        let mut out = Vec::new();
+        let mut writer = ReportWriter::new(
+            &mut out,
+            ReportFormat::Csv,
+            ReportKind::Tags.columns(),
+            false,
+        )
+        .unwrap();
+        assert!(writer.row(&[json!("only one")]).is_err());
+    }
+}
@@ -1,10 +1,12 @@
-use crate::models::{AppPreferences, Settings};
+use crate::models::{AppPreferences, RetrievalConfig, Settings};
+use crate::release_notes::ReleaseNotesCache;
 use tauri::AppHandle;
 use tauri_plugin_store::StoreExt;
 
 const STORE_FILE: &str = "settings.json";
 const SETTINGS_KEY: &str = "ai_settings";
 const PREFERENCES_KEY: &str = "app_preferences";
+const RELEASE_NOTES_KEY: &str = "release_notes_cache";
 
 /// Load settings from the Tauri store.
 pub fn load_settings(app: &AppHandle) -> Result<Settings, String> {
@@ -25,6 +27,22 @@ pub fn save_settings_to_store(app: &AppHandle, settings: &Settings) -> Result<()
     Ok(())
 }
 
+/// Check a retrieval configuration, global or per project, before it is
+/// saved: it needs candidates from at least one search, and finite scores
+/// with a boost that can't push full-text matches down.
+pub fn validate_retrieval(config: &RetrievalConfig) -> Result<(), String> {
+    if config.vector_candidates == 0 && config.fts_candidates == 0 {
+        return Err("Retrieval needs vector or full-text candidates".to_string());
+    }
+    if !config.fts_boost.is_finite() || !config.min_vector_score.is_finite() {
+        return Err("Retrieval scores must be finite numbers".to_string());
+    }
+    if config.fts_boost < 0.0 {
+        return Err("The full-text boost can't be negative".to_string());
+    }
+    Ok(())
+}
+
 /// Return settings with API keys masked for display in the frontend.
 pub fn mask_settings(settings: &Settings) -> Settings {
     Settings {
@@ -35,6 +53,11 @@ pub fn mask_settings(settings: &Settings) -> Settings {
         preferred_provider: settings.preferred_provider.clone(),
         anthropic_model: settings.anthropic_model.clone(),
         gemini_model: settings.gemini_model.clone(),
+        max_concurrent_requests: settings.max_concurrent_requests,
+        anthropic_prompt_caching: settings.anthropic_prompt_caching,
+        embedding_requests_per_minute: settings.embedding_requests_per_minute,
+        answer_language: settings.answer_language.clone(),
+        retrieval: settings.retrieval.clone(),
     }
 }
 
@@ -60,6 +83,19 @@ pub fn save_preferences_to_store(
     Ok(())
 }
 
+pub fn load_release_notes_cache(app: &AppHandle) -> Option<ReleaseNotesCache> {
+    let store = app.store(STORE_FILE).ok()?;
+    serde_json::from_value(store.get(RELEASE_NOTES_KEY)?).ok()
+}
+
+pub fn save_release_notes_cache(app: &AppHandle, cache: &ReleaseNotesCache) -> Result<(), String> {
+    let store = app.store(STORE_FILE).map_err(|e| e.to_string())?;
+    let value = serde_json::to_value(cache).map_err(|e| e.to_string())?;
+    store.set(RELEASE_NOTES_KEY, value);
+    store.save().map_err(|e| e.to_string())?;
+    Ok(())
+}
+
 fn mask_key(key: &str) -> String {
     let char_count = key.chars().count();
     if char_count <= 8 {
@@ -0,0 +1,209 @@
+//! dalil:// links to a document, optionally to a heading in it and to a
+//! passage the reader selected. The selection travels as a text directive
+//! after the anchor (`#anchor:~:text=...`), in the style of browser text
+//! fragments: short selections whole, long ones as their first and last
+//! words, which is enough to find the passage again.
+//! Mirrors buildDeepLink and parseDeepLink in src/lib/deepLinks.ts.
+
+use crate::models::SharedSelection;
+
+/// Longest selection, in characters, carried whole.
+const SELECTION_MAX_CHARS: usize = 300;
+/// Words kept from each end of a longer selection, and their most characters.
+const SELECTION_EDGE_WORDS: usize = 6;
+const SELECTION_EDGE_MAX_CHARS: usize = 80;
+
+const TEXT_DIRECTIVE: &str = ":~:text=";
+
+/// Percent-encode all but unreserved characters. Text in a directive has
+/// '-' encoded as well, since there it marks a prefix or suffix.
+fn encode_component(value: &str, keep_dash: bool) -> String {
+    value
+        .bytes()
+        .map(|b| match b {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'_' | b'.' | b'~' => (b as char).to_string(),
+            b'-' if keep_dash => "-".to_string(),
+            _ => format!("%{:02X}", b),
+        })
+        .collect()
+}
+
+fn encode_segment(value: &str) -> String {
+    encode_component(value, true)
+}
+
+/// Undo percent-encoding; `None` for malformed escapes or invalid UTF-8.
+pub(crate) fn percent_decode(value: &str) -> Option<String> {
+    let bytes = value.as_bytes();
+    let mut decoded = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        if bytes[i] == b'%' {
+            let hex = value.get(i + 1..i + 3)?;
+            decoded.push(u8::from_str_radix(hex, 16).ok()?);
+            i += 3;
+        } else {
+            decoded.push(bytes[i]);
+            i += 1;
+        }
+    }
+    String::from_utf8(decoded).ok()
+}
+
+/// Link to a document, `doc_slug` being its slug within `collection_id`.
+pub fn deep_link(project_id: &str, collection_id: &str, doc_slug: &str) -> String {
+    format!(
+        "dalil://project/{}/collection/{}/doc/{}",
+        encode_segment(project_id),
+        encode_segment(collection_id),
+        doc_slug
+            .split('/')
+            .map(encode_segment)
+            .collect::<Vec<_>>()
+            .join("/")
+    )
+}
+
+/// The selection as it is shared: whitespace collapsed, and only its first
+/// and last words when it is too long to carry whole.
+pub fn shared_selection(selected_text: &str) -> Option<SharedSelection> {
+    let words: Vec<&str> = selected_text.split_whitespace().collect();
+    if words.is_empty() {
+        return None;
+    }
+    let whole = words.join(" ");
+    if whole.chars().count() <= SELECTION_MAX_CHARS {
+        return Some(SharedSelection {
+            start: whole,
+            end: None,
+        });
+    }
+    let edge_words = words.len().min(SELECTION_EDGE_WORDS);
+    let start = words[..edge_words]
+        .join(" ")
+        .chars()
+        .take(SELECTION_EDGE_MAX_CHARS)
+        .collect();
+    let end = words[words.len() - edge_words..].join(" ");
+    let cut = end.chars().count().saturating_sub(SELECTION_EDGE_MAX_CHARS);
+    Some(SharedSelection {
+        start,
+        end: Some(end.chars().skip(cut).collect()),
+    })
+}
+
+/// A link to a document, scrolled to `anchor_id` and highlighting the
+/// selection when given.
+pub fn share_link(
+    project_id: &str,
+    collection_id: &str,
+    doc_slug: &str,
+    anchor_id: Option<&str>,
+    selection: Option<&SharedSelection>,
+) -> String {
+    let mut link = deep_link(project_id, collection_id, doc_slug);
+    if anchor_id.is_none() && selection.is_none() {
+        return link;
+    }
+    link.push('#');
+    if let Some(anchor_id) = anchor_id {
+        link.push_str(&encode_segment(anchor_id));
+    }
+    if let Some(selection) = selection {
+        link.push_str(TEXT_DIRECTIVE);
+        link.push_str(&encode_component(&selection.start, false));
+        if let Some(end) = &selection.end {
+            link.push(',');
+            link.push_str(&encode_component(end, false));
+        }
+    }
+    link
+}
+
+/// The selection carried by a link, if it has a well-formed text directive.
+/// Prefix and suffix terms (`prefix-,` and `,-suffix`) are skipped.
+pub fn parse_selection(link: &str) -> Option<SharedSelection> {
+    let (_, fragment) = link.split_once('#')?;
+    let (_, directive) = fragment.split_once(TEXT_DIRECTIVE)?;
+    let directive = directive.split('&').next().unwrap_or("");
+    let terms: Vec<&str> = directive
+        .split(',')
+        .filter(|term| !term.ends_with('-') && !term.starts_with('-'))
+        .collect();
+    let (start, end) = match terms.as_slice() {
+        [start] => (percent_decode(start)?, None),
+        [start, end] => (percent_decode(start)?, Some(percent_decode(end)?)),
+        _ => return None,
+    };
+    (!start.is_empty()).then_some(SharedSelection { start, end })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{parse_selection, share_link, shared_selection, SELECTION_MAX_CHARS};
+    use crate::models::SharedSelection;
+
+    fn round_trip(selected_text: &str) -> (String, Option<SharedSelection>) {
+        let selection = shared_selection(selected_text);
+        let link = share_link(
+            "handbook",
+            "ops",
+            "runbooks/deploy",
+            Some("roll-back"),
+            selection.as_ref(),
+        );
+        assert_eq!(parse_selection(&link), selection);
+        (link, selection)
+    }
+
+    #[test]
+    fn short_selections_round_trip_whole() {
+        let (link, selection) = round_trip("Ünïcødé — «déploiement», 配置 & 🚀,\n  then roll-back");
+        assert!(link.starts_with(
+            "dalil://project/handbook/collection/ops/doc/runbooks/deploy#roll-back:~:text="
+        ));
+        assert!(!link[link.find(":~:").unwrap()..].contains(['-', ',', '&', ' ']));
+        assert_eq!(
+            selection.unwrap().start,
+            "Ünïcødé — «déploiement», 配置 & 🚀, then roll-back"
+        );
+
+        assert_eq!(shared_selection(" \n\t "), None);
+        assert_eq!(
+            share_link("p", "c", "d", None, None),
+            "dalil://project/p/collection/c/doc/d"
+        );
+        let no_anchor = shared_selection("plain");
+        assert_eq!(
+            share_link("p", "c", "d", None, no_anchor.as_ref()),
+            "dalil://project/p/collection/c/doc/d#:~:text=plain"
+        );
+    }
+
+    #[test]
+    fn long_selections_keep_their_first_and_last_words() {
+        let long = format!("Début {} fin du passage 終わり", "mot ".repeat(500));
+        let (link, selection) = round_trip(&long);
+        let selection = selection.unwrap();
+        assert_eq!(selection.start, "Début mot mot mot mot mot");
+        assert_eq!(
+            selection.end.as_deref(),
+            Some("mot mot fin du passage 終わり")
+        );
+        assert!(link.len() < 400);
+
+        let one_word = "x".repeat(SELECTION_MAX_CHARS * 2);
+        let (_, selection) = round_trip(&format!("a b c d e f {}", one_word));
+        assert_eq!(selection.unwrap().end.unwrap().chars().count(), 80);
+
+        assert_eq!(parse_selection("dalil://project/p#a:~:text=%E9"), None);
+        assert_eq!(parse_selection("dalil://project/p#a"), None);
+        assert_eq!(
+            parse_selection("dalil://x#:~:text=before-,start,end,-after&x=1"),
+            Some(SharedSelection {
+                start: "start".to_string(),
+                end: Some("end".to_string()),
+            })
+        );
+    }
+}
@@ -0,0 +1,360 @@
+//! Keeps slugs stable across file renames. After a rebuild, documents whose
+//! slug vanished are paired with new slugs carrying the same content (or, failing
+//! that, the same title), and the pairs are written to a `slug_aliases` table in
+//! the new project database so old links keep resolving. User state pointing at
+//! the old slugs is then rewritten to the new ones.
+
+use crate::models::{DocumentError, SlugRename};
+use rusqlite::{params, Connection, OpenFlags, OptionalExtension};
+use std::collections::{HashMap, HashSet};
+use std::path::Path;
+
+const SLUG_ALIASES_SCHEMA: &str = "
+    CREATE TABLE IF NOT EXISTS slug_aliases (
+      old_slug TEXT PRIMARY KEY,
+      new_slug TEXT NOT NULL
+    );
+";
+
+/// User state tables keyed by document slug, rewritten when a document is renamed.
+const USER_STATE_SLUG_TABLES: &[&str] = &[
+    "bookmarks",
+    "doc_notes",
+    "doc_note_revisions",
+    "doc_note_drafts",
+    "doc_highlights",
+    "doc_views",
+    "closed_documents",
+    "last_sessions",
+];
+
+struct DocumentRow {
+    slug: String,
+    collection_id: String,
+    title: String,
+    content_raw: String,
+}
+
+fn document_rows(conn: &Connection) -> Result<Vec<DocumentRow>, String> {
+    let mut stmt = conn
+        .prepare("SELECT slug, collection_id, title, content_raw FROM documents")
+        .map_err(|e| e.to_string())?;
+    let rows = stmt
+        .query_map([], |row| {
+            Ok(DocumentRow {
+                slug: row.get(0)?,
+                collection_id: row.get(1)?,
+                title: row.get(2)?,
+                content_raw: row.get(3)?,
+            })
+        })
+        .map_err(|e| e.to_string())?;
+    rows.collect::<Result<Vec<_>, _>>()
+        .map_err(|e| e.to_string())
+}
+
+/// Aliases recorded by earlier builds. Databases without the table have none.
+fn existing_aliases(conn: &Connection) -> Vec<(String, String)> {
+    let Ok(mut stmt) = conn.prepare("SELECT old_slug, new_slug FROM slug_aliases") else {
+        return vec![];
+    };
+    stmt.query_map([], |row| Ok((row.get(0)?, row.get(1)?)))
+        .and_then(|rows| rows.collect())
+        .unwrap_or_default()
+}
+
+/// Pair each vanished document with a new one that has identical content, or
+/// failing that the same title. Ambiguous matches are left unpaired.
+fn detect_renames(old_docs: &[DocumentRow], new_docs: &[DocumentRow]) -> Vec<SlugRename> {
+    let old_slugs: HashSet<&str> = old_docs.iter().map(|d| d.slug.as_str()).collect();
+    let new_slugs: HashSet<&str> = new_docs.iter().map(|d| d.slug.as_str()).collect();
+    let removed: Vec<&DocumentRow> = old_docs
+        .iter()
+        .filter(|d| !new_slugs.contains(d.slug.as_str()))
+        .collect();
+    let added: Vec<&DocumentRow> = new_docs
+        .iter()
+        .filter(|d| !old_slugs.contains(d.slug.as_str()))
+        .collect();
+
+    let keys: [fn(&DocumentRow) -> String; 2] =
+        [|d| d.content_raw.clone(), |d| d.title.to_lowercase()];
+    let mut renames: Vec<SlugRename> = Vec::new();
+    let mut claimed: HashSet<&str> = HashSet::new();
+    for key in keys {
+        let unmatched: Vec<&DocumentRow> = removed
+            .iter()
+            .filter(|d| !renames.iter().any(|r| r.old_slug == d.slug))
+            .copied()
+            .collect();
+        let mut added_by_key: HashMap<String, Vec<&DocumentRow>> = HashMap::new();
+        for doc in added.iter().filter(|d| !claimed.contains(d.slug.as_str())) {
+            added_by_key.entry(key(doc)).or_default().push(doc);
+        }
+        let mut removed_by_key: HashMap<String, usize> = HashMap::new();
+        for doc in &unmatched {
+            *removed_by_key.entry(key(doc)).or_default() += 1;
+        }
+
+        for old in unmatched {
+            let k = key(old);
+            let (Some(candidates), Some(1)) = (added_by_key.get(&k), removed_by_key.get(&k)) else {
+                continue;
+            };
+            if let [new] = candidates.as_slice() {
+                claimed.insert(new.slug.as_str());
+                renames.push(SlugRename {
+                    old_slug: old.slug.clone(),
+                    new_slug: new.slug.clone(),
+                    collection_id: new.collection_id.clone(),
+                });
+            }
+        }
+    }
+    renames
+}
+
+/// Detect renames between the previous build (`old_conn`) and the database
+/// just written to `new_db_path`, and store aliases in the new database. Aliases
+/// from earlier builds are carried forward and re-pointed at the latest slug.
+/// Returns only the renames introduced by this build.
+pub fn record_slug_aliases(
+    old_conn: &Connection,
+    new_db_path: &Path,
+) -> Result<Vec<SlugRename>, String> {
+    let new_conn = Connection::open_with_flags(new_db_path, OpenFlags::SQLITE_OPEN_READ_WRITE)
+        .map_err(|e| e.to_string())?;
+    let old_docs = document_rows(old_conn)?;
+    let new_docs = document_rows(&new_conn)?;
+    let renames = detect_renames(&old_docs, &new_docs);
+
+    let new_slugs: HashSet<&str> = new_docs.iter().map(|d| d.slug.as_str()).collect();
+    let renamed_to: HashMap<&str, &str> = renames
+        .iter()
+        .map(|r| (r.old_slug.as_str(), r.new_slug.as_str()))
+        .collect();
+    let mut aliases: Vec<(String, String)> = existing_aliases(old_conn)
+        .into_iter()
+        .map(|(old, target)| {
+            let target = renamed_to
+                .get(target.as_str())
+                .map(|t| t.to_string())
+                .unwrap_or(target);
+            (old, target)
+        })
+        .collect();
+    aliases.extend(
+        renames
+            .iter()
+            .map(|r| (r.old_slug.clone(), r.new_slug.clone())),
+    );
+    // Drop aliases whose old slug is a real document again or whose target is gone
+    aliases.retain(|(old, target)| {
+        !new_slugs.contains(old.as_str()) && new_slugs.contains(target.as_str())
+    });
+
+    let tx = new_conn
+        .unchecked_transaction()
+        .map_err(|e| e.to_string())?;
+    tx.execute_batch(SLUG_ALIASES_SCHEMA)
+        .map_err(|e| e.to_string())?;
+    tx.execute("DELETE FROM slug_aliases", [])
+        .map_err(|e| e.to_string())?;
+    for (old, target) in &aliases {
+        tx.execute(
+            "INSERT OR REPLACE INTO slug_aliases (old_slug, new_slug) VALUES (?1, ?2)",
+            params![old, target],
+        )
+        .map_err(|e| e.to_string())?;
+    }
+    tx.commit().map_err(|e| e.to_string())?;
+    Ok(renames)
+}
+
+/// Rewrite user state for `project_id` from old slugs to new ones in a single
+/// transaction, logging each change to `slug_migrations`. Rows that would
+/// collide with existing state for the new slug (a note or view record already
+/// there) are left on the old slug. Returns the number of rows changed.
+pub fn migrate_user_state_slugs(
+    user_state_conn: &Connection,
+    project_id: &str,
+    renames: &[SlugRename],
+    now: i64,
+) -> Result<usize, String> {
+    if renames.is_empty() {
+        return Ok(0);
+    }
+    let tx = user_state_conn
+        .unchecked_transaction()
+        .map_err(|e| e.to_string())?;
+    let mut total = 0;
+    for rename in renames {
+        for table in USER_STATE_SLUG_TABLES {
+            let changed = if *table == "bookmarks" {
+                tx.execute(
+                    "UPDATE bookmarks SET doc_slug = ?1, collection_id = ?2, updated_at = ?3
+                     WHERE project_id = ?4 AND doc_slug = ?5",
+                    params![
+                        rename.new_slug,
+                        rename.collection_id,
+                        now,
+                        project_id,
+                        rename.old_slug
+                    ],
+                )
+            } else {
+                tx.execute(
+                    &format!(
+                        "UPDATE OR IGNORE {} SET doc_slug = ?1 WHERE project_id = ?2 AND doc_slug = ?3",
+                        table
+                    ),
+                    params![rename.new_slug, project_id, rename.old_slug],
+                )
+            }
+            .map_err(|e| e.to_string())?;
+
+            if changed > 0 {
+                tx.execute(
+                    "INSERT INTO slug_migrations (project_id, old_slug, new_slug, table_name, rows_changed, migrated_at)
+                     VALUES (?1, ?2, ?3, ?4, ?5, ?6)",
+                    params![
+                        project_id,
+                        rename.old_slug,
+                        rename.new_slug,
+                        table,
+                        changed as i64,
+                        now
+                    ],
+                )
+                .map_err(|e| e.to_string())?;
+                total += changed;
+            }
+        }
+    }
+    tx.commit().map_err(|e| e.to_string())?;
+    Ok(total)
+}
+
+/// Explain a `get_document` miss for `slug`, given the slug it is aliased to
+/// in the project database, from the removals recorded in user state.
+pub fn missing_document(
+    user_state_conn: &Connection,
+    project_id: &str,
+    slug: &str,
+    renamed_to: Option<String>,
+) -> DocumentError {
+    let removal = |slug: &str| {
+        user_state_conn
+            .query_row(
+                "SELECT removed_at FROM doc_removals WHERE project_id = ?1 AND doc_slug = ?2",
+                params![project_id, slug],
+                |row| row.get::<_, i64>(0),
+            )
+            .optional()
+    };
+    let removed_at = match removal(renamed_to.as_deref().unwrap_or(slug)) {
+        Ok(removed_at) => removed_at,
+        Err(e) => return DocumentError::from(e.to_string()),
+    };
+    DocumentError::NotFound {
+        slug: slug.to_string(),
+        renamed_to,
+        removed_at,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{detect_renames, missing_document, DocumentRow};
+    use crate::models::DocumentError;
+    use rusqlite::Connection;
+
+    fn doc(slug: &str, title: &str, content: &str) -> DocumentRow {
+        DocumentRow {
+            slug: slug.to_string(),
+            collection_id: "guides".to_string(),
+            title: title.to_string(),
+            content_raw: content.to_string(),
+        }
+    }
+
+    #[test]
+    fn renames_match_on_content_then_unique_title() {
+        let old = vec![
+            doc("guides/setup", "Setup", "Install things"),
+            doc("guides/deploys", "Deploys", "Ship it"),
+            doc("guides/gone", "Gone", "Deleted"),
+            doc("guides/kept", "Kept", "Unchanged"),
+        ];
+        let new = vec![
+            doc("guides/getting-started/setup", "Setup", "Install things"),
+            doc("guides/shipping", "Deploys", "Ship it, carefully"),
+            doc("guides/kept", "Kept", "Unchanged"),
+        ];
+
+        let renames = detect_renames(&old, &new);
+        let pairs: Vec<(&str, &str)> = renames
+            .iter()
+            .map(|r| (r.old_slug.as_str(), r.new_slug.as_str()))
+            .collect();
+        assert_eq!(
+            pairs,
+            vec![
+                ("guides/setup", "guides/getting-started/setup"),
+                ("guides/deploys", "guides/shipping"),
+            ]
+        );
+    }
+
+    #[test]
+    fn ambiguous_matches_are_not_renamed() {
+        let old = vec![doc("guides/a", "Notes", "x"), doc("guides/b", "Notes", "y")];
+        let new = vec![doc("guides/c", "Notes", "z")];
+        assert!(detect_renames(&old, &new).is_empty());
+    }
+
+    fn user_state() -> Connection {
+        let conn = Connection::open_in_memory().expect("open in-memory sqlite");
+        conn.execute_batch(
+            "CREATE TABLE doc_removals (
+                project_id TEXT NOT NULL,
+                doc_slug TEXT NOT NULL,
+                removed_at INTEGER NOT NULL,
+                PRIMARY KEY(project_id, doc_slug)
+            );
+            INSERT INTO doc_removals VALUES ('p', 'guides/gone', 1700000000);
+            INSERT INTO doc_removals VALUES ('other', 'guides/setup', 1700000000);",
+        )
+        .expect("create user state");
+        conn
+    }
+
+    fn not_found(slug: &str, renamed_to: Option<&str>, removed_at: Option<i64>) -> DocumentError {
+        DocumentError::NotFound {
+            slug: slug.to_string(),
+            renamed_to: renamed_to.map(str::to_string),
+            removed_at,
+        }
+    }
+
+    #[test]
+    fn misses_report_renames_removals_or_nothing() {
+        let conn = user_state();
+        assert_eq!(
+            missing_document(&conn, "p", "guides/old", Some("guides/setup".to_string())),
+            not_found("guides/old", Some("guides/setup"), None)
+        );
+        assert_eq!(
+            missing_document(&conn, "p", "guides/gone", None),
+            not_found("guides/gone", None, Some(1_700_000_000))
+        );
+        assert_eq!(
+            missing_document(&conn, "p", "guides/older", Some("guides/gone".to_string())),
+            not_found("guides/older", Some("guides/gone"), Some(1_700_000_000))
+        );
+        assert_eq!(
+            missing_document(&conn, "p", "guides/typo", None),
+            not_found("guides/typo", None, None)
+        );
+    }
+}
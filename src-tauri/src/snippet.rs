@@ -0,0 +1,432 @@
+//! Cleanup of FTS5 `snippet()` output. Depending on the builder, the indexed
+//! document text can still carry markup, so a snippet may start inside a tag's
+//! attributes, contain entities such as `&amp;`, or end halfway through a tag.
+//! Snippets are cleaned into text whose only markup is the `<mark>` pair
+//! search injects, and capped at a grapheme boundary. Also home to the
+//! plain-text helpers used for longer excerpts around a match.
+
+/// Longest snippet shown, in user-perceived characters. Leaves room for
+/// snippets of `MAX_SNIPPET_TOKENS`.
+pub const SNIPPET_MAX_CHARS: usize = 480;
+
+/// Bounds and default for the token count passed to FTS5 `snippet()`.
+pub const MIN_SNIPPET_TOKENS: i32 = 8;
+pub const MAX_SNIPPET_TOKENS: i32 = 64;
+pub const DEFAULT_SNIPPET_TOKENS: i32 = 30;
+
+const MARK_OPEN: &str = "<mark>";
+const MARK_CLOSE: &str = "</mark>";
+const ELLIPSIS: &str = "...";
+
+enum Piece {
+    Char(char),
+    MarkOpen,
+    MarkClose,
+}
+
+/// Decode entities, strip tags other than `<mark>`/`</mark>`, collapse
+/// whitespace, and cap the text at `SNIPPET_MAX_CHARS`.
+pub fn clean_snippet(raw: &str) -> String {
+    render(truncate(
+        pieces(strip_attribute_remnant(raw)),
+        SNIPPET_MAX_CHARS,
+    ))
+}
+
+/// Drop a leading `class="note">`-style fragment of a tag the snippet began
+/// inside.
+fn strip_attribute_remnant(raw: &str) -> &str {
+    let Some(end) = raw.find('>') else {
+        return raw;
+    };
+    let head = &raw[..end];
+    if !head.contains('<') && (head.contains('=') || head.contains('"')) {
+        &raw[end + 1..]
+    } else {
+        raw
+    }
+}
+
+fn pieces(raw: &str) -> Vec<Piece> {
+    let mut pieces = Vec::new();
+    let mut rest = raw;
+    while let Some(c) = rest.chars().next() {
+        if c == '<' {
+            if starts_with_ignore_case(rest, MARK_OPEN) {
+                pieces.push(Piece::MarkOpen);
+                rest = &rest[MARK_OPEN.len()..];
+                continue;
+            }
+            if starts_with_ignore_case(rest, MARK_CLOSE) {
+                pieces.push(Piece::MarkClose);
+                rest = &rest[MARK_CLOSE.len()..];
+                continue;
+            }
+            let opens_tag = rest[1..]
+                .chars()
+                .next()
+                .is_some_and(|next| next.is_ascii_alphabetic() || next == '/' || next == '!');
+            if opens_tag {
+                match rest.find('>') {
+                    Some(end) => {
+                        // Tags separate words, e.g. adjacent list items
+                        push_char(&mut pieces, ' ');
+                        rest = &rest[end + 1..];
+                    }
+                    // A tag cut off at the end of the snippet
+                    None => break,
+                }
+                continue;
+            }
+        }
+        if c == '&' {
+            match decode_entity(rest) {
+                Some((decoded, len)) => {
+                    push_char(&mut pieces, decoded);
+                    rest = &rest[len..];
+                    continue;
+                }
+                // An entity cut off at the end of the snippet
+                None if rest[1..]
+                    .chars()
+                    .all(|c| c.is_ascii_alphanumeric() || c == '#') =>
+                {
+                    break
+                }
+                None => {}
+            }
+        }
+        push_char(&mut pieces, c);
+        rest = &rest[c.len_utf8()..];
+    }
+    pieces
+}
+
+fn starts_with_ignore_case(text: &str, prefix: &str) -> bool {
+    text.get(..prefix.len())
+        .is_some_and(|head| head.eq_ignore_ascii_case(prefix))
+}
+
+/// Push `c`, folding runs of whitespace into one space.
+fn push_char(pieces: &mut Vec<Piece>, c: char) {
+    if c.is_whitespace() {
+        let after_space = match pieces.last() {
+            Some(Piece::Char(last)) => *last == ' ',
+            Some(_) => false,
+            None => true,
+        };
+        if !after_space {
+            pieces.push(Piece::Char(' '));
+        }
+    } else {
+        pieces.push(Piece::Char(c));
+    }
+}
+
+/// The character for an entity at the start of `text`, and its length.
+pub(crate) fn decode_entity(text: &str) -> Option<(char, usize)> {
+    let end = text.find(';')?;
+    let name = &text[1..end];
+    if name.is_empty() || name.len() > 10 {
+        return None;
+    }
+    let decoded = if let Some(number) = name.strip_prefix('#') {
+        let code = match number.strip_prefix(['x', 'X']) {
+            Some(hex) => u32::from_str_radix(hex, 16).ok()?,
+            None => number.parse().ok()?,
+        };
+        char::from_u32(code)?
+    } else {
+        match name {
+            "amp" => '&',
+            "lt" => '<',
+            "gt" => '>',
+            "quot" => '"',
+            "apos" => '\'',
+            "nbsp" => ' ',
+            "ndash" => '–',
+            "mdash" => '—',
+            "hellip" => '…',
+            "lsquo" => '‘',
+            "rsquo" => '’',
+            "ldquo" => '“',
+            "rdquo" => '”',
+            "copy" => '©',
+            "reg" => '®',
+            "trade" => '™',
+            _ => return None,
+        }
+    };
+    Some((decoded, end + 1))
+}
+
+/// Plain text of rendered HTML: tags dropped along with the contents of
+/// `script` and `style`, entities decoded, and whitespace collapsed.
+pub fn html_text(html: &str) -> String {
+    let mut text = String::with_capacity(html.len() / 2);
+    let mut in_skipped = false;
+    let mut rest = html;
+    while let Some(c) = rest.chars().next() {
+        if c == '<' {
+            if let Some(end) = rest.find('>') {
+                let tag = &rest[1..end];
+                let name = tag
+                    .trim_start_matches('/')
+                    .split(|c: char| !c.is_ascii_alphanumeric())
+                    .next()
+                    .unwrap_or_default();
+                if name.eq_ignore_ascii_case("script") || name.eq_ignore_ascii_case("style") {
+                    in_skipped = !tag.starts_with('/');
+                }
+                // Tags separate words, e.g. adjacent list items
+                text.push(' ');
+                rest = &rest[end + 1..];
+                continue;
+            }
+        }
+        let (decoded, len) = if c == '&' {
+            decode_entity(rest).unwrap_or((c, 1))
+        } else {
+            (c, c.len_utf8())
+        };
+        if !in_skipped {
+            text.push(decoded);
+        }
+        rest = &rest[len..];
+    }
+    text.split_whitespace().collect::<Vec<_>>().join(" ")
+}
+
+/// Words in rendered HTML: the runs of its text holding a letter or digit,
+/// so punctuation left alone after inline code doesn't count. The one
+/// definition behind stored and computed word counts, mirrored by
+/// `countWords` in scripts/lib/count-words.ts for the Node builder.
+pub fn word_count(html: &str) -> i64 {
+    html_text(html)
+        .split_whitespace()
+        .filter(|word| word.chars().any(char::is_alphanumeric))
+        .count() as i64
+}
+
+/// About `max_chars` of `text` around the first case-insensitive occurrence
+/// of any of `terms`, or from the start when none occurs. The cut is made at
+/// spaces and marked with an ellipsis at each trimmed end.
+pub fn excerpt_around(text: &str, terms: &[String], max_chars: usize) -> String {
+    let chars: Vec<char> = text.chars().collect();
+    let fold = |c: char| c.to_lowercase().next().unwrap_or(c);
+    let folded: Vec<char> = chars.iter().copied().map(fold).collect();
+    let first_match = terms
+        .iter()
+        .map(|term| term.chars().map(fold).collect::<Vec<char>>())
+        .filter(|term| !term.is_empty())
+        .filter_map(|term| {
+            folded
+                .windows(term.len())
+                .position(|w| w == term.as_slice())
+        })
+        .min();
+
+    if chars.len() <= max_chars {
+        return chars.iter().collect();
+    }
+    // Show some lead-in before the match
+    let mut start = first_match.map_or(0, |i| i.saturating_sub(max_chars / 3));
+    let mut end = (start + max_chars).min(chars.len());
+    start = end.saturating_sub(max_chars);
+    if start > 0 {
+        if let Some(space) = chars[start..end].iter().position(|c| c.is_whitespace()) {
+            start += space + 1;
+        }
+    }
+    if end < chars.len() {
+        if let Some(space) = chars[start..end].iter().rposition(|c| c.is_whitespace()) {
+            end = start + space;
+        }
+    }
+
+    let mut excerpt = String::new();
+    if start > 0 {
+        excerpt.push_str(ELLIPSIS);
+    }
+    excerpt.extend(&chars[start..end]);
+    if end < chars.len() {
+        excerpt.push_str(ELLIPSIS);
+    }
+    excerpt
+}
+
+/// Dice coefficient over character bigrams, used to pair a vanished anchor
+/// with the closest surviving heading and an AI source with its heading.
+pub fn text_similarity(a: &str, b: &str) -> f64 {
+    fn bigrams(value: &str) -> Vec<(char, char)> {
+        let chars: Vec<char> = value
+            .to_lowercase()
+            .chars()
+            .map(|c| if c.is_alphanumeric() { c } else { ' ' })
+            .collect();
+        chars.windows(2).map(|w| (w[0], w[1])).collect()
+    }
+
+    let a_grams = bigrams(a);
+    let mut b_grams = bigrams(b);
+    if a_grams.is_empty() || b_grams.is_empty() {
+        return 0.0;
+    }
+
+    let total = (a_grams.len() + b_grams.len()) as f64;
+    let mut shared = 0usize;
+    for gram in &a_grams {
+        if let Some(pos) = b_grams.iter().position(|g| g == gram) {
+            b_grams.swap_remove(pos);
+            shared += 1;
+        }
+    }
+    (2 * shared) as f64 / total
+}
+
+/// Characters that continue the grapheme before them: combining marks,
+/// variation selectors, emoji modifiers and tag characters.
+fn extends_grapheme(c: char) -> bool {
+    matches!(c as u32,
+        0x0300..=0x036F
+        | 0x1AB0..=0x1AFF
+        | 0x1DC0..=0x1DFF
+        | 0x200C..=0x200D
+        | 0x20D0..=0x20FF
+        | 0xFE00..=0xFE0F
+        | 0xFE20..=0xFE2F
+        | 0x1F3FB..=0x1F3FF
+        | 0xE0020..=0xE007F
+        | 0xE0100..=0xE01EF)
+}
+
+fn is_regional_indicator(c: char) -> bool {
+    matches!(c as u32, 0x1F1E6..=0x1F1FF)
+}
+
+/// Keep the first `max` graphemes, ending with an ellipsis and closing an
+/// open highlight when anything was cut.
+fn truncate(pieces: Vec<Piece>, max: usize) -> Vec<Piece> {
+    let mut kept = Vec::with_capacity(pieces.len());
+    let mut graphemes = 0;
+    let mut previous: Option<char> = None;
+    let mut regional_run = 0;
+    let mut in_mark = false;
+    for piece in pieces {
+        if let Piece::Char(c) = piece {
+            // Flags are pairs of regional indicators; a ZWJ joins the next character
+            let joins_previous = extends_grapheme(c)
+                || previous == Some('\u{200D}')
+                || (is_regional_indicator(c) && regional_run % 2 == 1);
+            regional_run = if is_regional_indicator(c) {
+                regional_run + 1
+            } else {
+                0
+            };
+            if !joins_previous {
+                if graphemes == max {
+                    let trimmed = kept
+                        .iter()
+                        .rposition(|p| !matches!(p, Piece::Char(' ')))
+                        .map_or(0, |i| i + 1);
+                    kept.truncate(trimmed);
+                    if in_mark {
+                        kept.push(Piece::MarkClose);
+                    }
+                    kept.extend(ELLIPSIS.chars().map(Piece::Char));
+                    return kept;
+                }
+                graphemes += 1;
+            }
+            previous = Some(c);
+        } else {
+            in_mark = matches!(piece, Piece::MarkOpen);
+        }
+        kept.push(piece);
+    }
+    kept
+}
+
+/// Text with `<` escaped where it would start a tag, so the highlights are
+/// the snippet's only markup.
+fn render(pieces: Vec<Piece>) -> String {
+    let mut out = String::new();
+    for piece in pieces {
+        match piece {
+            Piece::Char('<') => out.push_str("&lt;"),
+            Piece::Char(c) => out.push(c),
+            Piece::MarkOpen => out.push_str(MARK_OPEN),
+            Piece::MarkClose => out.push_str(MARK_CLOSE),
+        }
+    }
+    out.trim().to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{clean_snippet, excerpt_around, html_text, SNIPPET_MAX_CHARS};
+
+    #[test]
+    fn entities_and_markup_remnants_are_cleaned() {
+        assert_eq!(
+            clean_snippet(
+                "ss=\"callout\">Build &amp; deploy <mark>runbook</mark>\n\n<span class=\"x\">R&amp;D</span> &#8212; &lt;b&gt; done &hellip; <a hr"
+            ),
+            "Build & deploy <mark>runbook</mark> R&D — &lt;b> done …"
+        );
+        assert_eq!(
+            clean_snippet("Use <MARK>CI/CD</MARK> &copy and &am"),
+            "Use <mark>CI/CD</mark> &copy and"
+        );
+    }
+
+    #[test]
+    fn long_snippets_are_cut_between_graphemes() {
+        // A family emoji is seven code points joined by ZWJs, and é here is
+        // e plus a combining accent
+        let family = "👨\u{200D}👩\u{200D}👧\u{200D}👦";
+        let raw = format!(
+            "<mark>{}</mark>{}",
+            "e\u{301}".repeat(SNIPPET_MAX_CHARS - 1),
+            family.repeat(3)
+        );
+        let cleaned = clean_snippet(&raw);
+        assert!(cleaned.ends_with(&format!("{}...", family)));
+        assert!(!cleaned.contains(&format!("{}{}", family, family)));
+
+        let flags = format!("<mark>{}", "🇳🇿🇩🇪".repeat(SNIPPET_MAX_CHARS));
+        let cleaned = clean_snippet(&flags);
+        assert!(cleaned.ends_with("</mark>..."));
+        let flag_chars = cleaned.chars().filter(|c| *c as u32 >= 0x1F1E6).count();
+        assert_eq!(flag_chars, SNIPPET_MAX_CHARS * 2);
+    }
+
+    #[test]
+    fn html_text_keeps_only_visible_text() {
+        assert_eq!(
+            html_text(
+                "<style>p { color: red }</style><h2 id=\"x\">Deploy &amp; roll back</h2>\n\
+                 <ul><li>One</li><li>Two</li></ul><script>alert(1)</script><p>Caf&#233;</p>"
+            ),
+            "Deploy & roll back One Two Café"
+        );
+    }
+
+    #[test]
+    fn excerpts_start_shortly_before_the_first_match() {
+        let text = format!(
+            "{} Rollback steps follow here. {}",
+            "lead ".repeat(40),
+            "tail ".repeat(40)
+        );
+        let excerpt = excerpt_around(&text, &["ROLLBACK".to_string()], 60);
+        assert!(excerpt.starts_with("...lead"));
+        assert!(excerpt.ends_with("..."));
+        assert!(excerpt.contains("Rollback steps follow here."));
+        assert!(excerpt.chars().count() <= 66);
+
+        let start = excerpt_around(&text, &["missing".to_string()], 60);
+        assert!(start.starts_with("lead lead"));
+        assert_eq!(excerpt_around("short text", &[], 60), "short text");
+    }
+}
@@ -0,0 +1,162 @@
+//! Spotlight export (macOS only): one small HTML stub per document so
+//! Spotlight can index titles and excerpts, each opening the document via its
+//! dalil:// deep link.
+
+use crate::models::SpotlightExportSummary;
+use crate::share_links;
+use std::collections::HashSet;
+use std::path::{Path, PathBuf};
+use tauri::{AppHandle, Manager};
+
+pub struct SpotlightStub {
+    pub collection_id: String,
+    pub slug: String,
+    pub title: String,
+    pub excerpt: String,
+}
+
+/// Root folder for all exported stubs, one subfolder per project.
+pub fn spotlight_root(app: &AppHandle) -> Result<PathBuf, String> {
+    Ok(app
+        .path()
+        .app_data_dir()
+        .map_err(|e| e.to_string())?
+        .join("spotlight"))
+}
+
+fn escape_html(value: &str) -> String {
+    value
+        .replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+/// Link opening the stub's document.
+fn deep_link(project_id: &str, stub: &SpotlightStub) -> String {
+    let doc_slug = stub
+        .slug
+        .strip_prefix(&format!("{}/", stub.collection_id))
+        .unwrap_or(&stub.slug);
+    share_links::deep_link(project_id, &stub.collection_id, doc_slug)
+}
+
+/// Longest encoded slug kept whole in a file name; longer ones are cut and
+/// suffixed with a hash of the full slug to stay under filesystem limits.
+const STUB_NAME_MAX_LEN: usize = 160;
+
+/// File name for a document's stub. Distinct slugs always get distinct
+/// names: lowercase ASCII letters, digits and '-' are kept and every other
+/// byte becomes `_XX`, so case-insensitive volumes can't fold two together.
+fn stub_file_name(slug: &str) -> String {
+    let mut safe = String::with_capacity(slug.len());
+    for b in slug.bytes() {
+        match b {
+            b'a'..=b'z' | b'0'..=b'9' | b'-' => safe.push(b as char),
+            _ => safe.push_str(&format!("_{:02x}", b)),
+        }
+    }
+    if safe.len() > STUB_NAME_MAX_LEN {
+        // FNV-1a, stable across runs so unchanged stubs keep their names
+        let hash = slug.bytes().fold(0xcbf2_9ce4_8422_2325_u64, |h, b| {
+            (h ^ u64::from(b)).wrapping_mul(0x0100_0000_01b3)
+        });
+        safe.truncate(STUB_NAME_MAX_LEN);
+        safe.push_str(&format!("~{:016x}", hash));
+    }
+    format!("{}.html", safe)
+}
+
+fn render_stub(project_id: &str, stub: &SpotlightStub) -> String {
+    let link = escape_html(&deep_link(project_id, stub));
+    format!(
+        "<!doctype html>\n<html>\n<head>\n<meta charset=\"utf-8\">\n<title>{title}</title>\n<meta name=\"description\" content=\"{excerpt}\">\n<meta http-equiv=\"refresh\" content=\"0; url={link}\">\n</head>\n<body>\n<h1>{title}</h1>\n<p>{excerpt}</p>\n<p><a href=\"{link}\">Open in Dalil</a></p>\n</body>\n</html>\n",
+        title = escape_html(&stub.title),
+        excerpt = escape_html(&stub.excerpt),
+        link = link,
+    )
+}
+
+/// Write stubs for a project, skipping files whose contents are unchanged and
+/// deleting stubs for documents that no longer exist.
+pub fn export_project(
+    root: &Path,
+    project_id: &str,
+    stubs: &[SpotlightStub],
+) -> Result<SpotlightExportSummary, String> {
+    let dir = root.join(project_id);
+    std::fs::create_dir_all(&dir).map_err(|e| e.to_string())?;
+
+    let mut expected = HashSet::new();
+    let mut written = 0;
+    let mut unchanged = 0;
+    for stub in stubs {
+        let file_name = stub_file_name(&stub.slug);
+        let path = dir.join(&file_name);
+        let contents = render_stub(project_id, stub);
+        if std::fs::read_to_string(&path).ok().as_deref() == Some(contents.as_str()) {
+            unchanged += 1;
+        } else {
+            std::fs::write(&path, contents).map_err(|e| e.to_string())?;
+            written += 1;
+        }
+        expected.insert(file_name);
+    }
+
+    let mut removed = 0;
+    for entry in std::fs::read_dir(&dir).map_err(|e| e.to_string())? {
+        let entry = entry.map_err(|e| e.to_string())?;
+        let file_name = entry.file_name().to_string_lossy().to_string();
+        if file_name.ends_with(".html") && !expected.contains(&file_name) {
+            std::fs::remove_file(entry.path()).map_err(|e| e.to_string())?;
+            removed += 1;
+        }
+    }
+
+    Ok(SpotlightExportSummary {
+        project_id: project_id.to_string(),
+        directory: dir.to_string_lossy().to_string(),
+        written,
+        unchanged,
+        removed,
+    })
+}
+
+/// Remove a project's stubs, or every stub when `project_id` is None.
+pub fn remove_export(root: &Path, project_id: Option<&str>) -> Result<(), String> {
+    let path = match project_id {
+        Some(id) => root.join(id),
+        None => root.to_path_buf(),
+    };
+    if path.exists() {
+        std::fs::remove_dir_all(&path).map_err(|e| e.to_string())?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::stub_file_name;
+    use std::collections::HashSet;
+
+    #[test]
+    fn distinct_slugs_get_distinct_stub_names() {
+        let slugs = [
+            "guides/a-b",
+            "guides/a_b",
+            "guides_a-b",
+            "guides/a b",
+            "guides/A-b",
+            "guides/é",
+            "guides/e",
+        ];
+        let names: HashSet<String> = slugs.iter().map(|slug| stub_file_name(slug)).collect();
+        assert_eq!(names.len(), slugs.len());
+        assert_eq!(stub_file_name("guides/a-b"), "guides_2fa-b.html");
+
+        let long_a = format!("{}a", "x/".repeat(200));
+        let long_b = format!("{}b", "x/".repeat(200));
+        assert_ne!(stub_file_name(&long_a), stub_file_name(&long_b));
+        assert!(stub_file_name(&long_a).len() < 200);
+    }
+}
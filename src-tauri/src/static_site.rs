@@ -0,0 +1,498 @@
+//! Read-only static export of a project: one HTML page per document with a
+//! sidebar built from the navigation tree, links between documents rewritten
+//! to relative `.html` paths, and a JSON index for client-side search. The
+//! output needs no server beyond static file hosting. Document pages live
+//! under `docs/`, so no slug can collide with the site's own files at the
+//! root.
+
+use crate::models::NavigationNode;
+use std::collections::{HashMap, HashSet};
+use std::path::{Component, Path, PathBuf};
+
+pub const SEARCH_INDEX_FILE: &str = "search-index.json";
+const SEARCH_SCRIPT_FILE: &str = "search.js";
+const SITE_INDEX_FILE: &str = "index.html";
+/// Directory of the document pages, as in the app's `/docs/{slug}` routes.
+const PAGES_DIR: &str = "docs";
+
+/// Pages written between progress callbacks.
+pub const PROGRESS_EVERY: usize = 50;
+
+pub struct StaticPage {
+    pub slug: String,
+    pub title: String,
+    pub section: Option<String>,
+    pub content_html: String,
+}
+
+pub struct SiteCollection {
+    pub name: String,
+    pub nodes: Vec<NavigationNode>,
+}
+
+pub struct StaticSite {
+    pub title: String,
+    pub collections: Vec<SiteCollection>,
+    pub pages: Vec<StaticPage>,
+}
+
+const SEARCH_SCRIPT: &str = r#"(function () {
+  var root = document.body.dataset.root || '';
+  var input = document.getElementById('search');
+  var results = document.getElementById('search-results');
+  var index = null;
+  function show(query) {
+    results.innerHTML = '';
+    var terms = query.toLowerCase().split(/\s+/).filter(Boolean);
+    if (!terms.length) return;
+    index.filter(function (entry) {
+      var text = (entry.title + ' ' + entry.body).toLowerCase();
+      return terms.every(function (term) { return text.indexOf(term) !== -1; });
+    }).slice(0, 20).forEach(function (entry) {
+      var item = document.createElement('li');
+      var link = document.createElement('a');
+      link.href = root + 'docs/' + entry.slug + '.html';
+      link.textContent = entry.title;
+      item.appendChild(link);
+      results.appendChild(item);
+    });
+  }
+  input.addEventListener('input', function () {
+    if (index) return show(input.value);
+    fetch(root + 'search-index.json')
+      .then(function (response) { return response.json(); })
+      .then(function (entries) { index = entries; show(input.value); });
+  });
+})();
+"#;
+
+pub fn escape_html(value: &str) -> String {
+    value
+        .replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+/// Where a document's page goes under the output dir, or None for slugs that
+/// would escape the pages dir (absolute, `..`, empty segments).
+pub fn page_path(slug: &str) -> Option<PathBuf> {
+    let path = Path::new(slug);
+    let safe = !slug.is_empty()
+        && !slug.contains('\\')
+        && slug.split('/').all(|segment| !segment.is_empty())
+        && path
+            .components()
+            .all(|component| matches!(component, Component::Normal(_)));
+    safe.then(|| Path::new(PAGES_DIR).join(format!("{}.html", slug)))
+}
+
+/// `../` once per directory level of the page for `slug`, leading back to
+/// the site root.
+fn root_prefix(slug: &str) -> String {
+    "../".repeat(slug.matches('/').count() + 1)
+}
+
+fn page_href(root: &str, slug: &str) -> String {
+    format!("{}{}/{}.html", root, PAGES_DIR, escape_html(slug))
+}
+
+/// The exported slug a `/docs/{collection}/{slug}` route points at. The
+/// builder writes routes percent-encoded where needed, and a route to a bare
+/// collection opens its index page.
+fn route_slug(route: &str, exported: &HashSet<String>) -> Option<String> {
+    let route = crate::share_links::percent_decode(route.trim_end_matches('/'))?;
+    let slug = if route.contains('/') {
+        route
+    } else {
+        format!("{}/index", route)
+    };
+    exported.contains(&slug).then_some(slug)
+}
+
+/// Point `/docs/{collection}/{slug}` links at the exported page for that
+/// document, relative to the page for `from_slug`. Anchors and queries are
+/// kept; links to documents that were not exported are left alone.
+pub fn rewrite_links(html: &str, from_slug: &str, exported: &HashSet<String>) -> String {
+    const PREFIX: &str = "href=\"/docs/";
+    let root = root_prefix(from_slug);
+    let mut out = String::with_capacity(html.len());
+    let mut rest = html;
+    while let Some(start) = rest.find(PREFIX) {
+        let target_start = start + PREFIX.len();
+        let Some(len) = rest[target_start..].find('"') else {
+            break;
+        };
+        let target = &rest[target_start..target_start + len];
+        let split = target.find(['#', '?']).unwrap_or(target.len());
+        let (route, suffix) = target.split_at(split);
+        out.push_str(&rest[..start]);
+        if let Some(slug) = route_slug(route, exported) {
+            out.push_str(&format!("href=\"{}{}\"", page_href(&root, &slug), suffix));
+        } else {
+            out.push_str(&rest[start..target_start + len + 1]);
+        }
+        rest = &rest[target_start + len + 1..];
+    }
+    out.push_str(rest);
+    out
+}
+
+/// Nested lists for each collection's navigation tree, with the page for
+/// `current_slug` marked. Entries without an exported page are plain text.
+pub fn sidebar_html(
+    collections: &[SiteCollection],
+    current_slug: Option<&str>,
+    exported: &HashSet<String>,
+) -> String {
+    let root = current_slug.map(root_prefix).unwrap_or_default();
+    let mut out = String::new();
+    for collection in collections {
+        let slugs: HashSet<&str> = collection.nodes.iter().map(|n| n.slug.as_str()).collect();
+        let mut children: HashMap<Option<&str>, Vec<&NavigationNode>> = HashMap::new();
+        for node in &collection.nodes {
+            let parent = Some(node.parent_slug.as_str()).filter(|parent| slugs.contains(parent));
+            children.entry(parent).or_default().push(node);
+        }
+        for nodes in children.values_mut() {
+            nodes.sort_by_key(|node| node.sort_order);
+        }
+        out.push_str(&format!(
+            "<section>\n<h2>{}</h2>\n",
+            escape_html(&collection.name)
+        ));
+        render_nav_list(&mut out, &children, None, current_slug, exported, &root);
+        out.push_str("</section>\n");
+    }
+    out
+}
+
+fn render_nav_list(
+    out: &mut String,
+    children: &HashMap<Option<&str>, Vec<&NavigationNode>>,
+    parent: Option<&str>,
+    current_slug: Option<&str>,
+    exported: &HashSet<String>,
+    root: &str,
+) {
+    let Some(nodes) = children.get(&parent) else {
+        return;
+    };
+    out.push_str("<ul>\n");
+    for node in nodes {
+        out.push_str("<li>");
+        let title = escape_html(&node.title);
+        if exported.contains(&node.slug) {
+            let current = if current_slug == Some(node.slug.as_str()) {
+                " aria-current=\"page\""
+            } else {
+                ""
+            };
+            out.push_str(&format!(
+                "<a href=\"{}\"{}>{}</a>",
+                page_href(root, &node.slug),
+                current,
+                title
+            ));
+        } else {
+            out.push_str(&format!("<span>{}</span>", title));
+        }
+        out.push('\n');
+        render_nav_list(
+            out,
+            children,
+            Some(node.slug.as_str()),
+            current_slug,
+            exported,
+            root,
+        );
+        out.push_str("</li>\n");
+    }
+    out.push_str("</ul>\n");
+}
+
+fn render_page(site_title: &str, title: &str, root: &str, sidebar: &str, main: &str) -> String {
+    format!(
+        "<!doctype html>\n<html>\n<head>\n<meta charset=\"utf-8\">\n<meta name=\"viewport\" content=\"width=device-width, initial-scale=1\">\n<title>{title} · {site}</title>\n<style>\nbody {{ margin: 0; display: flex; font-family: system-ui, sans-serif; line-height: 1.5; }}\nnav {{ width: 18rem; flex: none; padding: 1rem; border-right: 1px solid #ddd; height: 100vh; overflow: auto; position: sticky; top: 0; box-sizing: border-box; }}\nnav ul {{ list-style: none; padding-left: 1rem; margin: 0; }}\nnav h2 {{ font-size: 0.9rem; text-transform: uppercase; }}\nnav [aria-current] {{ font-weight: bold; }}\nmain {{ flex: 1; padding: 1rem 2rem; max-width: 50rem; }}\npre {{ overflow: auto; }}\n</style>\n</head>\n<body data-root=\"{root}\">\n<nav>\n<p><a href=\"{root}{index}\">{site}</a></p>\n<input id=\"search\" type=\"search\" placeholder=\"Search\">\n<ul id=\"search-results\"></ul>\n{sidebar}</nav>\n<main>\n{main}\n</main>\n<script src=\"{root}{script}\"></script>\n</body>\n</html>\n",
+        title = escape_html(title),
+        site = escape_html(site_title),
+        root = root,
+        sidebar = sidebar,
+        main = main,
+        script = SEARCH_SCRIPT_FILE,
+        index = SITE_INDEX_FILE,
+    )
+}
+
+/// Write the site into `dir` and return the number of files written.
+/// `progress` is called with (pages written, total pages) every
+/// `PROGRESS_EVERY` pages and once at the end. Pages whose slug is not a safe
+/// relative path are skipped.
+pub fn write_site(
+    dir: &Path,
+    site: &StaticSite,
+    mut progress: impl FnMut(usize, usize),
+) -> Result<usize, String> {
+    std::fs::create_dir_all(dir)
+        .map_err(|e| format!("Failed to create {}: {}", dir.display(), e))?;
+    let pages: Vec<(&StaticPage, PathBuf)> = site
+        .pages
+        .iter()
+        .filter_map(|page| Some((page, page_path(&page.slug)?)))
+        .collect();
+    let exported: HashSet<String> = pages.iter().map(|(page, _)| page.slug.clone()).collect();
+
+    let total = pages.len();
+    let mut files = 0;
+    for (page, path) in &pages {
+        let path = dir.join(path);
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent).map_err(|e| e.to_string())?;
+        }
+        let sidebar = sidebar_html(&site.collections, Some(&page.slug), &exported);
+        let main = rewrite_links(&page.content_html, &page.slug, &exported);
+        let html = render_page(
+            &site.title,
+            &page.title,
+            &root_prefix(&page.slug),
+            &sidebar,
+            &main,
+        );
+        std::fs::write(&path, html)
+            .map_err(|e| format!("Failed to write {}: {}", path.display(), e))?;
+        files += 1;
+        if files % PROGRESS_EVERY == 0 {
+            progress(files, total);
+        }
+    }
+
+    let sidebar = sidebar_html(&site.collections, None, &exported);
+    let main = format!("<h1>{}</h1>", escape_html(&site.title));
+    std::fs::write(
+        dir.join(SITE_INDEX_FILE),
+        render_page(&site.title, &site.title, "", &sidebar, &main),
+    )
+    .map_err(|e| e.to_string())?;
+    let indexed: Vec<&StaticPage> = pages.iter().map(|(page, _)| *page).collect();
+    let index = search_index(&indexed);
+    std::fs::write(
+        dir.join(SEARCH_INDEX_FILE),
+        serde_json::to_vec(&index).map_err(|e| e.to_string())?,
+    )
+    .map_err(|e| e.to_string())?;
+    std::fs::write(dir.join(SEARCH_SCRIPT_FILE), SEARCH_SCRIPT).map_err(|e| e.to_string())?;
+    progress(total, total);
+    Ok(files + 3)
+}
+
+/// Search entries for the exported pages: slug, title, section and plain-text
+/// body.
+fn search_index(pages: &[&StaticPage]) -> serde_json::Value {
+    pages
+        .iter()
+        .map(|page| {
+            serde_json::json!({
+                "slug": page.slug,
+                "title": page.title,
+                "section": page.section,
+                "body": crate::snippet::html_text(&page.content_html),
+            })
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{
+        page_path, rewrite_links, sidebar_html, write_site, SiteCollection, StaticPage, StaticSite,
+        SEARCH_INDEX_FILE,
+    };
+    use crate::models::NavigationNode;
+    use std::collections::HashSet;
+
+    fn node(slug: &str, parent: Option<&str>, title: &str, sort_order: i32) -> NavigationNode {
+        NavigationNode {
+            id: sort_order,
+            collection_id: "guides".to_string(),
+            slug: slug.to_string(),
+            parent_slug: parent.unwrap_or_default().to_string(),
+            title: title.to_string(),
+            sort_order,
+            level: parent.map_or(0, |_| 1),
+            has_children: false,
+            word_count: None,
+            reading_minutes: None,
+        }
+    }
+
+    #[test]
+    fn links_become_relative_page_paths() {
+        let exported: HashSet<String> = ["guides/index", "guides/setup", "guides/deploys/rollback"]
+            .iter()
+            .map(|s| s.to_string())
+            .collect();
+        let html = r##"<a href="/docs/guides/deploys/rollback#steps">Roll back</a> <a href="/docs/other/missing">x</a> <a href="#top">top</a> <a href="/docs/guides/set%75p">Setup</a> <a href="/docs/guides/">Guides</a>"##;
+        assert_eq!(
+            rewrite_links(html, "guides/setup", &exported),
+            r##"<a href="../../docs/guides/deploys/rollback.html#steps">Roll back</a> <a href="/docs/other/missing">x</a> <a href="#top">top</a> <a href="../../docs/guides/setup.html">Setup</a> <a href="../../docs/guides/index.html">Guides</a>"##
+        );
+        assert_eq!(
+            rewrite_links(r#"<a href="/docs/guides/setup">"#, "top", &exported),
+            r#"<a href="../docs/guides/setup.html">"#
+        );
+
+        assert_eq!(
+            page_path("guides/setup"),
+            Some(std::path::PathBuf::from("docs/guides/setup.html"))
+        );
+        // A root README's page cannot overwrite the site's own index
+        assert_eq!(
+            page_path("index"),
+            Some(std::path::PathBuf::from("docs/index.html"))
+        );
+        for unsafe_slug in ["", "/etc/passwd", "guides/../../x", "guides//x", "a\\b"] {
+            assert!(page_path(unsafe_slug).is_none(), "{}", unsafe_slug);
+        }
+    }
+
+    #[test]
+    fn links_written_by_the_builder_point_at_exported_pages() {
+        let dir = std::env::temp_dir().join(format!("dalil-static-links-{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        let source = dir.join("source");
+        std::fs::create_dir_all(source.join("deploys")).unwrap();
+        std::fs::write(
+            source.join("README.md"),
+            "# Guides\n\nStart with [setup](01-setup.md) or [rolling back](deploys/rollback.md#steps).\n",
+        )
+        .unwrap();
+        std::fs::write(source.join("01-setup.md"), "# Setup\n\nInstall it.\n").unwrap();
+        std::fs::write(
+            source.join("deploys/rollback.md"),
+            "# Rollback\n\n## Steps\n\nBack to [setup](../01-setup.md).\n",
+        )
+        .unwrap();
+        let db_path = dir.join("guides.db");
+        crate::native_builder::build_project(&crate::native_builder::NativeBuildRequest {
+            source_path: &source,
+            db_path: &db_path,
+            collection_id: "guides",
+            collection_name: "Guides",
+            collection_icon: "",
+            incremental: false,
+        })
+        .unwrap();
+
+        let conn = rusqlite::Connection::open(&db_path).unwrap();
+        let pages: Vec<StaticPage> = conn
+            .prepare("SELECT slug, title, content_html FROM documents ORDER BY slug")
+            .unwrap()
+            .query_map([], |row| {
+                Ok(StaticPage {
+                    slug: row.get(0)?,
+                    title: row.get(1)?,
+                    section: None,
+                    content_html: row.get(2)?,
+                })
+            })
+            .unwrap()
+            .collect::<Result<_, _>>()
+            .unwrap();
+        let site = StaticSite {
+            title: "Team docs".to_string(),
+            collections: vec![],
+            pages,
+        };
+        let out = dir.join("site");
+        write_site(&out, &site, |_, _| {}).unwrap();
+
+        let read = |path: &str| std::fs::read_to_string(out.join(path)).unwrap();
+        let readme = read("docs/guides/index.html");
+        assert!(readme.contains("href=\"../../docs/guides/setup.html\""));
+        assert!(readme.contains("href=\"../../docs/guides/deploys/rollback.html#steps\""));
+        let rollback = read("docs/guides/deploys/rollback.html");
+        assert!(rollback.contains("href=\"../../../docs/guides/setup.html\""));
+        for page in [readme, rollback] {
+            assert!(!page.contains("href=\"/docs/"));
+        }
+        // The site's index is its own page, not the root README's
+        assert!(read("index.html").contains("<h1>Team docs</h1>"));
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn sidebar_nests_children_and_marks_the_current_page() {
+        let collections = vec![SiteCollection {
+            name: "Guides & Howtos".to_string(),
+            nodes: vec![
+                node("guides/setup", None, "Setup", 1),
+                node("guides/deploys", None, "Deploys", 2),
+                node(
+                    "guides/deploys/rollback",
+                    Some("guides/deploys"),
+                    "Rollback",
+                    1,
+                ),
+            ],
+        }];
+        let exported: HashSet<String> = ["guides/setup", "guides/deploys/rollback"]
+            .iter()
+            .map(|s| s.to_string())
+            .collect();
+        let html = sidebar_html(&collections, Some("guides/deploys/rollback"), &exported);
+        assert!(html.contains("<h2>Guides &amp; Howtos</h2>"));
+        assert!(html.contains("<a href=\"../../../docs/guides/setup.html\">Setup</a>"));
+        assert!(html.contains("<span>Deploys</span>\n<ul>\n<li><a href=\"../../../docs/guides/deploys/rollback.html\" aria-current=\"page\">Rollback</a>"));
+        assert!(html.find("Setup").unwrap() < html.find("Deploys").unwrap());
+    }
+
+    #[test]
+    fn writes_pages_index_and_search_entries() {
+        let dir = std::env::temp_dir().join(format!("dalil-static-site-{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        let site = StaticSite {
+            title: "Team docs".to_string(),
+            collections: vec![SiteCollection {
+                name: "Guides".to_string(),
+                nodes: vec![node("guides/setup", None, "Setup", 1)],
+            }],
+            pages: vec![
+                StaticPage {
+                    slug: "guides/setup".to_string(),
+                    title: "Setup".to_string(),
+                    section: Some("Start".to_string()),
+                    content_html: "<h1>Setup</h1><p>Install &amp; run.</p>".to_string(),
+                },
+                StaticPage {
+                    slug: "../escape".to_string(),
+                    title: "Escape".to_string(),
+                    section: None,
+                    content_html: String::new(),
+                },
+            ],
+        };
+        let mut calls = Vec::new();
+        let files = write_site(&dir, &site, |done, total| calls.push((done, total))).unwrap();
+        assert_eq!(files, 4);
+        assert_eq!(calls, [(1, 1)]);
+
+        let page = std::fs::read_to_string(dir.join("docs/guides/setup.html")).unwrap();
+        assert!(page.contains("<body data-root=\"../../\">"));
+        assert!(page.contains("<title>Setup · Team docs</title>"));
+        assert!(dir.join("index.html").exists());
+        assert!(!dir.parent().unwrap().join("escape.html").exists());
+
+        let index: serde_json::Value =
+            serde_json::from_slice(&std::fs::read(dir.join(SEARCH_INDEX_FILE)).unwrap()).unwrap();
+        assert_eq!(
+            index,
+            serde_json::json!([{
+                "slug": "guides/setup",
+                "title": "Setup",
+                "section": "Start",
+                "body": "Setup Install & run.",
+            }])
+        );
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,215 @@
+//! Query suggestions for the search box. Document titles and headings are
+//! kept in an in-memory index sorted by every word start, so a prefix lookup
+//! is a binary search and a short scan rather than a query.
+
+use crate::ai;
+use crate::models::QuerySuggestion;
+use rusqlite::Connection;
+
+/// A title or heading that can be suggested.
+struct Target {
+    kind: &'static str,
+    text: String,
+    /// `text` lowercased, which the index entries point into
+    lower: String,
+    slug: String,
+    anchor_id: Option<String>,
+}
+
+/// Order of a match: inside the text rather than at its start, a heading
+/// rather than a title, then text length and the text itself.
+type Rank<'a> = (bool, bool, usize, &'a str);
+
+/// Titles and headings of one project, looked up by prefix.
+pub struct SuggestionIndex {
+    targets: Vec<Target>,
+    /// (target, byte offset of a word start in its `lower`), sorted by the
+    /// text from that offset on
+    entries: Vec<(u32, u32)>,
+}
+
+impl SuggestionIndex {
+    /// Index every document title, and every heading when the builder
+    /// recorded them in `document_anchors`.
+    pub fn build(conn: &Connection) -> Result<Self, String> {
+        let mut targets = Vec::new();
+        let mut stmt = conn
+            .prepare("SELECT slug, title FROM documents")
+            .map_err(|e| e.to_string())?;
+        let rows = stmt
+            .query_map([], |row| Ok((row.get(0)?, row.get(1)?, None)))
+            .map_err(|e| e.to_string())?;
+        for row in rows {
+            let (slug, text, anchor_id) = row.map_err(|e| e.to_string())?;
+            targets.push(target("title", text, slug, anchor_id));
+        }
+        if ai::table_exists(conn, "document_anchors") {
+            let mut stmt = conn
+                .prepare(
+                    "SELECT d.slug, a.heading_text, a.anchor_id FROM document_anchors a \
+                     JOIN documents d ON d.id = a.document_id",
+                )
+                .map_err(|e| e.to_string())?;
+            let rows = stmt
+                .query_map([], |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?)))
+                .map_err(|e| e.to_string())?;
+            for row in rows {
+                let (slug, text, anchor_id) = row.map_err(|e| e.to_string())?;
+                targets.push(target("heading", text, slug, anchor_id));
+            }
+        }
+        Ok(Self::from_targets(targets))
+    }
+
+    fn from_targets(targets: Vec<Target>) -> Self {
+        let mut entries = Vec::new();
+        for (i, target) in targets.iter().enumerate() {
+            let mut previous_alphanumeric = false;
+            for (offset, c) in target.lower.char_indices() {
+                if c.is_alphanumeric() && !previous_alphanumeric {
+                    entries.push((i as u32, offset as u32));
+                }
+                previous_alphanumeric = c.is_alphanumeric();
+            }
+        }
+        let key =
+            |&(target, offset): &(u32, u32)| &targets[target as usize].lower[offset as usize..];
+        entries.sort_unstable_by(|a, b| key(a).cmp(key(b)));
+        Self { targets, entries }
+    }
+
+    fn key(&self, (target, offset): (u32, u32)) -> &str {
+        &self.targets[target as usize].lower[offset as usize..]
+    }
+
+    /// Titles and headings with a word starting with `prefix`, ignoring
+    /// case. Matches at the start of the text come first, then titles before
+    /// headings, then shorter text. A heading repeating its document's title
+    /// is left out. Only the best `limit` matches are kept while scanning, so
+    /// a short prefix matching much of the index stays cheap.
+    pub fn lookup(&self, prefix: &str, limit: usize) -> Vec<QuerySuggestion> {
+        let prefix = prefix.trim().to_lowercase();
+        if prefix.is_empty() || limit == 0 {
+            return vec![];
+        }
+        let start = self
+            .entries
+            .partition_point(|&entry| self.key(entry) < prefix.as_str());
+        // Best matches so far in rank order, at most one per (slug, text)
+        let mut best: Vec<(Rank, &Target)> = Vec::with_capacity(limit + 1);
+        for &(target, offset) in self.entries[start..]
+            .iter()
+            .take_while(|&&entry| self.key(entry).starts_with(&prefix))
+        {
+            let target = &self.targets[target as usize];
+            let rank = (
+                offset != 0,
+                target.kind != "title",
+                target.text.len(),
+                target.text.as_str(),
+            );
+            if best.len() == limit && best.last().is_some_and(|(last, _)| *last <= rank) {
+                continue;
+            }
+            if let Some(i) = best
+                .iter()
+                .position(|(_, t)| t.slug == target.slug && t.lower == target.lower)
+            {
+                if best[i].0 <= rank {
+                    continue;
+                }
+                best.remove(i);
+            }
+            let at = best.partition_point(|(other, _)| *other <= rank);
+            best.insert(at, (rank, target));
+            best.truncate(limit);
+        }
+
+        best.into_iter()
+            .map(|(_, target)| QuerySuggestion {
+                kind: target.kind.to_string(),
+                text: target.text.clone(),
+                slug: Some(target.slug.clone()),
+                anchor_id: target.anchor_id.clone(),
+            })
+            .collect()
+    }
+}
+
+fn target(kind: &'static str, text: String, slug: String, anchor_id: Option<String>) -> Target {
+    let text = text.trim().to_string();
+    Target {
+        kind,
+        lower: text.to_lowercase(),
+        text,
+        slug,
+        anchor_id,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::SuggestionIndex;
+    use rusqlite::Connection;
+
+    #[test]
+    fn prefixes_match_word_starts_in_titles_and_headings() {
+        let db = Connection::open_in_memory().unwrap();
+        db.execute_batch(
+            "CREATE TABLE documents (id INTEGER PRIMARY KEY, slug TEXT NOT NULL, title TEXT NOT NULL);
+            CREATE TABLE document_anchors (
+                document_id INTEGER NOT NULL,
+                anchor_id TEXT NOT NULL,
+                heading_text TEXT NOT NULL,
+                level INTEGER NOT NULL,
+                position INTEGER NOT NULL
+            );
+            INSERT INTO documents VALUES
+                (1, 'ops/deploys', 'Deploys'),
+                (2, 'ops/blue-green', 'Blue/green deploys'),
+                (3, 'ops/restarts', 'Restarts');
+            INSERT INTO document_anchors VALUES
+                (1, 'deploys', 'Deploys', 1, 0),
+                (1, 'deploying-on-fridays', 'Deploying on Fridays', 2, 1),
+                (3, 'after-a-deploy', 'After a deploy', 2, 0);",
+        )
+        .unwrap();
+        let index = SuggestionIndex::build(&db).unwrap();
+        let found = |prefix: &str, limit| {
+            index
+                .lookup(prefix, limit)
+                .into_iter()
+                .map(|s| (s.kind, s.text, s.anchor_id))
+                .collect::<Vec<_>>()
+        };
+        let owned = |kind: &str, text: &str, anchor: Option<&str>| {
+            (
+                kind.to_string(),
+                text.to_string(),
+                anchor.map(str::to_string),
+            )
+        };
+
+        assert_eq!(
+            found(" DEPLOY", 10),
+            [
+                owned("title", "Deploys", None),
+                owned(
+                    "heading",
+                    "Deploying on Fridays",
+                    Some("deploying-on-fridays")
+                ),
+                owned("title", "Blue/green deploys", None),
+                owned("heading", "After a deploy", Some("after-a-deploy")),
+            ]
+        );
+        assert_eq!(
+            found("green", 10),
+            [owned("title", "Blue/green deploys", None)]
+        );
+        assert_eq!(found("deploy", 1), found("deploy", 10)[..1]);
+        assert_eq!(found("deploy", 3), found("deploy", 10)[..3]);
+        assert!(found("", 10).is_empty());
+        assert!(found("zzz", 10).is_empty());
+    }
+}
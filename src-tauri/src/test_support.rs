@@ -0,0 +1,11 @@
+//! Helpers shared by unit tests.
+
+use std::path::PathBuf;
+
+/// An empty directory under the system temp dir, unique to this test run.
+pub fn scratch_dir(name: &str) -> PathBuf {
+    let dir = std::env::temp_dir().join(format!("dalil-{}-{}", name, std::process::id()));
+    let _ = std::fs::remove_dir_all(&dir);
+    std::fs::create_dir_all(&dir).unwrap();
+    dir
+}
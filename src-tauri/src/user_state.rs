@@ -1,8 +1,291 @@
-use rusqlite::Connection;
+use crate::models::{UsageSeries, UserStateError};
+use rusqlite::{Connection, OptionalExtension};
+use std::time::Duration;
 use tauri::{AppHandle, Manager};
 
 pub struct UserStateDb(pub std::sync::Mutex<Connection>);
 
+/// Stored as the database's `user_version`. Bump it whenever
+/// `init_user_state_db` changes the schema.
+pub const USER_STATE_SCHEMA_REVISION: i64 = 15;
+
+/// How long a statement waits for another connection's lock, such as a
+/// backup or maintenance pass, before failing with SQLITE_BUSY.
+pub const BUSY_TIMEOUT: Duration = Duration::from_secs(2);
+
+/// Further attempts at a write that still found the database busy, and the
+/// pause before the first of them (doubled each time).
+const BUSY_RETRIES: u32 = 3;
+const BUSY_BACKOFF: Duration = Duration::from_millis(100);
+
+/// How long soft-deleted user state stays restorable before it is purged.
+pub const TRASH_RETENTION_SECS: i64 = 30 * 24 * 60 * 60;
+
+/// Earlier versions of a note kept per document; older ones are pruned.
+pub const DOC_NOTE_REVISIONS_KEPT: i64 = 20;
+
+/// Edits within this long of a document's newest revision replace the note
+/// without keeping another one, so autosaves while typing collapse into one.
+pub const DOC_NOTE_REVISION_INTERVAL_SECS: i64 = 5 * 60;
+
+/// Permanently remove trashed items older than the retention window.
+pub fn purge_expired_deleted_items(conn: &Connection, now: i64) -> Result<usize, String> {
+    conn.execute(
+        "DELETE FROM deleted_items WHERE deleted_at < ?1",
+        rusqlite::params![now - TRASH_RETENTION_SECS],
+    )
+    .map_err(|e| format!("Failed to purge deleted items: {}", e))
+}
+
+/// Collections of a project the user has hidden from navigation.
+pub fn hidden_collections(conn: &Connection, project_id: &str) -> Result<Vec<String>, String> {
+    let mut stmt = conn
+        .prepare_cached(
+            "SELECT collection_id FROM collection_overrides WHERE project_id = ?1 AND hidden = 1",
+        )
+        .map_err(|e| e.to_string())?;
+    let rows = stmt
+        .query_map([project_id], |row| row.get(0))
+        .map_err(|e| e.to_string())?;
+    rows.collect::<Result<Vec<_>, _>>()
+        .map_err(|e| e.to_string())
+}
+
+/// Save a document's note. When the text changes, the note it replaces is kept
+/// in `doc_note_revisions`, dated when it was written and trimmed to the newest
+/// `DOC_NOTE_REVISIONS_KEPT`, unless the newest revision is less than
+/// `DOC_NOTE_REVISION_INTERVAL_SECS` old.
+/// Runs in a savepoint, so callers may already have a transaction open.
+pub fn write_doc_note(
+    conn: &Connection,
+    project_id: &str,
+    doc_slug: &str,
+    note: &str,
+    now: i64,
+) -> Result<(), String> {
+    write_doc_note_in_savepoint(conn, project_id, doc_slug, note, now, false)
+}
+
+/// Like `write_doc_note`, but the note being replaced is always kept as a
+/// revision, however recently the last one was taken.
+pub fn restore_doc_note(
+    conn: &Connection,
+    project_id: &str,
+    doc_slug: &str,
+    note: &str,
+    now: i64,
+) -> Result<(), String> {
+    write_doc_note_in_savepoint(conn, project_id, doc_slug, note, now, true)
+}
+
+fn write_doc_note_in_savepoint(
+    conn: &Connection,
+    project_id: &str,
+    doc_slug: &str,
+    note: &str,
+    now: i64,
+    always_capture: bool,
+) -> Result<(), String> {
+    conn.execute_batch("SAVEPOINT write_doc_note")
+        .map_err(|e| e.to_string())?;
+    let written = write_doc_note_rows(conn, project_id, doc_slug, note, now, always_capture);
+    let end = if written.is_ok() {
+        "RELEASE write_doc_note"
+    } else {
+        "ROLLBACK TO write_doc_note; RELEASE write_doc_note"
+    };
+    conn.execute_batch(end).map_err(|e| e.to_string())?;
+    written
+}
+
+fn write_doc_note_rows(
+    conn: &Connection,
+    project_id: &str,
+    doc_slug: &str,
+    note: &str,
+    now: i64,
+    always_capture: bool,
+) -> Result<(), String> {
+    let previous: Option<(String, i64)> = conn
+        .query_row(
+            "SELECT note, updated_at FROM doc_notes WHERE project_id = ?1 AND doc_slug = ?2",
+            rusqlite::params![project_id, doc_slug],
+            |row| Ok((row.get(0)?, row.get(1)?)),
+        )
+        .optional()
+        .map_err(|e| e.to_string())?;
+    let newest_revision_at: Option<i64> = conn
+        .query_row(
+            "SELECT MAX(created_at) FROM doc_note_revisions
+             WHERE project_id = ?1 AND doc_slug = ?2",
+            rusqlite::params![project_id, doc_slug],
+            |row| row.get(0),
+        )
+        .map_err(|e| e.to_string())?;
+    let recently_captured = !always_capture
+        && newest_revision_at.is_some_and(|at| now - at < DOC_NOTE_REVISION_INTERVAL_SECS);
+    if let Some((previous, written_at)) = previous
+        .filter(|(p, _)| p != note && !p.is_empty())
+        .filter(|_| !recently_captured)
+    {
+        conn.execute(
+            "INSERT INTO doc_note_revisions (project_id, doc_slug, note, created_at)
+             VALUES (?1, ?2, ?3, ?4)",
+            rusqlite::params![project_id, doc_slug, previous, written_at],
+        )
+        .map_err(|e| e.to_string())?;
+        conn.execute(
+            "DELETE FROM doc_note_revisions
+             WHERE project_id = ?1 AND doc_slug = ?2 AND id NOT IN (
+                 SELECT id FROM doc_note_revisions
+                 WHERE project_id = ?1 AND doc_slug = ?2
+                 ORDER BY id DESC LIMIT ?3
+             )",
+            rusqlite::params![project_id, doc_slug, DOC_NOTE_REVISIONS_KEPT],
+        )
+        .map_err(|e| e.to_string())?;
+    }
+    conn.execute(
+        "INSERT INTO doc_notes (project_id, doc_slug, note, updated_at)
+         VALUES (?1, ?2, ?3, ?4)
+         ON CONFLICT(project_id, doc_slug)
+         DO UPDATE SET note = excluded.note, updated_at = excluded.updated_at,
+             revision = doc_notes.revision + 1",
+        rusqlite::params![project_id, doc_slug, note, now],
+    )
+    .map_err(|e| e.to_string())?;
+    Ok(())
+}
+
+/// Whether `e` means another connection holds a lock the statement needed.
+pub fn is_busy(e: &rusqlite::Error) -> bool {
+    matches!(
+        e.sqlite_error_code(),
+        Some(rusqlite::ErrorCode::DatabaseBusy | rusqlite::ErrorCode::DatabaseLocked)
+    )
+}
+
+/// Run `write` in a transaction on the connection `lock` returns, retrying
+/// with backoff while the database is busy. The lock is released before each
+/// pause so other commands can use the connection meanwhile. A write that is
+/// still blocked after the retries fails with `UserStateError::Busy` so the
+/// UI can offer to try again.
+pub fn with_busy_retry<T, C: std::ops::Deref<Target = Connection>>(
+    mut lock: impl FnMut() -> Result<C, String>,
+    mut write: impl FnMut(&Connection) -> rusqlite::Result<T>,
+) -> Result<T, UserStateError> {
+    let mut backoff = BUSY_BACKOFF;
+    let mut attempt = 0;
+    loop {
+        let result = {
+            let conn = lock()?;
+            conn.unchecked_transaction().and_then(|tx| {
+                let value = write(&tx)?;
+                tx.commit()?;
+                Ok(value)
+            })
+        };
+        match result {
+            Err(e) if is_busy(&e) && attempt < BUSY_RETRIES => {
+                attempt += 1;
+                std::thread::sleep(backoff);
+                backoff *= 2;
+            }
+            result => return result.map_err(UserStateError::from),
+        }
+    }
+}
+
+/// Metrics counted in `local_stats`, in the order usage series are returned.
+pub const USAGE_METRICS: [&str; 4] = [
+    "questions_asked",
+    "searches",
+    "documents_opened",
+    "documents_viewed",
+];
+
+/// Add one to the count of `metric` for `project_id` on `day` (YYYY-MM-DD).
+pub fn record_usage(
+    conn: &Connection,
+    day: &str,
+    metric: &str,
+    project_id: &str,
+) -> Result<(), String> {
+    conn.prepare_cached(
+        "INSERT INTO local_stats (day, metric, project_id, count) VALUES (?1, ?2, ?3, 1)
+         ON CONFLICT(day, metric, project_id) DO UPDATE SET count = count + 1",
+    )
+    .and_then(|mut stmt| stmt.execute(rusqlite::params![day, metric, project_id]))
+    .map_err(|e| format!("Failed to record usage: {}", e))?;
+    Ok(())
+}
+
+/// The days a usage range covers, oldest first, ending with `today`.
+pub fn usage_range_days(range: &str, today: chrono::NaiveDate) -> Result<Vec<String>, String> {
+    let len = match range {
+        "week" => 7,
+        "month" => 30,
+        "quarter" => 90,
+        "year" => 365,
+        other => return Err(format!("Unknown usage range '{}'", other)),
+    };
+    Ok((0..len)
+        .rev()
+        .map(|back| {
+            (today - chrono::Days::new(back))
+                .format("%Y-%m-%d")
+                .to_string()
+        })
+        .collect())
+}
+
+/// Daily counts of each metric over `days`, zero where nothing was counted.
+/// `project_id` limits the counts to one project.
+pub fn usage_series(
+    conn: &Connection,
+    days: &[String],
+    project_id: Option<&str>,
+) -> Result<Vec<UsageSeries>, String> {
+    let (Some(first), Some(last)) = (days.first(), days.last()) else {
+        return Ok(vec![]);
+    };
+    let mut stmt = conn
+        .prepare_cached(
+            "SELECT metric, day, SUM(count) FROM local_stats
+             WHERE day BETWEEN ?1 AND ?2 AND (?3 IS NULL OR project_id = ?3)
+             GROUP BY metric, day",
+        )
+        .map_err(|e| e.to_string())?;
+    let rows = stmt
+        .query_map(rusqlite::params![first, last, project_id], |row| {
+            Ok((
+                row.get::<_, String>(0)?,
+                row.get::<_, String>(1)?,
+                row.get::<_, i64>(2)?,
+            ))
+        })
+        .map_err(|e| e.to_string())?;
+    let mut series: Vec<UsageSeries> = USAGE_METRICS
+        .iter()
+        .map(|metric| UsageSeries {
+            metric: metric.to_string(),
+            counts: vec![0; days.len()],
+            total: 0,
+        })
+        .collect();
+    for row in rows {
+        let (metric, day, count) = row.map_err(|e| e.to_string())?;
+        let Some(entry) = series.iter_mut().find(|s| s.metric == metric) else {
+            continue;
+        };
+        if let Ok(i) = days.binary_search(&day) {
+            entry.counts[i] = count;
+            entry.total += count;
+        }
+    }
+    Ok(series)
+}
+
 pub fn init_user_state_db(app: &AppHandle) -> Result<Connection, String> {
     let app_data_dir = app.path().app_data_dir().map_err(|e| e.to_string())?;
     std::fs::create_dir_all(&app_data_dir).map_err(|e| e.to_string())?;
@@ -15,7 +298,21 @@ pub fn init_user_state_db(app: &AppHandle) -> Result<Connection, String> {
             | rusqlite::OpenFlags::SQLITE_OPEN_NO_MUTEX,
     )
     .map_err(|e| format!("Failed to open user state DB at {:?}: {}", db_path, e))?;
+    conn.busy_timeout(BUSY_TIMEOUT)
+        .map_err(|e| format!("Failed to set user state busy timeout: {}", e))?;
+    apply_user_state_schema(&conn)?;
+
+    let now = std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .map(|d| d.as_secs() as i64)
+        .unwrap_or_default();
+    purge_expired_deleted_items(&conn, now)?;
 
+    Ok(conn)
+}
+
+/// Create the user state tables, or bring an older install's up to date.
+pub fn apply_user_state_schema(conn: &Connection) -> Result<(), String> {
     conn.execute_batch(
         "
         PRAGMA journal_mode = WAL;
@@ -68,6 +365,22 @@ pub fn init_user_state_db(app: &AppHandle) -> Result<Connection, String> {
             FOREIGN KEY(bookmark_id) REFERENCES bookmarks(id) ON DELETE CASCADE
         );
 
+        CREATE TABLE IF NOT EXISTS annotation_folder_items (
+            folder_id INTEGER NOT NULL,
+            kind TEXT NOT NULL,
+            item_id INTEGER NOT NULL,
+            PRIMARY KEY(folder_id, kind, item_id),
+            FOREIGN KEY(folder_id) REFERENCES bookmark_folders(id) ON DELETE CASCADE
+        );
+
+        CREATE TABLE IF NOT EXISTS annotation_tag_items (
+            tag_id INTEGER NOT NULL,
+            kind TEXT NOT NULL,
+            item_id INTEGER NOT NULL,
+            PRIMARY KEY(tag_id, kind, item_id),
+            FOREIGN KEY(tag_id) REFERENCES bookmark_tags(id) ON DELETE CASCADE
+        );
+
         CREATE TABLE IF NOT EXISTS bookmark_events (
             id INTEGER PRIMARY KEY AUTOINCREMENT,
             bookmark_id INTEGER NOT NULL,
@@ -83,11 +396,43 @@ pub fn init_user_state_db(app: &AppHandle) -> Result<Connection, String> {
             PRIMARY KEY(project_id, doc_slug)
         );
 
+        CREATE TABLE IF NOT EXISTS closed_documents (
+            id INTEGER PRIMARY KEY AUTOINCREMENT,
+            project_id TEXT NOT NULL,
+            doc_slug TEXT NOT NULL,
+            anchor_id TEXT,
+            closed_at INTEGER NOT NULL
+        );
+
+        CREATE TABLE IF NOT EXISTS recent_searches (
+            project_id TEXT NOT NULL,
+            query TEXT NOT NULL,
+            searched_at INTEGER NOT NULL,
+            PRIMARY KEY (project_id, query)
+        );
+
         CREATE TABLE IF NOT EXISTS doc_notes (
+            id INTEGER PRIMARY KEY,
             project_id TEXT NOT NULL,
             doc_slug TEXT NOT NULL,
             note TEXT NOT NULL DEFAULT '',
             updated_at INTEGER NOT NULL,
+            UNIQUE(project_id, doc_slug)
+        );
+
+        CREATE TABLE IF NOT EXISTS doc_note_revisions (
+            id INTEGER PRIMARY KEY AUTOINCREMENT,
+            project_id TEXT NOT NULL,
+            doc_slug TEXT NOT NULL,
+            note TEXT NOT NULL,
+            created_at INTEGER NOT NULL
+        );
+
+        CREATE TABLE IF NOT EXISTS doc_note_drafts (
+            project_id TEXT NOT NULL,
+            doc_slug TEXT NOT NULL,
+            draft TEXT NOT NULL,
+            updated_at INTEGER NOT NULL,
             PRIMARY KEY(project_id, doc_slug)
         );
 
@@ -109,7 +454,86 @@ pub fn init_user_state_db(app: &AppHandle) -> Result<Connection, String> {
             committed_at TEXT NOT NULL,
             changed_files_json TEXT NOT NULL,
             changed_doc_slugs_json TEXT NOT NULL,
-            recorded_at INTEGER NOT NULL
+            recorded_at INTEGER NOT NULL,
+            message TEXT NOT NULL DEFAULT ''
+        );
+
+        CREATE TABLE IF NOT EXISTS deleted_items (
+            id INTEGER PRIMARY KEY AUTOINCREMENT,
+            project_id TEXT NOT NULL,
+            kind TEXT NOT NULL,
+            payload_json TEXT NOT NULL,
+            restore_hints_json TEXT NOT NULL DEFAULT '{}',
+            deleted_at INTEGER NOT NULL
+        );
+
+        -- Rows a trash restore could not put back at their old id, so
+        -- later restores can find them by the id their payload recorded
+        CREATE TABLE IF NOT EXISTS trash_id_remaps (
+            project_id TEXT NOT NULL,
+            kind TEXT NOT NULL,
+            original_id INTEGER NOT NULL,
+            current_id INTEGER NOT NULL,
+            PRIMARY KEY(project_id, kind, original_id)
+        );
+
+        CREATE TABLE IF NOT EXISTS last_sessions (
+            project_id TEXT PRIMARY KEY,
+            collection_id TEXT NOT NULL,
+            doc_slug TEXT NOT NULL,
+            anchor_id TEXT,
+            updated_at INTEGER NOT NULL
+        );
+
+        CREATE TABLE IF NOT EXISTS doc_removals (
+            project_id TEXT NOT NULL,
+            doc_slug TEXT NOT NULL,
+            removed_at INTEGER NOT NULL,
+            PRIMARY KEY(project_id, doc_slug)
+        );
+
+        CREATE TABLE IF NOT EXISTS slug_migrations (
+            id INTEGER PRIMARY KEY AUTOINCREMENT,
+            project_id TEXT NOT NULL,
+            old_slug TEXT NOT NULL,
+            new_slug TEXT NOT NULL,
+            table_name TEXT NOT NULL,
+            rows_changed INTEGER NOT NULL,
+            migrated_at INTEGER NOT NULL
+        );
+
+        CREATE TABLE IF NOT EXISTS collection_overrides (
+            project_id TEXT NOT NULL,
+            collection_id TEXT NOT NULL,
+            custom_description TEXT,
+            custom_icon TEXT,
+            pinned INTEGER NOT NULL DEFAULT 0,
+            hidden INTEGER NOT NULL DEFAULT 0,
+            updated_at INTEGER NOT NULL,
+            PRIMARY KEY(project_id, collection_id)
+        );
+
+        CREATE TABLE IF NOT EXISTS project_activations (
+            id INTEGER PRIMARY KEY AUTOINCREMENT,
+            project_id TEXT NOT NULL,
+            activated_at INTEGER NOT NULL
+        );
+
+        CREATE TABLE IF NOT EXISTS local_stats (
+            day TEXT NOT NULL,
+            metric TEXT NOT NULL,
+            project_id TEXT NOT NULL,
+            count INTEGER NOT NULL DEFAULT 0,
+            PRIMARY KEY(day, metric, project_id)
+        );
+
+        CREATE TABLE IF NOT EXISTS inbox_items (
+            id INTEGER PRIMARY KEY AUTOINCREMENT,
+            project_id TEXT,
+            content TEXT NOT NULL,
+            created_at INTEGER NOT NULL,
+            resolved_at INTEGER,
+            resolved_doc_slug TEXT
         );
 
         CREATE INDEX IF NOT EXISTS idx_bookmarks_project_updated
@@ -120,12 +544,39 @@ pub fn init_user_state_db(app: &AppHandle) -> Result<Connection, String> {
             ON bookmarks(project_id, title_snapshot);
         CREATE INDEX IF NOT EXISTS idx_doc_views_project_last_viewed
             ON doc_views(project_id, last_viewed_at DESC);
+        CREATE INDEX IF NOT EXISTS idx_closed_documents_project
+            ON closed_documents(project_id, id DESC);
         CREATE INDEX IF NOT EXISTS idx_doc_notes_project_doc
             ON doc_notes(project_id, doc_slug);
+        CREATE INDEX IF NOT EXISTS idx_doc_note_revisions_project_doc
+            ON doc_note_revisions(project_id, doc_slug, id DESC);
         CREATE INDEX IF NOT EXISTS idx_doc_highlights_project_doc
             ON doc_highlights(project_id, doc_slug, created_at DESC);
         CREATE INDEX IF NOT EXISTS idx_change_feed_project_recorded
             ON project_change_feed(project_id, recorded_at DESC);
+        CREATE INDEX IF NOT EXISTS idx_project_activations_project
+            ON project_activations(project_id, activated_at DESC);
+        CREATE INDEX IF NOT EXISTS idx_deleted_items_project_deleted
+            ON deleted_items(project_id, deleted_at DESC);
+        CREATE INDEX IF NOT EXISTS idx_inbox_items_project_created
+            ON inbox_items(project_id, created_at DESC);
+        CREATE INDEX IF NOT EXISTS idx_annotation_folder_items_item
+            ON annotation_folder_items(kind, item_id);
+        CREATE INDEX IF NOT EXISTS idx_annotation_tag_items_item
+            ON annotation_tag_items(kind, item_id);
+
+        -- Notes and highlights can't carry foreign keys from
+        -- the shared relation tables, so their relations go when they do
+        CREATE TRIGGER IF NOT EXISTS doc_notes_delete_relations
+        AFTER DELETE ON doc_notes BEGIN
+            DELETE FROM annotation_folder_items WHERE kind = 'note' AND item_id = OLD.id;
+            DELETE FROM annotation_tag_items WHERE kind = 'note' AND item_id = OLD.id;
+        END;
+        CREATE TRIGGER IF NOT EXISTS doc_highlights_delete_relations
+        AFTER DELETE ON doc_highlights BEGIN
+            DELETE FROM annotation_folder_items WHERE kind = 'highlight' AND item_id = OLD.id;
+            DELETE FROM annotation_tag_items WHERE kind = 'highlight' AND item_id = OLD.id;
+        END;
         ",
     )
     .map_err(|e| format!("Failed to initialise user state DB schema: {}", e))?;
@@ -161,6 +612,92 @@ pub fn init_user_state_db(app: &AppHandle) -> Result<Connection, String> {
         .map_err(|e| format!("Failed to add bookmarks.open_count column: {}", e))?;
     }
 
+    let has_message_column: i64 = conn
+        .query_row(
+            "SELECT COUNT(*) FROM pragma_table_info('project_change_feed') WHERE name = 'message'",
+            [],
+            |row| row.get(0),
+        )
+        .map_err(|e| format!("Failed to inspect change feed schema: {}", e))?;
+    if has_message_column == 0 {
+        conn.execute(
+            "ALTER TABLE project_change_feed ADD COLUMN message TEXT NOT NULL DEFAULT ''",
+            [],
+        )
+        .map_err(|e| format!("Failed to add project_change_feed.message column: {}", e))?;
+    }
+
+    let has_hidden_column: i64 = conn
+        .query_row(
+            "SELECT COUNT(*) FROM pragma_table_info('collection_overrides') WHERE name = 'hidden'",
+            [],
+            |row| row.get(0),
+        )
+        .map_err(|e| format!("Failed to inspect collection overrides schema: {}", e))?;
+    if has_hidden_column == 0 {
+        conn.execute(
+            "ALTER TABLE collection_overrides ADD COLUMN hidden INTEGER NOT NULL DEFAULT 0",
+            [],
+        )
+        .map_err(|e| format!("Failed to add collection_overrides.hidden column: {}", e))?;
+    }
+
+    // Counts saves of a note, which can land within the same second
+    let has_revision_column: i64 = conn
+        .query_row(
+            "SELECT COUNT(*) FROM pragma_table_info('doc_notes') WHERE name = 'revision'",
+            [],
+            |row| row.get(0),
+        )
+        .map_err(|e| format!("Failed to inspect doc notes schema: {}", e))?;
+    if has_revision_column == 0 {
+        conn.execute(
+            "ALTER TABLE doc_notes ADD COLUMN revision INTEGER NOT NULL DEFAULT 0",
+            [],
+        )
+        .map_err(|e| format!("Failed to add doc_notes.revision column: {}", e))?;
+    }
+
+    // Annotation relations key notes by id. Older installs used the implicit
+    // rowid, which VACUUM may renumber, so the table is rebuilt keeping each
+    // note's rowid as its id.
+    let has_note_id_column: i64 = conn
+        .query_row(
+            "SELECT COUNT(*) FROM pragma_table_info('doc_notes') WHERE name = 'id'",
+            [],
+            |row| row.get(0),
+        )
+        .map_err(|e| format!("Failed to inspect doc notes schema: {}", e))?;
+    if has_note_id_column == 0 {
+        conn.execute_batch(
+            "BEGIN;
+            CREATE TABLE doc_notes_with_id (
+                id INTEGER PRIMARY KEY,
+                project_id TEXT NOT NULL,
+                doc_slug TEXT NOT NULL,
+                note TEXT NOT NULL DEFAULT '',
+                updated_at INTEGER NOT NULL,
+                revision INTEGER NOT NULL DEFAULT 0,
+                UNIQUE(project_id, doc_slug)
+            );
+            INSERT INTO doc_notes_with_id (id, project_id, doc_slug, note, updated_at, revision)
+                SELECT rowid, project_id, doc_slug, note, updated_at, revision FROM doc_notes;
+            DROP TABLE doc_notes;
+            ALTER TABLE doc_notes_with_id RENAME TO doc_notes;
+            CREATE INDEX idx_doc_notes_project_doc ON doc_notes(project_id, doc_slug);
+            CREATE TRIGGER doc_notes_delete_relations
+            AFTER DELETE ON doc_notes BEGIN
+                DELETE FROM annotation_folder_items WHERE kind = 'note' AND item_id = OLD.id;
+                DELETE FROM annotation_tag_items WHERE kind = 'note' AND item_id = OLD.id;
+            END;
+            COMMIT;",
+        )
+        .map_err(|e| {
+            let _ = conn.execute_batch("ROLLBACK");
+            format!("Failed to add doc_notes.id column: {}", e)
+        })?;
+    }
+
     conn.execute(
         "CREATE INDEX IF NOT EXISTS idx_bookmarks_project_favorite
          ON bookmarks(project_id, is_favorite DESC, updated_at DESC)",
@@ -173,6 +710,193 @@ pub fn init_user_state_db(app: &AppHandle) -> Result<Connection, String> {
         [],
     )
     .map_err(|e| format!("Failed to create bookmarks open-count index: {}", e))?;
+    conn.pragma_update(None, "user_version", USER_STATE_SCHEMA_REVISION)
+        .map_err(|e| format!("Failed to record user state schema revision: {}", e))?;
+    Ok(())
+}
 
-    Ok(conn)
+#[cfg(test)]
+mod tests {
+    use super::{
+        record_usage, usage_range_days, usage_series, with_busy_retry, write_doc_note,
+        DOC_NOTE_REVISIONS_KEPT, DOC_NOTE_REVISION_INTERVAL_SECS,
+    };
+    use crate::models::UserStateError;
+    use rusqlite::Connection;
+    use std::time::Duration;
+
+    #[test]
+    fn busy_writes_are_retried_then_reported_as_busy() {
+        let path = std::env::temp_dir().join(format!("dalil-busy-{}.db", std::process::id()));
+        let _ = std::fs::remove_file(&path);
+        let conn = Connection::open(&path).unwrap();
+        conn.execute_batch("CREATE TABLE bookmarks (id INTEGER PRIMARY KEY, title TEXT);")
+            .unwrap();
+        conn.busy_timeout(Duration::from_millis(10)).unwrap();
+
+        let holder = Connection::open(&path).unwrap();
+        holder.execute_batch("BEGIN IMMEDIATE").unwrap();
+        let mut attempts = 0;
+        let blocked = with_busy_retry(
+            || Ok(&conn),
+            |tx| {
+                attempts += 1;
+                tx.execute("INSERT INTO bookmarks (title) VALUES ('a')", [])
+            },
+        );
+        assert!(matches!(blocked, Err(UserStateError::Busy { .. })));
+        assert_eq!(attempts, 4);
+
+        // Released while the writer is backing off
+        let release = std::thread::spawn(move || {
+            std::thread::sleep(Duration::from_millis(50));
+            holder.execute_batch("COMMIT").unwrap();
+        });
+        with_busy_retry(
+            || Ok(&conn),
+            |tx| tx.execute("INSERT INTO bookmarks (title) VALUES ('b')", []),
+        )
+        .unwrap();
+        release.join().unwrap();
+        let count: i64 = conn
+            .query_row("SELECT COUNT(*) FROM bookmarks", [], |row| row.get(0))
+            .unwrap();
+        assert_eq!(count, 1);
+        let _ = std::fs::remove_file(&path);
+    }
+
+    #[test]
+    fn usage_series_are_daily_and_zero_filled() {
+        let conn = Connection::open_in_memory().unwrap();
+        conn.execute_batch(
+            "CREATE TABLE local_stats (
+                day TEXT NOT NULL,
+                metric TEXT NOT NULL,
+                project_id TEXT NOT NULL,
+                count INTEGER NOT NULL DEFAULT 0,
+                PRIMARY KEY(day, metric, project_id)
+            );",
+        )
+        .unwrap();
+        for (day, metric, project) in [
+            ("2026-03-01", "searches", "handbook"),
+            ("2026-03-01", "searches", "handbook"),
+            ("2026-03-01", "searches", "team"),
+            ("2026-03-07", "questions_asked", "team"),
+            ("2026-02-20", "searches", "team"),
+        ] {
+            record_usage(&conn, day, metric, project).unwrap();
+        }
+
+        let today = chrono::NaiveDate::from_ymd_opt(2026, 3, 7).unwrap();
+        let days = usage_range_days("week", today).unwrap();
+        assert_eq!(days.first().unwrap(), "2026-03-01");
+        assert_eq!(days.last().unwrap(), "2026-03-07");
+        assert!(usage_range_days("decade", today).is_err());
+
+        let all = usage_series(&conn, &days, None).unwrap();
+        let searches = all.iter().find(|s| s.metric == "searches").unwrap();
+        assert_eq!(searches.counts, [3, 0, 0, 0, 0, 0, 0]);
+        assert_eq!(searches.total, 3);
+        let questions = all.iter().find(|s| s.metric == "questions_asked").unwrap();
+        assert_eq!(questions.counts[6], 1);
+
+        let team = usage_series(&conn, &days, Some("team")).unwrap();
+        assert_eq!(team.iter().map(|s| s.total).sum::<i64>(), 2);
+    }
+
+    #[test]
+    fn overwritten_notes_are_kept_as_capped_revisions() {
+        let conn = Connection::open_in_memory().unwrap();
+        super::apply_user_state_schema(&conn).unwrap();
+        let revisions = |conn: &Connection| -> Vec<String> {
+            let mut stmt = conn
+                .prepare("SELECT note FROM doc_note_revisions ORDER BY id DESC")
+                .unwrap();
+            let rows = stmt.query_map([], |row| row.get(0)).unwrap();
+            rows.collect::<Result<_, _>>().unwrap()
+        };
+
+        let interval = DOC_NOTE_REVISION_INTERVAL_SECS;
+        write_doc_note(&conn, "p", "intro", "first", 1).unwrap();
+        write_doc_note(&conn, "p", "intro", "first", 2).unwrap();
+        assert!(revisions(&conn).is_empty());
+        write_doc_note(&conn, "p", "intro", "second", 3).unwrap();
+        assert_eq!(revisions(&conn), ["first"]);
+        let created_at: i64 = conn
+            .query_row("SELECT created_at FROM doc_note_revisions", [], |row| {
+                row.get(0)
+            })
+            .unwrap();
+        assert_eq!(created_at, 2);
+
+        // Autosaves soon after the last revision replace the note in place
+        write_doc_note(&conn, "p", "intro", "second draft", 4).unwrap();
+        write_doc_note(&conn, "p", "intro", "third", interval).unwrap();
+        assert_eq!(revisions(&conn), ["first"]);
+        write_doc_note(&conn, "p", "intro", "fourth", 2 + interval).unwrap();
+        assert_eq!(revisions(&conn), ["third", "first"]);
+
+        for n in 0..DOC_NOTE_REVISIONS_KEPT + 5 {
+            let at = (n + 3) * interval;
+            write_doc_note(&conn, "p", "intro", &format!("edit {}", n), at).unwrap();
+        }
+        let kept = revisions(&conn);
+        assert_eq!(kept.len() as i64, DOC_NOTE_REVISIONS_KEPT);
+        assert_eq!(kept[0], format!("edit {}", DOC_NOTE_REVISIONS_KEPT + 3));
+        let note: String = conn
+            .query_row("SELECT note FROM doc_notes", [], |row| row.get(0))
+            .unwrap();
+        assert_eq!(note, format!("edit {}", DOC_NOTE_REVISIONS_KEPT + 4));
+
+        // Callers such as inbox filing write notes inside their own transaction
+        let tx = conn.unchecked_transaction().unwrap();
+        write_doc_note(&tx, "p", "intro", "filed", 100 * interval).unwrap();
+        tx.rollback().unwrap();
+        let note: String = conn
+            .query_row("SELECT note FROM doc_notes", [], |row| row.get(0))
+            .unwrap();
+        assert_eq!(note, format!("edit {}", DOC_NOTE_REVISIONS_KEPT + 4));
+    }
+
+    #[test]
+    fn notes_keep_their_rowid_as_id_when_the_table_is_rebuilt() {
+        let conn = Connection::open_in_memory().unwrap();
+        conn.execute_batch(
+            "CREATE TABLE doc_notes (
+                project_id TEXT NOT NULL,
+                doc_slug TEXT NOT NULL,
+                note TEXT NOT NULL DEFAULT '',
+                updated_at INTEGER NOT NULL,
+                PRIMARY KEY(project_id, doc_slug)
+            );
+            INSERT INTO doc_notes VALUES ('p', 'intro', 'first', 1), ('p', 'setup', 'second', 2);
+            DELETE FROM doc_notes WHERE doc_slug = 'intro';",
+        )
+        .unwrap();
+        super::apply_user_state_schema(&conn).unwrap();
+        conn.execute_batch(
+            "INSERT INTO bookmark_folders (id, project_id, name, created_at, updated_at)
+                 VALUES (1, 'p', 'Ops', 0, 0);
+            INSERT INTO annotation_folder_items VALUES (1, 'note', 2);",
+        )
+        .unwrap();
+
+        let id: i64 = conn
+            .query_row(
+                "SELECT id FROM doc_notes WHERE doc_slug = 'setup'",
+                [],
+                |row| row.get(0),
+            )
+            .unwrap();
+        assert_eq!(id, 2);
+        write_doc_note(&conn, "p", "setup", "edited", 3).unwrap();
+        conn.execute("DELETE FROM doc_notes", []).unwrap();
+        let relations: i64 = conn
+            .query_row("SELECT COUNT(*) FROM annotation_folder_items", [], |row| {
+                row.get(0)
+            })
+            .unwrap();
+        assert_eq!(relations, 0);
+    }
 }
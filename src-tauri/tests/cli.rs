@@ -0,0 +1,282 @@
+use rusqlite::Connection;
+use std::path::{Path, PathBuf};
+use std::process::{Command, Output};
+
+/// Build a small project database with the schema from scripts/lib/create-database.ts.
+fn fixture_db(name: &str) -> PathBuf {
+    let dir = std::env::temp_dir().join(format!("dalil-cli-tests-{}", std::process::id()));
+    std::fs::create_dir_all(&dir).expect("create fixture dir");
+    let path = dir.join(format!("{}.db", name));
+    let _ = std::fs::remove_file(&path);
+
+    let conn = Connection::open(&path).expect("create fixture db");
+    conn.execute_batch(
+        "CREATE TABLE collections (
+            id TEXT PRIMARY KEY,
+            name TEXT NOT NULL,
+            icon TEXT NOT NULL,
+            description TEXT,
+            sort_order INTEGER NOT NULL DEFAULT 0
+        );
+        CREATE TABLE documents (
+            id INTEGER PRIMARY KEY AUTOINCREMENT,
+            collection_id TEXT NOT NULL REFERENCES collections(id),
+            slug TEXT NOT NULL UNIQUE,
+            title TEXT NOT NULL,
+            section TEXT NOT NULL DEFAULT '',
+            sort_order INTEGER NOT NULL DEFAULT 999,
+            parent_slug TEXT NOT NULL DEFAULT '',
+            content_html TEXT NOT NULL,
+            content_raw TEXT NOT NULL,
+            path TEXT NOT NULL,
+            last_modified TEXT NOT NULL DEFAULT ''
+        );
+        CREATE TABLE tags (id INTEGER PRIMARY KEY AUTOINCREMENT, tag TEXT NOT NULL UNIQUE);
+        CREATE TABLE document_tags (
+            document_id INTEGER NOT NULL,
+            tag_id INTEGER NOT NULL,
+            PRIMARY KEY (document_id, tag_id)
+        );
+        CREATE VIRTUAL TABLE documents_fts USING fts5(title, content, section, collection, tags);
+        CREATE TABLE chunks (
+            id INTEGER PRIMARY KEY AUTOINCREMENT,
+            document_id INTEGER NOT NULL,
+            chunk_index INTEGER NOT NULL,
+            content_text TEXT NOT NULL,
+            heading_context TEXT NOT NULL DEFAULT ''
+        );
+        CREATE TABLE chunk_embeddings (chunk_id INTEGER PRIMARY KEY, embedding BLOB);
+
+        INSERT INTO collections (id, name, icon, sort_order) VALUES
+            ('guides', 'Guides', 'document', 0),
+            ('ops', 'Operations', 'document', 1);
+        INSERT INTO documents (id, collection_id, slug, title, section, content_html, content_raw, path) VALUES
+            (1, 'guides', 'guides/deployments', 'Deployments', 'Shipping', '<p>How we deploy services</p>', 'How we deploy services', 'deployments.md'),
+            (2, 'ops', 'ops/on-call', 'On-call', 'Support', '<p>Rota and deploy freezes</p>', 'Rota and deploy freezes', 'on-call.md');
+        INSERT INTO documents_fts (rowid, title, content, section, collection, tags) VALUES
+            (1, 'Deployments', 'How we deploy services', 'Shipping', 'guides', 'release'),
+            (2, 'On-call', 'Rota and deploy freezes', 'Support', 'ops', '');
+        INSERT INTO tags (id, tag) VALUES (1, 'release');
+        INSERT INTO document_tags (document_id, tag_id) VALUES (1, 1);
+        INSERT INTO chunks (document_id, chunk_index, content_text) VALUES
+            (1, 0, 'How we deploy services'),
+            (2, 0, 'Rota and deploy freezes');",
+    )
+    .expect("populate fixture db");
+    path
+}
+
+fn run_cli(args: &[&str]) -> Output {
+    Command::new(env!("CARGO_BIN_EXE_dalil"))
+        .arg("--cli")
+        .args(args)
+        .output()
+        .expect("run dalil --cli")
+}
+
+fn stdout_json(output: &Output) -> serde_json::Value {
+    assert!(
+        output.status.success(),
+        "CLI failed: {}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+    serde_json::from_slice(&output.stdout).expect("CLI prints JSON")
+}
+
+fn path_arg(path: &Path) -> &str {
+    path.to_str().expect("fixture path is UTF-8")
+}
+
+#[test]
+fn stats_reports_row_counts() {
+    let db = fixture_db("stats");
+    let stats = stdout_json(&run_cli(&["stats", "--db", path_arg(&db)]));
+
+    assert_eq!(stats["documentCount"], 2);
+    assert_eq!(stats["collectionCount"], 2);
+    assert_eq!(stats["tagCount"], 1);
+    assert_eq!(stats["chunkCount"], 2);
+    assert_eq!(stats["embeddingCount"], 0);
+    assert!(stats["dbSizeBytes"].as_u64().unwrap_or(0) > 0);
+}
+
+#[test]
+fn search_returns_matching_documents() {
+    let db = fixture_db("search");
+    let results = stdout_json(&run_cli(&[
+        "search",
+        "--db",
+        path_arg(&db),
+        "--query",
+        "deploy",
+    ]));
+    let slugs: Vec<&str> = results
+        .as_array()
+        .expect("results array")
+        .iter()
+        .filter_map(|r| r["slug"].as_str())
+        .collect();
+
+    assert_eq!(slugs.len(), 2);
+    assert!(slugs.contains(&"guides/deployments"));
+    assert!(slugs.contains(&"ops/on-call"));
+}
+
+#[test]
+fn search_can_filter_by_collection_and_limit() {
+    let db = fixture_db("search-filter");
+    let filtered = stdout_json(&run_cli(&[
+        "search",
+        "--db",
+        path_arg(&db),
+        "--query",
+        "deploy",
+        "--collection",
+        "ops",
+    ]));
+    assert_eq!(filtered.as_array().map(Vec::len), Some(1));
+    assert_eq!(filtered[0]["slug"], "ops/on-call");
+
+    let limited = stdout_json(&run_cli(&[
+        "search",
+        "--db",
+        path_arg(&db),
+        "--query",
+        "deploy",
+        "--limit",
+        "1",
+    ]));
+    assert_eq!(limited.as_array().map(Vec::len), Some(1));
+}
+
+#[test]
+fn missing_database_fails_with_error() {
+    let output = run_cli(&["stats", "--db", "/nonexistent/dalil-cli-test.db"]);
+    assert_eq!(output.status.code(), Some(1));
+    assert!(String::from_utf8_lossy(&output.stderr).contains("database not found"));
+}
+
+#[test]
+fn missing_arguments_print_usage() {
+    let output = run_cli(&["search", "--query", "deploy"]);
+    assert_eq!(output.status.code(), Some(2));
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(stderr.contains("Missing required argument --db"));
+    assert!(stderr.contains("Usage:"));
+}
+
+/// Write a small Markdown source tree and return (source dir, output db path).
+fn fixture_source(name: &str) -> (PathBuf, PathBuf) {
+    let root = std::env::temp_dir().join(format!("dalil-cli-{}-{}", name, std::process::id()));
+    let source = root.join("team-handbook");
+    let _ = std::fs::remove_dir_all(&root);
+    std::fs::create_dir_all(source.join("01-getting-started")).expect("create source dir");
+    std::fs::write(source.join("README.md"), "# Team Handbook\n\nStart here.\n")
+        .expect("write README");
+    std::fs::write(
+        source.join("01-getting-started/01-setup.md"),
+        "---\ntitle: Local setup\ntags: [onboarding, tooling]\n---\n\n# Setup\n\nInstall the toolchain, then read the [deploy guide](02-deploys.md#rollback).\n",
+    )
+    .expect("write setup doc");
+    std::fs::write(
+        source.join("01-getting-started/02-deploys.md"),
+        "# Deploys\n\n## Rollback\n\nRevert the release tag.\n",
+    )
+    .expect("write deploys doc");
+    (source, root.join("out/project.db"))
+}
+
+fn run_build(source: &Path, output: &Path, extra: &[&str]) -> serde_json::Value {
+    let result = Command::new(env!("CARGO_BIN_EXE_dalil"))
+        .env_remove("OPENAI_API_KEY")
+        .args([
+            "--cli",
+            "build",
+            "--source",
+            path_arg(source),
+            "--output",
+            path_arg(output),
+        ])
+        .args(extra)
+        .output()
+        .expect("run dalil --cli build");
+    stdout_json(&result)
+}
+
+#[test]
+fn build_creates_database_from_markdown() {
+    let (source, output) = fixture_source("build");
+    let stats = run_build(&source, &output, &[]);
+    assert_eq!(stats["documentCount"], 3);
+    assert_eq!(stats["collectionCount"], 1);
+    assert_eq!(stats["tagCount"], 2);
+    assert_eq!(stats["embeddingCount"], 0);
+    assert_eq!(stats["build"]["documentsRebuilt"], 3);
+
+    let conn = Connection::open(&output).expect("open built db");
+    let (title, html): (String, String) = conn
+        .query_row(
+            "SELECT title, content_html FROM documents WHERE slug = 'team-handbook/getting-started/setup'",
+            [],
+            |row| Ok((row.get(0)?, row.get(1)?)),
+        )
+        .expect("setup document");
+    assert_eq!(title, "Local setup");
+    assert!(html.contains("href=\"/docs/team-handbook/getting-started/deploys#rollback\""));
+
+    let anchor: String = conn
+        .query_row(
+            "SELECT a.anchor_id FROM document_anchors a JOIN documents d ON d.id = a.document_id \
+             WHERE d.slug = 'team-handbook/getting-started/deploys' AND a.level = 2",
+            [],
+            |row| row.get(0),
+        )
+        .expect("rollback anchor");
+    assert_eq!(anchor, "rollback");
+
+    let section_children: i64 = conn
+        .query_row(
+            "SELECT COUNT(*) FROM navigation_tree WHERE parent_slug = 'getting-started'",
+            [],
+            |row| row.get(0),
+        )
+        .expect("navigation rows");
+    assert_eq!(section_children, 2);
+
+    let results = stdout_json(&run_cli(&[
+        "search",
+        "--db",
+        path_arg(&output),
+        "--query",
+        "toolchain",
+    ]));
+    assert_eq!(results[0]["slug"], "team-handbook/getting-started/setup");
+}
+
+#[test]
+fn rebuild_only_reprocesses_changed_files() {
+    let (source, output) = fixture_source("incremental");
+    run_build(&source, &output, &[]);
+
+    std::fs::write(
+        source.join("01-getting-started/02-deploys.md"),
+        "# Deploys\n\n## Rollback\n\nRevert the release tag and page the on-call engineer.\n",
+    )
+    .expect("edit deploys doc");
+    let incremental = run_build(&source, &output, &[]);
+    assert_eq!(incremental["build"]["documentsReused"], 2);
+    assert_eq!(incremental["build"]["documentsRebuilt"], 1);
+
+    let results = stdout_json(&run_cli(&[
+        "search",
+        "--db",
+        path_arg(&output),
+        "--query",
+        "engineer",
+    ]));
+    assert_eq!(results[0]["slug"], "team-handbook/getting-started/deploys");
+
+    let forced = run_build(&source, &output, &["--force"]);
+    assert_eq!(forced["build"]["documentsReused"], 0);
+    assert_eq!(forced["build"]["documentsRebuilt"], 3);
+}
@@ -1,18 +1,35 @@
 import { ref, computed } from 'vue'
 import { listen } from '@tauri-apps/api/event'
-import { askQuestion, cancelAiRequest } from '@/lib/api'
+import {
+  askAboutDocument,
+  askQuestion,
+  cancelAiRequest,
+  compareDocuments,
+  warmOllamaModel,
+} from '@/lib/api'
 import { useSettings } from './useSettings'
 import type { AiProvider } from '@/lib/types'
 
 export interface AiSourceReference {
+  sourceKind: 'document' | 'note' | 'highlight'
   chunkId: number
   documentId: number
   docSlug: string
   docTitle: string
   headingContext: string
+  /** Heading to scroll to when the source opens; null when none matched. */
+  anchorId: string | null
   excerpt: string
 }
 
+/** The document most of an answer's sources came from. */
+export interface DominantDocument {
+  docSlug: string
+  docTitle: string
+  /** Share of the sources' combined score, 0 to 100. */
+  sharePercent: number
+}
+
 export interface ConversationEntry {
   id: string
   question: string
@@ -22,26 +39,57 @@ export interface ConversationEntry {
   provider: AiProvider | null
   timestamp: number
   sources: AiSourceReference[]
+  /** Set when one document carries most of the sources, to offer opening it. */
+  dominantDocument: DominantDocument | null
+  /** Search terms to try when nothing in the handbook matched the question. */
+  suggestedTerms: string[] | null
+  /** What the answer is waiting on before its first content arrives. */
+  status: AiResponsePhase | null
 }
 
-interface AiResponseChunkEvent {
+export type AiResponsePhase = 'embedding' | 'retrieving' | 'connecting' | 'generating'
+
+/** Fields every `ai-response-*` event carries. */
+interface AiEventBase {
+  /** Payload version; see getEventSchemaVersion. */
+  schemaVersion: number
   requestId: string
+}
+
+interface AiResponseChunkEvent extends AiEventBase {
   content: string
 }
 
-interface AiResponseDoneEvent {
-  requestId: string
+interface AiResponseDoneEvent extends AiEventBase {
   cancelled: boolean
+  /** Present when Anthropic prompt caching was used. */
+  promptCache?: {
+    cacheCreationInputTokens: number
+    cacheReadInputTokens: number
+    hit: boolean
+  }
 }
 
-interface AiResponseErrorEvent {
-  requestId: string
+interface AiResponseErrorEvent extends AiEventBase {
   message: string
+  /** Set when the connection dropped mid-answer; the text so far is kept. */
+  partialContentLength: number | null
 }
 
-interface AiResponseSourcesEvent {
-  requestId: string
+interface AiResponseSourcesEvent extends AiEventBase {
   sources: AiSourceReference[]
+  dominantDocument: DominantDocument | null
+}
+
+interface AiResponseStatusEvent extends AiEventBase {
+  phase: AiResponsePhase
+  elapsedMs: number
+  heartbeat: boolean
+}
+
+interface AiResponseNoContextEvent extends AiEventBase {
+  bestScore: number | null
+  suggestedTerms: string[]
 }
 
 const isOpen = ref(false)
@@ -60,7 +108,14 @@ function createRequestId(): string {
 async function ensureListeners() {
   if (listenersReady.value) return
 
-  const [unlistenChunk, unlistenDone, unlistenError, unlistenSources] = await Promise.all([
+  const [
+    unlistenChunk,
+    unlistenDone,
+    unlistenError,
+    unlistenSources,
+    unlistenNoContext,
+    unlistenStatus,
+  ] = await Promise.all([
     listen<AiResponseChunkEvent>('ai-response-chunk', (event) => {
       const entry = entryByRequest.get(event.payload.requestId)
       if (!entry) return
@@ -86,10 +141,28 @@ async function ensureListeners() {
       const entry = entryByRequest.get(event.payload.requestId)
       if (!entry) return
       entry.sources = event.payload.sources ?? []
+      entry.dominantDocument = event.payload.dominantDocument ?? null
+    }),
+    listen<AiResponseNoContextEvent>('ai-response-no-context', (event) => {
+      const entry = entryByRequest.get(event.payload.requestId)
+      if (!entry) return
+      entry.suggestedTerms = event.payload.suggestedTerms
+    }),
+    listen<AiResponseStatusEvent>('ai-response-status', (event) => {
+      const entry = entryByRequest.get(event.payload.requestId)
+      if (!entry) return
+      entry.status = event.payload.phase
     }),
   ])
 
-  unlistenFns.value = [unlistenChunk, unlistenDone, unlistenError, unlistenSources]
+  unlistenFns.value = [
+    unlistenChunk,
+    unlistenDone,
+    unlistenError,
+    unlistenSources,
+    unlistenNoContext,
+    unlistenStatus,
+  ]
   listenersReady.value = true
 }
 
@@ -106,6 +179,8 @@ export function useAI() {
 
   function open() {
     isOpen.value = true
+    // Best-effort: load the Ollama model while the question is being typed
+    warmOllamaModel(true).catch(() => {})
   }
 
   function close() {
@@ -113,7 +188,11 @@ export function useAI() {
   }
 
   function toggle() {
-    isOpen.value = !isOpen.value
+    if (isOpen.value) {
+      close()
+    } else {
+      open()
+    }
   }
 
   function clearConversation() {
@@ -121,31 +200,74 @@ export function useAI() {
     entryByRequest.clear()
   }
 
-  async function ask(text: string, provider?: AiProvider) {
-    if (!text.trim()) return
-
-    await ensureListeners()
-
+  function startEntry(question: string, provider?: AiProvider): ConversationEntry {
     const entry: ConversationEntry = {
       id: createRequestId(),
-      question: text.trim(),
+      question,
       response: '',
       loading: true,
       error: null,
       provider: provider ?? null,
       timestamp: Date.now(),
       sources: [],
+      dominantDocument: null,
+      suggestedTerms: null,
+      status: null,
     }
 
     conversations.value.push(entry)
     entryByRequest.set(entry.id, entry)
+    return entry
+  }
+
+  function failEntry(entry: ConversationEntry, e: unknown) {
+    entry.error = e instanceof Error ? e.message : String(e)
+    entry.loading = false
+    entryByRequest.delete(entry.id)
+  }
+
+  async function ask(text: string, provider?: AiProvider) {
+    if (!text.trim()) return
+
+    await ensureListeners()
+    const entry = startEntry(text.trim(), provider)
 
     try {
       await askQuestion(text.trim(), entry.id, provider)
     } catch (e) {
-      entry.error = e instanceof Error ? e.message : String(e)
-      entry.loading = false
-      entryByRequest.delete(entry.id)
+      failEntry(entry, e)
+    }
+  }
+
+  /** Ask about one document using its whole text rather than search results. */
+  async function askAboutDoc(docSlug: string, docTitle: string, question?: string, provider?: AiProvider) {
+    const text = question?.trim() || 'Explain this page.'
+
+    open()
+    await ensureListeners()
+    const entry = startEntry(`${text} (${docTitle})`, provider)
+
+    try {
+      await askAboutDocument(docSlug, text, entry.id, provider)
+    } catch (e) {
+      failEntry(entry, e)
+    }
+  }
+
+  /** Ask for the differences, contradictions and gaps between two documents. */
+  async function compareDocs(
+    first: { slug: string, title: string },
+    second: { slug: string, title: string },
+    provider?: AiProvider,
+  ) {
+    open()
+    await ensureListeners()
+    const entry = startEntry(`Compare "${first.title}" and "${second.title}"`, provider)
+
+    try {
+      await compareDocuments(first.slug, second.slug, entry.id, provider)
+    } catch (e) {
+      failEntry(entry, e)
     }
   }
 
@@ -179,6 +301,8 @@ export function useAI() {
     toggle,
     clearConversation,
     ask,
+    askAboutDoc,
+    compareDocs,
     cancelCurrent,
     disposeListeners,
   }
@@ -16,6 +16,7 @@ import {
   bulkDeleteBookmarks as bulkDeleteBookmarksApi,
   bulkSetBookmarkFolder as bulkSetBookmarkFolderApi,
   bulkSetBookmarkTags as bulkSetBookmarkTagsApi,
+  bulkSetBookmarkFavorite as bulkSetBookmarkFavoriteApi,
 } from '@/lib/api'
 import type { Bookmark, BookmarkFolder, BookmarkTagEntity, BookmarkRelations } from '@/lib/types'
 
@@ -267,6 +268,17 @@ export function useBookmarks() {
     })
   }
 
+  async function bulkSetFavorite(projectId: string, bookmarkIds: number[], isFavorite: boolean): Promise<number> {
+    const changed = await bulkSetBookmarkFavoriteApi(projectId, bookmarkIds, isFavorite)
+    if (changed > 0) {
+      const idSet = new Set(bookmarkIds)
+      bookmarks.value = bookmarks.value.map((bookmark) =>
+        idSet.has(bookmark.id) ? { ...bookmark, isFavorite } : bookmark,
+      )
+    }
+    return changed
+  }
+
   return {
     bookmarks,
     folders,
@@ -293,6 +305,7 @@ export function useBookmarks() {
     bulkDelete,
     bulkSetFolder,
     bulkSetTags,
+    bulkSetFavorite,
     repairTarget,
   }
 }
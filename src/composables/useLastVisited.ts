@@ -1,29 +1,64 @@
 import { watch } from 'vue'
 import type { Router } from 'vue-router'
+import { getActiveProjectId, getLastSession, saveLastSession } from '@/lib/api'
+import { docSlugWithoutCollection } from '@/lib/deepLinks'
+import { useProjects } from '@/composables/useProjects'
 
 const STORAGE_KEY = 'dalil:last-path'
 
 export function useLastVisited(router: Router) {
-  // Persist the current path on every navigation
+  const { activeProjectId } = useProjects()
+
+  // Persist the current path on every navigation, and the open document
+  // and section per project so the next launch can return to it
   watch(
     () => router.currentRoute.value.fullPath,
     (path) => {
       if (path && path !== '/') {
         try { localStorage.setItem(STORAGE_KEY, path) } catch { /* ignore */ }
       }
+      const route = router.currentRoute.value
+      if (route.name !== 'doc' || !activeProjectId.value) return
+      const collection = route.params.collection as string
+      const slug = Array.isArray(route.params.slug) ? route.params.slug.join('/') : route.params.slug
+      if (!collection || !slug) return
+      const anchorId = route.hash ? decodeURIComponent(route.hash.slice(1)) : null
+      saveLastSession(activeProjectId.value, collection, `${collection}/${slug}`, anchorId)
+        .catch(() => { /* ignore */ })
     },
   )
 
-  // Restore the last visited path on initial load (only when landing on home)
-  function restoreIfHome() {
-    if (router.currentRoute.value.path === '/') {
-      try {
-        const last = localStorage.getItem(STORAGE_KEY)
-        if (last && last !== '/') {
-          router.replace(last)
-        }
-      } catch { /* ignore */ }
-    }
+  function restoreStoredPath() {
+    try {
+      const last = localStorage.getItem(STORAGE_KEY)
+      if (last && last !== '/') {
+        router.replace(last)
+      }
+    } catch { /* ignore */ }
+  }
+
+  // Restore the last session on initial load (only when landing on home),
+  // falling back to the last visited path
+  async function restoreIfHome() {
+    if (router.currentRoute.value.path !== '/') return
+    try {
+      const projectId = activeProjectId.value || await getActiveProjectId()
+      const session = await getLastSession(projectId)
+      // A deep link or the user may have navigated while this was loading
+      if (router.currentRoute.value.path !== '/') return
+      if (session) {
+        await router.replace({
+          name: 'doc',
+          params: {
+            collection: session.collectionId,
+            slug: docSlugWithoutCollection(session.collectionId, session.docSlug),
+          },
+          hash: session.anchorId ? `#${session.anchorId}` : '',
+        })
+        return
+      }
+    } catch { /* fall back to the stored path */ }
+    if (router.currentRoute.value.path === '/') restoreStoredPath()
   }
 
   return { restoreIfHome }
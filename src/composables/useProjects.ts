@@ -61,10 +61,10 @@ export function useProjects() {
     }
   }
 
-  async function rebuildProject(id: string) {
+  async function rebuildProject(id: string, force?: boolean) {
     buildStatus.value.set(id, 'building')
     try {
-      await rebuildProjectApi(id)
+      const summary = await rebuildProjectApi(id, force)
       buildStatus.value.set(id, 'complete')
 
       // If rebuilding the active project, reload collections
@@ -72,6 +72,7 @@ export function useProjects() {
         const { reload } = useCollections()
         await reload()
       }
+      return summary
     } catch (e) {
       buildStatus.value.set(id, 'error')
       throw e
@@ -1,5 +1,5 @@
 import { ref, watch } from 'vue'
-import { searchDocuments } from '@/lib/api'
+import { recordRecentSearch, searchDocuments } from '@/lib/api'
 import type { SearchResult } from '@/lib/types'
 import { useBookmarks } from './useBookmarks'
 import { useDocActivity } from './useDocActivity'
@@ -50,6 +50,7 @@ function clearSearch() {
 function recordSelection(result: SearchResult) {
   usageBySlug.value = updateSearchUsage(usageBySlug.value, result.slug)
   saveSearchUsage(usageBySlug.value)
+  recordRecentSearch(query.value).catch(() => {})
 }
 
 function performSearch() {
@@ -5,50 +5,214 @@ import type {
   Document,
   SearchResult,
   Tag,
+  TagCoverage,
+  QuerySuggestion,
   Chunk,
   Settings,
   AiProvider,
+  OrphanedProject,
+  OrphanRecovery,
   Project,
   ProjectStats,
+  ProjectRemovalPreview,
+  ProjectSwitchTiming,
+  SimilarDocument,
+  TagSuggestions,
+  ExplainedChunk,
+  RagTrace,
+  RetrievalCase,
+  RetrievalConfig,
+  RetrievalEvaluation,
+  StorageBreakdown,
+  DocumentListing,
+  IntegrityReport,
+  SearchDiagnostics,
+  PruneSummary,
+  QuantizeSummary,
+  EmbeddingRunSummary,
+  AiStatus,
   AppPreferences,
   Bookmark,
   DocActivityItem,
+  ClosedDocument,
   BookmarkFolder,
   BookmarkTagEntity,
   BookmarkRelations,
+  CollectionOverride,
   DocNote,
+  DocNoteRevision,
+  DocNoteWithDraft,
   DocHighlight,
+  AnnotationRef,
+  AnnotationListItem,
+  FlashcardExport,
+  InboxItem,
+  InboxDestination,
+  UserStateSnapshotSummary,
   ProjectChangeFeedItem,
+  ChangeDigest,
+  TermDefinition,
+  GlossaryTerm,
+  DocumentLocation,
+  DocumentAnchor,
+  BookmarkValidation,
+  ProjectHome,
+  CollectionBadge,
+  HeaderSummary,
+  DeletedItem,
+  RecentProject,
+  LastSession,
+  SpotlightExportSummary,
+  BuildSummary,
+  QueuedBuild,
+  OrphanedUserState,
+  ExternalAnnotationImport,
+  ShareLink,
+  BuildEnvironmentReport,
+  BuildLogEntry,
+  AboutInfo,
+  ReleaseNotes,
+  CrashReport,
+  UsageRange,
+  UsageStats,
+  CollectionSort,
+  TagSort,
+  TagDetail,
+  StaticSiteExport,
 } from './types'
 
-export async function getCollections(): Promise<Collection[]> {
-  return invoke('get_collections')
+export async function getCollections(
+  includeHidden?: boolean,
+  sort?: CollectionSort,
+): Promise<Collection[]> {
+  return invoke('get_collections', { includeHidden, sort })
+}
+
+export async function setCollectionOverride(
+  projectId: string,
+  collectionId: string,
+  customDescription: string | null,
+  customIcon: string | null,
+  pinned: boolean,
+): Promise<CollectionOverride> {
+  return invoke('set_collection_override', {
+    projectId,
+    collectionId,
+    customDescription,
+    customIcon,
+    pinned,
+  })
+}
+
+export async function setCollectionHidden(
+  projectId: string,
+  collectionId: string,
+  hidden: boolean,
+): Promise<CollectionOverride> {
+  return invoke('set_collection_hidden', { projectId, collectionId, hidden })
 }
 
 export async function getNavigation(collectionId: string): Promise<NavigationNode[]> {
   return invoke('get_navigation', { collectionId })
 }
 
-export async function getDocument(slug: string): Promise<Document> {
-  return invoke('get_document', { slug })
+export async function getDocument(
+  slug: string,
+  highlightTerms?: string[],
+): Promise<Document> {
+  return invoke('get_document', { slug, highlightTerms })
 }
 
 export async function searchDocuments(
   query: string,
   collectionId?: string,
   limit?: number,
+  snippetTokens?: number,
+  dedupeSimilarTitles?: boolean,
 ): Promise<SearchResult[]> {
-  return invoke('search_documents', { query, collectionId, limit })
+  return invoke('search_documents', {
+    query,
+    collectionId,
+    limit,
+    snippetTokens,
+    dedupeSimilarTitles,
+  })
+}
+
+export async function suggestQueries(prefix: string, limit?: number): Promise<QuerySuggestion[]> {
+  return invoke('suggest_queries', { prefix, limit })
 }
 
-export async function getTags(collectionId?: string): Promise<Tag[]> {
-  return invoke('get_tags', { collectionId })
+export async function recordRecentSearch(query: string): Promise<void> {
+  return invoke('record_recent_search', { query })
+}
+
+export async function getTags(collectionId?: string, sort?: TagSort): Promise<Tag[]> {
+  return invoke('get_tags', { collectionId, sort })
+}
+
+export async function getTagCoverage(): Promise<TagCoverage[]> {
+  return invoke('get_tag_coverage')
 }
 
 export async function getDocumentsByTag(tag: string): Promise<SearchResult[]> {
   return invoke('get_documents_by_tag', { tag })
 }
 
+export async function getTermDefinition(term: string): Promise<TermDefinition | null> {
+  return invoke('get_term_definition', { term })
+}
+
+export async function listGlossaryTerms(): Promise<GlossaryTerm[]> {
+  return invoke('list_glossary_terms')
+}
+
+export async function setProjectGlossaryCollection(
+  projectId: string,
+  glossaryCollection: string | null,
+): Promise<void> {
+  return invoke('set_project_glossary_collection', { projectId, glossaryCollection })
+}
+
+export async function setProjectLocalOnly(projectId: string, localOnly: boolean): Promise<void> {
+  return invoke('set_project_local_only', { projectId, localOnly })
+}
+
+export async function setProjectExcludedCollections(
+  projectId: string,
+  collectionIds: string[],
+): Promise<void> {
+  return invoke('set_project_excluded_collections', { projectId, collectionIds })
+}
+
+export async function setProjectRetrievalConfig(
+  projectId: string,
+  config: RetrievalConfig | null,
+): Promise<void> {
+  return invoke('set_project_retrieval_config', { projectId, config })
+}
+
+export async function locateDocument(query: string, limit?: number): Promise<DocumentLocation[]> {
+  return invoke('locate_document', { query, limit })
+}
+
+export async function getDocumentAnchors(slug: string): Promise<DocumentAnchor[]> {
+  return invoke('get_document_anchors', { slug })
+}
+
+export async function getDocumentOutline(slug: string): Promise<DocumentAnchor[]> {
+  return invoke('get_document_outline', { slug })
+}
+
+export async function createShareLink(
+  projectId: string,
+  docSlug: string,
+  anchorId?: string,
+  selectedText?: string,
+): Promise<ShareLink> {
+  return invoke('create_share_link', { projectId, docSlug, anchorId, selectedText })
+}
+
 export async function getSimilarChunks(
   queryEmbedding: number[],
   limit?: number,
@@ -56,6 +220,29 @@ export async function getSimilarChunks(
   return invoke('get_similar_chunks', { queryEmbedding, limit })
 }
 
+export async function hybridSearchChunks(
+  query: string,
+  queryEmbedding?: number[],
+  limit?: number,
+): Promise<ExplainedChunk[]> {
+  return invoke('hybrid_search_chunks', { query, queryEmbedding, limit })
+}
+
+/** Development aid: hit@k of the active project's retrieval settings over known answers. */
+export async function evaluateRetrieval(
+  cases: RetrievalCase[],
+  k?: number,
+): Promise<RetrievalEvaluation> {
+  return invoke('evaluate_retrieval', { cases, k })
+}
+
+export async function getSimilarDocuments(
+  docSlug: string,
+  limit?: number,
+): Promise<SimilarDocument[]> {
+  return invoke('get_similar_documents', { docSlug, limit })
+}
+
 export async function getSettings(): Promise<Settings> {
   return invoke('get_settings')
 }
@@ -68,18 +255,72 @@ export async function testProvider(provider: AiProvider): Promise<string> {
   return invoke('test_provider', { provider })
 }
 
+export async function warmOllamaModel(onlyIfPreferred?: boolean): Promise<void> {
+  return invoke('warm_ollama_model', { onlyIfPreferred })
+}
+
 export async function askQuestion(
   question: string,
   requestId: string,
   provider?: AiProvider,
+  queue?: boolean,
+  allowGeneralKnowledge?: boolean,
+  answerLanguage?: string,
+): Promise<void> {
+  return invoke('ask_question', {
+    question,
+    requestId,
+    provider,
+    queue,
+    allowGeneralKnowledge,
+    answerLanguage,
+  })
+}
+
+export async function askAboutDocument(
+  docSlug: string,
+  question: string,
+  requestId: string,
+  provider?: AiProvider,
+  queue?: boolean,
+  answerLanguage?: string,
+): Promise<void> {
+  return invoke('ask_about_document', {
+    docSlug,
+    question,
+    requestId,
+    provider,
+    queue,
+    answerLanguage,
+  })
+}
+
+export async function compareDocuments(
+  firstSlug: string,
+  secondSlug: string,
+  requestId: string,
+  provider?: AiProvider,
+  queue?: boolean,
 ): Promise<void> {
-  return invoke('ask_question', { question, requestId, provider })
+  return invoke('compare_documents', { firstSlug, secondSlug, requestId, provider, queue })
+}
+
+export async function suggestDocumentTags(
+  docSlug: string,
+  provider?: AiProvider,
+): Promise<TagSuggestions> {
+  return invoke('suggest_document_tags', { docSlug, provider })
 }
 
 export async function cancelAiRequest(requestId: string): Promise<void> {
   return invoke('cancel_ai_request', { requestId })
 }
 
+/** The pipeline trace of a question, when RAG traces were enabled. */
+export async function getRagTrace(requestId: string): Promise<RagTrace | null> {
+  return invoke('get_rag_trace', { requestId })
+}
+
 export async function listProjects(): Promise<Project[]> {
   return invoke('list_projects')
 }
@@ -88,26 +329,171 @@ export async function getActiveProjectId(): Promise<string> {
   return invoke('get_active_project_id')
 }
 
-export async function setActiveProject(projectId: string): Promise<void> {
+export async function setActiveProject(projectId: string): Promise<ProjectSwitchTiming> {
   return invoke('set_active_project', { projectId })
 }
 
+export async function getLastSession(projectId: string): Promise<LastSession | null> {
+  return invoke('get_last_session', { projectId })
+}
+
+export async function saveLastSession(
+  projectId: string,
+  collectionId: string,
+  docSlug: string,
+  anchorId?: string | null,
+): Promise<void> {
+  return invoke('save_last_session', { projectId, collectionId, docSlug, anchorId })
+}
+
+export async function getRecentProjects(limit?: number): Promise<RecentProject[]> {
+  return invoke('get_recent_projects', { limit })
+}
+
 export async function addProject(name: string, icon: string, sourcePath: string): Promise<Project> {
   return invoke('add_project', { name, icon, sourcePath })
 }
 
-export async function rebuildProject(projectId: string): Promise<void> {
-  return invoke('rebuild_project', { projectId })
+export async function addProjectFromDb(name: string, icon: string, dbPath: string): Promise<Project> {
+  return invoke('add_project_from_db', { name, icon, dbPath })
+}
+
+export async function listOrphanedProjects(): Promise<OrphanedProject[]> {
+  return invoke('list_orphaned_projects')
 }
 
-export async function removeProject(projectId: string): Promise<void> {
-  return invoke('remove_project', { projectId })
+export async function recoverOrphanedProjects(projects: OrphanRecovery[]): Promise<Project[]> {
+  return invoke('recover_orphaned_projects', { projects })
+}
+
+export async function restoreRegistryBackup(): Promise<Project[]> {
+  return invoke('restore_registry_backup')
+}
+
+export async function diagnoseBuildEnvironment(): Promise<BuildEnvironmentReport> {
+  return invoke('diagnose_build_environment')
+}
+
+export async function getAboutInfo(): Promise<AboutInfo> {
+  return invoke('get_about_info')
+}
+
+export async function getReleaseNotes(): Promise<ReleaseNotes> {
+  return invoke('get_release_notes')
+}
+
+export async function getLastCrashReport(): Promise<CrashReport | null> {
+  return invoke('get_last_crash_report')
+}
+
+export async function getBuildLogs(projectId: string): Promise<BuildLogEntry[]> {
+  return invoke('get_build_logs', { projectId })
+}
+
+export async function readBuildLog(path: string): Promise<string> {
+  return invoke('read_build_log', { path })
+}
+
+export async function rebuildProject(projectId: string, force?: boolean): Promise<BuildSummary> {
+  return invoke('rebuild_project', { projectId, force })
+}
+
+export async function reloadDocument(projectId: string, filePath: string): Promise<Document> {
+  return invoke('reload_document', { projectId, filePath })
+}
+
+export async function getBuildQueue(): Promise<QueuedBuild[]> {
+  return invoke('get_build_queue')
+}
+
+export async function cancelQueuedBuild(buildId: number): Promise<void> {
+  return invoke('cancel_queued_build', { buildId })
+}
+
+export async function getProjectRemovalPreview(projectId: string): Promise<ProjectRemovalPreview> {
+  return invoke('get_project_removal_preview', { projectId })
+}
+
+export async function removeProject(projectId: string, purgeUserState = true): Promise<void> {
+  return invoke('remove_project', { projectId, purgeUserState })
 }
 
 export async function getProjectStats(projectId: string): Promise<ProjectStats> {
   return invoke('get_project_stats', { projectId })
 }
 
+/** `forceRefresh` re-probes Ollama rather than using the cached verdict. */
+export async function getAiStatus(forceRefresh?: boolean): Promise<AiStatus> {
+  return invoke('get_ai_status', { forceRefresh })
+}
+
+export async function listAllDocuments(
+  projectId: string,
+  options: { collectionId?: string; limit?: number; offset?: number } = {},
+): Promise<DocumentListing> {
+  return invoke('list_all_documents', { projectId, ...options })
+}
+
+/** Writes the report to `destination` and resolves to the number of rows. */
+export async function exportReport(
+  projectId: string,
+  kind: 'documents' | 'tags' | 'bookmarks' | 'annotations',
+  format: 'csv' | 'json',
+  destination: string,
+  excelBom = false,
+): Promise<number> {
+  return invoke('export_report', { projectId, kind, format, destination, excelBom })
+}
+
+/** Write a read-only HTML copy of the project for static hosting; every collection when none are given. */
+export async function exportStaticSite(
+  projectId: string,
+  outputDir: string,
+  collectionIds?: string[],
+): Promise<StaticSiteExport> {
+  return invoke('export_static_site', { projectId, collectionIds, outputDir })
+}
+
+export async function getProjectIntegrityReport(): Promise<IntegrityReport> {
+  return invoke('get_project_integrity_report')
+}
+
+/** Version of the `ai-response-*` event payloads this app build emits. */
+export async function getEventSchemaVersion(): Promise<number> {
+  return invoke('get_event_schema_version')
+}
+
+export async function getStorageBreakdown(projectId: string): Promise<StorageBreakdown> {
+  return invoke('get_storage_breakdown', { projectId })
+}
+
+export async function getSearchDiagnostics(
+  projectId: string,
+  query?: string,
+): Promise<SearchDiagnostics> {
+  return invoke('get_search_diagnostics', { projectId, query })
+}
+
+export async function pruneEmbeddings(
+  projectId: string,
+  minChunkChars: number,
+  excludedCollections?: string[],
+): Promise<PruneSummary> {
+  return invoke('prune_embeddings', { projectId, minChunkChars, excludedCollections })
+}
+
+export async function generateMissingEmbeddings(
+  projectId: string,
+  requestId: string,
+  concurrency?: number,
+): Promise<EmbeddingRunSummary> {
+  return invoke('generate_missing_embeddings', { projectId, requestId, concurrency })
+}
+
+export async function convertEmbeddingsToInt8(projectId: string): Promise<QuantizeSummary> {
+  return invoke('convert_embeddings_to_int8', { projectId })
+}
+
 export async function openInEditor(editorCommand: string, path: string): Promise<void> {
   return invoke('open_in_editor', { editorCommand, path })
 }
@@ -120,6 +506,31 @@ export async function savePreferences(preferences: AppPreferences): Promise<void
   return invoke('save_preferences', { preferences })
 }
 
+export async function regenerateHttpApiToken(): Promise<AppPreferences> {
+  return invoke('regenerate_http_api_token')
+}
+
+export async function showQuickSearch(): Promise<void> {
+  return invoke('show_quick_search')
+}
+
+export async function hideQuickSearch(): Promise<void> {
+  return invoke('hide_quick_search')
+}
+
+export async function openQuickSearchResult(
+  collectionId: string,
+  slug: string,
+  anchorId?: string,
+): Promise<void> {
+  return invoke('open_quick_search_result', { collectionId, slug, anchorId })
+}
+
+/** macOS only; requires the spotlightExportEnabled preference. */
+export async function exportSpotlightIndex(projectId?: string): Promise<SpotlightExportSummary[]> {
+  return invoke('export_spotlight_index', { projectId })
+}
+
 export async function listBookmarks(
   projectId: string,
   query?: string,
@@ -176,6 +587,10 @@ export async function setBookmarkFavorite(bookmarkId: number, isFavorite: boolea
   return invoke('set_bookmark_favorite', { bookmarkId, isFavorite })
 }
 
+export async function validateBookmarks(projectId: string): Promise<BookmarkValidation[]> {
+  return invoke('validate_bookmarks', { projectId })
+}
+
 export async function listBookmarkFolders(projectId: string): Promise<BookmarkFolder[]> {
   return invoke('list_bookmark_folders', { projectId })
 }
@@ -216,6 +631,14 @@ export async function bulkSetBookmarkFolder(
   return invoke('bulk_set_bookmark_folder', { projectId, bookmarkIds, folderId })
 }
 
+export async function bulkSetBookmarkFavorite(
+  projectId: string,
+  bookmarkIds: number[],
+  isFavorite: boolean,
+): Promise<number> {
+  return invoke('bulk_set_bookmark_favorite', { projectId, bookmarkIds, isFavorite })
+}
+
 export async function bulkSetBookmarkTags(
   projectId: string,
   bookmarkIds: number[],
@@ -232,6 +655,14 @@ export async function markDocumentViewed(
   return invoke('mark_document_viewed', { projectId, docSlug, viewedAt })
 }
 
+export async function markAllViewed(
+  projectId: string,
+  collectionId?: string,
+  parentSlug?: string,
+): Promise<number> {
+  return invoke('mark_all_viewed', { projectId, collectionId, parentSlug })
+}
+
 export async function getRecentDocuments(
   projectId: string,
   limit?: number,
@@ -239,6 +670,22 @@ export async function getRecentDocuments(
   return invoke('get_recent_documents', { projectId, limit })
 }
 
+export async function recordDocumentClosed(
+  projectId: string,
+  docSlug: string,
+  anchorId?: string | null,
+): Promise<void> {
+  return invoke('record_document_closed', { projectId, docSlug, anchorId })
+}
+
+export async function getRecentlyClosed(
+  projectId: string,
+  openSlugs: string[],
+  limit?: number,
+): Promise<ClosedDocument[]> {
+  return invoke('get_recently_closed', { projectId, openSlugs, limit })
+}
+
 export async function getUpdatedDocuments(
   projectId: string,
   limit?: number,
@@ -246,6 +693,10 @@ export async function getUpdatedDocuments(
   return invoke('get_updated_documents', { projectId, limit })
 }
 
+export async function getTagDetail(projectId: string, tag: string): Promise<TagDetail> {
+  return invoke('get_tag_detail', { projectId, tag })
+}
+
 export async function getProjectChangeFeed(
   projectId: string,
   limit?: number,
@@ -253,12 +704,59 @@ export async function getProjectChangeFeed(
   return invoke('get_project_change_feed', { projectId, limit })
 }
 
+export async function generateChangeDigest(
+  projectId: string,
+  since: number,
+  until?: number,
+  useAi?: boolean,
+): Promise<ChangeDigest> {
+  return invoke('generate_change_digest', { projectId, since, until, useAi })
+}
+
+export async function getProjectHome(projectId: string): Promise<ProjectHome> {
+  return invoke('get_project_home', { projectId })
+}
+
+export async function getCollectionBadges(): Promise<CollectionBadge[]> {
+  return invoke('get_collection_badges')
+}
+
+export async function getHeaderSummary(): Promise<HeaderSummary> {
+  return invoke('get_header_summary')
+}
+
 export async function getDocNote(projectId: string, docSlug: string): Promise<DocNote | null> {
   return invoke('get_doc_note', { projectId, docSlug })
 }
 
-export async function saveDocNote(projectId: string, docSlug: string, note: string): Promise<DocNote> {
-  return invoke('save_doc_note', { projectId, docSlug, note })
+/** Rejects with a `conflict` UserStateError when `expectedRevision` is given and the note changed since. */
+export async function saveDocNote(
+  projectId: string,
+  docSlug: string,
+  note: string,
+  expectedRevision?: number,
+): Promise<DocNote> {
+  return invoke('save_doc_note', { projectId, docSlug, note, expectedRevision })
+}
+
+export async function saveDocNoteDraft(projectId: string, docSlug: string, draft: string): Promise<void> {
+  return invoke('save_doc_note_draft', { projectId, docSlug, draft })
+}
+
+export async function getDocNoteDraft(projectId: string, docSlug: string): Promise<DocNoteWithDraft> {
+  return invoke('get_doc_note_draft', { projectId, docSlug })
+}
+
+export async function listDocNoteRevisions(projectId: string, docSlug: string): Promise<DocNoteRevision[]> {
+  return invoke('list_doc_note_revisions', { projectId, docSlug })
+}
+
+export async function restoreDocNoteRevision(id: number): Promise<DocNote> {
+  return invoke('restore_doc_note_revision', { id })
+}
+
+export async function toggleNoteCheckbox(projectId: string, docSlug: string, index: number): Promise<DocNote> {
+  return invoke('toggle_note_checkbox', { projectId, docSlug, index })
 }
 
 export async function listDocHighlights(projectId: string, docSlug: string): Promise<DocHighlight[]> {
@@ -284,3 +782,101 @@ export async function addDocHighlight(
 export async function deleteDocHighlight(id: number): Promise<void> {
   return invoke('delete_doc_highlight', { id })
 }
+
+export async function setAnnotationFolders(annotation: AnnotationRef, folderIds: number[]): Promise<void> {
+  return invoke('set_annotation_folders', { annotation, folderIds })
+}
+
+export async function setAnnotationTags(annotation: AnnotationRef, tagIds: number[]): Promise<void> {
+  return invoke('set_annotation_tags', { annotation, tagIds })
+}
+
+export async function listAllAnnotations(
+  projectId: string,
+  folderId?: number,
+  tagId?: number,
+): Promise<AnnotationListItem[]> {
+  return invoke('list_all_annotations', { projectId, folderId, tagId })
+}
+
+export async function exportHighlightsFlashcards(
+  projectId: string,
+  format: 'tsv' | 'json',
+  docSlug?: string,
+  aiAssisted = false,
+): Promise<FlashcardExport | null> {
+  return invoke('export_highlights_flashcards', { projectId, docSlug, format, aiAssisted })
+}
+
+export async function addInboxItem(content: string, projectId?: string): Promise<InboxItem> {
+  return invoke('add_inbox_item', { projectId, content })
+}
+
+export async function listInboxItems(projectId?: string, includeResolved = false): Promise<InboxItem[]> {
+  return invoke('list_inbox_items', { projectId, includeResolved })
+}
+
+export async function resolveInboxItem(
+  id: number,
+  docSlug: string,
+  attachAs: 'note' | 'highlight',
+  projectId?: string,
+): Promise<InboxItem> {
+  return invoke('resolve_inbox_item', { id, projectId, docSlug, attachAs })
+}
+
+export async function deleteInboxItem(id: number): Promise<void> {
+  return invoke('delete_inbox_item', { id })
+}
+
+export async function suggestInboxDestinations(id: number, limit?: number): Promise<InboxDestination[]> {
+  return invoke('suggest_inbox_destinations', { id, limit })
+}
+
+export async function exportUserState(projectId: string, dest: string): Promise<UserStateSnapshotSummary> {
+  return invoke('export_user_state', { projectId, dest })
+}
+
+export async function importUserState(
+  path: string,
+  projectId: string,
+  mode: 'merge' | 'replace',
+): Promise<UserStateSnapshotSummary> {
+  return invoke('import_user_state', { path, projectId, mode })
+}
+
+/** Import a Readwise or Hypothesis JSON export into the active project. */
+export async function importExternalAnnotations(path: string): Promise<ExternalAnnotationImport> {
+  return invoke('import_external_annotations', { path })
+}
+
+export async function getOrphanedUserState(projectId: string): Promise<OrphanedUserState> {
+  return invoke('get_orphaned_user_state', { projectId })
+}
+
+export async function repairDocNote(projectId: string, docSlug: string, newDocSlug: string): Promise<DocNote> {
+  return invoke('repair_doc_note', { projectId, docSlug, newDocSlug })
+}
+
+export async function repairDocHighlight(id: number, docSlug: string, anchorId: string | null): Promise<DocHighlight> {
+  return invoke('repair_doc_highlight', { id, docSlug, anchorId })
+}
+
+export async function listDeletedItems(projectId: string): Promise<DeletedItem[]> {
+  return invoke('list_deleted_items', { projectId })
+}
+
+export async function restoreDeletedItem(id: number): Promise<DeletedItem> {
+  return invoke('restore_deleted_item', { id })
+}
+
+export async function purgeDeletedItems(
+  projectId?: string,
+  olderThanDays?: number,
+): Promise<number> {
+  return invoke('purge_deleted_items', { projectId, olderThanDays })
+}
+
+export async function getUsageStats(range: UsageRange, projectId?: string): Promise<UsageStats> {
+  return invoke('get_usage_stats', { range, projectId })
+}
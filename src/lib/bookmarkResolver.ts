@@ -2,6 +2,7 @@ import type { Router } from 'vue-router'
 import { getDocument, searchDocuments } from '@/lib/api'
 import type { Bookmark, SearchResult } from '@/lib/types'
 import { docSlugWithoutCollection } from './deepLinks'
+import { errorMessage } from './errors'
 
 export interface BookmarkResolveResult {
   status: 'opened' | 'missing-anchor' | 'missing-doc' | 'error'
@@ -48,7 +49,7 @@ export async function openBookmarkTarget(
     return {
       status: 'missing-doc',
       nearest: nearest[0],
-      message: e instanceof Error ? e.message : String(e),
+      message: errorMessage(e, 'Document not found'),
     }
   }
 }
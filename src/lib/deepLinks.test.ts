@@ -26,3 +26,48 @@ test('legacy deep link still parses', () => {
     anchorId: 'rollback',
   })
 })
+
+test('shared selections round-trip with unicode and reserved characters', () => {
+  const selection = { start: 'Ünïcødé — «déploiement», 配置 & 🚀, then roll-back (now!)', end: null }
+  const built = buildDeepLink({
+    projectId: 'handbook',
+    collectionId: 'ops',
+    docSlug: 'runbooks/deploy',
+    anchorId: 'roll-back',
+    selection,
+  })
+  const directive = built.slice(built.indexOf(':~:'))
+  assert.ok(!/[-,&#() ]/.test(directive.slice(':~:text='.length)))
+  assert.deepEqual(parseDeepLink(built), {
+    projectId: 'handbook',
+    collectionId: 'ops',
+    docSlug: 'runbooks/deploy',
+    anchorId: 'roll-back',
+    selection,
+  })
+})
+
+test('long selections shared as their first and last words round-trip', () => {
+  const selection = { start: 'Début mot mot mot mot mot', end: 'mot mot fin du passage 終わり' }
+  const parsed = parseDeepLink(buildDeepLink({
+    projectId: 'p',
+    collectionId: 'c',
+    docSlug: 'd',
+    selection,
+  }))
+  assert.deepEqual(parsed?.selection, selection)
+  assert.equal(parsed?.anchorId, undefined)
+})
+
+test('selection directives match the links create_share_link makes', () => {
+  assert.equal(
+    buildDeepLink({ projectId: 'p', collectionId: 'c', docSlug: 'd', selection: { start: 'plain', end: null } }),
+    'dalil://project/p/collection/c/doc/d#:~:text=plain',
+  )
+  assert.deepEqual(parseDeepLink('dalil://ops/deploy#a:~:text=before-,start,end,-after&x=1')?.selection, {
+    start: 'start',
+    end: 'end',
+  })
+  assert.equal(parseDeepLink('dalil://ops/deploy#a:~:text=%E9')?.selection, undefined)
+  assert.equal(parseDeepLink('dalil://ops/deploy#a:~:text=%E9')?.anchorId, 'a')
+})
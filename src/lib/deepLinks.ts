@@ -1,8 +1,11 @@
+import type { SharedSelection } from './types'
+
 export interface DeepLinkTarget {
   projectId: string
   collectionId: string
   docSlug: string
   anchorId?: string
+  selection?: SharedSelection
 }
 
 export interface ParsedDeepLinkTarget {
@@ -10,8 +13,13 @@ export interface ParsedDeepLinkTarget {
   collectionId: string
   docSlug: string
   anchorId?: string
+  selection?: SharedSelection
 }
 
+// Selected text travels after the anchor, as in browser text fragments:
+// #anchor:~:text=start or #anchor:~:text=start,end
+const TEXT_DIRECTIVE = ':~:text='
+
 function decode(value: string): string {
   try {
     return decodeURIComponent(value)
@@ -24,17 +32,59 @@ function encodePath(path: string): string {
   return path.split('/').map((segment) => encodeURIComponent(segment)).join('/')
 }
 
+// '-' marks a prefix or suffix in a text directive, so it is encoded along
+// with the characters encodeURIComponent leaves, as create_share_link does.
+function encodeDirectiveText(text: string): string {
+  return encodeURIComponent(text)
+    .replace(/[-!'()*]/g, (c) => `%${c.charCodeAt(0).toString(16).toUpperCase()}`)
+}
+
+function parseTextDirective(fragment: string): SharedSelection | undefined {
+  const at = fragment.indexOf(TEXT_DIRECTIVE)
+  if (at === -1) return undefined
+  // Prefix and suffix terms (prefix-, and ,-suffix) are not used for matching
+  const terms = fragment
+    .slice(at + TEXT_DIRECTIVE.length)
+    .split('&')[0]
+    .split(',')
+    .filter((term) => !term.startsWith('-') && !term.endsWith('-'))
+  if (terms.length === 0 || terms.length > 2) return undefined
+  try {
+    const start = decodeURIComponent(terms[0])
+    if (!start) return undefined
+    return { start, end: terms.length === 2 ? decodeURIComponent(terms[1]) : null }
+  } catch {
+    return undefined
+  }
+}
+
+function parseFragment(hashPart: string | undefined): Pick<ParsedDeepLinkTarget, 'anchorId' | 'selection'> {
+  if (!hashPart) return { anchorId: undefined }
+  const directiveAt = hashPart.indexOf(':~:')
+  if (directiveAt === -1) return { anchorId: decode(hashPart) }
+  const anchorPart = hashPart.slice(0, directiveAt)
+  const selection = parseTextDirective(hashPart.slice(directiveAt))
+  return {
+    anchorId: anchorPart ? decode(anchorPart) : undefined,
+    ...(selection ? { selection } : {}),
+  }
+}
+
 export function buildDeepLink(target: DeepLinkTarget): string {
   const base = `dalil://project/${encodeURIComponent(target.projectId)}/collection/${encodeURIComponent(target.collectionId)}/doc/${encodePath(target.docSlug)}`
-  if (!target.anchorId) return base
-  return `${base}#${encodeURIComponent(target.anchorId)}`
+  let fragment = target.anchorId ? encodeURIComponent(target.anchorId) : ''
+  if (target.selection) {
+    fragment += `${TEXT_DIRECTIVE}${encodeDirectiveText(target.selection.start)}`
+    if (target.selection.end) fragment += `,${encodeDirectiveText(target.selection.end)}`
+  }
+  return fragment ? `${base}#${fragment}` : base
 }
 
 export function parseDeepLink(rawUrl: string): ParsedDeepLinkTarget | null {
   const url = rawUrl.trim()
 
   // v2 format:
-  // dalil://project/{projectId}/collection/{collectionId}/doc/{docSlug}#anchor
+  // dalil://project/{projectId}/collection/{collectionId}/doc/{docSlug}#anchor:~:text=selection
   const v2 = url.match(/^dalil:\/\/project\/([^/]+)\/collection\/([^/]+)\/doc\/(.+)$/)
   if (v2) {
     const [, projectPart, collectionPart, docPartWithHash] = v2
@@ -43,7 +93,7 @@ export function parseDeepLink(rawUrl: string): ParsedDeepLinkTarget | null {
       projectId: decode(projectPart),
       collectionId: decode(collectionPart),
       docSlug: decode(docPart),
-      anchorId: hashPart ? decode(hashPart) : undefined,
+      ...parseFragment(hashPart),
     }
   }
 
@@ -56,7 +106,7 @@ export function parseDeepLink(rawUrl: string): ParsedDeepLinkTarget | null {
     return {
       collectionId: decode(collectionPart),
       docSlug: decode(docPart),
-      anchorId: hashPart ? decode(hashPart) : undefined,
+      ...parseFragment(hashPart),
     }
   }
 
@@ -0,0 +1,14 @@
+import test from 'node:test'
+import assert from 'node:assert/strict'
+import { errorMessage } from './errors'
+
+test('errorMessage reads errors, strings and typed command errors', () => {
+  assert.equal(errorMessage(new Error('boom')), 'boom')
+  assert.equal(errorMessage('Bookmark 4 does not exist for this project'), 'Bookmark 4 does not exist for this project')
+  assert.equal(
+    errorMessage({ kind: 'busy', message: 'Your saved data is temporarily busy. Try again in a moment.' }),
+    'Your saved data is temporarily busy. Try again in a moment.',
+  )
+  assert.equal(errorMessage({ kind: 'not_found', slug: 'ops/x' }, 'Not found'), 'Not found')
+  assert.equal(errorMessage(undefined, 'Could not save note'), 'Could not save note')
+})
@@ -0,0 +1,15 @@
+/**
+ * Readable message for a rejected command. Commands reject with a plain
+ * string, or with a typed error object such as `UserStateError` or
+ * `DocumentError` that carries a `message`; `String()` on those would give
+ * "[object Object]".
+ */
+export function errorMessage(e: unknown, fallback = 'Something went wrong'): string {
+  if (e instanceof Error) return e.message || fallback
+  if (typeof e === 'string') return e || fallback
+  if (typeof e === 'object' && e !== null && 'message' in e) {
+    const message = (e as { message: unknown }).message
+    if (typeof message === 'string' && message) return message
+  }
+  return fallback
+}
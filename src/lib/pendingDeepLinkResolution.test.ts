@@ -37,6 +37,7 @@ test('resolvePendingDeepLinkTarget falls back to nearest document', async () =>
       section: 'Runbooks',
       collection_id: 'ops',
       snippet: '',
+      context: '',
     },
   ]
   const resolution = await resolvePendingDeepLinkTarget(pending, {
@@ -15,6 +15,9 @@ function result(
     section,
     collection_id,
     snippet: '',
+    context: '',
+    collapsed_count: 0,
+    collapsed_slugs: [],
   }
 }
 
@@ -0,0 +1,56 @@
+import type { SharedSelection } from './types'
+
+export const FLASH_SELECTION_EVENT = 'dalil:flash-selection'
+
+// The selection from the last share link opened and the full slug of the
+// document it is in, kept until that document has rendered the text it points
+// at or the reader navigates elsewhere
+let pending: { docSlug: string; selection: SharedSelection } | null = null
+
+export function showSharedSelection(docSlug: string, selection: SharedSelection) {
+  pending = { docSlug, selection }
+  window.dispatchEvent(new CustomEvent<SharedSelection>(FLASH_SELECTION_EVENT, { detail: selection }))
+}
+
+export function pendingSharedSelection(docSlug: string): SharedSelection | null {
+  return pending?.docSlug === docSlug ? pending.selection : null
+}
+
+export function clearSharedSelection() {
+  pending = null
+}
+
+/**
+ * The range of `root`'s text covering the selection, comparing text with
+ * whitespace collapsed as create_share_link does. A selection shared as its
+ * first and last words covers everything from the one to the other.
+ */
+export function findSelectionRange(root: HTMLElement, selection: SharedSelection): Range | null {
+  const walker = document.createTreeWalker(root, NodeFilter.SHOW_TEXT)
+  const positions: Array<[Text, number]> = []
+  let text = ''
+  let lastWasSpace = true
+  for (let node = walker.nextNode() as Text | null; node; node = walker.nextNode() as Text | null) {
+    for (let i = 0; i < node.data.length; i++) {
+      const isSpace = /\s/.test(node.data[i])
+      if (isSpace && lastWasSpace) continue
+      text += isSpace ? ' ' : node.data[i]
+      positions.push([node, i])
+      lastWasSpace = isSpace
+    }
+  }
+
+  const start = text.indexOf(selection.start)
+  if (start === -1) return null
+  let endAt = start + selection.start.length
+  if (selection.end) {
+    const end = text.indexOf(selection.end, endAt)
+    if (end !== -1) endAt = end + selection.end.length
+  }
+  const [startNode, startOffset] = positions[start]
+  const [endNode, endOffset] = positions[endAt - 1]
+  const range = document.createRange()
+  range.setStart(startNode, startOffset)
+  range.setEnd(endNode, endOffset + 1)
+  return range
+}
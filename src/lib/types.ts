@@ -4,6 +4,8 @@ export interface Collection {
   icon: string
   description: string | null
   sort_order: number
+  pinned: boolean
+  hidden: boolean
 }
 
 export interface NavigationNode {
@@ -15,6 +17,9 @@ export interface NavigationNode {
   sort_order: number
   level: number
   has_children: boolean
+  /** Null when reading times are turned off in preferences */
+  word_count: number | null
+  reading_minutes: number | null
 }
 
 export interface Document {
@@ -28,21 +33,103 @@ export interface Document {
   content_html: string
   path: string
   last_modified: string | null
+  /** Marks inserted for `highlightTerms`; absent when none were requested */
+  highlight_count?: number
 }
 
+/** Rejection from `getDocument`. */
+export type DocumentError =
+  | {
+      kind: 'not_found'
+      slug: string
+      /** Slug a rebuild moved the document to */
+      renamedTo: string | null
+      /** When a rebuild removed the document, in Unix seconds */
+      removedAt: number | null
+    }
+  | { kind: 'failed'; message: string }
+
+/** Rejection from bookmark and view-tracking writes. `busy` means another
+ * connection held the database through every retry; trying again usually works.
+ * Show it with `errorMessage` from lib/errors. */
+export type UserStateError =
+  | { kind: 'busy'; message: string }
+  | { kind: 'failed'; message: string }
+  /** The note changed since it was read; `current` is what is stored now */
+  | { kind: 'conflict'; message: string; current: DocNote | null }
+
 export interface SearchResult {
   slug: string
   title: string
   section: string
   collection_id: string
   snippet: string
+  /** Plain text around the first match; empty for tag listings. */
+  context: string
+  /** Similar-titled results folded into this one when search dedupes titles. */
+  collapsed_count: number
+  collapsed_slugs: string[]
 }
 
+/** `recent` puts the collection with the newest document first. */
+export type CollectionSort = 'sort_order' | 'name' | 'recent'
+
+export type TagSort = 'count' | 'alpha'
+
 export interface Tag {
   tag: string
   count: number
 }
 
+export interface QuerySuggestion {
+  kind: 'title' | 'heading' | 'recent'
+  text: string
+  /** The document a title or heading belongs to; null for recent searches. */
+  slug: string | null
+  anchorId: string | null
+}
+
+export interface TagCoverage {
+  collectionId: string
+  collectionName: string
+  documentCount: number
+  /** Documents with no tag rows at all. */
+  untaggedCount: number
+  /** Documents with a tag row pointing at a deleted tag. */
+  danglingTagCount: number
+}
+
+export interface TermDefinition {
+  term: string
+  definition: string
+  docSlug: string
+  docTitle: string
+  collectionId: string
+  matchKind: 'title' | 'definition'
+}
+
+export interface GlossaryTerm {
+  term: string
+  slug: string
+}
+
+export interface DocumentLocation {
+  projectId: string
+  projectName: string
+  slug: string
+  title: string
+  collectionId: string
+  matchKind: 'slug' | 'title'
+  score: number
+}
+
+export interface DocumentAnchor {
+  anchorId: string
+  headingText: string
+  level: number
+  position: number
+}
+
 export interface Chunk {
   id: number
   document_id: number
@@ -51,6 +138,14 @@ export interface Chunk {
   heading_context: string
 }
 
+/** Hybrid search result with the parts of its score. */
+export interface ExplainedChunk extends Chunk {
+  score: number
+  vector_score: number | null
+  fts_matched: boolean
+  boost: number
+}
+
 export interface ProjectCollection {
   id: string
   name: string
@@ -66,7 +161,31 @@ export interface Project {
   sourcePath?: string
   dbPath?: string
   lastBuilt?: string
+  /** Imported from a prebuilt .db; these projects cannot be rebuilt in the app. */
+  builtFromDb?: boolean
   collections: ProjectCollection[]
+  glossaryCollection?: string
+  /** AI features use only local providers (Ollama) for this project. */
+  localOnly?: boolean
+  /** Collections left out of AI retrieval; they are still searchable. */
+  excludedCollections?: string[]
+  /** Hybrid search settings replacing those in `Settings`. */
+  retrieval?: RetrievalConfig
+  /** Present when the project database could not be opened, e.g. "unavailable: <reason>". */
+  status?: string
+}
+
+/** A database in the projects folder that no registered project uses. */
+export interface OrphanedProject {
+  id: string
+  dbPath: string
+  sizeBytes: number
+}
+
+export interface OrphanRecovery {
+  id: string
+  name: string
+  icon: string
 }
 
 export type BuildStatus = 'idle' | 'building' | 'complete' | 'error'
@@ -78,6 +197,13 @@ export interface ProjectBuildEvent {
   error?: string
 }
 
+export interface SimilarDocument {
+  slug: string
+  title: string
+  collectionId: string
+  score: number
+}
+
 export interface ProjectStats {
   documentCount: number
   collectionCount: number
@@ -85,10 +211,379 @@ export interface ProjectStats {
   chunkCount: number
   embeddingCount: number
   dbSizeBytes: number
+  /** Collections the user has hidden; still included in the counts above */
+  hiddenCollectionCount: number
+}
+
+/** What removing a project would delete from user state, and its database size. */
+export interface ProjectRemovalPreview {
+  projectId: string
+  bookmarks: number
+  notes: number
+  highlights: number
+  docViews: number
+  inboxItems: number
+  feedEntries: number
+  /** Null when the project has no database file on disk */
+  dbSizeBytes: number | null
+}
+
+/** Where the time went in a project switch, in milliseconds. */
+export interface ProjectSwitchTiming {
+  lockWaitMs: number
+  validationMs: number
+  saveRegistryMs: number
+  totalMs: number
+}
+
+export interface DocumentListItem {
+  slug: string
+  title: string
+  collectionId: string
+  section: string
+  lastModified: string | null
+  wordCount: number
+  readingMinutes: number
+}
+
+export interface DocumentListing {
+  documents: DocumentListItem[]
+  /** Documents matching the filter across all pages */
+  total: number
+}
+
+export interface IntegrityIssue {
+  /** e.g. "navigation_without_document" or "chunk_without_embedding" */
+  category: string
+  count: number
+  /** Slugs, or row ids where there is no slug, up to 50 */
+  examples: string[]
+}
+
+export interface IntegrityReport {
+  /** Only categories with at least one problem */
+  issues: IntegrityIssue[]
+}
+
+export interface TableStorage {
+  name: string
+  bytes: number
+  pages: number
+}
+
+export interface StorageBreakdown {
+  totalBytes: number
+  tables: TableStorage[]
+}
+
+export interface PruneSummary {
+  embeddingsRemoved: number
+  bytesBefore: number
+  bytesAfter: number
+}
+
+export interface AiStatus {
+  embeddingCount: number
+  annIndex: 'inactive' | 'building' | 'active'
+  excludedCollections: string[]
+  /** Hybrid search settings in effect for the active project. */
+  retrieval: RetrievalConfig
+  /** Ollama answered at the configured base URL; false when it was not probed because another provider is in use. */
+  ollamaAvailable: boolean
+}
+
+/** How hybrid search gathers and scores passages. */
+export interface RetrievalConfig {
+  vectorCandidates: number
+  ftsCandidates: number
+  /** Added to chunks that also match the text query; the floor for text-only matches. */
+  ftsBoost: number
+  /** Vector matches below this are dropped before merging. */
+  minVectorScore: number
+}
+
+export interface RagTraceChunk {
+  chunkId: number
+  documentId: number
+  headingContext: string
+  score: number
+  vectorScore: number | null
+  ftsMatched: boolean
+  boost: number
+  /** A short excerpt, or the whole chunk in a verbose trace. */
+  text: string
+}
+
+/** What happened while answering one question. */
+export interface RagTrace {
+  requestId: string
+  createdAt: number
+  projectId: string
+  question: string
+  provider: string
+  embeddingCached: boolean
+  /** Why embedding failed, leaving retrieval to full-text search. */
+  embeddingError: string | null
+  retrieval: RetrievalConfig
+  excludedCollections: string[]
+  vectorCandidates: RagTraceChunk[]
+  ftsCandidates: RagTraceChunk[]
+  /** Both legs merged, best first; sent as context only when `grounded`. */
+  merged: RagTraceChunk[]
+  personalContextCount: number
+  bestScore: number | null
+  minScore: number
+  grounded: boolean
+  /** Zero when nothing was sent to the provider. */
+  promptMessages: number
+  promptChars: number
+  promptTokensEstimate: number
+  verbose: boolean
+}
+
+export interface RetrievalCase {
+  question: string
+  expectedSlug: string
+}
+
+export interface RetrievalCaseResult {
+  question: string
+  expectedSlug: string
+  /** 1-based position of the first passage from the expected document; null when missed. */
+  rank: number | null
+  retrievedSlugs: string[]
+  /** Why the case couldn't be run; such cases count toward neither metric. */
+  error: string | null
+}
+
+export interface RetrievalEvaluation {
+  k: number
+  config: RetrievalConfig
+  /** False when no embedding provider was available and only text search ran. */
+  usedEmbeddings: boolean
+  hitRate: number
+  meanReciprocalRank: number
+  cases: RetrievalCaseResult[]
+}
+
+export interface FtsTokenizer {
+  table: string
+  /** The `tokenize` option as declared; empty when the default is used. */
+  declaration: string
+  /** 'unicode61', 'ascii' or 'trigram'. */
+  tokenizer: string
+  porter: boolean
+  tokenChars: string
+  separators: string
+}
+
+export interface SearchDiagnostics {
+  documentsFts: FtsTokenizer | null
+  chunksFts: FtsTokenizer | null
+  /** The MATCH expression search would send for the given query. */
+  sanitisedQuery: string | null
+}
+
+export interface EmbeddingRunSummary {
+  embedded: number
+  remaining: number
+  cancelled: boolean
+}
+
+export interface QuantizeSummary {
+  embeddingsConverted: number
+  bytesBefore: number
+  bytesAfter: number
+}
+
+/** Result of a project build; reused documents kept their chunks and embeddings. */
+export interface BuildSummary {
+  documentsRebuilt: number
+  documentsReused: number
+  chunksEmbedded: number
+}
+
+/** A build waiting in, or running at the head of, the build queue. */
+export interface QueuedBuild {
+  id: number
+  projectId: string
+  kind: 'add' | 'rebuild'
+  /** Number of builds ahead of this one; 0 for the running build. */
+  position: number
+  running: boolean
+  enqueuedAt: number
 }
 
 export interface AppPreferences {
   editorCommand: string | null
+  quickSearchEnabled?: boolean
+  quickSearchShortcut?: string | null
+  notificationsEnabled?: boolean
+  /** Local hour (0-23) at which notifications are silenced. */
+  quietHoursStart?: number | null
+  /** Local hour (0-23) at which notifications resume. */
+  quietHoursEnd?: number | null
+  /** macOS only: keep Spotlight stubs in sync with each build. */
+  spotlightExportEnabled?: boolean
+  /** Serve the read-only HTTP API on 127.0.0.1. */
+  httpApiEnabled?: boolean
+  httpApiPort?: number | null
+  /** Bearer token for the HTTP API; generated by the backend when first enabled. */
+  httpApiToken?: string | null
+  mcpServerEnabled?: boolean
+  eventLogEnabled?: boolean
+  eventWebhookUrl?: string | null
+  /** Build projects with the Node script, which adds Shiki code highlighting. */
+  nodeBuildPipeline?: boolean
+  /** Keep notes and highlights out of questions sent to AI providers. */
+  aiExcludePersonalContext?: boolean
+  /** Best retrieval score a question needs before it is sent to a provider. */
+  aiMinContextScore?: number | null
+  /** Stop-word list for question keywords: 'en', 'de', 'fr' or 'es'. Defaults to English. */
+  aiKeywordLanguage?: string | null
+  /** Extra words left out of question keywords. */
+  aiCustomStopWords?: string[]
+  /** Record how each question was answered, under logs/rag in the app data dir. */
+  aiRagTraces?: boolean
+  /** Keep whole chunk texts in RAG traces rather than short excerpts. */
+  aiRagTraceVerbose?: boolean
+  /** Leave collections hidden from navigation out of search and AI retrieval. */
+  excludeHiddenCollectionsFromSearch?: boolean
+  /** Node executable for the Node build pipeline; searched for when unset. */
+  nodeBinaryPath?: string | null
+  /** npm executable or npm-cli.js used to rebuild native modules; searched for when unset. */
+  npmBinaryPath?: string | null
+  releaseNotesUrl?: string | null
+  usageStatsDisabled?: boolean
+  /** 8-64; unset uses 30. */
+  searchSnippetTokens?: number | null
+  readingTimeDisabled?: boolean
+}
+
+export type UsageRange = 'week' | 'month' | 'quarter' | 'year'
+
+export type UsageMetric = 'questions_asked' | 'searches' | 'documents_opened' | 'documents_viewed'
+
+export interface UsageSeries {
+  metric: UsageMetric
+  /** One count per entry of `UsageStats.days`. */
+  counts: number[]
+  total: number
+}
+
+/** Local-only usage counts; `days` are YYYY-MM-DD, oldest first. */
+export interface UsageStats {
+  range: UsageRange
+  days: string[]
+  series: UsageSeries[]
+}
+
+/** Versions for the About dialog; timestamps are Unix seconds. */
+export interface AboutInfo {
+  crateVersion: string
+  appVersion: string
+  handbookBuiltAt: number | null
+  handbookDocumentCount: number | null
+  userStateSchemaRevision: number
+  /** e.g. "aarch64-apple-darwin" */
+  targetTriple: string
+  /** Set when the update server offers a newer version. */
+  pendingUpdateVersion: string | null
+  updateCheckError: string | null
+}
+
+export interface ReleaseNote {
+  version: string
+  date: string | null
+  /** Markdown */
+  body: string
+}
+
+/** Notes for this version and newer, newest first. `stale` means the feed
+ * could not be reached and these come from the last fetch. */
+export interface ReleaseNotes {
+  entries: ReleaseNote[]
+  stale: boolean
+  fetchedAt: number | null
+  error: string | null
+}
+
+/** A panic report; `crashedLastSession` is only true on the first fetch after
+ * the crash. `createdAt` is in Unix seconds. */
+export interface CrashReport {
+  path: string
+  createdAt: number
+  contents: string
+  crashedLastSession: boolean
+}
+
+/** A saved build log; `modifiedAt` is in Unix seconds. */
+export interface BuildLogEntry {
+  path: string
+  fileName: string
+  sizeBytes: number
+  modifiedAt: number
+}
+
+/** Payload of the `project-build-error` event. `logPath` is null when the log could not be written. */
+export interface ProjectBuildErrorEvent {
+  projectId: string
+  error: string
+  logPath: string | null
+}
+
+/** A node or npm executable the build pipeline tried; `version` is null when it could not run. */
+export interface ToolCandidate {
+  path: string
+  version: string | null
+}
+
+export interface BuildEnvironmentReport {
+  /** In the order they are tried. */
+  nodeCandidates: ToolCandidate[]
+  node: ToolCandidate | null
+  /** Empty when no node was found. */
+  npmCandidates: ToolCandidate[]
+  npm: ToolCandidate | null
+  loginShellNode: string | null
+  loginShellNpm: string | null
+  projectRoot: string | null
+  projectRootError: string | null
+  tsxPresent: boolean
+  betterSqlite3Present: boolean
+}
+
+export interface SpotlightExportSummary {
+  projectId: string
+  directory: string
+  written: number
+  unchanged: number
+  removed: number
+}
+
+export interface StaticSiteExport {
+  outputDir: string
+  /** Pages plus the index page, search index and search script */
+  fileCount: number
+}
+
+/** Payload of the `static-site-export-progress` event. */
+export interface StaticSiteExportProgress {
+  projectId: string
+  written: number
+  total: number
+}
+
+/** Payload of the `notification-opened` event, emitted when a build notification is clicked. */
+export interface NotificationOpenedEvent {
+  projectId: string
+}
+
+/** Payload of the `navigate-to-document` event sent to the main window. */
+export interface NavigateToDocumentEvent {
+  collectionId: string
+  slug: string
+  anchorId: string | null
 }
 
 export interface Bookmark {
@@ -128,6 +623,17 @@ export interface BookmarkRelations {
   tagIds: number[]
 }
 
+export type BookmarkValidationStatus = 'ok' | 'missing_document' | 'missing_anchor'
+
+export interface BookmarkValidation {
+  bookmarkId: number
+  docSlug: string
+  anchorId: string | null
+  status: BookmarkValidationStatus
+  suggestedAnchorId: string | null
+  suggestedHeadingText: string | null
+}
+
 export interface DocActivityItem {
   docSlug: string
   collectionId: string
@@ -138,11 +644,103 @@ export interface DocActivityItem {
   updatedSinceViewed: boolean
 }
 
+/** A document the user navigated away from, for reopening. */
+export interface ClosedDocument {
+  docSlug: string
+  anchorId: string | null
+  collectionId: string
+  title: string
+  section: string
+  closedAt: number
+}
+
+export interface TagTrend {
+  documentCount: number
+  updatedLast30Days: number
+  updatedLast90Days: number
+}
+
+export interface TagDetail {
+  tag: string
+  documents: DocActivityItem[]
+  /** `count` is the number of documents carrying both tags. */
+  relatedTags: Tag[]
+  trend: TagTrend
+}
+
+export interface CollectionBadge {
+  collectionId: string
+  updatedCount: number
+  neverViewedCount: number
+}
+
+export interface HeaderCount {
+  value: number
+  /** Past its refresh threshold; a recount is running in the background. */
+  stale: boolean
+}
+
+export interface HeaderSummary {
+  activeProjectId: string
+  projectName: string
+  /** Null until the first background count for the project finishes */
+  documentCount: HeaderCount | null
+  updatedCount: HeaderCount | null
+  buildingProjectId: string | null
+  queuedBuildCount: number
+}
+
+export interface HomeSection<T> {
+  items: T[]
+  error: string | null
+}
+
+export interface ProjectHome {
+  recent: HomeSection<DocActivityItem>
+  updated: HomeSection<DocActivityItem>
+  changeFeed: HomeSection<ProjectChangeFeedItem>
+  favoriteBookmarks: HomeSection<Bookmark>
+}
+
+export interface CollectionOverride {
+  projectId: string
+  collectionId: string
+  customDescription: string | null
+  customIcon: string | null
+  pinned: boolean
+  hidden: boolean
+  updatedAt: number
+}
+
 export interface DocNote {
   projectId: string
   docSlug: string
   note: string
   updatedAt: number
+  /** Bumped by every save; pass it back to `saveDocNote` to detect conflicts. */
+  revision: number
+}
+
+/** Note text autosaved while typing, not yet saved as the note. */
+export interface DocNoteDraft {
+  projectId: string
+  docSlug: string
+  draft: string
+  updatedAt: number
+}
+
+export interface DocNoteWithDraft {
+  note: DocNote | null
+  /** Null when there is no draft or it matches the saved note */
+  draft: DocNoteDraft | null
+}
+
+export interface DocNoteRevision {
+  id: number
+  projectId: string
+  docSlug: string
+  note: string
+  createdAt: number
 }
 
 export interface DocHighlight {
@@ -155,6 +753,151 @@ export interface DocHighlight {
   createdAt: number
 }
 
+/** A highlight or note from a Readwise or Hypothesis export. */
+export interface ExternalAnnotation {
+  source: 'readwise' | 'hypothesis'
+  title: string | null
+  url: string | null
+  /** The highlighted text. */
+  text: string | null
+  note: string | null
+  contextText: string | null
+}
+
+export interface ExternalAnnotationImport {
+  matched: number
+  unmatched: number
+  highlightsAdded: number
+  notesAdded: number
+  /** Highlights and notes the document already had. */
+  duplicatesSkipped: number
+  /** Items matching no document, or more than one, for review. */
+  unmatchedEntries: ExternalAnnotation[]
+}
+
+/** Text a share link highlights: the whole selection, or its first and last words. */
+export interface SharedSelection {
+  start: string
+  end: string | null
+}
+
+export interface ShareLink {
+  url: string
+  /** The selection as the link carries it. */
+  selection: SharedSelection | null
+  /** Whether the selection was cut to its first and last words. */
+  selectionShortened: boolean
+}
+
+/** Identifies a note (by its document) or a highlight for folder and tag assignment. */
+export type AnnotationRef =
+  | { kind: 'note'; projectId: string; docSlug: string }
+  | { kind: 'highlight'; id: number }
+
+export interface AnnotationListItem {
+  kind: 'note' | 'highlight'
+  id: number
+  docSlug: string
+  anchorId: string | null
+  text: string
+  contextText: string | null
+  updatedAt: number
+  folderIds: number[]
+  tagIds: number[]
+}
+
+export interface Flashcard {
+  front: string
+  back: string
+  docSlug: string
+  highlightId: number
+}
+
+export interface FlashcardExport {
+  path: string
+  cardCount: number
+  aiAssisted: boolean
+}
+
+/** What a user state snapshot file holds, returned by its export and import. */
+export interface UserStateSnapshotSummary {
+  path: string
+  /** The project the snapshot was taken from */
+  projectId: string
+  exportedAt: number
+  bookmarks: number
+  notes: number
+  highlights: number
+  docViews: number
+  inboxItems: number
+}
+
+export interface InboxItem {
+  id: number
+  projectId: string | null
+  content: string
+  createdAt: number
+  resolvedAt: number | null
+  resolvedDocSlug: string | null
+}
+
+export interface InboxDestination {
+  slug: string
+  title: string
+  collectionId: string
+  section: string
+  heading: string | null
+  score: number
+}
+
+export interface DocRemoval {
+  docSlug: string
+  removedAt: number
+}
+
+/** User state still pointing at documents a rebuild removed. */
+export interface OrphanedUserState {
+  removals: DocRemoval[]
+  bookmarks: Bookmark[]
+  notes: DocNote[]
+  highlights: DocHighlight[]
+}
+
+/** The document and section last open in a project, restored on launch. */
+export interface LastSession {
+  projectId: string
+  collectionId: string
+  docSlug: string
+  anchorId: string | null
+  updatedAt: number
+  isFallback: boolean
+}
+
+export interface RecentProject {
+  project: Project
+  lastUsedAt: number
+  activationCount: number
+}
+
+/** Payload of the `active-project-changed` event. */
+export interface ActiveProjectChangedEvent {
+  projectId: string
+  previousProjectId: string
+  /** Present when the user switched projects with `set_active_project`. */
+  timing?: ProjectSwitchTiming
+}
+
+export type DeletedItemKind = 'bookmarks' | 'bookmark_folder' | 'bookmark_tag' | 'doc_highlight'
+
+export interface DeletedItem {
+  id: number
+  projectId: string
+  kind: DeletedItemKind
+  label: string
+  itemCount: number
+  deletedAt: number
+}
+
 export interface ProjectChangeFeedItem {
   id: number
   projectId: string
@@ -164,10 +907,41 @@ export interface ProjectChangeFeedItem {
   changedFiles: string[]
   changedDocSlugs: string[]
   recordedAt: number
+  /** Commit subject; empty for entries recorded before it was captured. */
+  message: string
+}
+
+export interface ChangeDigest {
+  /** Unix seconds, inclusive. */
+  since: number
+  /** Unix seconds, exclusive. */
+  until: number
+  collections: DigestCollection[]
+  /** Whether any collection has an AI-written summary. */
+  aiSummaries: boolean
+  markdown: string
+}
+
+export interface DigestCollection {
+  /** Empty for commits that touched no known document. */
+  collectionId: string
+  name: string
+  documents: { slug: string, title: string, lastModified: string }[]
+  commits: { commitHash: string, message: string, author: string, committedAt: string }[]
+  summary: string | null
 }
 
 export type AiProvider = 'openai' | 'anthropic' | 'gemini' | 'ollama'
 
+export interface TagSuggestions {
+  /** Tags from the project's vocabulary. */
+  existing: string[]
+  /** New tags the model proposed. */
+  proposed: string[]
+  /** Tags the document already has. */
+  current: string[]
+}
+
 export interface Settings {
   openai_api_key: string | null
   anthropic_api_key: string | null
@@ -176,6 +950,16 @@ export interface Settings {
   preferred_provider: string | null
   anthropic_model: string | null
   gemini_model: string | null
+  /** AI requests allowed in flight per provider (default 2). */
+  max_concurrent_requests?: number | null
+  /** Use Anthropic prompt caching for the repeated system prompt and context. */
+  anthropic_prompt_caching?: boolean | null
+  /** Query embedding requests allowed per minute (default 120). */
+  embedding_requests_per_minute?: number | null
+  /** Language AI answers are written in, e.g. "German". */
+  answer_language?: string | null
+  /** Hybrid search settings; projects can override them. */
+  retrieval?: RetrievalConfig | null
 }
 
 export interface AiMessage {
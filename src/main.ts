@@ -1,16 +1,24 @@
 import { createApp } from 'vue'
 import { onOpenUrl } from '@tauri-apps/plugin-deep-link'
+import { listen } from '@tauri-apps/api/event'
 import App from './App.vue'
 import router from './router'
 import { getDocument, listProjects, searchDocuments, setActiveProject } from '@/lib/api'
 import { docSlugWithoutCollection, parseDeepLink } from '@/lib/deepLinks'
 import { clearPendingDeepLink, setPendingDeepLink } from '@/lib/pendingDeepLink'
+import { clearSharedSelection, showSharedSelection } from '@/lib/sharedSelection'
+import type { NavigateToDocumentEvent } from '@/lib/types'
 import './style.css'
 import './composables/useTheme'
 
 createApp(App).use(router).mount('#app')
 
+// A shared selection the linked document did not contain is not carried to
+// the next one
+router.afterEach(() => clearSharedSelection())
+
 let unlistenUrl: (() => void) | null = null
+let unlistenNavigate: (() => void) | null = null
 
 function emitDeepLinkStatus(kind: string, message: string, detail: Record<string, unknown> = {}) {
   window.dispatchEvent(new CustomEvent('dalil:deeplink-status', {
@@ -58,6 +66,9 @@ async function resolveDeepLink(url: string) {
       params: { collection: parsed.collectionId, slug: parsed.docSlug },
       hash: parsed.anchorId ? `#${parsed.anchorId}` : '',
     })
+    if (parsed.selection) {
+      showSharedSelection(fullSlug, parsed.selection)
+    }
 
     if (anchorMissing) {
       emitDeepLinkStatus(
@@ -107,6 +118,19 @@ onOpenUrl((urls) => {
   }
 }).then(fn => { unlistenUrl = fn })
 
+// Sent by the quick-search window when a result is chosen
+listen<NavigateToDocumentEvent>('navigate-to-document', (event) => {
+  const { collectionId, slug, anchorId } = event.payload
+  void router.push({
+    name: 'doc',
+    params: { collection: collectionId, slug },
+    hash: anchorId ? `#${anchorId}` : '',
+  })
+}).then(fn => { unlistenNavigate = fn })
+
 if (import.meta.hot) {
-  import.meta.hot.dispose(() => { unlistenUrl?.() })
+  import.meta.hot.dispose(() => {
+    unlistenUrl?.()
+    unlistenNavigate?.()
+  })
 }
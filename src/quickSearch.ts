@@ -0,0 +1,5 @@
+import { createApp } from 'vue'
+import QuickSearchWindow from '@/components/search/QuickSearchWindow.vue'
+import './style.css'
+
+createApp(QuickSearchWindow).mount('#app')
@@ -12,6 +12,14 @@ export default defineConfig(async () => ({
       '@': resolve(__dirname, './src'),
     },
   },
+  build: {
+    rollupOptions: {
+      input: {
+        main: resolve(__dirname, 'index.html'),
+        quickSearch: resolve(__dirname, 'quick-search.html'),
+      },
+    },
+  },
   clearScreen: false,
   server: {
     port: 1420,